@@ -0,0 +1,171 @@
+//! Model resolution, stream-event mapping and SSE line splitting
+//!
+//! `cargo bench --bench routing`
+
+use async_trait::async_trait;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures_util::{Stream, StreamExt};
+use omniference::adapter::{AdapterError, ChatAdapter};
+use omniference::service::ProviderManager;
+use omniference::adapters::body::LineBuffer;
+use omniference::skins::chunks::ChunkEncoder;
+use omniference::skins::openai::sse_chunk;
+use omniference::stream::{normalize, StreamEvent};
+use omniference::types::{
+    ChatRequestIR, DiscoveredModel, Modality, ModelCapabilities, ProviderConfig, ProviderEndpoint, ProviderKind,
+};
+use omniference::{AdapterRegistry, Router};
+use std::hint::black_box;
+use std::sync::Arc;
+
+const MODELS: usize = 1000;
+const DELTAS: usize = 100_000;
+
+/// Discovers `MODELS` models and never chats
+struct CatalogAdapter;
+
+#[async_trait]
+impl ChatAdapter for CatalogAdapter {
+    fn provider_kind(&self) -> ProviderKind {
+        ProviderKind::Custom("catalog".to_string())
+    }
+
+    async fn execute_chat(
+        &self,
+        _ir: ChatRequestIR,
+        _cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Box<dyn Stream<Item = StreamEvent> + Send + Unpin>, AdapterError> {
+        Err(AdapterError::internal("catalog adapter does not chat"))
+    }
+
+    async fn discover_models(&self, _endpoint: &ProviderEndpoint) -> Result<Vec<DiscoveredModel>, AdapterError> {
+        Ok((0..MODELS)
+            .map(|i| DiscoveredModel {
+                id: format!("catalog/model-{}", i),
+                name: format!("model-{}", i),
+                provider_name: "catalog".to_string(),
+                provider_kind: self.provider_kind(),
+                modalities: vec![Modality::Text],
+                capabilities: ModelCapabilities::default(),
+                created: None,
+            })
+            .collect())
+    }
+}
+
+/// A provider manager with `MODELS` discovered models and an alias for each
+fn catalog(runtime: &tokio::runtime::Runtime) -> ProviderManager {
+    let registry = AdapterRegistry::default();
+    registry.register(Arc::new(CatalogAdapter));
+    let router = Router::new(registry);
+    let mut manager = ProviderManager::new();
+    manager.register_provider(ProviderConfig {
+        name: "catalog".to_string(),
+        endpoint: ProviderEndpoint {
+            kind: ProviderKind::Custom("catalog".to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    runtime.block_on(manager.discover_models(&router)).expect("discovery");
+    for i in 0..MODELS {
+        manager.set_model_alias(format!("alias-{}", i), format!("catalog/model-{}", i));
+    }
+    manager
+}
+
+fn model_resolution(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let manager = catalog(&runtime);
+    let mut group = c.benchmark_group("resolve_model_ref/1k_aliases");
+    for (name, model) in [
+        ("exact_id", "catalog/model-512"),
+        ("alias", "alias-512"),
+        ("id_ignoring_case", "Catalog/Model-512"),
+        ("bare_name", "model-512"),
+        ("unknown", "missing-model"),
+    ] {
+        group.bench_with_input(BenchmarkId::from_parameter(name), model, |b, model| {
+            b.iter(|| manager.try_resolve_model_ref(black_box(model)))
+        });
+    }
+    group.finish();
+}
+
+fn deltas() -> impl Stream<Item = StreamEvent> + Send + Unpin {
+    futures_util::stream::iter(
+        (0..DELTAS)
+            .map(|i| StreamEvent::TextDelta { content: format!("token{} ", i % 100) })
+            .chain([StreamEvent::Done]),
+    )
+}
+
+fn stream_mapping(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let kind = ProviderKind::Custom("bench".to_string());
+    let mut group = c.benchmark_group("stream_mapping/100k_deltas");
+    group.throughput(Throughput::Elements(DELTAS as u64));
+    group.sample_size(10);
+    group.bench_function("normalize", |b| {
+        b.to_async(&runtime).iter(|| async {
+            normalize(kind.clone(), deltas()).count().await
+        })
+    });
+    group.bench_function("normalize_and_sse", |b| {
+        b.to_async(&runtime).iter(|| async {
+            normalize(kind.clone(), deltas())
+                .map(|event| sse_chunk(event, "chatcmpl-bench", "bench/model"))
+                .count()
+                .await
+        })
+    });
+    group.bench_function("normalize_and_chunk_encoder", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let mut encoder = ChunkEncoder::new("chatcmpl-bench", "bench/model");
+            normalize(kind.clone(), deltas()).map(|event| encoder.event(event)).count().await
+        })
+    });
+    group.finish();
+
+    // Serializing the deltas alone: the full chunk per event against the
+    // encoder's template
+    let mut group = c.benchmark_group("sse_chunk/100k_deltas");
+    group.throughput(Throughput::Elements(DELTAS as u64));
+    group.sample_size(10);
+    let events = || (0..DELTAS).map(|i| StreamEvent::TextDelta { content: format!("token{} ", i % 100) });
+    group.bench_function("sse_chunk", |b| {
+        b.iter(|| {
+            events().for_each(|event| {
+                let _ = black_box(sse_chunk(event, "chatcmpl-bench", "bench/model").into_event(None));
+            })
+        })
+    });
+    group.bench_function("chunk_encoder", |b| {
+        b.iter(|| {
+            let mut encoder = ChunkEncoder::new("chatcmpl-bench", "bench/model");
+            events().for_each(|event| {
+                let _ = black_box(encoder.event(event));
+            })
+        })
+    });
+    group.finish();
+}
+
+fn line_splitting(c: &mut Criterion) {
+    let chunks: Vec<Vec<u8>> = (0..DELTAS)
+        .map(|i| format!("data: {{\"choices\":[{{\"delta\":{{\"content\":\"token{} \"}}}}]}}\n\n", i % 100).into_bytes())
+        .collect();
+    let mut group = c.benchmark_group("line_buffer/100k_sse_events");
+    group.throughput(Throughput::Elements(DELTAS as u64));
+    group.sample_size(10);
+    group.bench_function("push", |b| {
+        b.iter(|| {
+            let mut lines = LineBuffer::new();
+            chunks.iter().map(|chunk| lines.push(black_box(chunk)).len()).sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, model_resolution, stream_mapping, line_splitting);
+criterion_main!(benches);
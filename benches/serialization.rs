@@ -0,0 +1,90 @@
+//! Request conversion and payload construction
+//!
+//! `cargo bench --bench serialization`
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use omniference::adapters::{AnthropicAdapter, OpenAIAdapter};
+use omniference::skins::openai::openai_to_chat_request;
+use omniference::types::{ChatRequestIR, Modality, ModelRef, OpenAIChatRequest, ProviderEndpoint, ProviderKind};
+use std::hint::black_box;
+
+/// A Chat Completions request with a system prompt and `turns` user and
+/// assistant messages
+fn conversation(turns: usize) -> OpenAIChatRequest {
+    let mut messages = vec![serde_json::json!({
+        "role": "system",
+        "content": "You are a helpful assistant. Answer concisely and cite sources where you can.",
+    })];
+    for turn in 0..turns {
+        let (role, content) = match turn % 2 {
+            0 => ("user", format!("Question {}: how does the router pick a provider for a pooled model?", turn)),
+            _ => ("assistant", format!("Answer {}: it asks the pool's balancer, which weighs each member's health and load.", turn)),
+        };
+        messages.push(serde_json::json!({ "role": role, "content": content }));
+    }
+    serde_json::from_value(serde_json::json!({
+        "model": "openai/gpt-4o",
+        "messages": messages,
+        "temperature": 0.7,
+        "max_tokens": 512,
+        "stream": true,
+        "tools": [{
+            "type": "function",
+            "function": {
+                "name": "search",
+                "description": "Search the documentation",
+                "parameters": {"type": "object", "properties": {"query": {"type": "string"}}, "required": ["query"]},
+            },
+        }],
+    }))
+    .expect("valid request")
+}
+
+fn model(kind: ProviderKind, model_id: &str) -> ModelRef {
+    ModelRef {
+        alias: format!("bench/{}", model_id),
+        provider: ProviderEndpoint {
+            kind,
+            base_url: "http://localhost:1".to_string(),
+            ..Default::default()
+        },
+        model_id: model_id.to_string(),
+        modalities: vec![Modality::Text],
+    }
+}
+
+fn ir(kind: ProviderKind, model_id: &str) -> ChatRequestIR {
+    openai_to_chat_request(conversation(50), model(kind, model_id)).expect("convertible request")
+}
+
+fn ir_conversion(c: &mut Criterion) {
+    let request = conversation(50);
+    let model = model(ProviderKind::OpenAI, "gpt-4o");
+    c.bench_function("openai_to_chat_request/50_messages", |b| {
+        b.iter_batched(
+            || (request.clone(), model.clone()),
+            |(request, model)| openai_to_chat_request(black_box(request), model).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn payload_construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build_request_body");
+    let openai = ir(ProviderKind::OpenAI, "gpt-4o");
+    group.bench_function("openai/50_messages", |b| {
+        b.iter(|| OpenAIAdapter::build_request_body(black_box(&openai)).unwrap())
+    });
+    let anthropic = ir(ProviderKind::Anthropic, "claude-sonnet-4");
+    let adapter = AnthropicAdapter::new();
+    group.bench_function("anthropic/50_messages", |b| {
+        b.iter(|| adapter.build_request_body(black_box(&anthropic)).unwrap())
+    });
+    group.bench_function("openai/50_messages/to_bytes", |b| {
+        b.iter(|| serde_json::to_vec(&OpenAIAdapter::build_request_body(black_box(&openai)).unwrap()).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, ir_conversion, payload_construction);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "grpc")]
+    {
+        // Use the vendored protoc unless one is provided explicitly
+        if std::env::var_os("PROTOC").is_none() {
+            std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+        }
+        tonic_build::compile_protos("proto/omniference.proto")?;
+    }
+    println!("cargo:rerun-if-changed=proto/omniference.proto");
+    Ok(())
+}
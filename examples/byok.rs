@@ -0,0 +1,42 @@
+//! Bring-your-own-key server
+//!
+//! Clients send their own OpenAI key in the `x-provider-api-key` header and
+//! requests go upstream with it instead of the server's key. The key is
+//! merged into the provider endpoint for that one request and is never
+//! logged, audited or stored.
+
+use omniference::{server::OmniferenceServerBuilder, types::{ProviderConfig, ProviderEndpoint, ProviderKind}};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let _ = dotenvy::dotenv();
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    // Requests without the header fall back to the server's own key, if any
+    let mut server = OmniferenceServerBuilder::new()
+        .with_provider(ProviderConfig {
+            name: "openai".to_string(),
+            endpoint: ProviderEndpoint {
+                kind: ProviderKind::OpenAI,
+                base_url: "https://api.openai.com".to_string(),
+                api_key: std::env::var("OPENAI_API_KEY").ok().map(Into::into),
+                ..Default::default()
+            },
+            enabled: true,
+            ..Default::default()
+        })
+        .with_provider_key_header()
+        .build();
+
+    let addr = std::env::var("SERVER_ADDR").unwrap_or_else(|_| "0.0.0.0:8080".to_string());
+    println!("Listening on {}", addr);
+    println!("   curl http://localhost:8080/api/openai-compatible/v1/chat/completions \\");
+    println!("     -H 'Content-Type: application/json' \\");
+    println!("     -H 'x-provider-api-key: sk-your-own-key' \\");
+    println!("     -d '{{\"model\": \"openai/gpt-4o-mini\", \"messages\": [{{\"role\": \"user\", \"content\": \"Hello!\"}}]}}'");
+
+    server.run(&addr).await?;
+    Ok(())
+}
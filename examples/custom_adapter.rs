@@ -0,0 +1,142 @@
+//! Example of enabling a custom `ChatAdapter` purely through configuration
+//!
+//! The adapter wraps a tiny local echo server. The application only registers
+//! a factory for the `echo` name; the provider itself comes from a JSON config
+//! with `"kind": "custom:echo"`, and the factory builds the adapter from the
+//! provider's `adapter_options`.
+
+use async_trait::async_trait;
+use futures_util::Stream;
+use omniference::{
+    adapter::{AdapterError, ChatAdapter},
+    stream::StreamEvent,
+    types::{
+        ChatRequestIR, ContentPart, DiscoveredModel, Message, Modality, ModelCapabilities, ProviderConfig,
+        ProviderEndpoint, ProviderKind, Role,
+    },
+    OmniferenceEngine,
+};
+use std::sync::Arc;
+
+/// Provider configuration as it might appear in a config file
+const PROVIDER_CONFIG: &str = r#"{
+    "name": "local-echo",
+    "enabled": true,
+    "endpoint": {
+        "kind": "custom:echo",
+        "base_url": "{ECHO_URL}",
+        "timeout": 5000,
+        "adapter_options": { "prefix": "echo: " }
+    }
+}"#;
+
+/// Sends the last user message to the echo server and streams back its reply
+struct EchoAdapter {
+    prefix: String,
+}
+
+#[async_trait]
+impl ChatAdapter for EchoAdapter {
+    fn provider_kind(&self) -> ProviderKind {
+        ProviderKind::Custom("echo".to_string())
+    }
+
+    fn supports_tools(&self) -> bool {
+        false
+    }
+
+    async fn discover_models(&self, _endpoint: &ProviderEndpoint) -> Result<Vec<DiscoveredModel>, AdapterError> {
+        Ok(vec![DiscoveredModel {
+            id: "echo/echo-1".to_string(),
+            name: "echo-1".to_string(),
+            provider_name: "echo".to_string(),
+            provider_kind: self.provider_kind(),
+            modalities: vec![Modality::Text],
+            capabilities: ModelCapabilities::text_only(),
+            created: None,
+        }])
+    }
+
+    async fn execute_chat(
+        &self,
+        ir: ChatRequestIR,
+        _cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Box<dyn Stream<Item = StreamEvent> + Send + Unpin>, AdapterError> {
+        let text: String = ir
+            .messages
+            .iter()
+            .rev()
+            .find(|message| message.role == Role::User)
+            .map(|message| {
+                message
+                    .parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        ContentPart::Text(text) => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let reply = reqwest::Client::new()
+            .post(format!("{}/echo", ir.model.provider.base_url))
+            .body(text)
+            .send()
+            .await
+            .map_err(|e| AdapterError::http(e.to_string()))?
+            .text()
+            .await
+            .map_err(|e| AdapterError::http(e.to_string()))?;
+
+        Ok(Box::new(futures_util::stream::iter(vec![
+            StreamEvent::TextDelta {
+                content: format!("{}{}", self.prefix, reply),
+            },
+            StreamEvent::Done,
+        ])))
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // A local echo server standing in for a custom backend
+    let app = axum::Router::new().route("/echo", axum::routing::post(|body: String| async move { body }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let echo_url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let mut engine = OmniferenceEngine::new();
+
+    // The only code the custom provider needs: a constructor for its name
+    engine.register_adapter_factory("echo", |options| {
+        let prefix = options
+            .get("prefix")
+            .and_then(|prefix| prefix.as_str())
+            .unwrap_or_default()
+            .to_string();
+        Ok(Arc::new(EchoAdapter { prefix }) as Arc<dyn ChatAdapter>)
+    });
+
+    // Everything else comes from configuration
+    let provider: ProviderConfig = serde_json::from_str(&PROVIDER_CONFIG.replace("{ECHO_URL}", &echo_url))?;
+    println!("Loaded provider '{}' of kind {}", provider.name, provider.endpoint.kind);
+    engine.register_provider(provider).await?;
+
+    for model in engine.discover_models().await? {
+        println!("Discovered {}", model.id);
+    }
+
+    let request = ChatRequestIR {
+        model: engine.resolve_model("local-echo/echo-1").await?,
+        messages: vec![Message {
+            role: Role::User,
+            parts: vec![ContentPart::Text("Hello from a config-defined provider".to_string())],
+            name: None,
+        }],
+        ..Default::default()
+    };
+
+    println!("{}", engine.chat_complete(request).await?);
+    Ok(())
+}
@@ -68,12 +68,14 @@ async fn main() -> anyhow::Result<()> {
             endpoint: ProviderEndpoint {
                 kind: ProviderKind::OpenAICompat,
                 base_url: openai_base,
-                api_key: openai_key,
+                api_key: openai_key.map(Into::into),
                 extra_headers: std::collections::BTreeMap::new(),
                 timeout: Some(30000),
+                ..Default::default()
             },
             enabled: true,
-        }).await.map_err(|e| anyhow::anyhow!(e))?;
+            ..Default::default()
+        }).await?;
     }
 
     println!("✅ Omniference configured");
@@ -84,7 +86,7 @@ async fn main() -> anyhow::Result<()> {
     // - /ai/api/openai-compatible/v1/chat/completions
     // - /ai/api/openai/v1/models
     // - etc.
-    let app = app.nest("/ai", omniference_server.app());
+    let app = app.nest("/ai", omniference_server.into_router());
 
     println!("📡 Routes configured:");
     println!("   - / - Home page");
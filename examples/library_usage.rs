@@ -4,7 +4,7 @@
 //! for direct chat completions without any HTTP server.
 
 use omniference::{
-    types::{ChatRequestIR, Message, ModelRef, ProviderConfig, ProviderEndpoint, ProviderKind},
+    types::{ChatRequestIR, Message, ModelRef, PrivacyMode, ProviderConfig, ProviderEndpoint, ProviderKind},
     OmniferenceEngine,
 };
 
@@ -35,11 +35,12 @@ async fn main() -> anyhow::Result<()> {
                 api_key: None,
                 extra_headers: std::collections::BTreeMap::new(),
                 timeout: Some(30000),
+                ..Default::default()
             },
             enabled: true,
+            ..Default::default()
         })
-        .await
-        .map_err(|e| anyhow::anyhow!(e))?;
+        .await?;
 
     println!("✅ Registered Ollama provider");
 
@@ -70,6 +71,7 @@ async fn main() -> anyhow::Result<()> {
                         api_key: None,
                         extra_headers: std::collections::BTreeMap::new(),
                         timeout: Some(30000),
+                        ..Default::default()
                     },
                     model_id: model.id.clone(),
                     modalities: model.modalities.clone(),
@@ -94,6 +96,14 @@ async fn main() -> anyhow::Result<()> {
                 request_timeout: None,
                 cache_key: None,
                 safety_identifier: None,
+                provider_extensions: serde_json::Map::new(),
+                extra_body: serde_json::Map::new(),
+                reasoning: None,
+                store: None,
+                privacy: PrivacyMode::Default,
+                request_metadata: std::collections::HashMap::new(),
+                background: false,
+                endpoint_overrides: None,
             };
 
             println!("\n💬 Sending request...");
@@ -120,6 +130,7 @@ async fn main() -> anyhow::Result<()> {
                         api_key: None,
                         extra_headers: std::collections::BTreeMap::new(),
                         timeout: Some(30000),
+                        ..Default::default()
                     },
                     model_id: model.id.clone(),
                     modalities: model.modalities.clone(),
@@ -143,6 +154,14 @@ async fn main() -> anyhow::Result<()> {
                 request_timeout: None,
                 cache_key: None,
                 safety_identifier: None,
+                provider_extensions: serde_json::Map::new(),
+                extra_body: serde_json::Map::new(),
+                reasoning: None,
+                store: None,
+                privacy: PrivacyMode::Default,
+                request_metadata: std::collections::HashMap::new(),
+                background: false,
+                endpoint_overrides: None,
             };
 
             match engine.chat(streaming_request).await {
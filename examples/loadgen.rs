@@ -0,0 +1,205 @@
+//! Load generator for the Chat Completions endpoint
+//!
+//! Fires streamed chat completions at a server, `--concurrency` at a time,
+//! and reports latency and time to first token (TTFT) percentiles. Without
+//! `--url` it starts a server in-process whose provider is a mock adapter
+//! that streams a fixed reply with a short pause per token, so the numbers
+//! show the gateway's own overhead.
+//!
+//! ```text
+//! cargo run --release --example loadgen -- --requests 2000 --concurrency 64
+//! cargo run --release --example loadgen -- --url http://localhost:8080 --model ollama/llama3
+//! ```
+
+use async_trait::async_trait;
+use futures_util::{Stream, StreamExt};
+use omniference::{
+    adapter::{AdapterError, ChatAdapter},
+    server::OmniferenceServerBuilder,
+    stream::StreamEvent,
+    types::{ChatRequestIR, DiscoveredModel, Modality, ModelCapabilities, ProviderConfig, ProviderEndpoint, ProviderKind},
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const MOCK_MODEL: &str = "mock/mock-model";
+
+/// Streams `tokens` deltas, `delay` apart
+struct MockAdapter {
+    tokens: usize,
+    delay: Duration,
+}
+
+#[async_trait]
+impl ChatAdapter for MockAdapter {
+    fn provider_kind(&self) -> ProviderKind {
+        ProviderKind::Custom("mock".to_string())
+    }
+
+    async fn execute_chat(
+        &self,
+        _ir: ChatRequestIR,
+        _cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Box<dyn Stream<Item = StreamEvent> + Send + Unpin>, AdapterError> {
+        let delay = self.delay;
+        let deltas = futures_util::stream::iter(0..self.tokens).then(move |i| async move {
+            tokio::time::sleep(delay).await;
+            StreamEvent::TextDelta { content: format!("token{} ", i) }
+        });
+        Ok(Box::new(Box::pin(deltas.chain(futures_util::stream::iter([StreamEvent::Done])))))
+    }
+
+    async fn discover_models(&self, _endpoint: &ProviderEndpoint) -> Result<Vec<DiscoveredModel>, AdapterError> {
+        Ok(vec![DiscoveredModel {
+            id: MOCK_MODEL.to_string(),
+            name: "mock-model".to_string(),
+            provider_name: "mock".to_string(),
+            provider_kind: self.provider_kind(),
+            modalities: vec![Modality::Text],
+            capabilities: ModelCapabilities::text_only(),
+            created: None,
+        }])
+    }
+}
+
+struct Options {
+    url: Option<String>,
+    model: String,
+    requests: usize,
+    concurrency: usize,
+    tokens: usize,
+}
+
+impl Options {
+    fn parse() -> anyhow::Result<Self> {
+        let mut options = Options {
+            url: None,
+            model: MOCK_MODEL.to_string(),
+            requests: 500,
+            concurrency: 32,
+            tokens: 32,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or_else(|| anyhow::anyhow!("{} needs a value", flag));
+            match flag.as_str() {
+                "--url" => options.url = Some(value()?),
+                "--model" => options.model = value()?,
+                "--requests" => options.requests = value()?.parse()?,
+                "--concurrency" => options.concurrency = value()?.parse()?,
+                "--tokens" => options.tokens = value()?.parse()?,
+                _ => anyhow::bail!(
+                    "usage: loadgen [--url <server>] [--model <model>] [--requests <n>] [--concurrency <n>] [--tokens <n>]"
+                ),
+            }
+        }
+        options.concurrency = options.concurrency.max(1);
+        Ok(options)
+    }
+}
+
+/// Start a server over the mock adapter on a free port, returning its URL
+async fn start_mock_server(tokens: usize) -> anyhow::Result<String> {
+    let mut server = OmniferenceServerBuilder::new()
+        .with_adapter(Arc::new(MockAdapter {
+            tokens,
+            delay: Duration::from_millis(1),
+        }))
+        .with_provider(ProviderConfig {
+            name: "mock".to_string(),
+            endpoint: ProviderEndpoint {
+                kind: ProviderKind::Custom("mock".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .build();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move { server.serve_with_listener(listener).await });
+    Ok(url)
+}
+
+/// Timings of one streamed request
+struct Sample {
+    ttft: Duration,
+    latency: Duration,
+}
+
+async fn run_request(client: &reqwest::Client, url: &str, model: &str) -> anyhow::Result<Sample> {
+    let started = Instant::now();
+    let response = client
+        .post(format!("{}/api/openai-compatible/v1/chat/completions", url))
+        .json(&serde_json::json!({
+            "model": model,
+            "messages": [{"role": "user", "content": "Count to ten."}],
+            "stream": true,
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+    let mut body = response.bytes_stream();
+    let mut ttft = None;
+    while let Some(bytes) = body.next().await {
+        let bytes = bytes?;
+        if ttft.is_none() && bytes.windows(9).any(|window| window == b"\"content\"") {
+            ttft = Some(started.elapsed());
+        }
+    }
+    let latency = started.elapsed();
+    Ok(Sample {
+        ttft: ttft.unwrap_or(latency),
+        latency,
+    })
+}
+
+/// The `p`th percentile of sorted `durations`
+fn percentile(durations: &[Duration], p: f64) -> Duration {
+    let index = ((durations.len() as f64 - 1.0) * p).round() as usize;
+    durations.get(index).copied().unwrap_or_default()
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let options = Options::parse()?;
+    let url = match &options.url {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => start_mock_server(options.tokens).await?,
+    };
+    let client = reqwest::Client::new();
+    println!(
+        "{} requests to {} at {}, {} at a time",
+        options.requests, options.model, url, options.concurrency
+    );
+
+    let started = Instant::now();
+    let results: Vec<_> = futures_util::stream::iter(0..options.requests)
+        .map(|_| run_request(&client, &url, &options.model))
+        .buffer_unordered(options.concurrency)
+        .collect()
+        .await;
+    let elapsed = started.elapsed();
+
+    let (mut samples, mut failures) = (Vec::new(), 0);
+    for result in results {
+        match result {
+            Ok(sample) => samples.push(sample),
+            Err(e) => {
+                if failures == 0 {
+                    eprintln!("first failure: {}", e);
+                }
+                failures += 1;
+            }
+        }
+    }
+    let mut latencies: Vec<_> = samples.iter().map(|sample| sample.latency).collect();
+    let mut ttfts: Vec<_> = samples.iter().map(|sample| sample.ttft).collect();
+    latencies.sort();
+    ttfts.sort();
+
+    println!("completed: {}, failed: {}", samples.len(), failures);
+    println!("throughput: {:.1} requests/s", samples.len() as f64 / elapsed.as_secs_f64());
+    println!("latency  p50 {:>8.2?}  p95 {:>8.2?}", percentile(&latencies, 0.5), percentile(&latencies, 0.95));
+    println!("ttft     p50 {:>8.2?}  p95 {:>8.2?}", percentile(&ttfts, 0.5), percentile(&ttfts, 0.95));
+    Ok(())
+}
@@ -31,19 +31,20 @@ async fn main() -> anyhow::Result<()> {
             api_key: None,
             extra_headers: std::collections::BTreeMap::new(),
             timeout: Some(30000),
+            ..Default::default()
         },
         enabled: true,
-    }).await.map_err(|e| anyhow::anyhow!(e))?;
+        ..Default::default()
+    }).await?;
 
     println!("✅ Ollama provider configured");
 
     // Alternative: Using builder pattern
     /*
     let mut server = omniference::server::OmniferenceServerBuilder::new()
-        .with_adapter(Arc::new(OllamaAdapter))
+        .with_provider(provider_config)
+        .with_route("/healthz", axum::routing::get(|| async { "ok" }))
         .build();
-    
-    server.add_provider(provider_config).await?;
     */
 
     // Server configuration
@@ -59,7 +60,7 @@ async fn main() -> anyhow::Result<()> {
     println!("\n🔍 Example curl commands:");
     println!("   # List models:");
     println!("   curl http://localhost:8080/api/openai/v1/models");
-    println!("");
+    println!();
     println!("   # Responses request:");
     println!("   curl -X POST http://localhost:8080/api/openai/v1/responses \\");
     println!("     -H 'Content-Type: application/json' \\");
@@ -0,0 +1,68 @@
+//! Example of getting a typed struct back from a model
+//!
+//! Run with: cargo run --example structured_output --features structured
+
+use omniference::{
+    types::{ChatRequestIR, ContentPart, Message, ProviderConfig, ProviderEndpoint, ProviderKind, Role},
+    OmniferenceEngine,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Deserialize, JsonSchema)]
+struct City {
+    name: String,
+    country: String,
+    population: u64,
+    landmarks: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let _ = dotenvy::dotenv();
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    let mut engine = OmniferenceEngine::new();
+    let ollama_base =
+        std::env::var("OLLAMA_BASE_URL").unwrap_or_else(|_| "http://localhost:11434".to_string());
+    engine
+        .register_provider(ProviderConfig {
+            name: "ollama".to_string(),
+            endpoint: ProviderEndpoint {
+                kind: ProviderKind::Ollama,
+                base_url: ollama_base,
+                timeout: Some(60000),
+                ..Default::default()
+            },
+            enabled: true,
+            ..Default::default()
+        })
+        .await?;
+
+    let models = engine.discover_models().await?;
+    let Some(model) = models.first() else {
+        println!("❌ No models found. Make sure Ollama is running and has models.");
+        return Ok(());
+    };
+    println!("🎯 Using model: {}", model.id);
+
+    let request = ChatRequestIR {
+        model: engine.resolve_model(&model.id).await?,
+        messages: vec![Message {
+            role: Role::User,
+            parts: vec![ContentPart::Text("Describe Paris.".to_string())],
+            name: None,
+        }],
+        ..Default::default()
+    };
+
+    let city: City = engine.chat_structured(request).await?;
+    println!("📦 {}, {} (population {})", city.name, city.country, city.population);
+    for landmark in &city.landmarks {
+        println!("   - {}", landmark);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+//! Example of running the Omniference Telegram bot
+//!
+//! To run this example:
+//! 1. Create a bot with @BotFather and copy its token
+//! 2. Set the TELEGRAM_TOKEN environment variable
+//! 3. Run with: cargo run --example telegram_bot --features telegram
+//!
+//! Send text or photos to chat; `/model` lists or sets the model and
+//! `/reset` clears the chat history.
+
+use omniference::{OmniferenceEngine, skins::telegram::{TelegramBot, TelegramBotConfig}, types::{ProviderConfig, ProviderKind, ProviderEndpoint}};
+use std::env;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // Load .env if present
+    let _ = dotenvy::dotenv();
+    // Initialize logging
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    let token = env::var("TELEGRAM_TOKEN")
+        .expect("TELEGRAM_TOKEN environment variable not set");
+
+    // Set up Omniference engine
+    let mut engine = OmniferenceEngine::new();
+    let ollama_base = env::var("OLLAMA_BASE_URL").unwrap_or_else(|_| "http://localhost:11434".to_string());
+    engine.register_provider(ProviderConfig {
+        name: "ollama".to_string(),
+        endpoint: ProviderEndpoint {
+            kind: ProviderKind::Ollama,
+            base_url: ollama_base,
+            api_key: None,
+            extra_headers: std::collections::BTreeMap::new(),
+            timeout: Some(30000),
+            ..Default::default()
+        },
+        enabled: true,
+        ..Default::default()
+    }).await?;
+
+    let mut config = TelegramBotConfig::new(token);
+    config.default_model = env::var("TELEGRAM_MODEL").ok();
+
+    println!("🔌 Starting Telegram bot...");
+    TelegramBot::new(engine, config).run().await?;
+
+    Ok(())
+}
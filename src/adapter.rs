@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use futures_util::Stream;
 use crate::{types::ChatRequestIR, stream::StreamEvent, types::DiscoveredModel};
+use crate::types::{ImageRequestIR, ImageResponseIR, SpeechRequestIR, SpeechResponseIR};
 
 #[async_trait]
 pub trait ChatAdapter: Send + Sync {
@@ -17,6 +18,24 @@ pub trait ChatAdapter: Send + Sync {
     async fn discover_models(&self, _endpoint: &crate::types::ProviderEndpoint) -> Result<Vec<DiscoveredModel>, AdapterError> {
         Ok(Vec::new())
     }
+
+    /// Generate images from a prompt. Adapters without image support keep
+    /// this default, which reports the request as unsupported.
+    async fn execute_image(&self, _ir: ImageRequestIR) -> Result<ImageResponseIR, AdapterError> {
+        Err(AdapterError::Unsupported(format!(
+            "image generation is not supported by {:?} providers",
+            self.provider_kind()
+        )))
+    }
+
+    /// Synthesize speech. Adapters without text-to-speech keep this default,
+    /// which reports the request as unsupported.
+    async fn execute_speech(&self, _ir: SpeechRequestIR) -> Result<SpeechResponseIR, AdapterError> {
+        Err(AdapterError::Unsupported(format!(
+            "text-to-speech is not supported by {:?} providers",
+            self.provider_kind()
+        )))
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -31,6 +50,11 @@ pub enum AdapterError {
     Timeout,
     #[error("internal: {0}")]
     Internal(String),
+    #[error("unsupported: {0}")]
+    Unsupported(String),
+    /// Credentials or auth headers for the upstream could not be produced
+    #[error("authentication error: {0}")]
+    Auth(String),
 }
 
 impl AdapterError {
@@ -56,4 +80,8 @@ impl AdapterError {
     pub fn internal<S: Into<String>>(msg: S) -> Self {
         AdapterError::Internal(msg.into())
     }
+
+    pub fn unsupported<S: Into<String>>(msg: S) -> Self {
+        AdapterError::Unsupported(msg.into())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,467 @@
+use crate::{
+    adapter::{AdapterError, ChatAdapter},
+    adapters::{body, http, sse},
+    stream::*,
+    types::*,
+};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+
+use std::collections::{BTreeMap, HashMap};
+use tokio_util::sync::CancellationToken;
+use crate::anthropic::{
+    AnthropicBlockDelta, AnthropicContentBlock, AnthropicErrorResponse, AnthropicImageSource,
+    AnthropicMessage, AnthropicMessagesRequest, AnthropicMessagesResponse, AnthropicModelsResponse,
+    AnthropicStreamEvent, AnthropicThinking, AnthropicTool, AnthropicToolChoice,
+};
+
+/// API version sent as `anthropic-version` unless the endpoint sets its own
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Output budget used when the request doesn't set `max_tokens`, which the
+/// Messages API requires
+pub const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+#[derive(Default)]
+pub struct AnthropicAdapter {
+    thinking_budgets: ThinkingBudgets,
+}
+
+#[async_trait]
+impl ChatAdapter for AnthropicAdapter {
+    fn provider_kind(&self) -> ProviderKind {
+        ProviderKind::Anthropic
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    fn supports_vision(&self) -> bool {
+        true
+    }
+
+    async fn discover_models(
+        &self,
+        endpoint: &ProviderEndpoint,
+    ) -> Result<Vec<DiscoveredModel>, AdapterError> {
+        let client = http::client(endpoint)?;
+        let url = Self::endpoint_url(endpoint, "/models");
+
+        let mut request = client.get(&url);
+
+        for (key, value) in Self::request_headers(endpoint).await? {
+            request = request.header(key, value);
+        }
+
+        let resp = http::send(request, endpoint, "Failed to fetch models").await?;
+
+        if !resp.status().is_success() {
+            return Err(Self::error_response(resp).await);
+        }
+
+        let models_response: AnthropicModelsResponse = resp
+            .json()
+            .await
+            .map_err(|e| AdapterError::Http(format!("Failed to parse models response: {}", e)))?;
+
+        let discovered_models: Vec<DiscoveredModel> = models_response
+            .data
+            .into_iter()
+            .map(|model| DiscoveredModel {
+                id: format!("anthropic/{}", model.id),
+                name: model.id,
+                provider_name: "anthropic".to_string(),
+                provider_kind: ProviderKind::Anthropic,
+                modalities: vec![Modality::Text, Modality::Vision],
+                capabilities: ModelCapabilities::text_only()
+                    .with_tools()
+                    .with_vision()
+                    .with_context_length(200_000),
+                created: model.created_at.as_deref().and_then(crate::types::parse_rfc3339),
+            })
+            .collect();
+
+        Ok(discovered_models)
+    }
+
+    async fn execute_chat(
+        &self,
+        ir: ChatRequestIR,
+        cancel: CancellationToken,
+    ) -> Result<Box<dyn futures_util::Stream<Item = StreamEvent> + Send + Unpin>, AdapterError>
+    {
+        let payload = self.build_request_body(&ir)?;
+
+        let client = http::client(&ir.model.provider)?;
+        let url = Self::endpoint_url(&ir.model.provider, "/messages");
+
+        let mut request = http::json_body(client.post(&url), &payload, &ir.model.provider, &ir.model.model_id);
+
+        for (key, value) in Self::request_headers(&ir.model.provider).await? {
+            request = request.header(key, value);
+        }
+
+        let mut resp = http::send(request, &ir.model.provider, "Failed to send request").await?;
+        let idle_timeout = ir.model.provider.idle_stream_timeout();
+
+        if !resp.status().is_success() {
+            return Err(Self::error_response(resp).await);
+        }
+
+        if !ir.stream {
+            let response: AnthropicMessagesResponse =
+                http::response_json(resp, &ir.model.provider, &ir.model.model_id, "Failed to parse response").await?;
+
+            let mut events = Vec::new();
+            for block in response.content {
+                match block {
+                    AnthropicContentBlock::Text { text } => {
+                        events.push(StreamEvent::TextDelta { content: text });
+                    }
+                    AnthropicContentBlock::ToolUse { id, name, input } => {
+                        events.push(StreamEvent::ToolCallStart {
+                            id: id.clone(),
+                            name,
+                            args_json: input,
+                        });
+                        events.push(StreamEvent::ToolCallEnd { id });
+                    }
+                    _ => {}
+                }
+            }
+            events.push(StreamEvent::Tokens {
+                input: response.usage.input_tokens,
+                output: response.usage.output_tokens,
+            });
+            events.push(StreamEvent::Done);
+
+            return Ok(Box::new(futures_util::stream::iter(events)));
+        }
+
+        let s = async_stream::try_stream! {
+            // Tool call ids by content block index
+            let mut tool_blocks: HashMap<usize, String> = HashMap::new();
+            let mut input_tokens = 0;
+            let mut lines = body::LineBuffer::new();
+
+            loop {
+                let chunk = match body::next_chunk(&mut resp, &cancel, idle_timeout).await? {
+                    body::BodyRead::Chunk(chunk) => chunk,
+                    body::BodyRead::End => break,
+                    body::BodyRead::Cancelled => {
+                        drop(resp);
+                        yield body::cancelled_event();
+                        return;
+                    }
+                };
+
+                for line in lines.push(&chunk) {
+                    let json_str = match sse::parse_line(&line) {
+                        sse::SseLine::Data(data) => data,
+                        sse::SseLine::Comment(comment) => {
+                            if let Some(status) = sse::comment_status(comment) {
+                                yield status;
+                            }
+                            continue;
+                        }
+                        sse::SseLine::Other => continue,
+                    };
+
+                    let Ok(event) = serde_json::from_str::<AnthropicStreamEvent>(json_str) else {
+                        continue;
+                    };
+
+                    match event {
+                        AnthropicStreamEvent::MessageStart { message } => {
+                            input_tokens = message.usage.input_tokens;
+                        }
+                        AnthropicStreamEvent::ContentBlockStart { index, content_block } => {
+                            match content_block {
+                                AnthropicContentBlock::Text { text } if !text.is_empty() => {
+                                    yield StreamEvent::TextDelta { content: text };
+                                }
+                                AnthropicContentBlock::ToolUse { id, name, .. } => {
+                                    tool_blocks.insert(index, id.clone());
+                                    yield StreamEvent::ToolCallStart {
+                                        id,
+                                        name,
+                                        args_json: serde_json::Value::Object(serde_json::Map::new()),
+                                    };
+                                }
+                                _ => {}
+                            }
+                        }
+                        AnthropicStreamEvent::ContentBlockDelta { index, delta } => match delta {
+                            AnthropicBlockDelta::TextDelta { text } => {
+                                yield StreamEvent::TextDelta { content: text };
+                            }
+                            AnthropicBlockDelta::InputJsonDelta { partial_json } => {
+                                if let Some(id) = tool_blocks.get(&index) {
+                                    if !partial_json.is_empty() {
+                                        yield StreamEvent::ToolCallDelta {
+                                            id: id.clone(),
+                                            args_delta_json: serde_json::Value::String(partial_json),
+                                        };
+                                    }
+                                }
+                            }
+                            AnthropicBlockDelta::Unknown => {}
+                        },
+                        AnthropicStreamEvent::ContentBlockStop { index } => {
+                            if let Some(id) = tool_blocks.remove(&index) {
+                                yield StreamEvent::ToolCallEnd { id };
+                            }
+                        }
+                        AnthropicStreamEvent::MessageDelta { usage, .. } => {
+                            yield StreamEvent::Tokens {
+                                input: input_tokens,
+                                output: usage.output_tokens,
+                            };
+                        }
+                        AnthropicStreamEvent::MessageStop => {
+                            yield StreamEvent::Done;
+                            return;
+                        }
+                        AnthropicStreamEvent::Ping => {}
+                        AnthropicStreamEvent::Error { error } => {
+                            yield StreamEvent::Error {
+                                code: error.r#type,
+                                message: error.message,
+                            };
+                            return;
+                        }
+                    }
+                }
+            }
+
+            yield StreamEvent::Done;
+        };
+
+        Ok(Box::new(Box::pin(s.map(
+            |r: Result<StreamEvent, AdapterError>| match r {
+                Ok(ev) => ev,
+                Err(e) => body::stream_error_event(e),
+            },
+        ))))
+    }
+}
+
+impl AnthropicAdapter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `budgets` to turn a request's reasoning effort into a thinking budget
+    pub fn with_thinking_budgets(mut self, budgets: ThinkingBudgets) -> Self {
+        self.thinking_budgets = budgets;
+        self
+    }
+
+    /// Messages API URL for `route`, defaulting to Anthropic's public API
+    /// when the endpoint leaves `base_url` empty
+    pub fn endpoint_url(endpoint: &ProviderEndpoint, route: &str) -> String {
+        let base = if endpoint.base_url.is_empty() {
+            ProviderKind::Anthropic.default_base_url().unwrap_or_default()
+        } else {
+            endpoint.base_url.as_str()
+        };
+        http::join_url(base, &format!("/v1{}", route))
+    }
+
+    /// `x-api-key` and `anthropic-version`, with the endpoint's own headers
+    /// applied over them
+    pub(crate) async fn request_headers(endpoint: &ProviderEndpoint) -> Result<BTreeMap<String, String>, AdapterError> {
+        let mut headers = BTreeMap::new();
+        if let Some(token) = endpoint.bearer_token().await? {
+            headers.insert("x-api-key".to_string(), token);
+        }
+        headers.insert("anthropic-version".to_string(), ANTHROPIC_VERSION.to_string());
+
+        for (key, value) in endpoint.headers().await? {
+            headers.retain(|existing, _| !existing.eq_ignore_ascii_case(&key));
+            headers.insert(key, value);
+        }
+        Ok(headers)
+    }
+
+    async fn error_response(resp: reqwest::Response) -> AdapterError {
+        let status = resp.status();
+        let text = resp
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+
+        match serde_json::from_str::<AnthropicErrorResponse>(&text) {
+            Ok(error_response) => AdapterError::Provider {
+                code: error_response.error.r#type,
+                message: error_response.error.message,
+            },
+            Err(_) => AdapterError::Provider {
+                code: status.as_u16().to_string(),
+                message: text,
+            },
+        }
+    }
+
+    /// Build the outbound JSON body, with the endpoint's static extensions and
+    /// then the request's own extensions merged on top
+    pub fn build_request_body(&self, ir: &ChatRequestIR) -> Result<serde_json::Value, AdapterError> {
+        let payload = self.build_messages_request(ir)?;
+        let mut body = serde_json::to_value(&payload)
+            .map_err(|e| AdapterError::internal(format!("Failed to serialize request: {}", e)))?;
+
+        if let serde_json::Value::Object(map) = &mut body {
+            for (key, value) in ir
+                .model
+                .provider
+                .extensions
+                .iter()
+                .chain(ir.provider_extensions.iter())
+            {
+                map.insert(key.clone(), value.clone());
+            }
+        }
+
+        Ok(body)
+    }
+
+    fn build_messages_request(&self, ir: &ChatRequestIR) -> Result<AnthropicMessagesRequest, AdapterError> {
+        let mut system: Vec<String> = Vec::new();
+        let mut messages: Vec<AnthropicMessage> = Vec::new();
+
+        for msg in &ir.messages {
+            let role = match msg.role {
+                Role::System | Role::Developer => {
+                    let text: String = msg
+                        .parts
+                        .iter()
+                        .filter_map(|part| match part {
+                            ContentPart::Text(text) => Some(text.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    system.push(text);
+                    continue;
+                }
+                Role::User | Role::Tool => "user",
+                Role::Assistant => "assistant",
+            };
+
+            let content: Vec<AnthropicContentBlock> = msg
+                .parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text(text) => Some(AnthropicContentBlock::Text { text: text.clone() }),
+                    ContentPart::ImageUrl { url, .. } => Some(AnthropicContentBlock::Image {
+                        source: Self::image_source(url),
+                    }),
+                    ContentPart::ToolCall { id, name, arguments } => Some(AnthropicContentBlock::ToolUse {
+                        id: id.clone(),
+                        name: name.clone(),
+                        input: match arguments {
+                            serde_json::Value::String(raw) => serde_json::from_str(raw)
+                                .unwrap_or_else(|_| serde_json::Value::Object(serde_json::Map::new())),
+                            other => other.clone(),
+                        },
+                    }),
+                    ContentPart::ToolResult { call_id, content } => Some(AnthropicContentBlock::ToolResult {
+                        tool_use_id: call_id.clone(),
+                        content: content.clone(),
+                    }),
+                    ContentPart::BlobRef { .. } => {
+                        tracing::warn!("BlobRef not supported by Anthropic adapter");
+                        None
+                    }
+                    ContentPart::Audio { .. } => {
+                        tracing::warn!("Audio not supported by Anthropic adapter");
+                        None
+                    }
+                    ContentPart::File { .. } => {
+                        tracing::warn!("File content not supported by Anthropic adapter");
+                        None
+                    }
+                })
+                .collect();
+
+            // Turns must alternate, so tool results and other same-role
+            // messages in a row are merged into one turn
+            match messages.last_mut() {
+                Some(last) if last.role == role => last.content.extend(content),
+                _ => messages.push(AnthropicMessage {
+                    role: role.to_string(),
+                    content,
+                }),
+            }
+        }
+
+        let tools: Vec<AnthropicTool> = ir
+            .tools
+            .iter()
+            .map(|tool| match tool {
+                ToolSpec::JsonSchema { name, description, schema, .. } => AnthropicTool {
+                    name: name.clone(),
+                    description: description.clone(),
+                    input_schema: schema.clone(),
+                },
+            })
+            .collect();
+
+        let tool_choice = if tools.is_empty() {
+            None
+        } else {
+            Some(match &ir.tool_choice {
+                ToolChoice::Auto => AnthropicToolChoice::Auto,
+                ToolChoice::None => AnthropicToolChoice::None,
+                ToolChoice::Required => AnthropicToolChoice::Any,
+                ToolChoice::Named(name) => AnthropicToolChoice::Tool { name: name.clone() },
+                ToolChoice::Allowed { mode, .. } if mode == "required" => AnthropicToolChoice::Any,
+                ToolChoice::Allowed { .. } => AnthropicToolChoice::Auto,
+            })
+        };
+
+        let budget_tokens = ir
+            .reasoning
+            .as_ref()
+            .and_then(|reasoning| reasoning.effort)
+            .map(|effort| self.thinking_budgets.budget_for(effort));
+        let mut max_tokens = ir.sampling.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+        if let Some(budget) = budget_tokens {
+            // The thinking budget counts towards `max_tokens` and must stay below it
+            if max_tokens <= budget {
+                max_tokens = budget + DEFAULT_MAX_TOKENS;
+            }
+        }
+
+        Ok(AnthropicMessagesRequest {
+            model: ir.model.model_id.clone(),
+            messages,
+            max_tokens,
+            system: if system.is_empty() {
+                None
+            } else {
+                Some(system.join("\n\n"))
+            },
+            tools,
+            tool_choice,
+            // Thinking requires the default temperature and no top_k
+            temperature: ir.sampling.temperature.filter(|_| budget_tokens.is_none()),
+            top_p: ir.sampling.top_p,
+            top_k: ir.sampling.top_k.filter(|_| budget_tokens.is_none()),
+            stop_sequences: ir.sampling.stop.clone(),
+            thinking: budget_tokens.map(|budget_tokens| AnthropicThinking::Enabled { budget_tokens }),
+            stream: ir.stream,
+        })
+    }
+
+    /// Inline `data:` URLs as base64 sources; pass anything else by URL
+    fn image_source(url: &str) -> AnthropicImageSource {
+        url.strip_prefix("data:")
+            .and_then(|rest| rest.split_once(";base64,"))
+            .map(|(media_type, data)| AnthropicImageSource::Base64 {
+                media_type: media_type.to_string(),
+                data: data.to_string(),
+            })
+            .unwrap_or_else(|| AnthropicImageSource::Url { url: url.to_string() })
+    }
+}
@@ -0,0 +1,126 @@
+//! Cancellable reads of streamed response bodies
+//!
+//! Waiting on `resp.chunk()` alone only notices cancellation once the
+//! provider sends more data. [`next_chunk`] races the read against the
+//! cancellation token so adapters can drop the response right away, which
+//! closes the connection and stops the upstream generation.
+//!
+//! With an idle timeout, a read that waits longer than it for the next chunk
+//! fails with the code `stream_stalled`.
+//!
+//! Chunk boundaries don't follow lines: a chunk may end halfway through a
+//! JSON object or a multi-byte character. [`LineBuffer`] reassembles lines.
+
+use crate::adapter::AdapterError;
+use crate::adapters::http::STREAM_STALLED;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Outcome of one body read
+pub enum BodyRead {
+    Chunk(bytes::Bytes),
+    /// The body is complete
+    End,
+    /// `cancel` fired first; drop the response to abort the request
+    Cancelled,
+}
+
+pub async fn next_chunk(
+    resp: &mut reqwest::Response,
+    cancel: &CancellationToken,
+    idle_timeout: Option<Duration>,
+) -> Result<BodyRead, AdapterError> {
+    let stalled = async {
+        match idle_timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Ok(BodyRead::Cancelled),
+        chunk = resp.chunk() => match chunk {
+            Ok(Some(chunk)) => Ok(BodyRead::Chunk(chunk)),
+            Ok(None) => Ok(BodyRead::End),
+            Err(e) => Err(AdapterError::Http(format!("Failed to read chunk: {}", e))),
+        },
+        _ = stalled => Err(AdapterError::provider(
+            STREAM_STALLED.to_string(),
+            format!("no data from the provider for {} ms", idle_timeout.unwrap_or_default().as_millis()),
+        )),
+    }
+}
+
+/// The event adapters send when a request is cancelled mid-stream
+pub fn cancelled_event() -> crate::stream::StreamEvent {
+    crate::stream::StreamEvent::Error {
+        code: "cancelled".to_string(),
+        message: "Request was cancelled".to_string(),
+    }
+}
+
+/// The event adapters send when reading a stream fails. A stalled stream
+/// keeps its `stream_stalled` code; other failures are `stream_error`s.
+pub fn stream_error_event(error: AdapterError) -> crate::stream::StreamEvent {
+    match error {
+        AdapterError::Provider { code, message } if code == STREAM_STALLED => {
+            crate::stream::StreamEvent::Error { code, message }
+        }
+        error => crate::stream::StreamEvent::Error {
+            code: "stream_error".to_string(),
+            message: error.to_string(),
+        },
+    }
+}
+
+/// Splits body chunks into complete lines, carrying partial trailing data
+/// (including incomplete UTF-8 sequences) over to the next chunk
+#[derive(Default)]
+pub struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a chunk and return the lines it completes, without their line
+    /// endings. Empty lines are returned too.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+        let Some(last_newline) = self.pending.iter().rposition(|b| *b == b'\n') else {
+            return Vec::new();
+        };
+        let rest = self.pending.split_off(last_newline + 1);
+        let mut complete = std::mem::replace(&mut self.pending, rest);
+        complete.pop();
+        // The first line keeps the buffer's allocation; the others (in SSE
+        // usually just the empty line ending an event) are copied out
+        let first_end = complete.iter().position(|b| *b == b'\n').unwrap_or(complete.len());
+        let mut lines = vec![String::new()];
+        lines.extend(
+            complete[first_end..]
+                .split(|b| *b == b'\n')
+                .skip(1)
+                .map(|line| into_line(line.strip_suffix(b"\r").unwrap_or(line).to_vec())),
+        );
+        complete.truncate(first_end);
+        if complete.last() == Some(&b'\r') {
+            complete.pop();
+        }
+        lines[0] = into_line(complete);
+        lines
+    }
+
+    /// The final line when the body doesn't end with a newline
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.pending);
+        (!rest.is_empty()).then(|| into_line(rest))
+    }
+}
+
+/// `line` decoded, without a copy when it is valid UTF-8
+fn into_line(line: Vec<u8>) -> String {
+    String::from_utf8(line).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
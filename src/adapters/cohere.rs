@@ -0,0 +1,513 @@
+use crate::{
+    adapter::{AdapterError, ChatAdapter},
+    adapters::{body, http, sse},
+    stream::*,
+    types::*,
+};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+
+use std::collections::{BTreeMap, HashMap};
+use tokio_util::sync::CancellationToken;
+use crate::cohere::{
+    CohereChatRequest, CohereChatResponse, CohereCitation, CohereContent, CohereErrorResponse, CohereFunction,
+    CohereFunctionCall, CohereImageUrl, CohereMessage, CohereModelsResponse, CohereResponseFormat, CohereStreamEvent,
+    CohereTool, CohereToolCall,
+};
+
+/// Adapter for Cohere's Chat API v2 (`/v2/chat`)
+#[derive(Default)]
+pub struct CohereAdapter;
+
+#[async_trait]
+impl ChatAdapter for CohereAdapter {
+    fn provider_kind(&self) -> ProviderKind {
+        ProviderKind::Cohere
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    fn supports_vision(&self) -> bool {
+        true
+    }
+
+    async fn discover_models(
+        &self,
+        endpoint: &ProviderEndpoint,
+    ) -> Result<Vec<DiscoveredModel>, AdapterError> {
+        let client = http::client(endpoint)?;
+        let url = Self::endpoint_url(endpoint, "/v1/models?endpoint=chat");
+
+        let mut request = client.get(&url);
+
+        for (key, value) in Self::request_headers(endpoint).await? {
+            request = request.header(key, value);
+        }
+
+        let resp = http::send(request, endpoint, "Failed to fetch models").await?;
+
+        if !resp.status().is_success() {
+            return Err(Self::error_response(resp).await);
+        }
+
+        let models_response: CohereModelsResponse = resp
+            .json()
+            .await
+            .map_err(|e| AdapterError::Http(format!("Failed to parse models response: {}", e)))?;
+
+        let discovered_models: Vec<DiscoveredModel> = models_response
+            .models
+            .into_iter()
+            // The listing also holds embedding and rerank models
+            .filter(|model| model.endpoints.iter().any(|endpoint| endpoint == "chat"))
+            .map(|model| {
+                let features = model.features.unwrap_or_default();
+                let has = |feature: &str| features.iter().any(|f| f == feature);
+                let supports_vision = model.supports_vision.unwrap_or(false) || has("vision");
+                let mut modalities = vec![Modality::Text];
+                if supports_vision {
+                    modalities.push(Modality::Vision);
+                }
+                DiscoveredModel {
+                    id: format!("cohere/{}", model.name),
+                    name: model.name,
+                    provider_name: "cohere".to_string(),
+                    provider_kind: ProviderKind::Cohere,
+                    modalities,
+                    capabilities: ModelCapabilities {
+                        supports_tools: features.is_empty() || has("tools") || has("strict_tools"),
+                        supports_vision,
+                        supports_json: has("json_mode") || has("json_schema"),
+                        context_length: model.context_length,
+                        ..ModelCapabilities::text_only()
+                    },
+                    created: None,
+                }
+            })
+            .collect();
+
+        Ok(discovered_models)
+    }
+
+    async fn execute_chat(
+        &self,
+        ir: ChatRequestIR,
+        cancel: CancellationToken,
+    ) -> Result<Box<dyn futures_util::Stream<Item = StreamEvent> + Send + Unpin>, AdapterError>
+    {
+        let payload = Self::build_request_body(&ir)?;
+
+        let client = http::client(&ir.model.provider)?;
+        let url = Self::endpoint_url(&ir.model.provider, "/v2/chat");
+
+        let mut request = http::json_body(client.post(&url), &payload, &ir.model.provider, &ir.model.model_id);
+
+        for (key, value) in Self::request_headers(&ir.model.provider).await? {
+            request = request.header(key, value);
+        }
+
+        let mut resp = http::send(request, &ir.model.provider, "Failed to send request").await?;
+        let idle_timeout = ir.model.provider.idle_stream_timeout();
+
+        if !resp.status().is_success() {
+            return Err(Self::error_response(resp).await);
+        }
+
+        if !ir.stream {
+            let response: CohereChatResponse =
+                http::response_json(resp, &ir.model.provider, &ir.model.model_id, "Failed to parse response").await?;
+            return Ok(Box::new(futures_util::stream::iter(Self::response_events(response))));
+        }
+
+        let s = async_stream::try_stream! {
+            // Tool call ids by tool call index
+            let mut tool_calls: HashMap<usize, String> = HashMap::new();
+            // Reply so far, for the final message: text and (id, name, arguments)
+            let mut content = String::new();
+            let mut calls: Vec<(String, String, String)> = Vec::new();
+            let mut lines = body::LineBuffer::new();
+
+            loop {
+                let chunk = match body::next_chunk(&mut resp, &cancel, idle_timeout).await? {
+                    body::BodyRead::Chunk(chunk) => chunk,
+                    body::BodyRead::End => break,
+                    body::BodyRead::Cancelled => {
+                        drop(resp);
+                        yield body::cancelled_event();
+                        return;
+                    }
+                };
+
+                for line in lines.push(&chunk) {
+                    let json_str = match sse::parse_line(&line) {
+                        sse::SseLine::Data(data) => data,
+                        sse::SseLine::Comment(comment) => {
+                            if let Some(status) = sse::comment_status(comment) {
+                                yield status;
+                            }
+                            continue;
+                        }
+                        sse::SseLine::Other => continue,
+                    };
+
+                    let Ok(event) = serde_json::from_str::<CohereStreamEvent>(json_str) else {
+                        continue;
+                    };
+
+                    match event {
+                        CohereStreamEvent::ContentStart { delta, .. } | CohereStreamEvent::ContentDelta { delta, .. } => {
+                            if let Some(text) = delta.message.content.map(|content| content.text) {
+                                if !text.is_empty() {
+                                    content.push_str(&text);
+                                    yield StreamEvent::TextDelta { content: text };
+                                }
+                            }
+                        }
+                        CohereStreamEvent::ToolPlanDelta { delta } => {
+                            if let Some(plan) = delta.message.tool_plan.filter(|plan| !plan.is_empty()) {
+                                yield StreamEvent::Status { state: "tool_plan".to_string(), detail: Some(plan) };
+                            }
+                        }
+                        CohereStreamEvent::ToolCallStart { index, delta } => {
+                            if let Some(call) = delta.message.tool_calls {
+                                let id = call.id.unwrap_or_else(|| format!("call_{}", index));
+                                tool_calls.insert(index, id.clone());
+                                calls.push((id.clone(), call.function.name.clone(), call.function.arguments.clone()));
+                                yield StreamEvent::ToolCallStart {
+                                    id: id.clone(),
+                                    name: call.function.name,
+                                    args_json: serde_json::Value::Object(serde_json::Map::new()),
+                                };
+                                if !call.function.arguments.is_empty() {
+                                    yield StreamEvent::ToolCallDelta {
+                                        id,
+                                        args_delta_json: serde_json::Value::String(call.function.arguments),
+                                    };
+                                }
+                            }
+                        }
+                        CohereStreamEvent::ToolCallDelta { index, delta } => {
+                            let arguments = delta.message.tool_calls.map(|call| call.function.arguments).unwrap_or_default();
+                            if let (Some(id), false) = (tool_calls.get(&index), arguments.is_empty()) {
+                                if let Some((_, _, raw)) = calls.iter_mut().find(|(call, _, _)| call == id) {
+                                    raw.push_str(&arguments);
+                                }
+                                yield StreamEvent::ToolCallDelta {
+                                    id: id.clone(),
+                                    args_delta_json: serde_json::Value::String(arguments),
+                                };
+                            }
+                        }
+                        CohereStreamEvent::ToolCallEnd { index } => {
+                            if let Some(id) = tool_calls.remove(&index) {
+                                yield StreamEvent::ToolCallEnd { id };
+                            }
+                        }
+                        CohereStreamEvent::CitationStart { delta, .. } => {
+                            if let Some(citation) = delta.message.citations {
+                                yield StreamEvent::Annotation { annotation: annotation(citation) };
+                            }
+                        }
+                        CohereStreamEvent::MessageEnd { delta } => {
+                            if let Some(usage) = delta.usage {
+                                let (input, output) = usage.counts();
+                                yield StreamEvent::Tokens { input, output };
+                            }
+                            if delta.finish_reason.as_deref() == Some("ERROR") {
+                                yield StreamEvent::Error {
+                                    code: "provider_error".to_string(),
+                                    message: delta.error.unwrap_or_else(|| "Cohere stopped with an error".to_string()),
+                                };
+                                return;
+                            }
+                            let finish_reason = delta.finish_reason.as_deref().map(finish_reason);
+                            yield crate::stream::final_message(
+                                std::mem::take(&mut content),
+                                std::mem::take(&mut calls),
+                                finish_reason,
+                            );
+                            yield StreamEvent::Done;
+                            return;
+                        }
+                        CohereStreamEvent::MessageStart { .. }
+                        | CohereStreamEvent::ContentEnd { .. }
+                        | CohereStreamEvent::CitationEnd { .. }
+                        | CohereStreamEvent::Unknown => {}
+                    }
+                }
+            }
+
+            yield StreamEvent::Done;
+        };
+
+        Ok(Box::new(Box::pin(s.map(
+            |r: Result<StreamEvent, AdapterError>| match r {
+                Ok(ev) => ev,
+                Err(e) => body::stream_error_event(e),
+            },
+        ))))
+    }
+}
+
+impl CohereAdapter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// API URL for `route`, defaulting to Cohere's public API when the
+    /// endpoint leaves `base_url` empty
+    pub fn endpoint_url(endpoint: &ProviderEndpoint, route: &str) -> String {
+        let base = if endpoint.base_url.is_empty() {
+            ProviderKind::Cohere.default_base_url().unwrap_or_default()
+        } else {
+            endpoint.base_url.as_str()
+        };
+        http::join_url(base, route)
+    }
+
+    /// Bearer authorization, with the endpoint's own headers applied over it
+    async fn request_headers(endpoint: &ProviderEndpoint) -> Result<BTreeMap<String, String>, AdapterError> {
+        let mut headers = BTreeMap::new();
+        if let Some(token) = endpoint.bearer_token().await? {
+            headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+        }
+
+        for (key, value) in endpoint.headers().await? {
+            headers.retain(|existing, _| !existing.eq_ignore_ascii_case(&key));
+            headers.insert(key, value);
+        }
+        Ok(headers)
+    }
+
+    async fn error_response(resp: reqwest::Response) -> AdapterError {
+        let status = resp.status();
+        let text = resp
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+
+        AdapterError::Provider {
+            code: status.as_u16().to_string(),
+            message: serde_json::from_str::<CohereErrorResponse>(&text)
+                .map(|error| error.message)
+                .unwrap_or(text),
+        }
+    }
+
+    /// The events of a complete (non-streaming) response
+    fn response_events(response: CohereChatResponse) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        let mut content = String::new();
+        let mut calls = Vec::new();
+        for part in response.message.content {
+            if let CohereContent::Text { text } = part {
+                content.push_str(&text);
+                events.push(StreamEvent::TextDelta { content: text });
+            }
+        }
+        if let Some(plan) = response.message.tool_plan.filter(|plan| !plan.is_empty()) {
+            events.push(StreamEvent::Status { state: "tool_plan".to_string(), detail: Some(plan) });
+        }
+        for call in response.message.tool_calls {
+            let args_json = serde_json::from_str(&call.function.arguments)
+                .unwrap_or_else(|_| serde_json::Value::String(call.function.arguments.clone()));
+            events.push(StreamEvent::ToolCallStart {
+                id: call.id.clone(),
+                name: call.function.name.clone(),
+                args_json,
+            });
+            events.push(StreamEvent::ToolCallEnd { id: call.id.clone() });
+            calls.push((call.id, call.function.name, call.function.arguments));
+        }
+        for citation in response.message.citations {
+            events.push(StreamEvent::Annotation { annotation: annotation(citation) });
+        }
+        if let Some(usage) = response.usage {
+            let (input, output) = usage.counts();
+            events.push(StreamEvent::Tokens { input, output });
+        }
+        if response.finish_reason.as_deref() == Some("ERROR") {
+            events.push(StreamEvent::Error {
+                code: "provider_error".to_string(),
+                message: "Cohere stopped with an error".to_string(),
+            });
+            return events;
+        }
+        let finish_reason = response.finish_reason.as_deref().map(finish_reason);
+        events.push(crate::stream::final_message(content, calls, finish_reason));
+        events.push(StreamEvent::Done);
+        events
+    }
+
+    /// Build the outbound JSON body, with the endpoint's static extensions and
+    /// then the request's own extensions merged on top
+    pub fn build_request_body(ir: &ChatRequestIR) -> Result<serde_json::Value, AdapterError> {
+        let payload = Self::build_chat_request(ir);
+        let mut body = serde_json::to_value(&payload)
+            .map_err(|e| AdapterError::internal(format!("Failed to serialize request: {}", e)))?;
+
+        if let serde_json::Value::Object(map) = &mut body {
+            for (key, value) in ir
+                .model
+                .provider
+                .extensions
+                .iter()
+                .chain(ir.provider_extensions.iter())
+            {
+                map.insert(key.clone(), value.clone());
+            }
+        }
+
+        Ok(body)
+    }
+
+    fn build_chat_request(ir: &ChatRequestIR) -> CohereChatRequest {
+        let mut messages = Vec::new();
+
+        for msg in &ir.messages {
+            let text = || -> String {
+                msg.parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        ContentPart::Text(text) => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect()
+            };
+            match msg.role {
+                Role::System | Role::Developer => messages.push(CohereMessage::System { content: text() }),
+                Role::User | Role::Tool | Role::Assistant => {
+                    let mut content = Vec::new();
+                    let mut tool_calls = Vec::new();
+                    for part in &msg.parts {
+                        match part {
+                            ContentPart::Text(text) => content.push(CohereContent::Text { text: text.clone() }),
+                            ContentPart::ImageUrl { url, .. } => content.push(CohereContent::ImageUrl {
+                                image_url: CohereImageUrl { url: url.clone() },
+                            }),
+                            ContentPart::ToolCall { id, name, arguments } => tool_calls.push(CohereToolCall {
+                                id: id.clone(),
+                                r#type: "function".to_string(),
+                                function: CohereFunctionCall {
+                                    name: name.clone(),
+                                    arguments: ContentPart::arguments_string(arguments),
+                                },
+                            }),
+                            // Each result is a message of its own
+                            ContentPart::ToolResult { call_id, content } => messages.push(CohereMessage::Tool {
+                                tool_call_id: call_id.clone(),
+                                content: content.clone(),
+                            }),
+                            ContentPart::BlobRef { .. } => tracing::warn!("BlobRef not supported by Cohere adapter"),
+                            ContentPart::Audio { .. } => tracing::warn!("Audio not supported by Cohere adapter"),
+                            ContentPart::File { .. } => tracing::warn!("File content not supported by Cohere adapter"),
+                        }
+                    }
+                    match msg.role {
+                        Role::Assistant => messages.push(CohereMessage::Assistant {
+                            content,
+                            tool_plan: None,
+                            tool_calls,
+                        }),
+                        _ if !content.is_empty() => messages.push(CohereMessage::User { content }),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        // A named tool, or an allowed subset, is forced by offering only those tools
+        let (allowed, tool_choice): (Option<Vec<&str>>, Option<&str>) = match &ir.tool_choice {
+            ToolChoice::Auto => (None, None),
+            ToolChoice::None => (None, Some("NONE")),
+            ToolChoice::Required => (None, Some("REQUIRED")),
+            ToolChoice::Named(name) => (Some(vec![name.as_str()]), Some("REQUIRED")),
+            ToolChoice::Allowed { mode, tools } => (
+                Some(tools.iter().map(String::as_str).collect()),
+                (mode == "required").then_some("REQUIRED"),
+            ),
+        };
+        let tools: Vec<CohereTool> = ir
+            .tools
+            .iter()
+            .filter_map(|tool| match tool {
+                ToolSpec::JsonSchema { name, description, schema, .. } => {
+                    if allowed.as_ref().is_some_and(|allowed| !allowed.contains(&name.as_str())) {
+                        return None;
+                    }
+                    Some(CohereTool {
+                        r#type: "function".to_string(),
+                        function: CohereFunction {
+                            name: name.clone(),
+                            description: description.clone(),
+                            parameters: schema.clone(),
+                        },
+                    })
+                }
+            })
+            .collect();
+
+        let response_format = match &ir.response_format {
+            None | Some(ResponseFormat::Text) => None,
+            Some(ResponseFormat::JsonObject) => Some(CohereResponseFormat {
+                r#type: "json_object".to_string(),
+                json_schema: None,
+            }),
+            Some(ResponseFormat::JsonSchema { schema, .. }) => Some(CohereResponseFormat {
+                r#type: "json_object".to_string(),
+                json_schema: Some(schema.clone()),
+            }),
+        };
+
+        CohereChatRequest {
+            model: ir.model.model_id.clone(),
+            messages,
+            tool_choice: tool_choice.filter(|_| !tools.is_empty()).map(str::to_string),
+            tools,
+            response_format,
+            max_tokens: ir.sampling.max_tokens,
+            temperature: ir.sampling.temperature,
+            p: ir.sampling.top_p,
+            k: ir.sampling.top_k,
+            seed: ir.sampling.seed,
+            stop_sequences: ir.sampling.stop.clone(),
+            frequency_penalty: ir.sampling.frequency_penalty,
+            presence_penalty: ir.sampling.presence_penalty,
+            stream: ir.stream,
+        }
+    }
+}
+
+/// Cohere's finish reason in OpenAI's terms
+fn finish_reason(reason: &str) -> String {
+    match reason {
+        "COMPLETE" | "STOP_SEQUENCE" => "stop".to_string(),
+        "MAX_TOKENS" => "length".to_string(),
+        "TOOL_CALL" => "tool_calls".to_string(),
+        other => other.to_ascii_lowercase(),
+    }
+}
+
+/// A citation as an annotation: a `url_citation` when its first source is a
+/// document with a URL, otherwise the citation as Cohere sent it
+fn annotation(citation: CohereCitation) -> serde_json::Value {
+    let document = citation
+        .sources
+        .iter()
+        .find_map(|source| source.get("document"))
+        .filter(|document| document.get("url").is_some_and(serde_json::Value::is_string));
+    match (document, citation.start, citation.end) {
+        (Some(document), Some(start), Some(end)) => serde_json::json!({
+            "type": "url_citation",
+            "url_citation": {
+                "start_index": start,
+                "end_index": end,
+                "url": document["url"],
+                "title": document.get("title").cloned().unwrap_or_default(),
+            },
+        }),
+        _ => serde_json::json!({ "type": "citation", "citation": citation }),
+    }
+}
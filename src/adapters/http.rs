@@ -0,0 +1,333 @@
+//! HTTP clients, URLs and timeouts shared by the adapters
+//!
+//! [`join_url`] puts a route under a base URL, which may carry a path
+//! prefix (`https://host/llm`) and a trailing slash or not. With the `uds`
+//! feature on Unix, a `unix:///var/run/ollama.sock` base URL sends requests
+//! through that socket.
+//!
+//! An endpoint's three timeouts each bound one phase of a request, so a dead
+//! host fails fast without cutting off long generations:
+//! `connect_timeout_ms` the connection, `first_byte_timeout_ms` the wait for
+//! the response headers, and `idle_stream_timeout_ms` each gap in a streamed
+//! body (see [`crate::adapters::body::next_chunk`]). Each failure has an
+//! error code of its own.
+//!
+//! Endpoints with the same connection settings (connect timeout, socket,
+//! proxy, TLS and pool options) share one client and its connection pool.
+//! `http2_prior_knowledge`, `pool_max_idle_per_host`, `pool_idle_timeout_ms`
+//! and `tcp_keepalive_ms` tune that client, e.g. for a gateway that prefers
+//! a few multiplexed HTTP/2 connections to many HTTP/1.1 ones. Without
+//! a `proxy_url`, clients use the `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY`
+//! environment variables.
+//!
+//! Adapters attach JSON request bodies with [`json_body`] and parse
+//! non-streamed responses with [`response_json`], which run the endpoint's
+//! [`PayloadTransformer`](crate::types::PayloadTransformer) over them.
+//! Endpoints with `compress_requests` get bodies of [`COMPRESS_MIN_BYTES`]
+//! or more gzipped, which pays off for long prompts such as RAG contexts.
+
+use crate::adapter::AdapterError;
+use crate::types::ProviderEndpoint;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Code of a request whose connection couldn't be made in time
+pub const CONNECT_TIMEOUT: &str = "connect_timeout";
+
+/// Code of a request whose response headers didn't arrive in time
+pub const FIRST_BYTE_TIMEOUT: &str = "first_byte_timeout";
+
+/// Code of a streamed response that went quiet for too long
+pub const STREAM_STALLED: &str = "stream_stalled";
+
+/// `proxy_url` value that connects directly, ignoring proxy environment variables
+pub const NO_PROXY: &str = "none";
+
+/// Smallest request body gzipped for endpoints with `compress_requests`;
+/// below it the compression costs more than it saves
+pub const COMPRESS_MIN_BYTES: usize = 1024;
+
+/// The settings a client is built from
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ClientKey {
+    connect_timeout_ms: Option<u64>,
+    socket: Option<String>,
+    proxy_url: Option<String>,
+    ca_cert_path: Option<String>,
+    danger_accept_invalid_certs: bool,
+    http2_prior_knowledge: bool,
+    pool_max_idle_per_host: Option<usize>,
+    pool_idle_timeout_ms: Option<u64>,
+    tcp_keepalive_ms: Option<u64>,
+}
+
+/// The client for `endpoint`'s connection settings, built on first use.
+/// Fails when the proxy URL is invalid or the CA file can't be read.
+pub fn client(endpoint: &ProviderEndpoint) -> Result<reqwest::Client, AdapterError> {
+    static CLIENTS: OnceLock<Mutex<HashMap<ClientKey, reqwest::Client>>> = OnceLock::new();
+    let key = ClientKey {
+        connect_timeout_ms: endpoint.connect_timeout_ms,
+        socket: unix_socket(&endpoint.base_url).map(str::to_string),
+        proxy_url: endpoint.proxy_url.clone(),
+        ca_cert_path: endpoint.ca_cert_path.clone(),
+        danger_accept_invalid_certs: endpoint.danger_accept_invalid_certs,
+        http2_prior_knowledge: endpoint.http2_prior_knowledge,
+        pool_max_idle_per_host: endpoint.pool_max_idle_per_host,
+        pool_idle_timeout_ms: endpoint.pool_idle_timeout_ms,
+        tcp_keepalive_ms: endpoint.tcp_keepalive_ms,
+    };
+    let clients = CLIENTS.get_or_init(Mutex::default);
+    if let Some(client) = clients.lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+        return Ok(client.clone());
+    }
+    let client = build_client(&key)?;
+    clients
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(key, client.clone());
+    Ok(client)
+}
+
+fn build_client(key: &ClientKey) -> Result<reqwest::Client, AdapterError> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(timeout) = key.connect_timeout_ms {
+        builder = builder.connect_timeout(std::time::Duration::from_millis(timeout));
+    }
+    #[cfg(all(unix, feature = "uds"))]
+    if let Some(socket) = &key.socket {
+        builder = builder.unix_socket(socket.as_str());
+    }
+    match key.proxy_url.as_deref() {
+        None => {}
+        Some(NO_PROXY) => builder = builder.no_proxy(),
+        Some(url) => {
+            let proxy = reqwest::Proxy::all(url)
+                .map_err(|e| AdapterError::invalid(format!("invalid proxy_url: {}", e)))?
+                .no_proxy(reqwest::NoProxy::from_env());
+            builder = builder.proxy(proxy);
+        }
+    }
+    if let Some(path) = &key.ca_cert_path {
+        for certificate in load_ca_certs(path).map_err(AdapterError::invalid)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    if key.danger_accept_invalid_certs {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    if key.http2_prior_knowledge {
+        builder = builder.http2_prior_knowledge();
+    }
+    if let Some(max) = key.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max);
+    }
+    if let Some(timeout) = key.pool_idle_timeout_ms {
+        builder = builder.pool_idle_timeout(std::time::Duration::from_millis(timeout));
+    }
+    if let Some(interval) = key.tcp_keepalive_ms {
+        builder = builder.tcp_keepalive(std::time::Duration::from_millis(interval));
+    }
+    builder
+        .build()
+        .map_err(|e| AdapterError::invalid(format!("failed to build the HTTP client: {}", e)))
+}
+
+/// The PEM certificates in the file at `path`, trusted besides the system roots
+pub fn load_ca_certs(path: &str) -> Result<Vec<reqwest::Certificate>, String> {
+    let pem = std::fs::read(path).map_err(|e| format!("can't read CA file {}: {}", path, e))?;
+    let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+        .map_err(|e| format!("CA file {} is not valid PEM: {}", path, e))?;
+    if certificates.is_empty() {
+        return Err(format!("CA file {} holds no certificates", path));
+    }
+    Ok(certificates)
+}
+
+/// The socket path of a `unix://` base URL, when Unix sockets are supported
+pub fn unix_socket(base_url: &str) -> Option<&str> {
+    if !cfg!(all(unix, feature = "uds")) {
+        return None;
+    }
+    base_url.strip_prefix("unix://").filter(|path| path.starts_with('/'))
+}
+
+/// `route` (a path, optionally with a query) under `base_url`, with one `/`
+/// between them however many either has. A `unix://` base stands for
+/// `http://localhost` on its socket.
+pub fn join_url(base_url: &str, route: &str) -> String {
+    let base = if unix_socket(base_url).is_some() { "http://localhost" } else { base_url };
+    let (path, query) = match route.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (route, None),
+    };
+    match reqwest::Url::parse(base) {
+        Ok(mut url) if !url.cannot_be_a_base() => {
+            let joined = format!("{}/{}", url.path().trim_end_matches('/'), path.trim_start_matches('/'));
+            url.set_path(&joined);
+            url.set_query(query);
+            url.into()
+        }
+        // Left for reqwest to reject with its own error
+        _ => format!("{}/{}", base.trim_end_matches('/'), route.trim_start_matches('/')),
+    }
+}
+
+/// `route` under the endpoint's base URL, or its kind's default base URL
+/// when it has none
+pub fn endpoint_url(endpoint: &ProviderEndpoint, route: &str) -> String {
+    let base = match endpoint.base_url.as_str() {
+        "" => endpoint.kind.default_base_url().unwrap_or_default(),
+        base => base,
+    };
+    join_url(base, route)
+}
+
+/// Send `request`, waiting at most `endpoint`'s first-byte timeout for the
+/// response headers. Other failures are reported as `"{context}: {error}"`.
+pub async fn send(
+    request: reqwest::RequestBuilder,
+    endpoint: &ProviderEndpoint,
+    context: &str,
+) -> Result<reqwest::Response, AdapterError> {
+    let response = match endpoint.first_byte_timeout() {
+        Some(timeout) => tokio::time::timeout(timeout, request.send()).await.map_err(|_| {
+            AdapterError::provider(
+                FIRST_BYTE_TIMEOUT.to_string(),
+                format!("{}: no response within {} ms", context, timeout.as_millis()),
+            )
+        })?,
+        None => request.send().await,
+    };
+    if let Ok(response) = &response {
+        crate::trace::record_response(response);
+    }
+    response.map_err(|e| {
+        if e.is_connect() && e.is_timeout() {
+            AdapterError::provider(CONNECT_TIMEOUT.to_string(), format!("{}: {}", context, e))
+        } else {
+            AdapterError::Http(format!("{}: {}", context, e))
+        }
+    })
+}
+
+/// `request` with `payload` as its JSON body, changed by the endpoint's
+/// payload transformer first when it has one and gzipped when the endpoint
+/// compresses requests
+pub fn json_body<T: serde::Serialize + ?Sized>(
+    request: reqwest::RequestBuilder,
+    payload: &T,
+    endpoint: &ProviderEndpoint,
+    model: &str,
+) -> reqwest::RequestBuilder {
+    let Some(transformer) = &endpoint.payload_transformer else {
+        return compressed_json(request, payload, endpoint);
+    };
+    match serde_json::to_value(payload) {
+        Ok(mut body) => {
+            transformer.transform_request(&endpoint.kind, model, &mut body);
+            compressed_json(request, &body, endpoint)
+        }
+        // Left for reqwest to report
+        Err(_) => request.json(payload),
+    }
+}
+
+fn compressed_json<T: serde::Serialize + ?Sized>(
+    request: reqwest::RequestBuilder,
+    payload: &T,
+    endpoint: &ProviderEndpoint,
+) -> reqwest::RequestBuilder {
+    if !endpoint.compress_requests {
+        return request.json(payload);
+    }
+    let body = match serde_json::to_vec(payload) {
+        Ok(body) if body.len() >= COMPRESS_MIN_BYTES => body,
+        _ => return request.json(payload),
+    };
+    let request = request.header(reqwest::header::CONTENT_TYPE, "application/json");
+    match gzip(&body) {
+        Ok(compressed) => request
+            .header(reqwest::header::CONTENT_ENCODING, "gzip")
+            .body(compressed),
+        Err(_) => request.body(body),
+    }
+}
+
+fn gzip(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::with_capacity(bytes.len() / 4), flate2::Compression::fast());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+/// Parse a non-streamed JSON response body, changed by the endpoint's
+/// payload transformer first when it has one
+pub fn response_body<T: serde::de::DeserializeOwned>(
+    bytes: &[u8],
+    endpoint: &ProviderEndpoint,
+    model: &str,
+) -> Result<T, serde_json::Error> {
+    let Some(transformer) = &endpoint.payload_transformer else {
+        return serde_json::from_slice(bytes);
+    };
+    let mut body: serde_json::Value = serde_json::from_slice(bytes)?;
+    transformer.transform_response(&endpoint.kind, model, &mut body);
+    serde_json::from_value(body)
+}
+
+/// Read and parse a non-streamed JSON response (see [`response_body`]).
+/// A body that doesn't parse but holds an error object is reported as that
+/// error (see [`embedded_error`]); other failures as `"{context}: {error}"`.
+pub async fn response_json<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+    endpoint: &ProviderEndpoint,
+    model: &str,
+    context: &str,
+) -> Result<T, AdapterError> {
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| AdapterError::Http(format!("{}: {}", context, e)))?;
+    response_body(&bytes, endpoint, model).map_err(|e| {
+        serde_json::from_slice::<serde_json::Value>(&bytes)
+            .ok()
+            .and_then(|body| embedded_error(&body))
+            .unwrap_or_else(|| AdapterError::Http(format!("{}: {}", context, e)))
+    })
+}
+
+/// The error in a body sent with a success status, as some OpenAI-compatible
+/// servers (LiteLLM, vLLM) do: `{"error": {"code": .., "message": ..}}`,
+/// `{"error": "message"}`, vLLM's `{"object": "error", "message": ..}` or the
+/// Responses API's `{"type": "error", "code": .., "message": ..}` event.
+/// The code may be a string or a number; without one the error's `type` is
+/// used. Objects of another kind, like a failed Responses API response,
+/// aren't errors here.
+pub fn embedded_error(body: &serde_json::Value) -> Option<AdapterError> {
+    let object = body.get("object").and_then(serde_json::Value::as_str);
+    let error = match (object, body.get("error")) {
+        (Some("error"), _) => body,
+        (Some(_), _) => return None,
+        (None, Some(serde_json::Value::String(message))) => {
+            return Some(AdapterError::provider("provider_error", message.as_str()));
+        }
+        (None, Some(error)) if error.is_object() => error,
+        (None, _) if body.get("type").and_then(serde_json::Value::as_str) == Some("error") => body,
+        _ => return None,
+    };
+    let message = error
+        .get("message")
+        .and_then(serde_json::Value::as_str)
+        .unwrap_or("Unknown error")
+        .to_string();
+    let code = match error.get("code") {
+        Some(serde_json::Value::String(code)) if !code.is_empty() => code.clone(),
+        Some(serde_json::Value::Number(code)) => code.to_string(),
+        _ => error
+            .get("type")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("provider_error")
+            .to_string(),
+    };
+    Some(AdapterError::Provider { code, message })
+}
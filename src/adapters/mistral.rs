@@ -0,0 +1,558 @@
+use crate::{
+    adapter::{AdapterError, ChatAdapter},
+    adapters::{body, http, sse},
+    stream::*,
+    types::*,
+};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+
+use std::collections::{BTreeMap, HashMap};
+use tokio_util::sync::CancellationToken;
+use crate::mistral::{
+    MistralChatRequest, MistralChatResponse, MistralContentChunk, MistralErrorResponse, MistralFunction,
+    MistralFunctionCall, MistralJsonSchema, MistralMessage, MistralModelsResponse, MistralNamedFunction,
+    MistralResponseFormat, MistralTool, MistralToolCall, MistralToolChoice,
+};
+
+/// Adapter for Mistral La Plateforme's chat completions API
+#[derive(Default)]
+pub struct MistralAdapter;
+
+#[async_trait]
+impl ChatAdapter for MistralAdapter {
+    fn provider_kind(&self) -> ProviderKind {
+        ProviderKind::Mistral
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    fn supports_vision(&self) -> bool {
+        true
+    }
+
+    async fn discover_models(
+        &self,
+        endpoint: &ProviderEndpoint,
+    ) -> Result<Vec<DiscoveredModel>, AdapterError> {
+        let client = http::client(endpoint)?;
+        let url = Self::endpoint_url(endpoint, "/models");
+
+        let mut request = client.get(&url);
+
+        for (key, value) in Self::request_headers(endpoint).await? {
+            request = request.header(key, value);
+        }
+
+        let resp = http::send(request, endpoint, "Failed to fetch models").await?;
+
+        if !resp.status().is_success() {
+            return Err(Self::error_response(resp).await);
+        }
+
+        let models_response: MistralModelsResponse = resp
+            .json()
+            .await
+            .map_err(|e| AdapterError::Http(format!("Failed to parse models response: {}", e)))?;
+
+        let discovered_models: Vec<DiscoveredModel> = models_response
+            .data
+            .into_iter()
+            // Embedding, moderation and OCR models can't chat
+            .filter(|model| model.capabilities.completion_chat)
+            .map(|model| {
+                let mut modalities = vec![Modality::Text];
+                if model.capabilities.vision {
+                    modalities.push(Modality::Vision);
+                }
+                DiscoveredModel {
+                    id: format!("mistral/{}", model.id),
+                    name: model.id,
+                    provider_name: "mistral".to_string(),
+                    provider_kind: ProviderKind::Mistral,
+                    modalities,
+                    capabilities: ModelCapabilities {
+                        supports_tools: model.capabilities.function_calling,
+                        supports_vision: model.capabilities.vision,
+                        context_length: model.max_context_length,
+                        ..ModelCapabilities::text_only().with_json()
+                    },
+                    created: model.created,
+                }
+            })
+            .collect();
+
+        Ok(discovered_models)
+    }
+
+    async fn execute_chat(
+        &self,
+        ir: ChatRequestIR,
+        cancel: CancellationToken,
+    ) -> Result<Box<dyn futures_util::Stream<Item = StreamEvent> + Send + Unpin>, AdapterError>
+    {
+        let payload = Self::build_request_body(&ir)?;
+
+        let client = http::client(&ir.model.provider)?;
+        let url = Self::endpoint_url(&ir.model.provider, "/chat/completions");
+
+        let mut request = http::json_body(client.post(&url), &payload, &ir.model.provider, &ir.model.model_id);
+
+        for (key, value) in Self::request_headers(&ir.model.provider).await? {
+            request = request.header(key, value);
+        }
+
+        let mut resp = http::send(request, &ir.model.provider, "Failed to send request").await?;
+        let idle_timeout = ir.model.provider.idle_stream_timeout();
+
+        if !resp.status().is_success() {
+            return Err(Self::error_response(resp).await);
+        }
+
+        if !ir.stream {
+            let response: MistralChatResponse =
+                http::response_json(resp, &ir.model.provider, &ir.model.model_id, "Failed to parse response").await?;
+            return Ok(Box::new(futures_util::stream::iter(Self::response_events(response))));
+        }
+
+        let s = async_stream::try_stream! {
+            // Calls by tool call index, and the reply so far for the final
+            // message: text and (id, name, arguments)
+            let mut open_calls: HashMap<u32, String> = HashMap::new();
+            let mut content = String::new();
+            let mut calls: Vec<(String, String, String)> = Vec::new();
+            let mut reason = None;
+            let mut lines = body::LineBuffer::new();
+
+            'read: loop {
+                let chunk = match body::next_chunk(&mut resp, &cancel, idle_timeout).await? {
+                    body::BodyRead::Chunk(chunk) => chunk,
+                    body::BodyRead::End => break,
+                    body::BodyRead::Cancelled => {
+                        drop(resp);
+                        yield body::cancelled_event();
+                        return;
+                    }
+                };
+
+                for line in lines.push(&chunk) {
+                    let json_str = match sse::parse_line(&line) {
+                        sse::SseLine::Data(data) => data,
+                        sse::SseLine::Comment(comment) => {
+                            if let Some(status) = sse::comment_status(comment) {
+                                yield status;
+                            }
+                            continue;
+                        }
+                        sse::SseLine::Other => continue,
+                    };
+
+                    if json_str == "[DONE]" {
+                        break 'read;
+                    }
+
+                    let Ok(response) = serde_json::from_str::<MistralChatResponse>(json_str) else {
+                        continue;
+                    };
+
+                    if let Some(choice) = response.choices.into_iter().next() {
+                        let delta = choice.delta.unwrap_or_default();
+                        if let Some(text) = delta.content.map(|content| content.text()).filter(|text| !text.is_empty()) {
+                            content.push_str(&text);
+                            yield StreamEvent::TextDelta { content: text };
+                        }
+
+                        for (position, call) in delta.tool_calls.unwrap_or_default().into_iter().enumerate() {
+                            let index = call.index.unwrap_or(position as u32);
+                            let arguments = arguments_string(&call.function.arguments);
+                            let id = match open_calls.get(&index) {
+                                Some(id) => id.clone(),
+                                None => {
+                                    let id = if call.id.is_empty() { format!("call_{}", index) } else { call.id };
+                                    open_calls.insert(index, id.clone());
+                                    calls.push((id.clone(), call.function.name.clone(), String::new()));
+                                    yield StreamEvent::ToolCallStart {
+                                        id: id.clone(),
+                                        name: call.function.name,
+                                        args_json: serde_json::Value::Object(serde_json::Map::new()),
+                                    };
+                                    id
+                                }
+                            };
+                            if !arguments.is_empty() {
+                                if let Some((_, _, raw)) = calls.iter_mut().find(|(call, _, _)| *call == id) {
+                                    raw.push_str(&arguments);
+                                }
+                                yield StreamEvent::ToolCallDelta {
+                                    id,
+                                    args_delta_json: serde_json::Value::String(arguments),
+                                };
+                            }
+                        }
+
+                        if choice.finish_reason.is_some() {
+                            let mut ended: Vec<_> = open_calls.drain().collect();
+                            ended.sort();
+                            for (_, id) in ended {
+                                yield StreamEvent::ToolCallEnd { id };
+                            }
+                            reason = choice.finish_reason;
+                        }
+                    }
+
+                    if let Some(usage) = response.usage {
+                        yield StreamEvent::Tokens {
+                            input: usage.prompt_tokens,
+                            output: usage.completion_tokens,
+                        };
+                    }
+                }
+            }
+
+            // Streams cut off before a finish_reason still close their calls
+            let mut ended: Vec<_> = open_calls.drain().collect();
+            ended.sort();
+            for (_, id) in ended {
+                yield StreamEvent::ToolCallEnd { id };
+            }
+
+            if reason.as_deref() == Some("error") {
+                yield StreamEvent::Error {
+                    code: "provider_error".to_string(),
+                    message: "Mistral stopped with an error".to_string(),
+                };
+                return;
+            }
+            if let Some(reason) = reason {
+                yield crate::stream::final_message(content, calls, Some(finish_reason(&reason)));
+            }
+            yield StreamEvent::Done;
+        };
+
+        Ok(Box::new(Box::pin(s.map(
+            |r: Result<StreamEvent, AdapterError>| match r {
+                Ok(ev) => ev,
+                Err(e) => body::stream_error_event(e),
+            },
+        ))))
+    }
+}
+
+impl MistralAdapter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// API URL for `route`, defaulting to Mistral's public API when the
+    /// endpoint leaves `base_url` empty
+    pub fn endpoint_url(endpoint: &ProviderEndpoint, route: &str) -> String {
+        let base = if endpoint.base_url.is_empty() {
+            ProviderKind::Mistral.default_base_url().unwrap_or_default()
+        } else {
+            endpoint.base_url.as_str()
+        };
+        http::join_url(base, &format!("/v1{}", route))
+    }
+
+    /// Bearer authorization, with the endpoint's own headers applied over it
+    async fn request_headers(endpoint: &ProviderEndpoint) -> Result<BTreeMap<String, String>, AdapterError> {
+        let mut headers = BTreeMap::new();
+        if let Some(token) = endpoint.bearer_token().await? {
+            headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+        }
+
+        for (key, value) in endpoint.headers().await? {
+            headers.retain(|existing, _| !existing.eq_ignore_ascii_case(&key));
+            headers.insert(key, value);
+        }
+        Ok(headers)
+    }
+
+    async fn error_response(resp: reqwest::Response) -> AdapterError {
+        let status = resp.status();
+        let text = resp
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+
+        match serde_json::from_str::<MistralErrorResponse>(&text) {
+            Ok(error_response) => AdapterError::Provider {
+                code: error_response
+                    .r#type
+                    .unwrap_or_else(|| status.as_u16().to_string()),
+                message: match error_response.message {
+                    serde_json::Value::String(message) => message,
+                    other => other.to_string(),
+                },
+            },
+            Err(_) => AdapterError::Provider {
+                code: status.as_u16().to_string(),
+                message: text,
+            },
+        }
+    }
+
+    /// The events of a complete (non-streaming) response
+    fn response_events(response: MistralChatResponse) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        let mut content = String::new();
+        let mut calls = Vec::new();
+        let mut reason = None;
+        if let Some(choice) = response.choices.into_iter().next() {
+            let message = choice.message.unwrap_or_default();
+            if let Some(text) = message.content.map(|content| content.text()).filter(|text| !text.is_empty()) {
+                content.push_str(&text);
+                events.push(StreamEvent::TextDelta { content: text });
+            }
+            for call in message.tool_calls.unwrap_or_default() {
+                let arguments = arguments_string(&call.function.arguments);
+                events.push(StreamEvent::ToolCallStart {
+                    id: call.id.clone(),
+                    name: call.function.name.clone(),
+                    args_json: serde_json::Value::Object(serde_json::Map::new()),
+                });
+                events.push(StreamEvent::ToolCallDelta {
+                    id: call.id.clone(),
+                    args_delta_json: serde_json::Value::String(arguments.clone()),
+                });
+                events.push(StreamEvent::ToolCallEnd { id: call.id.clone() });
+                calls.push((call.id, call.function.name, arguments));
+            }
+            reason = choice.finish_reason;
+        }
+        if let Some(usage) = response.usage {
+            events.push(StreamEvent::Tokens {
+                input: usage.prompt_tokens,
+                output: usage.completion_tokens,
+            });
+        }
+        if reason.as_deref() == Some("error") {
+            events.push(StreamEvent::Error {
+                code: "provider_error".to_string(),
+                message: "Mistral stopped with an error".to_string(),
+            });
+            return events;
+        }
+        events.push(crate::stream::final_message(content, calls, reason.as_deref().map(finish_reason)));
+        events.push(StreamEvent::Done);
+        events
+    }
+
+    /// Build the outbound JSON body, with the endpoint's static extensions and
+    /// then the request's own extensions merged on top
+    pub fn build_request_body(ir: &ChatRequestIR) -> Result<serde_json::Value, AdapterError> {
+        let payload = Self::build_chat_request(ir);
+        let mut body = serde_json::to_value(&payload)
+            .map_err(|e| AdapterError::internal(format!("Failed to serialize request: {}", e)))?;
+
+        if let serde_json::Value::Object(map) = &mut body {
+            for (key, value) in ir
+                .model
+                .provider
+                .extensions
+                .iter()
+                .chain(ir.provider_extensions.iter())
+            {
+                map.insert(key.clone(), value.clone());
+            }
+        }
+
+        Ok(body)
+    }
+
+    fn build_chat_request(ir: &ChatRequestIR) -> MistralChatRequest {
+        let mut messages = Vec::new();
+        // Tool names by call id, for the `name` of tool messages
+        let mut call_names: HashMap<&str, &str> = HashMap::new();
+
+        for msg in &ir.messages {
+            let text = || -> String {
+                msg.parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        ContentPart::Text(text) => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect()
+            };
+            match msg.role {
+                Role::System | Role::Developer => messages.push(MistralMessage::System { content: text() }),
+                Role::Assistant => {
+                    let tool_calls = msg
+                        .parts
+                        .iter()
+                        .filter_map(|part| match part {
+                            ContentPart::ToolCall { id, name, arguments } => {
+                                call_names.insert(id.as_str(), name.as_str());
+                                Some(MistralToolCall {
+                                    id: tool_call_id(id),
+                                    r#type: "function".to_string(),
+                                    function: MistralFunctionCall {
+                                        name: name.clone(),
+                                        arguments: serde_json::Value::String(ContentPart::arguments_string(arguments)),
+                                    },
+                                    index: None,
+                                })
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    let content = text();
+                    messages.push(MistralMessage::Assistant {
+                        content: (!content.is_empty()).then_some(content),
+                        tool_calls,
+                    });
+                }
+                Role::User | Role::Tool => {
+                    let mut content = Vec::new();
+                    for part in &msg.parts {
+                        match part {
+                            ContentPart::Text(text) => content.push(MistralContentChunk::Text { text: text.clone() }),
+                            ContentPart::ImageUrl { url, .. } => {
+                                content.push(MistralContentChunk::ImageUrl { image_url: url.clone() })
+                            }
+                            // Each result is a message of its own
+                            ContentPart::ToolResult { call_id, content } => messages.push(MistralMessage::Tool {
+                                tool_call_id: tool_call_id(call_id),
+                                name: call_names.get(call_id.as_str()).map(|name| name.to_string()),
+                                content: content.clone(),
+                            }),
+                            ContentPart::ToolCall { .. } => {}
+                            ContentPart::BlobRef { .. } => tracing::warn!("BlobRef not supported by Mistral adapter"),
+                            ContentPart::Audio { .. } => tracing::warn!("Audio not supported by Mistral adapter"),
+                            ContentPart::File { .. } => tracing::warn!("File content not supported by Mistral adapter"),
+                        }
+                    }
+                    if !content.is_empty() {
+                        messages.push(MistralMessage::User { content });
+                    }
+                }
+            }
+        }
+
+        let allowed: Option<Vec<&str>> = match &ir.tool_choice {
+            ToolChoice::Allowed { tools, .. } => Some(tools.iter().map(String::as_str).collect()),
+            _ => None,
+        };
+        let tools: Vec<MistralTool> = ir
+            .tools
+            .iter()
+            .filter_map(|tool| match tool {
+                ToolSpec::JsonSchema { name, description, schema, strict } => {
+                    if allowed.as_ref().is_some_and(|allowed| !allowed.contains(&name.as_str())) {
+                        return None;
+                    }
+                    Some(MistralTool {
+                        r#type: "function".to_string(),
+                        function: MistralFunction {
+                            name: name.clone(),
+                            description: description.clone(),
+                            parameters: schema.clone(),
+                            strict: *strict,
+                        },
+                    })
+                }
+            })
+            .collect();
+
+        let tool_choice = if tools.is_empty() {
+            None
+        } else {
+            Some(match &ir.tool_choice {
+                ToolChoice::Auto => MistralToolChoice::Mode("auto".to_string()),
+                ToolChoice::None => MistralToolChoice::Mode("none".to_string()),
+                ToolChoice::Required => MistralToolChoice::Mode("any".to_string()),
+                ToolChoice::Named(name) => MistralToolChoice::Function {
+                    r#type: "function".to_string(),
+                    function: MistralNamedFunction { name: name.clone() },
+                },
+                ToolChoice::Allowed { mode, .. } if mode == "required" => MistralToolChoice::Mode("any".to_string()),
+                ToolChoice::Allowed { .. } => MistralToolChoice::Mode("auto".to_string()),
+            })
+        };
+
+        let response_format = match &ir.response_format {
+            None | Some(ResponseFormat::Text) => None,
+            Some(ResponseFormat::JsonObject) => Some(MistralResponseFormat {
+                r#type: "json_object".to_string(),
+                json_schema: None,
+            }),
+            Some(ResponseFormat::JsonSchema { name, description, schema, strict }) => Some(MistralResponseFormat {
+                r#type: "json_schema".to_string(),
+                json_schema: Some(MistralJsonSchema {
+                    name: name.clone(),
+                    description: description.clone(),
+                    schema: schema.clone(),
+                    strict: *strict,
+                }),
+            }),
+        };
+
+        // `safe_prompt` may be set on the endpoint or per request
+        let safe_prompt = ir
+            .provider_extensions
+            .get("safe_prompt")
+            .or_else(|| ir.model.provider.extensions.get("safe_prompt"))
+            .and_then(serde_json::Value::as_bool);
+
+        // logit_bias, logprobs and top_k have no Mistral counterpart and are dropped
+        MistralChatRequest {
+            model: ir.model.model_id.clone(),
+            messages,
+            parallel_tool_calls: ir.sampling.parallel_tool_calls.filter(|_| !tools.is_empty()),
+            tools,
+            tool_choice,
+            response_format,
+            temperature: ir.sampling.temperature,
+            top_p: ir.sampling.top_p,
+            max_tokens: ir.sampling.max_tokens,
+            stop: ir.sampling.stop.clone(),
+            random_seed: ir.sampling.seed,
+            presence_penalty: ir.sampling.presence_penalty,
+            frequency_penalty: ir.sampling.frequency_penalty,
+            safe_prompt,
+            stream: ir.stream,
+        }
+    }
+}
+
+/// `id` as a Mistral tool call id: nine characters from `[a-zA-Z0-9]`.
+/// Ids from other providers are hashed into that form, the same way for the
+/// call and its result.
+pub fn tool_call_id(id: &str) -> String {
+    const ALPHABET: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    if id.len() == 9 && id.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+        return id.to_string();
+    }
+    // FNV-1a, stable across runs and platforms
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in id.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (0..9)
+        .map(|_| {
+            let c = ALPHABET[(hash % 62) as usize] as char;
+            hash /= 62;
+            c
+        })
+        .collect()
+}
+
+/// Arguments as the raw JSON string, whether sent as a string or an object
+fn arguments_string(arguments: &serde_json::Value) -> String {
+    match arguments {
+        serde_json::Value::Null => String::new(),
+        other => ContentPart::arguments_string(other),
+    }
+}
+
+/// Mistral's finish reason in OpenAI's terms
+fn finish_reason(reason: &str) -> String {
+    match reason {
+        "model_length" => "length".to_string(),
+        other => other.to_string(),
+    }
+}
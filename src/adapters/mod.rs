@@ -1,7 +1,18 @@
+pub mod anthropic;
+pub mod body;
+pub mod cohere;
+pub mod http;
+pub mod mistral;
 pub mod ollama;
 pub mod openai_compat;
 pub mod openai_responses;
+pub mod sse;
+pub mod tgi;
 
+pub use anthropic::AnthropicAdapter;
+pub use cohere::CohereAdapter;
+pub use mistral::MistralAdapter;
 pub use ollama::OllamaAdapter;
 pub use openai_compat::OpenAIAdapter;
-pub use openai_responses::OpenAIResponsesAdapter;
\ No newline at end of file
+pub use openai_responses::OpenAIResponsesAdapter;
+pub use tgi::TgiAdapter;
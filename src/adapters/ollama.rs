@@ -1,5 +1,6 @@
 use crate::{
     adapter::{AdapterError, ChatAdapter},
+    adapters::{body, http},
     stream::*,
     types::*,
 };
@@ -26,23 +27,16 @@ impl ChatAdapter for OllamaAdapter {
         &self,
         endpoint: &ProviderEndpoint,
     ) -> Result<Vec<DiscoveredModel>, AdapterError> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/api/tags", endpoint.base_url);
+        let client = http::client(endpoint)?;
+        let url = http::endpoint_url(endpoint, "/api/tags");
 
         let mut request = client.get(&url);
 
-        if let Some(timeout) = endpoint.timeout {
-            request = request.timeout(std::time::Duration::from_millis(timeout));
-        }
-
-        for (key, value) in &endpoint.extra_headers {
+        for (key, value) in endpoint.headers().await? {
             request = request.header(key, value);
         }
 
-        let resp = request
-            .send()
-            .await
-            .map_err(|e| AdapterError::Http(format!("Failed to fetch models: {}", e)))?;
+        let resp = http::send(request, endpoint, "Failed to fetch models").await?;
 
         if !resp.status().is_success() {
             let status = resp.status();
@@ -77,15 +71,8 @@ impl ChatAdapter for OllamaAdapter {
                     provider_name: "ollama".to_string(),
                     provider_kind: ProviderKind::Ollama,
                     modalities: vec![Modality::Text],
-                    capabilities: ModelCapabilities {
-                        supports_streaming: true,
-                        supports_tools: false,
-                        supports_vision: false,
-                        supports_json: true,
-                        supports_audio: false,
-                        max_tokens: None,
-                        context_length: None,
-                    },
+                    capabilities: ModelCapabilities::text_only().with_json(),
+                    created: crate::types::parse_rfc3339(&model.modified_at),
                 }
             })
             .collect();
@@ -101,23 +88,20 @@ impl ChatAdapter for OllamaAdapter {
     {
         let payload = Self::build_ollama_request(&ir)?;
 
-        let client = reqwest::Client::new();
-        let url = format!("{}/api/chat", ir.model.provider.base_url);
+        let client = http::client(&ir.model.provider)?;
+        let url = http::endpoint_url(&ir.model.provider, "/api/chat");
 
-        let mut request = client.post(&url).json(&payload);
+        let mut request = http::json_body(client.post(&url), &payload, &ir.model.provider, &ir.model.model_id);
 
-        if let Some(timeout) = ir.model.provider.timeout {
-            request = request.timeout(std::time::Duration::from_millis(timeout));
-        }
-
-        for (key, value) in &ir.model.provider.extra_headers {
+        for (key, value) in ir.model.provider.headers().await? {
             request = request.header(key, value);
         }
 
-        let mut resp = request
-            .send()
-            .await
-            .map_err(|e| AdapterError::Http(format!("Failed to send request: {}", e)))?;
+        let mut resp = http::send(request, &ir.model.provider, "Failed to send request").await?;
+        let idle_timeout = ir.model.provider.idle_stream_timeout();
+        // A non-streamed reply is a single line, which the payload transformer sees
+        let transform = (!ir.stream && ir.model.provider.payload_transformer.is_some())
+            .then(|| (ir.model.provider.clone(), ir.model.model_id.clone()));
 
         if !resp.status().is_success() {
             let status = resp.status();
@@ -132,54 +116,85 @@ impl ChatAdapter for OllamaAdapter {
         }
 
         let s = async_stream::try_stream! {
-            while let Some(chunk) = resp.chunk().await
-                .map_err(|e| AdapterError::Http(format!("Failed to read chunk: {}", e)))?
-            {
-                if cancel.is_cancelled() {
-                    yield StreamEvent::Error {
-                        code: "cancelled".to_string(),
-                        message: "Request was cancelled".to_string(),
-                    };
-                    break;
-                }
+            let mut lines = body::LineBuffer::new();
+            let mut ended = false;
+            // Malformed lines are skipped, but only the first one is logged
+            let mut parse_error_logged = false;
+            while !ended {
+                let complete = match body::next_chunk(&mut resp, &cancel, idle_timeout).await? {
+                    body::BodyRead::Chunk(chunk) => lines.push(&chunk),
+                    body::BodyRead::End => {
+                        ended = true;
+                        lines.finish().into_iter().collect()
+                    }
+                    body::BodyRead::Cancelled => {
+                        // Ollama stops generating when the connection closes
+                        drop(resp);
+                        yield body::cancelled_event();
+                        return;
+                    }
+                };
 
-                let chunk_str = String::from_utf8_lossy(&chunk);
-                for line in chunk_str.lines() {
-                    if line.is_empty() {
+                for line in complete {
+                    if line.trim().is_empty() {
                         continue;
                     }
 
-                    if let Ok(response) = serde_json::from_str::<OllamaResponse>(line) {
-                        if !response.response.is_empty() {
-                            yield StreamEvent::TextDelta {
-                                content: response.response,
-                            };
-                        }
-
-                        if let Some(input_tokens) = response.prompt_eval_count {
-                            if let Some(output_tokens) = response.eval_count {
-                                yield StreamEvent::Tokens {
-                                    input: input_tokens,
-                                    output: output_tokens,
-                                };
+                    let parsed = match &transform {
+                        Some((endpoint, model)) => http::response_body::<OllamaResponse>(line.as_bytes(), endpoint, model),
+                        None => serde_json::from_str::<OllamaResponse>(&line),
+                    };
+                    let response = match parsed {
+                        Ok(response) => response,
+                        Err(e) => {
+                            if !parse_error_logged {
+                                parse_error_logged = true;
+                                tracing::warn!(error = %e, line = %line, "Failed to parse Ollama stream line");
                             }
+                            continue;
                         }
+                    };
+
+                    if response.done_reason.as_deref() == Some("load") {
+                        yield StreamEvent::Status {
+                            state: "loading".to_string(),
+                            detail: Some(response.model.clone()),
+                        };
+                        continue;
+                    }
+
+                    let content = match response.message {
+                        Some(message) if !message.content.is_empty() => message.content,
+                        _ => response.response,
+                    };
+                    if !content.is_empty() {
+                        yield StreamEvent::TextDelta { content };
+                    }
 
-                        if response.done {
-                            yield StreamEvent::Done;
+                    if let Some(input_tokens) = response.prompt_eval_count {
+                        if let Some(output_tokens) = response.eval_count {
+                            yield StreamEvent::Tokens {
+                                input: input_tokens,
+                                output: output_tokens,
+                            };
                         }
                     }
+
+                    if response.done {
+                        yield StreamEvent::Done;
+                        return;
+                    }
                 }
             }
+
+            // The body ended without a final line
+            yield StreamEvent::Done;
         };
 
         Ok(Box::new(Box::pin(s.map(
             |r: Result<StreamEvent, AdapterError>| match r {
                 Ok(ev) => ev,
-                Err(e) => StreamEvent::Error {
-                    code: "stream_error".to_string(),
-                    message: e.to_string(),
-                },
+                Err(e) => body::stream_error_event(e),
             },
         ))))
     }
@@ -198,8 +213,10 @@ impl OllamaAdapter {
                     match part {
                         ContentPart::Text(text) => content.push_str(text),
                         ContentPart::ImageUrl { url, .. } => {
-                            if url.starts_with("data:") {
-                                images.get_or_insert_with(Vec::new).push(url.clone());
+                            // Ollama takes bare base64
+                            match url.strip_prefix("data:").and_then(|rest| rest.split_once(',')) {
+                                Some((_, data)) => images.get_or_insert_with(Vec::new).push(data.to_string()),
+                                None => tracing::warn!("Image URLs not supported by Ollama adapter; send a data URI"),
                             }
                         }
                         ContentPart::BlobRef { .. } => {
@@ -211,6 +228,10 @@ impl OllamaAdapter {
                         ContentPart::File { .. } => {
                             tracing::warn!("File content not supported by Ollama adapter");
                         }
+                        ContentPart::ToolCall { .. } => {
+                            tracing::warn!("Tool calls not supported by Ollama adapter");
+                        }
+                        ContentPart::ToolResult { content: output, .. } => content.push_str(output),
                     }
                 }
 
@@ -240,6 +261,13 @@ impl OllamaAdapter {
             } else {
                 Some(ir.sampling.stop.clone())
             },
+            min_p: ir.sampling.min_p,
+            typical_p: ir.sampling.typical_p,
+            repeat_penalty: ir.sampling.repeat_penalty,
+            repeat_last_n: ir.sampling.repeat_last_n,
+            mirostat: ir.sampling.mirostat,
+            mirostat_tau: ir.sampling.mirostat_tau,
+            mirostat_eta: ir.sampling.mirostat_eta,
         });
 
         Ok(OllamaChatRequest {
@@ -247,6 +275,11 @@ impl OllamaAdapter {
             messages,
             stream: ir.stream,
             options,
+            format: match &ir.response_format {
+                Some(ResponseFormat::JsonObject) => Some(serde_json::json!("json")),
+                Some(ResponseFormat::JsonSchema { schema, .. }) => Some(schema.clone()),
+                Some(ResponseFormat::Text) | None => None,
+            },
         })
     }
 }
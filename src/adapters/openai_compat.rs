@@ -1,12 +1,13 @@
 use crate::{
     adapter::{AdapterError, ChatAdapter},
+    adapters::{body, http, sse},
     stream::*,
     types::*,
 };
 use async_trait::async_trait;
 use futures_util::StreamExt;
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use tokio_util::sync::CancellationToken;
 
 pub struct OpenAIAdapter;
@@ -29,27 +30,20 @@ impl ChatAdapter for OpenAIAdapter {
         &self,
         endpoint: &ProviderEndpoint,
     ) -> Result<Vec<DiscoveredModel>, AdapterError> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/v1/models", endpoint.base_url);
+        let client = http::client(endpoint)?;
+        let url = Self::endpoint_url(endpoint, "/models");
 
         let mut request = client.get(&url);
 
-        if let Some(timeout) = endpoint.timeout {
-            request = request.timeout(std::time::Duration::from_millis(timeout));
+        if let Some(token) = endpoint.bearer_token().await? {
+            request = request.header("Authorization", format!("Bearer {}", token));
         }
 
-        if let Some(api_key) = &endpoint.api_key {
-            request = request.header("Authorization", format!("Bearer {}", api_key));
-        }
-
-        for (key, value) in &endpoint.extra_headers {
+        for (key, value) in endpoint.headers().await? {
             request = request.header(key, value);
         }
 
-        let resp = request
-            .send()
-            .await
-            .map_err(|e| AdapterError::Http(format!("Failed to fetch models: {}", e)))?;
+        let resp = http::send(request, endpoint, "Failed to fetch models").await?;
 
         if !resp.status().is_success() {
             let status = resp.status();
@@ -72,7 +66,11 @@ impl ChatAdapter for OpenAIAdapter {
             .data
             .into_iter()
             .map(|model| {
-                let capabilities = Self::infer_model_capabilities(&model.id);
+                let mut capabilities = Self::infer_model_capabilities(&model.id);
+                model.apply_advertised_capabilities(
+                    &mut capabilities.capabilities,
+                    &mut capabilities.modalities,
+                );
                 DiscoveredModel {
                     id: format!("openai-compat/{}", model.id),
                     name: model.id,
@@ -80,6 +78,8 @@ impl ChatAdapter for OpenAIAdapter {
                     provider_kind: ProviderKind::OpenAICompat,
                     modalities: capabilities.modalities,
                     capabilities: capabilities.capabilities,
+                    // Servers without the field send 0
+                    created: Some(model.created).filter(|created| *created > 0),
                 }
             })
             .collect();
@@ -93,29 +93,23 @@ impl ChatAdapter for OpenAIAdapter {
         cancel: CancellationToken,
     ) -> Result<Box<dyn futures_util::Stream<Item = StreamEvent> + Send + Unpin>, AdapterError>
     {
-        let payload = Self::build_openai_request(&ir)?;
+        let payload = Self::build_request_body(&ir)?;
 
-        let client = reqwest::Client::new();
-        let url = format!("{}/v1/chat/completions", ir.model.provider.base_url);
+        let client = http::client(&ir.model.provider)?;
+        let url = Self::endpoint_url(&ir.model.provider, "/chat/completions");
 
-        let mut request = client.post(&url).json(&payload);
-
-        if let Some(timeout) = ir.model.provider.timeout {
-            request = request.timeout(std::time::Duration::from_millis(timeout));
-        }
+        let mut request = http::json_body(client.post(&url), &payload, &ir.model.provider, &ir.model.model_id);
 
-        if let Some(api_key) = &ir.model.provider.api_key {
-            request = request.header("Authorization", format!("Bearer {}", api_key));
+        if let Some(token) = ir.model.provider.bearer_token().await? {
+            request = request.header("Authorization", format!("Bearer {}", token));
         }
 
-        for (key, value) in &ir.model.provider.extra_headers {
+        for (key, value) in ir.model.provider.headers().await? {
             request = request.header(key, value);
         }
 
-        let mut resp = request
-            .send()
-            .await
-            .map_err(|e| AdapterError::Http(format!("Failed to send request: {}", e)))?;
+        let mut resp = http::send(request, &ir.model.provider, "Failed to send request").await?;
+        let idle_timeout = ir.model.provider.idle_stream_timeout();
 
         if !resp.status().is_success() {
             let status = resp.status();
@@ -142,95 +136,125 @@ impl ChatAdapter for OpenAIAdapter {
 
         if ir.stream {
             let s = async_stream::try_stream! {
-                let mut tool_calls_buffer = HashMap::new();
-
-                while let Some(chunk) = resp.chunk().await
-                    .map_err(|e| AdapterError::Http(format!("Failed to read chunk: {}", e)))?
-                {
-                    if cancel.is_cancelled() {
-                        yield StreamEvent::Error {
-                            code: "cancelled".to_string(),
-                            message: "Request was cancelled".to_string(),
+                // Calls still receiving arguments, by (choice index, tool call index)
+                let mut tool_calls_buffer: BTreeMap<(u32, u32), OpenAIToolCall> = BTreeMap::new();
+                let mut lines = body::LineBuffer::new();
+
+                loop {
+                    let chunk = match body::next_chunk(&mut resp, &cancel, idle_timeout).await? {
+                        body::BodyRead::Chunk(chunk) => chunk,
+                        body::BodyRead::End => break,
+                        body::BodyRead::Cancelled => {
+                            drop(resp);
+                            yield body::cancelled_event();
+                            return;
+                        }
+                    };
+
+                    for line in lines.push(&chunk) {
+                        let json_str = match sse::parse_line(&line) {
+                            sse::SseLine::Data(data) => data,
+                            sse::SseLine::Comment(comment) => {
+                                if let Some(status) = sse::comment_status(comment) {
+                                    yield status;
+                                }
+                                continue;
+                            }
+                            sse::SseLine::Other => continue,
                         };
-                        break;
-                    }
 
-                    let chunk_str = String::from_utf8_lossy(&chunk);
-                    for line in chunk_str.lines() {
-                        let line = line.trim();
-                        if line.is_empty() {
-                            continue;
+                        if json_str == "[DONE]" {
+                            for tool_call in std::mem::take(&mut tool_calls_buffer).into_values() {
+                                yield StreamEvent::ToolCallEnd { id: tool_call.id };
+                            }
+                            yield StreamEvent::Done;
+                            return;
                         }
 
-                        if line == "data: [DONE]" {
-                            yield StreamEvent::Done;
+                        // Some servers report a failure as a data event of its own
+                        if let Some(error) = sse::error_event(json_str) {
+                            yield error;
                             return;
                         }
 
-                        if let Some(json_str) = line.strip_prefix("data: ") {
-                            if let Ok(response) = serde_json::from_str::<OpenAIChatResponse>(json_str) {
-                                if let Some(choice) = response.choices.first() {
-                                    if let Some(delta) = &choice.delta {
-                                        if let Some(content) = &delta.content {
-                                            yield StreamEvent::TextDelta {
-                                                content: content.clone(),
-                                            };
-                                        }
+                        if let Ok(mut response) = serde_json::from_str::<OpenAIChatResponse>(json_str) {
+                            if let Some(choice) = response.choices.first_mut() {
+                                if let Some(delta) = &mut choice.delta {
+                                    if let Some(content) = delta.content.take() {
+                                        yield StreamEvent::TextDelta { content };
+                                    }
 
-                                        if let Some(tool_calls) = &delta.tool_calls {
-                                            for tool_call_delta in tool_calls {
-                                                if let Some(id) = &tool_call_delta.id {
-                                                    let tool_call_id = id.clone();
-                                                    tool_calls_buffer.insert(tool_call_id.clone(), OpenAIToolCall {
-                                                        id: tool_call_id,
+                                    if let Some(refusal) = delta.refusal.as_ref().filter(|refusal| !refusal.is_empty()) {
+                                        yield StreamEvent::Refusal { text: refusal.clone() };
+                                    }
+
+                                    for annotation in &delta.annotations {
+                                        yield StreamEvent::Annotation { annotation: annotation.clone() };
+                                    }
+
+                                    if let Some(tool_calls) = &delta.tool_calls {
+                                        for tool_call_delta in tool_calls {
+                                            // Only the first chunk of a call carries its id and
+                                            // name; later ones are matched by index
+                                            let key = (choice.index, tool_call_delta.index);
+                                            let function = tool_call_delta.function.as_ref();
+                                            let tool_call = match tool_calls_buffer.entry(key) {
+                                                std::collections::btree_map::Entry::Occupied(entry) => entry.into_mut(),
+                                                std::collections::btree_map::Entry::Vacant(entry) => {
+                                                    let tool_call = entry.insert(OpenAIToolCall {
+                                                        id: tool_call_delta
+                                                            .id
+                                                            .clone()
+                                                            .unwrap_or_else(|| format!("call_{}", tool_call_delta.index)),
                                                         r#type: tool_call_delta.r#type.clone().unwrap_or_else(|| "function".to_string()),
                                                         function: OpenAIFunctionCall {
-                                                            name: tool_call_delta.function.as_ref().and_then(|f| f.name.clone()).unwrap_or_default(),
-                                                            arguments: tool_call_delta.function.as_ref().and_then(|f| f.arguments.clone()).unwrap_or_default(),
+                                                            name: function.and_then(|f| f.name.clone()).unwrap_or_default(),
+                                                            arguments: String::new(),
                                                         },
                                                     });
-
                                                     yield StreamEvent::ToolCallStart {
-                                                        id: tool_call_delta.id.clone().unwrap_or_default(),
-                                                        name: tool_call_delta.function.as_ref().and_then(|f| f.name.clone()).unwrap_or_default(),
+                                                        id: tool_call.id.clone(),
+                                                        name: tool_call.function.name.clone(),
                                                         args_json: serde_json::Value::Object(serde_json::Map::new()),
                                                     };
+                                                    tool_call
                                                 }
+                                            };
 
-                                                if let Some(tool_call_id) = &tool_call_delta.id {
-                                                    if let Some(function) = &tool_call_delta.function {
-                                                        if let Some(args_delta) = &function.arguments {
-                                                            if let Some(tool_call) = tool_calls_buffer.get_mut(tool_call_id) {
-                                                                tool_call.function.arguments.push_str(args_delta);
-
-                                                                yield StreamEvent::ToolCallDelta {
-                                                                    id: tool_call_id.clone(),
-                                                                    args_delta_json: serde_json::Value::String(args_delta.clone()),
-                                                                };
-                                                            }
-                                                        }
-                                                    }
+                                            if let Some(args_delta) = function.and_then(|f| f.arguments.as_ref()) {
+                                                if !args_delta.is_empty() {
+                                                    tool_call.function.arguments.push_str(args_delta);
+                                                    yield StreamEvent::ToolCallDelta {
+                                                        id: tool_call.id.clone(),
+                                                        args_delta_json: serde_json::Value::String(args_delta.clone()),
+                                                    };
                                                 }
                                             }
                                         }
                                     }
                                 }
 
-                                if let Some(usage) = response.usage {
-                                    yield StreamEvent::Tokens {
-                                        input: usage.prompt_tokens,
-                                        output: usage.completion_tokens,
-                                    };
+                                // All arguments have arrived once the choice finishes
+                                if choice.finish_reason.is_some() {
+                                    for tool_call in std::mem::take(&mut tool_calls_buffer).into_values() {
+                                        yield StreamEvent::ToolCallEnd { id: tool_call.id };
+                                    }
                                 }
                             }
+
+                            if let Some(usage) = response.usage {
+                                yield StreamEvent::Tokens {
+                                    input: usage.prompt_tokens,
+                                    output: usage.completion_tokens,
+                                };
+                            }
                         }
                     }
                 }
 
-                for tool_call in tool_calls_buffer.values() {
-                    yield StreamEvent::ToolCallEnd {
-                        id: tool_call.id.clone(),
-                    };
+                // Streams cut off before a finish_reason still close their calls
+                for tool_call in tool_calls_buffer.into_values() {
+                    yield StreamEvent::ToolCallEnd { id: tool_call.id };
                 }
 
                 yield StreamEvent::Done;
@@ -239,17 +263,12 @@ impl ChatAdapter for OpenAIAdapter {
             Ok(Box::new(Box::pin(s.map(
                 |r: Result<StreamEvent, AdapterError>| match r {
                     Ok(ev) => ev,
-                    Err(e) => StreamEvent::Error {
-                        code: "stream_error".to_string(),
-                        message: e.to_string(),
-                    },
+                    Err(e) => body::stream_error_event(e),
                 },
             ))))
         } else {
-            let response: OpenAIChatResponse = resp
-                .json()
-                .await
-                .map_err(|e| AdapterError::Http(format!("Failed to parse response: {}", e)))?;
+            let response: OpenAIChatResponse =
+                http::response_json(resp, &ir.model.provider, &ir.model.model_id, "Failed to parse response").await?;
 
             let s = async_stream::try_stream! {
                 if let Some(choice) = response.choices.first() {
@@ -260,6 +279,15 @@ impl ChatAdapter for OpenAIAdapter {
                             };
                         }
 
+                        if let Some(refusal) = message.refusal.as_ref().filter(|refusal| !refusal.is_empty()) {
+                            yield StreamEvent::Refusal { text: refusal.clone() };
+                        }
+
+                        // Citations, e.g. of web search results, as the provider sent them
+                        for annotation in &message.annotations {
+                            yield StreamEvent::Annotation { annotation: annotation.clone() };
+                        }
+
                         if let Some(tool_calls) = &message.tool_calls {
                             for tool_call in tool_calls {
                                 yield StreamEvent::ToolCallStart {
@@ -313,15 +341,258 @@ impl ChatAdapter for OpenAIAdapter {
             ))))
         }
     }
+
+    async fn execute_image(&self, ir: ImageRequestIR) -> Result<ImageResponseIR, AdapterError> {
+        // gpt-image models always return base64 and reject `response_format`
+        let response_format = ir
+            .response_format
+            .filter(|_| !ir.model.model_id.contains("gpt-image"))
+            .map(|format| match format {
+                ImageResponseFormat::Url => "url".to_string(),
+                ImageResponseFormat::B64Json => "b64_json".to_string(),
+            });
+        let payload = OpenAIImageRequest {
+            model: Some(ir.model.model_id.clone()),
+            prompt: ir.prompt.clone(),
+            n: ir.n,
+            size: ir.size.clone(),
+            quality: ir.quality.clone(),
+            response_format,
+            style: None,
+            user: None,
+        };
+
+        let client = http::client(&ir.model.provider)?;
+        let url = Self::endpoint_url(&ir.model.provider, "/images/generations");
+        let mut request = http::json_body(client.post(&url), &payload, &ir.model.provider, &ir.model.model_id);
+
+        if let Some(token) = ir.model.provider.bearer_token().await? {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+
+        for (key, value) in ir.model.provider.headers().await? {
+            request = request.header(key, value);
+        }
+
+        let resp = http::send(request, &ir.model.provider, "Failed to send request").await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            if let Ok(error_response) = serde_json::from_str::<OpenAIErrorResponse>(&text) {
+                return Err(AdapterError::Provider {
+                    code: error_response
+                        .error
+                        .code
+                        .unwrap_or_else(|| status.as_u16().to_string()),
+                    message: error_response.error.message,
+                });
+            }
+            return Err(AdapterError::Provider {
+                code: status.as_u16().to_string(),
+                message: text,
+            });
+        }
+
+        let response: OpenAIImageResponse =
+            http::response_json(resp, &ir.model.provider, &ir.model.model_id, "Failed to parse image response").await?;
+        Ok(ImageResponseIR {
+            created: response.created,
+            images: response
+                .data
+                .into_iter()
+                .map(|image| GeneratedImage {
+                    b64_json: image.b64_json,
+                    url: image.url,
+                    revised_prompt: image.revised_prompt,
+                })
+                .collect(),
+        })
+    }
+
+    async fn execute_speech(&self, ir: SpeechRequestIR) -> Result<SpeechResponseIR, AdapterError> {
+        let url = Self::endpoint_url(&ir.model.provider, "/audio/speech");
+        Self::send_speech_request(&url, &ir, false).await
+    }
 }
 
 impl OpenAIAdapter {
+    /// POST an `/audio/speech`-shaped request and stream the audio body back.
+    /// Custom voices are sent as `{"id": ...}` when `voice_objects` is set
+    /// (OpenAI), otherwise as plain names, which compatible TTS servers expect.
+    pub(crate) async fn send_speech_request(
+        url: &str,
+        ir: &SpeechRequestIR,
+        voice_objects: bool,
+    ) -> Result<SpeechResponseIR, AdapterError> {
+        let voice = match &ir.voice {
+            SpeechVoice::Preset(name) => serde_json::json!(name),
+            SpeechVoice::Custom(id) if voice_objects => serde_json::json!({ "id": id }),
+            SpeechVoice::Custom(id) => serde_json::json!(id),
+        };
+        let mut payload = serde_json::json!({
+            "model": ir.model.model_id,
+            "input": ir.input,
+            "voice": voice,
+        });
+        if let Some(format) = &ir.response_format {
+            payload["response_format"] = serde_json::json!(format);
+        }
+        if let Some(speed) = ir.speed {
+            payload["speed"] = serde_json::json!(speed);
+        }
+
+        let client = http::client(&ir.model.provider)?;
+        let mut request = http::json_body(client.post(url), &payload, &ir.model.provider, &ir.model.model_id);
+
+        if let Some(token) = ir.model.provider.bearer_token().await? {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+
+        for (key, value) in ir.model.provider.headers().await? {
+            request = request.header(key, value);
+        }
+
+        let resp = http::send(request, &ir.model.provider, "Failed to send request").await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            if let Ok(error_response) = serde_json::from_str::<OpenAIErrorResponse>(&text) {
+                return Err(AdapterError::Provider {
+                    code: error_response
+                        .error
+                        .code
+                        .unwrap_or_else(|| status.as_u16().to_string()),
+                    message: error_response.error.message,
+                });
+            }
+            return Err(AdapterError::Provider {
+                code: status.as_u16().to_string(),
+                message: text,
+            });
+        }
+
+        // Prefer the provider's content type unless it is generic
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| v.starts_with("audio/"))
+            .map(str::to_string)
+            .unwrap_or_else(|| speech_content_type(ir.response_format.as_deref()).to_string());
+        let audio = resp
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(|e| AdapterError::Http(format!("Failed to read audio: {}", e))));
+
+        Ok(SpeechResponseIR {
+            content_type,
+            audio: Box::pin(audio),
+        })
+    }
+
+    /// Resolve an API route for the endpoint, honouring its compat profile's
+    /// default base URL and path prefix. A base URL that already ends with
+    /// the prefix (`https://host/llm/v1`) doesn't get it twice.
+    pub fn endpoint_url(endpoint: &ProviderEndpoint, route: &str) -> String {
+        let profile = endpoint.compat_profile;
+        let base = if endpoint.base_url.is_empty() {
+            profile
+                .default_base_url()
+                .or(endpoint.kind.default_base_url())
+                .unwrap_or_default()
+        } else {
+            endpoint.base_url.as_str()
+        };
+        let prefix = profile.api_prefix();
+        let prefix = if base.trim_end_matches('/').ends_with(prefix) { "" } else { prefix };
+        http::join_url(base, &format!("{}{}", prefix, route))
+    }
+
+    /// Build the outbound JSON body: the typed Chat Completions request adjusted
+    /// for the endpoint's compat profile, with the endpoint's static extensions
+    /// and then the request's own extensions merged on top, so provider-specific
+    /// keys are never dropped. The client's `extra_body` fields fill in only
+    /// keys nothing else set. vLLM extensions (see [`crate::types::providers::vllm`])
+    /// are only sent with the `VLLM` profile, which also turns JSON schema
+    /// response formats into `guided_json`. Local sampling fields such as
+    /// `min_p` and `mirostat` go to generic OpenAI-compatible servers
+    /// (llama.cpp and the like) and, as far as it takes them, to vLLM; other
+    /// profiles and OpenAI itself never see them.
+    pub fn build_request_body(ir: &ChatRequestIR) -> Result<serde_json::Value, AdapterError> {
+        validate_request_metadata(&ir.request_metadata).map_err(AdapterError::Invalid)?;
+        let payload = Self::build_openai_request(ir)?;
+        let mut body = serde_json::to_value(&payload)
+            .map_err(|e| AdapterError::internal(format!("Failed to serialize request: {}", e)))?;
+
+        if let serde_json::Value::Object(map) = &mut body {
+            let profile = ir.model.provider.compat_profile;
+            let vllm = profile == CompatProfile::VLLM;
+            if ir.model.provider.kind == ProviderKind::OpenAICompat {
+                for (name, value) in ir.sampling.local_fields() {
+                    match (profile, name) {
+                        (CompatProfile::Generic, _) | (CompatProfile::VLLM, "min_p") => {
+                            map.insert(name.to_string(), value);
+                        }
+                        (CompatProfile::VLLM, "repeat_penalty") => {
+                            map.insert("repetition_penalty".to_string(), value);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            for (key, value) in ir
+                .model
+                .provider
+                .extensions
+                .iter()
+                .chain(ir.provider_extensions.iter())
+            {
+                if vllm || !VLLM_EXTENSION_FIELDS.contains(&key.as_str()) {
+                    map.insert(key.clone(), value.clone());
+                }
+            }
+            for (key, value) in &ir.extra_body {
+                map.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+
+            // Last, so merged fields can't bring back what the vendor rejects
+            for field in profile.unsupported_fields() {
+                map.remove(*field);
+            }
+            for (ours, theirs) in profile.renamed_fields() {
+                if let Some(value) = map.remove(*ours) {
+                    map.insert(theirs.to_string(), value);
+                }
+            }
+
+            // vLLM constrains output through guided decoding, not `json_schema`
+            if vllm && !VLLM_GUIDED_FIELDS.iter().any(|field| map.contains_key(*field)) {
+                if let Some(ResponseFormat::JsonSchema { schema, .. }) = &ir.response_format {
+                    map.remove("response_format");
+                    map.insert("guided_json".to_string(), schema.clone());
+                }
+            }
+        }
+
+        Ok(body)
+    }
+
     fn build_openai_request(ir: &ChatRequestIR) -> Result<OpenAIChatRequest, AdapterError> {
         let messages: Vec<OpenAIMessage> = ir
             .messages
             .iter()
             .map(|msg| {
                 let mut content = None;
+                let mut tool_calls = Vec::new();
+                let mut tool_call_id = None;
 
                 for part in &msg.parts {
                     match part {
@@ -341,6 +612,20 @@ impl OpenAIAdapter {
                         ContentPart::File { .. } => {
                             // Handle file content if needed
                         }
+                        ContentPart::ToolCall { id, name, arguments } => {
+                            tool_calls.push(OpenAIToolCall {
+                                id: id.clone(),
+                                r#type: "function".to_string(),
+                                function: OpenAIFunctionCall {
+                                    name: name.clone(),
+                                    arguments: ContentPart::arguments_string(arguments),
+                                },
+                            });
+                        }
+                        ContentPart::ToolResult { call_id, content: output } => {
+                            tool_call_id = Some(call_id.clone());
+                            content = Some(output.clone());
+                        }
                     }
                 }
 
@@ -359,8 +644,8 @@ impl OpenAIAdapter {
                         None => crate::OpenAIMessageContent::Text(String::new()),
                     },
                     name: None,
-                    tool_calls: None,
-                    tool_call_id: None,
+                    tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+                    tool_call_id,
                 }
             })
             .collect();
@@ -420,12 +705,24 @@ impl OpenAIAdapter {
             tool_choice: None, // TODO: Convert from serde_json::Value to OpenAIToolChoice
             functions: None,
             function_call: None,
-            response_format: None,
-            logit_bias: None,
-            logprobs: None,
-            top_logprobs: None,
+            response_format: ir.response_format.as_ref().map(|format| match format {
+                ResponseFormat::Text => OpenAIResponseFormat::Simple { r#type: "text".to_string() },
+                ResponseFormat::JsonObject => OpenAIResponseFormat::Simple { r#type: "json_object".to_string() },
+                ResponseFormat::JsonSchema { name, description, schema, strict } => OpenAIResponseFormat::JsonSchema {
+                    r#type: "json_schema".to_string(),
+                    json_schema: OpenAIJsonSchema {
+                        description: description.clone(),
+                        name: name.clone(),
+                        schema: schema.clone(),
+                        strict: *strict,
+                    },
+                },
+            }),
+            logit_bias: ir.sampling.logit_bias.clone(),
+            logprobs: ir.sampling.logprobs,
+            top_logprobs: ir.sampling.top_logprobs,
             n: None,
-            seed: None,
+            seed: ir.sampling.seed,
             user: None,
             stream_options: None,
             modalities: None,
@@ -435,19 +732,57 @@ impl OpenAIAdapter {
             } else {
                 None
             },
-            store: None,
-            metadata: None,
+            store: ir.store,
+            metadata: if ir.request_metadata.is_empty() {
+                None
+            } else {
+                Some(
+                    ir.request_metadata
+                        .iter()
+                        .map(|(key, value)| (key.clone(), serde_json::Value::String(value.clone())))
+                        .collect(),
+                )
+            },
             prediction: None,
             service_tier: None,
-            reasoning_effort: None,
+            reasoning_effort: Self::reasoning_effort(ir),
             verbosity: None,
             web_search_options: None,
             prompt_cache_key: None,
             safety_identifier: None,
+            extra: serde_json::Map::new(),
         })
     }
 
-    fn infer_model_capabilities(model_id: &str) -> ModelCapabilitiesWithModalities {
+    /// Whether the model family accepts `reasoning_effort`: OpenAI's o-series
+    /// and GPT-5, and xAI's Grok 3 Mini. Vendor prefixes such as OpenRouter's
+    /// `openai/` are ignored.
+    pub fn supports_reasoning_effort(model_id: &str) -> bool {
+        let model = model_id.rsplit('/').next().unwrap_or(model_id).to_lowercase();
+        ["o1", "o3", "o4"]
+            .iter()
+            .any(|family| model == *family || model.starts_with(&format!("{}-", family)))
+            || model.starts_with("gpt-5")
+            || model.starts_with("grok-3-mini")
+    }
+
+    /// The request's effort for models that support it. Only GPT-5 knows
+    /// `minimal`; older reasoning models get `low` instead.
+    fn reasoning_effort(ir: &ChatRequestIR) -> Option<OpenAIReasoningEffort> {
+        let model_id = &ir.model.model_id;
+        let effort = ir.reasoning.as_ref()?.effort?;
+        if !Self::supports_reasoning_effort(model_id) {
+            return None;
+        }
+        Some(match effort {
+            ReasoningEffort::Minimal if model_id.to_lowercase().contains("gpt-5") => OpenAIReasoningEffort::Minimal,
+            ReasoningEffort::Minimal | ReasoningEffort::Low => OpenAIReasoningEffort::Low,
+            ReasoningEffort::Medium => OpenAIReasoningEffort::Medium,
+            ReasoningEffort::High => OpenAIReasoningEffort::High,
+        })
+    }
+
+    fn infer_model_capabilities(model_id: &str) -> InferredCapabilities {
         let model_id_lower = model_id.to_lowercase();
 
         let supports_tools = model_id_lower.contains("gpt-4")
@@ -456,6 +791,7 @@ impl OpenAIAdapter {
             || model_id_lower.contains("command");
 
         let supports_vision = model_id_lower.contains("vision")
+            || model_id_lower.contains("gpt-4o")
             || model_id_lower.contains("gpt-4-vision")
             || model_id_lower.contains("claude-3");
 
@@ -481,23 +817,20 @@ impl OpenAIAdapter {
         if supports_vision {
             modalities.push(Modality::Vision);
         }
+        if model_id_lower.contains("dall-e") || model_id_lower.contains("gpt-image") {
+            modalities.push(Modality::ImageOut);
+        }
 
-        ModelCapabilitiesWithModalities {
+        InferredCapabilities {
             capabilities: ModelCapabilities {
-                supports_streaming: true,
                 supports_tools,
                 supports_vision,
                 supports_json,
-                supports_audio: false,
                 max_tokens,
                 context_length,
+                ..ModelCapabilities::text_only()
             },
             modalities,
         }
     }
 }
-
-struct ModelCapabilitiesWithModalities {
-    capabilities: ModelCapabilities,
-    modalities: Vec<Modality>,
-}
@@ -1,5 +1,6 @@
 use crate::{
     adapter::{AdapterError, ChatAdapter},
+    adapters::{body, http, sse},
     stream::*,
     types::*,
 };
@@ -11,6 +12,41 @@ use tokio_util::sync::CancellationToken;
 
 pub struct OpenAIResponsesAdapter;
 
+/// How often a response in OpenAI's background mode is polled
+pub const BACKGROUND_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Where a request went, for follow-up calls about its response
+struct Upstream {
+    client: reqwest::Client,
+    /// The `/v1/responses` URL
+    url: String,
+    token: Option<String>,
+    headers: std::collections::BTreeMap<String, String>,
+}
+
+impl Upstream {
+    fn authorized(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+        request
+    }
+
+    /// Ask the provider to stop response `id`, in the background
+    fn cancel(&self, id: &str) {
+        let request = self.authorized(self.client.post(format!("{}/{}/cancel", self.url, id)));
+        let id = id.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = request.send().await {
+                tracing::debug!(response_id = %id, error = %e, "Failed to cancel upstream response");
+            }
+        });
+    }
+}
+
 #[async_trait]
 impl ChatAdapter for OpenAIResponsesAdapter {
     fn provider_kind(&self) -> ProviderKind {
@@ -29,27 +65,20 @@ impl ChatAdapter for OpenAIResponsesAdapter {
         &self,
         endpoint: &ProviderEndpoint,
     ) -> Result<Vec<DiscoveredModel>, AdapterError> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/v1/models", endpoint.base_url);
+        let client = http::client(endpoint)?;
+        let url = http::endpoint_url(endpoint, "/v1/models");
 
         let mut request = client.get(&url);
 
-        if let Some(timeout) = endpoint.timeout {
-            request = request.timeout(std::time::Duration::from_millis(timeout));
-        }
-
-        if let Some(api_key) = &endpoint.api_key {
-            request = request.header("Authorization", format!("Bearer {}", api_key));
+        if let Some(token) = endpoint.bearer_token().await? {
+            request = request.header("Authorization", format!("Bearer {}", token));
         }
 
-        for (key, value) in &endpoint.extra_headers {
+        for (key, value) in endpoint.headers().await? {
             request = request.header(key, value);
         }
 
-        let resp = request
-            .send()
-            .await
-            .map_err(|e| AdapterError::Http(format!("Failed to fetch models: {}", e)))?;
+        let resp = http::send(request, endpoint, "Failed to fetch models").await?;
 
         if !resp.status().is_success() {
             let status = resp.status();
@@ -72,7 +101,11 @@ impl ChatAdapter for OpenAIResponsesAdapter {
             .data
             .into_iter()
             .map(|model| {
-                let capabilities = Self::infer_model_capabilities(&model.id);
+                let mut capabilities = Self::infer_model_capabilities(&model.id);
+                model.apply_advertised_capabilities(
+                    &mut capabilities.capabilities,
+                    &mut capabilities.modalities,
+                );
                 DiscoveredModel {
                     id: format!("openai/{}", model.id),
                     name: model.id,
@@ -80,6 +113,8 @@ impl ChatAdapter for OpenAIResponsesAdapter {
                     provider_kind: ProviderKind::OpenAI,
                     modalities: capabilities.modalities,
                     capabilities: capabilities.capabilities,
+                    // Servers without the field send 0
+                    created: Some(model.created).filter(|created| *created > 0),
                 }
             })
             .collect();
@@ -95,27 +130,24 @@ impl ChatAdapter for OpenAIResponsesAdapter {
     {
         let payload = Self::build_openai_request(&ir)?;
 
-        let client = reqwest::Client::new();
-        let url = format!("{}/v1/responses", ir.model.provider.base_url);
-
-        let mut request = client.post(&url).json(&payload);
+        let client = http::client(&ir.model.provider)?;
+        let url = http::endpoint_url(&ir.model.provider, "/v1/responses");
 
-        if let Some(timeout) = ir.model.provider.timeout {
-            request = request.timeout(std::time::Duration::from_millis(timeout));
-        }
+        let mut request = http::json_body(client.post(&url), &payload, &ir.model.provider, &ir.model.model_id);
 
-        if let Some(api_key) = &ir.model.provider.api_key {
-            request = request.header("Authorization", format!("Bearer {}", api_key));
+        let token = ir.model.provider.bearer_token().await?;
+        if let Some(token) = &token {
+            request = request.header("Authorization", format!("Bearer {}", token));
         }
 
-        for (key, value) in &ir.model.provider.extra_headers {
+        let headers = ir.model.provider.headers().await?;
+        for (key, value) in &headers {
             request = request.header(key, value);
         }
 
-        let mut resp = request
-            .send()
-            .await
-            .map_err(|e| AdapterError::Http(format!("Failed to send request: {}", e)))?;
+        let mut resp = http::send(request, &ir.model.provider, "Failed to send request").await?;
+        let idle_timeout = ir.model.provider.idle_stream_timeout();
+        let upstream = Upstream { client, url, token, headers };
 
         if !resp.status().is_success() {
             let status = resp.status();
@@ -143,198 +175,472 @@ impl ChatAdapter for OpenAIResponsesAdapter {
         if ir.stream {
             let s = async_stream::try_stream! {
                 let mut tool_calls_buffer: HashMap<String, OpenAIToolCallPayload> = HashMap::new();
+                let mut response_id: Option<String> = None;
+                let mut lines = body::LineBuffer::new();
+
+                loop {
+                    let chunk = match body::next_chunk(&mut resp, &cancel, idle_timeout).await? {
+                        body::BodyRead::Chunk(chunk) => chunk,
+                        body::BodyRead::End => break,
+                        body::BodyRead::Cancelled => {
+                            drop(resp);
+                            // Closing the stream doesn't stop a response that is
+                            // already running upstream; cancel it explicitly
+                            if let Some(id) = response_id {
+                                upstream.cancel(&id);
+                            }
+                            yield body::cancelled_event();
+                            return;
+                        }
+                    };
 
-                while let Some(chunk) = resp.chunk().await
-                    .map_err(|e| AdapterError::Http(format!("Failed to read chunk: {}", e)))?
-                {
-                    if cancel.is_cancelled() {
-                        yield StreamEvent::Error {
-                            code: "cancelled".to_string(),
-                            message: "Request was cancelled".to_string(),
+                    for line in lines.push(&chunk) {
+                        let json_str = match sse::parse_line(&line) {
+                            sse::SseLine::Data(data) => data,
+                            sse::SseLine::Comment(comment) => {
+                                if let Some(status) = sse::comment_status(comment) {
+                                    yield status;
+                                }
+                                continue;
+                            }
+                            sse::SseLine::Other => continue,
                         };
-                        break;
-                    }
 
-                    let chunk_str = String::from_utf8_lossy(&chunk);
-                    for line in chunk_str.lines() {
-                        let line = line.trim();
-                        if line.is_empty() {
+                        if json_str == "[DONE]" {
+                            yield StreamEvent::Done;
+                            return;
+                        }
+
+                        // Some servers report a failure as a data event of its own
+                        if let Some(error) = sse::error_event(json_str) {
+                            yield error;
+                            return;
+                        }
+
+                        if let Some(event) = Self::output_event(json_str) {
+                            yield event;
                             continue;
                         }
 
-                        if let Some(json_str) = line.strip_prefix("data: ") {
-                            if let Ok(response) = serde_json::from_str::<OpenAIStreamingResponse>(json_str) {
-                                for choice in &response.choices {
-                                    if let Some(content) = &choice.delta.content {
-                                        yield StreamEvent::TextDelta {
-                                            content: content.clone(),
-                                        };
-                                    }
+                        if let Some(status) = Self::lifecycle_status(json_str) {
+                            if response_id.is_none() {
+                                response_id = Self::response_id(json_str);
+                            }
+                            yield status;
+                            continue;
+                        }
+
+                        if let Ok(mut response) = serde_json::from_str::<OpenAIStreamingResponse>(json_str) {
+                            let mut finished = false;
+                            for choice in &mut response.choices {
+                                if let Some(content) = choice.delta.content.take() {
+                                    yield StreamEvent::TextDelta { content };
+                                }
 
-                                    if let Some(tool_calls) = &choice.delta.tool_calls {
-                                        for tool_call in tool_calls {
-                                            if let Some(function) = &tool_call.function {
-                                                if let Some(args_delta) = &function.arguments {
-                                                    if let Some(tool_call_buffer) = tool_calls_buffer.get_mut(&tool_call.id.clone().unwrap_or_default()) {
-                                                        tool_call_buffer.function.arguments.push_str(args_delta);
-
-                                                        yield StreamEvent::ToolCallDelta {
-                                                            id: tool_call.id.clone().unwrap_or_default(),
-                                                            args_delta_json: serde_json::Value::String(args_delta.to_string()),
-                                                        };
-                                                    }
+                                if let Some(tool_calls) = &choice.delta.tool_calls {
+                                    for tool_call in tool_calls {
+                                        if let Some(function) = &tool_call.function {
+                                            if let Some(args_delta) = &function.arguments {
+                                                if let Some(tool_call_buffer) = tool_calls_buffer.get_mut(&tool_call.id.clone().unwrap_or_default()) {
+                                                    tool_call_buffer.function.arguments.push_str(args_delta);
+
+                                                    yield StreamEvent::ToolCallDelta {
+                                                        id: tool_call.id.clone().unwrap_or_default(),
+                                                        args_delta_json: serde_json::Value::String(args_delta.to_string()),
+                                                    };
                                                 }
                                             }
                                         }
                                     }
+                                }
 
                                 // Check if this is the final chunk by looking at finish_reason
-                                for choice in &response.choices {
-                                    if choice.finish_reason.is_some() {
-                                        for tool_call in tool_calls_buffer.values() {
-                                            yield StreamEvent::ToolCallEnd {
-                                                id: tool_call.id.clone(),
-                                            };
-                                        }
-                                        break;
-                                    }
+                                if choice.finish_reason.is_some() {
+                                    finished = true;
                                 }
-                                    yield StreamEvent::Done;
-                                    return;
+                            }
+
+                            if finished {
+                                for tool_call in tool_calls_buffer.values() {
+                                    yield StreamEvent::ToolCallEnd {
+                                        id: tool_call.id.clone(),
+                                    };
                                 }
+                                yield StreamEvent::Done;
+                                return;
                             }
                         }
                     }
                 }
+
+                yield StreamEvent::Done;
             };
 
             Ok(Box::new(Box::pin(s.map(
                 |r: Result<StreamEvent, AdapterError>| match r {
                     Ok(ev) => ev,
-                    Err(e) => StreamEvent::Error {
-                        code: "stream_error".to_string(),
-                        message: e.to_string(),
-                    },
+                    Err(e) => body::stream_error_event(e),
                 },
             ))))
+        } else if ir.background {
+            let response: serde_json::Value =
+                http::response_json(resp, &ir.model.provider, &ir.model.model_id, "Failed to parse response").await?;
+            if let Some(error) = http::embedded_error(&response) {
+                return Err(error);
+            }
+            Ok(Box::new(Self::poll_background(response, upstream, ir.model.provider, ir.model.model_id, cancel)))
         } else {
-            let response: OpenAIResponsesResponse = resp
-                .json()
-                .await
-                .map_err(|e| AdapterError::Http(format!("Failed to parse response: {}", e)))?;
+            let response: OpenAIResponsesResponse =
+                http::response_json(resp, &ir.model.provider, &ir.model.model_id, "Failed to parse response").await?;
+            Ok(Box::new(Self::output_events(response)))
+        }
+    }
 
-            let s = async_stream::try_stream! {
-                if let Some(error) = response.error {
-                    yield StreamEvent::Error {
-                        code: error.code,
-                        message: error.message,
-                    };
-                    return;
+    /// Generates images with the `image_generation` tool, one response per
+    /// requested image. The tool only returns base64 data.
+    async fn execute_image(&self, ir: ImageRequestIR) -> Result<ImageResponseIR, AdapterError> {
+        if ir.response_format == Some(ImageResponseFormat::Url) {
+            return Err(AdapterError::Unsupported(
+                "the image_generation tool only returns b64_json images".to_string(),
+            ));
+        }
+
+        let mut tool = serde_json::json!({ "type": "image_generation" });
+        if let Some(size) = &ir.size {
+            tool["size"] = serde_json::json!(size);
+        }
+        if let Some(quality) = &ir.quality {
+            tool["quality"] = serde_json::json!(quality);
+        }
+        let payload = serde_json::json!({
+            "model": ir.model.model_id,
+            "input": ir.prompt,
+            "tools": [tool],
+            "tool_choice": { "type": "image_generation" },
+            "stream": false,
+        });
+
+        let endpoint = &ir.model.provider;
+        let model = ir.model.model_id.as_str();
+        let client = http::client(endpoint)?;
+        let url = http::endpoint_url(endpoint, "/v1/responses");
+        let token = endpoint.bearer_token().await?;
+        let headers = endpoint.headers().await?;
+        let requests = (0..ir.n.unwrap_or(1).max(1)).map(|_| {
+            let mut request = http::json_body(client.post(&url), &payload, endpoint, model);
+
+            if let Some(token) = &token {
+                request = request.header("Authorization", format!("Bearer {}", token));
+            }
+
+            for (key, value) in &headers {
+                request = request.header(key, value);
+            }
+
+            async move {
+                let resp = http::send(request, endpoint, "Failed to send request").await?;
+                let status = resp.status();
+                let text = resp
+                    .text()
+                    .await
+                    .map_err(|e| AdapterError::Http(format!("Failed to read response: {}", e)))?;
+                if !status.is_success() {
+                    return Err(AdapterError::Provider {
+                        code: status.as_u16().to_string(),
+                        message: text,
+                    });
                 }
+                http::response_body::<serde_json::Value>(text.as_bytes(), endpoint, model)
+                    .map_err(|e| AdapterError::Http(format!("Failed to parse response: {}", e)))
+            }
+        });
+
+        let mut images = Vec::new();
+        for response in futures_util::future::join_all(requests).await {
+            let response = response?;
+            let calls = response["output"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|item| item["type"] == "image_generation_call");
+            for call in calls {
+                if let Some(result) = call["result"].as_str() {
+                    images.push(GeneratedImage {
+                        b64_json: Some(result.to_string()),
+                        url: None,
+                        revised_prompt: call["revised_prompt"].as_str().map(str::to_string),
+                    });
+                }
+            }
+        }
+        if images.is_empty() {
+            return Err(AdapterError::Provider {
+                code: "no_image".to_string(),
+                message: "the model did not call the image_generation tool".to_string(),
+            });
+        }
 
-                for item in response.output {
-                    match item {
-                        crate::types::providers::openai::ResponseOutputItem::Message(message) => {
-                            for content_part in message.content {
-                                match content_part {
-                                    crate::types::providers::openai::ResponseOutputContent::OutputText(text_part) => {
-                                        yield StreamEvent::TextDelta {
-                                            content: text_part.text,
-                                        };
-                                    }
-                                    crate::types::providers::openai::ResponseOutputContent::Refusal(refusal_part) => {
-                                        yield StreamEvent::SystemNote {
-                                            content: format!("Refusal: {}", refusal_part.refusal),
+        Ok(ImageResponseIR {
+            created: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            images,
+        })
+    }
+
+    async fn execute_speech(&self, ir: SpeechRequestIR) -> Result<SpeechResponseIR, AdapterError> {
+        let url = http::endpoint_url(&ir.model.provider, "/v1/audio/speech");
+        crate::adapters::OpenAIAdapter::send_speech_request(&url, &ir, true).await
+    }
+}
+
+impl OpenAIResponsesAdapter {
+    /// The events of a finished response's output
+    fn output_events(response: OpenAIResponsesResponse) -> impl futures_util::Stream<Item = StreamEvent> + Send + Unpin {
+        let s = async_stream::try_stream! {
+            if let Some(error) = response.error {
+                yield StreamEvent::Error {
+                    code: error.code,
+                    message: error.message,
+                };
+                return;
+            }
+
+            for item in response.output {
+                match item {
+                    crate::types::providers::openai::ResponseOutputItem::Message(message) => {
+                        for content_part in message.content {
+                            match content_part {
+                                crate::types::providers::openai::ResponseOutputContent::OutputText(text_part) => {
+                                    yield StreamEvent::TextDelta {
+                                        content: text_part.text,
+                                    };
+                                    for annotation in text_part.annotations {
+                                        yield StreamEvent::Annotation {
+                                            annotation: crate::types::providers::openai::chat_annotation(annotation),
                                         };
                                     }
                                 }
+                                crate::types::providers::openai::ResponseOutputContent::Refusal(refusal_part) => {
+                                    yield StreamEvent::Refusal {
+                                        text: refusal_part.refusal,
+                                    };
+                                }
                             }
                         }
-                        crate::types::providers::openai::ResponseOutputItem::FunctionCall(function_call) => {
-                            let id = function_call.id.clone().unwrap_or_else(|| function_call.call_id.clone());
-                            yield StreamEvent::ToolCallStart {
-                                id: id.clone(),
-                                name: function_call.name.clone(),
-                                args_json: serde_json::Value::Object(serde_json::Map::new()),
-                            };
+                    }
+                    crate::types::providers::openai::ResponseOutputItem::FunctionCall(function_call) => {
+                        let id = function_call.id.clone().unwrap_or_else(|| function_call.call_id.clone());
+                        yield StreamEvent::ToolCallStart {
+                            id: id.clone(),
+                            name: function_call.name.clone(),
+                            args_json: serde_json::Value::Object(serde_json::Map::new()),
+                        };
 
-                            yield StreamEvent::ToolCallDelta {
-                                id: id.clone(),
-                                args_delta_json: serde_json::Value::String(function_call.arguments.clone()),
-                            };
+                        yield StreamEvent::ToolCallDelta {
+                            id: id.clone(),
+                            args_delta_json: serde_json::Value::String(function_call.arguments.clone()),
+                        };
 
-                            yield StreamEvent::ToolCallEnd {
-                                id: id.clone(),
-                            };
-                        }
-                        crate::types::providers::openai::ResponseOutputItem::Reasoning(reasoning) => {
-                            if !reasoning.summary.is_empty() {
-                                for summary in &reasoning.summary {
-                                    match summary {
-                                        crate::types::providers::openai::response_reasoning_item::Summary::SummaryText { text } => {
-                                            yield StreamEvent::SystemNote {
-                                                content: format!("Reasoning Summary: {}", text),
-                                            };
-                                        }
+                        yield StreamEvent::ToolCallEnd {
+                            id: id.clone(),
+                        };
+                    }
+                    crate::types::providers::openai::ResponseOutputItem::Reasoning(reasoning) => {
+                        if !reasoning.summary.is_empty() {
+                            for summary in &reasoning.summary {
+                                match summary {
+                                    crate::types::providers::openai::response_reasoning_item::Summary::SummaryText { text } => {
+                                        yield StreamEvent::SystemNote {
+                                            content: format!("Reasoning Summary: {}", text),
+                                        };
                                     }
                                 }
                             }
-                            if let Some(content) = &reasoning.content {
-                                for content_item in content {
-                                    match content_item {
-                                        crate::types::providers::openai::response_reasoning_item::Content::ReasoningText { text } => {
-                                            yield StreamEvent::SystemNote {
-                                                content: format!("Reasoning: {}", text),
-                                            };
-                                        }
+                        }
+                        if let Some(content) = &reasoning.content {
+                            for content_item in content {
+                                match content_item {
+                                    crate::types::providers::openai::response_reasoning_item::Content::ReasoningText { text } => {
+                                        yield StreamEvent::SystemNote {
+                                            content: format!("Reasoning: {}", text),
+                                        };
                                     }
                                 }
                             }
                         }
-                        _ => {
-                            // Handle other variants as system notes for now
-                            yield StreamEvent::SystemNote {
-                                content: format!("Unhandled output item: {:?}", item),
+                    }
+                    _ => {
+                        // Handle other variants as system notes for now
+                        yield StreamEvent::SystemNote {
+                            content: format!("Unhandled output item: {:?}", item),
+                        };
+                    }
+                }
+            }
+
+            if let Some(usage) = response.usage {
+                yield StreamEvent::Tokens {
+                    input: usage.input_tokens,
+                    output: usage.output_tokens,
+                };
+            }
+
+            yield StreamEvent::Done;
+        };
+
+        Box::pin(s.map(|r: Result<StreamEvent, AdapterError>| match r {
+            Ok(ev) => ev,
+            Err(e) => StreamEvent::Error {
+                code: "response_error".to_string(),
+                message: e.to_string(),
+            },
+        }))
+    }
+
+    /// Poll a response running in OpenAI's background mode until it ends,
+    /// reporting `queued` and `in_progress` as statuses. Cancelling stops it
+    /// upstream too.
+    fn poll_background(
+        mut response: serde_json::Value,
+        upstream: Upstream,
+        endpoint: ProviderEndpoint,
+        model: String,
+        cancel: CancellationToken,
+    ) -> impl futures_util::Stream<Item = StreamEvent> + Send + Unpin {
+        Box::pin(async_stream::stream! {
+            let mut reported: Option<String> = None;
+            loop {
+                let Some(id) = response["id"].as_str().map(str::to_string) else {
+                    yield StreamEvent::Error {
+                        code: "response_error".to_string(),
+                        message: "background response without an id".to_string(),
+                    };
+                    return;
+                };
+                let status = response["status"].as_str().unwrap_or_default().to_string();
+                if status == "cancelled" {
+                    yield body::cancelled_event();
+                    return;
+                }
+                if status != "queued" && status != "in_progress" {
+                    match serde_json::from_value::<OpenAIResponsesResponse>(response) {
+                        Ok(response) => {
+                            let mut events = Self::output_events(response);
+                            while let Some(event) = events.next().await {
+                                yield event;
+                            }
+                        }
+                        Err(e) => {
+                            yield StreamEvent::Error {
+                                code: "response_error".to_string(),
+                                message: format!("Failed to parse response: {}", e),
                             };
                         }
                     }
+                    return;
+                }
+                if reported.as_ref() != Some(&status) {
+                    yield StreamEvent::Status { state: status.clone(), detail: None };
+                    reported = Some(status);
                 }
 
-                if let Some(usage) = response.usage {
-                    yield StreamEvent::Tokens {
-                        input: usage.input_tokens,
-                        output: usage.output_tokens,
+                tokio::select! {
+                    _ = cancel.cancelled() => {
+                        upstream.cancel(&id);
+                        yield body::cancelled_event();
+                        return;
+                    }
+                    _ = tokio::time::sleep(BACKGROUND_POLL_INTERVAL) => {}
+                }
+                let request = upstream.authorized(upstream.client.get(format!("{}/{}", upstream.url, id)));
+                let polled = match http::send(request, &endpoint, "Failed to poll background response").await {
+                    Ok(polled) => polled,
+                    Err(e) => {
+                        yield body::stream_error_event(e);
+                        return;
+                    }
+                };
+                if !polled.status().is_success() {
+                    let status = polled.status();
+                    yield StreamEvent::Error {
+                        code: status.as_u16().to_string(),
+                        message: polled.text().await.unwrap_or_else(|_| "Unknown error".to_string()),
                     };
+                    return;
                 }
+                response = match http::response_json(polled, &endpoint, &model, "Failed to parse response").await {
+                    Ok(response) => response,
+                    Err(e) => {
+                        yield body::stream_error_event(e);
+                        return;
+                    }
+                };
+            }
+        })
+    }
 
-                yield StreamEvent::Done;
-            };
+    /// Map Responses API lifecycle events (`response.created`, `response.queued`,
+    /// `response.in_progress`) to status updates so callers can show progress.
+    fn lifecycle_status(json_str: &str) -> Option<StreamEvent> {
+        let value: serde_json::Value = serde_json::from_str(json_str).ok()?;
+        let state = match value.get("type")?.as_str()? {
+            "response.created" => "created",
+            "response.queued" => "queued",
+            "response.in_progress" => "in_progress",
+            _ => return None,
+        };
 
-            Ok(Box::new(Box::pin(s.map(
-                |r: Result<StreamEvent, AdapterError>| match r {
-                    Ok(ev) => ev,
-                    Err(e) => StreamEvent::Error {
-                        code: "response_error".to_string(),
-                        message: e.to_string(),
-                    },
-                },
-            ))))
+        Some(StreamEvent::Status {
+            state: state.to_string(),
+            detail: value
+                .pointer("/response/status")
+                .and_then(|s| s.as_str())
+                .map(str::to_string),
+        })
+    }
+
+    /// Map `response.output_text.annotation.added` events, e.g. a web search
+    /// citation, to annotations in the Chat Completions shape, and
+    /// `response.refusal.delta` events to refusals
+    fn output_event(json_str: &str) -> Option<StreamEvent> {
+        let mut value: serde_json::Value = serde_json::from_str(json_str).ok()?;
+        match value.get("type")?.as_str()? {
+            "response.output_text.annotation.added" => {
+                let annotation = value.get_mut("annotation")?.take();
+                Some(StreamEvent::Annotation {
+                    annotation: crate::types::providers::openai::chat_annotation(annotation),
+                })
+            }
+            "response.refusal.delta" => Some(StreamEvent::Refusal {
+                text: value.get("delta")?.as_str()?.to_string(),
+            }),
+            _ => None,
         }
     }
-}
 
-impl OpenAIResponsesAdapter {
-    fn build_openai_request(ir: &ChatRequestIR) -> Result<OpenAIResponsesRequestPayload, AdapterError> {
+    /// The id of the response a lifecycle event belongs to
+    fn response_id(json_str: &str) -> Option<String> {
+        let value: serde_json::Value = serde_json::from_str(json_str).ok()?;
+        value.pointer("/response/id")?.as_str().map(str::to_string)
+    }
+
+    /// The typed Responses API request sent for `ir`
+    pub fn build_openai_request(ir: &ChatRequestIR) -> Result<OpenAIResponsesRequestPayload, AdapterError> {
         use crate::types::providers::openai::*;
+        validate_request_metadata(&ir.request_metadata).map_err(AdapterError::Invalid)?;
 
         let input_items: Vec<ResponseInputItem> = ir
             .messages
             .iter()
-            .map(|msg| {
+            .flat_map(|msg| {
+                // Tool calls and their outputs are standalone input items
+                let mut items = Vec::new();
                 let content_parts: Vec<ResponseInputContentPart> = msg
                     .parts
                     .iter()
-                    .map(|part| match part {
+                    .filter_map(|part| Some(match part {
                         ContentPart::Text(text) => {
                             ResponseInputContentPart::InputText(ResponseInputText { text: text.clone() })
                         }
@@ -364,7 +670,26 @@ impl OpenAIResponsesAdapter {
                                 text: format!("File(filename={:?}, file_id={:?})", filename, file_id),
                             })
                         }
-                    })
+                        ContentPart::ToolCall { id, name, arguments } => {
+                            items.push(ResponseInputItem::FunctionCall(ResponseFunctionToolCall {
+                                id: None,
+                                call_id: id.clone(),
+                                name: name.clone(),
+                                arguments: ContentPart::arguments_string(arguments),
+                                status: None,
+                            }));
+                            return None;
+                        }
+                        ContentPart::ToolResult { call_id, content } => {
+                            items.push(ResponseInputItem::FunctionCallOutput(FunctionCallOutput {
+                                call_id: call_id.clone(),
+                                output: content.clone(),
+                                id: None,
+                                status: None,
+                            }));
+                            return None;
+                        }
+                    }))
                     .collect();
 
                 let role = match msg.role {
@@ -375,11 +700,14 @@ impl OpenAIResponsesAdapter {
                     Role::Developer => InputMessageRole::Developer,
                 };
 
-                ResponseInputItem::Message(InputMessage {
-                    content: InputMessageContent::Parts(content_parts),
-                    role,
-                    status: None,
-                })
+                if !content_parts.is_empty() || items.is_empty() {
+                    items.insert(0, ResponseInputItem::Message(InputMessage {
+                        content: InputMessageContent::Parts(content_parts),
+                        role,
+                        status: None,
+                    }));
+                }
+                items
             })
             .collect();
 
@@ -420,22 +748,34 @@ impl OpenAIResponsesAdapter {
             crate::types::ToolChoice::Allowed { .. } => Some(ToolChoice::String("auto".to_string())), // Map to auto for now
         };
 
-        let _reasoning_effort = ir
-            .metadata
-            .get("reasoning_effort")
-            .cloned()
-            .unwrap_or_else(|| "medium".to_string());
-
-        let _reasoning_summary = ir.metadata.get("reasoning_summary").cloned();
+        let reasoning = ir.reasoning.as_ref().map(|reasoning| Reasoning {
+            enabled: None,
+            effort: reasoning.effort.map(|effort| effort.as_str().to_string()),
+            summary: reasoning.summary.clone(),
+        });
 
         let verbosity = ir.metadata.get("text_verbosity").cloned();
 
         Ok(OpenAIResponsesRequestPayload {
             input: Some(OpenAIInputMessage::Items(input_items)),
             model: Some(ir.model.model_id.clone()),
-            reasoning: None, // Don't enable reasoning by default
+            reasoning,
+            store: ir.store,
+            background: ir.background.then_some(true),
+            metadata: (!ir.request_metadata.is_empty()).then(|| ir.request_metadata.clone()),
             text: Some(ResponseTextConfig {
-                format: None,
+                format: ir.response_format.as_ref().map(|format| match format {
+                    ResponseFormat::Text => ResponseFormatTextConfig::Text,
+                    ResponseFormat::JsonObject => ResponseFormatTextConfig::JsonObject,
+                    ResponseFormat::JsonSchema { name, description, schema, strict } => {
+                        ResponseFormatTextConfig::JsonSchema {
+                            name: name.clone(),
+                            schema: schema.clone(),
+                            description: description.clone(),
+                            strict: *strict,
+                        }
+                    }
+                }),
                 verbosity,
             }),
             tools,
@@ -453,7 +793,7 @@ impl OpenAIResponsesAdapter {
         })
     }
 
-    fn infer_model_capabilities(model_id: &str) -> ModelCapabilitiesWithModalities {
+    fn infer_model_capabilities(model_id: &str) -> InferredCapabilities {
         let model_id_lower = model_id.to_lowercase();
 
         let supports_tools = model_id_lower.contains("gpt-4")
@@ -463,10 +803,11 @@ impl OpenAIResponsesAdapter {
             || model_id_lower.contains("claude");
 
         let supports_vision = model_id_lower.contains("vision")
+            || model_id_lower.contains("gpt-4o")
             || model_id_lower.contains("gpt-4-vision")
             || model_id_lower.contains("claude-3");
 
-        let _supports_reasoning = model_id_lower.contains("o1")
+        let supports_reasoning = model_id_lower.contains("o1")
             || model_id_lower.contains("o3")
             || model_id_lower.contains("gpt-5");
 
@@ -500,23 +841,21 @@ impl OpenAIResponsesAdapter {
         if supports_vision {
             modalities.push(Modality::Vision);
         }
+        if model_id_lower.contains("dall-e") || model_id_lower.contains("gpt-image") {
+            modalities.push(Modality::ImageOut);
+        }
 
-        ModelCapabilitiesWithModalities {
-            capabilities: ModelCapabilities {
-                supports_streaming: true,
-                supports_tools,
-                supports_vision,
-                supports_json,
-                supports_audio: false,
-                max_tokens,
-                context_length,
-            },
-            modalities,
+        let mut capabilities = ModelCapabilities {
+            supports_tools,
+            supports_vision,
+            supports_json,
+            max_tokens,
+            context_length,
+            ..ModelCapabilities::text_only()
+        };
+        if supports_reasoning {
+            capabilities = capabilities.with_extra("reasoning", true);
         }
+        InferredCapabilities { capabilities, modalities }
     }
 }
-
-struct ModelCapabilitiesWithModalities {
-    capabilities: ModelCapabilities,
-    modalities: Vec<Modality>,
-}
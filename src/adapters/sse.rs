@@ -0,0 +1,58 @@
+//! Minimal Server-Sent Events line classification shared by the HTTP adapters.
+//!
+//! Providers interleave `data:` payloads with comment lines (`: OPENROUTER PROCESSING`),
+//! `event:` names and other fields. Adapters only care about payloads and comments,
+//! so everything else is reported as [`SseLine::Other`] and skipped.
+
+use crate::stream::StreamEvent;
+
+/// A single classified SSE line.
+#[derive(Debug, PartialEq)]
+pub enum SseLine<'a> {
+    /// A `data:` field with the prefix (and one optional leading space) removed.
+    Data(&'a str),
+    /// A comment line (`:` prefix), typically a keep-alive or processing notice.
+    Comment(&'a str),
+    /// Blank lines, `event:`, `id:`, `retry:` and anything unrecognised.
+    Other,
+}
+
+/// Classify one line of an SSE body.
+pub fn parse_line(line: &str) -> SseLine<'_> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    if let Some(comment) = line.strip_prefix(':') {
+        SseLine::Comment(comment.trim())
+    } else if let Some(data) = line.strip_prefix("data:") {
+        SseLine::Data(data.strip_prefix(' ').unwrap_or(data).trim_end())
+    } else {
+        SseLine::Other
+    }
+}
+
+/// Map a non-empty SSE comment to a [`StreamEvent::Status`].
+///
+/// Empty comments are pure keep-alives and produce no event.
+pub fn comment_status(comment: &str) -> Option<StreamEvent> {
+    if comment.is_empty() {
+        return None;
+    }
+    Some(StreamEvent::Status {
+        state: "processing".to_string(),
+        detail: Some(comment.to_string()),
+    })
+}
+
+/// Map a `data:` payload holding an error object, as some OpenAI-compatible
+/// servers send mid-stream instead of failing the request, to a
+/// [`StreamEvent::Error`] with the provider's code and message (see
+/// [`embedded_error`](crate::adapters::http::embedded_error)).
+pub fn error_event(data: &str) -> Option<StreamEvent> {
+    if !data.contains("\"error\"") {
+        return None;
+    }
+    let body: serde_json::Value = serde_json::from_str(data).ok()?;
+    match crate::adapters::http::embedded_error(&body)? {
+        crate::adapter::AdapterError::Provider { code, message } => Some(StreamEvent::Error { code, message }),
+        _ => None,
+    }
+}
@@ -0,0 +1,304 @@
+use crate::{
+    adapter::{AdapterError, ChatAdapter},
+    adapters::{body, http, sse},
+    stream::*,
+    templates,
+    types::*,
+};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+
+use std::collections::BTreeMap;
+use tokio_util::sync::CancellationToken;
+use crate::tgi::{
+    TgiDetails, TgiErrorResponse, TgiGenerateRequest, TgiGenerateResponse, TgiGrammar, TgiInfo, TgiParameters,
+    TgiStreamEvent,
+};
+
+/// Adapter for Hugging Face Text Generation Inference's native API.
+///
+/// Messages are flattened into one prompt with the endpoint's
+/// [`PromptTemplate`] for the model (see [`ProviderEndpoint::chat_template`]
+/// and [`crate::templates`]).
+#[derive(Default)]
+pub struct TgiAdapter;
+
+#[async_trait]
+impl ChatAdapter for TgiAdapter {
+    fn provider_kind(&self) -> ProviderKind {
+        ProviderKind::Tgi
+    }
+
+    fn supports_tools(&self) -> bool {
+        false
+    }
+
+    /// The one model the server runs, from `/info`
+    async fn discover_models(
+        &self,
+        endpoint: &ProviderEndpoint,
+    ) -> Result<Vec<DiscoveredModel>, AdapterError> {
+        let client = http::client(endpoint)?;
+        let url = http::endpoint_url(endpoint, "/info");
+
+        let mut request = client.get(&url);
+
+        for (key, value) in Self::request_headers(endpoint).await? {
+            request = request.header(key, value);
+        }
+
+        let resp = http::send(request, endpoint, "Failed to fetch model info").await?;
+
+        if !resp.status().is_success() {
+            return Err(Self::error_response(resp).await);
+        }
+
+        let info: TgiInfo = resp
+            .json()
+            .await
+            .map_err(|e| AdapterError::Http(format!("Failed to parse model info: {}", e)))?;
+
+        Ok(vec![DiscoveredModel {
+            id: format!("tgi/{}", info.model_id),
+            name: info.model_id,
+            provider_name: "tgi".to_string(),
+            provider_kind: ProviderKind::Tgi,
+            modalities: vec![Modality::Text],
+            capabilities: ModelCapabilities {
+                context_length: info.max_total_tokens,
+                ..ModelCapabilities::text_only().with_json()
+            },
+            created: None,
+        }])
+    }
+
+    async fn execute_chat(
+        &self,
+        ir: ChatRequestIR,
+        cancel: CancellationToken,
+    ) -> Result<Box<dyn futures_util::Stream<Item = StreamEvent> + Send + Unpin>, AdapterError>
+    {
+        let payload = Self::build_request_body(&ir)?;
+
+        let client = http::client(&ir.model.provider)?;
+        let route = if ir.stream { "/generate_stream" } else { "/generate" };
+        let url = http::endpoint_url(&ir.model.provider, route);
+
+        let mut request = http::json_body(client.post(&url), &payload, &ir.model.provider, &ir.model.model_id);
+
+        for (key, value) in Self::request_headers(&ir.model.provider).await? {
+            request = request.header(key, value);
+        }
+
+        let mut resp = http::send(request, &ir.model.provider, "Failed to send request").await?;
+        let idle_timeout = ir.model.provider.idle_stream_timeout();
+
+        if !resp.status().is_success() {
+            return Err(Self::error_response(resp).await);
+        }
+
+        if !ir.stream {
+            let response: TgiGenerateResponse =
+                http::response_json(resp, &ir.model.provider, &ir.model.model_id, "Failed to parse response").await?;
+            let mut events = Vec::new();
+            if !response.generated_text.is_empty() {
+                events.push(StreamEvent::TextDelta { content: response.generated_text.clone() });
+            }
+            events.extend(finish_events(response.generated_text, response.details));
+            return Ok(Box::new(futures_util::stream::iter(events)));
+        }
+
+        let s = async_stream::try_stream! {
+            let mut content = String::new();
+            let mut lines = body::LineBuffer::new();
+
+            loop {
+                let chunk = match body::next_chunk(&mut resp, &cancel, idle_timeout).await? {
+                    body::BodyRead::Chunk(chunk) => chunk,
+                    body::BodyRead::End => break,
+                    body::BodyRead::Cancelled => {
+                        drop(resp);
+                        yield body::cancelled_event();
+                        return;
+                    }
+                };
+
+                for line in lines.push(&chunk) {
+                    let json_str = match sse::parse_line(&line) {
+                        sse::SseLine::Data(data) => data,
+                        sse::SseLine::Comment(comment) => {
+                            if let Some(status) = sse::comment_status(comment) {
+                                yield status;
+                            }
+                            continue;
+                        }
+                        sse::SseLine::Other => continue,
+                    };
+
+                    let Ok(event) = serde_json::from_str::<TgiStreamEvent>(json_str) else {
+                        continue;
+                    };
+
+                    if let Some(message) = event.error {
+                        yield StreamEvent::Error {
+                            code: event.error_type.unwrap_or_else(|| "provider_error".to_string()),
+                            message,
+                        };
+                        return;
+                    }
+                    // Special tokens such as `</s>` aren't part of the text
+                    if let Some(token) = event.token.filter(|token| !token.special && !token.text.is_empty()) {
+                        content.push_str(&token.text);
+                        yield StreamEvent::TextDelta { content: token.text };
+                    }
+                    if let Some(details) = event.details {
+                        for event in finish_events(std::mem::take(&mut content), Some(details)) {
+                            yield event;
+                        }
+                        return;
+                    }
+                }
+            }
+
+            yield StreamEvent::Done;
+        };
+
+        Ok(Box::new(Box::pin(s.map(
+            |r: Result<StreamEvent, AdapterError>| match r {
+                Ok(ev) => ev,
+                Err(e) => body::stream_error_event(e),
+            },
+        ))))
+    }
+}
+
+impl TgiAdapter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Bearer authorization (for Inference Endpoints), with the endpoint's
+    /// own headers applied over it
+    async fn request_headers(endpoint: &ProviderEndpoint) -> Result<BTreeMap<String, String>, AdapterError> {
+        let mut headers = BTreeMap::new();
+        if let Some(token) = endpoint.bearer_token().await? {
+            headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+        }
+
+        for (key, value) in endpoint.headers().await? {
+            headers.retain(|existing, _| !existing.eq_ignore_ascii_case(&key));
+            headers.insert(key, value);
+        }
+        Ok(headers)
+    }
+
+    async fn error_response(resp: reqwest::Response) -> AdapterError {
+        let status = resp.status();
+        let text = resp
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+
+        match serde_json::from_str::<TgiErrorResponse>(&text) {
+            Ok(error_response) => AdapterError::Provider {
+                code: error_response
+                    .error_type
+                    .unwrap_or_else(|| status.as_u16().to_string()),
+                message: error_response.error,
+            },
+            Err(_) => AdapterError::Provider {
+                code: status.as_u16().to_string(),
+                message: text,
+            },
+        }
+    }
+
+    /// Build the outbound JSON body; the endpoint's static extensions and
+    /// then the request's own extensions are merged into `parameters`
+    pub fn build_request_body(ir: &ChatRequestIR) -> Result<serde_json::Value, AdapterError> {
+        let payload = Self::build_generate_request(ir);
+        let mut body = serde_json::to_value(&payload)
+            .map_err(|e| AdapterError::internal(format!("Failed to serialize request: {}", e)))?;
+
+        if let Some(serde_json::Value::Object(parameters)) = body.get_mut("parameters") {
+            for (key, value) in ir
+                .model
+                .provider
+                .extensions
+                .iter()
+                .chain(ir.provider_extensions.iter())
+            {
+                parameters.insert(key.clone(), value.clone());
+            }
+        }
+
+        Ok(body)
+    }
+
+    fn build_generate_request(ir: &ChatRequestIR) -> TgiGenerateRequest {
+        if !ir.tools.is_empty() {
+            tracing::warn!("Tools not supported by TGI adapter");
+        }
+        let template = ir.model.provider.chat_template(&ir.model.model_id);
+        let sampling = &ir.sampling;
+
+        let mut stop = templates::stop_tokens(&template);
+        for sequence in &sampling.stop {
+            if !stop.contains(sequence) {
+                stop.push(sequence.clone());
+            }
+        }
+
+        let grammar = match &ir.response_format {
+            None | Some(ResponseFormat::Text) => None,
+            Some(ResponseFormat::JsonObject) => Some(TgiGrammar {
+                r#type: "json".to_string(),
+                value: serde_json::json!({"type": "object"}),
+            }),
+            Some(ResponseFormat::JsonSchema { schema, .. }) => Some(TgiGrammar {
+                r#type: "json".to_string(),
+                value: schema.clone(),
+            }),
+        };
+
+        TgiGenerateRequest {
+            inputs: templates::render_prompt(&ir.messages, &template),
+            parameters: TgiParameters {
+                max_new_tokens: sampling.max_tokens,
+                // TGI rejects a temperature of 0 and a top_p of 1; both mean
+                // the default
+                temperature: sampling.temperature.filter(|t| *t > 0.0),
+                top_p: sampling.top_p.filter(|p| *p > 0.0 && *p < 1.0),
+                top_k: sampling.top_k.filter(|k| *k > 0),
+                typical_p: sampling.typical_p.filter(|p| *p > 0.0 && *p < 1.0),
+                repetition_penalty: sampling.repeat_penalty,
+                frequency_penalty: sampling.frequency_penalty,
+                seed: sampling.seed,
+                stop,
+                grammar,
+                details: true,
+                decoder_input_details: !ir.stream,
+                return_full_text: false,
+            },
+        }
+    }
+}
+
+/// `Tokens`, the final message and `Done` for a reply, from its details
+fn finish_events(content: String, details: Option<TgiDetails>) -> Vec<StreamEvent> {
+    let Some(details) = details else {
+        return vec![StreamEvent::Done];
+    };
+    let finish_reason = match details.finish_reason.as_str() {
+        "eos_token" | "stop_sequence" => "stop".to_string(),
+        other => other.to_string(),
+    };
+    vec![
+        StreamEvent::Tokens {
+            input: details.input_tokens(),
+            output: details.generated_tokens,
+        },
+        crate::stream::final_message(content, Vec::new(), Some(finish_reason)),
+        StreamEvent::Done,
+    ]
+}
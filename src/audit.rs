@@ -0,0 +1,443 @@
+//! Audit trail of chat requests
+//!
+//! The router hands every chat request it routes to an [`AuditSink`] once the
+//! request is over: the request IR, the output the client received, usage,
+//! latency, the provider that served it and the caller's API key name.
+//! Streams that fail or are abandoned are recorded with the output produced
+//! so far and an `error` or `cancelled` outcome. Requests rejected before
+//! reaching a provider are recorded too.
+//!
+//! Requests in [`PrivacyMode::NoStore`](crate::types::PrivacyMode::NoStore) are recorded without their content:
+//! the record keeps the model, provider, usage, cost, latency, outcome and
+//! metadata, but no messages, prediction or output.
+//!
+//! Recording never affects the request: sink errors are logged with
+//! `tracing::error!` and otherwise ignored. [`NoopAuditSink`] is the default;
+//! [`JsonlAuditSink`] appends one JSON object per line to daily files.
+
+use crate::adapter::AdapterError;
+use crate::error::EngineError;
+use crate::stream::{StreamEvent, ToolCallSummary};
+use crate::types::{ChatRequestIR, ContentPart};
+use async_trait::async_trait;
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Request metadata key naming the API key the caller authenticated with,
+/// set by whatever authenticates clients in front of the router
+pub const API_KEY_NAME_METADATA: &str = "api_key_name";
+
+const REDACTED: &str = "[redacted]";
+
+/// How a request ended
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Completed,
+    /// Failed before or during the stream; `output` holds what was produced
+    Error { code: String, message: String },
+    /// Cancelled or dropped by the client; `output` holds what was produced
+    Cancelled,
+}
+
+impl AuditOutcome {
+    /// The outcome of a request the router refused to route
+    pub(crate) fn from_error(error: &EngineError) -> Self {
+        let code = match error {
+            EngineError::Cancelled => return AuditOutcome::Cancelled,
+            EngineError::Adapter(AdapterError::Provider { code, .. }) => code.as_str(),
+            EngineError::ContentPolicy { .. } => "content_policy_violation",
+            EngineError::Overloaded { .. } => "server_overloaded",
+            EngineError::BudgetExceeded { .. } => "budget_exceeded",
+            EngineError::DeadlineExceeded(_) => "deadline_exceeded",
+            EngineError::Timeout => "timeout",
+            _ => "routing_error",
+        };
+        AuditOutcome::Error {
+            code: code.to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// One audited request
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Unix timestamp in seconds when the request was routed
+    pub timestamp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_name: Option<String>,
+    pub model_alias: String,
+    /// Provider (or pool member) that served the request
+    pub provider: String,
+    /// The request as routed, redacted per [`AuditRedaction`]
+    pub request: ChatRequestIR,
+    pub output: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCallSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<AuditUsage>,
+    /// Total cost in USD, when a pricing table priced the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    pub latency_ms: u64,
+    pub outcome: AuditOutcome,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+/// Receives a record for every routed chat request
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    async fn record(&self, record: &AuditRecord) -> std::io::Result<()>;
+}
+
+/// Records nothing
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopAuditSink;
+
+#[async_trait]
+impl AuditSink for NoopAuditSink {
+    async fn record(&self, _record: &AuditRecord) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// What to leave out of audit records. Endpoint credentials and header
+/// values are always redacted.
+#[derive(Clone, Debug, Default)]
+pub struct AuditRedaction {
+    /// Replace message text, media, tool arguments and results, and the
+    /// output with `[redacted]`
+    pub content: bool,
+    /// Request metadata keys whose values are replaced
+    pub metadata_keys: Vec<String>,
+}
+
+impl AuditRedaction {
+    fn request(&self, request: &ChatRequestIR) -> ChatRequestIR {
+        let mut request = request.clone();
+        request.endpoint_overrides = None;
+        for value in request.model.provider.extra_headers.values_mut() {
+            *value = REDACTED.to_string();
+        }
+        for value in request.model.provider.privacy_headers.values_mut() {
+            *value = REDACTED.to_string();
+        }
+        let provider = &mut request.model.provider;
+        provider.proxy_url = provider.proxy_url.as_deref().map(crate::types::secret::redact_url_password);
+        // Callbacks hold no values, and records with them couldn't be read back
+        request
+            .model
+            .provider
+            .header_providers
+            .retain(|provider| !matches!(provider, crate::types::HeaderProvider::Callback(_)));
+        for provider in &mut request.model.provider.header_providers {
+            if let crate::types::HeaderProvider::Static { headers } = provider {
+                for value in headers.values_mut() {
+                    *value = REDACTED.to_string();
+                }
+            }
+        }
+        for key in &self.metadata_keys {
+            if let Some(value) = request.metadata.get_mut(key) {
+                *value = REDACTED.to_string();
+            }
+        }
+        if request.privacy.is_no_store() {
+            request.messages.clear();
+            request.prediction = None;
+            request.extra_body.clear();
+            return request;
+        }
+        if self.content {
+            for part in request.messages.iter_mut().flat_map(|message| message.parts.iter_mut()) {
+                match part {
+                    ContentPart::Text(text)
+                    | ContentPart::ImageUrl { url: text, .. }
+                    | ContentPart::Audio { data: text, .. }
+                    | ContentPart::ToolResult { content: text, .. } => *text = REDACTED.to_string(),
+                    ContentPart::File { file_data: Some(data), .. } => *data = REDACTED.to_string(),
+                    ContentPart::ToolCall { arguments, .. } => *arguments = serde_json::json!(REDACTED),
+                    _ => {}
+                }
+            }
+        }
+        request
+    }
+
+    fn output(&self, record: &mut AuditRecord) {
+        if record.request.privacy.is_no_store() {
+            record.output.clear();
+            record.tool_calls.clear();
+        } else if self.content {
+            if !record.output.is_empty() {
+                record.output = REDACTED.to_string();
+            }
+            for call in &mut record.tool_calls {
+                call.args_json = serde_json::json!(REDACTED);
+            }
+        }
+    }
+}
+
+/// The sink and redaction a router audits with
+#[derive(Clone)]
+pub(crate) struct Auditor {
+    sink: Arc<dyn AuditSink>,
+    redaction: AuditRedaction,
+}
+
+impl Default for Auditor {
+    fn default() -> Self {
+        Self {
+            sink: Arc::new(NoopAuditSink),
+            redaction: AuditRedaction::default(),
+        }
+    }
+}
+
+impl Auditor {
+    pub(crate) fn with_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.sink = sink;
+        self
+    }
+
+    pub(crate) fn with_redaction(mut self, redaction: AuditRedaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Start auditing `request`, which is about to be routed
+    pub(crate) fn start(&self, request: &ChatRequestIR) -> AuditTrail {
+        let provider = request
+            .model
+            .alias
+            .split_once('/')
+            .map_or(request.model.alias.as_str(), |(provider, _)| provider);
+        AuditTrail {
+            auditor: self.clone(),
+            started: Instant::now(),
+            record: Some(AuditRecord {
+                timestamp: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0),
+                request_id: request.metadata.get("request_id").cloned(),
+                api_key_name: request.metadata.get(API_KEY_NAME_METADATA).cloned(),
+                model_alias: request.model.alias.clone(),
+                provider: provider.to_string(),
+                request: self.redaction.request(request),
+                output: String::new(),
+                tool_calls: Vec::new(),
+                usage: None,
+                cost_usd: None,
+                latency_ms: 0,
+                outcome: AuditOutcome::Cancelled,
+            }),
+            completed: false,
+        }
+    }
+}
+
+/// The audit record of one request in flight. Dropping it before
+/// [`finish`](Self::finish) records the request as cancelled, with the
+/// output seen so far.
+pub(crate) struct AuditTrail {
+    auditor: Auditor,
+    started: Instant,
+    record: Option<AuditRecord>,
+    /// A `FinalMessage` arrived; callers may stop reading after it
+    completed: bool,
+}
+
+impl AuditTrail {
+    fn observe(&mut self, event: &StreamEvent) {
+        let Some(record) = self.record.as_mut() else { return };
+        match event {
+            StreamEvent::TextDelta { content } => record.output.push_str(content),
+            StreamEvent::Tokens { input, output } => {
+                record.usage = Some(AuditUsage { input_tokens: *input, output_tokens: *output })
+            }
+            StreamEvent::Cost { total_usd, .. } => record.cost_usd = *total_usd,
+            StreamEvent::Status { state, detail: Some(endpoint) } if state == "endpoint" => {
+                record.provider = endpoint.clone()
+            }
+            StreamEvent::FinalMessage { content, tool_calls, .. } => {
+                record.output = content.clone();
+                record.tool_calls = tool_calls.clone();
+                self.completed = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Send the record with `outcome` to the sink in the background
+    pub(crate) fn finish(mut self, outcome: AuditOutcome) {
+        self.submit(outcome);
+    }
+
+    fn submit(&mut self, outcome: AuditOutcome) {
+        let Some(mut record) = self.record.take() else { return };
+        record.outcome = outcome;
+        record.latency_ms = self.started.elapsed().as_millis() as u64;
+        self.auditor.redaction.output(&mut record);
+        let sink = self.auditor.sink.clone();
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::error!(request_id = ?record.request_id, "No runtime to write the audit record on");
+            return;
+        };
+        runtime.spawn(async move {
+            if let Err(e) = sink.record(&record).await {
+                tracing::error!(request_id = ?record.request_id, error = %e, "Failed to write audit record");
+            }
+        });
+    }
+
+    /// Pass `events` through, recording the request when the stream ends
+    pub(crate) fn follow<S>(mut self, mut events: S) -> impl Stream<Item = StreamEvent> + Send
+    where
+        S: Stream<Item = StreamEvent> + Send + Unpin,
+    {
+        async_stream::stream! {
+            while let Some(event) = events.next().await {
+                self.observe(&event);
+                let outcome = match &event {
+                    StreamEvent::Error { code, .. } if code == "cancelled" => Some(AuditOutcome::Cancelled),
+                    StreamEvent::Error { code, message } => Some(AuditOutcome::Error {
+                        code: code.clone(),
+                        message: message.clone(),
+                    }),
+                    StreamEvent::Done => Some(AuditOutcome::Completed),
+                    _ => None,
+                };
+                if let Some(outcome) = outcome {
+                    self.submit(outcome);
+                }
+                yield event;
+            }
+            let outcome = if self.completed { AuditOutcome::Completed } else { AuditOutcome::Cancelled };
+            self.submit(outcome);
+        }
+    }
+}
+
+impl Drop for AuditTrail {
+    fn drop(&mut self) {
+        let outcome = if self.completed { AuditOutcome::Completed } else { AuditOutcome::Cancelled };
+        self.submit(outcome);
+    }
+}
+
+/// Appends records as JSON lines to `audit-YYYY-MM-DD.jsonl` files (UTC) in
+/// a directory, deleting files older than the retention period
+pub struct JsonlAuditSink {
+    dir: PathBuf,
+    retention: Option<Duration>,
+    /// The day of the open file and the file itself
+    file: tokio::sync::Mutex<Option<(u64, tokio::fs::File)>>,
+}
+
+impl JsonlAuditSink {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            retention: None,
+            file: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Delete daily files older than `retention` when a new day starts
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    pub fn dir(&self) -> &std::path::Path {
+        &self.dir
+    }
+
+    /// The file records for `day` (days since the Unix epoch) go to
+    pub fn file_for_day(&self, day: u64) -> PathBuf {
+        let (year, month, day) = civil_date(day);
+        self.dir.join(format!("audit-{:04}-{:02}-{:02}.jsonl", year, month, day))
+    }
+
+    async fn prune(&self, today: u64) -> std::io::Result<()> {
+        let Some(retention) = self.retention else { return Ok(()) };
+        let keep_days = retention.as_secs().div_ceil(86_400);
+        let Some(oldest) = today.checked_sub(keep_days) else { return Ok(()) };
+        let oldest = self.file_for_day(oldest);
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else { continue };
+            // Names sort by date, so comparing them compares days
+            let is_audit_file = name.starts_with("audit-") && name.ends_with(".jsonl");
+            if is_audit_file && entry.path() < oldest {
+                tokio::fs::remove_file(entry.path()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AuditSink for JsonlAuditSink {
+    async fn record(&self, record: &AuditRecord) -> std::io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let mut line = match serde_json::to_vec(record) {
+            Ok(line) => line,
+            // Keep the record without its request rather than lose it
+            Err(e) => {
+                tracing::warn!(request_id = ?record.request_id, error = %e, "Audit record's request can't be serialized");
+                let mut record = record.clone();
+                record.request = ChatRequestIR::default();
+                serde_json::to_vec(&record)?
+            }
+        };
+        line.push(b'\n');
+        let today = record.timestamp / 86_400;
+        let mut file = self.file.lock().await;
+        if file.as_ref().is_none_or(|(day, _)| *day != today) {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            let opened = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.file_for_day(today))
+                .await?;
+            *file = Some((today, opened));
+            if let Err(e) = self.prune(today).await {
+                tracing::warn!(dir = %self.dir.display(), error = %e, "Failed to prune old audit files");
+            }
+        }
+        let (_, file) = file.as_mut().expect("audit file opened above");
+        file.write_all(&line).await?;
+        file.flush().await
+    }
+}
+
+/// `(year, month, day)` of a day count since 1970-01-01
+pub(crate) fn civil_date(days: u64) -> (u64, u64, u64) {
+    // Howard Hinnant's days-to-civil algorithm, for dates after the epoch
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
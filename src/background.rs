@@ -0,0 +1,355 @@
+//! Background chat requests
+//!
+//! A background request runs detached from its caller, like the Responses
+//! API's `background: true`: [`BackgroundResponses::submit`] returns a
+//! [`ResponseHandle`] right away while a spawned task drives the request to
+//! completion. The handle reports the request's [`BackgroundStatus`], waits
+//! for its [`BackgroundResult`] and cancels it. Requests stay findable by id
+//! until their retention runs out after they end, which is what the OpenAI
+//! skin's `GET /v1/responses/{id}` polls.
+//!
+//! Any provider can serve a background request; the spawned task emulates
+//! background mode for those without one. OpenAI Responses providers run the
+//! request in their own background mode, polled by the adapter, and are told
+//! to stop it when the request is cancelled.
+
+use crate::error::EngineError;
+use crate::multiplex::FinalReply;
+use crate::stream::StreamEvent;
+use crate::types::ChatRequestIR;
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+/// How long a finished request stays findable by id unless
+/// [`BackgroundResponses::with_retention`] says otherwise (1 hour)
+pub const DEFAULT_BACKGROUND_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// Where a background request is, in the Responses API's terms
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackgroundStatus {
+    /// Waiting for a provider, e.g. in a provider queue or upstream
+    Queued,
+    InProgress,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl BackgroundStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackgroundStatus::Queued => "queued",
+            BackgroundStatus::InProgress => "in_progress",
+            BackgroundStatus::Completed => "completed",
+            BackgroundStatus::Failed => "failed",
+            BackgroundStatus::Cancelled => "cancelled",
+        }
+    }
+
+    /// Whether the request has ended
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, BackgroundStatus::Completed | BackgroundStatus::Failed | BackgroundStatus::Cancelled)
+    }
+}
+
+/// The reply of a completed background request
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BackgroundResult {
+    pub reply: FinalReply,
+    /// Citations and other notes on the reply (see [`StreamEvent::Annotation`])
+    pub annotations: Vec<serde_json::Value>,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+/// A background request's progress
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackgroundState {
+    pub status: BackgroundStatus,
+    /// Set once the request has completed
+    pub result: Option<BackgroundResult>,
+    /// The code and message of a failed request
+    pub error: Option<(String, String)>,
+    /// Unix timestamp in seconds when the request ended
+    pub completed_at: Option<u64>,
+}
+
+/// A request submitted with [`BackgroundResponses::submit`]. Clones refer to
+/// the same request; dropping them doesn't cancel it.
+#[derive(Clone)]
+pub struct ResponseHandle {
+    job: Arc<Job>,
+}
+
+struct Job {
+    id: String,
+    model: String,
+    owner: Option<String>,
+    created_at: u64,
+    cancel: CancellationToken,
+    state: watch::Sender<BackgroundState>,
+}
+
+impl ResponseHandle {
+    /// The request's id: its `request_id` metadata, or a new `resp_` id
+    pub fn id(&self) -> &str {
+        &self.job.id
+    }
+
+    /// The model alias the request was submitted for
+    pub fn model(&self) -> &str {
+        &self.job.model
+    }
+
+    /// The API key name that submitted the request, if any (see
+    /// [`crate::audit::API_KEY_NAME_METADATA`])
+    pub fn owner(&self) -> Option<&str> {
+        self.job.owner.as_deref()
+    }
+
+    /// Unix timestamp in seconds when the request was submitted
+    pub fn created_at(&self) -> u64 {
+        self.job.created_at
+    }
+
+    pub fn status(&self) -> BackgroundStatus {
+        self.job.state.borrow().status
+    }
+
+    pub fn state(&self) -> BackgroundState {
+        self.job.state.borrow().clone()
+    }
+
+    /// Wait for the request to end: its result once completed, the error it
+    /// failed with, or [`EngineError::Cancelled`]
+    pub async fn await_result(&self) -> Result<BackgroundResult, EngineError> {
+        let mut state = self.job.state.subscribe();
+        let state = state
+            .wait_for(|state| state.status.is_terminal())
+            .await
+            .map_err(|_| EngineError::Cancelled)?
+            .clone();
+        match (state.status, state.result, state.error) {
+            (BackgroundStatus::Completed, Some(result), _) => Ok(result),
+            (BackgroundStatus::Failed, _, Some((code, message))) => Err(EngineError::from_stream_error(code, message)),
+            _ => Err(EngineError::Cancelled),
+        }
+    }
+
+    /// Stop the request; a request that has already ended keeps its outcome
+    pub fn cancel(&self) {
+        self.job.finish(BackgroundStatus::Cancelled, None, None);
+        self.job.cancel.cancel();
+    }
+}
+
+impl std::fmt::Debug for ResponseHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseHandle")
+            .field("id", &self.job.id)
+            .field("model", &self.job.model)
+            .field("status", &self.status())
+            .finish()
+    }
+}
+
+impl Job {
+    /// End the request unless it has already ended
+    fn finish(&self, status: BackgroundStatus, result: Option<BackgroundResult>, error: Option<(String, String)>) {
+        self.state.send_if_modified(|state| {
+            if state.status.is_terminal() {
+                return false;
+            }
+            *state = BackgroundState {
+                status,
+                result,
+                error,
+                completed_at: Some(unix_now()),
+            };
+            true
+        });
+    }
+
+    /// Move a running request to `status`
+    fn progress(&self, status: BackgroundStatus) {
+        self.state.send_if_modified(|state| {
+            if state.status.is_terminal() || state.status == status {
+                return false;
+            }
+            state.status = status;
+            true
+        });
+    }
+}
+
+/// Background requests by id. Clones share them.
+#[derive(Clone)]
+pub struct BackgroundResponses {
+    jobs: Arc<Mutex<HashMap<String, ResponseHandle>>>,
+    retention: Duration,
+}
+
+impl Default for BackgroundResponses {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundResponses {
+    pub fn new() -> Self {
+        Self::with_retention(DEFAULT_BACKGROUND_RETENTION)
+    }
+
+    /// Keep finished requests findable for `retention`
+    pub fn with_retention(retention: Duration) -> Self {
+        Self {
+            jobs: Arc::default(),
+            retention,
+        }
+    }
+
+    /// Run `request` in a spawned task: `start` routes it with a token
+    /// cancelled by [`ResponseHandle::cancel`] or by `parent`, and the task
+    /// reads its events until the stream ends
+    pub fn submit<F, Fut, S>(&self, mut request: ChatRequestIR, parent: &CancellationToken, start: F) -> ResponseHandle
+    where
+        F: FnOnce(ChatRequestIR, CancellationToken) -> Fut,
+        Fut: Future<Output = Result<S, EngineError>> + Send + 'static,
+        S: Stream<Item = StreamEvent> + Send + Unpin + 'static,
+    {
+        request.background = true;
+        let id = request
+            .metadata
+            .get("request_id")
+            .cloned()
+            .unwrap_or_else(|| format!("resp_{}", uuid::Uuid::new_v4().simple()));
+        let (state, _) = watch::channel(BackgroundState {
+            status: BackgroundStatus::Queued,
+            result: None,
+            error: None,
+            completed_at: None,
+        });
+        let handle = ResponseHandle {
+            job: Arc::new(Job {
+                id: id.clone(),
+                model: request.model.alias.clone(),
+                owner: request.metadata.get(crate::audit::API_KEY_NAME_METADATA).cloned(),
+                created_at: unix_now(),
+                cancel: parent.child_token(),
+                state,
+            }),
+        };
+        let started = start(request, handle.job.cancel.clone());
+        tokio::spawn(run(handle.job.clone(), started));
+
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        self.prune(&mut jobs);
+        jobs.insert(id, handle.clone());
+        handle
+    }
+
+    /// The request with `id`, while it runs and for the retention after
+    pub fn get(&self, id: &str) -> Option<ResponseHandle> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        self.prune(&mut jobs);
+        jobs.get(id).cloned()
+    }
+
+    /// Number of requests still findable
+    pub fn len(&self) -> usize {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget the requests that ended longer than the retention ago
+    fn prune(&self, jobs: &mut HashMap<String, ResponseHandle>) {
+        let now = unix_now();
+        jobs.retain(|_, handle| {
+            handle
+                .job
+                .state
+                .borrow()
+                .completed_at
+                .is_none_or(|completed_at| now.saturating_sub(completed_at) < self.retention.as_secs())
+        });
+    }
+}
+
+/// Drive one request, recording its progress and outcome on `job`
+async fn run<Fut, S>(job: Arc<Job>, started: Fut)
+where
+    Fut: Future<Output = Result<S, EngineError>>,
+    S: Stream<Item = StreamEvent> + Unpin,
+{
+    let started = tokio::select! {
+        _ = job.cancel.cancelled() => return job.finish(BackgroundStatus::Cancelled, None, None),
+        started = started => started,
+    };
+    let mut events = match started {
+        Ok(events) => events,
+        Err(EngineError::Cancelled) => return job.finish(BackgroundStatus::Cancelled, None, None),
+        Err(e) => {
+            let code = match crate::audit::AuditOutcome::from_error(&e) {
+                crate::audit::AuditOutcome::Error { code, .. } => code,
+                _ => "routing_error".to_string(),
+            };
+            return job.finish(BackgroundStatus::Failed, None, Some((code, e.to_string())));
+        }
+    };
+    job.progress(BackgroundStatus::InProgress);
+
+    // Adapters end their streams once cancelled, after stopping the upstream
+    let mut result = BackgroundResult::default();
+    let mut deltas = String::new();
+    let mut final_message = None;
+    let mut refusal: Option<String> = None;
+    while let Some(event) = events.next().await {
+        match event {
+            StreamEvent::TextDelta { content } => deltas.push_str(&content),
+            StreamEvent::Annotation { annotation } => result.annotations.push(annotation),
+            StreamEvent::Refusal { text } => refusal.get_or_insert_with(String::new).push_str(&text),
+            StreamEvent::FinalMessage { content, tool_calls, finish_reason } => {
+                final_message = Some(FinalReply { content, tool_calls, finish_reason, refusal: None });
+            }
+            StreamEvent::Tokens { input, output } => {
+                result.input_tokens = input;
+                result.output_tokens = output;
+            }
+            StreamEvent::Status { state, .. } if state == "queued" => job.progress(BackgroundStatus::Queued),
+            StreamEvent::Status { state, .. } if state == "in_progress" => job.progress(BackgroundStatus::InProgress),
+            StreamEvent::Error { code, .. } if code == "cancelled" => {
+                return job.finish(BackgroundStatus::Cancelled, None, None);
+            }
+            StreamEvent::Error { code, message } => {
+                return job.finish(BackgroundStatus::Failed, None, Some((code, message)));
+            }
+            _ => {}
+        }
+    }
+    if job.cancel.is_cancelled() {
+        return job.finish(BackgroundStatus::Cancelled, None, None);
+    }
+    result.reply = final_message.unwrap_or(FinalReply {
+        content: deltas,
+        ..Default::default()
+    });
+    result.reply.refusal = refusal;
+    job.finish(BackgroundStatus::Completed, Some(result), None);
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,74 @@
+//! Flow control between providers and slow clients
+//!
+//! Streamed responses are pull-driven: the SSE body polls the event stream,
+//! which polls the provider's response, so a client reading slowly slows
+//! down reading from the provider and nothing piles up in between. Where
+//! events are buffered anyway (a [`SharedChatStream`](crate::multiplex::SharedChatStream)
+//! fanned out to several subscribers, a resumable stream kept for
+//! reconnecting clients, a WebSocket carrying several requests), the buffer
+//! has a high-water mark, and a [`SlowClientPolicy`] says what happens to a
+//! client that falls that far behind.
+
+use crate::stream::StreamEvent;
+
+/// Error code of streams ended because their client fell too far behind
+pub const CLIENT_TOO_SLOW: &str = "client_too_slow";
+
+/// Events a client may fall behind by default
+pub const DEFAULT_HIGH_WATER_MARK: usize = 256;
+
+/// What happens to a client that falls a buffer's high-water mark behind
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SlowClientPolicy {
+    /// Drop the oldest events the client hasn't read and tell it how many it
+    /// missed; the provider is read at full speed
+    #[default]
+    Skip,
+    /// Stop reading from the provider until the client catches up
+    Pause,
+    /// End the client's stream with a [`CLIENT_TOO_SLOW`] error
+    Disconnect,
+}
+
+/// A buffer's high-water mark and what happens when a client reaches it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BackPressure {
+    /// Events a client may be behind, at least 1
+    pub high_water_mark: usize,
+    pub policy: SlowClientPolicy,
+}
+
+impl Default for BackPressure {
+    fn default() -> Self {
+        Self::skip(DEFAULT_HIGH_WATER_MARK)
+    }
+}
+
+impl BackPressure {
+    pub fn skip(high_water_mark: usize) -> Self {
+        Self::new(high_water_mark, SlowClientPolicy::Skip)
+    }
+
+    pub fn pause(high_water_mark: usize) -> Self {
+        Self::new(high_water_mark, SlowClientPolicy::Pause)
+    }
+
+    pub fn disconnect(high_water_mark: usize) -> Self {
+        Self::new(high_water_mark, SlowClientPolicy::Disconnect)
+    }
+
+    fn new(high_water_mark: usize, policy: SlowClientPolicy) -> Self {
+        Self {
+            high_water_mark: high_water_mark.max(1),
+            policy,
+        }
+    }
+}
+
+/// The event ending the stream of a client `behind` events behind
+pub fn too_slow(behind: usize) -> StreamEvent {
+    StreamEvent::Error {
+        code: CLIENT_TOO_SLOW.to_string(),
+        message: format!("Client fell {} events behind the stream", behind),
+    }
+}
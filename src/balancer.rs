@@ -0,0 +1,411 @@
+//! Load balancing across providers that serve the same models
+//!
+//! Providers configured with the same [`ProviderConfig::pool`] form a pool;
+//! discovery registers `{pool}/{model}` aliases for the models they share.
+//! Requests for a pool alias are spread across its members by the pool's
+//! [`BalancePolicy`] at routing time. Endpoints whose recent failure rate
+//! crosses the [`HealthPolicy`] threshold sit out of the rotation for a
+//! cooldown period.
+//!
+//! With [sticky routing](LoadBalancer::with_sticky_routing), requests sharing
+//! a cache key or prompt prefix go to the same member (rendezvous hashing), so
+//! backends like vLLM and llama.cpp can reuse their KV cache.
+//!
+//! [`ProviderConfig::pool`]: crate::types::ProviderConfig::pool
+
+use crate::adapter::AdapterError;
+use crate::types::{Message, ProviderEndpoint};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BalancePolicy {
+    #[default]
+    RoundRobin,
+    /// The member with the fewest requests in flight; ties go round-robin
+    LeastInFlight,
+    /// Smooth weighted round-robin by [`ProviderConfig::weight`](crate::types::ProviderConfig::weight)
+    Weighted,
+}
+
+impl BalancePolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BalancePolicy::RoundRobin => "round_robin",
+            BalancePolicy::LeastInFlight => "least_in_flight",
+            BalancePolicy::Weighted => "weighted",
+        }
+    }
+}
+
+/// When an endpoint is taken out of rotation
+#[derive(Clone, Debug)]
+pub struct HealthPolicy {
+    /// Number of recent requests considered
+    pub window: usize,
+    /// Minimum recent requests before an endpoint can be marked unhealthy
+    pub min_samples: usize,
+    /// Failure ratio within the window that marks an endpoint unhealthy
+    pub failure_ratio: f64,
+    /// How long an unhealthy endpoint stays out of rotation
+    pub cooldown: Duration,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            window: 20,
+            min_samples: 5,
+            failure_ratio: 0.5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A provider serving a pool alias
+#[derive(Clone, Debug)]
+pub struct PoolMember {
+    pub provider: String,
+    pub endpoint: ProviderEndpoint,
+    pub weight: u32,
+}
+
+/// Counters for one endpoint, keyed by provider name in [`LoadBalancer::stats`]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct EndpointStats {
+    pub in_flight: usize,
+    pub requests: u64,
+    pub failures: u64,
+    pub healthy: bool,
+}
+
+#[derive(Default)]
+struct EndpointState {
+    in_flight: usize,
+    requests: u64,
+    failures: u64,
+    recent: VecDeque<bool>,
+    unhealthy_until: Option<Instant>,
+}
+
+impl EndpointState {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until.is_none_or(|until| now >= until)
+    }
+}
+
+struct Pool {
+    name: String,
+    members: Vec<PoolMember>,
+    next: usize,
+    current_weights: Vec<i64>,
+}
+
+#[derive(Default)]
+struct BalancerState {
+    pools: HashMap<String, Pool>,
+    endpoints: HashMap<String, EndpointState>,
+}
+
+/// Pool membership and per-endpoint health shared by clones of a router
+#[derive(Clone, Default)]
+pub struct LoadBalancer {
+    default_policy: BalancePolicy,
+    policies: HashMap<String, BalancePolicy>,
+    health: HealthPolicy,
+    sticky_prefix: Option<usize>,
+    state: Arc<Mutex<BalancerState>>,
+}
+
+/// The member picked for a request
+pub struct PoolSelection {
+    pub member: PoolMember,
+    /// `"sticky"` or the [`BalancePolicy`] that made the choice
+    pub routing: &'static str,
+    pub guard: EndpointGuard,
+}
+
+impl LoadBalancer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Policy for pools without their own
+    pub fn with_default_policy(mut self, policy: BalancePolicy) -> Self {
+        self.default_policy = policy;
+        self
+    }
+
+    /// Policy for one pool, by pool name
+    pub fn with_pool_policy(mut self, pool: impl Into<String>, policy: BalancePolicy) -> Self {
+        self.policies.insert(pool.into(), policy);
+        self
+    }
+
+    pub fn with_health_policy(mut self, health: HealthPolicy) -> Self {
+        self.health = health;
+        self
+    }
+
+    /// Route requests with the same `cache_key` — or, without one, the same
+    /// first `prefix_messages` messages — to the same member while it is healthy
+    pub fn with_sticky_routing(mut self, prefix_messages: usize) -> Self {
+        self.sticky_prefix = Some(prefix_messages);
+        self
+    }
+
+    /// The sticky routing key for a request, if sticky routing is enabled
+    pub fn sticky_key(&self, cache_key: Option<&str>, messages: &[Message]) -> Option<String> {
+        let prefix = self.sticky_prefix?;
+        if let Some(key) = cache_key {
+            return Some(key.to_string());
+        }
+        if messages.is_empty() {
+            return None;
+        }
+        Some(prefix_key(&messages[..prefix.min(messages.len())]))
+    }
+
+    /// Replace the pool aliases (`{pool}/{model}`) and their members
+    pub fn set_pools(&self, pools: BTreeMap<String, (String, Vec<PoolMember>)>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.pools = pools
+            .into_iter()
+            .map(|(alias, (name, members))| {
+                let current_weights = vec![0; members.len()];
+                (alias, Pool { name, members, next: 0, current_weights })
+            })
+            .collect();
+    }
+
+    /// Whether `alias` is served by a pool
+    pub fn is_pooled(&self, alias: &str) -> bool {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.pools.contains_key(alias)
+    }
+
+    /// Per-endpoint counters, keyed by provider name
+    pub fn stats(&self) -> BTreeMap<String, EndpointStats> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        state
+            .endpoints
+            .iter()
+            .map(|(provider, endpoint)| {
+                (
+                    provider.clone(),
+                    EndpointStats {
+                        in_flight: endpoint.in_flight,
+                        requests: endpoint.requests,
+                        failures: endpoint.failures,
+                        healthy: endpoint.is_healthy(now),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Pick a member for a pool alias and count the request as in flight.
+    /// With a `sticky_key`, the key's rendezvous member wins unless it is
+    /// unhealthy. Returns `None` for aliases that aren't pooled.
+    pub fn select(&self, alias: &str, sticky_key: Option<&str>) -> Option<PoolSelection> {
+        let mut guard = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let state = &mut *guard;
+        let pool = state.pools.get_mut(alias)?;
+        if pool.members.is_empty() {
+            return None;
+        }
+        let policy = self.policies.get(&pool.name).copied().unwrap_or(self.default_policy);
+
+        // Unhealthy members sit out unless the whole pool is unhealthy
+        let now = Instant::now();
+        let mut candidates: Vec<usize> = (0..pool.members.len())
+            .filter(|&i| {
+                state
+                    .endpoints
+                    .get(&pool.members[i].provider)
+                    .is_none_or(|e| e.is_healthy(now))
+            })
+            .collect();
+        if candidates.is_empty() {
+            candidates = (0..pool.members.len()).collect();
+        }
+
+        let in_flight = |i: usize| {
+            state
+                .endpoints
+                .get(&pool.members[i].provider)
+                .map_or(0, |e| e.in_flight)
+        };
+        let sticky = sticky_key
+            .and_then(|key| rendezvous(key, &pool.members))
+            .filter(|i| candidates.contains(i));
+        let routing = if sticky.is_some() { "sticky" } else { policy.as_str() };
+        let chosen = match (sticky, policy) {
+            (Some(chosen), _) => chosen,
+            (None, BalancePolicy::RoundRobin) => {
+                let chosen = candidates[pool.next % candidates.len()];
+                pool.next = pool.next.wrapping_add(1);
+                chosen
+            }
+            (None, BalancePolicy::LeastInFlight) => {
+                let least = candidates.iter().map(|&i| in_flight(i)).min().unwrap_or(0);
+                let tied: Vec<usize> = candidates.iter().copied().filter(|&i| in_flight(i) == least).collect();
+                let chosen = tied[pool.next % tied.len()];
+                pool.next = pool.next.wrapping_add(1);
+                chosen
+            }
+            (None, BalancePolicy::Weighted) => {
+                let total: i64 = candidates.iter().map(|&i| pool.members[i].weight as i64).sum();
+                for &i in &candidates {
+                    pool.current_weights[i] += pool.members[i].weight as i64;
+                }
+                let chosen = candidates
+                    .iter()
+                    .copied()
+                    .max_by_key(|&i| (pool.current_weights[i], std::cmp::Reverse(i)))
+                    .unwrap_or(candidates[0]);
+                pool.current_weights[chosen] -= total;
+                chosen
+            }
+        };
+
+        let member = pool.members[chosen].clone();
+        let endpoint = state.endpoints.entry(member.provider.clone()).or_default();
+        endpoint.in_flight += 1;
+        endpoint.requests += 1;
+        let guard = EndpointGuard {
+            state: self.state.clone(),
+            health: self.health.clone(),
+            provider: member.provider.clone(),
+            finished: false,
+        };
+        Some(PoolSelection { member, routing, guard })
+    }
+}
+
+/// FNV-1a; unlike `DefaultHasher`, stable across processes and releases
+fn stable_hash(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in *part {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        // Separator so ("ab", "c") and ("a", "bc") differ
+        hash ^= 0xff;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Routing key for a prompt prefix: a hash of the messages' roles and content
+pub fn prefix_key(messages: &[Message]) -> String {
+    let encoded = serde_json::to_vec(messages).unwrap_or_default();
+    format!("prefix:{:016x}", stable_hash(&[&encoded]))
+}
+
+/// Weighted rendezvous (highest random weight) hashing: the index of the
+/// member `key` maps to. Adding or removing a member only moves the keys
+/// that map to that member.
+pub fn rendezvous(key: &str, members: &[PoolMember]) -> Option<usize> {
+    members
+        .iter()
+        .enumerate()
+        .filter(|(_, member)| member.weight > 0)
+        .map(|(i, member)| {
+            let hash = stable_hash(&[key.as_bytes(), member.provider.as_bytes()]);
+            // Map to (0, 1) and scale so heavier members win proportionally more keys
+            let unit = ((hash >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+            (i, member.weight as f64 / -unit.ln())
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+/// Whether an error code points at the endpoint rather than the request:
+/// HTTP 5xx and 429 statuses and transport-level stream failures
+pub fn is_endpoint_failure(code: &str) -> bool {
+    match code.parse::<u16>() {
+        Ok(status) => status >= 500 || status == 429,
+        Err(_) => matches!(
+            code,
+            "stream_error" | "http_error" | "timeout" | "connect_timeout" | "first_byte_timeout" | "stream_stalled"
+        ),
+    }
+}
+
+impl AdapterError {
+    /// See [`is_endpoint_failure`]
+    pub fn is_endpoint_failure(&self) -> bool {
+        match self {
+            AdapterError::Http(_) | AdapterError::Timeout => true,
+            AdapterError::Provider { code, .. } => is_endpoint_failure(code),
+            _ => false,
+        }
+    }
+}
+
+/// Tracks one in-flight request; dropping it without [`finish`](Self::finish)
+/// (e.g. when the client goes away) records no outcome.
+pub struct EndpointGuard {
+    state: Arc<Mutex<BalancerState>>,
+    health: HealthPolicy,
+    provider: String,
+    finished: bool,
+}
+
+impl EndpointGuard {
+    pub fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    /// Record the request's outcome for the endpoint's health
+    pub fn finish(mut self, success: bool) {
+        self.finished = true;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(endpoint) = state.endpoints.get_mut(&self.provider) else {
+            return;
+        };
+        endpoint.in_flight = endpoint.in_flight.saturating_sub(1);
+        if !success {
+            endpoint.failures += 1;
+        }
+        endpoint.recent.push_back(success);
+        while endpoint.recent.len() > self.health.window {
+            endpoint.recent.pop_front();
+        }
+
+        let failures = endpoint.recent.iter().filter(|ok| !**ok).count();
+        let samples = endpoint.recent.len();
+        if samples >= self.health.min_samples
+            && failures as f64 >= self.health.failure_ratio * samples as f64
+        {
+            tracing::warn!(
+                provider = %self.provider,
+                failures,
+                samples,
+                cooldown_secs = self.health.cooldown.as_secs(),
+                "Taking endpoint out of rotation"
+            );
+            endpoint.unhealthy_until = Some(Instant::now() + self.health.cooldown);
+            // Start over once the cooldown ends
+            endpoint.recent.clear();
+        }
+    }
+}
+
+impl Drop for EndpointGuard {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(endpoint) = state.endpoints.get_mut(&self.provider) {
+            endpoint.in_flight = endpoint.in_flight.saturating_sub(1);
+        }
+    }
+}
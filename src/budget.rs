@@ -0,0 +1,376 @@
+//! Spending budgets per API key
+//!
+//! A [`BudgetTracker`] gives API keys a spending limit per UTC day or month.
+//! The router adds the cost of every priced request (see [`crate::pricing`])
+//! to the spend of the key named by the request's
+//! [`API_KEY_NAME_METADATA`](crate::audit::API_KEY_NAME_METADATA), and
+//! rejects requests of keys whose spend reached their limit with
+//! [`EngineError::BudgetExceeded`] until the window resets. Keys past their
+//! `warn_at` share of the limit are still served; the HTTP skins flag their
+//! responses with the [`BUDGET_WARNING_HEADER`].
+//!
+//! Budgets and spend survive restarts when the tracker has a
+//! [`BudgetStore`]; [`JsonFileBudgetStore`] keeps them in a JSON snapshot
+//! file that is rewritten after every change.
+
+use crate::error::EngineError;
+use crate::stream::StreamEvent;
+use async_trait::async_trait;
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Response header set while a key is over its warning threshold
+pub const BUDGET_WARNING_HEADER: &str = "x-budget-warning";
+
+/// The API key a request authenticated with, as a request extension.
+/// Authentication layers insert it so the HTTP skins can attribute spend.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiKeyName(pub String);
+
+/// How long spend accumulates before it starts over
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetWindow {
+    /// UTC calendar days
+    Daily,
+    /// UTC calendar months
+    #[default]
+    Monthly,
+}
+
+impl BudgetWindow {
+    /// Label of the window containing `now` (Unix seconds), e.g. `2025-06-01`
+    /// or `2025-06`, and the Unix time it ends at
+    pub fn period(&self, now: u64) -> (String, u64) {
+        let today = now / 86_400;
+        let (year, month, day) = crate::audit::civil_date(today);
+        match self {
+            BudgetWindow::Daily => (format!("{:04}-{:02}-{:02}", year, month, day), (today + 1) * 86_400),
+            BudgetWindow::Monthly => {
+                let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+                (
+                    format!("{:04}-{:02}", year, month),
+                    days_from_civil(next_year, next_month, 1) * 86_400,
+                )
+            }
+        }
+    }
+}
+
+/// Days since 1970-01-01 of a date after the epoch; inverse of `civil_date`
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// A key's spending limit
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    pub limit_usd: f64,
+    #[serde(default)]
+    pub window: BudgetWindow,
+    /// Share of the limit (e.g. `0.8`) past which responses carry a warning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warn_at: Option<f64>,
+}
+
+impl Budget {
+    pub fn daily(limit_usd: f64) -> Self {
+        Self {
+            limit_usd,
+            window: BudgetWindow::Daily,
+            warn_at: None,
+        }
+    }
+
+    pub fn monthly(limit_usd: f64) -> Self {
+        Self {
+            limit_usd,
+            window: BudgetWindow::Monthly,
+            warn_at: None,
+        }
+    }
+
+    pub fn warn_at(mut self, share: f64) -> Self {
+        self.warn_at = Some(share);
+        self
+    }
+}
+
+/// What a key spent in one window
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeySpend {
+    /// Label of the window the spend belongs to (see [`BudgetWindow::period`])
+    pub window: String,
+    pub spent_usd: f64,
+}
+
+/// Budgets and spend, as persisted by a [`BudgetStore`]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetSnapshot {
+    #[serde(default)]
+    pub budgets: BTreeMap<String, Budget>,
+    #[serde(default)]
+    pub spend: BTreeMap<String, KeySpend>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetState {
+    Ok,
+    /// Past the warning threshold, still served
+    Warning,
+    /// Requests are rejected until the window resets
+    Exceeded,
+}
+
+/// A key's budget and its spend in the current window
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BudgetStatus {
+    pub key: String,
+    pub limit_usd: f64,
+    pub spent_usd: f64,
+    pub window: String,
+    /// Unix time the window ends and spend starts over
+    pub resets_at: u64,
+    pub state: BudgetState,
+}
+
+/// Keeps budgets and spend across restarts
+#[async_trait]
+pub trait BudgetStore: Send + Sync {
+    /// The last saved snapshot; empty when nothing was saved yet
+    async fn load(&self) -> std::io::Result<BudgetSnapshot>;
+    async fn save(&self, snapshot: &BudgetSnapshot) -> std::io::Result<()>;
+}
+
+/// Stores the snapshot as a JSON file, replaced atomically on every save
+pub struct JsonFileBudgetStore {
+    path: PathBuf,
+}
+
+impl JsonFileBudgetStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+}
+
+#[async_trait]
+impl BudgetStore for JsonFileBudgetStore {
+    async fn load(&self) -> std::io::Result<BudgetSnapshot> {
+        match tokio::fs::read(&self.path).await {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BudgetSnapshot::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn save(&self, snapshot: &BudgetSnapshot) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let temp = self.path.with_extension("tmp");
+        tokio::fs::write(&temp, serde_json::to_vec_pretty(snapshot)?).await?;
+        tokio::fs::rename(&temp, &self.path).await
+    }
+}
+
+/// Budgets and spend by API key name. Clones share their state.
+#[derive(Clone, Default)]
+pub struct BudgetTracker {
+    state: Arc<Mutex<BudgetSnapshot>>,
+    store: Option<Arc<dyn BudgetStore>>,
+    /// Serializes saves so the last one written holds the latest state
+    saving: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl BudgetTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_budget(self, key: impl Into<String>, budget: Budget) -> Self {
+        self.lock().budgets.insert(key.into(), budget);
+        self
+    }
+
+    /// Persist budgets and spend in `store`; call [`restore`](Self::restore)
+    /// to pick up what it saved before
+    pub fn with_store(mut self, store: Arc<dyn BudgetStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Load the store's snapshot. Budgets set in code win over saved ones;
+    /// saved spend is added to the spend recorded since startup.
+    pub async fn restore(&self) -> std::io::Result<()> {
+        let Some(store) = &self.store else { return Ok(()) };
+        let saved = store.load().await?;
+        let mut state = self.lock();
+        for (key, budget) in saved.budgets {
+            state.budgets.entry(key).or_insert(budget);
+        }
+        for (key, saved) in saved.spend {
+            match state.spend.get_mut(&key) {
+                Some(spend) if spend.window == saved.window => spend.spent_usd += saved.spent_usd,
+                Some(_) => {}
+                None => {
+                    state.spend.insert(key, saved);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BudgetSnapshot> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Set or remove `key`'s budget
+    pub fn set_budget(&self, key: impl Into<String>, budget: Option<Budget>) {
+        let key = key.into();
+        {
+            let mut state = self.lock();
+            match budget {
+                Some(budget) => state.budgets.insert(key, budget),
+                None => state.budgets.remove(&key),
+            };
+        }
+        self.persist();
+    }
+
+    pub fn budget(&self, key: &str) -> Option<Budget> {
+        self.lock().budgets.get(key).copied()
+    }
+
+    /// `key`'s budget and spend now, or `None` when it has no budget
+    pub fn status(&self, key: &str) -> Option<BudgetStatus> {
+        let state = self.lock();
+        let budget = state.budgets.get(key)?;
+        Some(status_of(key, budget, state.spend.get(key), now()))
+    }
+
+    /// Status of every key with a budget
+    pub fn statuses(&self) -> Vec<BudgetStatus> {
+        let state = self.lock();
+        let now = now();
+        state
+            .budgets
+            .iter()
+            .map(|(key, budget)| status_of(key, budget, state.spend.get(key), now))
+            .collect()
+    }
+
+    /// `key`'s status, or [`EngineError::BudgetExceeded`] once its spend
+    /// reached the limit
+    pub fn check(&self, key: &str) -> Result<Option<BudgetStatus>, EngineError> {
+        match self.status(key) {
+            Some(status) if status.state == BudgetState::Exceeded => Err(EngineError::BudgetExceeded {
+                key: status.key,
+                limit_usd: status.limit_usd,
+                spent_usd: status.spent_usd,
+                resets_at: status.resets_at,
+            }),
+            status => Ok(status),
+        }
+    }
+
+    /// Add `cost_usd` to `key`'s spend in the current window
+    pub fn record(&self, key: &str, cost_usd: f64) {
+        {
+            let mut state = self.lock();
+            let window = state.budgets.get(key).map_or(BudgetWindow::default(), |budget| budget.window);
+            let (label, _) = window.period(now());
+            let spend = state.spend.entry(key.to_string()).or_insert_with(|| KeySpend {
+                window: label.clone(),
+                spent_usd: 0.0,
+            });
+            if spend.window != label {
+                *spend = KeySpend { window: label, spent_usd: 0.0 };
+            }
+            spend.spent_usd += cost_usd;
+        }
+        self.persist();
+    }
+
+    /// Forget `key`'s spend in the current window, returning its new status
+    pub fn reset(&self, key: &str) -> Option<BudgetStatus> {
+        self.lock().spend.remove(key);
+        self.persist();
+        self.status(key)
+    }
+
+    pub fn snapshot(&self) -> BudgetSnapshot {
+        self.lock().clone()
+    }
+
+    /// Save the current state in the background; failures are logged
+    fn persist(&self) {
+        let Some(store) = self.store.clone() else { return };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("Budget spend not saved: no async runtime");
+            return;
+        };
+        let tracker = self.clone();
+        runtime.spawn(async move {
+            let _saving = tracker.saving.lock().await;
+            if let Err(e) = store.save(&tracker.snapshot()).await {
+                tracing::error!(error = %e, "Failed to save budget spend");
+            }
+        });
+    }
+}
+
+fn status_of(key: &str, budget: &Budget, spend: Option<&KeySpend>, now: u64) -> BudgetStatus {
+    let (window, resets_at) = budget.window.period(now);
+    let spent_usd = spend.filter(|spend| spend.window == window).map_or(0.0, |spend| spend.spent_usd);
+    let state = if spent_usd >= budget.limit_usd {
+        BudgetState::Exceeded
+    } else if budget.warn_at.is_some_and(|share| spent_usd >= budget.limit_usd * share) {
+        BudgetState::Warning
+    } else {
+        BudgetState::Ok
+    };
+    BudgetStatus {
+        key: key.to_string(),
+        limit_usd: budget.limit_usd,
+        spent_usd,
+        window,
+        resets_at,
+        state,
+    }
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Pass `events` through, adding the total of each `Cost` event to `key`'s spend
+pub(crate) fn record_spend<S>(tracker: BudgetTracker, key: String, mut events: S) -> impl Stream<Item = StreamEvent> + Send
+where
+    S: Stream<Item = StreamEvent> + Send + Unpin,
+{
+    async_stream::stream! {
+        while let Some(event) = events.next().await {
+            if let StreamEvent::Cost { total_usd: Some(total), .. } = &event {
+                tracker.record(&key, *total);
+            }
+            yield event;
+        }
+    }
+}
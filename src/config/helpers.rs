@@ -5,21 +5,29 @@ use std::collections::BTreeMap;
 pub fn create_endpoint_from_config(
     provider: &crate::config::TestProviderConfig,
 ) -> ProviderEndpoint {
-    let kind = match provider.provider_type.as_str() {
-        "Ollama" => ProviderKind::Ollama,
-        "OpenAI" => ProviderKind::OpenAI,
-        "OpenAICompat" => ProviderKind::OpenAICompat,
-        "Anthropic" => ProviderKind::Anthropic,
-        "Google" => ProviderKind::Google,
-        _ => ProviderKind::Ollama, // fallback
+    let (kind, compat_profile) = match provider.provider_type.as_str() {
+        "Groq" => (ProviderKind::OpenAICompat, CompatProfile::Groq),
+        "XAI" => (ProviderKind::OpenAICompat, CompatProfile::XAI),
+        "DeepSeek" => (ProviderKind::OpenAICompat, CompatProfile::DeepSeek),
+        "VLLM" => (ProviderKind::OpenAICompat, CompatProfile::VLLM),
+        other => match other.parse::<ProviderKind>() {
+            // Explicit `custom:<name>` kinds are served by registered adapter factories
+            Ok(ProviderKind::Custom(name)) if other.to_ascii_lowercase().starts_with("custom:") => {
+                (ProviderKind::Custom(name), CompatProfile::Generic)
+            }
+            Ok(ProviderKind::Custom(_)) | Err(_) => (ProviderKind::Ollama, CompatProfile::Generic), // fallback
+            Ok(kind) => (kind, CompatProfile::Generic),
+        },
     };
 
     ProviderEndpoint {
         kind,
         base_url: provider.base_url.clone(),
-        api_key: provider.api_key.clone(),
+        api_key: provider.api_key.clone().map(Into::into),
         extra_headers: BTreeMap::new(),
         timeout: provider.timeout.map(|t| t as u64),
+        compat_profile,
+        ..Default::default()
     }
 }
 
@@ -55,6 +63,14 @@ pub fn create_test_request(
         prediction: None,
         safety_identifier: None,
         cache_key: None,
+        provider_extensions: serde_json::Map::new(),
+        extra_body: serde_json::Map::new(),
+        reasoning: None,
+        store: None,
+        privacy: PrivacyMode::Default,
+        request_metadata: std::collections::HashMap::new(),
+        background: false,
+        endpoint_overrides: None,
     }
 }
 
@@ -70,7 +86,7 @@ pub fn should_run_live_tests() -> bool {
 /// Helper function to check if a provider is enabled and configured
 pub fn is_provider_enabled(provider_name: &str) -> bool {
     if let Ok(config) = crate::config::TestConfig::load() {
-        config.get_provider(provider_name).map_or(false, |p| {
+        config.get_provider(provider_name).is_some_and(|p| {
             p.enabled && p.api_key.is_some() || p.name == "ollama_local"
         })
     } else {
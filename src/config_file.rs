@@ -0,0 +1,777 @@
+//! Server configuration files
+//!
+//! A [`ConfigFile`] is a JSON document listing providers, model aliases and
+//! defaults:
+//!
+//! ```json
+//! {
+//!   "providers": [
+//!     {
+//!       "name": "openai",
+//!       "enabled": true,
+//!       "endpoint": { "kind": "openai", "api_key": "${OPENAI_API_KEY}", "timeout": 60000 }
+//!     }
+//!   ],
+//!   "model_aliases": { "smart": "openai/gpt-4o" },
+//!   "default_model": "smart"
+//! }
+//! ```
+//!
+//! `chat_templates` gives prompt templates by model alias for providers that
+//! take a single prompt, e.g. `{ "local": "chatml" }` (see
+//! [`crate::templates`]).
+//!
+//! A `passthrough` array lists gateway paths proxied to a provider as they
+//! are (see [`crate::passthrough`]). `startup_check` (`off`, `warn`,
+//! `disable` or `refuse`) verifies the providers before the server starts,
+//! asking each provider's `probe_model` for one token (see [`crate::verify`]).
+//! `compression: true` compresses responses for clients that accept it.
+//!
+//! `${VAR}` in any string is replaced by the environment variable `VAR`.
+//! [`ConfigFile::load`] checks the document before deserializing it and
+//! reports every problem at once, each with the key path it was found at,
+//! instead of stopping at serde's first message. `omniference config
+//! validate <path>` runs the same check.
+//!
+//! Deployments without a file can list providers in environment variables
+//! instead (see [`providers_from_env`]):
+//!
+//! | Variable | Meaning |
+//! |---|---|
+//! | `OMNIFERENCE_PROVIDERS` | Comma-separated provider names, e.g. `openai,ollama` |
+//! | `OMNIFERENCE_<NAME>_KIND` | Provider kind; defaults to the name when it is a known kind |
+//! | `OMNIFERENCE_<NAME>_BASE_URL` | Base URL; may be left out for kinds with a default |
+//! | `OMNIFERENCE_<NAME>_API_KEY` | API key |
+//! | `OMNIFERENCE_<NAME>_ORGANIZATION` | OpenAI organization id |
+//! | `OMNIFERENCE_<NAME>_PROJECT` | OpenAI project id |
+//! | `OMNIFERENCE_<NAME>_TIMEOUT` | Deprecated alias of `FIRST_BYTE_TIMEOUT` |
+//! | `OMNIFERENCE_<NAME>_CONNECT_TIMEOUT` | Connect timeout in milliseconds |
+//! | `OMNIFERENCE_<NAME>_FIRST_BYTE_TIMEOUT` | Timeout for the response headers in milliseconds |
+//! | `OMNIFERENCE_<NAME>_IDLE_STREAM_TIMEOUT` | Longest gap in a streamed response in milliseconds |
+//! | `OMNIFERENCE_<NAME>_HEADERS` | Extra headers as a JSON object, e.g. `{"X-Team": "search"}` |
+//!
+//! `<NAME>` is the provider name upper-cased, with `-` and `.` written as
+//! `_`. [`ConfigFile::merge_providers`] adds them to a file's providers; a
+//! provider defined in both keeps the file's settings.
+
+use crate::passthrough::PassthroughRoute;
+use crate::verify::StartupCheck;
+use crate::templates::{ChatTemplate, PromptTemplate};
+use crate::types::{CompatProfile, ProviderConfig, ProviderEndpoint, ProviderKind};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Longest provider or queue timeout accepted, in milliseconds (one hour)
+pub const MAX_TIMEOUT_MS: u64 = 3_600_000;
+
+/// Environment variable listing the providers configured by environment
+pub const PROVIDERS_ENV: &str = "OMNIFERENCE_PROVIDERS";
+
+/// Provider kind names accepted besides `custom:<name>`
+const KNOWN_KINDS: &[&str] = &["openai", "openai-compat", "anthropic", "cohere", "google", "mistral", "tgi", "ollama", "lmstudio"];
+
+/// Providers, model aliases and defaults of a server
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub providers: Vec<ProviderConfig>,
+    /// Alias name to model id, e.g. `"smart": "openai/gpt-4o"`
+    #[serde(default)]
+    pub model_aliases: BTreeMap<String, String>,
+    /// Prompt templates by model alias, added to the `chat_templates` of
+    /// the provider the alias targets (see [`crate::templates`])
+    #[serde(default)]
+    pub chat_templates: BTreeMap<String, PromptTemplate>,
+    #[serde(default)]
+    pub default_provider: Option<String>,
+    #[serde(default)]
+    pub default_model: Option<String>,
+    /// Gateway paths proxied to a provider as they are (see [`crate::passthrough`])
+    #[serde(default)]
+    pub passthrough: Vec<PassthroughRoute>,
+    /// Provider verification on start (see [`crate::verify`])
+    #[serde(default)]
+    pub startup_check: Option<StartupCheck>,
+    /// Response compression (see `OmniferenceServerBuilder::with_compression`)
+    #[serde(default)]
+    pub compression: Option<bool>,
+}
+
+/// One problem found in a config file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// Where the problem is, e.g. `providers[1].endpoint.kind`; empty for the document
+    pub key: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.key, self.message)
+        }
+    }
+}
+
+/// Every problem that kept a config file from loading
+#[derive(Debug, thiserror::Error)]
+#[error("invalid config {}:{}", .path.display(), format_problems(.problems))]
+pub struct ConfigError {
+    pub path: PathBuf,
+    pub problems: Vec<ConfigProblem>,
+}
+
+/// Every problem in the provider environment variables
+#[derive(Debug, thiserror::Error)]
+#[error("invalid provider environment variables:{}", format_problems(.problems))]
+pub struct EnvConfigError {
+    pub problems: Vec<ConfigProblem>,
+}
+
+fn format_problems(problems: &[ConfigProblem]) -> String {
+    problems.iter().map(|problem| format!("\n  {}", problem)).collect()
+}
+
+impl ConfigFile {
+    /// Read, check and deserialize the config file at `path`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| ConfigError {
+            path: path.to_path_buf(),
+            problems: vec![problem("", format!("can't read the file: {}", e))],
+        })?;
+        Self::parse(&text).map_err(|problems| ConfigError {
+            path: path.to_path_buf(),
+            problems,
+        })
+    }
+
+    /// Check and deserialize a config document, with `${VAR}`s replaced
+    pub fn parse(text: &str) -> Result<Self, Vec<ConfigProblem>> {
+        let mut document: Value = serde_json::from_str(text)
+            .map_err(|e| vec![problem("", format!("not valid JSON: {}", e))])?;
+        let mut problems = interpolate_env(&mut document, "", &|name| std::env::var(name).ok());
+        problems.extend(validate(&document));
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+        let mut config: Self = serde_json::from_value(document).map_err(|e| vec![problem("", e.to_string())])?;
+        for provider in &mut config.providers {
+            fill_default_base_url(&mut provider.endpoint);
+        }
+        for (alias, template) in &config.chat_templates {
+            let Some((provider, model)) = config.model_aliases.get(alias).and_then(|target| target.split_once('/')) else {
+                continue;
+            };
+            if let Some(provider) = config.providers.iter_mut().find(|existing| existing.name == provider) {
+                provider.endpoint.chat_templates.insert(model.to_string(), template.clone());
+            }
+        }
+        Ok(config)
+    }
+
+    /// Add `providers` the config doesn't define yet, returning their names.
+    /// A provider named in both keeps the config's settings.
+    pub fn merge_providers(&mut self, providers: Vec<ProviderConfig>) -> Vec<String> {
+        let mut added = Vec::new();
+        for provider in providers {
+            if !self.providers.iter().any(|existing| existing.name == provider.name) {
+                added.push(provider.name.clone());
+                self.providers.push(provider);
+            }
+        }
+        added
+    }
+}
+
+/// The providers listed in `OMNIFERENCE_PROVIDERS`, configured by their
+/// `OMNIFERENCE_<NAME>_*` variables (see the [module docs](self))
+pub fn providers_from_env() -> Result<Vec<ProviderConfig>, EnvConfigError> {
+    providers_from_env_with(|name| std::env::var(name).ok())
+}
+
+/// Like [`providers_from_env`], reading variables through `lookup`
+pub fn providers_from_env_with(lookup: impl Fn(&str) -> Option<String>) -> Result<Vec<ProviderConfig>, EnvConfigError> {
+    let mut problems = Vec::new();
+    let mut providers: Vec<ProviderConfig> = Vec::new();
+    let names = lookup(PROVIDERS_ENV).unwrap_or_default();
+    for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        if providers.iter().any(|provider| provider.name == name) {
+            problems.push(problem(PROVIDERS_ENV, format!("duplicate provider name \"{}\"", name)));
+            continue;
+        }
+        if let Some(provider) = provider_from_env(name, &lookup, &mut problems) {
+            providers.push(provider);
+        }
+    }
+    if problems.is_empty() {
+        Ok(providers)
+    } else {
+        Err(EnvConfigError { problems })
+    }
+}
+
+fn provider_from_env(
+    name: &str,
+    lookup: &impl Fn(&str) -> Option<String>,
+    problems: &mut Vec<ConfigProblem>,
+) -> Option<ProviderConfig> {
+    let prefix = format!("OMNIFERENCE_{}_", name.to_ascii_uppercase().replace(['-', '.'], "_"));
+    let var = |field: &str| {
+        let key = format!("{}{}", prefix, field);
+        let value = lookup(&key).filter(|value| !value.is_empty());
+        (key, value)
+    };
+    let before = problems.len();
+
+    let kind = match var("KIND") {
+        (key, Some(kind)) => check_kind(&Value::String(kind), &key, problems),
+        (key, None) => match name.parse::<ProviderKind>() {
+            Ok(ProviderKind::Custom(_)) => {
+                problems.push(problem(&key, format!("not set, and \"{}\" is not a provider kind", name)));
+                None
+            }
+            Ok(kind) => Some(kind),
+        },
+    };
+    let base_url = match var("BASE_URL") {
+        (key, Some(url)) => {
+            if let Err(message) = check_url(&url) {
+                problems.push(problem(&key, message));
+            }
+            url
+        }
+        (key, None) => {
+            if kind.as_ref().is_some_and(|kind| kind.default_base_url().is_none()) {
+                problems.push(problem(&key, "not set; this provider kind has no default"));
+            }
+            String::new()
+        }
+    };
+    let mut timeout = |field: &str| match var(field) {
+        (key, Some(timeout)) => match timeout.trim().parse::<u64>() {
+            Ok(ms) if (1..=MAX_TIMEOUT_MS).contains(&ms) => Some(ms),
+            _ => {
+                problems.push(problem(
+                    &key,
+                    format!("\"{}\" is not a timeout; expected 1 to {} milliseconds", timeout, MAX_TIMEOUT_MS),
+                ));
+                None
+            }
+        },
+        (_, None) => None,
+    };
+    let timeouts = [
+        timeout("TIMEOUT"),
+        timeout("CONNECT_TIMEOUT"),
+        timeout("FIRST_BYTE_TIMEOUT"),
+        timeout("IDLE_STREAM_TIMEOUT"),
+    ];
+    let extra_headers = match var("HEADERS") {
+        (key, Some(headers)) => serde_json::from_str(&headers).unwrap_or_else(|e| {
+            problems.push(problem(&key, format!("expected a JSON object of header names to values: {}", e)));
+            BTreeMap::new()
+        }),
+        (_, None) => BTreeMap::new(),
+    };
+    if problems.len() > before {
+        return None;
+    }
+
+    let mut endpoint = ProviderEndpoint {
+        kind: kind?,
+        base_url,
+        api_key: var("API_KEY").1.map(Into::into),
+        organization: var("ORGANIZATION").1,
+        project: var("PROJECT").1,
+        extra_headers,
+        timeout: timeouts[0],
+        connect_timeout_ms: timeouts[1],
+        first_byte_timeout_ms: timeouts[2],
+        idle_stream_timeout_ms: timeouts[3],
+        ..Default::default()
+    };
+    fill_default_base_url(&mut endpoint);
+    Some(ProviderConfig {
+        name: name.to_string(),
+        endpoint,
+        enabled: true,
+        ..Default::default()
+    })
+}
+
+/// Spell out the default base URL of an endpoint that leaves it empty;
+/// not every adapter falls back to it on its own
+fn fill_default_base_url(endpoint: &mut ProviderEndpoint) {
+    if endpoint.base_url.is_empty() && endpoint.kind != ProviderKind::OpenAICompat {
+        if let Some(url) = endpoint.kind.default_base_url() {
+            endpoint.base_url = url.to_string();
+        }
+    }
+}
+
+/// Every problem in a config document, in document order. An empty list
+/// means it deserializes into a [`ConfigFile`] that the server accepts.
+pub fn validate(document: &Value) -> Vec<ConfigProblem> {
+    let mut problems = Vec::new();
+    let Some(root) = document.as_object() else {
+        return vec![problem("", format!("expected an object, got {}", type_name(document)))];
+    };
+
+    let mut names = HashSet::new();
+    let mut prefixes: HashSet<String> = KNOWN_KINDS.iter().map(|kind| kind.to_string()).collect();
+    match root.get("providers") {
+        None => {}
+        Some(Value::Array(providers)) => {
+            for (index, provider) in providers.iter().enumerate() {
+                let key = format!("providers[{}]", index);
+                validate_provider(provider, &key, &mut names, &mut problems);
+                if let Some(pool) = provider.get("pool").and_then(Value::as_str) {
+                    prefixes.insert(pool.to_string());
+                }
+            }
+        }
+        Some(other) => problems.push(problem("providers", expected("an array", other))),
+    }
+    prefixes.extend(names.iter().cloned());
+
+    match root.get("model_aliases") {
+        None => {}
+        Some(Value::Object(aliases)) => {
+            for (alias, target) in aliases {
+                let key = format!("model_aliases.{}", alias);
+                match target.as_str() {
+                    None => problems.push(problem(&key, expected("a model id string", target))),
+                    Some(target) => {
+                        if let Some((provider, _)) = target.split_once('/') {
+                            if !prefixes.contains(provider) {
+                                problems.push(problem(
+                                    &key,
+                                    format!("targets \"{}\", but no provider or pool is named \"{}\"", target, provider),
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Some(other) => problems.push(problem("model_aliases", expected("an object", other))),
+    }
+
+    match root.get("chat_templates") {
+        None => {}
+        Some(Value::Object(templates)) => {
+            for (alias, template) in templates {
+                let key = format!("chat_templates.{}", alias);
+                validate_template(template, &key, &mut problems);
+                match root.get("model_aliases").and_then(|aliases| aliases.get(alias)).and_then(Value::as_str) {
+                    None => problems.push(problem(&key, format!("no model alias is named \"{}\"", alias))),
+                    Some(target) => {
+                        if !target.split_once('/').is_some_and(|(provider, _)| names.contains(provider)) {
+                            problems.push(problem(
+                                &key,
+                                format!("the alias targets \"{}\", which isn't a model of a provider", target),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        Some(other) => problems.push(problem("chat_templates", expected("an object of templates by model alias", other))),
+    }
+
+    match root.get("default_provider") {
+        None | Some(Value::Null) => {}
+        Some(Value::String(provider)) if names.contains(provider) => {}
+        Some(Value::String(provider)) => {
+            problems.push(problem("default_provider", format!("no provider is named \"{}\"", provider)))
+        }
+        Some(other) => problems.push(problem("default_provider", expected("a provider name", other))),
+    }
+    if let Some(model) = root.get("default_model").filter(|model| !model.is_null() && !model.is_string()) {
+        problems.push(problem("default_model", expected("a model name", model)));
+    }
+
+    match root.get("passthrough") {
+        None => {}
+        Some(Value::Array(routes)) => {
+            let mut prefixes = HashSet::new();
+            for (index, route) in routes.iter().enumerate() {
+                validate_passthrough(route, &format!("passthrough[{}]", index), &names, &mut prefixes, &mut problems);
+            }
+        }
+        Some(other) => problems.push(problem("passthrough", expected("an array", other))),
+    }
+    match root.get("startup_check") {
+        None | Some(Value::Null) => {}
+        Some(check) if StartupCheck::deserialize(check).is_ok() => {}
+        Some(other) => problems.push(problem("startup_check", expected("\"off\", \"warn\", \"disable\" or \"refuse\"", other))),
+    }
+    match root.get("compression") {
+        None | Some(Value::Null) | Some(Value::Bool(_)) => {}
+        Some(other) => problems.push(problem("compression", expected("true or false", other))),
+    }
+    problems
+}
+
+fn validate_passthrough(
+    route: &Value,
+    key: &str,
+    providers: &HashSet<String>,
+    prefixes: &mut HashSet<String>,
+    problems: &mut Vec<ConfigProblem>,
+) {
+    let Some(fields) = route.as_object() else {
+        problems.push(problem(key, expected("an object", route)));
+        return;
+    };
+    for field in ["path_prefix", "upstream_path"] {
+        match fields.get(field) {
+            None | Some(Value::Null) if field == "upstream_path" => {}
+            None => problems.push(problem(key, format!("missing \"{}\"", field))),
+            Some(Value::String(path)) if path.starts_with('/') && path.trim_end_matches('/').len() > 1 => {
+                if field == "path_prefix" && !prefixes.insert(path.trim_end_matches('/').to_string()) {
+                    problems.push(problem(&format!("{}.{}", key, field), format!("duplicate path prefix \"{}\"", path)));
+                }
+            }
+            Some(other) => problems.push(problem(&format!("{}.{}", key, field), expected("a path starting with /", other))),
+        }
+    }
+    match fields.get("provider") {
+        None => problems.push(problem(key, "missing \"provider\"")),
+        Some(Value::String(provider)) if providers.contains(provider) => {}
+        Some(Value::String(provider)) => {
+            problems.push(problem(&format!("{}.provider", key), format!("no provider is named \"{}\"", provider)))
+        }
+        Some(other) => problems.push(problem(&format!("{}.provider", key), expected("a provider name", other))),
+    }
+    if let Some(value) = fields.get("max_response_bytes").filter(|value| !value.is_null()) {
+        if value.as_u64().is_none_or(|n| n == 0) {
+            problems.push(problem(&format!("{}.max_response_bytes", key), expected("a positive integer", value)));
+        }
+    }
+}
+
+fn validate_provider(provider: &Value, key: &str, names: &mut HashSet<String>, problems: &mut Vec<ConfigProblem>) {
+    let Some(fields) = provider.as_object() else {
+        problems.push(problem(key, expected("an object", provider)));
+        return;
+    };
+    let tenant = match fields.get("tenant") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(tenant)) if !tenant.is_empty() && !tenant.contains('/') => Some(tenant.as_str()),
+        Some(other) => {
+            problems.push(problem(&format!("{}.tenant", key), expected("a tenant id without '/'", other)));
+            None
+        }
+    };
+    match fields.get("name") {
+        None => problems.push(problem(key, "missing \"name\"")),
+        Some(Value::String(name)) if name.is_empty() => problems.push(problem(&format!("{}.name", key), "is empty")),
+        Some(Value::String(name)) => {
+            // Tenants' providers may share names
+            if !names.insert(crate::tenant::qualified_name(tenant, name)) {
+                problems.push(problem(&format!("{}.name", key), format!("duplicate provider name \"{}\"", name)));
+            }
+        }
+        Some(other) => problems.push(problem(&format!("{}.name", key), expected("a string", other))),
+    }
+    match fields.get("enabled") {
+        None => problems.push(problem(key, "missing \"enabled\"")),
+        Some(Value::Bool(_)) => {}
+        Some(other) => problems.push(problem(&format!("{}.enabled", key), expected("true or false", other))),
+    }
+    for field in ["max_concurrent_requests", "max_queue_depth", "weight"] {
+        if let Some(value) = fields.get(field).filter(|value| !value.is_null()) {
+            if !value.as_u64().is_some_and(|n| n > 0 || field == "max_queue_depth") {
+                problems.push(problem(&format!("{}.{}", key, field), expected("a positive integer", value)));
+            }
+        }
+    }
+    if let Some(timeout) = fields.get("queue_timeout_ms") {
+        check_timeout(timeout, &format!("{}.queue_timeout_ms", key), problems);
+    }
+    match fields.get("probe_model") {
+        None | Some(Value::Null) => {}
+        Some(Value::String(model)) if !model.is_empty() => {}
+        Some(other) => problems.push(problem(&format!("{}.probe_model", key), expected("a model name", other))),
+    }
+    for field in ["warm_up", "keep_warm"] {
+        match fields.get(field) {
+            None | Some(Value::Bool(_)) => {}
+            Some(other) => problems.push(problem(&format!("{}.{}", key, field), expected("true or false", other))),
+        }
+    }
+
+    let key = format!("{}.endpoint", key);
+    let endpoint = match fields.get("endpoint") {
+        Some(Value::Object(endpoint)) => endpoint,
+        Some(other) => return problems.push(problem(&key, expected("an object", other))),
+        None => return problems.push(problem(&key, "missing")),
+    };
+    let kind = match endpoint.get("kind") {
+        None => {
+            problems.push(problem(&key, "missing \"kind\""));
+            None
+        }
+        Some(kind) => check_kind(kind, &format!("{}.kind", key), problems),
+    };
+    for field in [
+        "timeout",
+        "connect_timeout_ms",
+        "first_byte_timeout_ms",
+        "idle_stream_timeout_ms",
+        "pool_idle_timeout_ms",
+        "tcp_keepalive_ms",
+    ] {
+        if let Some(timeout) = endpoint.get(field) {
+            check_timeout(timeout, &format!("{}.{}", key, field), problems);
+        }
+    }
+    match endpoint.get("proxy_url") {
+        None | Some(Value::Null) => {}
+        Some(Value::String(url)) if url == crate::adapters::http::NO_PROXY => {}
+        Some(Value::String(url)) => {
+            if let Err(e) = reqwest::Proxy::all(url.as_str()) {
+                problems.push(problem(&format!("{}.proxy_url", key), format!("invalid proxy URL: {}", e)));
+            }
+        }
+        Some(other) => problems.push(problem(&format!("{}.proxy_url", key), expected("a proxy URL or \"none\"", other))),
+    }
+    match endpoint.get("ca_cert_path") {
+        None | Some(Value::Null) => {}
+        Some(Value::String(path)) => {
+            if let Err(message) = crate::adapters::http::load_ca_certs(path) {
+                problems.push(problem(&format!("{}.ca_cert_path", key), message));
+            }
+        }
+        Some(other) => problems.push(problem(&format!("{}.ca_cert_path", key), expected("a file path", other))),
+    }
+    for field in ["danger_accept_invalid_certs", "http2_prior_knowledge", "compress_requests"] {
+        if let Some(value) = endpoint.get(field).filter(|value| !value.is_boolean()) {
+            problems.push(problem(&format!("{}.{}", key, field), expected("true or false", value)));
+        }
+    }
+    match endpoint.get("chat_templates") {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(templates)) => {
+            for (model, template) in templates {
+                validate_template(template, &format!("{}.chat_templates.{}", key, model), problems);
+            }
+        }
+        Some(other) => problems.push(problem(
+            &format!("{}.chat_templates", key),
+            expected("an object of templates by model", other),
+        )),
+    }
+    match endpoint.get("auth_mode") {
+        None | Some(Value::Null) => {}
+        Some(Value::String(mode)) if mode == "configured" => {}
+        Some(Value::String(mode)) if mode == "passthrough" => {
+            if endpoint.get("api_key").is_some_and(|api_key| !api_key.is_null()) || endpoint.get("auth").is_some_and(|auth| !auth.is_null()) {
+                problems.push(problem(
+                    &format!("{}.auth_mode", key),
+                    "passthrough providers send the caller's key; remove \"api_key\" and \"auth\"",
+                ));
+            }
+        }
+        Some(other) => problems.push(problem(&format!("{}.auth_mode", key), expected("\"configured\" or \"passthrough\"", other))),
+    }
+    for field in ["organization", "project"] {
+        match endpoint.get(field) {
+            None | Some(Value::Null) => {}
+            Some(Value::String(id)) if !id.trim().is_empty() => {
+                if kind.as_ref().is_some_and(|kind| !matches!(kind, ProviderKind::OpenAI | ProviderKind::OpenAICompat)) {
+                    problems.push(problem(
+                        &format!("{}.{}", key, field),
+                        "only OpenAI and OpenAI-compatible providers take an organization or project",
+                    ));
+                }
+            }
+            Some(other) => problems.push(problem(&format!("{}.{}", key, field), expected("an OpenAI id", other))),
+        }
+    }
+    match endpoint.get("pool_max_idle_per_host") {
+        None | Some(Value::Null) => {}
+        Some(value) if value.is_u64() => {}
+        Some(other) => problems.push(problem(
+            &format!("{}.pool_max_idle_per_host", key),
+            expected("a whole number of connections", other),
+        )),
+    }
+
+    let profile = endpoint
+        .get("compat_profile")
+        .and_then(|profile| serde_json::from_value::<CompatProfile>(profile.clone()).ok())
+        .unwrap_or_default();
+    let has_default = kind.as_ref().is_some_and(|kind| {
+        kind.default_base_url().is_some() || (*kind == ProviderKind::OpenAICompat && profile.default_base_url().is_some())
+    });
+    match endpoint.get("base_url") {
+        None | Some(Value::Null) if has_default => {}
+        Some(Value::String(url)) if url.is_empty() && has_default => {}
+        None | Some(Value::Null) => {
+            if kind.is_some() {
+                problems.push(problem(&key, "missing \"base_url\"; this provider kind has no default"));
+            }
+        }
+        Some(Value::String(url)) => {
+            if let Err(message) = check_url(url) {
+                problems.push(problem(&format!("{}.base_url", key), message));
+            }
+        }
+        Some(other) => problems.push(problem(&format!("{}.base_url", key), expected("a URL string", other))),
+    }
+}
+
+/// The kind `value` names, if it's a known kind or an explicit custom one
+fn check_kind(value: &Value, key: &str, problems: &mut Vec<ConfigProblem>) -> Option<ProviderKind> {
+    let name = match value {
+        Value::String(name) => name,
+        Value::Object(custom) if custom.len() == 1 && custom.get("Custom").is_some_and(Value::is_string) => {
+            return serde_json::from_value(value.clone()).ok();
+        }
+        other => {
+            problems.push(problem(key, expected("a provider kind string", other)));
+            return None;
+        }
+    };
+    // Any other name parses as a custom kind, which hides typos like "olama"
+    let kind: ProviderKind = name.parse().ok()?;
+    let explicit = name.split_once(':').is_some_and(|(prefix, _)| prefix.eq_ignore_ascii_case("custom"));
+    if matches!(kind, ProviderKind::Custom(_)) && !explicit {
+        problems.push(problem(
+            key,
+            format!(
+                "unknown provider kind \"{}\"; expected one of {} or \"custom:<name>\"",
+                name,
+                KNOWN_KINDS.join(", ")
+            ),
+        ));
+        return None;
+    }
+    Some(kind)
+}
+
+fn check_timeout(value: &Value, key: &str, problems: &mut Vec<ConfigProblem>) {
+    match value {
+        Value::Null => {}
+        Value::Number(number) => match number.as_u64() {
+            Some(ms) if (1..=MAX_TIMEOUT_MS).contains(&ms) => {}
+            _ => problems.push(problem(key, format!("{} is out of range; expected 1 to {} milliseconds", number, MAX_TIMEOUT_MS))),
+        },
+        other => problems.push(problem(key, expected("a number of milliseconds", other))),
+    }
+}
+
+fn check_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("\"{}\" is not a valid URL: {}", url, e))?;
+    match parsed.scheme() {
+        "http" | "https" if parsed.has_host() => Ok(()),
+        "http" | "https" => Err(format!("\"{}\" has no host", url)),
+        "unix" if crate::adapters::http::unix_socket(url).is_some() => Ok(()),
+        "unix" if cfg!(all(unix, feature = "uds")) => {
+            Err(format!("\"{}\" has no socket path; expected unix:///path/to.sock", url))
+        }
+        "unix" => Err(format!("\"{}\" is a Unix socket; those need the `uds` feature on Unix", url)),
+        scheme => Err(format!("\"{}\" uses scheme \"{}\"; expected http or https", url, scheme)),
+    }
+}
+
+/// Replace `${VAR}` in every string of `value` with `lookup(VAR)`, returning
+/// a problem for each variable that doesn't resolve
+fn interpolate_env(value: &mut Value, key: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Vec<ConfigProblem> {
+    match value {
+        Value::String(text) => {
+            let mut problems = Vec::new();
+            let mut result = String::new();
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("${") {
+                let Some(end) = rest[start..].find('}') else {
+                    break;
+                };
+                let name = &rest[start + 2..start + end];
+                result.push_str(&rest[..start]);
+                match lookup(name) {
+                    Some(resolved) => result.push_str(&resolved),
+                    None => problems.push(problem(key, format!("environment variable {} is not set", name))),
+                }
+                rest = &rest[start + end + 1..];
+            }
+            result.push_str(rest);
+            *text = result;
+            problems
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(index, item)| interpolate_env(item, &format!("{}[{}]", key, index), lookup))
+            .collect(),
+        Value::Object(fields) => fields
+            .iter_mut()
+            .flat_map(|(field, item)| {
+                let key = if key.is_empty() { field.clone() } else { format!("{}.{}", key, field) };
+                interpolate_env(item, &key, lookup)
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn problem(key: &str, message: impl Into<String>) -> ConfigProblem {
+    ConfigProblem {
+        key: key.to_string(),
+        message: message.into(),
+    }
+}
+
+/// A role-prefix object, a format name, a template string or an object with
+/// one (see [`crate::templates`])
+fn validate_template(template: &Value, key: &str, problems: &mut Vec<ConfigProblem>) {
+    let parsed = match template {
+        Value::Object(fields) if !fields.contains_key("template") => {
+            serde_json::from_value::<ChatTemplate>(template.clone()).map(PromptTemplate::from)
+        }
+        Value::String(source) if !source.contains("{{") && !source.contains("{%") => {
+            serde_json::from_value::<PromptTemplate>(template.clone()).and_then(|parsed| match parsed {
+                PromptTemplate::Format(_) => Ok(parsed),
+                _ => Err(serde::de::Error::custom(expected("chatml, llama3, mistral or a template string", template))),
+            })
+        }
+        Value::String(_) | Value::Object(_) => serde_json::from_value::<PromptTemplate>(template.clone()),
+        other => {
+            problems.push(problem(key, expected("a template name, string or object", other)));
+            return;
+        }
+    };
+    match parsed {
+        Ok(parsed) => {
+            if let Err(e) = parsed.validate() {
+                problems.push(problem(key, e.to_string()));
+            }
+        }
+        Err(e) => problems.push(problem(key, e.to_string())),
+    }
+}
+
+fn expected(what: &str, got: &Value) -> String {
+    match got {
+        Value::String(text) => format!("expected {}, got the string \"{}\"", what, text),
+        other => format!("expected {}, got {}", what, type_name(other)),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
@@ -0,0 +1,305 @@
+//! Context-window management
+//!
+//! A [`ContextManager`] runs in the router before a chat request reaches its
+//! adapter. When the prompt (plus the tokens reserved for the reply) would
+//! exceed the model's context length, it drops the oldest unprotected messages
+//! or, in [`ContextStrategy::Summarize`] mode, replaces them with a summary
+//! produced by a side request to the same model. Management is opt-in per
+//! alias; with the default policy requests pass through untouched.
+
+use crate::adapter::ChatAdapter;
+use crate::error::EngineError;
+use crate::stream::StreamEvent;
+use crate::tokens::TokenCounter;
+use crate::types::{ChatRequestIR, ContentPart, DiscoveredModel, Message, ModelRef, Role};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    /// Send requests as they are
+    #[default]
+    Off,
+    /// Drop the oldest unprotected messages until the request fits
+    Truncate,
+    /// Like `Truncate`, but replace the dropped messages with a summary
+    Summarize,
+}
+
+impl ContextStrategy {
+    fn as_str(self) -> &'static str {
+        match self {
+            ContextStrategy::Off => "off",
+            ContextStrategy::Truncate => "truncate",
+            ContextStrategy::Summarize => "summarize",
+        }
+    }
+}
+
+/// How to keep requests for one alias within its context window
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextPolicy {
+    pub strategy: ContextStrategy,
+    /// Tokens kept free for the reply when the request doesn't set `max_tokens`
+    pub reserve_output_tokens: usize,
+    /// Messages with these roles are never dropped
+    pub protected_roles: Vec<Role>,
+    /// Context length to use when discovery doesn't report one
+    pub context_length: Option<u32>,
+}
+
+impl Default for ContextPolicy {
+    fn default() -> Self {
+        Self {
+            strategy: ContextStrategy::Off,
+            reserve_output_tokens: 1024,
+            protected_roles: vec![Role::System, Role::Developer],
+            context_length: None,
+        }
+    }
+}
+
+impl ContextPolicy {
+    pub fn truncate() -> Self {
+        Self {
+            strategy: ContextStrategy::Truncate,
+            ..Default::default()
+        }
+    }
+
+    pub fn summarize() -> Self {
+        Self {
+            strategy: ContextStrategy::Summarize,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_reserve_output_tokens(mut self, tokens: usize) -> Self {
+        self.reserve_output_tokens = tokens;
+        self
+    }
+
+    pub fn with_protected_roles(mut self, roles: Vec<Role>) -> Self {
+        self.protected_roles = roles;
+        self
+    }
+
+    pub fn with_context_length(mut self, tokens: u32) -> Self {
+        self.context_length = Some(tokens);
+        self
+    }
+}
+
+const SUMMARY_INSTRUCTIONS: &str = "Summarize the following conversation excerpt in a few sentences. \
+Keep names, facts, decisions and open questions; reply with the summary only.";
+
+/// Per-alias context policies plus the context lengths learned from discovery
+#[derive(Clone, Debug, Default)]
+pub struct ContextManager {
+    default_policy: ContextPolicy,
+    policies: HashMap<String, ContextPolicy>,
+    context_lengths: Arc<RwLock<HashMap<String, u32>>>,
+}
+
+impl ContextManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Policy for aliases without their own
+    pub fn with_default_policy(mut self, policy: ContextPolicy) -> Self {
+        self.default_policy = policy;
+        self
+    }
+
+    /// Policy for one model, keyed by discovered id (`provider/model`) or bare model name
+    pub fn with_policy(mut self, alias: impl Into<String>, policy: ContextPolicy) -> Self {
+        self.policies.insert(alias.into(), policy);
+        self
+    }
+
+    pub fn policy_for(&self, model: &ModelRef) -> &ContextPolicy {
+        self.policies
+            .get(&model.alias)
+            .or_else(|| self.policies.get(&model.model_id))
+            .unwrap_or(&self.default_policy)
+    }
+
+    /// Remember the context lengths reported for discovered models
+    pub fn record_models(&self, models: &[DiscoveredModel]) {
+        let mut lengths = self.context_lengths.write().unwrap_or_else(|e| e.into_inner());
+        for model in models {
+            if let Some(context_length) = model.capabilities.context_length {
+                lengths.insert(model.id.clone(), context_length);
+            }
+        }
+    }
+
+    /// The model's context length: the policy's value, else the discovered one
+    pub fn context_length(&self, model: &ModelRef) -> Option<u32> {
+        if let Some(context_length) = self.policy_for(model).context_length {
+            return Some(context_length);
+        }
+        let lengths = self.context_lengths.read().unwrap_or_else(|e| e.into_inner());
+        lengths.get(&model.alias).copied().or_else(|| {
+            // OpenRouter-style variants share their base model's window
+            let (base, _) = crate::types::providers::openrouter::split_model_suffix(&model.alias);
+            lengths.get(base).copied()
+        })
+    }
+
+    /// Shrink the request's history to fit the context window, recording
+    /// `context_strategy`, `context_dropped_messages` and
+    /// `context_original_tokens` in its metadata when anything was dropped.
+    ///
+    /// Best effort: when the window is unknown, counting fails, or nothing
+    /// can be dropped, the request is sent as is.
+    pub(crate) async fn fit(
+        &self,
+        adapter: &Arc<dyn ChatAdapter>,
+        counter: &dyn TokenCounter,
+        ir: &mut ChatRequestIR,
+        cancel: &CancellationToken,
+    ) {
+        let policy = self.policy_for(&ir.model);
+        if policy.strategy == ContextStrategy::Off {
+            return;
+        }
+        let Some(context_length) = self.context_length(&ir.model) else {
+            return;
+        };
+        let reserve = ir
+            .sampling
+            .max_tokens
+            .map(|tokens| tokens as usize)
+            .unwrap_or(policy.reserve_output_tokens);
+        let budget = (context_length as usize).saturating_sub(reserve);
+
+        let count = match counter.count(&ir.model, &ir.messages).await {
+            Ok(count) => count,
+            Err(e) => {
+                tracing::warn!(model_alias = %ir.model.alias, error = %e, "Token count failed; skipping context management");
+                return;
+            }
+        };
+        if count.prompt_tokens <= budget {
+            return;
+        }
+
+        let messages = std::mem::take(&mut ir.messages);
+        let (kept, dropped) = crate::tokens::split_to_budget(messages, &count, budget, &policy.protected_roles);
+        ir.messages = kept;
+        if dropped.is_empty() {
+            tracing::warn!(
+                model_alias = %ir.model.alias,
+                prompt_tokens = count.prompt_tokens,
+                budget,
+                "Request exceeds the context window and no messages can be dropped"
+            );
+            return;
+        }
+
+        let mut strategy = policy.strategy;
+        if strategy == ContextStrategy::Summarize {
+            match summarize(adapter, &ir.model, &dropped, cancel).await {
+                Ok(summary) => {
+                    let at = ir
+                        .messages
+                        .iter()
+                        .take_while(|m| matches!(m.role, Role::System | Role::Developer))
+                        .count();
+                    ir.messages.insert(
+                        at,
+                        Message {
+                            role: Role::System,
+                            parts: vec![ContentPart::Text(format!(
+                                "Summary of the earlier conversation:\n{}",
+                                summary
+                            ))],
+                            name: None,
+                        },
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!(model_alias = %ir.model.alias, error = %e, "Summarizing history failed; truncating instead");
+                    strategy = ContextStrategy::Truncate;
+                }
+            }
+        }
+
+        tracing::info!(
+            request_id = %ir.metadata.get("request_id").map(String::as_str).unwrap_or("unknown"),
+            model_alias = %ir.model.alias,
+            strategy = strategy.as_str(),
+            dropped = dropped.len(),
+            prompt_tokens = count.prompt_tokens,
+            budget,
+            "Shrank history to fit the context window"
+        );
+        ir.metadata.insert("context_strategy".to_string(), strategy.as_str().to_string());
+        ir.metadata.insert("context_dropped_messages".to_string(), dropped.len().to_string());
+        ir.metadata.insert("context_original_tokens".to_string(), count.prompt_tokens.to_string());
+    }
+}
+
+/// Ask the model for a summary of `messages`
+async fn summarize(
+    adapter: &Arc<dyn ChatAdapter>,
+    model: &ModelRef,
+    messages: &[Message],
+    cancel: &CancellationToken,
+) -> Result<String, EngineError> {
+    let transcript: Vec<String> = messages
+        .iter()
+        .map(|m| {
+            let role = match m.role {
+                Role::Developer => "developer",
+                Role::System => "system",
+                Role::User => "user",
+                Role::Assistant => "assistant",
+                Role::Tool => "tool",
+            };
+            format!("{}: {}", role, crate::tokens::message_text(m))
+        })
+        .collect();
+    let request = ChatRequestIR {
+        model: model.clone(),
+        messages: vec![
+            Message {
+                role: Role::System,
+                parts: vec![ContentPart::Text(SUMMARY_INSTRUCTIONS.to_string())],
+                name: None,
+            },
+            Message {
+                role: Role::User,
+                parts: vec![ContentPart::Text(transcript.join("\n"))],
+                name: None,
+            },
+        ],
+        ..Default::default()
+    };
+
+    let mut events = adapter.execute_chat(request, cancel.child_token()).await?;
+    let mut summary = String::new();
+    while let Some(event) = events.next().await {
+        match event {
+            StreamEvent::TextDelta { content } => summary.push_str(&content),
+            StreamEvent::FinalMessage { content, .. } if summary.is_empty() => summary = content,
+            StreamEvent::Error { code, message } => {
+                return Err(EngineError::from_stream_error(code, message));
+            }
+            StreamEvent::Done => break,
+            _ => {}
+        }
+    }
+    let summary = summary.trim();
+    if summary.is_empty() {
+        return Err(EngineError::config("summary request returned no text"));
+    }
+    Ok(summary.to_string())
+}
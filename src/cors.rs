@@ -0,0 +1,128 @@
+use axum::http::{HeaderName, HeaderValue, Method};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+
+/// CORS policy for the HTTP server.
+///
+/// The default allows no cross-origin requests. Origins are matched exactly
+/// (`https://app.example.com`) or by a single wildcard
+/// (`https://*.example.com`); `"*"` allows any origin.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CorsConfig {
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// Request headers to allow; empty mirrors the preflight's
+    /// `Access-Control-Request-Headers`
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+    /// Methods to allow; empty allows `GET`, `POST` and `OPTIONS`
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+    #[serde(default)]
+    pub allow_credentials: bool,
+    /// Allow everything (`CorsLayer::permissive()`); intended for local development
+    #[serde(default)]
+    pub permissive: bool,
+}
+
+impl CorsConfig {
+    /// Explicit opt-in to the fully permissive policy
+    pub fn permissive() -> Self {
+        Self {
+            permissive: true,
+            ..Default::default()
+        }
+    }
+
+    /// Allow the given origins (exact or wildcard patterns)
+    pub fn with_origins<I, S>(origins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed_origins: origins.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether `origin` is allowed by the configured patterns
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.permissive
+            || self
+                .allowed_origins
+                .iter()
+                .any(|pattern| origin_matches(pattern, origin))
+    }
+
+    /// Build the tower-http layer for this policy
+    pub fn to_layer(&self) -> CorsLayer {
+        if self.permissive {
+            return CorsLayer::permissive();
+        }
+
+        let origins = self.allowed_origins.clone();
+        let any_origin = origins.iter().any(|o| o == "*");
+        let allow_origin = if any_origin && self.allow_credentials {
+            // `*` can't be combined with credentials; reflect the origin instead
+            AllowOrigin::mirror_request()
+        } else if any_origin {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+                origin
+                    .to_str()
+                    .map(|origin| origins.iter().any(|pattern| origin_matches(pattern, origin)))
+                    .unwrap_or(false)
+            })
+        };
+
+        let allow_headers = if self.allowed_headers.is_empty() {
+            AllowHeaders::mirror_request()
+        } else {
+            AllowHeaders::list(
+                self.allowed_headers
+                    .iter()
+                    .filter_map(|h| HeaderName::from_bytes(h.as_bytes()).ok()),
+            )
+        };
+
+        let allow_methods = if self.allowed_methods.is_empty() {
+            AllowMethods::list([Method::GET, Method::POST, Method::OPTIONS])
+        } else {
+            AllowMethods::list(
+                self.allowed_methods
+                    .iter()
+                    .filter_map(|m| Method::from_bytes(m.to_ascii_uppercase().as_bytes()).ok()),
+            )
+        };
+
+        let mut layer = CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_headers(allow_headers)
+            .allow_methods(allow_methods)
+            .allow_credentials(self.allow_credentials);
+        if let Some(secs) = self.max_age_secs {
+            layer = layer.max_age(Duration::from_secs(secs));
+        }
+        layer
+    }
+}
+
+fn origin_matches(pattern: &str, origin: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            origin.len() > prefix.len() + suffix.len()
+                && origin.starts_with(prefix)
+                && origin.ends_with(suffix)
+                && !origin[prefix.len()..origin.len() - suffix.len()].contains('/')
+        }
+        None => pattern.eq_ignore_ascii_case(origin),
+    }
+}
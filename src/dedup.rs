@@ -0,0 +1,187 @@
+//! In-flight request deduplication
+//!
+//! A client retrying a request that timed out on its side would otherwise
+//! start the same generation again while the first one is still running.
+//! With a [`RequestDeduplicator`] on the router, a request identical to one
+//! in flight (same [`ChatRequestIR::fingerprint`]) joins that generation
+//! instead. The generation runs as a replaying [`SharedChatStream`], so a
+//! request that joins late still gets the full output from the first event.
+//!
+//! Requests only join generations on the same endpoint (its kind, base URL,
+//! organization and project), and requests that can't be fingerprinted
+//! never join or lead one.
+//!
+//! Only requests meant to produce the same output are joined: those with a
+//! temperature of 0, and those carrying an idempotency key (the HTTP skins'
+//! `Idempotency-Key` header, kept as [`IDEMPOTENCY_KEY_METADATA`]). A
+//! request can join once the first one has reached its provider; identical
+//! requests waiting in a provider queue together each run. The generation
+//! is cancelled when every caller attached to it has gone. Usage, cost and
+//! budget spend are recorded once, for the request that started it.
+
+use crate::multiplex::SharedChatStream;
+use crate::stream::StreamEvent;
+use crate::types::ChatRequestIR;
+use futures_util::{stream::BoxStream, Stream, StreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Metadata key of a request's idempotency key
+pub const IDEMPOTENCY_KEY_METADATA: &str = "idempotency_key";
+
+/// Whether identical copies of `request` may share one generation. Requests
+/// whose [`endpoint_overrides`](ChatRequestIR::endpoint_overrides) replace
+/// the endpoint never do: they may be paid for with the caller's own key.
+/// Ones that only name an organization or project join requests naming
+/// the same.
+pub fn is_deduplicable(request: &ChatRequestIR) -> bool {
+    !request.endpoint_overrides.as_ref().is_some_and(crate::types::EndpointOverrides::replaces_endpoint)
+        && (request.sampling.temperature == Some(0.0) || request.metadata.contains_key(IDEMPOTENCY_KEY_METADATA))
+}
+
+/// The key `request` shares generations under: its fingerprint and the
+/// endpoint it's sent to, or `None` when it can't be fingerprinted
+pub(crate) fn dedup_key(request: &ChatRequestIR) -> Option<[u8; 32]> {
+    use sha2::{Digest, Sha256};
+
+    let fingerprint = match request.try_fingerprint() {
+        Ok(fingerprint) => fingerprint,
+        Err(e) => {
+            tracing::warn!(model_alias = %request.model.alias, error = %e, "Request can't be fingerprinted; not deduplicating it");
+            return None;
+        }
+    };
+    let endpoint = &request.model.provider;
+    let overrides = request.endpoint_overrides.as_ref();
+    let organization = overrides.and_then(|o| o.organization.as_ref()).or(endpoint.organization.as_ref());
+    let project = overrides.and_then(|o| o.project.as_ref()).or(endpoint.project.as_ref());
+    let mut hasher = Sha256::new();
+    hasher.update(fingerprint);
+    for part in [
+        format!("{:?}", endpoint.kind),
+        endpoint.base_url.clone(),
+        organization.cloned().unwrap_or_default(),
+        project.cloned().unwrap_or_default(),
+    ] {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    Some(hasher.finalize().into())
+}
+
+/// Counters of a [`RequestDeduplicator`]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DedupStats {
+    /// Generations started for deduplicable requests
+    pub started: u64,
+    /// Requests that joined a generation in flight
+    pub hits: u64,
+    pub in_flight: usize,
+}
+
+/// Generations in flight by request fingerprint. Clones share them.
+#[derive(Clone, Default)]
+pub struct RequestDeduplicator {
+    inner: Arc<Inner>,
+}
+
+/// A generation's id and its shared stream
+type Generation = (u64, Arc<SharedChatStream>);
+
+#[derive(Default)]
+struct Inner {
+    in_flight: Mutex<HashMap<[u8; 32], Generation>>,
+    started: AtomicU64,
+    hits: AtomicU64,
+}
+
+impl RequestDeduplicator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stats(&self) -> DedupStats {
+        DedupStats {
+            started: self.inner.started.load(Ordering::Relaxed),
+            hits: self.inner.hits.load(Ordering::Relaxed),
+            in_flight: self.inner.in_flight.lock().unwrap_or_else(|e| e.into_inner()).len(),
+        }
+    }
+
+    /// All events of the generation with fingerprint `key`, if one is in
+    /// flight, until `cancel` fires
+    pub(crate) fn join(
+        &self,
+        key: &[u8; 32],
+        cancel: CancellationToken,
+    ) -> Option<BoxStream<'static, StreamEvent>> {
+        let shared = self
+            .inner
+            .in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(key)
+            .map(|(_, shared)| shared.clone())?;
+        let events = shared.subscribe();
+        // Everyone else may have left just before we subscribed
+        if shared.is_cancelled() {
+            return None;
+        }
+        self.inner.hits.fetch_add(1, Ordering::Relaxed);
+        Some(events.take_until(cancel.cancelled_owned()).boxed())
+    }
+
+    /// Share `events` under `key` until they end, and return them for the
+    /// caller that started them. `generation` cancels the provider request;
+    /// `cancel` only ends this caller's stream.
+    pub(crate) fn lead<S>(
+        &self,
+        key: [u8; 32],
+        events: S,
+        generation: CancellationToken,
+        cancel: CancellationToken,
+    ) -> BoxStream<'static, StreamEvent>
+    where
+        S: Stream<Item = StreamEvent> + Send + Unpin + 'static,
+    {
+        let id = self.inner.started.fetch_add(1, Ordering::Relaxed);
+        let entry = InFlight {
+            inner: self.inner.clone(),
+            key,
+            id,
+        };
+        let events = async_stream::stream! {
+            let _entry = entry;
+            let mut events = events;
+            while let Some(event) = events.next().await {
+                yield event;
+            }
+        };
+        // Locked before the generation starts, so a short one can't end
+        // before its entry is added
+        let mut in_flight = self.inner.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        let shared = Arc::new(SharedChatStream::replaying(events.boxed(), generation));
+        let subscription = shared.subscribe();
+        in_flight.entry(key).or_insert((id, shared));
+        subscription.take_until(cancel.cancelled_owned()).boxed()
+    }
+}
+
+/// Removes its generation from the in-flight map once the events are dropped
+struct InFlight {
+    inner: Arc<Inner>,
+    key: [u8; 32],
+    id: u64,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut in_flight = self.inner.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        if in_flight.get(&self.key).is_some_and(|(id, _)| *id == self.id) {
+            in_flight.remove(&self.key);
+        }
+    }
+}
@@ -1,21 +1,37 @@
+use crate::error::EngineError;
+use crate::mcp::McpTransport;
 use crate::service::OmniferenceService;
 use crate::router::Router;
-use crate::types::{ProviderConfig, ChatRequestIR, DiscoveredModel};
+use crate::types::{ProviderConfig, ChatRequestIR, DiscoveredModel, ModelRef};
 use futures_util::StreamExt;
 
-/// High-level engine for easy library usage
+/// High-level engine for easy library usage.
+///
+/// [`new`](Self::new) registers the built-in adapters (Anthropic, Cohere,
+/// Mistral, Ollama, OpenAI Chat Completions, OpenAI Responses and TGI); [`empty`](Self::empty)
+/// registers none. Clones, and engines and servers over the same
+/// [`OmniferenceCore`](crate::OmniferenceCore), share providers and models.
+#[derive(Clone)]
 pub struct OmniferenceEngine {
     service: OmniferenceService,
 }
 
 impl OmniferenceEngine {
-    /// Create a new engine with default configuration
+    /// Create a new engine with all built-in adapters registered
     pub fn new() -> Self {
         Self {
             service: OmniferenceService::new(),
         }
     }
 
+    /// Create an engine without any adapters; register them with
+    /// [`register_adapter`](Self::register_adapter) before adding providers
+    pub fn empty() -> Self {
+        Self {
+            service: OmniferenceService::empty(),
+        }
+    }
+
     /// Create an engine with a custom router
     pub fn with_router(router: Router) -> Self {
         Self {
@@ -23,17 +39,75 @@ impl OmniferenceEngine {
         }
     }
 
+    /// Create an engine over `core`, e.g. one an embedded server shares
+    pub fn from_core(core: crate::service::OmniferenceCore) -> Self {
+        Self {
+            service: OmniferenceService::from_core(core),
+        }
+    }
+
+    /// The state this engine shares with servers over it
+    pub fn core(&self) -> &crate::service::OmniferenceCore {
+        self.service.core()
+    }
+
     
     /// Register a provider configuration
-    pub async fn register_provider(&mut self, provider: ProviderConfig) -> Result<(), String> {
+    pub async fn register_provider(&mut self, provider: ProviderConfig) -> Result<(), EngineError> {
         self.service.register_provider(provider).await
     }
 
+    /// Add or replace the adapter for its provider kind (e.g. to swap in a
+    /// mock), returning the one it replaced
+    pub fn register_adapter(&self, adapter: std::sync::Arc<dyn crate::adapter::ChatAdapter>) -> Option<std::sync::Arc<dyn crate::adapter::ChatAdapter>> {
+        self.service.register_adapter(adapter)
+    }
+
+    /// Remove the adapter for `kind`
+    pub fn unregister_adapter(&self, kind: &crate::types::ProviderKind) -> Option<std::sync::Arc<dyn crate::adapter::ChatAdapter>> {
+        self.service.unregister_adapter(kind)
+    }
+
+    /// Provider kinds with a registered adapter
+    pub fn adapter_kinds(&self) -> Vec<crate::types::ProviderKind> {
+        self.service.adapter_kinds()
+    }
+
+    /// Register a constructor for `custom:<name>` providers. Registering a
+    /// provider of that kind builds its adapter from the endpoint's
+    /// `adapter_options`, so custom adapters can be enabled from configuration.
+    pub fn register_adapter_factory<F>(&self, name: impl Into<String>, factory: F) -> Option<crate::router::AdapterFactory>
+    where
+        F: Fn(&serde_json::Map<String, serde_json::Value>) -> Result<std::sync::Arc<dyn crate::adapter::ChatAdapter>, crate::adapter::AdapterError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.service.register_adapter_factory(name, factory)
+    }
+
+    /// The adapter serving `kind`, if any
+    pub fn adapter(&self, kind: &crate::types::ProviderKind) -> Option<std::sync::Arc<dyn crate::adapter::ChatAdapter>> {
+        self.service.adapters().get(kind)
+    }
+
     /// Discover all available models from registered providers
-    pub async fn discover_models(&mut self) -> Result<Vec<DiscoveredModel>, String> {
+    pub async fn discover_models(&mut self) -> Result<Vec<DiscoveredModel>, EngineError> {
         self.service.discover_models().await
     }
 
+    /// Discover all models, along with the providers whose discovery failed
+    /// or timed out
+    pub async fn discover_models_report(&mut self) -> Result<crate::service::Discovery, EngineError> {
+        self.service.discover_models_report().await
+    }
+
+    /// How long discovery waits for each provider's model list (10 seconds
+    /// by default)
+    pub async fn set_discovery_timeout(&self, timeout: std::time::Duration) {
+        self.service.set_discovery_timeout(timeout).await
+    }
+
     /// Get a specific model by ID
     pub async fn get_model(&self, model_id: &str) -> Option<DiscoveredModel> {
         self.service.get_model(model_id).await
@@ -44,40 +118,313 @@ impl OmniferenceEngine {
         self.service.list_models().await
     }
 
+    /// Resolve a model id, name or alias to a routable ModelRef
+    pub async fn resolve_model(&self, model: &str) -> Result<ModelRef, EngineError> {
+        self.service.resolve_model(model).await
+    }
+
+    /// Resolve `alias` as `target`, returning the target it replaced
+    pub async fn set_model_alias(&self, alias: impl Into<String>, target: impl Into<String>) -> Option<String> {
+        self.service.set_model_alias(alias, target).await
+    }
+
+    /// Prefer `provider`'s model when an unprefixed name matches several providers
+    pub async fn set_default_provider(&self, provider: Option<String>) {
+        self.service.set_default_provider(provider).await
+    }
+
+    /// Serve `model` when a request's model is empty, `default` or `auto`
+    pub async fn set_default_model(&self, model: Option<String>) {
+        self.service.set_default_model(model).await
+    }
+
+    /// Run `transformer` over the JSON bodies exchanged with `provider` (see
+    /// [`crate::types::payload`]); `None` removes it. Returns whether the
+    /// provider is registered.
+    pub async fn set_payload_transformer(
+        &self,
+        provider: &str,
+        transformer: Option<crate::types::PayloadTransformer>,
+    ) -> bool {
+        self.service.set_payload_transformer(provider, transformer).await
+    }
+
+    /// Connect to an MCP server and let the engine execute its tools during chat
+    ///
+    /// `allowed_tools` limits which of the server's tools are exposed to models
+    /// (`None` exposes all). Returns the names of the registered tools.
+    pub async fn register_mcp_server(
+        &self,
+        label: &str,
+        transport: McpTransport,
+        allowed_tools: Option<Vec<String>>,
+    ) -> Result<Vec<String>, EngineError> {
+        self.service.register_mcp_server(label, transport, allowed_tools).await
+    }
+
+    /// Register a Rust function as a tool the engine executes during chat
+    ///
+    /// ```rust,no_run
+    /// # async fn example(engine: omniference::OmniferenceEngine) {
+    /// engine.register_tool(
+    ///     "get_time",
+    ///     "Current UTC time as a Unix timestamp",
+    ///     serde_json::json!({ "type": "object", "properties": {} }),
+    ///     |_args| async move {
+    ///         let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap();
+    ///         Ok::<_, std::io::Error>(serde_json::json!(now.as_secs()))
+    ///     },
+    /// ).await;
+    /// # }
+    /// ```
+    pub async fn register_tool<F, Fut, E>(
+        &self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        schema: serde_json::Value,
+        handler: F,
+    ) where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<serde_json::Value, E>> + Send + 'static,
+        E: std::fmt::Display + 'static,
+    {
+        self.service.register_tool(name, description, schema, handler).await
+    }
+
+    /// Execute a chat request, running registered tools until the model
+    /// gives a final answer. Calls to unregistered tools end the stream with
+    /// an `unknown_tool` error event.
+    pub async fn chat_with_tools(
+        &self,
+        request: ChatRequestIR,
+    ) -> Result<impl futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin, EngineError> {
+        self.service.chat_with_tools(request).await
+    }
+
+    /// Cap the model round trips per chat request when executing tools
+    pub async fn set_max_tool_iterations(&self, max_iterations: usize) {
+        self.service.tools().write().await.set_max_iterations(max_iterations);
+    }
+
     /// Execute a chat request
+    ///
+    /// Besides content, the stream carries `StreamEvent::Status` progress updates
+    /// (queueing, model loading) that UIs can use to show activity. When MCP
+    /// tools are registered, their execution is reported with
+    /// `StreamEvent::ToolExecutionStart`/`ToolExecutionEnd`.
     pub async fn chat(
         &self,
         request: ChatRequestIR,
-    ) -> Result<impl futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin, String> {
+    ) -> Result<impl futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin, EngineError> {
         self.service.chat(request).await
     }
 
-    /// Execute a chat request and collect all messages into a string
-    pub async fn chat_complete(&self, request: ChatRequestIR) -> Result<String, String> {
-        let stream = self.chat(request).await?;
-        
-        let mut content = String::new();
-        tokio::pin!(stream);
-        
+    /// Execute a chat request once and share its events between several
+    /// consumers, e.g. a transcript view and a token counter
+    ///
+    /// ```rust,no_run
+    /// # async fn example(engine: omniference::OmniferenceEngine, request: omniference::ChatRequestIR) -> Result<(), omniference::EngineError> {
+    /// use futures_util::StreamExt;
+    ///
+    /// let shared = engine.chat_shared(request).await?;
+    /// let mut transcript = shared.subscribe();
+    /// let reply = shared.final_message();
+    /// tokio::spawn(async move {
+    ///     while let Some(event) = transcript.next().await {
+    ///         println!("{:?}", event);
+    ///     }
+    /// });
+    /// println!("{}", reply.await?.content);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn chat_shared(&self, request: ChatRequestIR) -> Result<crate::multiplex::SharedChatStream, EngineError> {
+        self.service.chat_shared(request).await
+    }
+
+    /// Run a chat request in the background: the handle returns right away
+    /// and can wait for the reply, report progress or cancel the request
+    ///
+    /// ```rust,no_run
+    /// # async fn example(engine: omniference::OmniferenceEngine, request: omniference::ChatRequestIR) -> Result<(), omniference::EngineError> {
+    /// let handle = engine.submit_background(request);
+    /// println!("submitted {}", handle.id());
+    /// let result = handle.await_result().await?;
+    /// println!("{}", result.reply.content);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn submit_background(&self, request: ChatRequestIR) -> crate::background::ResponseHandle {
+        self.service.submit_background(request)
+    }
+
+    /// A background request by id, while it runs and for a while after
+    pub fn background_response(&self, id: &str) -> Option<crate::background::ResponseHandle> {
+        self.service.background.get(id)
+    }
+
+    /// Generate images; providers without image support fail with
+    /// `AdapterError::Unsupported`
+    pub async fn generate_image(&self, request: crate::types::ImageRequestIR) -> Result<crate::types::ImageResponseIR, EngineError> {
+        self.service.generate_image(request).await
+    }
+
+    /// Count the prompt tokens `messages` would use on a model, e.g. to check
+    /// a conversation against the context window before sending it
+    pub async fn count_tokens(&self, model_alias: &str, messages: &[crate::types::Message]) -> Result<crate::tokens::TokenCount, EngineError> {
+        self.service.count_tokens(model_alias, messages).await
+    }
+
+    /// Synthesize speech; the audio is streamed as it arrives from the provider
+    pub async fn synthesize_speech(&self, request: crate::types::SpeechRequestIR) -> Result<crate::types::SpeechResponseIR, EngineError> {
+        self.service.synthesize_speech(request).await
+    }
+
+    /// Execute a chat request and return the reply text from its
+    /// `FinalMessage` (the concatenated deltas if the stream has none)
+    pub async fn chat_complete(&self, request: ChatRequestIR) -> Result<String, EngineError> {
+        Ok(self.chat_reply(request).await?.content)
+    }
+
+    /// Execute a chat request and return its complete reply: the content of
+    /// its `FinalMessage` (the concatenated deltas if the stream has none),
+    /// tool calls, finish reason and the model's refusal, if it refused
+    ///
+    /// ```rust,no_run
+    /// # async fn example(engine: omniference::OmniferenceEngine, request: omniference::ChatRequestIR) -> Result<(), omniference::EngineError> {
+    /// let reply = engine.chat_reply(request).await?;
+    /// match reply.refusal {
+    ///     Some(refusal) => println!("refused: {}", refusal),
+    ///     None => println!("{}", reply.content),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn chat_reply(&self, request: ChatRequestIR) -> Result<crate::multiplex::FinalReply, EngineError> {
+        let mut stream = self.chat(request).await?;
+        let mut deltas = String::new();
+        let mut reply = None;
+        let mut refusal: Option<String> = None;
         while let Some(event) = stream.next().await {
             match event {
-                crate::stream::StreamEvent::TextDelta { content: chunk } => {
-                    content.push_str(&chunk);
-                }
-                crate::stream::StreamEvent::FinalMessage { content: final_content, .. } => {
-                    content.push_str(&final_content);
+                crate::stream::StreamEvent::TextDelta { content } => deltas.push_str(&content),
+                crate::stream::StreamEvent::Refusal { text } => refusal.get_or_insert_with(String::new).push_str(&text),
+                crate::stream::StreamEvent::FinalMessage { content, tool_calls, finish_reason } => {
+                    reply = Some(crate::multiplex::FinalReply { content, tool_calls, finish_reason, refusal: None });
                 }
                 crate::stream::StreamEvent::Error { code, message } => {
-                    return Err(format!("{}: {}", code, message));
+                    return Err(EngineError::from_stream_error(code, message));
                 }
-                crate::stream::StreamEvent::Done => {
-                    break;
+                crate::stream::StreamEvent::Done => break,
+                _ => {}
+            }
+        }
+        let mut reply = reply.unwrap_or(crate::multiplex::FinalReply {
+            content: deltas,
+            ..Default::default()
+        });
+        reply.refusal = refusal;
+        Ok(reply)
+    }
+
+    /// Execute a chat request and parse the reply into `T`
+    ///
+    /// The JSON schema generated for `T` is sent as a `json_schema` response
+    /// format (strict when the schema allows it) to models that support JSON
+    /// output, and described in a system message otherwise. If the reply
+    /// doesn't parse, the request is retried once with the error appended.
+    ///
+    /// ```rust,no_run
+    /// # async fn example(engine: omniference::OmniferenceEngine, request: omniference::ChatRequestIR) -> Result<(), omniference::EngineError> {
+    /// #[derive(serde::Deserialize, schemars::JsonSchema)]
+    /// struct Weather {
+    ///     city: String,
+    ///     celsius: f32,
+    /// }
+    ///
+    /// let weather: Weather = engine.chat_structured(request).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "structured")]
+    pub async fn chat_structured<T>(&self, mut request: ChatRequestIR) -> Result<T, EngineError>
+    where
+        T: serde::de::DeserializeOwned + schemars::JsonSchema,
+    {
+        let supports_json = self
+            .get_model(&request.model.alias)
+            .await
+            .is_some_and(|m| m.capabilities.supports_json);
+        let (name, schema) = crate::structured::schema_for::<T>();
+        crate::structured::apply_schema(&mut request, name, schema, supports_json);
+
+        let content = self.collect_reply(request.clone()).await?;
+        let error = match crate::structured::parse_reply(&content) {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+
+        crate::structured::append_correction(&mut request, content, &error);
+        let content = self.collect_reply(request).await?;
+        crate::structured::parse_reply(&content).map_err(|e| EngineError::StructuredOutput {
+            message: e.to_string(),
+            content,
+        })
+    }
+
+    /// The reply text of a chat request, preferring the final message over
+    /// the concatenated deltas
+    #[cfg(feature = "structured")]
+    async fn collect_reply(&self, request: ChatRequestIR) -> Result<String, EngineError> {
+        let mut stream = self.chat(request).await?;
+        let mut deltas = String::new();
+        let mut final_content = None;
+        while let Some(event) = stream.next().await {
+            match event {
+                crate::stream::StreamEvent::TextDelta { content } => deltas.push_str(&content),
+                crate::stream::StreamEvent::FinalMessage { content, .. } => final_content = Some(content),
+                crate::stream::StreamEvent::Error { code, message } => {
+                    return Err(EngineError::from_stream_error(code, message));
                 }
+                crate::stream::StreamEvent::Done => break,
                 _ => {}
             }
         }
-        
-        Ok(content)
+        Ok(final_content.filter(|c| !c.is_empty()).unwrap_or(deltas))
+    }
+
+    /// Check every enabled provider end to end: model discovery, then a
+    /// one-token chat with its probe model, if configured (see [`crate::verify`])
+    pub async fn verify_providers(&self) -> crate::verify::VerificationReport {
+        self.service.verify_providers().await
+    }
+
+    /// Send `model_alias` the same short prompt twice with a fixed seed and
+    /// temperature 0 and report whether the replies matched, or why the
+    /// requests failed (see [`crate::verify`])
+    pub async fn probe_determinism(&self, model_alias: &str) -> crate::verify::DeterminismReport {
+        let mut report = crate::verify::DeterminismReport::new(model_alias);
+        let model = match self.resolve_model(model_alias).await {
+            Ok(model) => model,
+            Err(e) => return report.fail(e),
+        };
+        for _ in 0..2 {
+            match self.chat_reply(crate::verify::determinism_request(model.clone())).await {
+                Ok(reply) => report.reply(reply.content),
+                Err(e) => return report.fail(e),
+            }
+        }
+        report
+    }
+
+    /// Usage and cost totals by model, when the router has a pricing table
+    pub fn usage(&self) -> std::collections::BTreeMap<String, crate::pricing::ModelUsage> {
+        self.service.usage().snapshot()
+    }
+
+    /// How often output limits injected, clamped or cut off replies, by alias
+    pub fn output_limit_stats(&self) -> std::collections::BTreeMap<String, crate::guardrails::OutputLimitStats> {
+        self.service.output_guardrails().stats()
     }
 
     /// Get the underlying service for advanced usage
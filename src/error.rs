@@ -0,0 +1,79 @@
+use crate::adapter::AdapterError;
+use crate::mcp::McpError;
+
+/// Errors returned by the engine, service and server APIs.
+///
+/// Implements `std::error::Error`, so `?` converts it into `anyhow::Error`
+/// in applications that use anyhow.
+#[derive(thiserror::Error, Debug)]
+pub enum EngineError {
+    #[error("failed to register provider {provider}: {message}")]
+    ProviderRegistration { provider: String, message: String },
+    #[error("model not found: {0}")]
+    ModelNotFound(String),
+    /// The model name matches models of several providers
+    #[error("model {model} is ambiguous; candidates: {}", .candidates.join(", "))]
+    AmbiguousModel { model: String, candidates: Vec<String> },
+    #[error(transparent)]
+    Adapter(AdapterError),
+    #[error("request timed out")]
+    Timeout,
+    /// The client's request timeout (`ChatRequestIR::request_timeout`) ran out
+    #[error("{0}")]
+    DeadlineExceeded(String),
+    #[error("request was cancelled")]
+    Cancelled,
+    #[error("configuration error: {0}")]
+    Config(String),
+    #[error("mcp server {server}: {source}")]
+    Mcp { server: String, source: McpError },
+    #[error("request rejected by content policy: {}", .categories.join(", "))]
+    ContentPolicy { categories: Vec<String> },
+    /// The reply could not be parsed into the requested type, even after a retry
+    #[error("structured output did not match the schema: {message}")]
+    StructuredOutput { message: String, content: String },
+    /// The provider's concurrency limit was reached and the request could not be queued
+    #[error("provider {provider} is overloaded: {reason}")]
+    Overloaded { provider: String, reason: String },
+    /// The caller's API key spent its budget for the current window
+    #[error("API key {key} has spent ${spent_usd:.4} of its ${limit_usd:.2} budget; it resets at {resets_at}")]
+    BudgetExceeded { key: String, limit_usd: f64, spent_usd: f64, resets_at: u64 },
+}
+
+impl EngineError {
+    pub fn provider_registration<S: Into<String>>(provider: S, message: S) -> Self {
+        EngineError::ProviderRegistration {
+            provider: provider.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn config<S: Into<String>>(msg: S) -> Self {
+        EngineError::Config(msg.into())
+    }
+
+    pub fn overloaded(provider: impl Into<String>, reason: impl Into<String>) -> Self {
+        EngineError::Overloaded {
+            provider: provider.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Map an in-stream `StreamEvent::Error` onto the matching variant
+    pub fn from_stream_error(code: String, message: String) -> Self {
+        match code.as_str() {
+            "cancelled" => EngineError::Cancelled,
+            "deadline_exceeded" => EngineError::DeadlineExceeded(message),
+            _ => EngineError::Adapter(AdapterError::Provider { code, message }),
+        }
+    }
+}
+
+impl From<AdapterError> for EngineError {
+    fn from(err: AdapterError) -> Self {
+        match err {
+            AdapterError::Timeout => EngineError::Timeout,
+            other => EngineError::Adapter(other),
+        }
+    }
+}
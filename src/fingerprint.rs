@@ -0,0 +1,147 @@
+//! Request fingerprints
+//!
+//! [`ChatRequestIR::fingerprint`] hashes a canonical form of a request with
+//! SHA-256, so equal requests get the same 32 bytes in every process and on
+//! every machine. Response caches, sticky routing and deduplication of
+//! in-flight requests can key on it instead of ad hoc `Debug` output.
+//!
+//! The canonical form is the request's JSON serialization with object keys
+//! sorted at every level and no whitespace between tokens. Text is hashed
+//! verbatim: whitespace inside a message changes what the model sees, so it
+//! changes the fingerprint too. [`VOLATILE_FIELDS`] are left out, and
+//! [`Fingerprinter::excluding`] leaves out more:
+//!
+//! ```
+//! use omniference::ChatRequestIR;
+//!
+//! let mut request = ChatRequestIR::default();
+//! let key = request.fingerprinter().excluding(["sampling"]).finish();
+//! request.sampling.temperature = Some(0.2);
+//! assert_eq!(request.fingerprinter().excluding(["sampling"]).finish(), key);
+//! assert_ne!(request.fingerprint(), key);
+//! ```
+
+use crate::types::ChatRequestIR;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Fields that differ between otherwise equal requests and are never hashed.
+/// A dotted path names a field inside an object; `model.provider` holds the
+/// resolved endpoint and its credentials, not part of what was asked.
+pub const VOLATILE_FIELDS: &[&str] = &[
+    "stream",
+    "request_timeout",
+    "model.provider",
+    "metadata.request_id",
+    "metadata.queue_wait_ms",
+];
+
+/// Prefix of the hashed bytes; bumped if the canonical form ever changes
+const FINGERPRINT_VERSION: &[u8] = b"omniference-fingerprint-v1\n";
+
+impl ChatRequestIR {
+    /// SHA-256 of the request's canonical form, without [`VOLATILE_FIELDS`]
+    pub fn fingerprint(&self) -> [u8; 32] {
+        self.fingerprinter().finish()
+    }
+
+    /// Like [`fingerprint`](Self::fingerprint), but failing when the request
+    /// can't be serialized
+    pub fn try_fingerprint(&self) -> Result<[u8; 32], serde_json::Error> {
+        self.fingerprinter().try_finish()
+    }
+
+    /// A fingerprint that can leave out further fields
+    pub fn fingerprinter(&self) -> Fingerprinter<'_> {
+        Fingerprinter {
+            request: self,
+            excluded: VOLATILE_FIELDS.iter().map(|field| field.to_string()).collect(),
+        }
+    }
+}
+
+/// Builds the fingerprint of one request
+pub struct Fingerprinter<'a> {
+    request: &'a ChatRequestIR,
+    excluded: Vec<String>,
+}
+
+impl Fingerprinter<'_> {
+    /// Also leave out `fields`, given as top-level field names (`sampling`)
+    /// or dotted paths (`sampling.seed`, `metadata.user`)
+    pub fn excluding<I>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.excluded.extend(fields.into_iter().map(Into::into));
+        self
+    }
+
+    /// The fingerprint; a request that can't be serialized gets a random
+    /// one, so it matches no other request
+    pub fn finish(&self) -> [u8; 32] {
+        self.try_finish().unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Request can't be fingerprinted");
+            let mut random = [0u8; 32];
+            random[..16].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+            random[16..].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+            random
+        })
+    }
+
+    pub fn try_finish(&self) -> Result<[u8; 32], serde_json::Error> {
+        // The endpoint is never hashed, so it isn't serialized either
+        let mut request = self.request.clone();
+        request.model.provider = Default::default();
+        let mut value = serde_json::to_value(&request)?;
+        for field in &self.excluded {
+            remove_path(&mut value, field);
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(FINGERPRINT_VERSION);
+        write_canonical(&value, &mut hasher);
+        Ok(hasher.finalize().into())
+    }
+}
+
+fn remove_path(value: &mut Value, path: &str) {
+    let (parent, field) = match path.rsplit_once('.') {
+        Some((parent, field)) => (parent.split('.').try_fold(value, |value, key| value.get_mut(key)), field),
+        None => (Some(value), path),
+    };
+    if let Some(Value::Object(map)) = parent {
+        map.remove(field);
+    }
+}
+
+/// Compact JSON with sorted keys, whatever order the map keeps them in
+fn write_canonical(value: &Value, hasher: &mut Sha256) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            hasher.update(b"{");
+            for (index, (key, value)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    hasher.update(b",");
+                }
+                hasher.update(Value::String(key.clone()).to_string());
+                hasher.update(b":");
+                write_canonical(value, hasher);
+            }
+            hasher.update(b"}");
+        }
+        Value::Array(items) => {
+            hasher.update(b"[");
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    hasher.update(b",");
+                }
+                write_canonical(item, hasher);
+            }
+            hasher.update(b"]");
+        }
+        scalar => hasher.update(scalar.to_string()),
+    }
+}
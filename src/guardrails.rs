@@ -0,0 +1,218 @@
+//! Output guardrails
+//!
+//! [`OutputGuardrails`] cap how much a reply may produce, per alias. A
+//! `max_output_tokens` limit is sent as the request's `max_tokens` when the
+//! client didn't set one and lowers a higher one; a `max_output_bytes` limit
+//! cuts the reply's stream off once its text passes that size, stops the
+//! upstream request and ends the reply with finish reason `length`, as if the
+//! model had hit its token limit. Both show up in the request's metadata, as
+//! `Status` events on its stream and in the per-alias [`OutputLimitStats`].
+//! Without limits requests pass through untouched.
+
+use crate::stream::{PartialReply, StreamEvent};
+use crate::types::{ChatRequestIR, ModelRef};
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Request metadata saying how `max_tokens` was limited: `injected` or `clamped`
+pub const OUTPUT_TOKENS_LIMIT_METADATA: &str = "output_tokens_limit";
+
+/// `Status` state announcing the `max_tokens` a request was limited to
+pub const OUTPUT_TOKENS_LIMITED_STATE: &str = "output_tokens_limited";
+
+/// `Status` state of a reply cut off at its `max_output_bytes`
+pub const OUTPUT_TRUNCATED_STATE: &str = "output_truncated";
+
+/// How much one alias's replies may produce
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputLimits {
+    /// Highest `max_tokens` sent upstream, and the one sent when the client sets none
+    pub max_output_tokens: Option<u32>,
+    /// Longest reply in bytes of text, refusal and tool call arguments
+    pub max_output_bytes: Option<usize>,
+}
+
+impl OutputLimits {
+    pub fn with_max_output_tokens(mut self, tokens: u32) -> Self {
+        self.max_output_tokens = Some(tokens);
+        self
+    }
+
+    pub fn with_max_output_bytes(mut self, bytes: usize) -> Self {
+        self.max_output_bytes = Some(bytes);
+        self
+    }
+}
+
+/// How often one alias's limits kicked in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputLimitStats {
+    /// Requests without `max_tokens` that were sent the limit
+    pub injected: u64,
+    /// Requests whose `max_tokens` was lowered to the limit
+    pub clamped: u64,
+    /// Replies cut off at `max_output_bytes`
+    pub truncated: u64,
+}
+
+/// Per-alias output limits and how often they applied. Clones share the stats.
+#[derive(Clone, Debug, Default)]
+pub struct OutputGuardrails {
+    default_limits: OutputLimits,
+    limits: HashMap<String, OutputLimits>,
+    stats: Arc<Mutex<BTreeMap<String, OutputLimitStats>>>,
+}
+
+impl OutputGuardrails {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits for aliases without their own
+    pub fn with_default_limits(mut self, limits: OutputLimits) -> Self {
+        self.default_limits = limits;
+        self
+    }
+
+    /// Limits for one model, keyed by discovered id (`provider/model`) or bare model name
+    pub fn with_limits(mut self, alias: impl Into<String>, limits: OutputLimits) -> Self {
+        self.limits.insert(alias.into(), limits);
+        self
+    }
+
+    pub fn limits_for(&self, model: &ModelRef) -> OutputLimits {
+        self.limits
+            .get(&model.alias)
+            .or_else(|| self.limits.get(&model.model_id))
+            .copied()
+            .unwrap_or(self.default_limits)
+    }
+
+    /// How often the limits applied, by alias
+    pub fn stats(&self) -> BTreeMap<String, OutputLimitStats> {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn record(&self, alias: &str, update: impl FnOnce(&mut OutputLimitStats)) {
+        update(self.stats.lock().unwrap_or_else(|e| e.into_inner()).entry(alias.to_string()).or_default());
+    }
+
+    /// Limit `ir`'s `max_tokens` to its alias's `max_output_tokens`, returning
+    /// how its stream is to be guarded
+    pub(crate) fn apply(&self, ir: &mut ChatRequestIR) -> OutputGuard {
+        let limits = self.limits_for(&ir.model);
+        let mut guard = OutputGuard {
+            alias: ir.model.alias.clone(),
+            limited: None,
+            max_output_bytes: limits.max_output_bytes,
+        };
+        if let Some(limit) = limits.max_output_tokens {
+            let how = match ir.sampling.max_tokens {
+                None => Some("injected"),
+                Some(requested) if requested > limit => Some("clamped"),
+                Some(_) => None,
+            };
+            if let Some(how) = how {
+                ir.sampling.max_tokens = Some(limit);
+                ir.metadata.insert(OUTPUT_TOKENS_LIMIT_METADATA.to_string(), how.to_string());
+                self.record(&ir.model.alias, |stats| match how {
+                    "injected" => stats.injected += 1,
+                    _ => stats.clamped += 1,
+                });
+                guard.limited = Some((how, limit));
+            }
+        }
+        guard
+    }
+
+    /// Announce a limited `max_tokens` on `events` and cut them off at
+    /// `max_output_bytes`, cancelling `upstream`
+    pub(crate) fn guard<S>(
+        &self,
+        guard: OutputGuard,
+        upstream: CancellationToken,
+        mut events: S,
+    ) -> impl Stream<Item = StreamEvent> + Send
+    where
+        S: Stream<Item = StreamEvent> + Send + Unpin,
+    {
+        let guardrails = self.clone();
+        async_stream::stream! {
+            if let Some((how, limit)) = guard.limited {
+                yield StreamEvent::Status {
+                    state: OUTPUT_TOKENS_LIMITED_STATE.to_string(),
+                    detail: Some(format!("{} max_tokens={}", how, limit)),
+                };
+            }
+            let limit = guard.max_output_bytes.unwrap_or(usize::MAX);
+            let mut reply = PartialReply::default();
+            let mut open_calls: Vec<String> = Vec::new();
+            let mut sent = 0usize;
+            while let Some(event) = events.next().await {
+                let size = match &event {
+                    StreamEvent::TextDelta { content } => content.len(),
+                    StreamEvent::Refusal { text } => text.len(),
+                    StreamEvent::ToolCallStart { args_json, .. } => args_json.to_string().len(),
+                    StreamEvent::ToolCallDelta { args_delta_json, .. } => args_delta_json.to_string().len(),
+                    _ => 0,
+                };
+                if sent + size <= limit {
+                    sent += size;
+                    match &event {
+                        StreamEvent::ToolCallStart { id, .. } => open_calls.push(id.clone()),
+                        StreamEvent::ToolCallEnd { id } => open_calls.retain(|call| call != id),
+                        _ => {}
+                    }
+                    reply.observe(&event);
+                    yield event;
+                    continue;
+                }
+
+                // Keep what fits of a text delta, then end the reply here
+                if let StreamEvent::TextDelta { content } = &event {
+                    let mut end = limit - sent;
+                    while !content.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    if end > 0 {
+                        let event = StreamEvent::TextDelta { content: content[..end].to_string() };
+                        reply.observe(&event);
+                        yield event;
+                    }
+                }
+                upstream.cancel();
+                guardrails.record(&guard.alias, |stats| stats.truncated += 1);
+                tracing::warn!(model_alias = %guard.alias, limit, "Reply cut off at its output size limit");
+                for id in open_calls {
+                    yield StreamEvent::ToolCallEnd { id };
+                }
+                yield StreamEvent::Status {
+                    state: OUTPUT_TRUNCATED_STATE.to_string(),
+                    detail: Some(format!("max_output_bytes={}", limit)),
+                };
+                yield reply.final_message(Some("length".to_string()));
+                yield StreamEvent::Done;
+                return;
+            }
+        }
+    }
+}
+
+/// What [`OutputGuardrails::apply`] did to one request
+pub(crate) struct OutputGuard {
+    alias: String,
+    /// How `max_tokens` was limited, and to what
+    limited: Option<(&'static str, u32)>,
+    max_output_bytes: Option<usize>,
+}
+
+impl OutputGuard {
+    /// Whether the request's stream needs [`OutputGuardrails::guard`]
+    pub(crate) fn is_needed(&self) -> bool {
+        self.limited.is_some() || self.max_output_bytes.is_some()
+    }
+}
@@ -0,0 +1,141 @@
+//! Normalization of image inputs
+//!
+//! Clients send images as URLs, as data URIs that don't always name the
+//! right mime type, or as bare base64. Before a chat request is routed, every
+//! inline image is turned into a `data:` URI whose mime type comes from the
+//! image's own magic bytes. Images in a format providers don't accept (e.g.
+//! TIFF), data that isn't an image, and images larger than the configured
+//! limit are rejected up front with [`AdapterError::Invalid`], which the
+//! server answers with a 400. `http(s)` URLs are passed on unchanged.
+
+use crate::adapter::AdapterError;
+use crate::types::{ChatRequestIR, ContentPart};
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+
+/// Largest decoded image accepted unless the router sets its own limit (20 MiB)
+pub const DEFAULT_MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// Mime types providers accept for inline images
+pub const SUPPORTED_IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Shortest string taken for bare base64 rather than a (relative) URL
+const MIN_BARE_BASE64_LEN: usize = 16;
+
+/// Standard base64, with or without padding
+const LENIENT_BASE64: GeneralPurpose = GeneralPurpose::new(
+    &base64::alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// The image format `bytes` start with, by magic bytes; formats providers
+/// don't accept are named too, so they can be rejected by name
+pub fn sniff_mime(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n', ..] => Some("image/png"),
+        [0xff, 0xd8, 0xff, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [b'I', b'I', 0x2a, 0x00, ..] | [b'M', b'M', 0x00, 0x2a, ..] => Some("image/tiff"),
+        [b'B', b'M', ..] => Some("image/bmp"),
+        [_, _, _, _, b'f', b't', b'y', b'p', b'h', b'e', b'i', b'c', ..] => Some("image/heic"),
+        [_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f', ..] => Some("image/avif"),
+        _ => None,
+    }
+}
+
+/// An inline image: its data URI and mime type
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NormalizedImage {
+    pub url: String,
+    pub mime: String,
+}
+
+/// Normalize one image URL. Returns `None` for URLs that aren't inline
+/// images (`http(s)` and other schemes), which are left alone.
+pub fn normalize_image_url(url: &str, max_bytes: usize) -> Result<Option<NormalizedImage>, String> {
+    let url = url.trim();
+    let data = match url.strip_prefix("data:") {
+        Some(rest) => {
+            let Some((header, data)) = rest.split_once(',') else {
+                return Err("the data URI has no ',' before its data".to_string());
+            };
+            if !header.split(';').any(|param| param.eq_ignore_ascii_case("base64")) {
+                return Err("only base64 data URIs are supported for images".to_string());
+            }
+            data
+        }
+        None if looks_like_base64(url) => url,
+        None => return Ok(None),
+    };
+
+    let data: String = data.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    let padding = data.bytes().rev().take_while(|&b| b == b'=').count();
+    let decoded_len = (data.len() * 3 / 4).saturating_sub(padding);
+    if decoded_len > max_bytes {
+        return Err(format!(
+            "the image is {} bytes, more than the {} byte limit",
+            decoded_len, max_bytes
+        ));
+    }
+    let bytes = LENIENT_BASE64
+        .decode(&data)
+        .map_err(|e| format!("the image data is not valid base64: {}", e))?;
+    let mime = match sniff_mime(&bytes) {
+        Some(mime) if SUPPORTED_IMAGE_TYPES.contains(&mime) => mime,
+        Some(mime) => {
+            return Err(format!(
+                "{} images are not supported; use one of: {}",
+                mime,
+                SUPPORTED_IMAGE_TYPES.join(", ")
+            ))
+        }
+        None => {
+            return Err(format!(
+                "the image data is not a recognized image; use one of: {}",
+                SUPPORTED_IMAGE_TYPES.join(", ")
+            ))
+        }
+    };
+    // Providers expect padded base64
+    let data = match data.len() % 4 {
+        0 => data,
+        _ => base64::engine::general_purpose::STANDARD.encode(&bytes),
+    };
+    Ok(Some(NormalizedImage {
+        url: format!("data:{};base64,{}", mime, data),
+        mime: mime.to_string(),
+    }))
+}
+
+/// Normalize every image of `ir` in place, naming the offending image on error
+pub fn normalize_images(ir: &mut ChatRequestIR, max_bytes: usize) -> Result<(), AdapterError> {
+    for (message_index, message) in ir.messages.iter_mut().enumerate() {
+        for (part_index, part) in message.parts.iter_mut().enumerate() {
+            let ContentPart::ImageUrl { url, mime } = part else {
+                continue;
+            };
+            match normalize_image_url(url, max_bytes) {
+                Ok(Some(image)) => {
+                    *url = image.url;
+                    *mime = Some(image.mime);
+                }
+                Ok(None) => {}
+                Err(reason) => {
+                    return Err(AdapterError::Invalid(format!(
+                        "Invalid image in message {}, part {}: {}",
+                        message_index, part_index, reason
+                    )))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn looks_like_base64(url: &str) -> bool {
+    url.len() >= MIN_BARE_BASE64_LEN
+        && url
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'=' | b'\r' | b'\n'))
+}
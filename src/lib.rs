@@ -23,9 +23,8 @@
 //! 
 //! ## Quick Start (Library Usage)
 //! 
-//! ```rust
-//! use omniference::{OmniferenceEngine, types::{ProviderConfig, ProviderKind, ProviderEndpoint}};
-//! use std::sync::Arc;
+//! ```rust,no_run
+//! use omniference::{OmniferenceEngine, types::{ChatRequestIR, ProviderConfig, ProviderKind, ProviderEndpoint}};
 //! 
 //! #[tokio::main]
 //! async fn main() -> anyhow::Result<()> {
@@ -41,21 +40,24 @@
 //!             api_key: None,
 //!             extra_headers: std::collections::BTreeMap::new(),
 //!             timeout: Some(30000),
+//!             ..Default::default()
 //!         },
 //!         enabled: true,
-//!     }).await;
+//!         ..Default::default()
+//!     }).await?;
 //!     
 //!     // Create chat request
-//!     let request = omniference::types::ChatRequestIR {
-//!
-//!         // ... request details
+//!     let request = ChatRequestIR {
+//!         stream: true,
+//!         // ... model and messages
+//!         ..Default::default()
 //!     };
 //!     
-//!     // Execute chat
+//!     // Execute chat; errors are `omniference::EngineError`
 //!     let stream = engine.chat(request).await?;
 //!     
 //!     // Process stream...
-//!
+//!     # drop(stream);
 //!     
 //!     Ok(())
 //! }
@@ -68,30 +70,110 @@
 pub mod adapter;
 pub mod router;
 pub mod stream;
+pub mod multiplex;
+pub mod background;
+pub mod backpressure;
+pub mod dedup;
 pub mod types;
+pub mod templates;
+pub mod fingerprint;
 
 // Service layer
 pub mod service;
+pub mod config_file;
+pub mod tenant;
+pub mod verify;
+pub mod warmup;
 
-// Interface layers  
+// Interface layers: the HTTP server and its skins need the `server` feature;
+// the bot and gRPC skins have features of their own
 pub mod skins;
+#[cfg(feature = "server")]
 pub mod server;
+#[cfg(feature = "server")]
+pub mod cors;
+pub mod passthrough;
 
-// Provider adapters
+// Provider adapters, and conformance checks for adapter authors
 pub mod adapters;
+#[cfg(feature = "server")]
+pub mod testing;
+
+// Content moderation
+pub mod moderation;
+
+// Image input normalization
+pub mod images;
+
+// Tool execution
+pub mod mcp;
+pub mod tools;
+
+// Load balancing, request queueing and smart routing
+pub mod balancer;
+pub mod limiter;
+pub mod routing;
+
+// Token counting, context-window management and output limits
+pub mod tokens;
+pub mod context;
+pub mod guardrails;
+
+// Cost tracking and budgets per API key
+pub mod pricing;
+pub mod budget;
+
+// Conversation persistence
+pub mod store;
+
+// JSON-mode reply validation
+pub mod validation;
+
+// Audit trail and routing traces of chat requests
+pub mod audit;
+pub mod trace;
+
+// Structured outputs
+#[cfg(feature = "structured")]
+pub mod structured;
 
 // High-level API
 pub mod engine;
+pub mod error;
 
 
 // Re-export common types and functions for convenience
 pub use adapter::*;
 pub use router::*;
 pub use stream::*;
+pub use multiplex::*;
+pub use background::*;
+pub use dedup::*;
 pub use types::*;
+pub use fingerprint::*;
+pub use config_file::*;
 pub use service::*;
-pub use server::*;
+#[cfg(feature = "server")]
+pub use server::{OmniferenceServer, OmniferenceServerBuilder};
+#[cfg(feature = "server")]
+pub use cors::CorsConfig;
+pub use passthrough::PassthroughRoute;
 pub use engine::*;
+pub use error::*;
+pub use moderation::*;
+pub use mcp::*;
+pub use tools::*;
+pub use tokens::*;
+pub use context::*;
+pub use guardrails::*;
+pub use balancer::*;
+pub use limiter::*;
+pub use routing::*;
+pub use pricing::*;
+pub use budget::*;
+pub use store::*;
+pub use validation::*;
+pub use audit::*;
 
 #[cfg(test)]
 pub mod config;
@@ -0,0 +1,202 @@
+//! Per-provider concurrency limits
+//!
+//! Providers configured with `max_concurrent_requests` get a semaphore in the
+//! router. Requests beyond the limit wait in a queue, up to
+//! `max_queue_depth` of them and for at most `queue_timeout_ms`; others are
+//! rejected with [`EngineError::Overloaded`]. A permit is held until the
+//! response (streamed or not) has been fully consumed.
+
+use crate::error::EngineError;
+use crate::types::ProviderConfig;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+/// Concurrency limit for one provider
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConcurrencyLimit {
+    pub max_concurrent_requests: usize,
+    /// Requests allowed to wait for a slot; `None` is unbounded
+    pub max_queue_depth: Option<usize>,
+    /// Longest a request may wait for a slot; `None` waits indefinitely
+    pub queue_timeout: Option<Duration>,
+}
+
+impl ConcurrencyLimit {
+    /// The limit configured for a provider, if any
+    pub fn from_config(config: &ProviderConfig) -> Option<Self> {
+        Some(Self {
+            max_concurrent_requests: config.max_concurrent_requests?,
+            max_queue_depth: config.max_queue_depth,
+            queue_timeout: config.queue_timeout_ms.map(Duration::from_millis),
+        })
+    }
+}
+
+/// Counters for one provider, keyed by provider name in [`ConcurrencyLimiter::stats`]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct QueueStats {
+    pub in_flight: usize,
+    pub queued: usize,
+    pub admitted: u64,
+    /// Turned away because the queue was full
+    pub rejected: u64,
+    /// Gave up after waiting `queue_timeout`
+    pub timed_out: u64,
+    /// Summed queue wait of admitted requests
+    pub total_wait_ms: u64,
+    pub max_wait_ms: u64,
+}
+
+struct ProviderQueue {
+    limit: ConcurrencyLimit,
+    semaphore: Arc<Semaphore>,
+    stats: Mutex<QueueStats>,
+}
+
+impl ProviderQueue {
+    fn stats(&self) -> std::sync::MutexGuard<'_, QueueStats> {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Leaves the queue when the waiting request is admitted, rejected or dropped
+struct Waiting<'a>(&'a ProviderQueue);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.stats().queued -= 1;
+    }
+}
+
+/// A slot on a limited provider; the slot is freed on drop
+pub struct QueuePermit {
+    _permit: OwnedSemaphorePermit,
+    provider: String,
+    waited: Duration,
+}
+
+impl QueuePermit {
+    pub fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    /// Time spent in the queue before the request was admitted
+    pub fn waited(&self) -> Duration {
+        self.waited
+    }
+}
+
+/// Concurrency limits and queues for all limited providers
+#[derive(Clone, Default)]
+pub struct ConcurrencyLimiter {
+    queues: Arc<Mutex<HashMap<String, Arc<ProviderQueue>>>>,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the limits. Queues whose limit is unchanged keep their
+    /// in-flight and waiting requests.
+    pub fn set_limits(&self, limits: impl IntoIterator<Item = (String, ConcurrencyLimit)>) {
+        let mut queues = self.queues.lock().unwrap_or_else(|e| e.into_inner());
+        let mut updated = HashMap::new();
+        for (provider, limit) in limits {
+            let queue = match queues.remove(&provider) {
+                Some(queue) if queue.limit == limit => queue,
+                _ => Arc::new(ProviderQueue {
+                    semaphore: Arc::new(Semaphore::new(limit.max_concurrent_requests)),
+                    limit,
+                    stats: Mutex::new(QueueStats::default()),
+                }),
+            };
+            updated.insert(provider, queue);
+        }
+        *queues = updated;
+    }
+
+    pub fn is_limited(&self, provider: &str) -> bool {
+        self.queues.lock().unwrap_or_else(|e| e.into_inner()).contains_key(provider)
+    }
+
+    pub fn stats(&self) -> BTreeMap<String, QueueStats> {
+        let queues = self.queues.lock().unwrap_or_else(|e| e.into_inner());
+        queues
+            .iter()
+            .map(|(provider, queue)| {
+                let mut stats = queue.stats().clone();
+                stats.in_flight = queue.limit.max_concurrent_requests - queue.semaphore.available_permits();
+                (provider.clone(), stats)
+            })
+            .collect()
+    }
+
+    /// Wait for a slot on `provider`. Returns `None` for providers without a
+    /// limit, and [`EngineError::Overloaded`] when the queue is full or the
+    /// wait times out.
+    pub async fn acquire(
+        &self,
+        provider: &str,
+        cancel: &CancellationToken,
+    ) -> Result<Option<QueuePermit>, EngineError> {
+        let Some(queue) = self.queues.lock().unwrap_or_else(|e| e.into_inner()).get(provider).cloned() else {
+            return Ok(None);
+        };
+        let started = Instant::now();
+        let permit = match queue.semaphore.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                let waiting = {
+                    let mut stats = queue.stats();
+                    if queue.limit.max_queue_depth.is_some_and(|depth| stats.queued >= depth) {
+                        stats.rejected += 1;
+                        return Err(EngineError::overloaded(provider, "request queue is full"));
+                    }
+                    stats.queued += 1;
+                    Waiting(&queue)
+                };
+                let acquire = queue.semaphore.clone().acquire_owned();
+                let acquired = tokio::select! {
+                    _ = cancel.cancelled() => return Err(EngineError::Cancelled),
+                    acquired = async {
+                        match queue.limit.queue_timeout {
+                            Some(timeout) => tokio::time::timeout(timeout, acquire).await.ok(),
+                            None => Some(acquire.await),
+                        }
+                    } => acquired,
+                };
+                drop(waiting);
+                match acquired {
+                    Some(Ok(permit)) => permit,
+                    Some(Err(_)) => return Err(EngineError::overloaded(provider, "request queue was closed")),
+                    None => {
+                        queue.stats().timed_out += 1;
+                        return Err(EngineError::overloaded(provider, "timed out waiting in the request queue"));
+                    }
+                }
+            }
+        };
+
+        let waited = started.elapsed();
+        let wait_ms = waited.as_millis() as u64;
+        {
+            let mut stats = queue.stats();
+            stats.admitted += 1;
+            stats.total_wait_ms += wait_ms;
+            stats.max_wait_ms = stats.max_wait_ms.max(wait_ms);
+        }
+        if wait_ms > 0 {
+            tracing::debug!(provider = %provider, wait_ms, "Request admitted after queueing");
+        }
+        Ok(Some(QueuePermit {
+            _permit: permit,
+            provider: provider.to_string(),
+            waited,
+        }))
+    }
+}
@@ -1,35 +1,103 @@
-use omniference::{server::OmniferenceServer, types::{ProviderConfig, ProviderKind}};
+use omniference::{
+    config_file::{providers_from_env, ConfigFile},
+    server::{OmniferenceServer, OmniferenceServerBuilder},
+    types::{ProviderConfig, ProviderKind},
+};
+
+const USAGE: &str = "usage: omniference [--config <path>] [--check]\n       omniference [--config <path>] probe-seed [<model>...]\n       omniference config validate [<path>]";
+
+/// What to do once the providers are set up
+enum Command {
+    Serve,
+    Check,
+    /// Probe these models, or every discovered one
+    ProbeSeed(Vec<String>),
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut config_path = std::env::var("OMNIFERENCE_CONFIG").ok();
+    let mut command = Command::Serve;
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] => {}
+        ["--check"] => command = Command::Check,
+        ["--config", path] => config_path = Some(path.to_string()),
+        ["--config", path, "--check"] | ["--check", "--config", path] => {
+            config_path = Some(path.to_string());
+            command = Command::Check;
+        }
+        ["probe-seed", models @ ..] => command = Command::ProbeSeed(models.iter().map(|model| model.to_string()).collect()),
+        ["--config", path, "probe-seed", models @ ..] => {
+            config_path = Some(path.to_string());
+            command = Command::ProbeSeed(models.iter().map(|model| model.to_string()).collect());
+        }
+        ["config", "validate"] => return validate_config(config_path.as_deref()),
+        ["config", "validate", path] => return validate_config(Some(path)),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+
     // Initialize logging
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
+    // Providers from the config file and from OMNIFERENCE_* variables
+    let mut config = match &config_path {
+        Some(path) => ConfigFile::load(path)?,
+        None => ConfigFile::default(),
+    };
+    let from_env = config.merge_providers(providers_from_env()?);
+    for provider in config.providers.iter().filter(|provider| from_env.contains(&provider.name)) {
+        let endpoint = &provider.endpoint;
+        tracing::info!(
+            "Provider {} configured from environment: kind {}, base URL {}, API key {}",
+            provider.name,
+            endpoint.kind,
+            endpoint.base_url,
+            if endpoint.api_key.is_some() { "set" } else { "not set" },
+        );
+    }
+
     // Create and configure server
-    let mut server = OmniferenceServer::new();
-    
-    // Add Ollama provider
-    server.add_provider(ProviderConfig {
-        name: "ollama".to_string(),
-        endpoint: omniference::types::ProviderEndpoint {
-            kind: ProviderKind::Ollama,
-            base_url: "http://localhost:11434".to_string(),
-            api_key: None,
-            extra_headers: std::collections::BTreeMap::new(),
-            timeout: Some(30000),
-        },
-        enabled: true,
-    }).await.map_err(|e| anyhow::anyhow!(e))?;
+    let mut server = match config_path.is_some() || !from_env.is_empty() {
+        true => OmniferenceServerBuilder::new().with_config(config).build(),
+        false => {
+            let mut server = OmniferenceServer::new();
+
+            // Add Ollama provider
+            server.add_provider(ProviderConfig {
+                name: "ollama".to_string(),
+                endpoint: omniference::types::ProviderEndpoint {
+                    kind: ProviderKind::Ollama,
+                    base_url: "http://localhost:11434".to_string(),
+                    api_key: None,
+                    extra_headers: std::collections::BTreeMap::new(),
+                    timeout: Some(30000),
+                    ..Default::default()
+                },
+                enabled: true,
+                ..Default::default()
+            }).await?;
+            server
+        }
+    };
+
+    match command {
+        Command::Serve => {}
+        Command::Check => return check_providers(&server).await,
+        Command::ProbeSeed(models) => return probe_seed(&server, models).await,
+    }
 
     // Alternative usage with builder pattern:
     /*
     let mut server = OmniferenceServerBuilder::new()
-        .with_adapter(Arc::new(OllamaAdapter))
+        .with_provider(provider_config)
+        .with_route("/healthz", axum::routing::get(|| async { "ok" }))
         .build();
-    
-    server.add_provider(provider_config).await?;
     */
 
     // Run the server
@@ -38,4 +106,63 @@ async fn main() -> anyhow::Result<()> {
     server.run(addr).await?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// `omniference config validate`: check the config file, if any, and the
+/// provider environment variables, reporting every problem and exiting 1 if
+/// there are any
+fn validate_config(path: Option<&str>) -> anyhow::Result<()> {
+    let mut failed = false;
+    if let Some(path) = path {
+        match ConfigFile::load(path) {
+            Ok(config) => println!("{}: ok ({} providers)", path, config.providers.len()),
+            Err(error) => {
+                eprintln!("{}", error);
+                failed = true;
+            }
+        }
+    }
+    match providers_from_env() {
+        Ok(providers) => println!("environment: ok ({} providers)", providers.len()),
+        Err(error) => {
+            eprintln!("{}", error);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// `omniference --check`: verify every enabled provider end to end, print
+/// the report as JSON and exit 1 if any provider failed
+async fn check_providers(server: &OmniferenceServer) -> anyhow::Result<()> {
+    let report = server.engine().verify_providers().await;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if !report.is_ok() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// `omniference probe-seed`: send each model the same seeded request twice,
+/// print the reports as JSON and exit 1 if any model replied differently or
+/// failed
+async fn probe_seed(server: &OmniferenceServer, mut models: Vec<String>) -> anyhow::Result<()> {
+    let discovered = server.service().discover_models().await?;
+    if models.is_empty() {
+        models = discovered.into_iter().map(|model| model.id).collect();
+        models.sort();
+    }
+    let engine = server.engine();
+    let mut reports = Vec::new();
+    for model in &models {
+        reports.push(engine.probe_determinism(model).await);
+    }
+    println!("{}", serde_json::to_string_pretty(&reports)?);
+    if !reports.iter().all(|report| report.is_deterministic()) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,393 @@
+//! Model Context Protocol client
+//!
+//! Connects to MCP servers over stdio (a spawned process speaking
+//! line-delimited JSON-RPC) or streamable HTTP, lists their tools and calls
+//! them. Register a server with `OmniferenceEngine::register_mcp_server` to
+//! let the engine execute its tools during chat.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, Mutex};
+
+/// Protocol revision requested during initialization
+pub const MCP_PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// How to reach an MCP server
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum McpTransport {
+    /// Spawn `command` and talk JSON-RPC over its stdin/stdout
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+    },
+    /// Streamable HTTP endpoint (e.g. `https://example.com/mcp`)
+    StreamableHttp {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+impl McpTransport {
+    pub fn stdio(command: impl Into<String>, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        McpTransport::Stdio {
+            command: command.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            env: BTreeMap::new(),
+        }
+    }
+
+    pub fn http(url: impl Into<String>) -> Self {
+        McpTransport::StreamableHttp {
+            url: url.into(),
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum McpError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("http error: {0}")]
+    Http(String),
+    #[error("server error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("invalid response: {0}")]
+    Protocol(String),
+    #[error("connection closed")]
+    Closed,
+}
+
+impl From<reqwest::Error> for McpError {
+    fn from(e: reqwest::Error) -> Self {
+        McpError::Http(e.to_string())
+    }
+}
+
+/// A tool advertised by an MCP server
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct McpToolInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "inputSchema", default = "empty_object_schema")]
+    pub input_schema: Value,
+}
+
+fn empty_object_schema() -> Value {
+    json!({ "type": "object" })
+}
+
+/// Result of a tool call, flattened to text for the model
+#[derive(Clone, Debug, PartialEq)]
+pub struct McpToolOutput {
+    pub content: String,
+    pub is_error: bool,
+}
+
+impl McpToolOutput {
+    /// Text items are concatenated; other content (images, resources) is
+    /// passed through as JSON.
+    fn from_result(result: &Value) -> Self {
+        let content = result
+            .get("content")
+            .and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .map(|item| match item.get("text").and_then(Value::as_str) {
+                        Some(text) if item.get("type").and_then(Value::as_str) == Some("text") => text.to_string(),
+                        _ => item.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+        let content = match (content.is_empty(), result.get("structuredContent")) {
+            (true, Some(structured)) => structured.to_string(),
+            _ => content,
+        };
+        Self {
+            content,
+            is_error: result.get("isError").and_then(Value::as_bool).unwrap_or(false),
+        }
+    }
+}
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, McpError>>>>>;
+
+enum Connection {
+    Stdio {
+        stdin: Arc<Mutex<ChildStdin>>,
+        pending: Pending,
+        // Kept so the process is killed when the client is dropped
+        _child: Child,
+    },
+    Http {
+        client: reqwest::Client,
+        url: String,
+        headers: BTreeMap<String, String>,
+        session_id: std::sync::Mutex<Option<String>>,
+    },
+}
+
+/// Connection to a single MCP server
+pub struct McpClient {
+    connection: Connection,
+    next_id: AtomicU64,
+    server_info: Value,
+}
+
+impl McpClient {
+    /// Connect and perform the initialization handshake
+    pub async fn connect(transport: McpTransport) -> Result<Self, McpError> {
+        let connection = match transport {
+            McpTransport::Stdio { command, args, env } => Self::spawn(&command, &args, &env)?,
+            McpTransport::StreamableHttp { url, headers } => Connection::Http {
+                client: reqwest::Client::new(),
+                url,
+                headers,
+                session_id: std::sync::Mutex::new(None),
+            },
+        };
+        let mut client = Self {
+            connection,
+            next_id: AtomicU64::new(1),
+            server_info: Value::Null,
+        };
+
+        let result = client
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": MCP_PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "omniference", "version": env!("CARGO_PKG_VERSION") },
+                }),
+            )
+            .await?;
+        client.server_info = result.get("serverInfo").cloned().unwrap_or(Value::Null);
+        client.notify("notifications/initialized").await?;
+        Ok(client)
+    }
+
+    /// `serverInfo` reported during initialization
+    pub fn server_info(&self) -> &Value {
+        &self.server_info
+    }
+
+    /// List all tools, following pagination cursors
+    pub async fn list_tools(&self) -> Result<Vec<McpToolInfo>, McpError> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.request("tools/list", params).await?;
+            let page: Vec<McpToolInfo> = serde_json::from_value(result.get("tools").cloned().unwrap_or(json!([])))
+                .map_err(|e| McpError::Protocol(e.to_string()))?;
+            tools.extend(page);
+            cursor = result.get("nextCursor").and_then(Value::as_str).map(str::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<McpToolOutput, McpError> {
+        let result = self
+            .request("tools/call", json!({ "name": name, "arguments": arguments }))
+            .await?;
+        Ok(McpToolOutput::from_result(&result))
+    }
+
+    fn spawn(command: &str, args: &[String], env: &BTreeMap<String, String>) -> Result<Connection, McpError> {
+        let mut child = Command::new(command)
+            .args(args)
+            .envs(env)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdin = Arc::new(Mutex::new(child.stdin.take().ok_or(McpError::Closed)?));
+        let stdout = child.stdout.take().ok_or(McpError::Closed)?;
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+
+        let reader_pending = pending.clone();
+        let reader_stdin = stdin.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                    tracing::debug!(%line, "Ignoring non-JSON output from MCP server");
+                    continue;
+                };
+                if message.get("method").is_some() {
+                    // Server-initiated request or notification
+                    if let Some(reply) = reply_to_server_request(&message) {
+                        let mut stdin = reader_stdin.lock().await;
+                        let _ = stdin.write_all(format!("{}\n", reply).as_bytes()).await;
+                        let _ = stdin.flush().await;
+                    }
+                    continue;
+                }
+                if let Some(id) = message.get("id").and_then(Value::as_u64) {
+                    if let Some(tx) = reader_pending.lock().await.remove(&id) {
+                        let _ = tx.send(parse_response(message));
+                    }
+                }
+            }
+            // Fail anything still waiting once the process exits
+            for (_, tx) in reader_pending.lock().await.drain() {
+                let _ = tx.send(Err(McpError::Closed));
+            }
+        });
+
+        Ok(Connection::Stdio {
+            stdin,
+            pending,
+            _child: child,
+        })
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+
+        match &self.connection {
+            Connection::Stdio { stdin, pending, .. } => {
+                let (tx, rx) = oneshot::channel();
+                pending.lock().await.insert(id, tx);
+                {
+                    let mut stdin = stdin.lock().await;
+                    stdin.write_all(format!("{}\n", message).as_bytes()).await?;
+                    stdin.flush().await?;
+                }
+                rx.await.map_err(|_| McpError::Closed)?
+            }
+            Connection::Http { .. } => {
+                let response = self.post(&message).await?;
+                let is_sse = response
+                    .headers()
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .is_some_and(|v| v.starts_with("text/event-stream"));
+                if is_sse {
+                    read_sse_response(response, id).await
+                } else {
+                    parse_response(response.json().await?)
+                }
+            }
+        }
+    }
+
+    async fn notify(&self, method: &str) -> Result<(), McpError> {
+        let message = json!({ "jsonrpc": "2.0", "method": method });
+        match &self.connection {
+            Connection::Stdio { stdin, .. } => {
+                let mut stdin = stdin.lock().await;
+                stdin.write_all(format!("{}\n", message).as_bytes()).await?;
+                stdin.flush().await?;
+            }
+            Connection::Http { .. } => {
+                self.post(&message).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn post(&self, message: &Value) -> Result<reqwest::Response, McpError> {
+        let Connection::Http {
+            client,
+            url,
+            headers,
+            session_id,
+        } = &self.connection
+        else {
+            unreachable!("post is only used by the HTTP transport");
+        };
+
+        let mut request = client
+            .post(url)
+            .header(reqwest::header::ACCEPT, "application/json, text/event-stream")
+            .header("MCP-Protocol-Version", MCP_PROTOCOL_VERSION)
+            .json(message);
+        for (key, value) in headers {
+            request = request.header(key, value);
+        }
+        if let Some(session) = session_id.lock().unwrap().clone() {
+            request = request.header("Mcp-Session-Id", session);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(McpError::Http(format!("{} returned {}", url, response.status())));
+        }
+        if let Some(session) = response.headers().get("Mcp-Session-Id").and_then(|v| v.to_str().ok()) {
+            *session_id.lock().unwrap() = Some(session.to_string());
+        }
+        Ok(response)
+    }
+}
+
+fn parse_response(message: Value) -> Result<Value, McpError> {
+    if let Some(error) = message.get("error") {
+        return Err(McpError::Rpc {
+            code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+            message: error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error")
+                .to_string(),
+        });
+    }
+    message
+        .get("result")
+        .cloned()
+        .ok_or_else(|| McpError::Protocol("response has neither result nor error".to_string()))
+}
+
+/// Answer pings; reject other server-to-client requests we don't support
+fn reply_to_server_request(message: &Value) -> Option<Value> {
+    let id = message.get("id")?;
+    Some(match message.get("method").and_then(Value::as_str) {
+        Some("ping") => json!({ "jsonrpc": "2.0", "id": id, "result": {} }),
+        _ => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32601, "message": "method not supported by client" },
+        }),
+    })
+}
+
+/// Read an SSE response body until the JSON-RPC response with `id` arrives
+async fn read_sse_response(mut response: reqwest::Response, id: u64) -> Result<Value, McpError> {
+    let mut buffer = String::new();
+    while let Some(chunk) = response.chunk().await? {
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+        while let Some(end) = buffer.find('\n') {
+            let line: String = buffer.drain(..=end).collect();
+            let Some(data) = line.trim_end().strip_prefix("data:") else {
+                continue;
+            };
+            if let Ok(message) = serde_json::from_str::<Value>(data.trim_start()) {
+                if message.get("id").and_then(Value::as_u64) == Some(id) {
+                    return parse_response(message);
+                }
+            }
+        }
+    }
+    Err(McpError::Closed)
+}
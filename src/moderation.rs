@@ -0,0 +1,263 @@
+//! Content moderation
+//!
+//! [`ModerationClient`] calls an OpenAI-compatible `/moderations` endpoint and
+//! backs the server's moderation route. [`ContentFilter`]s run in the router
+//! before a chat request reaches its adapter; a flagged request is rejected
+//! with [`EngineError::ContentPolicy`] and never spends provider tokens.
+
+use crate::adapter::AdapterError;
+use crate::adapters::openai_compat::OpenAIAdapter;
+use crate::error::EngineError;
+use crate::types::{ChatRequestIR, ContentPart, ProviderEndpoint, Role};
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// `POST /moderations` request body
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModerationRequest {
+    pub input: ModerationInput,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ModerationInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModerationResponse {
+    pub id: String,
+    pub model: String,
+    pub results: Vec<ModerationResult>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ModerationResult {
+    pub flagged: bool,
+    #[serde(default)]
+    pub categories: BTreeMap<String, bool>,
+    #[serde(default)]
+    pub category_scores: BTreeMap<String, f64>,
+}
+
+/// Client for an OpenAI-compatible moderation endpoint
+#[derive(Clone)]
+pub struct ModerationClient {
+    endpoint: ProviderEndpoint,
+    model: Option<String>,
+    client: reqwest::Client,
+}
+
+impl ModerationClient {
+    pub fn new(endpoint: ProviderEndpoint) -> Self {
+        Self {
+            endpoint,
+            model: None,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Moderation model used when the request doesn't name one
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub async fn moderate(&self, request: &ModerationRequest) -> Result<ModerationResponse, AdapterError> {
+        let mut body = request.clone();
+        if body.model.is_none() {
+            body.model = self.model.clone();
+        }
+
+        let url = OpenAIAdapter::endpoint_url(&self.endpoint, "/moderations");
+        let mut http = self.client.post(&url).json(&body);
+        if let Some(timeout) = self.endpoint.timeout {
+            http = http.timeout(std::time::Duration::from_millis(timeout));
+        }
+        if let Some(token) = self.endpoint.bearer_token().await? {
+            http = http.header("Authorization", format!("Bearer {}", token));
+        }
+        for (key, value) in self.endpoint.headers().await? {
+            http = http.header(key, value);
+        }
+
+        let resp = http
+            .send()
+            .await
+            .map_err(|e| AdapterError::Http(format!("Failed to send moderation request: {}", e)))?;
+        let status = resp.status();
+        let text = resp
+            .text()
+            .await
+            .map_err(|e| AdapterError::Http(format!("Failed to read moderation response: {}", e)))?;
+        if !status.is_success() {
+            return Err(AdapterError::Provider {
+                code: status.as_u16().to_string(),
+                message: text,
+            });
+        }
+        serde_json::from_str(&text)
+            .map_err(|e| AdapterError::Internal(format!("Failed to parse moderation response: {}", e)))
+    }
+}
+
+/// Verdict of a [`ContentFilter`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FilterDecision {
+    pub flagged: bool,
+    pub categories: Vec<String>,
+}
+
+/// Pre-flight check on the user content of a chat request
+#[async_trait]
+pub trait ContentFilter: Send + Sync {
+    async fn check(&self, text: &str) -> Result<FilterDecision, AdapterError>;
+}
+
+/// Flags text matching local keyword or regex rules, each tagged with a category
+#[derive(Clone, Default)]
+pub struct KeywordFilter {
+    rules: Vec<(String, Regex)>,
+}
+
+impl KeywordFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flag `keyword` as a whole word, ignoring case
+    pub fn keyword(mut self, category: impl Into<String>, keyword: &str) -> Self {
+        let pattern = format!(r"(?i)\b{}\b", regex::escape(keyword));
+        self.rules.push((category.into(), Regex::new(&pattern).expect("escaped keyword is a valid regex")));
+        self
+    }
+
+    /// Flag text matching a regular expression
+    pub fn pattern(mut self, category: impl Into<String>, pattern: &str) -> Result<Self, regex::Error> {
+        self.rules.push((category.into(), Regex::new(pattern)?));
+        Ok(self)
+    }
+}
+
+#[async_trait]
+impl ContentFilter for KeywordFilter {
+    async fn check(&self, text: &str) -> Result<FilterDecision, AdapterError> {
+        let mut categories: Vec<String> = Vec::new();
+        for (category, regex) in &self.rules {
+            if regex.is_match(text) && !categories.contains(category) {
+                categories.push(category.clone());
+            }
+        }
+        Ok(FilterDecision {
+            flagged: !categories.is_empty(),
+            categories,
+        })
+    }
+}
+
+/// Checks content with a remote moderation endpoint
+pub struct ModerationFilter {
+    client: Arc<ModerationClient>,
+}
+
+impl ModerationFilter {
+    pub fn new(client: Arc<ModerationClient>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl ContentFilter for ModerationFilter {
+    async fn check(&self, text: &str) -> Result<FilterDecision, AdapterError> {
+        let response = self
+            .client
+            .moderate(&ModerationRequest {
+                input: ModerationInput::Single(text.to_string()),
+                model: None,
+            })
+            .await?;
+        let mut decision = FilterDecision::default();
+        for result in response.results {
+            decision.flagged |= result.flagged;
+            for (category, hit) in result.categories {
+                if hit && !decision.categories.contains(&category) {
+                    decision.categories.push(category);
+                }
+            }
+        }
+        Ok(decision)
+    }
+}
+
+/// Run the filters over the request's user content, recording the outcome in
+/// `content_filter` / `content_filter_categories` metadata.
+pub(crate) async fn apply_filters(
+    filters: &[Arc<dyn ContentFilter>],
+    ir: &mut ChatRequestIR,
+) -> Result<(), EngineError> {
+    if filters.is_empty() {
+        return Ok(());
+    }
+
+    let text: Vec<&str> = ir
+        .messages
+        .iter()
+        .filter(|m| m.role == Role::User)
+        .flat_map(|m| m.parts.iter())
+        .filter_map(|part| match part {
+            ContentPart::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    let text = text.join("\n");
+    filter_text(filters, &text, &mut ir.metadata).await
+}
+
+/// Run the filters over `text` (e.g. an image prompt), recording the outcome
+/// in `metadata` like [`apply_filters`].
+pub(crate) async fn filter_text(
+    filters: &[Arc<dyn ContentFilter>],
+    text: &str,
+    metadata: &mut BTreeMap<String, String>,
+) -> Result<(), EngineError> {
+    if filters.is_empty() {
+        return Ok(());
+    }
+
+    let mut decision = FilterDecision::default();
+    if !text.is_empty() {
+        for filter in filters {
+            let verdict = filter.check(text).await?;
+            decision.flagged |= verdict.flagged;
+            for category in verdict.categories {
+                if !decision.categories.contains(&category) {
+                    decision.categories.push(category);
+                }
+            }
+        }
+    }
+
+    let outcome = if decision.flagged { "flagged" } else { "allowed" };
+    metadata.insert("content_filter".to_string(), outcome.to_string());
+    if !decision.categories.is_empty() {
+        metadata.insert("content_filter_categories".to_string(), decision.categories.join(","));
+    }
+
+    if decision.flagged {
+        tracing::warn!(
+            request_id = %metadata.get("request_id").map(String::as_str).unwrap_or("unknown"),
+            categories = %decision.categories.join(","),
+            "Request rejected by content filter"
+        );
+        return Err(EngineError::ContentPolicy {
+            categories: decision.categories,
+        });
+    }
+    Ok(())
+}
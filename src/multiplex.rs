@@ -0,0 +1,382 @@
+//! Sharing one chat stream between several consumers
+//!
+//! [`SharedChatStream`] drives a chat request once and fans its events out
+//! to any number of subscribers over a bounded broadcast channel. The request
+//! starts when the first subscriber is polled, so subscribers created before
+//! that see every event; later ones see events from the moment they
+//! subscribe. A subscriber that falls more than the buffer behind skips the
+//! oldest events and receives a `StreamEvent::Status` with state `lagged`
+//! instead, unless the stream's [`BackPressure`] pauses the request until
+//! the subscriber catches up or ends the subscriber's stream with a
+//! `client_too_slow` error. [`SharedChatStream::final_message`] resolves
+//! with the complete reply however far behind its caller is.
+//!
+//! A [`SharedChatStream::replaying`] stream instead keeps every event and
+//! starts right away: each subscriber gets the stream from its first event,
+//! whenever it subscribes, and never lags.
+//!
+//! Once all subscribers (including pending `final_message` futures) are
+//! dropped, the request is cancelled and the provider stream dropped.
+
+use crate::backpressure::{BackPressure, SlowClientPolicy};
+use crate::error::EngineError;
+use crate::stream::{StreamEvent, ToolCallSummary};
+use futures_util::{stream::BoxStream, Stream, StreamExt};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, watch, Notify};
+use tokio_util::sync::CancellationToken;
+
+/// Events a subscriber may fall behind before it starts skipping them
+pub const SHARED_STREAM_BUFFER: usize = 256;
+
+/// The complete reply of a shared stream
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FinalReply {
+    pub content: String,
+    pub tool_calls: Vec<ToolCallSummary>,
+    pub finish_reason: Option<String>,
+    /// The model's refusal (see [`StreamEvent::Refusal`]); a refused request
+    /// has no content
+    pub refusal: Option<String>,
+}
+
+/// The reply, or the code and message of the stream's error
+type Outcome = Result<FinalReply, (String, String)>;
+
+/// One chat request whose events are broadcast to every subscriber
+pub struct SharedChatStream {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    /// Taken when the stream ends so subscribers see it close
+    sender: Mutex<Option<broadcast::Sender<StreamEvent>>>,
+    back_pressure: BackPressure,
+    /// Notified whenever a subscriber reads an event or leaves
+    drained: Notify,
+    /// Set for replaying streams
+    replay: Option<Replay>,
+    outcome: watch::Receiver<Option<Outcome>>,
+    subscribers: Mutex<Subscribers>,
+    /// Cancelled by the first subscriber poll to start the request
+    start: CancellationToken,
+    cancel: CancellationToken,
+}
+
+/// Every event so far of a replaying stream
+struct Replay {
+    history: Mutex<History>,
+    /// Bumped whenever an event is added or the stream ends
+    updates: watch::Sender<u64>,
+}
+
+#[derive(Default)]
+struct History {
+    events: Vec<StreamEvent>,
+    finished: bool,
+}
+
+#[derive(Default)]
+struct Subscribers {
+    count: usize,
+    /// The `SharedChatStream` handle was dropped
+    released: bool,
+}
+
+impl SharedChatStream {
+    /// Share `events`, cancelling `cancel` once nobody listens anymore
+    pub fn new<S>(events: S, cancel: CancellationToken) -> Self
+    where
+        S: Stream<Item = StreamEvent> + Send + Unpin + 'static,
+    {
+        Self::with_capacity(events, SHARED_STREAM_BUFFER, cancel)
+    }
+
+    /// Like [`new`](Self::new), buffering `capacity` events per subscriber
+    pub fn with_capacity<S>(events: S, capacity: usize, cancel: CancellationToken) -> Self
+    where
+        S: Stream<Item = StreamEvent> + Send + Unpin + 'static,
+    {
+        Self::with_back_pressure(events, BackPressure::skip(capacity), cancel)
+    }
+
+    /// Like [`new`](Self::new), buffering up to `back_pressure`'s high-water
+    /// mark of events per subscriber and treating subscribers that fall
+    /// that far behind by its policy
+    pub fn with_back_pressure<S>(events: S, back_pressure: BackPressure, cancel: CancellationToken) -> Self
+    where
+        S: Stream<Item = StreamEvent> + Send + Unpin + 'static,
+    {
+        Self::spawn(events, back_pressure, None, cancel)
+    }
+
+    /// Share `events` from the start: the request starts now, and every
+    /// subscriber gets all of its events whenever it subscribes. Holds the
+    /// whole stream in memory until it ends.
+    pub fn replaying<S>(events: S, cancel: CancellationToken) -> Self
+    where
+        S: Stream<Item = StreamEvent> + Send + Unpin + 'static,
+    {
+        let replay = Replay {
+            history: Mutex::new(History::default()),
+            updates: watch::Sender::new(0),
+        };
+        let stream = Self::spawn(events, BackPressure::skip(1), Some(replay), cancel);
+        stream.shared.start.cancel();
+        stream
+    }
+
+    fn spawn<S>(events: S, back_pressure: BackPressure, replay: Option<Replay>, cancel: CancellationToken) -> Self
+    where
+        S: Stream<Item = StreamEvent> + Send + Unpin + 'static,
+    {
+        let (sender, _) = broadcast::channel(back_pressure.high_water_mark.max(1));
+        let (outcome_tx, outcome) = watch::channel(None);
+        let shared = Arc::new(Shared {
+            sender: Mutex::new(Some(sender)),
+            back_pressure,
+            drained: Notify::new(),
+            replay,
+            outcome,
+            subscribers: Mutex::new(Subscribers::default()),
+            start: CancellationToken::new(),
+            cancel,
+        });
+        tokio::spawn(pump(shared.clone(), events, outcome_tx));
+        Self { shared }
+    }
+
+    /// A new consumer of the stream's events. After the stream has ended
+    /// it yields nothing, unless the stream is replaying.
+    pub fn subscribe(&self) -> impl Stream<Item = StreamEvent> + Send + Unpin + 'static {
+        let guard = SubscriberGuard::new(self.shared.clone());
+        if self.shared.replay.is_some() {
+            return replay(guard);
+        }
+        let receiver = self
+            .shared
+            .sender
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|sender| sender.subscribe());
+        async_stream::stream! {
+            let Some(mut receiver) = receiver else {
+                return;
+            };
+            guard.start();
+            loop {
+                let received = receiver.recv().await;
+                guard.shared.drained.notify_waiters();
+                match received {
+                    Ok(event) => yield event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) if guard.shared.back_pressure.policy == SlowClientPolicy::Disconnect => {
+                        yield crate::backpressure::too_slow(skipped as usize);
+                        break;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        yield StreamEvent::Status {
+                            state: "lagged".to_string(),
+                            detail: Some(format!("{} events skipped", skipped)),
+                        };
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+        .boxed()
+    }
+
+    /// The complete reply once the stream ends: its `FinalMessage`, or the
+    /// concatenated text deltas when it has none. Counts as a subscriber
+    /// until it resolves.
+    pub fn final_message(&self) -> impl Future<Output = Result<FinalReply, EngineError>> + Send + 'static {
+        let guard = SubscriberGuard::new(self.shared.clone());
+        let mut outcome = self.shared.outcome.clone();
+        async move {
+            guard.start();
+            let outcome = outcome
+                .wait_for(Option::is_some)
+                .await
+                .map_err(|_| EngineError::Cancelled)?
+                .clone();
+            match outcome {
+                Some(Ok(reply)) => Ok(reply),
+                Some(Err((code, message))) => Err(EngineError::from_stream_error(code, message)),
+                None => Err(EngineError::Cancelled),
+            }
+        }
+    }
+
+    /// Number of live subscribers
+    pub fn subscriber_count(&self) -> usize {
+        self.shared.subscribers.lock().unwrap_or_else(|e| e.into_inner()).count
+    }
+
+    /// Cancel the request for all subscribers
+    pub fn cancel(&self) {
+        self.shared.cancel.cancel();
+    }
+
+    /// Whether the request was cancelled, e.g. because all subscribers left
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.cancel.is_cancelled()
+    }
+}
+
+/// All events of a replaying stream, from the first, until it ends
+fn replay(guard: SubscriberGuard) -> BoxStream<'static, StreamEvent> {
+    async_stream::stream! {
+        let Some(replay) = guard.shared.replay.as_ref() else {
+            return;
+        };
+        let mut updates = replay.updates.subscribe();
+        let mut next = 0;
+        loop {
+            updates.borrow_and_update();
+            let (pending, finished) = {
+                let history = replay.history.lock().unwrap_or_else(|e| e.into_inner());
+                (history.events[next..].to_vec(), history.finished)
+            };
+            next += pending.len();
+            let idle = pending.is_empty();
+            for event in pending {
+                yield event;
+            }
+            if finished {
+                break;
+            }
+            if idle && updates.changed().await.is_err() {
+                break;
+            }
+        }
+    }
+    .boxed()
+}
+
+impl Drop for SharedChatStream {
+    /// A request nobody subscribed to is cancelled with its handle
+    fn drop(&mut self) {
+        let mut subscribers = self.shared.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        subscribers.released = true;
+        if subscribers.count == 0 {
+            self.shared.cancel.cancel();
+        }
+    }
+}
+
+/// Keeps the request alive while a subscriber exists
+struct SubscriberGuard {
+    shared: Arc<Shared>,
+}
+
+impl SubscriberGuard {
+    fn new(shared: Arc<Shared>) -> Self {
+        shared.subscribers.lock().unwrap_or_else(|e| e.into_inner()).count += 1;
+        Self { shared }
+    }
+
+    fn start(&self) {
+        self.shared.start.cancel();
+    }
+}
+
+impl Drop for SubscriberGuard {
+    fn drop(&mut self) {
+        self.shared.drained.notify_waiters();
+        let mut subscribers = self.shared.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        subscribers.count -= 1;
+        if subscribers.count == 0 && (self.shared.start.is_cancelled() || subscribers.released) {
+            self.shared.cancel.cancel();
+        }
+    }
+}
+
+/// Wait until every subscriber is less than the high-water mark behind;
+/// false when the stream was cancelled meanwhile
+async fn drained(shared: &Shared, sender: &broadcast::Sender<StreamEvent>) -> bool {
+    loop {
+        let notified = shared.drained.notified();
+        if sender.len() < shared.back_pressure.high_water_mark {
+            return true;
+        }
+        tokio::select! {
+            _ = notified => {}
+            _ = shared.cancel.cancelled() => return false,
+        }
+    }
+}
+
+/// Drive `events` once the first subscriber polls, broadcasting each event
+/// and recording the reply for `final_message`
+async fn pump<S>(shared: Arc<Shared>, mut events: S, outcome_tx: watch::Sender<Option<Outcome>>)
+where
+    S: Stream<Item = StreamEvent> + Send + Unpin,
+{
+    let cancelled = || Err(("cancelled".to_string(), "shared chat stream cancelled".to_string()));
+    let mut outcome = None;
+    tokio::select! {
+        _ = shared.start.cancelled() => {}
+        _ = shared.cancel.cancelled() => {}
+    }
+
+    let mut deltas = String::new();
+    let mut refusal: Option<String> = None;
+    while !shared.cancel.is_cancelled() {
+        let event = tokio::select! {
+            biased;
+            _ = shared.cancel.cancelled() => break,
+            event = events.next() => event,
+        };
+        let Some(event) = event else {
+            outcome.get_or_insert_with(|| {
+                Ok(FinalReply {
+                    content: std::mem::take(&mut deltas),
+                    refusal: refusal.take(),
+                    ..Default::default()
+                })
+            });
+            break;
+        };
+        match &event {
+            StreamEvent::TextDelta { content } => deltas.push_str(content),
+            StreamEvent::Refusal { text } => refusal.get_or_insert_with(String::new).push_str(text),
+            StreamEvent::FinalMessage {
+                content,
+                tool_calls,
+                finish_reason,
+            } => {
+                outcome = Some(Ok(FinalReply {
+                    content: content.clone(),
+                    tool_calls: tool_calls.clone(),
+                    finish_reason: finish_reason.clone(),
+                    refusal: refusal.clone(),
+                }))
+            }
+            StreamEvent::Error { code, message } => outcome = Some(Err((code.clone(), message.clone()))),
+            _ => {}
+        }
+        if let Some(replay) = &shared.replay {
+            replay.history.lock().unwrap_or_else(|e| e.into_inner()).events.push(event);
+            replay.updates.send_modify(|version| *version += 1);
+            continue;
+        }
+        let Some(sender) = shared.sender.lock().unwrap_or_else(|e| e.into_inner()).clone() else {
+            continue;
+        };
+        if shared.back_pressure.policy == SlowClientPolicy::Pause && !drained(&shared, &sender).await {
+            break;
+        }
+        // Fails while no subscriber stream listens, e.g. only `final_message` waits
+        let _ = sender.send(event);
+    }
+
+    drop(events);
+    shared.sender.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(replay) = &shared.replay {
+        replay.history.lock().unwrap_or_else(|e| e.into_inner()).finished = true;
+        replay.updates.send_modify(|version| *version += 1);
+    }
+    outcome_tx.send_replace(Some(outcome.unwrap_or_else(cancelled)));
+}
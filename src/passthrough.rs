@@ -0,0 +1,362 @@
+//! Raw passthrough routes
+//!
+//! A passthrough route proxies the endpoints under a path prefix that the
+//! gateway doesn't model (e.g. `/api/openai/v1/files`) to one provider. The
+//! method, query and body go to the provider's base URL unchanged, with the
+//! provider's credentials in place of the client's (or the client's own key,
+//! for providers in [`AuthMode::Passthrough`](crate::types::AuthMode)); the response is
+//! streamed back as is. The routes sit behind the same layers as the skin
+//! routes, so authentication and request tracing apply to them too. A route
+//! to a tenant's provider only serves API keys of that tenant (see
+//! [`crate::tenant`]).
+//!
+//! Hop-by-hop headers are dropped in both directions, and a response longer
+//! than the route's `max_response_bytes` is cut off. Paths with `.` or `..`
+//! segments, percent-encoded or not, are refused: resolved against the
+//! upstream URL they would leave the route's prefix with its credentials.
+
+use serde::{Deserialize, Serialize};
+
+/// Longest response a route streams back unless it sets its own limit (100 MiB)
+pub const DEFAULT_MAX_RESPONSE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Headers that only apply to one connection and are never forwarded
+#[cfg(feature = "server")]
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Request headers replaced by the provider's own credentials
+#[cfg(feature = "server")]
+const CLIENT_CREDENTIAL_HEADERS: &[&str] = &["authorization", "x-api-key", "x-provider-api-key", "cookie"];
+
+/// Requests under `path_prefix` forwarded to `provider`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PassthroughRoute {
+    /// Gateway path whose requests are forwarded, e.g. `/api/openai/v1/files`
+    pub path_prefix: String,
+    /// Name of the provider to forward to
+    pub provider: String,
+    /// Path under the provider's base URL that `path_prefix` maps to;
+    /// defaults to `path_prefix` from its `/v1` segment on (`/v1/files`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_path: Option<String>,
+    /// Longest response streamed back; defaults to [`DEFAULT_MAX_RESPONSE_BYTES`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<u64>,
+}
+
+impl PassthroughRoute {
+    pub fn new(path_prefix: impl Into<String>, provider: impl Into<String>) -> Self {
+        Self {
+            path_prefix: path_prefix.into(),
+            provider: provider.into(),
+            upstream_path: None,
+            max_response_bytes: None,
+        }
+    }
+
+    /// The upstream path `path_prefix` maps to
+    pub fn upstream_path(&self) -> &str {
+        if let Some(path) = &self.upstream_path {
+            return path.trim_end_matches('/');
+        }
+        let prefix = self.path_prefix.trim_end_matches('/');
+        match prefix.find("/v1/").or_else(|| prefix.ends_with("/v1").then(|| prefix.len() - 3)) {
+            Some(start) => &prefix[start..],
+            None => prefix,
+        }
+    }
+
+    pub fn max_response_bytes(&self) -> u64 {
+        self.max_response_bytes.unwrap_or(DEFAULT_MAX_RESPONSE_BYTES)
+    }
+
+    /// The upstream URL of `path` (below `path_prefix`) and `query` on `endpoint`.
+    /// A base URL that already ends with `/v1` doesn't get it twice.
+    pub fn upstream_url(&self, endpoint: &crate::types::ProviderEndpoint, path: &str, query: Option<&str>) -> String {
+        let mut upstream = self.upstream_path();
+        if endpoint.base_url.trim_end_matches('/').ends_with("/v1") {
+            upstream = upstream.strip_prefix("/v1").unwrap_or(upstream);
+        }
+        let route = match query {
+            Some(query) => format!("{}{}?{}", upstream, path, query),
+            None => format!("{}{}", upstream, path),
+        };
+        crate::adapters::http::endpoint_url(endpoint, &route)
+    }
+}
+
+/// Whether `path` has a `.` or `..` segment, raw or percent-encoded
+pub fn has_dot_segment(path: &str) -> bool {
+    // URL parsers treat `\` like `/` in http(s) paths
+    path.split(['/', '\\']).any(|segment| {
+        let decoded = percent_decoded(segment);
+        decoded.split(['/', '\\']).any(|part| part == "." || part == "..")
+    })
+}
+
+/// `segment` percent-decoded until nothing changes, so double encoding
+/// can't hide a dot
+fn percent_decoded(segment: &str) -> String {
+    let mut decoded = segment.as_bytes().to_vec();
+    loop {
+        let mut out = Vec::with_capacity(decoded.len());
+        let mut index = 0;
+        while index < decoded.len() {
+            let hex = decoded.get(index + 1..index + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+            match hex.filter(|_| decoded[index] == b'%').and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    out.push(byte);
+                    index += 3;
+                }
+                None => {
+                    out.push(decoded[index]);
+                    index += 1;
+                }
+            }
+        }
+        if out == decoded {
+            return String::from_utf8_lossy(&out).into_owned();
+        }
+        decoded = out;
+    }
+}
+
+#[cfg(feature = "server")]
+pub use server::{routes, routes_with_tenants};
+#[cfg(feature = "server")]
+pub(crate) use server::routes_with_options;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::*;
+    use crate::adapters::http;
+    use crate::service::ProviderManager;
+    use crate::tenant::{TenantDirectory, TenantScope};
+    use crate::types::{ProviderEndpoint, ProviderKind};
+    use axum::extract::Request;
+    use axum::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
+    use axum::response::{IntoResponse, Response};
+    use futures_util::StreamExt;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    /// The routes forwarding each of `routes`, looking their providers up in
+    /// `providers` per request; routes to tenant providers serve no one
+    pub fn routes(routes: &[PassthroughRoute], providers: Arc<RwLock<ProviderManager>>) -> axum::Router {
+        routes_with_tenants(routes, providers, TenantDirectory::default())
+    }
+
+    /// Like [`routes`], with routes to a tenant's providers serving the API
+    /// keys `tenants` assigns to that tenant
+    pub fn routes_with_tenants(
+        routes: &[PassthroughRoute],
+        providers: Arc<RwLock<ProviderManager>>,
+        tenants: TenantDirectory,
+    ) -> axum::Router {
+        routes_with_options(routes, providers, tenants, false)
+    }
+
+    /// Like [`routes_with_tenants`], sending the client's `OpenAI-Organization`
+    /// and `OpenAI-Project` headers on when `organization_headers` is set
+    pub(crate) fn routes_with_options(
+        routes: &[PassthroughRoute],
+        providers: Arc<RwLock<ProviderManager>>,
+        tenants: TenantDirectory,
+        organization_headers: bool,
+    ) -> axum::Router {
+        let mut router = axum::Router::new();
+        for route in routes {
+            let prefix = route.path_prefix.trim_end_matches('/').to_string();
+            let handler = {
+                let route = Arc::new(route.clone());
+                let providers = providers.clone();
+                let tenants = tenants.clone();
+                move |request: Request| {
+                    forward(route.clone(), providers.clone(), tenants.clone(), organization_headers, request)
+                }
+            };
+            router = router
+                .route(&prefix, axum::routing::any(handler.clone()))
+                .route(&format!("{}/*rest", prefix), axum::routing::any(handler));
+        }
+        router
+    }
+
+    async fn forward(
+        route: Arc<PassthroughRoute>,
+        providers: Arc<RwLock<ProviderManager>>,
+        tenants: TenantDirectory,
+        organization_headers: bool,
+        mut request: Request,
+    ) -> Response {
+        let api_key = request.extensions().get::<crate::budget::ApiKeyName>().map(|key| key.0.clone());
+        let tenant = api_key.as_deref().and_then(|key| tenants.tenant_of(key));
+        // Other tenants' providers look like missing ones
+        let provider = providers
+            .read()
+            .await
+            .get_provider(&route.provider)
+            .filter(|p| p.enabled && TenantScope::of(tenant.as_deref()).sees(p.tenant.as_deref()))
+            .cloned();
+        let Some(provider) = provider else {
+            return error(
+                StatusCode::BAD_GATEWAY,
+                "provider_not_found",
+                format!("Passthrough provider '{}' is not configured", route.provider),
+            );
+        };
+        let mut endpoint = provider.endpoint;
+        if endpoint.auth_mode == crate::types::AuthMode::Passthrough {
+            let Some(crate::types::ProviderKey(key)) = request.extensions().get::<crate::types::ProviderKey>() else {
+                return error(
+                    StatusCode::UNAUTHORIZED,
+                    "missing_provider_key",
+                    format!("Passthrough provider '{}' takes the caller's own API key", route.provider),
+                );
+            };
+            crate::types::EndpointOverrides { api_key: Some(key.clone()), ..Default::default() }.apply(&mut endpoint);
+        }
+        // The client's organization and project win over the provider's where
+        // the server lets them; elsewhere they're never sent
+        let [organization, project] = [crate::types::ORGANIZATION_HEADER, crate::types::PROJECT_HEADER].map(|name| {
+            let value = request.headers_mut().remove(name)?;
+            let value = value.to_str().ok()?.trim();
+            (organization_headers && !value.is_empty()).then(|| value.to_string())
+        });
+        crate::types::EndpointOverrides { organization, project, ..Default::default() }.apply(&mut endpoint);
+        let (parts, body) = request.into_parts();
+        let path = parts.uri.path();
+        let rest = path.strip_prefix(route.path_prefix.trim_end_matches('/')).unwrap_or("");
+        if has_dot_segment(rest) {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "invalid_path",
+                "Passthrough paths can't contain '.' or '..' segments".to_string(),
+            );
+        }
+        let url = route.upstream_url(&endpoint, rest, parts.uri.query());
+
+        let mut headers = parts.headers.clone();
+        strip_hop_by_hop(&mut headers);
+        for name in CLIENT_CREDENTIAL_HEADERS.iter().chain(&["host"]) {
+            headers.remove(*name);
+        }
+        match auth_headers(&endpoint).await {
+            Ok(auth) => {
+                for (name, value) in auth {
+                    if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+                        headers.insert(name, value);
+                    }
+                }
+            }
+            Err(e) => return error(StatusCode::BAD_GATEWAY, "upstream_auth_failed", e.to_string()),
+        }
+
+        let client = match http::client(&endpoint) {
+            Ok(client) => client,
+            Err(e) => return error(StatusCode::BAD_GATEWAY, "upstream_error", e.to_string()),
+        };
+        let request = client
+            .request(parts.method.clone(), &url)
+            .headers(headers)
+            .body(reqwest::Body::wrap_stream(body.into_data_stream()));
+        let upstream = match http::send(request, &endpoint, "Passthrough request failed").await {
+            Ok(upstream) => upstream,
+            Err(crate::adapter::AdapterError::Provider { code, message }) if code == http::FIRST_BYTE_TIMEOUT => {
+                return error(StatusCode::GATEWAY_TIMEOUT, &code, message);
+            }
+            Err(e) => return error(StatusCode::BAD_GATEWAY, "upstream_error", e.to_string()),
+        };
+        tracing::info!(
+            path_prefix = %route.path_prefix,
+            provider = %route.provider,
+            method = %parts.method,
+            path = %path,
+            status = upstream.status().as_u16(),
+            api_key = api_key.as_deref().unwrap_or("-"),
+            "Passthrough request"
+        );
+
+        let limit = route.max_response_bytes();
+        if upstream.content_length().is_some_and(|length| length > limit) {
+            return error(
+                StatusCode::BAD_GATEWAY,
+                "response_too_large",
+                format!("The provider's response exceeds the {} byte limit", limit),
+            );
+        }
+        let status = upstream.status();
+        let mut headers = upstream.headers().clone();
+        strip_hop_by_hop(&mut headers);
+        headers.remove(header::CONTENT_LENGTH);
+        let mut sent = 0u64;
+        let body = upstream.bytes_stream().map(move |chunk| {
+            let chunk = chunk.map_err(std::io::Error::other)?;
+            sent += chunk.len() as u64;
+            if sent > limit {
+                tracing::warn!(limit, "Passthrough response cut off at its size limit");
+                return Err(std::io::Error::other(format!("response exceeds the {} byte limit", limit)));
+            }
+            Ok(chunk)
+        });
+        let mut response = axum::body::Body::from_stream(body).into_response();
+        *response.status_mut() = status;
+        *response.headers_mut() = headers;
+        response
+    }
+
+    /// The provider credentials of `endpoint` in its provider's scheme, with
+    /// the endpoint's own headers applied over them
+    async fn auth_headers(
+        endpoint: &ProviderEndpoint,
+    ) -> Result<std::collections::BTreeMap<String, String>, crate::adapter::AdapterError> {
+        if endpoint.kind == ProviderKind::Anthropic {
+            return crate::adapters::anthropic::AnthropicAdapter::request_headers(endpoint).await;
+        }
+        let mut headers = std::collections::BTreeMap::new();
+        if let Some(token) = endpoint.bearer_token().await? {
+            headers.insert("authorization".to_string(), format!("Bearer {}", token));
+        }
+        for (key, value) in endpoint.headers().await? {
+            headers.retain(|existing, _| !existing.eq_ignore_ascii_case(&key));
+            headers.insert(key, value);
+        }
+        Ok(headers)
+    }
+
+    /// Remove the hop-by-hop headers, including those the `Connection` header names
+    fn strip_hop_by_hop(headers: &mut HeaderMap) {
+        let named: Vec<String> = headers
+            .get_all(header::CONNECTION)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        for name in HOP_BY_HOP_HEADERS.iter().copied().chain(named.iter().map(String::as_str)) {
+            headers.remove(name);
+        }
+    }
+
+    fn error(status: StatusCode, code: &str, message: String) -> Response {
+        let error = serde_json::json!({
+            "error": {
+                "message": message,
+                "type": "passthrough_error",
+                "code": code
+            }
+        });
+        (status, axum::Json(error)).into_response()
+    }
+}
@@ -0,0 +1,277 @@
+//! Cost tracking
+//!
+//! A [`PricingTable`] maps model name patterns to prices in USD per million
+//! tokens. With a table configured, the router follows every chat stream,
+//! prices its usage and sends a [`StreamEvent::Cost`] before the final
+//! message. The cost also lands in audit records and in the per-model totals
+//! of [`UsageMeter`]. Models the table doesn't know report a cost of `null`,
+//! never zero.
+//!
+//! [`PricingTable::default`] holds list prices for common OpenAI and
+//! Anthropic models; entries loaded from config override them by pattern:
+//!
+//! ```json
+//! {
+//!   "gpt-4o*": { "input": 2.5, "output": 10.0, "cached_input": 1.25 },
+//!   "llama3*": { "input": 0.0, "output": 0.0 }
+//! }
+//! ```
+
+use crate::stream::StreamEvent;
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Prices in USD per million tokens
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    /// Prompt tokens served from the provider's cache; `input` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_input: Option<f64>,
+}
+
+impl ModelPrice {
+    pub fn new(input: f64, output: f64) -> Self {
+        Self {
+            input,
+            output,
+            cached_input: None,
+        }
+    }
+
+    pub fn with_cached_input(mut self, cached_input: f64) -> Self {
+        self.cached_input = Some(cached_input);
+        self
+    }
+
+    /// Cost of one request's usage; `cached` counts toward `input`
+    pub fn cost(&self, input: u32, output: u32, cached: u32) -> Cost {
+        let cached = cached.min(input);
+        let input_usd = (f64::from(input - cached) * self.input
+            + f64::from(cached) * self.cached_input.unwrap_or(self.input))
+            / 1_000_000.0;
+        let output_usd = f64::from(output) * self.output / 1_000_000.0;
+        Cost {
+            input_usd: Some(input_usd),
+            output_usd: Some(output_usd),
+            total_usd: Some(input_usd + output_usd),
+        }
+    }
+}
+
+/// Cost of a request in USD; all `None` when the model has no price
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cost {
+    pub input_usd: Option<f64>,
+    pub output_usd: Option<f64>,
+    pub total_usd: Option<f64>,
+}
+
+impl Cost {
+    pub fn to_event(self) -> StreamEvent {
+        StreamEvent::Cost {
+            input_usd: self.input_usd,
+            output_usd: self.output_usd,
+            total_usd: self.total_usd,
+        }
+    }
+}
+
+/// Prices by model name pattern. A pattern is a model name that may contain
+/// `*` wildcards; when several match, the one with the most literal
+/// characters wins.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PricingTable {
+    prices: BTreeMap<String, ModelPrice>,
+}
+
+impl Default for PricingTable {
+    /// List prices of common OpenAI and Anthropic models. They change over
+    /// time and exclude negotiated discounts; override them as needed.
+    fn default() -> Self {
+        let price = |input, output, cached| ModelPrice::new(input, output).with_cached_input(cached);
+        Self::empty()
+            .with_price("gpt-5*", price(1.25, 10.0, 0.125))
+            .with_price("gpt-5-mini*", price(0.25, 2.0, 0.025))
+            .with_price("gpt-5-nano*", price(0.05, 0.4, 0.005))
+            .with_price("gpt-4.1*", price(2.0, 8.0, 0.5))
+            .with_price("gpt-4.1-mini*", price(0.4, 1.6, 0.1))
+            .with_price("gpt-4.1-nano*", price(0.1, 0.4, 0.025))
+            .with_price("gpt-4o*", price(2.5, 10.0, 1.25))
+            .with_price("gpt-4o-mini*", price(0.15, 0.6, 0.075))
+            .with_price("gpt-4-turbo*", ModelPrice::new(10.0, 30.0))
+            .with_price("gpt-3.5-turbo*", ModelPrice::new(0.5, 1.5))
+            .with_price("o1*", price(15.0, 60.0, 7.5))
+            .with_price("o1-mini*", price(1.1, 4.4, 0.55))
+            .with_price("o3*", price(2.0, 8.0, 0.5))
+            .with_price("o3-mini*", price(1.1, 4.4, 0.55))
+            .with_price("o4-mini*", price(1.1, 4.4, 0.275))
+            .with_price("claude-3-haiku*", price(0.25, 1.25, 0.03))
+            .with_price("claude-3-5-haiku*", price(0.8, 4.0, 0.08))
+            .with_price("claude-haiku-4*", price(1.0, 5.0, 0.1))
+            .with_price("claude-3-5-sonnet*", price(3.0, 15.0, 0.3))
+            .with_price("claude-3-7-sonnet*", price(3.0, 15.0, 0.3))
+            .with_price("claude-sonnet-4*", price(3.0, 15.0, 0.3))
+            .with_price("claude-3-opus*", price(15.0, 75.0, 1.5))
+            .with_price("claude-opus-4*", price(15.0, 75.0, 1.5))
+            .with_price("claude-opus-4-5*", price(5.0, 25.0, 0.5))
+    }
+}
+
+impl PricingTable {
+    /// A table without any prices
+    pub fn empty() -> Self {
+        Self {
+            prices: BTreeMap::new(),
+        }
+    }
+
+    /// Add or replace the price for `pattern`
+    pub fn with_price(mut self, pattern: impl Into<String>, price: ModelPrice) -> Self {
+        self.prices.insert(pattern.into(), price);
+        self
+    }
+
+    /// This table with `overrides`' prices added, replacing equal patterns
+    pub fn merged(mut self, overrides: PricingTable) -> Self {
+        self.prices.extend(overrides.prices);
+        self
+    }
+
+    /// The price of `model`, matched with and without a vendor prefix
+    /// (e.g. `openai/gpt-4o`)
+    pub fn price(&self, model: &str) -> Option<&ModelPrice> {
+        let bare = model.rsplit_once('/').map_or(model, |(_, name)| name);
+        self.prices
+            .iter()
+            .filter(|(pattern, _)| pattern_matches(pattern, model) || pattern_matches(pattern, bare))
+            .max_by_key(|(pattern, _)| pattern.chars().filter(|c| *c != '*').count())
+            .map(|(_, price)| price)
+    }
+
+    /// Cost of a request to `model`
+    pub fn cost(&self, model: &str, input: u32, output: u32, cached: u32) -> Cost {
+        self.price(model)
+            .map(|price| price.cost(input, output, cached))
+            .unwrap_or_default()
+    }
+}
+
+/// Case-insensitive match of `name` against a pattern with `*` wildcards
+fn pattern_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let name = name.to_lowercase();
+    let mut parts = pattern.split('*');
+    let Some(first) = parts.next() else {
+        return true;
+    };
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Usage totals of one model
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ModelUsage {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cached_tokens: u64,
+    /// Sum over the requests that had a price
+    pub cost_usd: f64,
+    /// Requests whose model had no price
+    pub unpriced_requests: u64,
+}
+
+/// Running usage and cost totals by model alias. Clones share their totals.
+#[derive(Clone, Debug, Default)]
+pub struct UsageMeter {
+    totals: Arc<Mutex<BTreeMap<String, ModelUsage>>>,
+}
+
+impl UsageMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, alias: &str, input: u32, output: u32, cached: u32, cost: &Cost) {
+        let mut totals = self.totals.lock().unwrap_or_else(|e| e.into_inner());
+        let usage = totals.entry(alias.to_string()).or_default();
+        usage.requests += 1;
+        usage.input_tokens += u64::from(input);
+        usage.output_tokens += u64::from(output);
+        usage.cached_tokens += u64::from(cached);
+        match cost.total_usd {
+            Some(total) => usage.cost_usd += total,
+            None => usage.unpriced_requests += 1,
+        }
+    }
+
+    /// Totals so far, by model alias
+    pub fn snapshot(&self) -> BTreeMap<String, ModelUsage> {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn reset(&self) {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+/// Pass `events` through, sending a `Cost` event priced with `table` before
+/// the final message (or `Done`) once usage has been reported, and adding
+/// the request to `meter`. `model` is the provider's model id and `alias`
+/// the name the meter records it under.
+pub(crate) fn with_cost<S>(
+    table: Arc<PricingTable>,
+    meter: UsageMeter,
+    model: String,
+    alias: String,
+    mut events: S,
+) -> impl Stream<Item = StreamEvent> + Send
+where
+    S: Stream<Item = StreamEvent> + Send + Unpin,
+{
+    async_stream::stream! {
+        let mut usage: Option<(u32, u32)> = None;
+        let mut cached = 0;
+        let mut priced = false;
+        while let Some(event) = events.next().await {
+            match &event {
+                StreamEvent::Tokens { input, output } => usage = Some((*input, *output)),
+                StreamEvent::OpenAIMetadata { prompt_tokens_details: Some(details), .. } => {
+                    cached = details.cached_tokens
+                }
+                StreamEvent::FinalMessage { .. } | StreamEvent::Done if !priced => {
+                    if let Some((input, output)) = usage {
+                        priced = true;
+                        let cost = table.cost(&model, input, output, cached);
+                        meter.record(&alias, input, output, cached, &cost);
+                        yield cost.to_event();
+                    }
+                }
+                _ => {}
+            }
+            yield event;
+        }
+        if let (false, Some((input, output))) = (priced, usage) {
+            let cost = table.cost(&model, input, output, cached);
+            meter.record(&alias, input, output, cached, &cost);
+            yield cost.to_event();
+        }
+    }
+}
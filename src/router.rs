@@ -1,57 +1,723 @@
 use crate::types::ProviderKind;
-use crate::adapter::ChatAdapter;
-use std::{sync::Arc, collections::HashMap};
+use crate::adapter::{AdapterError, ChatAdapter};
+use crate::balancer::{EndpointGuard, LoadBalancer};
+use crate::context::{ContextManager, ContextPolicy};
+use crate::guardrails::{OutputGuardrails, OutputLimits};
+use crate::limiter::{ConcurrencyLimiter, QueuePermit};
+use crate::moderation::ContentFilter;
+use crate::tokens::{DefaultTokenCounter, TokenCounter};
+use crate::validation::JsonValidation;
+use crate::audit::{AuditOutcome, AuditRedaction, AuditSink, Auditor};
+use std::{sync::{Arc, RwLock}, collections::HashMap};
 
+/// Builds the adapter for `ProviderKind::Custom` providers from the
+/// endpoint's `adapter_options`
+pub type AdapterFactory = Arc<
+    dyn Fn(&serde_json::Map<String, serde_json::Value>) -> Result<Arc<dyn ChatAdapter>, AdapterError>
+        + Send
+        + Sync,
+>;
+
+/// Adapters by the provider kind they serve.
+///
+/// Clones share their contents, so adapters registered or removed after a
+/// router or server was built take effect there too.
 #[derive(Clone, Default)]
 pub struct AdapterRegistry {
-    by_kind: HashMap<ProviderKind, Arc<dyn ChatAdapter>>,
+    by_kind: Arc<RwLock<HashMap<ProviderKind, Arc<dyn ChatAdapter>>>>,
+    factories: Arc<RwLock<HashMap<String, AdapterFactory>>>,
 }
 
 impl AdapterRegistry {
-    pub fn register(&mut self, adapter: Arc<dyn ChatAdapter>) {
-        self.by_kind.insert(adapter.provider_kind(), adapter);
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<ProviderKind, Arc<dyn ChatAdapter>>> {
+        self.by_kind.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<ProviderKind, Arc<dyn ChatAdapter>>> {
+        self.by_kind.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Register `adapter` for its provider kind, returning the adapter it replaced
+    pub fn register(&self, adapter: Arc<dyn ChatAdapter>) -> Option<Arc<dyn ChatAdapter>> {
+        self.write().insert(adapter.provider_kind(), adapter)
+    }
+
+    /// Remove the adapter for `kind`; providers of that kind stop routing
+    pub fn unregister(&self, kind: &ProviderKind) -> Option<Arc<dyn ChatAdapter>> {
+        self.write().remove(kind)
     }
 
     pub fn get(&self, kind: &ProviderKind) -> Option<Arc<dyn ChatAdapter>> {
-        self.by_kind.get(kind).cloned()
+        self.read().get(kind).cloned()
+    }
+
+    pub fn contains(&self, kind: &ProviderKind) -> bool {
+        self.read().contains_key(kind)
+    }
+
+    /// Register a constructor for `ProviderKind::Custom(name)` adapters,
+    /// returning the factory it replaced. Adding a provider of that kind
+    /// without a registered adapter builds one from its `adapter_options`.
+    pub fn register_factory<F>(&self, name: impl Into<String>, factory: F) -> Option<AdapterFactory>
+    where
+        F: Fn(&serde_json::Map<String, serde_json::Value>) -> Result<Arc<dyn ChatAdapter>, AdapterError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.factories
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name.into(), Arc::new(factory))
+    }
+
+    /// Names with a registered adapter factory, sorted
+    pub fn factory_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.factories.read().unwrap_or_else(|e| e.into_inner()).keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Make sure an adapter serves `endpoint`, building it with the kind's
+    /// factory when none is registered yet. The first provider of a custom
+    /// kind decides the options the shared adapter is built with.
+    pub fn ensure_adapter(&self, endpoint: &crate::types::ProviderEndpoint) -> Result<(), AdapterError> {
+        if self.contains(&endpoint.kind) {
+            return Ok(());
+        }
+        let ProviderKind::Custom(name) = &endpoint.kind else {
+            return Ok(());
+        };
+        let Some(factory) = self.factories.read().unwrap_or_else(|e| e.into_inner()).get(name).cloned() else {
+            return Ok(());
+        };
+
+        let adapter = factory(&endpoint.adapter_options)?;
+        if adapter.provider_kind() != endpoint.kind {
+            return Err(AdapterError::invalid(format!(
+                "adapter factory '{}' built an adapter for {} instead",
+                name,
+                adapter.provider_kind()
+            )));
+        }
+        self.write().entry(endpoint.kind.clone()).or_insert(adapter);
+        Ok(())
+    }
+
+    /// Kinds with a registered adapter, in a stable order
+    pub fn kinds(&self) -> Vec<ProviderKind> {
+        let mut kinds: Vec<ProviderKind> = self.read().keys().cloned().collect();
+        kinds.sort_by_key(|kind| format!("{:?}", kind));
+        kinds
     }
 
+    /// Same as [`kinds`](Self::kinds)
     pub fn list_kinds(&self) -> Vec<ProviderKind> {
-        self.by_kind.keys().cloned().collect()
+        self.kinds()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.by_kind.is_empty()
+        self.read().is_empty()
     }
 }
 
 #[derive(Clone)]
 pub struct Router {
     pub registry: AdapterRegistry,
+    filters: Vec<Arc<dyn ContentFilter>>,
+    token_counter: Arc<dyn TokenCounter>,
+    context: ContextManager,
+    output: OutputGuardrails,
+    balancer: LoadBalancer,
+    limiter: ConcurrencyLimiter,
+    json_validation: JsonValidation,
+    audit: Auditor,
+    routing: Option<crate::routing::RoutingPolicy>,
+    pricing: Option<Arc<crate::pricing::PricingTable>>,
+    usage: crate::pricing::UsageMeter,
+    budgets: Option<crate::budget::BudgetTracker>,
+    dedup: Option<crate::dedup::RequestDeduplicator>,
+    max_image_bytes: usize,
 }
 
 impl Router {
     pub fn new(registry: AdapterRegistry) -> Self {
-        Self { registry }
+        Self {
+            registry,
+            filters: Vec::new(),
+            token_counter: Arc::new(DefaultTokenCounter::new()),
+            context: ContextManager::new(),
+            output: OutputGuardrails::new(),
+            balancer: LoadBalancer::new(),
+            limiter: ConcurrencyLimiter::new(),
+            json_validation: JsonValidation::Off,
+            audit: Auditor::default(),
+            routing: None,
+            pricing: None,
+            usage: crate::pricing::UsageMeter::new(),
+            budgets: None,
+            dedup: None,
+            max_image_bytes: crate::images::DEFAULT_MAX_IMAGE_BYTES,
+        }
+    }
+
+    /// Balance pooled providers with `balancer`'s policies
+    pub fn with_load_balancer(mut self, balancer: LoadBalancer) -> Self {
+        self.balancer = balancer;
+        self
+    }
+
+    pub fn load_balancer(&self) -> &LoadBalancer {
+        &self.balancer
+    }
+
+    /// Price chat usage with `table` (see [`crate::pricing`])
+    pub fn with_pricing(mut self, table: crate::pricing::PricingTable) -> Self {
+        self.pricing = Some(Arc::new(table));
+        self
+    }
+
+    pub fn pricing(&self) -> Option<&crate::pricing::PricingTable> {
+        self.pricing.as_deref()
+    }
+
+    /// Usage and cost totals of the requests priced so far
+    pub fn usage(&self) -> &crate::pricing::UsageMeter {
+        &self.usage
+    }
+
+    /// Enforce the spending budgets of API keys (see [`crate::budget`]).
+    /// Spend is only recorded for priced requests, so set a pricing table too.
+    pub fn with_budgets(mut self, budgets: crate::budget::BudgetTracker) -> Self {
+        self.budgets = Some(budgets);
+        self
+    }
+
+    pub fn budgets(&self) -> Option<&crate::budget::BudgetTracker> {
+        self.budgets.as_ref()
+    }
+
+    /// Let identical in-flight requests share one generation (see [`crate::dedup`])
+    pub fn with_deduplication(mut self, dedup: crate::dedup::RequestDeduplicator) -> Self {
+        self.dedup = Some(dedup);
+        self
+    }
+
+    pub fn deduplicator(&self) -> Option<&crate::dedup::RequestDeduplicator> {
+        self.dedup.as_ref()
+    }
+
+    /// Route requests for the policy's virtual model (see [`crate::routing`])
+    pub fn with_routing_policy(mut self, policy: crate::routing::RoutingPolicy) -> Self {
+        self.routing = Some(policy);
+        self
+    }
+
+    pub fn routing_policy(&self) -> Option<&crate::routing::RoutingPolicy> {
+        self.routing.as_ref()
+    }
+
+    /// The routing policy's choice for `ir`, when it asks for the policy's model
+    pub async fn choose_route(
+        &self,
+        ir: &crate::types::ChatRequestIR,
+    ) -> Result<Option<crate::routing::RoutingDecision>, crate::error::EngineError> {
+        let Some(policy) = self.routing.as_ref().filter(|policy| policy.handles(&ir.model.alias)) else {
+            return Ok(None);
+        };
+        let count = self.count_tokens(&ir.model, &ir.messages).await?;
+        Ok(Some(policy.select(&crate::routing::RequestFeatures::of(ir, count.prompt_tokens))))
+    }
+
+    /// For pool aliases, route to a member picked by the load balancer and
+    /// record its provider name as `endpoint` metadata, and how it was
+    /// chosen (`sticky` or the pool's policy) as `endpoint_routing`
+    fn select_endpoint(
+        &self,
+        model: &mut crate::types::ModelRef,
+        metadata: &mut std::collections::BTreeMap<String, String>,
+        sticky_key: Option<&str>,
+    ) -> Option<EndpointGuard> {
+        let selection = self.balancer.select(&model.alias, sticky_key).or_else(|| {
+            // OpenRouter-style variants are balanced like their base alias
+            let (base, _) = crate::types::providers::openrouter::split_model_suffix(&model.alias);
+            self.balancer.select(base, sticky_key)
+        })?;
+        tracing::debug!(
+            model_alias = %model.alias,
+            endpoint = %selection.member.provider,
+            routing = selection.routing,
+            "Selected pool endpoint"
+        );
+        model.provider = selection.member.endpoint;
+        metadata.insert("endpoint".to_string(), selection.member.provider);
+        metadata.insert("endpoint_routing".to_string(), selection.routing.to_string());
+        Some(selection.guard)
+    }
+
+    /// Per-provider concurrency limits, set from provider configs at discovery
+    pub fn concurrency_limiter(&self) -> &ConcurrencyLimiter {
+        &self.limiter
+    }
+
+    /// Wait for a slot on the provider serving `model`: the selected pool
+    /// member, else the provider named by the alias prefix. Records the wait
+    /// as `queue_wait_ms` metadata.
+    async fn acquire_slot(
+        &self,
+        model: &crate::types::ModelRef,
+        metadata: &mut std::collections::BTreeMap<String, String>,
+        cancel: &tokio_util::sync::CancellationToken,
+    ) -> Result<Option<QueuePermit>, crate::error::EngineError> {
+        let provider = match metadata.get("endpoint") {
+            Some(endpoint) => endpoint.as_str(),
+            None => model.alias.split_once('/').map_or(model.alias.as_str(), |(provider, _)| provider),
+        };
+        let permit = self.limiter.acquire(provider, cancel).await?;
+        if let Some(permit) = &permit {
+            metadata.insert("queue_wait_ms".to_string(), permit.waited().as_millis().to_string());
+        }
+        Ok(permit)
+    }
+
+    /// Manage the context window of every alias without its own policy
+    pub fn with_default_context_policy(mut self, policy: ContextPolicy) -> Self {
+        self.context = self.context.with_default_policy(policy);
+        self
+    }
+
+    /// Manage the context window of one alias (discovered id or model name)
+    pub fn with_context_policy(mut self, alias: impl Into<String>, policy: ContextPolicy) -> Self {
+        self.context = self.context.with_policy(alias, policy);
+        self
+    }
+
+    pub fn context_manager(&self) -> &ContextManager {
+        &self.context
+    }
+
+    /// Limit the replies of every alias without its own output limits
+    pub fn with_default_output_limits(mut self, limits: OutputLimits) -> Self {
+        self.output = self.output.with_default_limits(limits);
+        self
+    }
+
+    /// Limit the replies of one alias (discovered id or model name)
+    pub fn with_output_limits(mut self, alias: impl Into<String>, limits: OutputLimits) -> Self {
+        self.output = self.output.with_limits(alias, limits);
+        self
+    }
+
+    pub fn output_guardrails(&self) -> &OutputGuardrails {
+        &self.output
+    }
+
+    /// Count prompt tokens with `counter` instead of [`DefaultTokenCounter`]
+    pub fn with_token_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+        self.token_counter = counter;
+        self
+    }
+
+    pub async fn count_tokens(
+        &self,
+        model: &crate::types::ModelRef,
+        messages: &[crate::types::Message],
+    ) -> Result<crate::tokens::TokenCount, crate::error::EngineError> {
+        Ok(self.token_counter.count(model, messages).await?)
+    }
+
+    /// Check user content with `filter` before routing; flagged requests are
+    /// rejected with `EngineError::ContentPolicy`
+    pub fn with_content_filter(mut self, filter: Arc<dyn ContentFilter>) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Validate the JSON of `json_object`/`json_schema` replies (see
+    /// [`crate::validation`]); requests can override this with
+    /// `json_validation` metadata
+    pub fn with_json_validation(mut self, mode: JsonValidation) -> Self {
+        self.json_validation = mode;
+        self
+    }
+
+    pub fn json_validation(&self) -> JsonValidation {
+        self.json_validation
+    }
+
+    /// Reject inline images larger than `bytes` once decoded (see [`crate::images`])
+    pub fn with_max_image_bytes(mut self, bytes: usize) -> Self {
+        self.max_image_bytes = bytes;
+        self
+    }
+
+    /// Record every chat request with `sink` (see [`crate::audit`])
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit = self.audit.with_sink(sink);
+        self
+    }
+
+    /// What to leave out of audit records
+    pub fn with_audit_redaction(mut self, redaction: AuditRedaction) -> Self {
+        self.audit = self.audit.with_redaction(redaction);
+        self
     }
 
     pub async fn route_chat(
         &self,
         ir: crate::types::ChatRequestIR,
         cancel: tokio_util::sync::CancellationToken,
-    ) -> anyhow::Result<impl futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin>
+    ) -> Result<impl futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin, crate::error::EngineError>
+    {
+        let trail = self.audit.start(&ir);
+        match self.route_chat_deduplicated(ir, cancel).await {
+            Ok(events) => {
+                let stream: Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin> =
+                    Box::new(Box::pin(trail.follow(events)));
+                Ok(stream)
+            }
+            Err(e) => {
+                trail.finish(AuditOutcome::from_error(&e));
+                Err(e)
+            }
+        }
+    }
+
+    /// Join an identical generation in flight, or start one others can join
+    async fn route_chat_deduplicated(
+        &self,
+        ir: crate::types::ChatRequestIR,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin>, crate::error::EngineError>
+    {
+        let dedup = self.dedup.as_ref().filter(|_| crate::dedup::is_deduplicable(&ir));
+        let Some((dedup, key)) = dedup.and_then(|dedup| Some((dedup, crate::dedup::dedup_key(&ir)?))) else {
+            return self.route_chat_audited(ir, cancel).await;
+        };
+        if let Some(events) = dedup.join(&key, cancel.clone()) {
+            tracing::info!(
+                request_id = %ir.metadata.get("request_id").map(String::as_str).unwrap_or("unknown"),
+                model_alias = %ir.model.alias,
+                "Joined identical chat request in flight"
+            );
+            return Ok(Box::new(events));
+        }
+
+        // The generation outlives this caller once others have joined it
+        let generation = tokio_util::sync::CancellationToken::new();
+        let events = tokio::select! {
+            events = self.route_chat_audited(ir, generation.clone()) => events?,
+            _ = cancel.cancelled() => {
+                generation.cancel();
+                return Err(crate::error::EngineError::Cancelled);
+            }
+        };
+        Ok(Box::new(dedup.lead(key, events, generation, cancel)))
+    }
+
+    async fn route_chat_audited(
+        &self,
+        ir: crate::types::ChatRequestIR,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin>, crate::error::EngineError>
+    {
+        if let (Some(budgets), Some(key)) = (&self.budgets, ir.metadata.get(crate::audit::API_KEY_NAME_METADATA)) {
+            budgets.check(key)?;
+        }
+        let deadline = ir.request_timeout.map(Deadline::new);
+        let mode = self.json_validation.for_request(&ir);
+        if mode == JsonValidation::Off {
+            return self.route_chat_unvalidated(ir, cancel, deadline).await;
+        }
+        let events = self.route_chat_unvalidated(ir.clone(), cancel.clone(), deadline).await?;
+        let stream: Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin> = Box::new(Box::pin(
+            crate::validation::validate_json(self.clone(), ir, cancel, deadline, mode, events),
+        ));
+        Ok(stream)
+    }
+
+    /// Route a chat request without JSON validation, within `deadline` when set
+    pub(crate) async fn route_chat_unvalidated(
+        &self,
+        ir: crate::types::ChatRequestIR,
+        cancel: tokio_util::sync::CancellationToken,
+        deadline: Option<Deadline>,
+    ) -> Result<Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin>, crate::error::EngineError>
     {
+        let Some(deadline) = deadline else {
+            return self.dispatch_chat(ir, cancel, None).await;
+        };
+        let stream = ir.stream;
+        let events = tokio::time::timeout_at(deadline.at, self.dispatch_chat(ir, cancel, Some(deadline)))
+            .await
+            .map_err(|_| deadline.exceeded("before the request reached the provider"))?
+            // The upstream's own timeout, set from the deadline, may win the race
+            .map_err(|e| match deadline.remaining().is_zero() {
+                true => deadline.exceeded("before the provider answered"),
+                false => e,
+            })?;
+        let stream: Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin> =
+            Box::new(Box::pin(deadline.bound(events, stream)));
+        Ok(stream)
+    }
+
+    async fn dispatch_chat(
+        &self,
+        mut ir: crate::types::ChatRequestIR,
+        cancel: tokio_util::sync::CancellationToken,
+        deadline: Option<Deadline>,
+    ) -> Result<Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin>, crate::error::EngineError>
+    {
+        crate::images::normalize_images(&mut ir, self.max_image_bytes)?;
+        let sticky_key = self.balancer.sticky_key(ir.cache_key.as_deref(), &ir.messages);
+        let endpoint = self.select_endpoint(&mut ir.model, &mut ir.metadata, sticky_key.as_deref());
+        // Failures with the caller's own key or URL say nothing about the
+        // endpoint's health; another organization or project doesn't change it
+        let overridden = ir.endpoint_overrides.as_ref().is_some_and(crate::types::EndpointOverrides::replaces_endpoint);
+        let caller_key = ir.endpoint_overrides.as_ref().is_some_and(|overrides| overrides.api_key.is_some());
+        if let Some(overrides) = ir.endpoint_overrides.take() {
+            overrides.apply(&mut ir.model.provider);
+        }
+        if ir.model.provider.auth_mode == crate::types::AuthMode::Passthrough && !caller_key {
+            return Err(crate::adapter::AdapterError::Auth(format!(
+                "{} takes the caller's own API key, and the request has none",
+                ir.model.alias
+            ))
+            .into());
+        }
+        if ir.privacy.is_no_store() {
+            let privacy_headers = ir.model.provider.privacy_headers.clone();
+            ir.model.provider.extra_headers.extend(privacy_headers);
+        }
         let kind = ir.model.provider.kind.clone();
         let adapter = self.registry.get(&kind)
-            .ok_or_else(|| anyhow::anyhow!("no adapter for {:?}", kind))?;
+            .ok_or_else(|| crate::error::EngineError::config(format!("no adapter for {:?}", kind)))?;
+        crate::moderation::apply_filters(&self.filters, &mut ir).await?;
+        let output_guard = self.output.apply(&mut ir);
+        self.context.fit(&adapter, self.token_counter.as_ref(), &mut ir, &cancel).await;
+        let permit = self.acquire_slot(&ir.model, &mut ir.metadata, &cancel).await?;
         
         tracing::info!(
             request_id = %ir.metadata.get("request_id").unwrap_or(&"unknown".to_string()),
             model_alias = %ir.model.alias,
             provider_kind = ?kind,
+            endpoint = %ir.metadata.get("endpoint").map(String::as_str).unwrap_or("-"),
             "Routing chat request"
         );
 
-        Ok(adapter.execute_chat(ir, cancel).await?)
+        // Don't let the upstream outlive the client's deadline
+        if let Some(deadline) = deadline {
+            // Rounded up so the deadline itself fires first
+            let remaining = deadline.remaining().as_millis() as u64 + 1;
+            let timeout = ir
+                .model
+                .provider
+                .first_byte_timeout()
+                .map_or(remaining, |timeout| (timeout.as_millis() as u64).min(remaining));
+            ir.model.provider.first_byte_timeout_ms = Some(timeout);
+        }
+
+        let model_id = ir.model.model_id.clone();
+        let alias = ir.model.alias.clone();
+        let api_key = ir.metadata.get(crate::audit::API_KEY_NAME_METADATA).cloned();
+        let traced = ir.metadata.contains_key(crate::trace::TRACE_METADATA);
+        let started = std::time::Instant::now();
+        let upstream_cancel = cancel.child_token();
+        let (events, request_id) =
+            crate::trace::capture_request_id(adapter.execute_chat(ir, upstream_cancel.clone())).await;
+        let upstream = traced.then(|| crate::trace::UpstreamCall {
+            latency: started.elapsed(),
+            request_id,
+        });
+        let events = match events {
+            Ok(events) => events,
+            Err(e) => {
+                if let Some(endpoint) = endpoint.filter(|_| !overridden) {
+                    endpoint.finish(!e.is_endpoint_failure());
+                }
+                return Err(e.into());
+            }
+        };
+        let events: Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin> =
+            Box::new(Box::pin(crate::stream::normalize(kind, events)));
+        let events: Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin> =
+            if output_guard.is_needed() {
+                Box::new(Box::pin(self.output.guard(output_guard, upstream_cancel, events)))
+            } else {
+                events
+            };
+        let events: Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin> = match &self.pricing {
+            Some(table) => Box::new(Box::pin(crate::pricing::with_cost(
+                table.clone(),
+                self.usage.clone(),
+                model_id,
+                alias,
+                events,
+            ))),
+            None => events,
+        };
+        let events: Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin> =
+            match (&self.budgets, api_key) {
+                (Some(budgets), Some(key)) => Box::new(Box::pin(crate::budget::record_spend(budgets.clone(), key, events))),
+                _ => events,
+            };
+        let events: Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin> =
+            Box::new(Box::pin(crate::stream::with_final_message(events)));
+        if endpoint.is_none() && permit.is_none() && upstream.is_none() {
+            return Ok(events);
+        }
+
+        // Announce the chosen endpoint, queue wait and (for traced requests)
+        // the upstream call, hold the provider slot until the stream ends,
+        // and feed the outcome back into the endpoint's health
+        let mut events = events;
+        let stream = async_stream::stream! {
+            if let Some(endpoint) = &endpoint {
+                yield crate::stream::StreamEvent::Status {
+                    state: "endpoint".to_string(),
+                    detail: Some(endpoint.provider().to_string()),
+                };
+            }
+            if let Some(permit) = &permit {
+                yield crate::stream::StreamEvent::Status {
+                    state: "queue_wait".to_string(),
+                    detail: Some(format!("{}ms", permit.waited().as_millis())),
+                };
+            }
+            if let Some(call) = &upstream {
+                yield call.to_event();
+            }
+            let mut success = true;
+            while let Some(event) = futures_util::StreamExt::next(&mut events).await {
+                if let crate::stream::StreamEvent::Error { code, .. } = &event {
+                    success &= !crate::balancer::is_endpoint_failure(code);
+                }
+                yield event;
+            }
+            if let Some(endpoint) = endpoint.filter(|_| !overridden) {
+                endpoint.finish(success);
+            }
+            drop(permit);
+        };
+        let stream: Box<dyn futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin> =
+            Box::new(Box::pin(stream));
+        Ok(stream)
     }
-}
\ No newline at end of file
+
+    pub async fn route_image(
+        &self,
+        mut ir: crate::types::ImageRequestIR,
+    ) -> Result<crate::types::ImageResponseIR, crate::error::EngineError> {
+        let endpoint = self.select_endpoint(&mut ir.model, &mut ir.metadata, None);
+        let kind = ir.model.provider.kind.clone();
+        let adapter = self.registry.get(&kind)
+            .ok_or_else(|| crate::error::EngineError::config(format!("no adapter for {:?}", kind)))?;
+        crate::moderation::filter_text(&self.filters, &ir.prompt, &mut ir.metadata).await?;
+        let permit = self
+            .acquire_slot(&ir.model, &mut ir.metadata, &tokio_util::sync::CancellationToken::new())
+            .await?;
+
+        tracing::info!(
+            model_alias = %ir.model.alias,
+            provider_kind = ?kind,
+            "Routing image request"
+        );
+
+        let result = adapter.execute_image(ir).await;
+        drop(permit);
+        if let Some(endpoint) = endpoint {
+            endpoint.finish(result.as_ref().map_or_else(|e| !e.is_endpoint_failure(), |_| true));
+        }
+        Ok(result?)
+    }
+
+    pub async fn route_speech(
+        &self,
+        mut ir: crate::types::SpeechRequestIR,
+    ) -> Result<crate::types::SpeechResponseIR, crate::error::EngineError> {
+        let endpoint = self.select_endpoint(&mut ir.model, &mut ir.metadata, None);
+        let kind = ir.model.provider.kind.clone();
+        let adapter = self.registry.get(&kind)
+            .ok_or_else(|| crate::error::EngineError::config(format!("no adapter for {:?}", kind)))?;
+        crate::moderation::filter_text(&self.filters, &ir.input, &mut ir.metadata).await?;
+        let permit = self
+            .acquire_slot(&ir.model, &mut ir.metadata, &tokio_util::sync::CancellationToken::new())
+            .await?;
+
+        tracing::info!(
+            model_alias = %ir.model.alias,
+            provider_kind = ?kind,
+            "Routing speech request"
+        );
+
+        let result = adapter.execute_speech(ir).await;
+        drop(permit);
+        if let Some(endpoint) = endpoint {
+            endpoint.finish(result.as_ref().map_or_else(|e| !e.is_endpoint_failure(), |_| true));
+        }
+        Ok(result?)
+    }
+}
+
+/// The time by which a request with a `request_timeout` must be answered
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Deadline {
+    at: tokio::time::Instant,
+    budget: std::time::Duration,
+}
+
+impl Deadline {
+    pub(crate) fn new(budget: std::time::Duration) -> Self {
+        Self {
+            at: tokio::time::Instant::now() + budget,
+            budget,
+        }
+    }
+
+    fn remaining(&self) -> std::time::Duration {
+        self.at.saturating_duration_since(tokio::time::Instant::now())
+    }
+
+    fn exceeded(&self, stage: &str) -> crate::error::EngineError {
+        crate::error::EngineError::DeadlineExceeded(format!(
+            "request timeout of {}ms exceeded {}",
+            self.budget.as_millis(),
+            stage
+        ))
+    }
+
+    /// Fail `events` with `deadline_exceeded` if the first content event
+    /// (anything but a `Status`) misses the deadline, or for non-streaming
+    /// requests if the whole stream does
+    fn bound<S>(self, mut events: S, streaming: bool) -> impl futures_util::Stream<Item = crate::stream::StreamEvent> + Send
+    where
+        S: futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin,
+    {
+        async_stream::stream! {
+            let mut bounded = true;
+            loop {
+                let event = if bounded {
+                    match tokio::time::timeout_at(self.at, futures_util::StreamExt::next(&mut events)).await {
+                        Ok(event) => event,
+                        Err(_) => {
+                            let stage = if streaming { "before the provider responded" } else { "before the reply was complete" };
+                            tracing::warn!(budget_ms = self.budget.as_millis() as u64, "Request deadline exceeded");
+                            yield crate::stream::StreamEvent::Error {
+                                code: "deadline_exceeded".to_string(),
+                                message: self.exceeded(stage).to_string(),
+                            };
+                            return;
+                        }
+                    }
+                } else {
+                    futures_util::StreamExt::next(&mut events).await
+                };
+                let Some(event) = event else { return };
+                if streaming && !matches!(event, crate::stream::StreamEvent::Status { .. }) {
+                    bounded = false;
+                }
+                yield event;
+            }
+        }
+    }
+}
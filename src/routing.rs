@@ -0,0 +1,196 @@
+//! Smart model routing
+//!
+//! A [`RoutingPolicy`] lets clients ask for a virtual model (`auto` by
+//! default) and have the service pick a concrete one from the request itself:
+//! its prompt size, and whether it uses tools, images or JSON output. Rules
+//! are checked in order and the first match names the target; requests no
+//! rule matches go to the policy's default. The target is any name model
+//! resolution accepts, and the decision is recorded in the request's
+//! `routing_rule` and `routed_model` metadata.
+//!
+//! Policies deserialize from configuration:
+//!
+//! ```json
+//! {
+//!   "model": "auto",
+//!   "default": "openai/gpt-4o",
+//!   "rules": [
+//!     { "name": "small-talk", "max_prompt_tokens": 2000, "requires_tools": false,
+//!       "requires_vision": false, "target": "ollama/llama3.2" }
+//!   ]
+//! }
+//! ```
+
+use crate::types::{ChatRequestIR, ContentPart, ModelRef, ProviderEndpoint, ResponseFormat};
+use serde::{Deserialize, Serialize};
+
+/// Model name routed by a policy unless configured otherwise
+pub const AUTO_MODEL: &str = "auto";
+
+/// One routing rule. Unset predicates match any request; `requires_*` set to
+/// `false` match only requests without that feature.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RoutingRule {
+    /// Recorded as `routing_rule` metadata; rules without one are named by position
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Prompts of at most this many tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prompt_tokens: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_tools: Option<bool>,
+    /// Image inputs anywhere in the conversation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_vision: Option<bool>,
+    /// `json_object` or `json_schema` response formats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_json: Option<bool>,
+    /// Model served when the rule matches
+    pub target: String,
+}
+
+impl RoutingRule {
+    pub fn new(target: impl Into<String>) -> Self {
+        Self {
+            target: target.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn max_prompt_tokens(mut self, tokens: usize) -> Self {
+        self.max_prompt_tokens = Some(tokens);
+        self
+    }
+
+    pub fn requires_tools(mut self, tools: bool) -> Self {
+        self.requires_tools = Some(tools);
+        self
+    }
+
+    pub fn requires_vision(mut self, vision: bool) -> Self {
+        self.requires_vision = Some(vision);
+        self
+    }
+
+    pub fn requires_json(mut self, json: bool) -> Self {
+        self.requires_json = Some(json);
+        self
+    }
+
+    pub fn matches(&self, features: &RequestFeatures) -> bool {
+        self.max_prompt_tokens.is_none_or(|max| features.prompt_tokens <= max)
+            && self.requires_tools.is_none_or(|tools| features.has_tools == tools)
+            && self.requires_vision.is_none_or(|vision| features.has_vision == vision)
+            && self.requires_json.is_none_or(|json| features.wants_json == json)
+    }
+}
+
+/// What routing rules look at
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RequestFeatures {
+    pub prompt_tokens: usize,
+    pub has_tools: bool,
+    pub has_vision: bool,
+    pub wants_json: bool,
+}
+
+impl RequestFeatures {
+    /// Features of `ir`, whose prompt was counted as `prompt_tokens`
+    pub fn of(ir: &ChatRequestIR, prompt_tokens: usize) -> Self {
+        Self {
+            prompt_tokens,
+            has_tools: !ir.tools.is_empty(),
+            has_vision: ir.messages.iter().flat_map(|m| &m.parts).any(|part| match part {
+                ContentPart::ImageUrl { .. } => true,
+                ContentPart::BlobRef { mime, .. } => mime.starts_with("image/"),
+                _ => false,
+            }),
+            wants_json: matches!(
+                ir.response_format,
+                Some(ResponseFormat::JsonObject | ResponseFormat::JsonSchema { .. })
+            ),
+        }
+    }
+}
+
+/// The model a policy picked and why
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoutingDecision {
+    pub target: String,
+    /// Name of the matching rule (`rule-<index>` when unnamed), or `default`
+    pub rule: String,
+}
+
+/// Rules routing a virtual model to concrete ones
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoutingPolicy {
+    /// The virtual model clients request
+    #[serde(default = "default_policy_model")]
+    pub model: String,
+    #[serde(default)]
+    pub rules: Vec<RoutingRule>,
+    /// Model served when no rule matches
+    pub default: String,
+}
+
+fn default_policy_model() -> String {
+    AUTO_MODEL.to_string()
+}
+
+impl RoutingPolicy {
+    /// A policy for [`AUTO_MODEL`] sending everything to `default` until rules are added
+    pub fn new(default: impl Into<String>) -> Self {
+        Self {
+            model: default_policy_model(),
+            rules: Vec::new(),
+            default: default.into(),
+        }
+    }
+
+    /// Route `model` instead of [`AUTO_MODEL`]
+    pub fn for_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn with_rule(mut self, rule: RoutingRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Whether requests for `model` are routed by this policy
+    pub fn handles(&self, model: &str) -> bool {
+        model.trim().eq_ignore_ascii_case(&self.model)
+    }
+
+    /// Stand-in for the virtual model until [`select`](Self::select) picks a real one
+    pub fn placeholder_model(&self) -> ModelRef {
+        ModelRef {
+            alias: self.model.clone(),
+            provider: ProviderEndpoint::default(),
+            model_id: self.model.clone(),
+            modalities: Vec::new(),
+        }
+    }
+
+    /// The first matching rule's target, or the default
+    pub fn select(&self, features: &RequestFeatures) -> RoutingDecision {
+        self.rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.matches(features))
+            .map(|(index, rule)| RoutingDecision {
+                target: rule.target.clone(),
+                rule: rule.name.clone().unwrap_or_else(|| format!("rule-{}", index)),
+            })
+            .unwrap_or_else(|| RoutingDecision {
+                target: self.default.clone(),
+                rule: "default".to_string(),
+            })
+    }
+}
@@ -1,61 +1,203 @@
-use axum::{routing::{post, get}, Router, body::Bytes};
+use axum::{routing::{post, get, MethodRouter}, Router, body::Bytes};
 use tower::{ServiceBuilder};
 use tower_http::{trace::TraceLayer, cors::CorsLayer};
+use crate::adapter::ChatAdapter;
+use crate::cors::CorsConfig;
+use crate::error::EngineError;
+use crate::moderation::{ContentFilter, ModerationClient};
+use crate::balancer::LoadBalancer;
+use crate::context::ContextPolicy;
+use crate::guardrails::OutputLimits;
+use crate::store::{ConversationStore, InMemoryConversationStore};
+use crate::tokens::TokenCounter;
+use crate::router::AdapterRegistry;
 use crate::service::OmniferenceService;
+use crate::skins::SkinKind;
 use crate::types::ProviderConfig;
+use std::sync::Arc;
 use tokio::net::TcpListener;
 // use serde_json::json; // not currently used
 
-/// HTTP server that provides OpenAI-compatible API
+type LayerFn = Arc<dyn Fn(Router) -> Router + Send + Sync>;
+
+/// HTTP server that provides OpenAI-compatible API.
+///
+/// [`new`](Self::new) and [`OmniferenceServerBuilder`] register all built-in
+/// adapters (Ollama, OpenAI Chat Completions and OpenAI Responses), so
+/// providers of those kinds work without further setup; [`empty`](Self::empty)
+/// registers none. Adding a provider whose kind has no adapter fails.
 pub struct OmniferenceServer {
     service: OmniferenceService,
     app: Option<Router>,
+    skins: Vec<SkinKind>,
+    routes: Vec<(String, MethodRouter)>,
+    layers: Vec<LayerFn>,
+    cors: Option<CorsLayer>,
+    trace: bool,
+    compression: bool,
+    discover_on_start: bool,
+    moderation: Option<Arc<ModerationClient>>,
+    conversations: Arc<dyn ConversationStore>,
+    /// Bearer token of the `/admin` routes; they are not served without one
+    admin_token: Option<String>,
+    extra_body_passthrough: bool,
+    debug_headers: bool,
+    provider_key_header: bool,
+    organization_headers: bool,
+    tenants: Option<crate::tenant::TenantDirectory>,
+    startup_check: crate::verify::StartupCheck,
+    resumable_streams: Option<crate::skins::resumable::ResumableStreams>,
+    sse_keep_alive: crate::skins::keepalive::SseKeepAlive,
+    back_pressure: crate::backpressure::BackPressure,
+    system_fingerprint: crate::skins::openai::SystemFingerprint,
+    auto_tools: crate::tools::AutoToolExecution,
+    passthrough: Vec<crate::passthrough::PassthroughRoute>,
 }
 
 impl OmniferenceServer {
-    /// Create a new server instance
+    /// Create a server with all built-in adapters registered
     pub fn new() -> Self {
-        Self {
-            service: OmniferenceService::new(),
-            app: None,
-        }
+        Self::with_service(OmniferenceService::new())
+    }
+
+    /// Create a server without any adapters; register them with
+    /// [`register_adapter`](Self::register_adapter) before adding providers
+    pub fn empty() -> Self {
+        Self::with_service(OmniferenceService::empty())
+    }
+
+    /// Create a server over `core`, sharing providers and models with
+    /// engines over it (see [`OmniferenceCore`](crate::service::OmniferenceCore))
+    pub fn from_core(core: crate::service::OmniferenceCore) -> Self {
+        Self::with_service(OmniferenceService::from_core(core))
     }
 
-    
     /// Create a server with a custom service
     pub fn with_service(service: OmniferenceService) -> Self {
         Self {
             service,
             app: None,
+            skins: SkinKind::all().to_vec(),
+            routes: Vec::new(),
+            layers: Vec::new(),
+            cors: None,
+            trace: true,
+            compression: false,
+            discover_on_start: false,
+            moderation: None,
+            conversations: Arc::new(InMemoryConversationStore::new()),
+            admin_token: None,
+            extra_body_passthrough: true,
+            debug_headers: true,
+            provider_key_header: false,
+            organization_headers: false,
+            tenants: None,
+            startup_check: Default::default(),
+            resumable_streams: None,
+            sse_keep_alive: Default::default(),
+            back_pressure: Default::default(),
+            system_fingerprint: Default::default(),
+            auto_tools: Default::default(),
+            passthrough: Vec::new(),
         }
     }
 
+    /// Start configuring a server with [`OmniferenceServerBuilder`]
+    pub fn builder() -> OmniferenceServerBuilder {
+        OmniferenceServerBuilder::new()
+    }
+
     
     /// Add a provider configuration
-    pub async fn add_provider(&mut self, provider: ProviderConfig) -> Result<(), String> {
+    pub async fn add_provider(&mut self, provider: ProviderConfig) -> Result<(), EngineError> {
         self.service.register_provider(provider).await
     }
 
+    /// Add or replace the adapter for its provider kind, returning the one
+    /// it replaced. Takes effect for already built routers too.
+    pub fn register_adapter(&self, adapter: Arc<dyn ChatAdapter>) -> Option<Arc<dyn ChatAdapter>> {
+        self.service.register_adapter(adapter)
+    }
+
+    /// Provider kinds with a registered adapter
+    pub fn adapter_kinds(&self) -> Vec<crate::types::ProviderKind> {
+        self.service.adapter_kinds()
+    }
+
+    /// Register a constructor for `custom:<name>` providers (see
+    /// [`OmniferenceService::register_adapter_factory`])
+    pub fn register_adapter_factory<F>(&self, name: impl Into<String>, factory: F) -> Option<crate::router::AdapterFactory>
+    where
+        F: Fn(&serde_json::Map<String, serde_json::Value>) -> Result<Arc<dyn ChatAdapter>, crate::adapter::AdapterError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.service.register_adapter_factory(name, factory)
+    }
+
     /// Build the Axum application
     fn build_app(&self) -> Router {
-        let ctx = crate::skins::context::SkinContext::with_provider_manager(
-            self.service.router.as_ref().clone(),
-            self.service.provider_manager().clone(),
-        );
-        
-        Router::new()
-            // OpenAI Responses API
-            .route("/api/openai/v1/responses", post(crate::skins::openai::handle_responses))
-            .route("/api/openai-compatible/v1/chat/completions", post(crate::skins::openai::handle_chat))
-            .route("/api/openai/v1/models", get(crate::skins::openai::handle_models))
-            .route("/api/openai-compatible/v1/models", get(crate::skins::openai::handle_models))
-            .with_state(ctx)
-            .layer(
-                ServiceBuilder::new()
-                    .layer(TraceLayer::new_for_http())
-                    .layer(CorsLayer::permissive())
-            )
-            .fallback(axum::routing::any(skin_aware_error_handler))
+        let mut ctx = crate::skins::context::SkinContext::from_core(self.service.core());
+        ctx.moderation = self.moderation.clone();
+        ctx.conversations = self.conversations.clone();
+        ctx.extra_body_passthrough = self.extra_body_passthrough;
+        ctx.debug_headers = self.debug_headers;
+        ctx.provider_key_header = self.provider_key_header;
+        ctx.organization_headers = self.organization_headers;
+        ctx.tenants = self.tenants.clone().unwrap_or_default();
+        ctx.resumable_streams = self.resumable_streams.clone();
+        ctx.sse_keep_alive = self.sse_keep_alive.clone();
+        ctx.back_pressure = self.back_pressure;
+        ctx.system_fingerprint = self.system_fingerprint.clone();
+        ctx.auto_tools = self.auto_tools.clone();
+
+        let mut api = Router::new();
+        for skin in &self.skins {
+            api = api.merge(skin_routes(*skin));
+        }
+        let mut app = api.with_state(ctx);
+        for (path, method_router) in &self.routes {
+            app = app.route(path, method_router.clone());
+        }
+        if !self.passthrough.is_empty() {
+            app = app.merge(crate::passthrough::routes_with_options(
+                &self.passthrough,
+                self.service.provider_manager().clone(),
+                self.tenants.clone().unwrap_or_default(),
+                self.organization_headers,
+            ));
+        }
+        // Inside the custom layers, so authentication has claimed its header
+        app = app.layer(axum::middleware::from_fn(caller_provider_key));
+        if let (Some(token), Some(budgets)) = (&self.admin_token, self.service.router.budgets()) {
+            app = app.merge(budget_admin_routes(budgets.clone(), self.tenants.clone(), token.clone()));
+        }
+        if let Some(token) = &self.admin_token {
+            app = app.merge(discovery_admin_routes(self.service.provider_manager().clone(), token.clone()));
+        }
+        if let (Some(token), Some(tenants)) = (&self.admin_token, &self.tenants) {
+            app = app.merge(tenant_admin_routes(tenants.clone(), self.service.provider_manager().clone(), token.clone()));
+        }
+
+        let app = if self.compression {
+            app.layer(compression_layer())
+        } else {
+            app
+        };
+        let app = if self.trace {
+            app.layer(TraceLayer::new_for_http())
+        } else {
+            app
+        };
+        let mut app = match &self.cors {
+            Some(cors) => app.layer(ServiceBuilder::new().layer(cors.clone())),
+            None => app,
+        };
+        for layer in &self.layers {
+            app = layer(app);
+        }
+        app.fallback(axum::routing::any(skin_aware_error_handler))
     }
 
     /// Get the Axum application (for embedding in existing Axum apps)
@@ -66,8 +208,21 @@ impl OmniferenceServer {
         self.app.as_ref().unwrap().clone()
     }
 
+    /// Consume the server and return its Axum application, ready to be
+    /// nested into another app (e.g. `.nest("/llm", server.into_router())`).
+    ///
+    /// Routes are relative to the nesting point. The router carries its own
+    /// `TraceLayer`, and a CORS layer if one was configured; build with
+    /// [`OmniferenceServerBuilder::without_trace`] when the parent app already
+    /// traces requests, otherwise they are traced twice.
+    pub fn into_router(mut self) -> Router {
+        self.app()
+    }
+
     /// Run the server on the specified address
     pub async fn run(&mut self, addr: &str) -> anyhow::Result<()> {
+        self.startup_check().await?;
+        self.discover_pending().await;
         let app = self.app();
         
         tracing::info!("Starting Omniference server on {}", addr);
@@ -80,11 +235,60 @@ impl OmniferenceServer {
 
     /// Run the server with a custom listener (for embedding)
     pub async fn serve_with_listener(&mut self, listener: TcpListener) -> anyhow::Result<()> {
+        self.startup_check().await?;
+        self.discover_pending().await;
         let app = self.app();
         axum::serve(listener, app).await?;
         Ok(())
     }
 
+    /// Verify the providers as the server's
+    /// [`StartupCheck`](crate::verify::StartupCheck) says, returning the report
+    /// unless the check is off. [`run`](Self::run) and
+    /// [`serve_with_listener`](Self::serve_with_listener) call it first.
+    pub async fn startup_check(&self) -> Result<Option<crate::verify::VerificationReport>, EngineError> {
+        use crate::verify::StartupCheck;
+
+        if self.startup_check == StartupCheck::Off {
+            return Ok(None);
+        }
+        let report = self.service.verify_providers().await;
+        for check in report.failed() {
+            tracing::warn!(
+                provider_name = %check.provider,
+                stage = ?check.failed_stage,
+                error = check.error.as_deref().unwrap_or_default(),
+                "Provider failed verification"
+            );
+        }
+        match self.startup_check {
+            StartupCheck::Refuse if !report.is_ok() => {
+                let failed: Vec<&str> = report.failed().map(|check| check.provider.as_str()).collect();
+                return Err(EngineError::Config(format!("providers failed verification: {}", failed.join(", "))));
+            }
+            StartupCheck::Disable => {
+                let mut manager = self.service.provider_manager().write().await;
+                for check in report.failed() {
+                    manager.disable_provider(&check.provider);
+                }
+            }
+            _ => {}
+        }
+        Ok(Some(report))
+    }
+
+    /// Discover models for providers added through the builder, which
+    /// registers them synchronously without contacting the provider.
+    async fn discover_pending(&mut self) {
+        if !self.discover_on_start {
+            return;
+        }
+        self.discover_on_start = false;
+        if let Err(e) = self.service.discover_models().await {
+            tracing::warn!(error = %e, "Failed to discover models on startup");
+        }
+    }
+
     /// Get a reference to the underlying service
     pub fn service(&self) -> &OmniferenceService {
         &self.service
@@ -94,6 +298,12 @@ impl OmniferenceServer {
     pub fn service_mut(&mut self) -> &mut OmniferenceService {
         &mut self.service
     }
+
+    /// An engine over this server's core, for calling models from the
+    /// embedding app without going through HTTP
+    pub fn engine(&self) -> crate::OmniferenceEngine {
+        crate::OmniferenceEngine::from_core(self.service.core().clone())
+    }
 }
 
 impl Default for OmniferenceServer {
@@ -102,34 +312,922 @@ impl Default for OmniferenceServer {
     }
 }
 
+/// Routes served by a single protocol skin
+fn skin_routes(skin: SkinKind) -> Router<crate::skins::context::SkinContext> {
+    match skin {
+        SkinKind::OpenAI => Router::new()
+            // OpenAI Responses API
+            .route("/api/openai/v1/responses", post(crate::skins::openai::handle_responses))
+            .route("/api/openai/v1/responses/:id", get(crate::skins::openai::handle_get_response))
+            .route("/api/openai/v1/responses/:id/cancel", post(crate::skins::openai::handle_cancel_response))
+            // Chat Completions streaming over WebSocket
+            .route("/api/openai/v1/chat/ws", get(crate::skins::websocket::handle_chat_ws))
+            .route("/api/openai/v1/chat/stream/:request_id", get(crate::skins::openai::handle_chat_stream_resume))
+            .route("/api/openai/v1/models", get(crate::skins::openai::handle_models))
+            .route("/api/openai/v1/moderations", post(crate::skins::openai::handle_moderations))
+            .route("/api/openai/v1/images/generations", post(crate::skins::openai::handle_image_generations))
+            .route("/api/openai/v1/audio/speech", post(crate::skins::openai::handle_audio_speech))
+            .route("/api/openai/v1/tokenize", post(crate::skins::openai::handle_tokenize)),
+        SkinKind::OpenAICompatible => Router::new()
+            .route("/api/openai-compatible/v1/chat/completions", post(crate::skins::openai::handle_chat))
+            .route("/api/openai-compatible/v1/chat/stream/:request_id", get(crate::skins::openai::handle_chat_stream_resume))
+            .route("/api/openai-compatible/v1/models", get(crate::skins::openai::handle_models))
+            .route("/api/openai-compatible/v1/moderations", post(crate::skins::openai::handle_moderations))
+            .route("/api/openai-compatible/v1/images/generations", post(crate::skins::openai::handle_image_generations))
+            .route("/api/openai-compatible/v1/audio/speech", post(crate::skins::openai::handle_audio_speech))
+            .route("/api/openai-compatible/v1/tokenize", post(crate::skins::openai::handle_tokenize)),
+    }
+}
+
+/// Expose the caller's own provider key as a [`ProviderKey`](crate::types::ProviderKey)
+/// extension, unless a layer already did: the `x-provider-api-key` header,
+/// else the `Authorization` bearer token when no layer authenticated the
+/// request with it
+async fn caller_provider_key(mut request: axum::extract::Request, next: axum::middleware::Next) -> axum::response::Response {
+    if request.extensions().get::<crate::types::ProviderKey>().is_none() {
+        let headers = request.headers();
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        let gateway_key = request.extensions().get::<crate::budget::ApiKeyName>().is_some();
+        let key = header(crate::skins::openai::PROVIDER_KEY_HEADER).or_else(|| {
+            header(axum::http::header::AUTHORIZATION.as_str())
+                .filter(|_| !gateway_key)
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .map(str::trim)
+        });
+        if let Some(key) = key.map(crate::types::SecretString::from) {
+            request.extensions_mut().insert(crate::types::ProviderKey(key));
+        }
+    }
+    next.run(request).await
+}
+
+/// State of the budget admin routes
+/// Whether `headers` carry `Authorization: Bearer <token>`
+fn admin_authorized(token: &str, headers: &axum::http::HeaderMap) -> bool {
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|value| value == token)
+}
+
+fn admin_unauthorized() -> axum::response::Response {
+    use axum::response::IntoResponse;
+    (
+        axum::http::StatusCode::UNAUTHORIZED,
+        axum::Json(admin_error("invalid_admin_token", "A valid admin token is required")),
+    )
+        .into_response()
+}
+
+fn admin_error(code: &str, message: &str) -> serde_json::Value {
+    serde_json::json!({ "error": { "message": message, "type": "invalid_request_error", "code": code } })
+}
+
+/// Query of the `/admin` lists
+#[derive(serde::Deserialize)]
+struct AdminQuery {
+    /// Only list what belongs to this tenant
+    tenant: Option<String>,
+}
+
+#[derive(Clone)]
+struct BudgetAdmin {
+    budgets: crate::budget::BudgetTracker,
+    tenants: Option<crate::tenant::TenantDirectory>,
+    token: Arc<str>,
+}
+
+/// `GET /admin/budgets[?tenant=<tenant>]`, `GET /admin/budgets/:key` and
+/// `POST /admin/budgets/:key/reset`, behind `Authorization: Bearer <token>`
+fn budget_admin_routes(
+    budgets: crate::budget::BudgetTracker,
+    tenants: Option<crate::tenant::TenantDirectory>,
+    token: String,
+) -> Router {
+    use axum::{extract::{Path, Query, State}, http::{HeaderMap, StatusCode}, response::{IntoResponse, Response}, Json};
+
+    fn authorized(admin: &BudgetAdmin, headers: &HeaderMap) -> bool {
+        admin_authorized(&admin.token, headers)
+    }
+
+    fn unauthorized() -> Response {
+        admin_unauthorized()
+    }
+
+    fn key_status(status: Option<crate::budget::BudgetStatus>, key: &str) -> Response {
+        match status {
+            Some(status) => Json(status).into_response(),
+            None => (
+                StatusCode::NOT_FOUND,
+                Json(admin_error("budget_not_found", &format!("API key '{}' has no budget", key))),
+            )
+                .into_response(),
+        }
+    }
+
+    async fn list(State(admin): State<BudgetAdmin>, Query(query): Query<AdminQuery>, headers: HeaderMap) -> Response {
+        if !authorized(&admin, &headers) {
+            return unauthorized();
+        }
+        let mut statuses = admin.budgets.statuses();
+        if let Some(tenant) = &query.tenant {
+            let keys = admin.tenants.as_ref().map(|tenants| tenants.keys_of(tenant)).unwrap_or_default();
+            statuses.retain(|status| keys.contains(&status.key));
+        }
+        Json(serde_json::json!({ "data": statuses })).into_response()
+    }
+
+    async fn show(State(admin): State<BudgetAdmin>, Path(key): Path<String>, headers: HeaderMap) -> Response {
+        if !authorized(&admin, &headers) {
+            return unauthorized();
+        }
+        key_status(admin.budgets.status(&key), &key)
+    }
+
+    async fn reset(State(admin): State<BudgetAdmin>, Path(key): Path<String>, headers: HeaderMap) -> Response {
+        if !authorized(&admin, &headers) {
+            return unauthorized();
+        }
+        key_status(admin.budgets.reset(&key), &key)
+    }
+
+    Router::new()
+        .route("/admin/budgets", get(list))
+        .route("/admin/budgets/:key", get(show))
+        .route("/admin/budgets/:key/reset", post(reset))
+        .with_state(BudgetAdmin {
+            budgets,
+            tenants,
+            token: token.into(),
+        })
+}
+
+#[derive(Clone)]
+struct DiscoveryAdmin {
+    provider_manager: Arc<tokio::sync::RwLock<crate::service::ProviderManager>>,
+    token: Arc<str>,
+}
+
+/// `GET /admin/discovery`, the providers whose last model discovery failed,
+/// behind `Authorization: Bearer <token>`
+fn discovery_admin_routes(
+    provider_manager: Arc<tokio::sync::RwLock<crate::service::ProviderManager>>,
+    token: String,
+) -> Router {
+    use axum::{extract::State, http::HeaderMap, response::{IntoResponse, Response}, Json};
+
+    async fn errors(State(admin): State<DiscoveryAdmin>, headers: HeaderMap) -> Response {
+        if !admin_authorized(&admin.token, &headers) {
+            return admin_unauthorized();
+        }
+        let manager = admin.provider_manager.read().await;
+        Json(serde_json::json!({ "errors": manager.discovery_errors() })).into_response()
+    }
+
+    Router::new().route("/admin/discovery", get(errors)).with_state(DiscoveryAdmin {
+        provider_manager,
+        token: token.into(),
+    })
+}
+
+#[derive(Clone)]
+struct TenantAdmin {
+    tenants: crate::tenant::TenantDirectory,
+    provider_manager: Arc<tokio::sync::RwLock<crate::service::ProviderManager>>,
+    token: Arc<str>,
+}
+
+/// `GET /admin/tenants`, `GET /admin/tenants/:tenant/models` and
+/// `PUT`/`DELETE /admin/tenants/:tenant/keys/:key`, behind
+/// `Authorization: Bearer <token>`
+fn tenant_admin_routes(
+    tenants: crate::tenant::TenantDirectory,
+    provider_manager: Arc<tokio::sync::RwLock<crate::service::ProviderManager>>,
+    token: String,
+) -> Router {
+    use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, response::{IntoResponse, Response}, routing::put, Json};
+
+    fn tenant_keys(admin: &TenantAdmin, tenant: &str) -> serde_json::Value {
+        serde_json::json!({ "tenant": tenant, "keys": admin.tenants.keys_of(tenant) })
+    }
+
+    async fn list(State(admin): State<TenantAdmin>, headers: HeaderMap) -> Response {
+        if !admin_authorized(&admin.token, &headers) {
+            return admin_unauthorized();
+        }
+        let data: Vec<_> = admin.tenants.tenants().iter().map(|tenant| tenant_keys(&admin, tenant)).collect();
+        Json(serde_json::json!({ "data": data })).into_response()
+    }
+
+    async fn models(State(admin): State<TenantAdmin>, Path(tenant): Path<String>, headers: HeaderMap) -> Response {
+        if !admin_authorized(&admin.token, &headers) {
+            return admin_unauthorized();
+        }
+        let manager = admin.provider_manager.read().await;
+        let models = manager.list_models_in(crate::tenant::TenantScope::Tenant(&tenant));
+        Json(serde_json::json!({ "data": models })).into_response()
+    }
+
+    async fn assign(State(admin): State<TenantAdmin>, Path((tenant, key)): Path<(String, String)>, headers: HeaderMap) -> Response {
+        if !admin_authorized(&admin.token, &headers) {
+            return admin_unauthorized();
+        }
+        admin.tenants.assign(key, tenant.clone());
+        Json(tenant_keys(&admin, &tenant)).into_response()
+    }
+
+    async fn unassign(State(admin): State<TenantAdmin>, Path((tenant, key)): Path<(String, String)>, headers: HeaderMap) -> Response {
+        if !admin_authorized(&admin.token, &headers) {
+            return admin_unauthorized();
+        }
+        if admin.tenants.tenant_of(&key).as_deref() != Some(tenant.as_str()) {
+            let message = format!("API key '{}' is not assigned to tenant '{}'", key, tenant);
+            return (StatusCode::NOT_FOUND, Json(admin_error("tenant_key_not_found", &message))).into_response();
+        }
+        admin.tenants.unassign(&key);
+        Json(tenant_keys(&admin, &tenant)).into_response()
+    }
+
+    Router::new()
+        .route("/admin/tenants", get(list))
+        .route("/admin/tenants/:tenant/models", get(models))
+        .route("/admin/tenants/:tenant/keys/:key", put(assign).delete(unassign))
+        .with_state(TenantAdmin {
+            tenants,
+            provider_manager,
+            token: token.into(),
+        })
+}
+
+/// Response compression that leaves streams alone: the default predicate
+/// already skips SSE, gRPC, images and tiny bodies
+fn compression_layer() -> tower_http::compression::CompressionLayer<impl tower_http::compression::Predicate> {
+    use tower_http::compression::predicate::{DefaultPredicate, NotForContentType, Predicate};
+    tower_http::compression::CompressionLayer::new()
+        .no_br()
+        .no_deflate()
+        .compress_when(DefaultPredicate::new().and(NotForContentType::const_new("audio/")))
+}
+
 /// Builder for configuring an OmniferenceServer
+///
+/// ```rust,no_run
+/// use omniference::{server::OmniferenceServerBuilder, skins::SkinKind, types::ProviderConfig};
+/// use axum::routing::get;
+///
+/// # fn example(provider: ProviderConfig) {
+/// let server = OmniferenceServerBuilder::new()
+///     .with_provider(provider)
+///     .enable_skin(SkinKind::OpenAICompatible)
+///     .with_route("/healthz", get(|| async { "ok" }))
+///     .build();
+/// # }
+/// ```
 pub struct OmniferenceServerBuilder {
-    service: OmniferenceService,
+    service: Option<OmniferenceService>,
+    registry: AdapterRegistry,
+    providers: Vec<ProviderConfig>,
+    skins: Vec<SkinKind>,
+    routes: Vec<(String, MethodRouter)>,
+    layers: Vec<LayerFn>,
+    cors: Option<CorsLayer>,
+    trace: bool,
+    compression: bool,
+    filters: Vec<Arc<dyn ContentFilter>>,
+    token_counter: Option<Arc<dyn TokenCounter>>,
+    default_context_policy: Option<ContextPolicy>,
+    context_policies: Vec<(String, ContextPolicy)>,
+    default_output_limits: Option<OutputLimits>,
+    output_limits: Vec<(String, OutputLimits)>,
+    load_balancer: Option<LoadBalancer>,
+    routing_policy: Option<crate::routing::RoutingPolicy>,
+    pricing: Option<crate::pricing::PricingTable>,
+    budgets: Option<crate::budget::BudgetTracker>,
+    dedup: Option<crate::dedup::RequestDeduplicator>,
+    admin_token: Option<String>,
+    extra_body_passthrough: bool,
+    debug_headers: bool,
+    provider_key_header: bool,
+    organization_headers: bool,
+    tenants: Option<crate::tenant::TenantDirectory>,
+    startup_check: crate::verify::StartupCheck,
+    resumable_streams: Option<crate::skins::resumable::ResumableStreams>,
+    sse_keep_alive: crate::skins::keepalive::SseKeepAlive,
+    back_pressure: crate::backpressure::BackPressure,
+    system_fingerprint: crate::skins::openai::SystemFingerprint,
+    auto_tools: crate::tools::AutoToolExecution,
+    passthrough: Vec<crate::passthrough::PassthroughRoute>,
+    payload_transformers: Vec<(String, crate::types::PayloadTransformer)>,
+    json_validation: Option<crate::validation::JsonValidation>,
+    max_image_bytes: Option<usize>,
+    audit: Option<(Arc<dyn crate::audit::AuditSink>, crate::audit::AuditRedaction)>,
+    moderation: Option<Arc<ModerationClient>>,
+    conversations: Option<Arc<dyn ConversationStore>>,
+    model_aliases: Vec<(String, String)>,
+    tenant_model_aliases: Vec<(String, String, String)>,
+    default_provider: Option<String>,
+    default_model: Option<String>,
 }
 
 impl OmniferenceServerBuilder {
     pub fn new() -> Self {
         Self {
-            service: OmniferenceService::new(),
+            service: None,
+            registry: OmniferenceService::create_full_adapter_registry(),
+            providers: Vec::new(),
+            skins: Vec::new(),
+            routes: Vec::new(),
+            layers: Vec::new(),
+            cors: None,
+            trace: true,
+            compression: false,
+            filters: Vec::new(),
+            token_counter: None,
+            default_context_policy: None,
+            context_policies: Vec::new(),
+            default_output_limits: None,
+            output_limits: Vec::new(),
+            load_balancer: None,
+            routing_policy: None,
+            pricing: None,
+            budgets: None,
+            dedup: None,
+            admin_token: None,
+            extra_body_passthrough: true,
+            debug_headers: true,
+            provider_key_header: false,
+            organization_headers: false,
+            tenants: None,
+            startup_check: Default::default(),
+            resumable_streams: None,
+            sse_keep_alive: Default::default(),
+            back_pressure: Default::default(),
+            system_fingerprint: Default::default(),
+            auto_tools: Default::default(),
+            passthrough: Vec::new(),
+            payload_transformers: Vec::new(),
+            json_validation: None,
+            max_image_bytes: None,
+            audit: None,
+            moderation: None,
+            conversations: None,
+            model_aliases: Vec::new(),
+            tenant_model_aliases: Vec::new(),
+            default_provider: None,
+            default_model: None,
         }
     }
 
+    /// Use an existing service. Adapters added with `with_adapter` are
+    /// ignored in that case; the service's own router is used.
     pub fn with_service(mut self, service: OmniferenceService) -> Self {
-        self.service = service;
+        self.service = Some(service);
         self
     }
 
-    
-    pub fn with_provider(mut self, _provider: ProviderConfig) -> Self {
-        // Note: This is synchronous, provider registration will be done async later
-        self.service = OmniferenceService::with_router(self.service.router.as_ref().clone());
+    /// Serve over `core`, sharing providers and models with engines over it
+    /// (see [`OmniferenceCore`](crate::service::OmniferenceCore)). Like
+    /// [`with_service`](Self::with_service), the core's own router is used.
+    pub fn with_core(self, core: crate::service::OmniferenceCore) -> Self {
+        self.with_service(OmniferenceService::from_core(core))
+    }
+
+    /// Register an adapter, replacing any built-in adapter for the same provider kind
+    pub fn with_adapter(self, adapter: Arc<dyn ChatAdapter>) -> Self {
+        self.registry.register(adapter);
+        self
+    }
+
+    /// Register a constructor for `custom:<name>` providers; their adapter is
+    /// built from the endpoint's `adapter_options` when the provider is added
+    pub fn with_adapter_factory<F>(self, name: impl Into<String>, factory: F) -> Self
+    where
+        F: Fn(&serde_json::Map<String, serde_json::Value>) -> Result<Arc<dyn ChatAdapter>, crate::adapter::AdapterError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.registry.register_factory(name, factory);
+        self
+    }
+
+    /// Don't register the built-in adapter for `kind`
+    pub fn without_adapter(self, kind: crate::types::ProviderKind) -> Self {
+        self.registry.unregister(&kind);
+        self
+    }
+
+    /// Start from an empty registry. Drops adapters added so far, so call it
+    /// before `with_adapter`.
+    pub fn without_builtin_adapters(mut self) -> Self {
+        self.registry = AdapterRegistry::default();
+        self
+    }
+
+    /// Add a provider. Registration happens in `build()`; model discovery
+    /// runs when the server starts, or on the first `/models` request when
+    /// the router is embedded.
+    pub fn with_provider(mut self, provider: ProviderConfig) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    /// Resolve `alias` as `target` (see [`crate::service::ProviderManager::set_model_alias`])
+    pub fn with_model_alias(mut self, alias: impl Into<String>, target: impl Into<String>) -> Self {
+        self.model_aliases.push((alias.into(), target.into()));
+        self
+    }
+
+    /// Resolve `alias` as `target` for `tenant`'s requests (see
+    /// [`crate::service::ProviderManager::set_tenant_model_alias`])
+    pub fn with_tenant_model_alias(
+        mut self,
+        tenant: impl Into<String>,
+        alias: impl Into<String>,
+        target: impl Into<String>,
+    ) -> Self {
+        self.tenant_model_aliases.push((tenant.into(), alias.into(), target.into()));
+        self
+    }
+
+    /// Prefer `provider`'s model when an unprefixed model name matches several providers
+    pub fn with_default_provider(mut self, provider: impl Into<String>) -> Self {
+        self.default_provider = Some(provider.into());
+        self
+    }
+
+    /// Serve `model` when a request omits the model or asks for `default` or
+    /// `auto`. Responses still name the model that served them.
+    pub fn with_default_model(mut self, model: impl Into<String>) -> Self {
+        self.default_model = Some(model.into());
+        self
+    }
+
+    /// Add the providers, model aliases and defaults of a config file (see
+    /// [`crate::config_file::ConfigFile::load`])
+    pub fn with_config(mut self, config: crate::config_file::ConfigFile) -> Self {
+        self.providers.extend(config.providers);
+        self.model_aliases.extend(config.model_aliases);
+        self.passthrough.extend(config.passthrough);
+        if config.default_provider.is_some() {
+            self.default_provider = config.default_provider;
+        }
+        if config.default_model.is_some() {
+            self.default_model = config.default_model;
+        }
+        if let Some(check) = config.startup_check {
+            self.startup_check = check;
+        }
+        if let Some(compression) = config.compression {
+            self.compression = compression;
+        }
+        self
+    }
+
+    /// Add a user route alongside the API routes
+    pub fn with_route(mut self, path: &str, method_router: MethodRouter) -> Self {
+        self.routes.push((path.to_string(), method_router));
+        self
+    }
+
+    /// Wrap the application in a tower layer (applied outside the built-in layers)
+    pub fn with_layer<L>(mut self, layer: L) -> Self
+    where
+        L: tower::Layer<axum::routing::Route> + Clone + Send + Sync + 'static,
+        L::Service: tower::Service<axum::extract::Request> + Clone + Send + 'static,
+        <L::Service as tower::Service<axum::extract::Request>>::Response: axum::response::IntoResponse + 'static,
+        <L::Service as tower::Service<axum::extract::Request>>::Error: Into<std::convert::Infallible> + 'static,
+        <L::Service as tower::Service<axum::extract::Request>>::Future: Send + 'static,
+    {
+        self.layers.push(Arc::new(move |router: Router| router.layer(layer.clone())));
+        self
+    }
+
+    /// Serve the given skin. If no skin is enabled explicitly, all skins are served.
+    pub fn enable_skin(mut self, skin: SkinKind) -> Self {
+        if !self.skins.contains(&skin) {
+            self.skins.push(skin);
+        }
+        self
+    }
+
+    /// Serve CORS headers according to `config`. Without this no
+    /// cross-origin requests are allowed.
+    pub fn with_cors(mut self, config: CorsConfig) -> Self {
+        self.cors = Some(config.to_layer());
+        self
+    }
+
+    /// Use a hand-built tower-http CORS layer
+    pub fn with_cors_layer(mut self, cors: CorsLayer) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Don't install a CORS layer (the default; e.g. when the embedding app handles CORS)
+    pub fn without_cors(mut self) -> Self {
+        self.cors = None;
+        self
+    }
+
+    /// Check user content with `filter` before routing chat requests. Like
+    /// `with_adapter`, this is ignored when an existing service is used.
+    pub fn with_content_filter(mut self, filter: Arc<dyn ContentFilter>) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Count tokens for `/tokenize` with `counter`. Like `with_adapter`, this
+    /// is ignored when an existing service is used.
+    pub fn with_token_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+        self.token_counter = Some(counter);
+        self
+    }
+
+    /// Keep requests within the model's context window (see [`ContextPolicy`]).
+    /// Ignored when an existing service is used.
+    pub fn with_default_context_policy(mut self, policy: ContextPolicy) -> Self {
+        self.default_context_policy = Some(policy);
+        self
+    }
+
+    /// Context policy for one alias; overrides the default policy
+    pub fn with_context_policy(mut self, alias: impl Into<String>, policy: ContextPolicy) -> Self {
+        self.context_policies.push((alias.into(), policy));
+        self
+    }
+
+    /// Cap the tokens and bytes of every reply (see [`OutputLimits`]).
+    /// Ignored when an existing service is used.
+    pub fn with_default_output_limits(mut self, limits: OutputLimits) -> Self {
+        self.default_output_limits = Some(limits);
+        self
+    }
+
+    /// Output limits for one alias; override the default limits
+    pub fn with_output_limits(mut self, alias: impl Into<String>, limits: OutputLimits) -> Self {
+        self.output_limits.push((alias.into(), limits));
+        self
+    }
+
+    /// Balance pooled providers (see [`ProviderConfig::pool`]) with
+    /// `balancer`'s policies. Ignored when an existing service is used.
+    pub fn with_load_balancer(mut self, balancer: LoadBalancer) -> Self {
+        self.load_balancer = Some(balancer);
+        self
+    }
+
+    /// Pick a model for requests naming the policy's virtual model (see
+    /// [`crate::routing`]). Ignored when an existing service is used.
+    pub fn with_routing_policy(mut self, policy: crate::routing::RoutingPolicy) -> Self {
+        self.routing_policy = Some(policy);
+        self
+    }
+
+    /// Price chat usage with `table` and report it as `Cost` events (see
+    /// [`crate::pricing`]). Ignored when an existing service is used.
+    pub fn with_pricing(mut self, table: crate::pricing::PricingTable) -> Self {
+        self.pricing = Some(table);
+        self
+    }
+
+    /// Enforce spending budgets per API key (see [`crate::budget`]); needs a
+    /// pricing table to record spend. Ignored when an existing service is used.
+    pub fn with_budgets(mut self, budgets: crate::budget::BudgetTracker) -> Self {
+        self.budgets = Some(budgets);
+        self
+    }
+
+    /// Let identical chat requests in flight share one generation (see
+    /// [`crate::dedup`]). Ignored when an existing service is used.
+    pub fn with_request_deduplication(mut self, dedup: crate::dedup::RequestDeduplicator) -> Self {
+        self.dedup = Some(dedup);
+        self
+    }
+
+    /// Serve the `/admin` routes (reading and resetting budget spend,
+    /// assigning API keys to tenants, listing failed model discoveries) to
+    /// requests with `Authorization: Bearer <token>`
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    /// Validate the JSON of `json_object`/`json_schema` replies (see
+    /// [`crate::validation`]). Ignored when an existing service is used.
+    pub fn with_json_validation(mut self, mode: crate::validation::JsonValidation) -> Self {
+        self.json_validation = Some(mode);
+        self
+    }
+
+    /// Reject inline images larger than `bytes` once decoded (see
+    /// [`crate::images`]). Ignored when an existing service is used.
+    pub fn with_max_image_bytes(mut self, bytes: usize) -> Self {
+        self.max_image_bytes = Some(bytes);
+        self
+    }
+
+    /// Record every chat request with `sink`, leaving out what `redaction`
+    /// names (see [`crate::audit`]). Ignored when an existing service is used.
+    pub fn with_audit_sink(
+        mut self,
+        sink: Arc<dyn crate::audit::AuditSink>,
+        redaction: crate::audit::AuditRedaction,
+    ) -> Self {
+        self.audit = Some((sink, redaction));
+        self
+    }
+
+    /// Serve `POST /moderations` by proxying to `client`
+    pub fn with_moderation(mut self, client: ModerationClient) -> Self {
+        self.moderation = Some(Arc::new(client));
+        self
+    }
+
+    /// Keep stored Responses API responses in `store` instead of memory
+    pub fn with_conversation_store(mut self, store: Arc<dyn ConversationStore>) -> Self {
+        self.conversations = Some(store);
+        self
+    }
+
+    /// Drop Chat Completions request fields the skin doesn't model instead of
+    /// passing them on to OpenAI-compatible providers (the default)
+    pub fn without_extra_body_passthrough(mut self) -> Self {
+        self.extra_body_passthrough = false;
+        self
+    }
+
+    /// Ignore the `x-omniference-provider` and `x-omniference-trace` request
+    /// headers, which let clients pick a provider and see how requests were
+    /// routed (see [`crate::trace`]). For servers open to untrusted clients.
+    pub fn without_debug_headers(mut self) -> Self {
+        self.debug_headers = false;
+        self
+    }
+
+    /// Let clients bring their own provider key: the OpenAI skin sends the
+    /// `x-provider-api-key` request header upstream instead of the
+    /// provider's configured key (see [`ChatRequestIR::endpoint_overrides`](crate::types::ChatRequestIR::endpoint_overrides))
+    pub fn with_provider_key_header(mut self) -> Self {
+        self.provider_key_header = true;
+        self
+    }
+
+    /// Let clients pick the OpenAI organization and project billed: both
+    /// OpenAI endpoints and the passthrough routes send the
+    /// `OpenAI-Organization` and `OpenAI-Project` request headers instead of
+    /// the provider's `organization` and `project`
+    pub fn with_organization_headers(mut self) -> Self {
+        self.organization_headers = true;
+        self
+    }
+
+    /// Verify the providers before serving (see [`crate::verify`]), logging,
+    /// disabling or refusing to start on failures as `check` says
+    pub fn with_startup_check(mut self, check: crate::verify::StartupCheck) -> Self {
+        self.startup_check = check;
+        self
+    }
+
+    /// Scope requests to the tenant of their API key (see [`crate::tenant`]),
+    /// and serve `/admin/tenants` when an admin token is set
+    pub fn with_tenants(mut self, tenants: crate::tenant::TenantDirectory) -> Self {
+        self.tenants = Some(tenants);
+        self
+    }
+
+    /// Keep streamed chat completions running when the client disconnects,
+    /// buffering their chunks so the client can reconnect to
+    /// `.../chat/stream/{request_id}` (see [`crate::skins::resumable`])
+    pub fn with_resumable_streams(mut self, streams: crate::skins::resumable::ResumableStreams) -> Self {
+        self.resumable_streams = Some(streams);
+        self
+    }
+
+    /// Frames a WebSocket queues for a slow client, and whether requests
+    /// then wait for it or end with `client_too_slow` (see
+    /// [`crate::backpressure`]); resumable streams take theirs from
+    /// [`ResumableStreams::with_back_pressure`](crate::skins::resumable::ResumableStreams::with_back_pressure)
+    pub fn with_back_pressure(mut self, back_pressure: crate::backpressure::BackPressure) -> Self {
+        self.back_pressure = back_pressure;
+        self
+    }
+
+    /// What chat completions report as their `system_fingerprint`, and
+    /// chat completions and responses as their `service_tier`: the
+    /// provider's (the default), a fixed fingerprint or random ones (see
+    /// [`SystemFingerprint`](crate::skins::openai::SystemFingerprint))
+    pub fn with_system_fingerprint(mut self, fingerprint: crate::skins::openai::SystemFingerprint) -> Self {
+        self.system_fingerprint = fingerprint;
+        self
+    }
+
+    /// Run the tools registered with the service for every alias's chat
+    /// completions and stream the model's follow-up replies, with tool
+    /// progress as `tool_execution_start`/`tool_execution_end` SSE events
+    /// (see [`crate::tools`]). Requests can still opt out with
+    /// [`AUTO_TOOLS_METADATA`](crate::tools::AUTO_TOOLS_METADATA).
+    pub fn with_default_auto_tools(mut self, limits: crate::tools::ToolLoopLimits) -> Self {
+        self.auto_tools = self.auto_tools.with_default_limits(limits);
+        self
+    }
+
+    /// Run registered tools for one alias's chat completions; overrides the default limits
+    pub fn with_auto_tools(mut self, alias: impl Into<String>, limits: crate::tools::ToolLoopLimits) -> Self {
+        self.auto_tools = self.auto_tools.with_limits(alias, limits);
+        self
+    }
+
+    /// Let chat completions of the other aliases run registered tools under
+    /// `limits` when they ask to with
+    /// [`AUTO_TOOLS_METADATA`](crate::tools::AUTO_TOOLS_METADATA); without
+    /// this the flag only opts out
+    pub fn with_client_auto_tools(mut self, limits: crate::tools::ToolLoopLimits) -> Self {
+        self.auto_tools = self.auto_tools.with_client_opt_in(limits);
+        self
+    }
+
+    /// Keep-alive interval and comment of SSE responses, and heartbeats
+    /// repeating pending adapter statuses (see [`crate::skins::keepalive`])
+    pub fn with_sse_keep_alive(mut self, keep_alive: crate::skins::keepalive::SseKeepAlive) -> Self {
+        self.sse_keep_alive = keep_alive;
+        self
+    }
+
+    /// Forward requests under `route.path_prefix` to its provider as they are
+    /// (see [`crate::passthrough`])
+    pub fn with_passthrough(mut self, route: crate::passthrough::PassthroughRoute) -> Self {
+        self.passthrough.push(route);
+        self
+    }
+
+    /// Run `transformer` over the JSON bodies exchanged with the provider
+    /// named `provider` (see [`crate::types::payload`])
+    pub fn with_payload_transformer(
+        mut self,
+        provider: impl Into<String>,
+        transformer: crate::types::PayloadTransformer,
+    ) -> Self {
+        self.payload_transformers.push((provider.into(), transformer));
+        self
+    }
+
+    /// Compress responses with gzip or zstd when the client's
+    /// `Accept-Encoding` allows it. Streamed responses (SSE and audio) and
+    /// bodies under 32 bytes are sent as they are, so streams still flush
+    /// chunk by chunk.
+    pub fn with_compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// Don't install the built-in `TraceLayer` (e.g. when the embedding app traces requests)
+    pub fn without_trace(mut self) -> Self {
+        self.trace = false;
         self
     }
 
     pub fn build(self) -> OmniferenceServer {
+        let filters = self.filters;
+        let token_counter = self.token_counter;
+        let default_context_policy = self.default_context_policy;
+        let context_policies = self.context_policies;
+        let default_output_limits = self.default_output_limits;
+        let output_limits = self.output_limits;
+        let load_balancer = self.load_balancer;
+        let routing_policy = self.routing_policy;
+        let pricing = self.pricing;
+        let budgets = self.budgets;
+        let dedup = self.dedup;
+        let json_validation = self.json_validation;
+        let max_image_bytes = self.max_image_bytes;
+        let audit = self.audit;
+        let service = self.service.unwrap_or_else(|| {
+            let mut router = filters
+                .into_iter()
+                .fold(crate::router::Router::new(self.registry), |router, filter| {
+                    router.with_content_filter(filter)
+                });
+            if let Some(counter) = token_counter {
+                router = router.with_token_counter(counter);
+            }
+            if let Some(policy) = default_context_policy {
+                router = router.with_default_context_policy(policy);
+            }
+            for (alias, policy) in context_policies {
+                router = router.with_context_policy(alias, policy);
+            }
+            if let Some(limits) = default_output_limits {
+                router = router.with_default_output_limits(limits);
+            }
+            for (alias, limits) in output_limits {
+                router = router.with_output_limits(alias, limits);
+            }
+            if let Some(balancer) = load_balancer {
+                router = router.with_load_balancer(balancer);
+            }
+            if let Some(policy) = routing_policy {
+                router = router.with_routing_policy(policy);
+            }
+            if let Some(table) = pricing {
+                router = router.with_pricing(table);
+            }
+            if let Some(budgets) = budgets {
+                router = router.with_budgets(budgets);
+            }
+            if let Some(dedup) = dedup {
+                router = router.with_deduplication(dedup);
+            }
+            if let Some(mode) = json_validation {
+                router = router.with_json_validation(mode);
+            }
+            if let Some(bytes) = max_image_bytes {
+                router = router.with_max_image_bytes(bytes);
+            }
+            if let Some((sink, redaction)) = audit {
+                router = router.with_audit_sink(sink).with_audit_redaction(redaction);
+            }
+            OmniferenceService::with_router(router)
+        });
+
+        let discover_on_start = !self.providers.is_empty();
+        let mut providers = self.providers;
+        for (name, transformer) in self.payload_transformers {
+            match providers.iter_mut().find(|provider| provider.name == name) {
+                Some(provider) => provider.endpoint.payload_transformer = Some(transformer),
+                None => tracing::warn!(provider_name = %name, "Payload transformer for an unknown provider"),
+            }
+        }
+        for provider in providers {
+            let name = provider.name.clone();
+            if let Err(e) = service.register_provider_deferred(provider) {
+                tracing::error!(provider_name = %name, error = %e, "Skipping provider");
+            }
+        }
+        if !self.model_aliases.is_empty()
+            || !self.tenant_model_aliases.is_empty()
+            || self.default_provider.is_some()
+            || self.default_model.is_some()
+        {
+            match service.provider_manager().try_write() {
+                Ok(mut manager) => {
+                    for (alias, target) in self.model_aliases {
+                        manager.set_model_alias(alias, target);
+                    }
+                    for (tenant, alias, target) in self.tenant_model_aliases {
+                        manager.set_tenant_model_alias(tenant, alias, target);
+                    }
+                    if self.default_provider.is_some() {
+                        manager.set_default_provider(self.default_provider);
+                    }
+                    if self.default_model.is_some() {
+                        manager.set_default_model(self.default_model);
+                    }
+                }
+                Err(_) => tracing::error!("Provider manager is in use; skipping model aliases and defaults"),
+            }
+        }
+
+        let skins = if self.skins.is_empty() {
+            SkinKind::all().to_vec()
+        } else {
+            self.skins
+        };
+
         OmniferenceServer {
-            service: self.service,
+            service,
             app: None,
+            skins,
+            routes: self.routes,
+            layers: self.layers,
+            cors: self.cors,
+            trace: self.trace,
+            compression: self.compression,
+            discover_on_start,
+            moderation: self.moderation,
+            conversations: self
+                .conversations
+                .unwrap_or_else(|| Arc::new(InMemoryConversationStore::new())),
+            admin_token: self.admin_token,
+            extra_body_passthrough: self.extra_body_passthrough,
+            debug_headers: self.debug_headers,
+            provider_key_header: self.provider_key_header,
+            organization_headers: self.organization_headers,
+            tenants: self.tenants,
+            startup_check: self.startup_check,
+            resumable_streams: self.resumable_streams,
+            sse_keep_alive: self.sse_keep_alive,
+            back_pressure: self.back_pressure,
+            system_fingerprint: self.system_fingerprint,
+            auto_tools: self.auto_tools,
+            passthrough: self.passthrough,
         }
     }
 }
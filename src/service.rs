@@ -1,63 +1,269 @@
+use crate::balancer::PoolMember;
+use crate::error::EngineError;
+use crate::mcp::{McpClient, McpTransport};
 use crate::router::{AdapterRegistry, Router};
-use crate::types::{DiscoveredModel, ProviderConfig};
-use std::collections::HashMap;
+use crate::tenant::{qualified_name, TenantScope};
+use crate::tools::{FnToolHandler, McpToolHandler, RegisteredTool, ToolRegistry, UnknownToolPolicy};
+use crate::types::{DiscoveredModel, ModelRef, PayloadTransformer, ProviderConfig};
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
 use tracing::warn;
 
-/// High-level service that manages providers and models
+/// The state behind an app's engine, service and server: the router with its
+/// adapter registry, the provider manager resolving models, the tools, and
+/// the background requests.
+/// Clones share it, so an app embedding both an
+/// [`OmniferenceEngine`](crate::OmniferenceEngine) and an
+/// [`OmniferenceServer`](crate::server::OmniferenceServer) built from one core
+/// registers providers once and sees the same models through either.
+///
+/// ```rust,no_run
+/// # async fn example(provider: omniference::ProviderConfig) -> Result<(), omniference::EngineError> {
+/// use omniference::{server::OmniferenceServer, OmniferenceCore, OmniferenceEngine};
+///
+/// let core = OmniferenceCore::new();
+/// let mut engine = OmniferenceEngine::from_core(core.clone());
+/// let server = OmniferenceServer::from_core(core);
+/// engine.register_provider(provider).await?; // served over HTTP too
+/// # Ok(())
+/// # }
+/// ```
 #[derive(Clone)]
-pub struct OmniferenceService {
+pub struct OmniferenceCore {
     pub router: Arc<Router>,
-    provider_manager: Arc<RwLock<ProviderManager>>,
-    cancel_tokens: Arc<CancellationToken>,
+    pub provider_manager: Arc<RwLock<ProviderManager>>,
+    pub tools: Arc<RwLock<ToolRegistry>>,
+    pub cancel_tokens: Arc<CancellationToken>,
+    pub background: crate::background::BackgroundResponses,
 }
 
-impl OmniferenceService {
+impl OmniferenceCore {
+    /// A core with all built-in adapters registered
     pub fn new() -> Self {
-        let registry = Self::create_full_adapter_registry();
-        Self {
-            router: Arc::new(Router::new(registry)),
-            provider_manager: Arc::new(RwLock::new(ProviderManager::new())),
-            cancel_tokens: Arc::new(CancellationToken::new()),
-        }
+        Self::with_router(Router::new(OmniferenceService::create_full_adapter_registry()))
+    }
+
+    /// A core without any adapters; register them before adding providers
+    pub fn empty() -> Self {
+        Self::with_router(Router::new(AdapterRegistry::default()))
     }
 
     pub fn with_router(router: Router) -> Self {
         Self {
             router: Arc::new(router),
             provider_manager: Arc::new(RwLock::new(ProviderManager::new())),
+            tools: Arc::new(RwLock::new(ToolRegistry::new())),
             cancel_tokens: Arc::new(CancellationToken::new()),
+            background: crate::background::BackgroundResponses::new(),
         }
     }
+}
+
+impl Default for OmniferenceCore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// High-level service that manages providers and models, over an
+/// [`OmniferenceCore`] it shares with its clones
+#[derive(Clone)]
+pub struct OmniferenceService {
+    core: OmniferenceCore,
+}
+
+impl std::ops::Deref for OmniferenceService {
+    type Target = OmniferenceCore;
+
+    fn deref(&self) -> &OmniferenceCore {
+        &self.core
+    }
+}
+
+impl OmniferenceService {
+    /// A service with all built-in adapters registered
+    pub fn new() -> Self {
+        Self::from_core(OmniferenceCore::new())
+    }
+
+    /// A service without any adapters; register them before adding providers
+    pub fn empty() -> Self {
+        Self::from_core(OmniferenceCore::empty())
+    }
+
+    pub fn with_router(router: Router) -> Self {
+        Self::from_core(OmniferenceCore::with_router(router))
+    }
+
+    /// A service over `core`, sharing its providers and models
+    pub fn from_core(core: OmniferenceCore) -> Self {
+        Self { core }
+    }
+
+    /// The state this service shares with engines and servers over it
+    pub fn core(&self) -> &OmniferenceCore {
+        &self.core
+    }
 
     /// Create an adapter registry with all built-in adapters
-    fn create_full_adapter_registry() -> AdapterRegistry {
-        let mut registry = AdapterRegistry::default();
+    pub fn create_full_adapter_registry() -> AdapterRegistry {
+        let registry = AdapterRegistry::default();
 
         // Register all built-in adapters
+        registry.register(std::sync::Arc::new(crate::adapters::AnthropicAdapter::new()));
+        registry.register(std::sync::Arc::new(crate::adapters::CohereAdapter));
+        registry.register(std::sync::Arc::new(crate::adapters::MistralAdapter));
         registry.register(std::sync::Arc::new(crate::adapters::OllamaAdapter));
         registry.register(std::sync::Arc::new(crate::adapters::OpenAIAdapter));
         registry.register(std::sync::Arc::new(crate::adapters::OpenAIResponsesAdapter));
+        registry.register(std::sync::Arc::new(crate::adapters::TgiAdapter));
 
         registry
     }
 
-    pub async fn register_provider(&self, provider: ProviderConfig) -> Result<(), String> {
+    fn validate_provider(&self, provider: &ProviderConfig) -> Result<(), EngineError> {
+        if provider.name.trim().is_empty() {
+            return Err(EngineError::provider_registration("", "provider name must not be empty"));
+        }
+        if provider.tenant.as_deref().is_some_and(|tenant| tenant.is_empty() || tenant.contains('/')) {
+            return Err(EngineError::provider_registration(
+                provider.name.clone(),
+                "tenant must be a non-empty id without '/'".to_string(),
+            ));
+        }
+        self.router
+            .registry
+            .ensure_adapter(&provider.endpoint)
+            .map_err(|e| EngineError::provider_registration(provider.name.clone(), e.to_string()))?;
+        // Builds the provider's HTTP client, which loads its CA file
+        crate::adapters::http::client(&provider.endpoint)
+            .map_err(|e| EngineError::provider_registration(provider.name.clone(), e.to_string()))?;
+        if !self.router.registry.contains(&provider.endpoint.kind) {
+            let registered: Vec<String> = self.router.registry.kinds().iter().map(|kind| format!("{:?}", kind)).collect();
+            return Err(EngineError::provider_registration(
+                provider.name.clone(),
+                format!(
+                    "no adapter registered for provider kind {:?} (registered: {}); register one before adding the provider",
+                    provider.endpoint.kind,
+                    if registered.is_empty() { "none".to_string() } else { registered.join(", ") },
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The adapter registry shared with the router
+    pub fn adapters(&self) -> &AdapterRegistry {
+        &self.router.registry
+    }
+
+    /// Usage and cost totals of the requests priced so far
+    pub fn usage(&self) -> &crate::pricing::UsageMeter {
+        self.router.usage()
+    }
+
+    /// Per-alias output limits and how often they applied
+    pub fn output_guardrails(&self) -> &crate::guardrails::OutputGuardrails {
+        self.router.output_guardrails()
+    }
+
+    /// Add or replace the adapter for its provider kind, returning the one it replaced
+    pub fn register_adapter(&self, adapter: Arc<dyn crate::adapter::ChatAdapter>) -> Option<Arc<dyn crate::adapter::ChatAdapter>> {
+        self.router.registry.register(adapter)
+    }
+
+    /// Remove the adapter for `kind`; providers of that kind fail to route until one is registered again
+    pub fn unregister_adapter(&self, kind: &crate::types::ProviderKind) -> Option<Arc<dyn crate::adapter::ChatAdapter>> {
+        self.router.registry.unregister(kind)
+    }
+
+    pub fn adapter_kinds(&self) -> Vec<crate::types::ProviderKind> {
+        self.router.registry.kinds()
+    }
+
+    /// Register a constructor for `ProviderKind::Custom(name)` adapters, used
+    /// when a provider of that kind is added (see [`AdapterRegistry::register_factory`])
+    pub fn register_adapter_factory<F>(&self, name: impl Into<String>, factory: F) -> Option<crate::router::AdapterFactory>
+    where
+        F: Fn(&serde_json::Map<String, serde_json::Value>) -> Result<Arc<dyn crate::adapter::ChatAdapter>, crate::adapter::AdapterError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.router.registry.register_factory(name, factory)
+    }
+
+    pub async fn register_provider(&self, provider: ProviderConfig) -> Result<(), EngineError> {
+        self.validate_provider(&provider)?;
+
         let mut manager = self.provider_manager.write().await;
+        self.start_warm_up(&mut manager, &provider);
         manager.register_provider(provider.clone());
 
-        if let Err(e) = manager.discover_models(&self.router).await {
-            tracing::warn!(provider_name = %provider.name, error = %e, "Failed to discover models during provider registration");
+        match manager.discover_models(&self.router).await {
+            Ok(discovery) => discovery.log_errors(),
+            Err(e) => {
+                tracing::warn!(provider_name = %provider.name, error = %e, "Failed to discover models during provider registration")
+            }
         }
 
         Ok(())
     }
 
-    pub async fn discover_models(&self) -> Result<Vec<DiscoveredModel>, String> {
+    /// Register a provider without discovering its models, for synchronous
+    /// setup code such as `OmniferenceServerBuilder::build`. Call
+    /// `discover_models` before resolving models from it.
+    pub fn register_provider_deferred(&self, provider: ProviderConfig) -> Result<(), EngineError> {
+        self.validate_provider(&provider)?;
+
+        let mut manager = self.provider_manager.try_write().map_err(|_| {
+            EngineError::provider_registration(
+                provider.name.clone(),
+                "provider manager is in use".to_string(),
+            )
+        })?;
+        self.start_warm_up(&mut manager, &provider);
+        manager.register_provider(provider);
+        Ok(())
+    }
+
+    /// Start warming up `provider`'s connection if it asks for it and no
+    /// warm-up of it runs yet (see [`crate::warmup`])
+    fn start_warm_up(&self, manager: &mut ProviderManager, provider: &ProviderConfig) {
+        if !provider.warm_up && !provider.keep_warm {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!(provider_name = %provider.name, "Provider registered outside a Tokio runtime; not warming up its connection");
+            return;
+        };
+        let name = provider.qualified_name();
+        if manager.start_warming(&name) {
+            runtime.spawn(crate::warmup::keep_warm(name, Arc::downgrade(&self.provider_manager)));
+        }
+    }
+
+    pub async fn discover_models(&self) -> Result<Vec<DiscoveredModel>, EngineError> {
+        self.discover_models_report().await.map(|discovery| discovery.models)
+    }
+
+    /// Discover the models of all providers, along with the providers whose
+    /// discovery failed or timed out (see [`ProviderManager::discover_models`])
+    pub async fn discover_models_report(&self) -> Result<Discovery, EngineError> {
         let mut manager = self.provider_manager.write().await;
-        manager.discover_models(&self.router).await
+        let discovery = manager.discover_models(&self.router).await?;
+        discovery.log_errors();
+        Ok(discovery)
+    }
+
+    /// How long discovery waits for each provider's model list
+    pub async fn set_discovery_timeout(&self, timeout: Duration) {
+        self.provider_manager.write().await.set_discovery_timeout(timeout);
     }
 
     pub async fn get_model(&self, model_id: &str) -> Option<DiscoveredModel> {
@@ -70,22 +276,254 @@ impl OmniferenceService {
         manager.list_models().into_iter().cloned().collect()
     }
 
+    /// Discover the models of all providers, returning those `scope` sees
+    pub async fn discover_models_in(&self, scope: TenantScope<'_>) -> Result<Vec<DiscoveredModel>, EngineError> {
+        let mut manager = self.provider_manager.write().await;
+        manager.discover_models(&self.router).await?.log_errors();
+        Ok(manager.list_models_in(scope).into_iter().cloned().collect())
+    }
+
+    /// The discovered models `scope` sees (see [`crate::tenant`])
+    pub async fn list_models_in(&self, scope: TenantScope<'_>) -> Vec<DiscoveredModel> {
+        let manager = self.provider_manager.read().await;
+        manager.list_models_in(scope).into_iter().cloned().collect()
+    }
+
+    /// Resolve a model id, name or alias to a routable ModelRef (see
+    /// [`ProviderManager::try_resolve_model_ref`]). The routing policy's
+    /// virtual model resolves to a placeholder that [`chat`](Self::chat)
+    /// replaces with the model the policy picks.
+    pub async fn resolve_model(&self, model: &str) -> Result<ModelRef, EngineError> {
+        if let Some(policy) = self.router.routing_policy().filter(|policy| policy.handles(model)) {
+            return Ok(policy.placeholder_model());
+        }
+        let manager = self.provider_manager.read().await;
+        manager.try_resolve_model_ref(model).map_err(|error| error.into_engine_error(model))
+    }
+
+    /// Resolve `alias` as `target` (see [`ProviderManager::set_model_alias`])
+    pub async fn set_model_alias(&self, alias: impl Into<String>, target: impl Into<String>) -> Option<String> {
+        self.provider_manager.write().await.set_model_alias(alias, target)
+    }
+
+    /// Resolve `alias` as `target` for `tenant`'s requests (see
+    /// [`ProviderManager::set_tenant_model_alias`])
+    pub async fn set_tenant_model_alias(
+        &self,
+        tenant: impl Into<String>,
+        alias: impl Into<String>,
+        target: impl Into<String>,
+    ) -> Option<String> {
+        self.provider_manager.write().await.set_tenant_model_alias(tenant, alias, target)
+    }
+
+    /// Prefer `provider`'s model when an unprefixed name matches several providers
+    pub async fn set_default_provider(&self, provider: Option<String>) {
+        self.provider_manager.write().await.set_default_provider(provider);
+    }
+
+    /// Serve `model` when a request omits the model or asks for `default` or `auto`
+    pub async fn set_default_model(&self, model: Option<String>) {
+        self.provider_manager.write().await.set_default_model(model);
+    }
+
+    /// Hook `provider`'s payloads (see [`ProviderManager::set_payload_transformer`])
+    pub async fn set_payload_transformer(&self, provider: &str, transformer: Option<PayloadTransformer>) -> bool {
+        self.provider_manager.write().await.set_payload_transformer(provider, transformer)
+    }
+
+    /// Execute a chat request. When tools are registered, they are offered
+    /// to the model and executed by the engine (see [`crate::tools`]).
     pub async fn chat(
+        &self,
+        mut request: crate::types::ChatRequestIR,
+    ) -> Result<impl futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin, EngineError>
+    {
+        self.apply_routing_policy(&mut request).await?;
+        self.start_chat(request, self.cancel_tokens.as_ref().clone()).await
+    }
+
+    /// Execute a chat request once and fan its events out to any number of
+    /// subscribers (see [`crate::multiplex`])
+    pub async fn chat_shared(
+        &self,
+        mut request: crate::types::ChatRequestIR,
+    ) -> Result<crate::multiplex::SharedChatStream, EngineError> {
+        self.apply_routing_policy(&mut request).await?;
+        let cancel = self.cancel_tokens.child_token();
+        let stream = self.start_chat(request, cancel.clone()).await?;
+        Ok(crate::multiplex::SharedChatStream::new(stream, cancel))
+    }
+
+    /// Run a chat request in the background (see [`crate::background`]); it
+    /// can be found by its id with `self.background.get` until its retention
+    /// runs out
+    pub fn submit_background(&self, request: crate::types::ChatRequestIR) -> crate::background::ResponseHandle {
+        let service = self.clone();
+        self.background.submit(request, &self.cancel_tokens, move |mut request, cancel| async move {
+            service.apply_routing_policy(&mut request).await?;
+            service.start_chat(request, cancel).await
+        })
+    }
+
+    async fn start_chat(
         &self,
         request: crate::types::ChatRequestIR,
-    ) -> Result<impl futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin, String>
+        cancel: CancellationToken,
+    ) -> Result<futures_util::stream::BoxStream<'static, crate::stream::StreamEvent>, EngineError> {
+        let tools = self.tools.read().await.clone();
+        if tools.is_empty() {
+            let stream = self.router.route_chat(request, cancel).await?;
+            return Ok(stream.boxed());
+        }
+
+        // Resolve routing errors up front, like the plain path
+        self.router
+            .registry
+            .get(&request.model.provider.kind)
+            .ok_or_else(|| EngineError::config(format!("no adapter for {:?}", request.model.provider.kind)))?;
+        Ok(crate::tools::run_tool_loop(self.router.clone(), tools, request, cancel, UnknownToolPolicy::ReturnToCaller).boxed())
+    }
+
+    /// When `request` asks for the routing policy's virtual model, replace it
+    /// with the model the policy picks (see [`crate::routing`])
+    pub async fn apply_routing_policy(
+        &self,
+        request: &mut crate::types::ChatRequestIR,
+    ) -> Result<Option<crate::routing::RoutingDecision>, EngineError> {
+        apply_routing_policy(&self.router, &self.provider_manager, request, TenantScope::All).await
+    }
+
+    /// Generate images with the request's model
+    pub async fn generate_image(
+        &self,
+        request: crate::types::ImageRequestIR,
+    ) -> Result<crate::types::ImageResponseIR, EngineError> {
+        self.router.route_image(request).await
+    }
+
+    /// Count the prompt tokens `messages` would use on `model`
+    pub async fn count_tokens(
+        &self,
+        model: &str,
+        messages: &[crate::types::Message],
+    ) -> Result<crate::tokens::TokenCount, EngineError> {
+        let model_ref = self.resolve_model(model).await?;
+        self.router.count_tokens(&model_ref, messages).await
+    }
+
+    /// Synthesize speech with the request's model
+    pub async fn synthesize_speech(
+        &self,
+        request: crate::types::SpeechRequestIR,
+    ) -> Result<crate::types::SpeechResponseIR, EngineError> {
+        self.router.route_speech(request).await
+    }
+
+    /// Like [`chat`](Self::chat), but always drives the tool loop and treats
+    /// calls to unregistered tools as an error instead of returning them.
+    pub async fn chat_with_tools(
+        &self,
+        mut request: crate::types::ChatRequestIR,
+    ) -> Result<impl futures_util::Stream<Item = crate::stream::StreamEvent> + Send + Unpin, EngineError>
     {
-        let cancel = self.cancel_tokens.clone();
+        self.apply_routing_policy(&mut request).await?;
         self.router
-            .route_chat(request, cancel.as_ref().clone())
-            .await
-            .map_err(|e| e.to_string())
+            .registry
+            .get(&request.model.provider.kind)
+            .ok_or_else(|| EngineError::config(format!("no adapter for {:?}", request.model.provider.kind)))?;
+        let tools = self.tools.read().await.clone();
+        let cancel = self.cancel_tokens.as_ref().clone();
+        Ok(crate::tools::run_tool_loop(self.router.clone(), tools, request, cancel, UnknownToolPolicy::Error).boxed())
+    }
+
+    /// Register a local async function as a tool executed by the engine.
+    /// The handler receives the parsed arguments; its output (or error
+    /// message) is sent back to the model.
+    pub async fn register_tool<F, Fut, E>(
+        &self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        schema: serde_json::Value,
+        handler: F,
+    ) where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<serde_json::Value, E>> + Send + 'static,
+        E: std::fmt::Display + 'static,
+    {
+        self.tools.write().await.register(RegisteredTool {
+            name: name.into(),
+            description: Some(description.into()),
+            schema,
+            source: "local".to_string(),
+            handler: Arc::new(FnToolHandler::new(handler)),
+        });
+    }
+
+    /// Connect to an MCP server and register its tools for engine-side
+    /// execution. `allowed_tools` limits which tools are exposed (`None`
+    /// exposes all). Returns the names of the registered tools.
+    pub async fn register_mcp_server(
+        &self,
+        label: &str,
+        transport: McpTransport,
+        allowed_tools: Option<Vec<String>>,
+    ) -> Result<Vec<String>, EngineError> {
+        let mcp_error = |source| EngineError::Mcp {
+            server: label.to_string(),
+            source,
+        };
+        let client = Arc::new(McpClient::connect(transport).await.map_err(mcp_error)?);
+        let advertised = client.list_tools().await.map_err(mcp_error)?;
+
+        let mut registry = self.tools.write().await;
+        let mut registered = Vec::new();
+        for tool in advertised {
+            if allowed_tools.as_ref().is_some_and(|allowed| !allowed.contains(&tool.name)) {
+                continue;
+            }
+            if let Some(existing) = registry.get(&tool.name) {
+                if existing.source != label {
+                    warn!(tool = %tool.name, previous = %existing.source, server = %label, "Replacing tool registered by another source");
+                }
+            }
+            registered.push(tool.name.clone());
+            registry.register(RegisteredTool {
+                handler: Arc::new(McpToolHandler::new(client.clone(), tool.name.clone())),
+                name: tool.name,
+                description: tool.description,
+                schema: tool.input_schema,
+                source: label.to_string(),
+            });
+        }
+        Ok(registered)
+    }
+
+    /// Tools executed by the engine during chat
+    pub fn tools(&self) -> &Arc<RwLock<ToolRegistry>> {
+        &self.tools
     }
 
     pub fn create_cancellation_token(&self) -> CancellationToken {
         CancellationToken::new()
     }
 
+    /// Check every enabled provider end to end (see [`crate::verify`]),
+    /// concurrently
+    pub async fn verify_providers(&self) -> crate::verify::VerificationReport {
+        let mut providers: Vec<(String, ProviderConfig)> = {
+            let manager = self.provider_manager.read().await;
+            manager.providers.iter().filter(|(_, config)| config.enabled).map(|(name, config)| (name.clone(), config.clone())).collect()
+        };
+        providers.sort_by(|a, b| a.0.cmp(&b.0));
+        let checks = providers
+            .iter()
+            .map(|(name, config)| crate::verify::check_provider(&self.router, name.clone(), config));
+        crate::verify::VerificationReport {
+            providers: futures_util::future::join_all(checks).await,
+        }
+    }
+
     /// Get the provider manager for HTTP context sharing
     pub fn provider_manager(&self) -> &Arc<RwLock<ProviderManager>> {
         &self.provider_manager
@@ -98,10 +536,100 @@ impl Default for OmniferenceService {
     }
 }
 
-/// Manages provider configurations and discovered models
+/// Manages provider configurations and discovered models. Providers,
+/// pools and models of tenants are kept under their qualified names (see
+/// [`crate::tenant`]).
 pub struct ProviderManager {
     providers: HashMap<String, ProviderConfig>,
     discovered_models: HashMap<String, DiscoveredModel>,
+    /// `{pool}/{model}` aliases registered for pooled providers
+    pool_aliases: HashSet<String>,
+    /// User-defined model names and the model they resolve to
+    model_aliases: HashMap<String, String>,
+    /// Model aliases only a tenant's requests resolve, by tenant
+    tenant_aliases: HashMap<String, HashMap<String, String>>,
+    default_provider: Option<String>,
+    default_model: Option<String>,
+    /// Providers with a running warm-up task (see [`crate::warmup`])
+    warming: HashSet<String>,
+    /// Longest wait for one provider's model list
+    discovery_timeout: Duration,
+    /// Providers whose last discovery failed
+    discovery_errors: Vec<DiscoveryError>,
+}
+
+/// Longest wait for one provider's model list, unless
+/// [`ProviderManager::set_discovery_timeout`] says otherwise
+pub const DEFAULT_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A provider whose models couldn't be discovered
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DiscoveryError {
+    /// The provider's qualified name
+    pub provider: String,
+    pub message: String,
+}
+
+/// The outcome of [`ProviderManager::discover_models`]: the models of the
+/// providers that answered, and an error for each that didn't
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Discovery {
+    pub models: Vec<DiscoveredModel>,
+    pub errors: Vec<DiscoveryError>,
+}
+
+impl Discovery {
+    /// Warn about each provider whose discovery failed
+    pub fn log_errors(&self) {
+        for error in &self.errors {
+            warn!(provider = %error.provider, error = %error.message, "Failed to discover models for provider");
+        }
+    }
+}
+
+/// Route `request` with the router's policy, resolving the chosen target and
+/// recording it as `routing_rule` and `routed_model` metadata
+pub(crate) async fn apply_routing_policy(
+    router: &Router,
+    provider_manager: &RwLock<ProviderManager>,
+    request: &mut crate::types::ChatRequestIR,
+    scope: TenantScope<'_>,
+) -> Result<Option<crate::routing::RoutingDecision>, EngineError> {
+    let Some(decision) = router.choose_route(request).await? else {
+        return Ok(None);
+    };
+    let resolved = provider_manager.read().await.try_resolve_model_ref_in(&decision.target, scope);
+    request.model = resolved.map_err(|error| error.into_engine_error(&decision.target))?;
+    request.metadata.insert("routing_rule".to_string(), decision.rule.clone());
+    request.metadata.insert("routed_model".to_string(), request.model.alias.clone());
+    Ok(Some(decision))
+}
+
+/// Whether `model` asks the server to pick: empty, `default` or `auto`
+pub fn is_default_model_name(model: &str) -> bool {
+    let model = model.trim();
+    model.is_empty() || model.eq_ignore_ascii_case("default") || model.eq_ignore_ascii_case("auto")
+}
+
+/// Why a model name could not be resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelResolutionError {
+    NotFound,
+    /// The name matches models of several providers; `candidates` are their IDs, sorted
+    Ambiguous { candidates: Vec<String> },
+}
+
+impl ModelResolutionError {
+    /// The engine error for failing to resolve `model`
+    pub fn into_engine_error(self, model: &str) -> EngineError {
+        match self {
+            ModelResolutionError::NotFound => EngineError::ModelNotFound(model.to_string()),
+            ModelResolutionError::Ambiguous { candidates } => EngineError::AmbiguousModel {
+                model: model.to_string(),
+                candidates,
+            },
+        }
+    }
 }
 
 impl Default for ProviderManager {
@@ -115,50 +643,141 @@ impl ProviderManager {
         Self {
             providers: HashMap::new(),
             discovered_models: HashMap::new(),
+            pool_aliases: HashSet::new(),
+            model_aliases: HashMap::new(),
+            tenant_aliases: HashMap::new(),
+            default_provider: None,
+            default_model: None,
+            warming: HashSet::new(),
+            discovery_timeout: DEFAULT_DISCOVERY_TIMEOUT,
+            discovery_errors: Vec::new(),
         }
     }
 
+    /// Register `provider` under its [qualified name](ProviderConfig::qualified_name)
     pub fn register_provider(&mut self, provider: ProviderConfig) {
-        self.providers.insert(provider.name.clone(), provider);
+        self.providers.insert(provider.qualified_name(), provider);
+    }
+
+    /// How long [`discover_models`](Self::discover_models) waits for each
+    /// provider's model list
+    pub fn set_discovery_timeout(&mut self, timeout: Duration) {
+        self.discovery_timeout = timeout;
+    }
+
+    /// Providers whose model lists the last discovery didn't get
+    pub fn discovery_errors(&self) -> &[DiscoveryError] {
+        &self.discovery_errors
     }
 
+    /// Ask every enabled provider for its models, all at once and each
+    /// within the discovery timeout. A provider that fails or times out
+    /// keeps the models it was discovered with before and is listed in
+    /// [`Discovery::errors`]; the others' models are returned.
     pub async fn discover_models(
         &mut self,
         router: &Router,
-    ) -> Result<Vec<DiscoveredModel>, String> {
+    ) -> Result<Discovery, EngineError> {
         let mut all_models = Vec::new();
+        let mut errors = Vec::new();
+        let mut pools: BTreeMap<String, (String, Vec<(PoolMember, DiscoveredModel)>)> = BTreeMap::new();
+        let discovered_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
 
-        for (name, provider_config) in &self.providers {
-            if !provider_config.enabled {
-                continue;
-            }
+        let timeout = self.discovery_timeout;
+        let mut lists = futures_util::future::join_all(
+            self.providers
+                .iter()
+                .filter(|(_, provider)| provider.enabled)
+                .map(|(name, provider)| async move {
+                    let Some(adapter) = router.registry.get(&provider.endpoint.kind) else {
+                        return (name, Err(format!("no adapter is registered for {:?}", provider.endpoint.kind)));
+                    };
+                    let models = match tokio::time::timeout(timeout, adapter.discover_models(&provider.endpoint)).await {
+                        Ok(models) => models.map_err(|e| e.to_string()),
+                        Err(_) => Err(format!("no model list within {} ms", timeout.as_millis())),
+                    };
+                    (name, models)
+                }),
+        )
+        .await;
+        lists.sort_by(|a, b| a.0.cmp(b.0));
 
-            if let Some(adapter) = router.registry.get(&provider_config.endpoint.kind) {
-                match adapter.discover_models(&provider_config.endpoint).await {
-                    Ok(models) => {
-                        for model in models {
-                            // Normalize to use configured provider name as prefix and provider_name
-                            let normalized = DiscoveredModel {
-                                id: format!("{}/{}", name, model.name),
-                                name: model.name.clone(),
-                                provider_name: name.clone(),
-                                provider_kind: model.provider_kind.clone(),
-                                modalities: model.modalities.clone(),
-                                capabilities: model.capabilities.clone(),
+        for (name, models) in lists {
+            let provider_config = &self.providers[name];
+            match models {
+                Ok(models) => {
+                    for model in models {
+                        // Normalize to use configured provider name as prefix and provider_name
+                        let id = format!("{}/{}", name, model.name);
+                        // Models without a date of their own keep the one of their first discovery
+                        let created = model
+                            .created
+                            .or_else(|| self.discovered_models.get(&id).and_then(|known| known.created))
+                            .unwrap_or(discovered_at);
+                        let mut normalized = DiscoveredModel {
+                            id,
+                            name: model.name.clone(),
+                            provider_name: name.clone(),
+                            provider_kind: model.provider_kind.clone(),
+                            modalities: model.modalities.clone(),
+                            capabilities: model.capabilities.clone(),
+                            created: Some(created),
+                        };
+                        // Configured overrides beat advertised and inferred capabilities
+                        if let Some(overrides) = provider_config.model_overrides.get(&model.name) {
+                            overrides.apply(&mut normalized);
+                        }
+                        if let Some(pool) = &provider_config.pool {
+                            let pool = qualified_name(provider_config.tenant.as_deref(), pool);
+                            let member = PoolMember {
+                                provider: name.clone(),
+                                endpoint: provider_config.endpoint.clone(),
+                                weight: provider_config.weight.unwrap_or(1),
                             };
-                            self.discovered_models
-                                .insert(normalized.id.clone(), normalized.clone());
-                            all_models.push(normalized);
+                            pools
+                                .entry(format!("{}/{}", pool, model.name))
+                                .or_insert_with(|| (pool.clone(), Vec::new()))
+                                .1
+                                .push((member, normalized.clone()));
                         }
-                    }
-                    Err(e) => {
-                        warn!(%name, error = %e, "Failed to discover models for provider");
+                        self.discovered_models
+                            .insert(normalized.id.clone(), normalized.clone());
+                        all_models.push(normalized);
                     }
                 }
+                Err(message) => errors.push(DiscoveryError {
+                    provider: name.clone(),
+                    message,
+                }),
             }
         }
 
-        Ok(all_models)
+        // Each pool alias resolves through its first member; the router picks
+        // the actual endpoint per request
+        let mut balanced = BTreeMap::new();
+        for (alias, (pool, mut members)) in pools {
+            members.sort_by(|a, b| a.0.provider.cmp(&b.0.provider));
+            let mut model = members[0].1.clone();
+            model.id = alias.clone();
+            self.discovered_models.insert(alias.clone(), model.clone());
+            self.pool_aliases.insert(alias.clone());
+            all_models.push(model);
+            balanced.insert(alias, (pool, members.into_iter().map(|(member, _)| member).collect()));
+        }
+        router.load_balancer().set_pools(balanced);
+        router.concurrency_limiter().set_limits(self.providers.iter().filter_map(|(name, config)| {
+            crate::limiter::ConcurrencyLimit::from_config(config).map(|limit| (name.clone(), limit))
+        }));
+
+        router.context_manager().record_models(&all_models);
+        self.discovery_errors = errors.clone();
+        Ok(Discovery {
+            models: all_models,
+            errors,
+        })
     }
 
     pub fn get_model(&self, model_id: &str) -> Option<&DiscoveredModel> {
@@ -169,11 +788,356 @@ impl ProviderManager {
         self.discovered_models.values().collect()
     }
 
+    /// The discovered models (including pool aliases) `scope` sees
+    pub fn list_models_in(&self, scope: TenantScope) -> Vec<&DiscoveredModel> {
+        self.discovered_models
+            .values()
+            .filter(|model| scope.sees(self.model_tenant(model)))
+            .collect()
+    }
+
+    /// The tenant owning `model`, `None` for shared models
+    pub fn model_tenant(&self, model: &DiscoveredModel) -> Option<&str> {
+        self.providers.get(&model.provider_name).and_then(|provider| provider.tenant.as_deref())
+    }
+
+    /// The discovered model `model_id` if `scope` sees it, trying the ID
+    /// under the scope's tenant first
+    fn get_model_in(&self, model_id: &str, scope: TenantScope) -> Option<&DiscoveredModel> {
+        scope
+            .tenant()
+            .and_then(|tenant| self.get_model(&qualified_name(Some(tenant), model_id)))
+            .or_else(|| self.get_model(model_id))
+            .filter(|model| scope.sees(self.model_tenant(model)))
+    }
+
+    /// The target of the alias `alias` for `scope`: the tenant's own aliases win
+    fn model_alias_in(&self, alias: &str, scope: TenantScope) -> Option<&String> {
+        scope
+            .tenant()
+            .and_then(|tenant| self.tenant_aliases.get(tenant)?.get(alias))
+            .or_else(|| self.model_aliases.get(alias))
+    }
+
+    /// IDs of every discovered model (including pool aliases), sorted
+    pub fn known_model_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.discovered_models.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Discovered model IDs closest to an unresolvable `model`, best first
+    /// and at most `limit`. IDs extending `model` always qualify; others must
+    /// be within a small edit distance of the ID or of the bare model name.
+    pub fn suggest_models(&self, model: &str, limit: usize) -> Vec<String> {
+        self.suggest_models_in(model, limit, TenantScope::All)
+    }
+
+    /// [`suggest_models`](Self::suggest_models) among the models `scope` sees
+    pub fn suggest_models_in(&self, model: &str, limit: usize, scope: TenantScope) -> Vec<String> {
+        let query = model.to_lowercase();
+        let query_name = query.rsplit_once('/').map_or(query.as_str(), |(_, name)| name);
+        let threshold = (query.chars().count() / 3).max(2);
+
+        let mut scored: Vec<(usize, &String)> = self
+            .list_models_in(scope)
+            .into_iter()
+            .map(|discovered| (&discovered.id, discovered))
+            .filter_map(|(id, discovered)| {
+                let id_lower = id.to_lowercase();
+                let name_lower = discovered.name.to_lowercase();
+                let distance = edit_distance(&query, &id_lower).min(edit_distance(query_name, &name_lower));
+                let extends = !query_name.is_empty() && (id_lower.starts_with(&query) || name_lower.starts_with(query_name));
+                (distance <= threshold || extends).then_some((distance, id))
+            })
+            .collect();
+        scored.sort();
+        scored.into_iter().take(limit).map(|(_, id)| id.clone()).collect()
+    }
+
+    /// Resolve a model identifier or name to a concrete ModelRef, or `None`
+    /// when it is unknown or ambiguous (see [`try_resolve_model_ref`](Self::try_resolve_model_ref))
+    pub fn resolve_model_ref(&self, model: &str) -> Option<ModelRef> {
+        self.try_resolve_model_ref(model).ok()
+    }
+
+    /// Resolve a model identifier or name to a concrete ModelRef, trying in order:
+    /// - the exact discovered ID (e.g., "openrouter/gpt-5-nano")
+    /// - the alias table (see [`set_model_alias`](Self::set_model_alias)) and
+    ///   legacy kind prefixes (e.g., "openai-compat/gpt-5-nano")
+    /// - the discovered ID, ignoring case (e.g., "OpenRouter/GPT-5-nano")
+    /// - the model name without any provider prefix, ignoring case (e.g.,
+    ///   "gpt-5-nano" or "openai/gpt-5-nano"). Load-balanced pool aliases win,
+    ///   then the default provider for unprefixed names; other matches across
+    ///   several providers are ambiguous.
+    ///
+    /// OpenRouter variant suffixes (e.g., "openrouter/gpt-4o:online") are kept
+    /// on the outbound model id. With a default model configured, missing and
+    /// wildcard names (see [`is_default_model_name`]) resolve to it instead.
+    pub fn try_resolve_model_ref(&self, model: &str) -> Result<ModelRef, ModelResolutionError> {
+        self.try_resolve_model_ref_in(model, TenantScope::All)
+    }
+
+    /// Resolve `model` like [`try_resolve_model_ref`](Self::try_resolve_model_ref),
+    /// among the models `scope` sees. Other tenants' models are not found;
+    /// a tenant's own models win over shared ones of the same name, and its
+    /// own aliases over global ones.
+    pub fn try_resolve_model_ref_in(&self, model: &str, scope: TenantScope) -> Result<ModelRef, ModelResolutionError> {
+        match self.default_model.as_deref() {
+            Some(default) if is_default_model_name(model) => self.resolve_named_model_ref(default, scope),
+            _ => self.resolve_named_model_ref(model, scope),
+        }
+    }
+
+    /// Resolve `model` like [`try_resolve_model_ref_in`](Self::try_resolve_model_ref_in),
+    /// but only to the model of that name `provider` (a provider or pool
+    /// name, of the scope's tenant or shared) serves. Names matching models
+    /// of several providers are fine here; models `provider` doesn't serve
+    /// are not found.
+    pub fn try_resolve_model_ref_on(
+        &self,
+        model: &str,
+        provider: &str,
+        scope: TenantScope,
+    ) -> Result<ModelRef, ModelResolutionError> {
+        let owned = scope.tenant().map(|tenant| format!("{}/", qualified_name(Some(tenant), provider)));
+        let prefix = match owned {
+            Some(owned) if self.discovered_models.keys().any(|id| id.starts_with(&owned)) => owned,
+            _ => format!("{}/", provider),
+        };
+        let target = match self.try_resolve_model_ref_in(model, scope) {
+            Ok(model_ref) if model_ref.alias.starts_with(&prefix) => return Ok(model_ref),
+            Ok(model_ref) => format!("{}{}", prefix, model_ref.model_id),
+            Err(ModelResolutionError::Ambiguous { candidates }) => {
+                let id = candidates
+                    .into_iter()
+                    .find(|id| id.starts_with(&prefix))
+                    .ok_or(ModelResolutionError::NotFound)?;
+                match crate::types::providers::openrouter::split_model_suffix(model) {
+                    (_, Some(suffix)) => format!("{}:{}", id, suffix),
+                    (_, None) => id,
+                }
+            }
+            Err(error) => return Err(error),
+        };
+        // Bare-name matching may find the model at another provider
+        self.resolve_named_model_ref(&target, scope)
+            .ok()
+            .filter(|model_ref| model_ref.alias.starts_with(&prefix))
+            .ok_or(ModelResolutionError::NotFound)
+    }
+
+    fn resolve_named_model_ref(&self, model: &str, scope: TenantScope) -> Result<ModelRef, ModelResolutionError> {
+        let error = match self.resolve_discovered_model_ref(model, scope) {
+            Ok(model_ref) => return Ok(model_ref),
+            Err(error) => error,
+        };
+
+        let (base, suffix) = crate::types::providers::openrouter::split_model_suffix(model);
+        let Some(suffix) = suffix else {
+            return Err(error);
+        };
+        let mut model_ref = self.resolve_discovered_model_ref(base, scope)?;
+        model_ref.alias = format!("{}:{}", model_ref.alias, suffix);
+        model_ref.model_id = format!("{}:{}", model_ref.model_id, suffix);
+        Ok(model_ref)
+    }
+
+    fn resolve_discovered_model_ref(&self, model: &str, scope: TenantScope) -> Result<ModelRef, ModelResolutionError> {
+        let discovered = match (self.get_model_in(model, scope), self.model_alias_in(model, scope)) {
+            (Some(discovered), _) => discovered,
+            (None, Some(target)) => self.find_discovered_model(target, scope)?,
+            (None, None) => self.find_discovered_model(model, scope)?,
+        };
+
+        // Find provider endpoint: prefer exact provider name match if available,
+        // falling back to the first provider of the same kind
+        let provider = self
+            .get_provider(&discovered.provider_name)
+            .or_else(|| {
+                self.providers
+                    .values()
+                    .find(|p| p.endpoint.kind == discovered.provider_kind && scope.sees(p.tenant.as_deref()))
+            })
+            .ok_or(ModelResolutionError::NotFound)?;
+
+        Ok(ModelRef {
+            alias: discovered.id.clone(),
+            provider: provider.endpoint.clone(),
+            model_id: discovered.name.clone(),
+            modalities: discovered.modalities.clone(),
+        })
+    }
+
+    fn find_discovered_model(&self, model: &str, scope: TenantScope) -> Result<&DiscoveredModel, ModelResolutionError> {
+        if let Some(discovered) = self.get_model_in(model, scope) {
+            return Ok(discovered);
+        }
+        let visible = self.list_models_in(scope);
+
+        // Legacy kind-prefixed IDs
+        let (prefix, name) = match model.split_once('/') {
+            Some((prefix, name)) => (Some(prefix), name),
+            None => (None, model),
+        };
+        let kind_hint = match prefix {
+            Some("openai-compat") => Some(crate::types::ProviderKind::OpenAICompat),
+            Some("openai") => Some(crate::types::ProviderKind::OpenAI),
+            Some("ollama") => Some(crate::types::ProviderKind::Ollama),
+            Some("lmstudio") => Some(crate::types::ProviderKind::LMStudio),
+            _ => None,
+        };
+        if let Some(kind) = kind_hint {
+            let mut by_kind: Vec<&DiscoveredModel> = visible
+                .iter()
+                .copied()
+                .filter(|m| m.name == name && m.provider_kind == kind)
+                .collect();
+            by_kind.sort_by(|a, b| a.id.cmp(&b.id));
+            if let Some(discovered) = by_kind.first() {
+                return Ok(discovered);
+            }
+        }
+
+        let owned_id = scope.tenant().map(|tenant| qualified_name(Some(tenant), model));
+        for id in owned_id.as_deref().into_iter().chain([model]) {
+            let mut by_id: Vec<&DiscoveredModel> = visible.iter().copied().filter(|m| m.id.eq_ignore_ascii_case(id)).collect();
+            if by_id.len() == 1 {
+                return Ok(by_id.remove(0));
+            }
+        }
+
+        // Bare names, or a prefix this gateway doesn't use
+        let mut candidates: Vec<&DiscoveredModel> = visible
+            .iter()
+            .copied()
+            .filter(|m| m.name.eq_ignore_ascii_case(name))
+            .collect();
+        candidates.sort_by(|a, b| a.id.cmp(&b.id));
+        // A tenant's own models win over shared ones
+        if let Some(tenant) = scope.tenant() {
+            if candidates.iter().any(|m| self.model_tenant(m) == Some(tenant)) {
+                candidates.retain(|m| self.model_tenant(m) == Some(tenant));
+            }
+        }
+        let pools: Vec<&DiscoveredModel> = candidates
+            .iter()
+            .copied()
+            .filter(|m| self.pool_aliases.contains(&m.id))
+            .collect();
+        let default_provider = self.default_provider.as_deref().filter(|_| prefix.is_none());
+        let defaults: Vec<&DiscoveredModel> = candidates
+            .iter()
+            .copied()
+            .filter(|m| default_provider == Some(m.provider_name.as_str()) && !self.pool_aliases.contains(&m.id))
+            .collect();
+        match (candidates.as_slice(), pools.as_slice(), defaults.as_slice()) {
+            ([], _, _) => Err(ModelResolutionError::NotFound),
+            ([only], _, _) | (_, [only], _) | (_, [], [only]) => Ok(only),
+            _ => Err(ModelResolutionError::Ambiguous {
+                candidates: candidates.iter().map(|m| m.id.clone()).collect(),
+            }),
+        }
+    }
+
+    /// Resolve `alias` as `target`, an exact model ID or any form
+    /// [`try_resolve_model_ref`](Self::try_resolve_model_ref) accepts.
+    /// Returns the target it replaced.
+    pub fn set_model_alias(&mut self, alias: impl Into<String>, target: impl Into<String>) -> Option<String> {
+        self.model_aliases.insert(alias.into(), target.into())
+    }
+
+    pub fn remove_model_alias(&mut self, alias: &str) -> Option<String> {
+        self.model_aliases.remove(alias)
+    }
+
+    /// Resolve `alias` as `target` for `tenant`'s requests only, before
+    /// global aliases. Returns the target it replaced.
+    pub fn set_tenant_model_alias(
+        &mut self,
+        tenant: impl Into<String>,
+        alias: impl Into<String>,
+        target: impl Into<String>,
+    ) -> Option<String> {
+        self.tenant_aliases.entry(tenant.into()).or_default().insert(alias.into(), target.into())
+    }
+
+    pub fn remove_tenant_model_alias(&mut self, tenant: &str, alias: &str) -> Option<String> {
+        self.tenant_aliases.get_mut(tenant)?.remove(alias)
+    }
+
+    /// Provider whose model wins when an unprefixed name matches several providers
+    pub fn set_default_provider(&mut self, provider: Option<String>) {
+        self.default_provider = provider;
+    }
+
+    pub fn default_provider(&self) -> Option<&str> {
+        self.default_provider.as_deref()
+    }
+
+    /// Model served when a request omits the model or asks for `default` or
+    /// `auto`; any name [`try_resolve_model_ref`](Self::try_resolve_model_ref) accepts
+    pub fn set_default_model(&mut self, model: Option<String>) {
+        self.default_model = model;
+    }
+
+    pub fn default_model(&self) -> Option<&str> {
+        self.default_model.as_deref()
+    }
+
     pub fn get_provider(&self, name: &str) -> Option<&ProviderConfig> {
         self.providers.get(name)
     }
 
+    /// Run `transformer` over the JSON bodies of `provider`'s requests and
+    /// responses, or stop with `None`. Returns whether the provider exists.
+    /// Load-balanced pools pick it up at the next model discovery.
+    pub fn set_payload_transformer(&mut self, provider: &str, transformer: Option<PayloadTransformer>) -> bool {
+        match self.providers.get_mut(provider) {
+            Some(config) => {
+                config.endpoint.payload_transformer = transformer;
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn list_providers(&self) -> Vec<&ProviderConfig> {
         self.providers.values().collect()
     }
+
+    /// Stop routing to `provider`, dropping its discovered models. Returns
+    /// whether the provider exists.
+    pub fn disable_provider(&mut self, provider: &str) -> bool {
+        let Some(config) = self.providers.get_mut(provider) else {
+            return false;
+        };
+        config.enabled = false;
+        self.discovered_models.retain(|_, model| model.provider_name != provider);
+        true
+    }
+
+    /// Mark `provider`'s warm-up task as running; false if it already was
+    pub(crate) fn start_warming(&mut self, provider: &str) -> bool {
+        self.warming.insert(provider.to_string())
+    }
+
+    pub(crate) fn stop_warming(&mut self, provider: &str) {
+        self.warming.remove(provider);
+    }
+}
+
+/// Levenshtein distance between two strings, by character
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
 }
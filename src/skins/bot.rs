@@ -0,0 +1,370 @@
+//! Helpers shared by the chat-bot interfaces (Discord, Telegram)
+
+use crate::engine::OmniferenceEngine;
+use crate::error::EngineError;
+use crate::skins::settings::{ChatSettings, InMemorySettingsStore, SettingsStore};
+use crate::store::ConversationStore;
+use crate::stream::StreamEvent;
+use crate::types::{ChatRequestIR, ContentPart, Message, Role};
+use futures_util::{Stream, StreamExt};
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Split `text` into pieces of at most `limit` characters.
+///
+/// Splits prefer line boundaries; lines longer than `limit` are broken at
+/// whitespace where possible and hard-split otherwise. Never splits inside a
+/// UTF-8 character.
+pub fn chunk_message(text: &str, limit: usize) -> Vec<String> {
+    assert!(limit > 0, "chunk limit must be positive");
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+
+    for line in text.split('\n') {
+        let line_len = line.chars().count();
+        let sep = usize::from(!current.is_empty());
+        if current_len + sep + line_len <= limit {
+            if sep == 1 {
+                current.push('\n');
+            }
+            current.push_str(line);
+            current_len += sep + line_len;
+            continue;
+        }
+
+        if !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+
+        let mut rest = line;
+        while rest.chars().count() > limit {
+            let (head, tail) = split_long_line(rest, limit);
+            chunks.push(head.to_string());
+            rest = tail;
+        }
+        current.push_str(rest);
+        current_len = rest.chars().count();
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Split a line that exceeds `limit` characters, preferring the last space
+fn split_long_line(line: &str, limit: usize) -> (&str, &str) {
+    let hard = line
+        .char_indices()
+        .nth(limit)
+        .map(|(i, _)| i)
+        .unwrap_or(line.len());
+    if line[hard..].starts_with(' ') {
+        return (&line[..hard], &line[hard + 1..]);
+    }
+    match line[..hard].rfind(' ') {
+        Some(space) if space > 0 => (&line[..space], &line[space + 1..]),
+        _ => line.split_at(hard),
+    }
+}
+
+/// Rolling per-conversation message history with a fixed window.
+///
+/// Keys are interface-specific conversation ids (e.g. a Discord channel id).
+#[derive(Debug, Clone)]
+pub struct ConversationHistory {
+    window: usize,
+    conversations: HashMap<u64, VecDeque<Message>>,
+}
+
+impl ConversationHistory {
+    /// Keep at most `window` messages per conversation
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            conversations: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, conversation: u64, role: Role, text: impl Into<String>) {
+        self.push_message(
+            conversation,
+            Message {
+                role,
+                parts: vec![ContentPart::Text(text.into())],
+                name: None,
+            },
+        );
+    }
+
+    pub fn push_message(&mut self, conversation: u64, message: Message) {
+        if self.window == 0 {
+            return;
+        }
+        let messages = self.conversations.entry(conversation).or_default();
+        messages.push_back(message);
+        while messages.len() > self.window {
+            messages.pop_front();
+        }
+    }
+
+    /// Messages for a conversation, oldest first
+    pub fn messages(&self, conversation: u64) -> Vec<Message> {
+        self.conversations
+            .get(&conversation)
+            .map(|m| m.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&mut self, conversation: u64) {
+        self.conversations.remove(&conversation);
+    }
+}
+
+/// Progress of a streamed bot reply
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReplyUpdate {
+    /// Text so far; emitted every `edit_every` deltas
+    Partial(String),
+    /// The complete reply
+    Final(String),
+    /// The request failed; carries any partial text and the error message
+    Failed { partial: String, message: String },
+}
+
+/// Fold a chat stream into accumulated-text updates suitable for editing a
+/// chat message in place.
+pub fn reply_updates<S>(mut events: S, edit_every: usize) -> impl Stream<Item = ReplyUpdate> + Send
+where
+    S: Stream<Item = StreamEvent> + Send + Unpin,
+{
+    let edit_every = edit_every.max(1);
+    async_stream::stream! {
+        let mut content = String::new();
+        let mut deltas = 0usize;
+        while let Some(event) = events.next().await {
+            match event {
+                StreamEvent::TextDelta { content: chunk } => {
+                    content.push_str(&chunk);
+                    deltas += 1;
+                    if deltas.is_multiple_of(edit_every) {
+                        yield ReplyUpdate::Partial(content.clone());
+                    }
+                }
+                StreamEvent::FinalMessage { content: final_content, .. } if content.is_empty() => {
+                    content = final_content;
+                }
+                StreamEvent::Error { message, .. } => {
+                    yield ReplyUpdate::Failed { partial: content, message };
+                    return;
+                }
+                StreamEvent::Done => break,
+                _ => {}
+            }
+        }
+        yield ReplyUpdate::Final(content);
+    }
+}
+
+/// Where a [`ChatSession`] keeps its history
+#[derive(Clone)]
+enum HistoryBackend {
+    Memory(Arc<Mutex<ConversationHistory>>),
+    /// Full history in a store under `{namespace}:{conversation}`; only the
+    /// last `window` messages are sent
+    Store {
+        store: Arc<dyn ConversationStore>,
+        namespace: String,
+        window: usize,
+    },
+}
+
+impl HistoryBackend {
+    async fn record(&self, conversation: u64, message: Message) {
+        match self {
+            HistoryBackend::Memory(history) => history.lock().await.push_message(conversation, message),
+            HistoryBackend::Store { store, namespace, window } => {
+                if *window == 0 {
+                    return;
+                }
+                let id = format!("{}:{}", namespace, conversation);
+                if let Err(e) = store.append_message(&id, message).await {
+                    tracing::warn!(conversation = %id, error = %e, "Failed to store message");
+                }
+            }
+        }
+    }
+
+    /// The conversation's recent messages, oldest first
+    async fn recent(&self, conversation: u64) -> Vec<Message> {
+        match self {
+            HistoryBackend::Memory(history) => history.lock().await.messages(conversation),
+            HistoryBackend::Store { store, namespace, window } => {
+                let id = format!("{}:{}", namespace, conversation);
+                match store.get(&id).await {
+                    Ok(Some(stored)) => {
+                        let skip = stored.messages.len().saturating_sub(*window);
+                        stored.messages.into_iter().skip(skip).collect()
+                    }
+                    Ok(None) => Vec::new(),
+                    Err(e) => {
+                        tracing::warn!(conversation = %id, error = %e, "Failed to load history");
+                        Vec::new()
+                    }
+                }
+            }
+        }
+    }
+
+    async fn clear(&self, conversation: u64) {
+        match self {
+            HistoryBackend::Memory(history) => history.lock().await.clear(conversation),
+            HistoryBackend::Store { store, namespace, .. } => {
+                let id = format!("{}:{}", namespace, conversation);
+                if let Err(e) = store.delete(&id).await {
+                    tracing::warn!(conversation = %id, error = %e, "Failed to clear history");
+                }
+            }
+        }
+    }
+}
+
+/// Conversation state shared by the bot interfaces: rolling history per
+/// conversation, layered settings, and model resolution.
+pub struct ChatSession {
+    engine: Arc<OmniferenceEngine>,
+    history: HistoryBackend,
+    history_window: usize,
+    history_tokens: Option<usize>,
+    settings: Arc<dyn SettingsStore>,
+    defaults: ChatSettings,
+}
+
+impl ChatSession {
+    /// `defaults` apply when neither guild nor conversation settings are set
+    pub fn new(engine: impl Into<Arc<OmniferenceEngine>>, history_window: usize, defaults: ChatSettings) -> Self {
+        Self {
+            engine: engine.into(),
+            history: HistoryBackend::Memory(Arc::new(Mutex::new(ConversationHistory::new(history_window)))),
+            history_window,
+            history_tokens: None,
+            settings: Arc::new(InMemorySettingsStore::new()),
+            defaults,
+        }
+    }
+
+    /// Drop the oldest turns until the prompt (system prompt included) fits
+    /// in `tokens`, as counted by the engine's token counter
+    pub fn with_history_token_budget(mut self, tokens: usize) -> Self {
+        self.history_tokens = Some(tokens);
+        self
+    }
+
+    pub fn with_settings_store(mut self, settings: Arc<dyn SettingsStore>) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Persist history in `store` instead of memory, as conversations with
+    /// ids `{namespace}:{conversation}` (e.g. `discord:1234`)
+    pub fn with_conversation_store(mut self, store: Arc<dyn ConversationStore>, namespace: impl Into<String>) -> Self {
+        self.history = HistoryBackend::Store {
+            store,
+            namespace: namespace.into(),
+            window: self.history_window,
+        };
+        self
+    }
+
+    pub fn engine(&self) -> &Arc<OmniferenceEngine> {
+        &self.engine
+    }
+
+    pub fn settings_store(&self) -> &Arc<dyn SettingsStore> {
+        &self.settings
+    }
+
+    /// Settings for a conversation: conversation over guild over defaults
+    pub async fn effective_settings(&self, guild: Option<u64>, conversation: u64) -> ChatSettings {
+        self.settings.resolve(guild, conversation, &self.defaults).await
+    }
+
+    /// The configured model, or the first discovered one
+    pub async fn active_model(&self, settings: &ChatSettings) -> Option<String> {
+        if let Some(model) = &settings.model {
+            return Some(model.clone());
+        }
+        let mut models = self.engine.list_models().await;
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        models.into_iter().next().map(|m| m.id)
+    }
+
+    pub async fn reset(&self, conversation: u64) {
+        self.history.clear(conversation).await;
+    }
+
+    /// Record the user's message and start streaming the reply. The final
+    /// reply is added to the history when the returned stream completes.
+    pub async fn start_turn(
+        &self,
+        guild: Option<u64>,
+        conversation: u64,
+        parts: Vec<ContentPart>,
+        edit_every: usize,
+    ) -> Result<Pin<Box<dyn Stream<Item = ReplyUpdate> + Send>>, EngineError> {
+        let settings = self.effective_settings(guild, conversation).await;
+        let model = self
+            .active_model(&settings)
+            .await
+            .ok_or_else(|| EngineError::config("no models available; check the provider configuration"))?;
+        let model_ref = self.engine.resolve_model(&model).await?;
+
+        let user_message = Message {
+            role: Role::User,
+            parts,
+            name: None,
+        };
+        self.history.record(conversation, user_message.clone()).await;
+        let messages = self.history.recent(conversation).await;
+        let messages = if messages.is_empty() { vec![user_message] } else { messages };
+
+        let mut request = ChatRequestIR {
+            model: model_ref,
+            messages,
+            stream: true,
+            ..Default::default()
+        };
+        settings.apply(&mut request);
+        if let Some(budget) = self.history_tokens {
+            let count = self.engine.count_tokens(&model, &request.messages).await?;
+            if count.prompt_tokens > budget {
+                let messages = std::mem::take(&mut request.messages);
+                request.messages = crate::tokens::fit_to_budget(messages, &count, budget);
+            }
+        }
+
+        let events = self.engine.chat(request).await?;
+        let history = self.history.clone();
+        let updates = reply_updates(events, edit_every);
+        Ok(Box::pin(async_stream::stream! {
+            futures_util::pin_mut!(updates);
+            while let Some(update) = updates.next().await {
+                if let ReplyUpdate::Final(text) = &update {
+                    if !text.is_empty() {
+                        let reply = Message {
+                            role: Role::Assistant,
+                            parts: vec![ContentPart::Text(text.clone())],
+                            name: None,
+                        };
+                        history.record(conversation, reply).await;
+                    }
+                }
+                yield update;
+            }
+        }))
+    }
+}
@@ -0,0 +1,158 @@
+//! Serialization of Chat Completions stream chunks
+//!
+//! Every chunk of a stream repeats the same id, creation time and model, and
+//! text deltas differ only in their content. A [`ChunkEncoder`] serializes
+//! the chunk around the content once per stream and then only escapes each
+//! delta between that prefix and suffix, into a buffer it reuses. The output
+//! is byte for byte what serializing the full
+//! [`OpenAIStreamChunk`](crate::types::OpenAIStreamChunk) gives.
+
+use crate::skins::resumable::SseChunk;
+use crate::stream::StreamEvent;
+use axum::response::sse::Event;
+use std::fmt::Write;
+
+/// Content whose JSON form marks where the delta goes in the template
+const MARKER: &str = "\u{0}";
+const MARKER_JSON: &str = "\"\\u0000\"";
+
+/// Serializes the chunks of one Chat Completions stream
+pub struct ChunkEncoder {
+    request_id: String,
+    model: String,
+    created: u64,
+    /// The text delta chunk up to its content
+    prefix: String,
+    /// The text delta chunk after its content
+    suffix: String,
+    /// The final chunk, with its finish reason
+    done: String,
+    buffer: String,
+}
+
+impl ChunkEncoder {
+    /// An encoder for the stream of `request_id` on `model`, created now
+    pub fn new(request_id: &str, model: &str) -> Self {
+        let created = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Self::with_created(request_id, model, created)
+    }
+
+    /// An encoder for chunks created at `created` (Unix seconds)
+    pub fn with_created(request_id: &str, model: &str, created: u64) -> Self {
+        let chunk = |content: Option<&str>, finish_reason: Option<&str>| {
+            let mut chunk = crate::skins::openai::stream_chunk(
+                request_id,
+                model,
+                content.map(str::to_string),
+                finish_reason.map(str::to_string),
+            );
+            chunk.created = created;
+            serde_json::to_string(&chunk).unwrap_or_default()
+        };
+        let template = chunk(Some(MARKER), None);
+        let (prefix, suffix) = template.split_once(MARKER_JSON).unwrap_or((&template, ""));
+        Self {
+            request_id: request_id.to_string(),
+            model: model.to_string(),
+            created,
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+            done: chunk(None, Some("stop")),
+            buffer: String::new(),
+        }
+    }
+
+    /// The `created` time of the stream's chunks
+    pub fn created(&self) -> u64 {
+        self.created
+    }
+
+    /// The SSE message for `event`, like [`sse_chunk`](crate::skins::openai::sse_chunk)
+    pub fn encode(&mut self, event: StreamEvent) -> SseChunk {
+        match event {
+            StreamEvent::TextDelta { content } => {
+                let mut data = String::with_capacity(self.prefix.len() + content.len() + 2 + self.suffix.len());
+                self.write_delta(&mut data, &content);
+                SseChunk::Data(data)
+            }
+            StreamEvent::Refusal { text } => {
+                self.chunk(crate::skins::openai::refusal_chunk(&self.request_id, &self.model, text))
+            }
+            StreamEvent::Annotation { annotation } => {
+                self.chunk(crate::skins::openai::annotation_chunk(&self.request_id, &self.model, annotation))
+            }
+            StreamEvent::Done => SseChunk::Data(self.done.clone()),
+            // A reply cut off at its output limit finishes with `length`
+            StreamEvent::FinalMessage { finish_reason: Some(reason), .. } if reason == "length" => {
+                let done = crate::skins::openai::stream_chunk(&self.request_id, &self.model, None, Some(reason));
+                self.done = self.serialize(done);
+                SseChunk::Data(String::new())
+            }
+            event => crate::skins::openai::contentless_chunk(event),
+        }
+    }
+
+    /// The SSE event for `event`; text deltas are written into the reused
+    /// buffer rather than a string of their own
+    pub fn event(&mut self, event: StreamEvent) -> Result<Event, axum::Error> {
+        match event {
+            StreamEvent::TextDelta { content } => {
+                let mut buffer = std::mem::take(&mut self.buffer);
+                buffer.clear();
+                self.write_delta(&mut buffer, &content);
+                let event = Event::default().data(&buffer);
+                self.buffer = buffer;
+                Ok(event)
+            }
+            StreamEvent::Done => Ok(Event::default().data(&self.done)),
+            event => self.encode(event).into_event(None),
+        }
+    }
+
+    /// `chunk` at the stream's creation time
+    fn chunk(&self, chunk: crate::types::OpenAIStreamChunk) -> SseChunk {
+        SseChunk::Data(self.serialize(chunk))
+    }
+
+    fn serialize(&self, mut chunk: crate::types::OpenAIStreamChunk) -> String {
+        chunk.created = self.created;
+        serde_json::to_string(&chunk).unwrap_or_default()
+    }
+
+    fn write_delta(&self, out: &mut String, content: &str) {
+        out.push_str(&self.prefix);
+        escape_json(out, content);
+        out.push_str(&self.suffix);
+    }
+}
+
+/// Append `text` to `out` as a JSON string, escaped the way `serde_json` does
+pub fn escape_json(out: &mut String, text: &str) {
+    out.push('"');
+    let mut start = 0;
+    for (i, byte) in text.bytes().enumerate() {
+        let escape = match byte {
+            b'"' => "\\\"",
+            b'\\' => "\\\\",
+            b'\n' => "\\n",
+            b'\r' => "\\r",
+            b'\t' => "\\t",
+            0x08 => "\\b",
+            0x0c => "\\f",
+            0x00..=0x1f => "",
+            _ => continue,
+        };
+        out.push_str(&text[start..i]);
+        if escape.is_empty() {
+            let _ = write!(out, "\\u{:04x}", byte);
+        } else {
+            out.push_str(escape);
+        }
+        start = i + 1;
+    }
+    out.push_str(&text[start..]);
+    out.push('"');
+}
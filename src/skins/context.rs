@@ -1,10 +1,15 @@
-use crate::{router::Router, service::ProviderManager};
+use crate::{moderation::ModerationClient, router::Router, service::ProviderManager};
+use crate::store::{ConversationStore, InMemoryConversationStore};
 use crate::skins::{SkinErrorHandler, OpenAIErrorHandler};
+use crate::tenant::TenantScope;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
 
+/// Most close matches listed in a model-not-found error
+pub const MAX_MODEL_SUGGESTIONS: usize = 5;
+
 #[derive(Clone)]
 pub struct SkinContext {
     pub router: Arc<Router>,
@@ -12,6 +17,41 @@ pub struct SkinContext {
     pub provider_manager: Arc<RwLock<ProviderManager>>,
     pub cancel_tokens: Arc<CancellationToken>,
     pub error_handler: Arc<dyn SkinErrorHandler + Send + Sync>,
+    /// Backs the `/moderations` route; the route answers 404 without it
+    pub moderation: Option<Arc<ModerationClient>>,
+    /// Stored responses for the Responses API's `previous_response_id`
+    pub conversations: Arc<dyn ConversationStore>,
+    /// Pass request fields the skin doesn't model on to providers (see
+    /// [`ChatRequestIR::extra_body`](crate::types::ChatRequestIR::extra_body))
+    pub extra_body_passthrough: bool,
+    /// Buffers streamed chat completions for reconnecting clients; streams
+    /// end with their connection without it
+    pub resumable_streams: Option<crate::skins::resumable::ResumableStreams>,
+    /// Keep-alive and heartbeat comments of SSE responses
+    pub sse_keep_alive: crate::skins::keepalive::SseKeepAlive,
+    /// Honor the provider override and trace request headers (see
+    /// [`crate::trace`]); off for servers open to untrusted clients
+    pub debug_headers: bool,
+    /// Send the `x-provider-api-key` request header upstream as the
+    /// provider's API key (see [`crate::skins::openai::PROVIDER_KEY_HEADER`])
+    pub provider_key_header: bool,
+    /// Send the organization and project of the `OpenAI-Organization` and
+    /// `OpenAI-Project` request headers instead of the provider's
+    pub organization_headers: bool,
+    /// The tenants of API keys; requests of other keys only see shared
+    /// providers (see [`crate::tenant`])
+    pub tenants: crate::tenant::TenantDirectory,
+    /// Frames a WebSocket queues for a slow client, and what happens when
+    /// the queue is full
+    pub back_pressure: crate::backpressure::BackPressure,
+    /// Responses API requests with `background: true`, polled by id
+    pub background: crate::background::BackgroundResponses,
+    /// What responses report as their `system_fingerprint` and `service_tier`
+    pub system_fingerprint: crate::skins::openai::SystemFingerprint,
+    /// Tools the gateway can run for chat requests itself
+    pub tools: Arc<RwLock<crate::tools::ToolRegistry>>,
+    /// Which chat requests the gateway runs `tools` for (see [`crate::tools`])
+    pub auto_tools: crate::tools::AutoToolExecution,
 }
 
 impl SkinContext {
@@ -22,6 +62,20 @@ impl SkinContext {
             provider_manager: Arc::new(RwLock::new(ProviderManager::new())),
             cancel_tokens: Arc::new(CancellationToken::new()),
             error_handler: Arc::new(OpenAIErrorHandler),
+            moderation: None,
+            conversations: Arc::new(InMemoryConversationStore::new()),
+            extra_body_passthrough: true,
+            resumable_streams: None,
+            sse_keep_alive: Default::default(),
+            debug_headers: true,
+            provider_key_header: false,
+            organization_headers: false,
+            tenants: Default::default(),
+            back_pressure: Default::default(),
+            system_fingerprint: Default::default(),
+            tools: Default::default(),
+            auto_tools: Default::default(),
+            background: Default::default(),
         }
     }
 
@@ -32,6 +86,47 @@ impl SkinContext {
             provider_manager,
             cancel_tokens: Arc::new(CancellationToken::new()),
             error_handler: Arc::new(OpenAIErrorHandler),
+            moderation: None,
+            conversations: Arc::new(InMemoryConversationStore::new()),
+            extra_body_passthrough: true,
+            resumable_streams: None,
+            sse_keep_alive: Default::default(),
+            debug_headers: true,
+            provider_key_header: false,
+            organization_headers: false,
+            tenants: Default::default(),
+            back_pressure: Default::default(),
+            system_fingerprint: Default::default(),
+            tools: Default::default(),
+            auto_tools: Default::default(),
+            background: Default::default(),
+        }
+    }
+
+    /// A context over `core`'s router, providers, cancellation token and
+    /// background requests, so the skins see what engines over the same core
+    /// register
+    pub fn from_core(core: &crate::service::OmniferenceCore) -> Self {
+        Self {
+            router: core.router.clone(),
+            model_resolver: Arc::new(RwLock::new(ModelResolver::new())),
+            provider_manager: core.provider_manager.clone(),
+            cancel_tokens: core.cancel_tokens.clone(),
+            error_handler: Arc::new(OpenAIErrorHandler),
+            moderation: None,
+            conversations: Arc::new(InMemoryConversationStore::new()),
+            extra_body_passthrough: true,
+            resumable_streams: None,
+            sse_keep_alive: Default::default(),
+            debug_headers: true,
+            provider_key_header: false,
+            organization_headers: false,
+            tenants: Default::default(),
+            back_pressure: Default::default(),
+            system_fingerprint: Default::default(),
+            tools: core.tools.clone(),
+            auto_tools: Default::default(),
+            background: core.background.clone(),
         }
     }
 
@@ -42,15 +137,31 @@ impl SkinContext {
             provider_manager,
             cancel_tokens: Arc::new(CancellationToken::new()),
             error_handler,
+            moderation: None,
+            conversations: Arc::new(InMemoryConversationStore::new()),
+            extra_body_passthrough: true,
+            resumable_streams: None,
+            sse_keep_alive: Default::default(),
+            debug_headers: true,
+            provider_key_header: false,
+            organization_headers: false,
+            tenants: Default::default(),
+            back_pressure: Default::default(),
+            system_fingerprint: Default::default(),
+            tools: Default::default(),
+            auto_tools: Default::default(),
+            background: Default::default(),
         }
     }
 }
 
-/// Determine which skin to use based on the request path
+/// Determine which skin to use based on the request path.
+///
+/// Matches anywhere in the path so the gateway can be nested under a prefix.
 pub fn determine_skin_from_path(path: &str) -> Arc<dyn SkinErrorHandler + Send + Sync> {
-    if path.starts_with("/api/openai/v1/") || path.starts_with("/api/openai-compatible/v1/") {
+    if path.contains("/api/openai/v1/") || path.contains("/api/openai-compatible/v1/") {
         Arc::new(OpenAIErrorHandler)
-    } else if path.starts_with("/api/anthropic/v1/") {
+    } else if path.contains("/api/anthropic/v1/") {
         // Placeholder for future Anthropic handler
         Arc::new(OpenAIErrorHandler) // Will be replaced with AnthropicErrorHandler
     } else {
@@ -87,66 +198,111 @@ impl ModelResolver {
 
 impl SkinContext {
     /// Resolve a model identifier or name to a concrete ModelRef using the ProviderManager.
-    /// Supports:
-    /// - exact discovered ID (e.g., "openrouter/gpt-5-nano")
-    /// - bare model name (e.g., "gpt-5-nano")
-    /// - legacy kind prefix (e.g., "openai-compat/gpt-5-nano")
+    /// See [`ProviderManager::resolve_model_ref`] for the accepted forms.
     pub async fn resolve_model_ref(&self, model: &str) -> Option<crate::types::ModelRef> {
-        let mgr = self.provider_manager.read().await;
-
-        // Try exact ID match first
-        let discovered = if let Some(m) = mgr.get_model(model) {
-            Some(m.clone())
-        } else {
-            // Support legacy kind-prefixed IDs and name-only lookups
-            let mut candidate: Option<crate::types::DiscoveredModel> = None;
-            if let Some((prefix, rest)) = model.split_once('/') {
-                use crate::types::ProviderKind as PK;
-                let kind_hint = match prefix {
-                    "openai-compat" => Some(PK::OpenAICompat),
-                    "openai" => Some(PK::OpenAI),
-                    "ollama" => Some(PK::Ollama),
-                    "lmstudio" => Some(PK::LMStudio),
-                    _ => None,
-                };
-                if let Some(k) = kind_hint {
-                    candidate = mgr
-                        .list_models()
-                        .into_iter()
-                        .find(|m| m.name == rest && m.provider_kind == k)
-                        .cloned();
-                }
+        self.provider_manager.read().await.resolve_model_ref(model)
+    }
+
+    /// The tenant of requests authenticated with `api_key`, if any
+    pub fn tenant_of(&self, api_key: Option<&str>) -> Option<String> {
+        api_key.and_then(|key| self.tenants.tenant_of(key))
+    }
+
+    /// Resolve `model` among the models `tenant` (or, without one, everyone)
+    /// may use, answering unknown and ambiguous names with the skin's error response
+    pub async fn resolve_model(&self, model: &str, tenant: Option<&str>) -> Result<crate::types::ModelRef, axum::response::Response> {
+        // The routing policy picks the real model once the request is converted
+        if let Some(policy) = self.router.routing_policy().filter(|policy| policy.handles(model)) {
+            return Ok(policy.placeholder_model());
+        }
+        let manager = self.provider_manager.read().await;
+        if model.trim().is_empty() && manager.default_model().is_none() {
+            return Err(self.error_handler.handle_json_error(serde_json::Error::io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "missing field `model`: a model is required when no default model is configured",
+            ))));
+        }
+        let scope = TenantScope::of(tenant);
+        let resolved = manager.try_resolve_model_ref_in(model, scope);
+        drop(manager);
+        match resolved {
+            Ok(model_ref) => Ok(model_ref),
+            Err(crate::service::ModelResolutionError::NotFound) => Err(self.model_not_found(model, scope).await),
+            Err(crate::service::ModelResolutionError::Ambiguous { candidates }) => {
+                Err(self.error_handler.handle_ambiguous_model(model, &candidates))
             }
+        }
+    }
 
-            candidate.or_else(|| {
-                mgr.list_models()
-                    .into_iter()
-                    .find(|m| m.name == model)
-                    .cloned()
-            })
-        }?;
-
-        // Find provider endpoint: prefer exact provider name match if available
-        let provider_endpoint = if let Some(p) = mgr.get_provider(&discovered.provider_name) {
-            p.endpoint.clone()
-        } else {
-            // Fallback: first provider of the same kind
-            if let Some(p) = mgr
-                .list_providers()
-                .into_iter()
-                .find(|p| p.endpoint.kind == discovered.provider_kind)
-            {
-                p.endpoint.clone()
-            } else {
-                return None;
+    /// Resolve `model` like [`resolve_model`](Self::resolve_model), but only
+    /// to the model `provider` serves (see [`ProviderManager::try_resolve_model_ref_on`])
+    pub async fn resolve_model_on(
+        &self,
+        model: &str,
+        provider: &str,
+        tenant: Option<&str>,
+    ) -> Result<crate::types::ModelRef, axum::response::Response> {
+        let scope = TenantScope::of(tenant);
+        let resolved = self.provider_manager.read().await.try_resolve_model_ref_on(model, provider, scope);
+        match resolved {
+            Ok(model_ref) => Ok(model_ref),
+            Err(crate::service::ModelResolutionError::NotFound) => {
+                Err(self.model_not_found(&format!("{}/{}", provider, model), scope).await)
             }
+            Err(crate::service::ModelResolutionError::Ambiguous { candidates }) => {
+                Err(self.error_handler.handle_ambiguous_model(model, &candidates))
+            }
+        }
+    }
+
+    /// Route a chat request, running registered tools and continuing with
+    /// the model's follow-up reply when the request opts into it (see
+    /// [`AutoToolExecution::apply`](crate::tools::AutoToolExecution::apply))
+    pub async fn route_chat(
+        &self,
+        mut ir: crate::types::ChatRequestIR,
+        cancel: CancellationToken,
+    ) -> Result<futures_util::stream::BoxStream<'static, crate::stream::StreamEvent>, crate::error::EngineError> {
+        use futures_util::StreamExt;
+
+        let limits = self.auto_tools.apply(&mut ir);
+        let mut tools = self.tools.read().await.clone();
+        let Some(limits) = limits.filter(|_| !tools.is_empty()) else {
+            return Ok(self.router.route_chat(ir, cancel).await?.boxed());
         };
+        tools.limit(limits);
+
+        // Resolve routing errors up front, like the plain path
+        self.router.registry.get(&ir.model.provider.kind).ok_or_else(|| {
+            crate::error::EngineError::config(format!("no adapter for {:?}", ir.model.provider.kind))
+        })?;
+        let unknown_tools = crate::tools::UnknownToolPolicy::ReturnToCaller;
+        Ok(crate::tools::run_tool_loop(self.router.clone(), tools, ir, cancel, unknown_tools).boxed())
+    }
+
+    /// Replace the routing policy's virtual model on `ir` with the model the
+    /// policy picks (see [`crate::service::OmniferenceService::apply_routing_policy`])
+    /// The policy picks among the models of the request's tenant (its
+    /// [`TENANT_METADATA`](crate::tenant::TENANT_METADATA)).
+    pub async fn apply_routing_policy(&self, ir: &mut crate::types::ChatRequestIR) -> Result<(), axum::response::Response> {
+        let tenant = ir.metadata.get(crate::tenant::TENANT_METADATA).cloned();
+        let scope = TenantScope::of(tenant.as_deref());
+        match crate::service::apply_routing_policy(&self.router, &self.provider_manager, ir, scope).await {
+            Ok(_) => Ok(()),
+            Err(crate::error::EngineError::ModelNotFound(model)) => Err(self.model_not_found(&model, scope).await),
+            Err(crate::error::EngineError::AmbiguousModel { model, candidates }) => {
+                Err(self.error_handler.handle_ambiguous_model(&model, &candidates))
+            }
+            Err(other) => Err(self.error_handler.handle_provider_error("routing_error".to_string(), other.to_string())),
+        }
+    }
 
-        Some(crate::types::ModelRef {
-            alias: discovered.id.clone(),
-            provider: provider_endpoint,
-            model_id: discovered.name.clone(),
-            modalities: discovered.modalities.clone(),
-        })
+    /// The skin's model-not-found response for `model`, listing up to
+    /// [`MAX_MODEL_SUGGESTIONS`] close matches among the models `scope` sees
+    pub async fn model_not_found(&self, model: &str, scope: TenantScope<'_>) -> axum::response::Response {
+        let manager = self.provider_manager.read().await;
+        let suggestions = manager.suggest_models_in(model, MAX_MODEL_SUGGESTIONS, scope);
+        let available = manager.list_models_in(scope).len();
+        self.error_handler.handle_model_not_found_with_suggestions(model, &suggestions, available)
     }
 }
@@ -0,0 +1,536 @@
+//! Discord interface (enabled with the `discord` feature)
+//!
+//! [`DiscordBot`] wraps an [`OmniferenceEngine`] in a Serenity client. Users
+//! chat via the `/ai` slash command or a message prefix (`!ai` by default) or
+//! by mentioning the bot. Replies stream in by editing the response message
+//! every few deltas, and each channel keeps a rolling conversation history.
+//!
+//! Model and sampling settings are stored per channel and per guild through a
+//! [`SettingsStore`]; guild-wide changes require the Manage Server permission.
+
+use crate::engine::OmniferenceEngine;
+use crate::skins::bot::{chunk_message, ChatSession, ReplyUpdate};
+use crate::skins::settings::{ChatSettings, SettingsScope, SettingsStore};
+use crate::store::ConversationStore;
+use crate::types::ContentPart;
+use futures_util::StreamExt;
+use serenity::all::{
+    ChannelId, Command, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context,
+    CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage,
+    EditInteractionResponse, EditMessage, EventHandler, GatewayIntents, GuildId, Http, Interaction,
+    MessageId, Ready, ResolvedOption, ResolvedValue, UserId,
+};
+use std::sync::{Arc, OnceLock};
+
+/// Maximum length of a Discord message
+pub const DISCORD_MESSAGE_LIMIT: usize = 2000;
+
+/// Configuration for [`DiscordBot`]
+#[derive(Clone, Debug)]
+pub struct DiscordBotConfig {
+    pub token: String,
+    /// Model id or alias to use when no guild or channel setting applies;
+    /// defaults to the first discovered model
+    pub default_model: Option<String>,
+    pub system_prompt: Option<String>,
+    /// Messages kept per channel (user and assistant turns)
+    pub history_window: usize,
+    /// Prompt token budget for each turn; the oldest turns in the window are
+    /// left out to stay within it. `None` sends the whole window.
+    pub history_tokens: Option<usize>,
+    /// Number of text deltas between edits of the streaming reply
+    pub edit_every: usize,
+    /// Plain-message trigger in addition to slash commands and mentions
+    pub message_prefix: Option<String>,
+}
+
+impl DiscordBotConfig {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            default_model: None,
+            system_prompt: None,
+            history_window: 20,
+            history_tokens: Some(4096),
+            edit_every: 20,
+            message_prefix: Some("!ai".to_string()),
+        }
+    }
+}
+
+/// Discord bot backed by an [`OmniferenceEngine`]
+pub struct DiscordBot {
+    config: DiscordBotConfig,
+    session: ChatSession,
+}
+
+impl DiscordBot {
+    pub fn new(engine: impl Into<Arc<OmniferenceEngine>>, config: DiscordBotConfig) -> Self {
+        let defaults = ChatSettings {
+            model: config.default_model.clone(),
+            system_prompt: config.system_prompt.clone(),
+            ..Default::default()
+        };
+        let mut session = ChatSession::new(engine, config.history_window, defaults);
+        if let Some(tokens) = config.history_tokens {
+            session = session.with_history_token_budget(tokens);
+        }
+        Self { config, session }
+    }
+
+    /// Persist model/sampling settings somewhere other than memory
+    pub fn with_settings_store(mut self, settings: Arc<dyn SettingsStore>) -> Self {
+        self.session = self.session.with_settings_store(settings);
+        self
+    }
+
+    /// Persist conversation history in `store` (as `discord:<chat id>`) instead of memory
+    pub fn with_conversation_store(mut self, store: Arc<dyn ConversationStore>) -> Self {
+        self.session = self.session.with_conversation_store(store, "discord");
+        self
+    }
+
+    /// Slash commands registered by the bot on startup
+    pub fn commands() -> Vec<CreateCommand> {
+        vec![
+            CreateCommand::new("ai")
+                .description("Chat with the AI")
+                .add_option(
+                    CreateCommandOption::new(CommandOptionType::String, "prompt", "Your message")
+                        .required(true),
+                ),
+            CreateCommand::new("model")
+                .description("Show or change the model")
+                .add_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "list",
+                    "List available models",
+                ))
+                .add_option(
+                    CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Switch model")
+                        .add_sub_option(
+                            CreateCommandOption::new(CommandOptionType::String, "name", "Model id or alias")
+                                .required(true),
+                        )
+                        .add_sub_option(
+                            CreateCommandOption::new(CommandOptionType::String, "scope", "Where the model applies")
+                                .add_string_choice("this channel", "channel")
+                                .add_string_choice("server default", "server"),
+                        ),
+                ),
+            CreateCommand::new("settings")
+                .description("Change this server's chat settings")
+                .add_option(
+                    CreateCommandOption::new(CommandOptionType::SubCommand, "temperature", "Sampling temperature")
+                        .add_sub_option(
+                            CreateCommandOption::new(CommandOptionType::Number, "value", "0.0 - 2.0")
+                                .min_number_value(0.0)
+                                .max_number_value(2.0)
+                                .required(true),
+                        ),
+                )
+                .add_option(
+                    CreateCommandOption::new(CommandOptionType::SubCommand, "max_tokens", "Maximum reply length")
+                        .add_sub_option(
+                            CreateCommandOption::new(CommandOptionType::Integer, "value", "Tokens")
+                                .min_int_value(1)
+                                .required(true),
+                        ),
+                )
+                .add_option(
+                    CreateCommandOption::new(CommandOptionType::SubCommand, "system_prompt", "System prompt")
+                        .add_sub_option(
+                            CreateCommandOption::new(CommandOptionType::String, "value", "Prompt text")
+                                .required(true),
+                        ),
+                )
+                .add_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "show",
+                    "Show the effective settings for this channel",
+                )),
+            CreateCommand::new("reset").description("Clear this channel's conversation history"),
+        ]
+    }
+
+    /// Connect to Discord and handle events until the client shuts down
+    pub async fn run(self) -> Result<(), serenity::Error> {
+        let intents = GatewayIntents::GUILD_MESSAGES
+            | GatewayIntents::DIRECT_MESSAGES
+            | GatewayIntents::MESSAGE_CONTENT
+            | GatewayIntents::GUILDS;
+        let token = self.config.token.clone();
+        let handler = Handler {
+            bot: Arc::new(self),
+            user_id: OnceLock::new(),
+        };
+        let mut client = serenity::Client::builder(&token, intents)
+            .event_handler(handler)
+            .await?;
+        client.start().await
+    }
+
+    async fn effective_settings(&self, guild: Option<GuildId>, channel: ChannelId) -> ChatSettings {
+        self.session
+            .effective_settings(guild.map(|g| g.get()), channel.get())
+            .await
+    }
+
+    /// Run one chat turn for `channel` and stream the answer into `reply`
+    async fn respond(
+        &self,
+        http: &Http,
+        guild: Option<GuildId>,
+        channel: ChannelId,
+        prompt: String,
+        mut reply: StreamingReply,
+    ) {
+        let parts = vec![ContentPart::Text(prompt)];
+        let guild = guild.map(|g| g.get());
+        let mut updates = match self.session.start_turn(guild, channel.get(), parts, self.config.edit_every).await {
+            Ok(updates) => updates,
+            Err(e) => {
+                reply.render(http, &format!("❌ {}", e)).await;
+                return;
+            }
+        };
+
+        while let Some(update) = updates.next().await {
+            match update {
+                ReplyUpdate::Partial(text) => reply.render(http, &text).await,
+                ReplyUpdate::Final(text) if text.trim().is_empty() => {
+                    reply.render(http, "(empty response)").await
+                }
+                ReplyUpdate::Final(text) => reply.render(http, &text).await,
+                ReplyUpdate::Failed { partial, message } => {
+                    reply.render(http, &format!("{}\n\n❌ Error: {}", partial, message)).await
+                }
+            }
+        }
+    }
+
+    async fn handle_command(&self, ctx: &Context, command: CommandInteraction) {
+        match command.data.name.as_str() {
+            "ai" => {
+                let prompt = command
+                    .data
+                    .options
+                    .iter()
+                    .find(|o| o.name == "prompt")
+                    .and_then(|o| match &o.value {
+                        CommandDataOptionValue::String(s) => Some(s.clone()),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                if let Err(e) = command.defer(&ctx.http).await {
+                    tracing::warn!(error = %e, "Failed to defer Discord interaction");
+                    return;
+                }
+                let (guild, channel) = (command.guild_id, command.channel_id);
+                let reply = StreamingReply::interaction(command);
+                self.respond(&ctx.http, guild, channel, prompt, reply).await;
+            }
+            "model" => {
+                let text = self.model_command(&command).await;
+                respond_ephemeral(ctx, &command, text).await;
+            }
+            "settings" => {
+                let text = self.settings_command(&command).await;
+                respond_ephemeral(ctx, &command, text).await;
+            }
+            "reset" => {
+                self.session.reset(command.channel_id.get()).await;
+                respond_ephemeral(ctx, &command, "🧹 Conversation history cleared.".to_string()).await;
+            }
+            other => {
+                tracing::debug!(command = other, "Ignoring unknown Discord command");
+            }
+        }
+    }
+}
+
+impl DiscordBot {
+    async fn model_command(&self, command: &CommandInteraction) -> String {
+        let options = command.data.options();
+        let Some(ResolvedOption { name: sub, value: ResolvedValue::SubCommand(args), .. }) = options.first() else {
+            return "Usage: `/model list` or `/model set <name>`".to_string();
+        };
+
+        if *sub == "list" {
+            let settings = self.effective_settings(command.guild_id, command.channel_id).await;
+            let active = self.session.active_model(&settings).await.unwrap_or_else(|| "none".to_string());
+            let mut ids: Vec<String> = self.session.engine().list_models().await.into_iter().map(|m| m.id).collect();
+            ids.sort();
+            let mut text = format!("Active model: `{}`\nAvailable models:", active);
+            for id in ids {
+                text.push_str(&format!("\n- `{}`", id));
+            }
+            return chunk_message(&text, DISCORD_MESSAGE_LIMIT).swap_remove(0);
+        }
+
+        let Some(name) = string_arg(args, "name") else {
+            return "Please provide a model name.".to_string();
+        };
+        let server_wide = string_arg(args, "scope") == Some("server");
+
+        // Only accept models that were actually discovered
+        let model_ref = match self.session.engine().resolve_model(name).await {
+            Ok(model_ref) => model_ref,
+            Err(_) => return format!("❌ Unknown model `{}`. Use `/model list` to see available models.", name),
+        };
+
+        let scope = if server_wide {
+            let Some(guild) = command.guild_id else {
+                return "❌ Server defaults can only be set inside a server.".to_string();
+            };
+            if !can_manage_guild(command) {
+                return "❌ You need the Manage Server permission to change the server default.".to_string();
+            }
+            SettingsScope::Guild(guild.get())
+        } else {
+            SettingsScope::Channel(command.channel_id.get())
+        };
+
+        let mut settings = self.session.settings_store().get(scope).await.unwrap_or_default();
+        settings.model = Some(model_ref.alias.clone());
+        if let Err(e) = self.session.settings_store().set(scope, settings).await {
+            return format!("❌ Failed to save settings: {}", e);
+        }
+        let target = if server_wide { "this server" } else { "this channel" };
+        format!("✅ Model for {} set to `{}`.", target, model_ref.alias)
+    }
+
+    async fn settings_command(&self, command: &CommandInteraction) -> String {
+        let options = command.data.options();
+        let Some(ResolvedOption { name: sub, value: ResolvedValue::SubCommand(args), .. }) = options.first() else {
+            return "Usage: `/settings <temperature|max_tokens|system_prompt|show>`".to_string();
+        };
+
+        if *sub == "show" {
+            let settings = self.effective_settings(command.guild_id, command.channel_id).await;
+            let model = self.session.active_model(&settings).await.unwrap_or_else(|| "none".to_string());
+            let fmt = |v: Option<String>| v.unwrap_or_else(|| "provider default".to_string());
+            return format!(
+                "Model: `{}`\nTemperature: {}\nMax tokens: {}\nSystem prompt: {}",
+                model,
+                fmt(settings.temperature.map(|t| t.to_string())),
+                fmt(settings.max_tokens.map(|t| t.to_string())),
+                fmt(settings.system_prompt),
+            );
+        }
+
+        // Settings are stored per guild; in DMs they apply to the conversation
+        let scope = match command.guild_id {
+            Some(_) if !can_manage_guild(command) => {
+                return "❌ You need the Manage Server permission to change settings.".to_string();
+            }
+            Some(guild) => SettingsScope::Guild(guild.get()),
+            None => SettingsScope::Channel(command.channel_id.get()),
+        };
+        let mut settings = self.session.settings_store().get(scope).await.unwrap_or_default();
+
+        let value = args.iter().find(|o| o.name == "value").map(|o| &o.value);
+        match (*sub, value) {
+            ("temperature", Some(ResolvedValue::Number(t))) if (0.0..=2.0).contains(t) => {
+                settings.temperature = Some(*t as f32);
+            }
+            ("max_tokens", Some(ResolvedValue::Integer(n))) if *n > 0 => {
+                settings.max_tokens = Some((*n).min(u32::MAX as i64) as u32);
+            }
+            ("system_prompt", Some(ResolvedValue::String(text))) => {
+                settings.system_prompt = Some(text.to_string());
+            }
+            _ => return format!("❌ Invalid value for `{}`.", sub),
+        }
+
+        if let Err(e) = self.session.settings_store().set(scope, settings).await {
+            return format!("❌ Failed to save settings: {}", e);
+        }
+        format!("✅ Updated `{}`.", sub)
+    }
+}
+
+fn string_arg<'a>(args: &'a [ResolvedOption<'a>], name: &str) -> Option<&'a str> {
+    args.iter().find(|o| o.name == name).and_then(|o| match o.value {
+        ResolvedValue::String(s) => Some(s),
+        _ => None,
+    })
+}
+
+/// Whether the invoking member has Manage Server in this guild
+fn can_manage_guild(command: &CommandInteraction) -> bool {
+    command
+        .member
+        .as_ref()
+        .and_then(|m| m.permissions)
+        .is_some_and(|p| p.manage_guild())
+}
+
+async fn respond_ephemeral(ctx: &Context, command: &CommandInteraction, content: String) {
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new().content(content).ephemeral(true),
+    );
+    if let Err(e) = command.create_response(&ctx.http, response).await {
+        tracing::warn!(error = %e, "Failed to respond to Discord command");
+    }
+}
+
+struct Handler {
+    bot: Arc<DiscordBot>,
+    user_id: OnceLock<UserId>,
+}
+
+impl Handler {
+    /// Extract the prompt from a plain message if it is addressed to the bot
+    fn prompt_from(&self, msg: &serenity::all::Message) -> Option<String> {
+        if let Some(prefix) = &self.bot.config.message_prefix {
+            if let Some(rest) = msg.content.strip_prefix(prefix.as_str()) {
+                return Some(rest.trim().to_string());
+            }
+        }
+        let me = self.user_id.get()?;
+        if !msg.mentions.iter().any(|u| u.id == *me) {
+            return None;
+        }
+        let content = msg
+            .content
+            .replace(&format!("<@{}>", me), "")
+            .replace(&format!("<@!{}>", me), "");
+        Some(content.trim().to_string())
+    }
+}
+
+#[serenity::async_trait]
+impl EventHandler for Handler {
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        let _ = self.user_id.set(ready.user.id);
+        if let Err(e) = Command::set_global_commands(&ctx.http, DiscordBot::commands()).await {
+            tracing::warn!(error = %e, "Failed to register Discord slash commands");
+        }
+        tracing::info!(user = %ready.user.name, "Discord bot connected");
+    }
+
+    async fn message(&self, ctx: Context, msg: serenity::all::Message) {
+        if msg.author.bot {
+            return;
+        }
+        let Some(prompt) = self.prompt_from(&msg) else {
+            return;
+        };
+        if prompt.is_empty() {
+            if let Err(e) = msg.channel_id.say(&ctx.http, "Please provide a message.").await {
+                tracing::warn!(error = %e, "Failed to send Discord message");
+            }
+            return;
+        }
+
+        let _ = msg.channel_id.broadcast_typing(&ctx.http).await;
+        let reply = match StreamingReply::message(&ctx.http, &msg).await {
+            Ok(reply) => reply,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to send Discord message");
+                return;
+            }
+        };
+        self.bot.respond(&ctx.http, msg.guild_id, msg.channel_id, prompt, reply).await;
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::Command(command) = interaction {
+            self.bot.handle_command(&ctx, command).await;
+        }
+    }
+}
+
+enum ReplyTarget {
+    Message { channel: ChannelId },
+    Interaction(Box<CommandInteraction>),
+}
+
+/// A reply that grows as content streams in, spilling over into additional
+/// messages once it exceeds the Discord length limit.
+struct StreamingReply {
+    target: ReplyTarget,
+    /// Messages sent so far with the content they currently show
+    sent: Vec<(MessageId, String)>,
+}
+
+impl StreamingReply {
+    /// Reply to a user message, starting with a placeholder
+    async fn message(http: &Http, msg: &serenity::all::Message) -> Result<Self, serenity::Error> {
+        let placeholder = "🤔 Thinking...".to_string();
+        let sent = msg
+            .channel_id
+            .send_message(http, CreateMessage::new().content(&placeholder).reference_message(msg))
+            .await?;
+        Ok(Self {
+            target: ReplyTarget::Message { channel: msg.channel_id },
+            sent: vec![(sent.id, placeholder)],
+        })
+    }
+
+    /// Reply to an already deferred slash command
+    fn interaction(command: CommandInteraction) -> Self {
+        Self {
+            target: ReplyTarget::Interaction(Box::new(command)),
+            sent: Vec::new(),
+        }
+    }
+
+    async fn render(&mut self, http: &Http, content: &str) {
+        for (index, chunk) in chunk_message(content, DISCORD_MESSAGE_LIMIT).into_iter().enumerate() {
+            let result = match self.sent.get(index) {
+                Some((_, shown)) if *shown == chunk => continue,
+                Some((id, _)) => self.edit(http, index, *id, &chunk).await,
+                None => self.create(http, index, &chunk).await,
+            };
+            match result {
+                Ok(id) if index < self.sent.len() => self.sent[index] = (id, chunk),
+                Ok(id) => self.sent.push((id, chunk)),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to update Discord reply");
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn create(&self, http: &Http, index: usize, chunk: &str) -> Result<MessageId, serenity::Error> {
+        let message = match &self.target {
+            ReplyTarget::Message { channel } => channel.say(http, chunk).await?,
+            ReplyTarget::Interaction(command) if index == 0 => {
+                command
+                    .edit_response(http, EditInteractionResponse::new().content(chunk))
+                    .await?
+            }
+            ReplyTarget::Interaction(command) => {
+                command
+                    .create_followup(http, CreateInteractionResponseFollowup::new().content(chunk))
+                    .await?
+            }
+        };
+        Ok(message.id)
+    }
+
+    async fn edit(&self, http: &Http, index: usize, id: MessageId, chunk: &str) -> Result<MessageId, serenity::Error> {
+        let message = match &self.target {
+            ReplyTarget::Message { channel } => {
+                channel
+                    .edit_message(http, id, EditMessage::new().content(chunk))
+                    .await?
+            }
+            ReplyTarget::Interaction(command) if index == 0 => {
+                command
+                    .edit_response(http, EditInteractionResponse::new().content(chunk))
+                    .await?
+            }
+            ReplyTarget::Interaction(command) => {
+                command
+                    .edit_followup(http, id, CreateInteractionResponseFollowup::new().content(chunk))
+                    .await?
+            }
+        };
+        Ok(message.id)
+    }
+}
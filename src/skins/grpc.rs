@@ -0,0 +1,290 @@
+//! gRPC interface (enabled with the `grpc` feature)
+//!
+//! Implements the `omniference.v1.Inference` service from
+//! `proto/omniference.proto` on top of an [`OmniferenceService`], so it shares
+//! provider registration and model resolution with the HTTP skins.
+//!
+//! ```rust,no_run
+//! # async fn example(service: omniference::OmniferenceService) -> Result<(), tonic::transport::Error> {
+//! use omniference::skins::grpc::GrpcService;
+//!
+//! tonic::transport::Server::builder()
+//!     .add_service(GrpcService::new(service).into_server())
+//!     .serve("0.0.0.0:50051".parse().unwrap())
+//!     .await
+//! # }
+//! ```
+
+use crate::error::EngineError;
+use crate::service::OmniferenceService;
+use crate::stream::StreamEvent;
+use crate::types::{ChatRequestIR, ContentPart, Message, Role, Sampling};
+use futures_util::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+use tonic::{Request, Response, Status};
+
+/// Types and service stubs generated from `proto/omniference.proto`
+pub mod proto {
+    tonic::include_proto!("omniference.v1");
+}
+
+use proto::inference_server::{Inference, InferenceServer};
+
+type InFlight = Arc<Mutex<HashMap<String, CancellationToken>>>;
+
+/// gRPC front-end for an [`OmniferenceService`]
+#[derive(Clone)]
+pub struct GrpcService {
+    service: OmniferenceService,
+    in_flight: InFlight,
+}
+
+impl GrpcService {
+    pub fn new(service: OmniferenceService) -> Self {
+        Self {
+            service,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Wrap in the generated tonic server for `Server::add_service`
+    pub fn into_server(self) -> InferenceServer<Self> {
+        InferenceServer::new(self)
+    }
+}
+
+/// Removes a call from the in-flight table and cancels it when its response
+/// stream is dropped, including when the client disconnects.
+struct InFlightGuard {
+    request_id: String,
+    cancel: CancellationToken,
+    in_flight: InFlight,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+        self.in_flight.lock().unwrap().remove(&self.request_id);
+    }
+}
+
+fn to_status(error: EngineError) -> Status {
+    match error {
+        EngineError::ModelNotFound(model) => Status::not_found(format!("model '{}' not found", model)),
+        EngineError::Timeout | EngineError::DeadlineExceeded(_) => Status::deadline_exceeded(error.to_string()),
+        EngineError::Cancelled => Status::cancelled(error.to_string()),
+        EngineError::Config(_) | EngineError::ProviderRegistration { .. } => {
+            Status::failed_precondition(error.to_string())
+        }
+        EngineError::Adapter(_) | EngineError::Mcp { .. } => Status::unavailable(error.to_string()),
+        EngineError::ContentPolicy { .. } | EngineError::AmbiguousModel { .. } => {
+            Status::invalid_argument(error.to_string())
+        }
+        EngineError::StructuredOutput { .. } => Status::internal(error.to_string()),
+        EngineError::Overloaded { .. } | EngineError::BudgetExceeded { .. } => {
+            Status::resource_exhausted(error.to_string())
+        }
+    }
+}
+
+fn parse_role(role: &str) -> Result<Role, String> {
+    match role {
+        "developer" => Ok(Role::Developer),
+        "system" => Ok(Role::System),
+        "user" => Ok(Role::User),
+        "assistant" => Ok(Role::Assistant),
+        "tool" => Ok(Role::Tool),
+        other => Err(format!("unknown role '{}'", other)),
+    }
+}
+
+fn to_message(message: proto::ChatMessage) -> Result<Message, String> {
+    let parts = message
+        .parts
+        .into_iter()
+        .filter_map(|part| match part.part? {
+            proto::content_part::Part::Text(text) => Some(ContentPart::Text(text)),
+            proto::content_part::Part::ImageUrl(url) => Some(ContentPart::ImageUrl { url, mime: None }),
+        })
+        .collect();
+    Ok(Message {
+        role: parse_role(&message.role)?,
+        parts,
+        name: message.name,
+    })
+}
+
+/// Map a [`StreamEvent`] to its protobuf form; refusals become `refusal`
+/// status updates, and events with no gRPC counterpart (system notes,
+/// annotations, OpenAI metadata, cost) are dropped.
+pub fn to_chat_event(request_id: &str, event: StreamEvent) -> Option<proto::ChatEvent> {
+    use proto::chat_event::Event;
+
+    let event = match event {
+        StreamEvent::TextDelta { content } => Event::TextDelta(content),
+        StreamEvent::ToolCallStart { id, name, args_json } => Event::ToolCallStart(proto::ToolCallStart {
+            id,
+            name,
+            arguments_json: args_json.to_string(),
+        }),
+        StreamEvent::ToolCallDelta { id, args_delta_json } => Event::ToolCallDelta(proto::ToolCallDelta {
+            id,
+            arguments_json_delta: match args_delta_json {
+                serde_json::Value::String(delta) => delta,
+                other => other.to_string(),
+            },
+        }),
+        StreamEvent::ToolCallEnd { id } => Event::ToolCallEnd(id),
+        StreamEvent::Status { state, detail } => Event::Status(proto::Status { state, detail }),
+        StreamEvent::Refusal { text } => Event::Status(proto::Status {
+            state: "refusal".to_string(),
+            detail: Some(text),
+        }),
+        StreamEvent::ToolExecutionStart { name, .. } => Event::Status(proto::Status {
+            state: "tool_running".to_string(),
+            detail: Some(name),
+        }),
+        StreamEvent::ToolExecutionEnd { name, is_error, .. } => Event::Status(proto::Status {
+            state: if is_error { "tool_failed" } else { "tool_completed" }.to_string(),
+            detail: Some(name),
+        }),
+        StreamEvent::Tokens { input, output } => Event::Usage(proto::Usage {
+            input_tokens: input,
+            output_tokens: output,
+        }),
+        StreamEvent::FinalMessage { content, .. } => Event::FinalMessage(proto::FinalMessage { content }),
+        StreamEvent::Error { code, message } => Event::Error(proto::Error { code, message }),
+        StreamEvent::Done => Event::Done(proto::Done {}),
+        StreamEvent::SystemNote { .. }
+        | StreamEvent::Annotation { .. }
+        | StreamEvent::OpenAIMetadata { .. }
+        | StreamEvent::Cost { .. } => return None,
+    };
+    Some(proto::ChatEvent {
+        request_id: request_id.to_string(),
+        event: Some(event),
+    })
+}
+
+#[tonic::async_trait]
+impl Inference for GrpcService {
+    type ChatStream = Pin<Box<dyn Stream<Item = Result<proto::ChatEvent, Status>> + Send>>;
+
+    async fn chat(&self, request: Request<proto::ChatRequest>) -> Result<Response<Self::ChatStream>, Status> {
+        let request = request.into_inner();
+        let model = self.service.resolve_model(&request.model).await.map_err(to_status)?;
+        let messages = request
+            .messages
+            .into_iter()
+            .map(to_message)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Status::invalid_argument)?;
+        if messages.is_empty() {
+            return Err(Status::invalid_argument("messages must not be empty"));
+        }
+
+        let request_id = if request.request_id.is_empty() {
+            uuid::Uuid::new_v4().to_string()
+        } else {
+            request.request_id
+        };
+        let mut metadata: std::collections::BTreeMap<String, String> = request.metadata.into_iter().collect();
+        metadata.insert("request_id".to_string(), request_id.clone());
+
+        let ir = ChatRequestIR {
+            model,
+            messages,
+            sampling: Sampling {
+                temperature: request.temperature,
+                top_p: request.top_p,
+                max_tokens: request.max_tokens,
+                stop: request.stop,
+                seed: request.seed,
+                ..Default::default()
+            },
+            stream: true,
+            metadata,
+            ..Default::default()
+        };
+
+        let cancel = CancellationToken::new();
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if in_flight.contains_key(&request_id) {
+                return Err(Status::already_exists(format!("request '{}' is already in flight", request_id)));
+            }
+            in_flight.insert(request_id.clone(), cancel.clone());
+        }
+        let guard = InFlightGuard {
+            request_id: request_id.clone(),
+            cancel: cancel.clone(),
+            in_flight: self.in_flight.clone(),
+        };
+
+        let mut events = self
+            .service
+            .router
+            .route_chat(ir, cancel.clone())
+            .await
+            .map_err(to_status)?;
+
+        let stream = async_stream::stream! {
+            let _guard = guard;
+            loop {
+                let event = tokio::select! {
+                    _ = cancel.cancelled() => {
+                        yield Err(Status::cancelled("request cancelled"));
+                        break;
+                    }
+                    event = events.next() => event,
+                };
+                let Some(event) = event else { break };
+                let finished = matches!(event, StreamEvent::Done | StreamEvent::Error { .. });
+                if let Some(event) = to_chat_event(&request_id, event) {
+                    yield Ok(event);
+                }
+                if finished {
+                    break;
+                }
+            }
+        };
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn list_models(
+        &self,
+        _request: Request<proto::ListModelsRequest>,
+    ) -> Result<Response<proto::ListModelsResponse>, Status> {
+        let mut models: Vec<proto::Model> = self
+            .service
+            .list_models()
+            .await
+            .into_iter()
+            .map(|m| proto::Model {
+                id: m.id,
+                name: m.name,
+                provider_name: m.provider_name,
+                supports_tools: m.capabilities.supports_tools,
+                supports_vision: m.capabilities.supports_vision,
+                context_length: m.capabilities.context_length,
+            })
+            .collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(Response::new(proto::ListModelsResponse { models }))
+    }
+
+    async fn cancel(&self, request: Request<proto::CancelRequest>) -> Result<Response<proto::CancelResponse>, Status> {
+        let request_id = request.into_inner().request_id;
+        let cancelled = match self.in_flight.lock().unwrap().get(&request_id) {
+            Some(cancel) => {
+                cancel.cancel();
+                true
+            }
+            None => false,
+        };
+        Ok(Response::new(proto::CancelResponse { cancelled }))
+    }
+}
@@ -0,0 +1,105 @@
+//! Keep-alive and heartbeat comments on SSE responses
+//!
+//! Every SSE response sends a keep-alive comment after `interval` without
+//! other output, so proxies don't close idle connections. With a heartbeat
+//! interval set, a stream whose adapter last reported a status (e.g. Ollama
+//! loading a model) also repeats that status every heartbeat interval as a
+//! `: heartbeat: <status>` comment until output resumes, so clients can tell
+//! a provider that is still working from a dead one.
+
+use crate::stream::StreamEvent;
+use axum::response::sse::KeepAlive;
+use futures_util::{Stream, StreamExt};
+use std::time::Duration;
+
+/// Keep-alive interval of axum's default
+pub const DEFAULT_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Status state of the repeated events; their detail is the original status
+pub const HEARTBEAT_STATE: &str = "heartbeat";
+
+/// Keep-alive and heartbeat settings of the server's SSE responses
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SseKeepAlive {
+    /// Quiet time after which a keep-alive comment is sent
+    pub interval: Duration,
+    /// Text of the keep-alive comment; empty sends a bare `:`
+    pub text: String,
+    /// How often a pending adapter status is repeated; never without one
+    pub heartbeat_interval: Option<Duration>,
+}
+
+impl Default for SseKeepAlive {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_KEEP_ALIVE_INTERVAL,
+            text: String::new(),
+            heartbeat_interval: None,
+        }
+    }
+}
+
+impl SseKeepAlive {
+    /// Keep-alive comments after `interval` of quiet, without heartbeats
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            ..Default::default()
+        }
+    }
+
+    /// Send `text` as the keep-alive comment
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = text.into();
+        self
+    }
+
+    /// Repeat a pending adapter status every `interval`
+    pub fn with_heartbeat(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = Some(interval);
+        self
+    }
+
+    /// The keep-alive of an SSE response
+    pub fn keep_alive(&self) -> KeepAlive {
+        KeepAlive::new()
+            .interval(self.interval)
+            .text(self.text.replace(['\r', '\n'], " "))
+    }
+
+    /// `stream` with heartbeat status events added while its last event is a
+    /// status and it is quiet; unchanged without a heartbeat interval
+    pub fn with_heartbeats<S>(&self, stream: S) -> std::pin::Pin<Box<dyn Stream<Item = StreamEvent> + Send>>
+    where
+        S: Stream<Item = StreamEvent> + Send + Unpin + 'static,
+    {
+        let Some(every) = self.heartbeat_interval else {
+            return Box::pin(stream);
+        };
+        Box::pin(async_stream::stream! {
+            let mut stream = stream;
+            let mut pending: Option<String> = None;
+            loop {
+                let next = match pending {
+                    Some(_) => tokio::time::timeout(every, stream.next()).await,
+                    None => Ok(stream.next().await),
+                };
+                match next {
+                    Ok(Some(event)) => {
+                        pending = match &event {
+                            StreamEvent::Status { state, detail: Some(detail) } => Some(format!("{}: {}", state, detail)),
+                            StreamEvent::Status { state, detail: None } => Some(state.clone()),
+                            _ => None,
+                        };
+                        yield event;
+                    }
+                    Ok(None) => break,
+                    Err(_) => yield StreamEvent::Status {
+                        state: HEARTBEAT_STATE.to_string(),
+                        detail: pending.clone(),
+                    },
+                }
+            }
+        })
+    }
+}
@@ -1,15 +1,61 @@
+#[cfg(feature = "server")]
 pub mod openai;
+#[cfg(feature = "server")]
 pub mod context;
+#[cfg(feature = "server")]
+pub mod resumable;
+#[cfg(feature = "server")]
+pub mod chunks;
+#[cfg(feature = "server")]
+pub mod keepalive;
+#[cfg(feature = "server")]
+pub mod semantic;
+pub mod bot;
+pub mod settings;
+#[cfg(feature = "server")]
+pub mod websocket;
+#[cfg(feature = "discord")]
+pub mod discord;
+#[cfg(feature = "telegram")]
+pub mod telegram;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 
-pub use openai::*;
-pub use context::*;
+#[cfg(feature = "server")]
+pub use context::{ModelResolver, SkinContext};
 
+#[cfg(feature = "server")]
 use axum::{response::Response, response::IntoResponse};
 
+/// Protocol skins the server can expose
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SkinKind {
+    /// `/api/openai/v1/*` (Responses API, WebSocket chat streaming and models)
+    OpenAI,
+    /// `/api/openai-compatible/v1/*` (Chat Completions and models)
+    OpenAICompatible,
+}
+
+impl SkinKind {
+    pub fn all() -> &'static [SkinKind] {
+        &[SkinKind::OpenAI, SkinKind::OpenAICompatible]
+    }
+}
+
 /// Trait for skin-specific error handling
+#[cfg(feature = "server")]
 pub trait SkinErrorHandler {
     /// Handle JSON deserialization errors for this skin
     fn handle_json_error(&self, error: serde_json::Error) -> Response;
+
+    /// Handle well-formed requests that fail a semantic check. Defaults to
+    /// [`handle_json_error`](Self::handle_json_error).
+    fn handle_invalid_field(&self, error: &semantic::FieldError) -> Response {
+        self.handle_json_error(serde_json::Error::io(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            error.to_string(),
+        )))
+    }
     
     /// Handle not found errors for this skin
     fn handle_not_found(&self) -> Response;
@@ -20,13 +66,79 @@ pub trait SkinErrorHandler {
     /// Handle model not found errors for this skin
     fn handle_model_not_found(&self, model_name: &str) -> Response;
 
+    /// Handle model not found errors, with the closest known model IDs and
+    /// the number of models available. Defaults to
+    /// [`handle_model_not_found`](Self::handle_model_not_found).
+    fn handle_model_not_found_with_suggestions(
+        &self,
+        model_name: &str,
+        _suggestions: &[String],
+        _available_models: usize,
+    ) -> Response {
+        self.handle_model_not_found(model_name)
+    }
+
+    /// Handle model names that match models of several providers. Defaults
+    /// to a 409 `ambiguous_model` error listing the candidates.
+    fn handle_ambiguous_model(&self, model_name: &str, candidates: &[String]) -> Response {
+        let message = format!(
+            "Model '{}' is ambiguous; specify one of: {}",
+            model_name,
+            candidates.join(", ")
+        );
+        json_error(axum::http::StatusCode::CONFLICT, "ambiguous_model", &message)
+    }
+
     /// Handle provider errors for this skin
     fn handle_provider_error(&self, code: String, message: String) -> Response;
+
+    /// Handle requests rejected by a content filter. Defaults to a 400
+    /// `content_policy_violation` error.
+    fn handle_content_policy(&self, categories: &[String]) -> Response {
+        let message = match categories {
+            [] => "Request rejected by the content policy".to_string(),
+            _ => format!("Request rejected by the content policy ({})", categories.join(", ")),
+        };
+        json_error(axum::http::StatusCode::BAD_REQUEST, "content_policy_violation", &message)
+    }
+
+    /// Handle requests rejected by a provider's concurrency limit. Defaults
+    /// to a 429 `server_overloaded` error.
+    fn handle_overloaded(&self, message: &str) -> Response {
+        json_error(axum::http::StatusCode::TOO_MANY_REQUESTS, "server_overloaded", message)
+    }
+
+    /// Handle requests that ran past the client's request timeout. Defaults
+    /// to a 504 `deadline_exceeded` error.
+    fn handle_timeout(&self, message: &str) -> Response {
+        json_error(axum::http::StatusCode::GATEWAY_TIMEOUT, "deadline_exceeded", message)
+    }
+
+    /// Handle requests of API keys that spent their budget. Defaults to a
+    /// 429 `budget_exceeded` error.
+    fn handle_budget_exceeded(&self, message: &str) -> Response {
+        json_error(axum::http::StatusCode::TOO_MANY_REQUESTS, "budget_exceeded", message)
+    }
+}
+
+/// A `{"error": {"message", "code"}}` response with `status`, as the
+/// default [`SkinErrorHandler`] methods send
+#[cfg(feature = "server")]
+fn json_error(status: axum::http::StatusCode, code: &str, message: &str) -> Response {
+    let error = serde_json::json!({
+        "error": {
+            "message": message,
+            "code": code
+        }
+    });
+    (status, axum::Json(error)).into_response()
 }
 
 /// OpenAI skin error handler
+#[cfg(feature = "server")]
 pub struct OpenAIErrorHandler;
 
+#[cfg(feature = "server")]
 impl SkinErrorHandler for OpenAIErrorHandler {
     fn handle_json_error(&self, error: serde_json::Error) -> Response {
         eprintln!("Error: {}", error);
@@ -59,6 +171,21 @@ impl SkinErrorHandler for OpenAIErrorHandler {
         ).into_response()
     }
 
+    fn handle_invalid_field(&self, error: &semantic::FieldError) -> Response {
+        let error = serde_json::json!({
+            "error": {
+                "message": error.message,
+                "type": "invalid_request_error",
+                "param": error.pointer,
+                "code": "invalid_value"
+            }
+        });
+        (
+            axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+            axum::Json(error)
+        ).into_response()
+    }
+
     fn handle_not_found(&self) -> Response {
         let error = serde_json::json!({
             "error": {
@@ -101,6 +228,57 @@ impl SkinErrorHandler for OpenAIErrorHandler {
         ).into_response()
     }
 
+    fn handle_model_not_found_with_suggestions(
+        &self,
+        model_name: &str,
+        suggestions: &[String],
+        available_models: usize,
+    ) -> Response {
+        let message = match suggestions {
+            [] => format!("Model '{}' not found", model_name),
+            _ => format!(
+                "Model '{}' not found. Did you mean {}?",
+                model_name,
+                suggestions.iter().map(|s| format!("'{}'", s)).collect::<Vec<_>>().join(", ")
+            ),
+        };
+        // Extra fields next to the standard ones, which OpenAI clients ignore
+        let error = serde_json::json!({
+            "error": {
+                "message": message,
+                "type": "invalid_request_error",
+                "param": "model",
+                "code": "model_not_found",
+                "suggestions": suggestions,
+                "available_models": available_models
+            }
+        });
+        (
+            axum::http::StatusCode::NOT_FOUND,
+            axum::Json(error)
+        ).into_response()
+    }
+
+    fn handle_ambiguous_model(&self, model_name: &str, candidates: &[String]) -> Response {
+        let error = serde_json::json!({
+            "error": {
+                "message": format!(
+                    "Model '{}' is ambiguous; specify one of: {}",
+                    model_name,
+                    candidates.join(", ")
+                ),
+                "type": "invalid_request_error",
+                "param": "model",
+                "code": "model_ambiguous",
+                "candidates": candidates
+            }
+        });
+        (
+            axum::http::StatusCode::CONFLICT,
+            axum::Json(error)
+        ).into_response()
+    }
+
     fn handle_provider_error(&self, code: String, message: String) -> Response {
         let error = serde_json::json!({
             "error": {
@@ -114,4 +292,65 @@ impl SkinErrorHandler for OpenAIErrorHandler {
             axum::Json(error)
         ).into_response()
     }
+
+    fn handle_content_policy(&self, categories: &[String]) -> Response {
+        let message = if categories.is_empty() {
+            "Your request was rejected by the content policy.".to_string()
+        } else {
+            format!("Your request was rejected by the content policy ({}).", categories.join(", "))
+        };
+        let error = serde_json::json!({
+            "error": {
+                "message": message,
+                "type": "invalid_request_error",
+                "code": "content_policy_violation"
+            }
+        });
+        (
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(error)
+        ).into_response()
+    }
+
+    fn handle_overloaded(&self, message: &str) -> Response {
+        let error = serde_json::json!({
+            "error": {
+                "message": message,
+                "type": "server_error",
+                "code": "server_overloaded"
+            }
+        });
+        (
+            axum::http::StatusCode::TOO_MANY_REQUESTS,
+            axum::Json(error)
+        ).into_response()
+    }
+
+    fn handle_timeout(&self, message: &str) -> Response {
+        let error = serde_json::json!({
+            "error": {
+                "message": message,
+                "type": "timeout_error",
+                "code": "deadline_exceeded"
+            }
+        });
+        (
+            axum::http::StatusCode::GATEWAY_TIMEOUT,
+            axum::Json(error)
+        ).into_response()
+    }
+
+    fn handle_budget_exceeded(&self, message: &str) -> Response {
+        let error = serde_json::json!({
+            "error": {
+                "message": message,
+                "type": "insufficient_quota",
+                "code": "budget_exceeded"
+            }
+        });
+        (
+            axum::http::StatusCode::TOO_MANY_REQUESTS,
+            axum::Json(error)
+        ).into_response()
+    }
 }
@@ -42,19 +42,22 @@
 //! ```
 
 use crate::skins::context::SkinContext;
-use crate::{stream::StreamEvent, types::*};
+use crate::skins::resumable::{ResumeError, SseChunk};
+use crate::{stream::StreamEvent, tenant::TenantScope, trace::RouteTrace, types::*};
 use crate::types::providers::openai::{
     ResponseInputItem, InputMessageRole, InputMessageContent,
-    ResponseInputContentPart,
+    ResponseInputContentPart, ResponseFormatTextConfig,
 };
 use axum::{extract::State, response::IntoResponse};
+use serde::Deserialize;
 
 use futures_util::StreamExt;
 
 use std::collections::BTreeMap;
 use uuid::Uuid;
 
-fn openai_to_chat_request(
+/// Convert a Chat Completions request for `model` into the IR
+pub fn openai_to_chat_request(
     req: OpenAIChatRequest,
     model: ModelRef,
 ) -> anyhow::Result<crate::ChatRequestIR> {
@@ -116,6 +119,28 @@ fn openai_to_chat_request(
                 }
             }
 
+            for call in msg.tool_calls.unwrap_or_default() {
+                parts.push(ContentPart::ToolCall {
+                    arguments: serde_json::from_str(&call.function.arguments)
+                        .unwrap_or(serde_json::Value::String(call.function.arguments)),
+                    id: call.id,
+                    name: call.function.name,
+                });
+            }
+            if let Some(call_id) = msg.tool_call_id {
+                // Tool output replaces the plain text part
+                let content = parts
+                    .iter()
+                    .filter_map(|p| match p {
+                        ContentPart::Text(t) => Some(t.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("");
+                parts.retain(|p| !matches!(p, ContentPart::Text(_)));
+                parts.push(ContentPart::ToolResult { call_id, content });
+            }
+
             Message {
                 role,
                 parts,
@@ -132,10 +157,10 @@ fn openai_to_chat_request(
     if let Some(seed) = req.seed {
         metadata.insert("seed".to_string(), seed.to_string());
     }
-    if let Some(rf) = req.response_format {
+    if let Some(rf) = &req.response_format {
         metadata.insert(
             "response_format".to_string(),
-            serde_json::to_string(&rf).unwrap_or_default(),
+            serde_json::to_string(rf).unwrap_or_default(),
         );
     }
     if let Some(ref lb) = req.logit_bias {
@@ -177,12 +202,16 @@ fn openai_to_chat_request(
     if let Some(store) = req.store {
         metadata.insert("store".to_string(), store.to_string());
     }
-    if let Some(req_metadata) = req.metadata {
-        metadata.insert(
-            "request_metadata".to_string(),
-            format!("{:?}", req_metadata),
-        );
+    let mut request_metadata = std::collections::HashMap::new();
+    for (key, value) in req.metadata.unwrap_or_default() {
+        match value {
+            serde_json::Value::String(value) => {
+                request_metadata.insert(key, value);
+            }
+            other => anyhow::bail!("metadata value for '{}' must be a string, got {}", key, other),
+        }
     }
+    crate::types::validate_request_metadata(&request_metadata).map_err(anyhow::Error::msg)?;
     if let Some(ref prediction) = req.prediction {
         metadata.insert("prediction".to_string(), format!("{:?}", prediction));
     }
@@ -192,12 +221,18 @@ fn openai_to_chat_request(
             serde_json::to_string(&service_tier).unwrap_or_default(),
         );
     }
-    if let Some(reasoning_effort) = req.reasoning_effort {
-        metadata.insert(
-            "reasoning_effort".to_string(),
-            format!("{:?}", reasoning_effort).to_lowercase(),
-        );
-    }
+    let reasoning = req.reasoning_effort.map(|effort| {
+        metadata.insert("reasoning_effort".to_string(), format!("{:?}", effort).to_lowercase());
+        ReasoningConfig {
+            effort: Some(match effort {
+                OpenAIReasoningEffort::Minimal => ReasoningEffort::Minimal,
+                OpenAIReasoningEffort::Low => ReasoningEffort::Low,
+                OpenAIReasoningEffort::Medium => ReasoningEffort::Medium,
+                OpenAIReasoningEffort::High => ReasoningEffort::High,
+            }),
+            summary: None,
+        }
+    });
     if let Some(verbosity) = req.verbosity {
         metadata.insert("verbosity".to_string(), verbosity);
     }
@@ -270,6 +305,16 @@ fn openai_to_chat_request(
         }
     };
 
+    // Local-backend sampling knobs arrive as unknown body fields
+    let mut extra = req.extra;
+    let min_p = take_extra_field(&mut extra, "min_p");
+    let typical_p = take_extra_field(&mut extra, "typical_p");
+    let repeat_penalty = take_extra_field(&mut extra, "repeat_penalty");
+    let repeat_last_n = take_extra_field(&mut extra, "repeat_last_n");
+    let mirostat = take_extra_field(&mut extra, "mirostat");
+    let mirostat_tau = take_extra_field(&mut extra, "mirostat_tau");
+    let mirostat_eta = take_extra_field(&mut extra, "mirostat_eta");
+
     Ok(crate::ChatRequestIR {
         model: model.clone(),
         messages,
@@ -311,9 +356,16 @@ fn openai_to_chat_request(
             logit_bias: req.logit_bias,
             logprobs: req.logprobs,
             top_logprobs: req.top_logprobs,
+            min_p,
+            typical_p,
+            repeat_penalty,
+            repeat_last_n,
+            mirostat,
+            mirostat_tau,
+            mirostat_eta,
         },
         stream: req.stream.unwrap_or(false),
-        response_format: None, // Would need conversion from OpenAIResponseFormat
+        response_format: req.response_format.map(chat_response_format).transpose()?,
         audio_output: req.audio.map(|audio| AudioOutput {
             voice: audio.voice.map(|v| format!("{:?}", v).to_lowercase()),
             format: audio.format.map(|f| format!("{:?}", f).to_lowercase()),
@@ -342,6 +394,14 @@ fn openai_to_chat_request(
         request_timeout: None,
         cache_key: req.prompt_cache_key,
         safety_identifier: req.safety_identifier,
+        provider_extensions: serde_json::Map::new(),
+        extra_body: extra,
+        reasoning,
+        store: req.store,
+        privacy: privacy_mode(req.store),
+        request_metadata,
+        background: false,
+        endpoint_overrides: None,
     })
 }
 
@@ -470,11 +530,9 @@ fn responses_to_chat_request(
             OpenAIInputMessage::AssistantMessage { content } => {
                 let mut parts = Vec::new();
                 for part in content {
-                    match part {
-                        OpenAIContentPartPayload::OutputText { text } => {
-                            parts.push(ContentPart::Text(text.clone()));
-                        }
-                        _ => {} // Skip other content types for now
+                    // Skip other content types for now
+                    if let OpenAIContentPartPayload::OutputText { text } = part {
+                        parts.push(ContentPart::Text(text.clone()));
                     }
                 }
                 
@@ -487,11 +545,9 @@ fn responses_to_chat_request(
             OpenAIInputMessage::SystemMessage { content } => {
                 let mut parts = Vec::new();
                 for part in content {
-                    match part {
-                        OpenAIContentPartPayload::InputText { text } => {
-                            parts.push(ContentPart::Text(text.clone()));
-                        }
-                        _ => {} // Skip other content types for now
+                    // Skip other content types for now
+                    if let OpenAIContentPartPayload::InputText { text } = part {
+                        parts.push(ContentPart::Text(text.clone()));
                     }
                 }
                 
@@ -504,11 +560,9 @@ fn responses_to_chat_request(
             OpenAIInputMessage::DeveloperMessage { content } => {
                 let mut parts = Vec::new();
                 for part in content {
-                    match part {
-                        OpenAIContentPartPayload::InputText { text } => {
-                            parts.push(ContentPart::Text(text.clone()));
-                        }
-                        _ => {} // Skip other content types for now
+                    // Skip other content types for now
+                    if let OpenAIContentPartPayload::InputText { text } = part {
+                        parts.push(ContentPart::Text(text.clone()));
                     }
                 }
                 
@@ -529,6 +583,23 @@ fn responses_to_chat_request(
             metadata.insert("reasoning_enabled".to_string(), enabled.to_string());
         }
     }
+    let request_metadata = req.metadata.clone().unwrap_or_default();
+    crate::types::validate_request_metadata(&request_metadata).map_err(anyhow::Error::msg)?;
+    let reasoning = match &req.reasoning {
+        Some(reasoning) if reasoning.effort.is_some() || reasoning.summary.is_some() => {
+            let effort = match reasoning.effort.as_deref() {
+                Some(effort) => Some(ReasoningEffort::parse(effort).ok_or_else(|| {
+                    anyhow::anyhow!("Invalid reasoning.effort '{}': expected minimal, low, medium or high", effort)
+                })?),
+                None => None,
+            };
+            Some(ReasoningConfig {
+                effort,
+                summary: reasoning.summary.clone(),
+            })
+        }
+        _ => None,
+    };
     if let Some(text) = &req.text {
         if let Some(verbosity) = &text.verbosity {
             metadata.insert("text_verbosity".to_string(), verbosity.clone());
@@ -548,7 +619,7 @@ fn responses_to_chat_request(
             ..Default::default()
         },
         stream: req.stream.unwrap_or(false),
-        response_format: None,
+        response_format: req.text.and_then(|text| text.format).map(text_response_format),
         audio_output: None,
         web_search_options: None,
         prediction: None,
@@ -556,22 +627,391 @@ fn responses_to_chat_request(
         request_timeout: None,
         cache_key: None,
         safety_identifier: None,
+        provider_extensions: serde_json::Map::new(),
+        extra_body: serde_json::Map::new(),
+        reasoning,
+        store: req.store,
+        privacy: privacy_mode(req.store),
+        request_metadata,
+        background: req.background.unwrap_or(false),
+        endpoint_overrides: None,
     })
 }
 
+/// The IR form of a Chat Completions `response_format`
+fn chat_response_format(format: OpenAIResponseFormat) -> anyhow::Result<ResponseFormat> {
+    match format {
+        OpenAIResponseFormat::JsonSchema { json_schema, .. } => Ok(ResponseFormat::JsonSchema {
+            name: json_schema.name,
+            description: json_schema.description,
+            schema: json_schema.schema,
+            strict: json_schema.strict,
+        }),
+        OpenAIResponseFormat::Simple { r#type } => match r#type.as_str() {
+            "text" => Ok(ResponseFormat::Text),
+            "json_object" => Ok(ResponseFormat::JsonObject),
+            "json_schema" => Err(anyhow::anyhow!("response_format of type 'json_schema' needs a 'json_schema' object")),
+            other => Err(anyhow::anyhow!(
+                "Invalid response_format type '{}': expected text, json_object or json_schema",
+                other
+            )),
+        },
+    }
+}
+
+/// The IR form of a Responses API `text.format`
+fn text_response_format(format: ResponseFormatTextConfig) -> ResponseFormat {
+    match format {
+        ResponseFormatTextConfig::Text => ResponseFormat::Text,
+        ResponseFormatTextConfig::JsonObject => ResponseFormat::JsonObject,
+        ResponseFormatTextConfig::JsonSchema { name, schema, description, strict } => {
+            ResponseFormat::JsonSchema { name, description, schema, strict }
+        }
+    }
+}
+
+/// Remove `key` from the unknown body fields when it parses as a `T`; a
+/// value of another type stays behind for the provider to judge
+fn take_extra_field<T: serde::de::DeserializeOwned>(
+    extra: &mut serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Option<T> {
+    let value = serde_json::from_value(extra.get(key)?.clone()).ok()?;
+    extra.remove(key);
+    Some(value)
+}
+
+/// Header putting a request in [`PrivacyMode::NoStore`] whatever its `store` field says
+pub const NO_STORE_HEADER: &str = "x-omniference-no-store";
+
+/// `store: false` requests must not be kept by the engine either
+fn privacy_mode(store: Option<bool>) -> PrivacyMode {
+    if store == Some(false) {
+        PrivacyMode::NoStore
+    } else {
+        PrivacyMode::Default
+    }
+}
+
+/// Whether [`NO_STORE_HEADER`] is set to anything but `false` or `0`
+fn no_store_requested(headers: &axum::http::HeaderMap) -> bool {
+    header_flag(headers, NO_STORE_HEADER)
+}
+
+/// Whether the header `name` is set to anything but `false` or `0`
+fn header_flag(headers: &axum::http::HeaderMap, name: &str) -> bool {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| !matches!(value.trim().to_ascii_lowercase().as_str(), "false" | "0"))
+}
+
+/// Header pinning a request to one provider or pool, e.g. `ollama-backup`;
+/// models that provider doesn't serve are not found
+pub const PROVIDER_OVERRIDE_HEADER: &str = "x-omniference-provider";
+
+/// Header asking for the request's route in response headers (see [`crate::trace`])
+pub const TRACE_HEADER: &str = "x-omniference-trace";
+
+/// Header carrying the client's own key for the upstream provider: always
+/// for [`AuthMode::Passthrough`](crate::types::AuthMode::Passthrough)
+/// providers, and for the others on servers built with
+/// `with_provider_key_header`
+pub const PROVIDER_KEY_HEADER: &str = "x-provider-api-key";
+
+/// Send the caller's own key upstream: the [`ProviderKey`](crate::types::ProviderKey)
+/// for passthrough providers, which fail with a 401 without one, and
+/// [`PROVIDER_KEY_HEADER`] for others when the server honors it. Returns the
+/// error response when the request can't go out
+fn apply_provider_key(
+    ctx: &SkinContext,
+    ir: &mut crate::ChatRequestIR,
+    headers: &axum::http::HeaderMap,
+    provider_key: Option<crate::types::ProviderKey>,
+) -> Option<axum::response::Response> {
+    let key = if ir.model.provider.auth_mode == crate::types::AuthMode::Passthrough {
+        match provider_key {
+            Some(crate::types::ProviderKey(key)) => key,
+            None => {
+                use axum::response::IntoResponse;
+                let error = serde_json::json!({
+                    "error": {
+                        "message": format!(
+                            "{} takes your own API key: send it as `Authorization: Bearer <key>` or in the {} header",
+                            ir.model.alias, PROVIDER_KEY_HEADER
+                        ),
+                        "type": "invalid_request_error",
+                        "code": "missing_provider_key"
+                    }
+                });
+                return Some((axum::http::StatusCode::UNAUTHORIZED, axum::Json(error)).into_response());
+            }
+        }
+    } else {
+        let header = headers
+            .get(PROVIDER_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|key| ctx.provider_key_header && !key.is_empty());
+        match header {
+            Some(key) => key.into(),
+            None => return None,
+        }
+    };
+    ir.endpoint_overrides.get_or_insert_with(Default::default).api_key = Some(key);
+    None
+}
+
+/// The tenant of the request's API key (see [`crate::tenant`])
+fn request_tenant(ctx: &SkinContext, api_key: &Option<axum::Extension<crate::budget::ApiKeyName>>) -> Option<String> {
+    ctx.tenant_of(api_key.as_ref().map(|axum::Extension(crate::budget::ApiKeyName(name))| name.as_str()))
+}
+
+/// Mark `ir` as a request of `tenant`, so routing policies keep to its models
+fn apply_tenant(ir: &mut crate::ChatRequestIR, tenant: Option<&str>) {
+    if let Some(tenant) = tenant {
+        ir.metadata.insert(crate::tenant::TENANT_METADATA.to_string(), tenant.to_string());
+    }
+}
+
+/// Resolve `model` among `tenant`'s models, on the provider
+/// [`PROVIDER_OVERRIDE_HEADER`] names when the server honors it
+async fn resolve_requested_model(
+    ctx: &SkinContext,
+    model: &str,
+    headers: &axum::http::HeaderMap,
+    tenant: Option<&str>,
+) -> Result<crate::types::ModelRef, axum::response::Response> {
+    let provider = headers
+        .get(PROVIDER_OVERRIDE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|provider| ctx.debug_headers && !provider.is_empty());
+    match provider {
+        Some(provider) => ctx.resolve_model_on(model, provider, tenant).await,
+        None => ctx.resolve_model(model, tenant).await,
+    }
+}
+
+/// Mark `ir` for tracing when [`TRACE_HEADER`] asks for it and the server
+/// honors it, returning the trace to collect its route into
+fn start_trace(ctx: &SkinContext, ir: &mut crate::ChatRequestIR, headers: &axum::http::HeaderMap) -> Option<RouteTrace> {
+    if !ctx.debug_headers || !header_flag(headers, TRACE_HEADER) {
+        return None;
+    }
+    ir.metadata.insert(crate::trace::TRACE_METADATA.to_string(), "true".to_string());
+    Some(RouteTrace::for_model(&ir.model))
+}
+
+/// `response` with the headers of `trace`, if the request is traced
+fn with_trace_headers(mut response: axum::response::Response, trace: Option<&RouteTrace>) -> axum::response::Response {
+    for (name, value) in trace.map(RouteTrace::headers).unwrap_or_default() {
+        if let Ok(value) = axum::http::HeaderValue::from_str(&value) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    response
+}
+
+/// Record the events `stream` starts with into `trace`, when the request is
+/// traced, so a streamed response can carry its headers
+async fn observe_leading<S>(stream: S, trace: Option<&mut RouteTrace>) -> Box<dyn futures_util::Stream<Item = StreamEvent> + Send + Unpin>
+where
+    S: futures_util::Stream<Item = StreamEvent> + Send + Unpin + 'static,
+{
+    match trace {
+        Some(trace) => Box::new(crate::trace::observe_leading(stream, trace).await),
+        None => Box::new(stream),
+    }
+}
+
+/// Header keying retries of the same request; identical requests in flight
+/// with the same key share one generation (see [`crate::dedup`])
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Record the request's idempotency key, if it has one
+fn apply_idempotency_key(ir: &mut crate::ChatRequestIR, headers: &axum::http::HeaderMap) {
+    if let Some(key) = headers.get(IDEMPOTENCY_KEY_HEADER).and_then(|value| value.to_str().ok()) {
+        ir.metadata.insert(crate::dedup::IDEMPOTENCY_KEY_METADATA.to_string(), key.trim().to_string());
+    }
+}
+
+/// Send the OpenAI organization and project the client names, if any,
+/// instead of the provider's own (see [`crate::types::ORGANIZATION_HEADER`]);
+/// for servers built `with_organization_headers`
+fn apply_organization_headers(ir: &mut crate::ChatRequestIR, headers: &axum::http::HeaderMap) {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    let organization = header(crate::types::ORGANIZATION_HEADER);
+    let project = header(crate::types::PROJECT_HEADER);
+    if organization.is_some() || project.is_some() {
+        let overrides = ir.endpoint_overrides.get_or_insert_with(Default::default);
+        overrides.organization = organization;
+        overrides.project = project;
+    }
+}
+
+/// Put `ir` in no-store mode, for the provider as well, when `headers` ask for it
+fn apply_no_store_header(ir: &mut crate::ChatRequestIR, headers: &axum::http::HeaderMap) {
+    if no_store_requested(headers) {
+        ir.privacy = PrivacyMode::NoStore;
+        ir.store = Some(false);
+    }
+}
+
+/// Build a Chat Completions stream chunk carrying a content delta or a finish reason
+pub(crate) fn stream_chunk(
+    request_id: &str,
+    model: &str,
+    content: Option<String>,
+    finish_reason: Option<String>,
+) -> OpenAIStreamChunk {
+    OpenAIStreamChunk {
+        id: request_id.to_string(),
+        object: "response.chunk".to_string(),
+        created: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        model: model.to_string(),
+        choices: vec![OpenAIStreamChoice {
+            index: 0,
+            delta: OpenAIDelta {
+                role: None,
+                content,
+                tool_calls: None,
+                refusal: None,
+                annotations: Vec::new(),
+            },
+            finish_reason,
+        }],
+    }
+}
+
+/// Build a Chat Completions stream chunk carrying refusal text
+pub(crate) fn refusal_chunk(request_id: &str, model: &str, text: String) -> OpenAIStreamChunk {
+    let mut chunk = stream_chunk(request_id, model, None, None);
+    chunk.choices[0].delta.refusal = Some(text);
+    chunk
+}
+
+/// Build a Chat Completions stream chunk carrying one annotation, e.g. a citation
+pub(crate) fn annotation_chunk(request_id: &str, model: &str, annotation: serde_json::Value) -> OpenAIStreamChunk {
+    let mut chunk = stream_chunk(request_id, model, None, None);
+    chunk.choices[0].delta.annotations.push(annotation);
+    chunk
+}
+
+/// The client's time budget from `x-request-timeout-ms` (milliseconds) or
+/// `Request-Timeout` (seconds), whichever is shorter
+fn request_timeout(headers: &axum::http::HeaderMap) -> Option<std::time::Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(str::trim);
+    let millis = header("x-request-timeout-ms")
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(std::time::Duration::from_millis);
+    let seconds = header("request-timeout")
+        .and_then(|value| value.parse::<f64>().ok())
+        .and_then(|seconds| std::time::Duration::try_from_secs_f64(seconds).ok());
+    match (millis, seconds) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+    .filter(|timeout| !timeout.is_zero())
+}
+
+/// Response for an in-stream error of a non-streamed request
+fn stream_error(ctx: &SkinContext, code: String, message: String) -> axum::response::Response {
+    route_error(ctx, crate::error::EngineError::from_stream_error(code, message))
+}
+
+/// Response for a request the router refused to route
+fn route_error(ctx: &SkinContext, error: crate::error::EngineError) -> axum::response::Response {
+    match error {
+        crate::error::EngineError::DeadlineExceeded(message) => ctx.error_handler.handle_timeout(&message),
+        crate::error::EngineError::ContentPolicy { categories } => ctx.error_handler.handle_content_policy(&categories),
+        e @ crate::error::EngineError::Overloaded { .. } => ctx.error_handler.handle_overloaded(&e.to_string()),
+        e @ crate::error::EngineError::BudgetExceeded { .. } => ctx.error_handler.handle_budget_exceeded(&e.to_string()),
+        crate::error::EngineError::Adapter(crate::adapter::AdapterError::Auth(message)) => {
+            ctx.error_handler.handle_provider_error("authentication_error".to_string(), message)
+        }
+        // The upstream's own code and message, e.g. an organization mismatch
+        crate::error::EngineError::Adapter(crate::adapter::AdapterError::Provider { code, message }) => {
+            ctx.error_handler.handle_provider_error(code, message)
+        }
+        other => ctx.error_handler.handle_json_error(serde_json::Error::io(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            other.to_string(),
+        ))),
+    }
+}
+
+/// Run `handler` unless the caller's API key spent its budget (see
+/// [`crate::budget`]), flagging the response when the key is past its
+/// warning threshold
+async fn within_budget<F>(
+    ctx: &SkinContext,
+    api_key: Option<axum::Extension<crate::budget::ApiKeyName>>,
+    handler: impl FnOnce(Option<String>) -> F,
+) -> axum::response::Response
+where
+    F: std::future::Future<Output = axum::response::Response>,
+{
+    let api_key = api_key.map(|axum::Extension(crate::budget::ApiKeyName(name))| name);
+    let status = match (ctx.router.budgets(), &api_key) {
+        (Some(budgets), Some(key)) => match budgets.check(key) {
+            Ok(status) => status,
+            Err(e) => return ctx.error_handler.handle_budget_exceeded(&e.to_string()),
+        },
+        _ => None,
+    };
+    let warning = status.filter(|status| status.state == crate::budget::BudgetState::Warning).and_then(|status| {
+        axum::http::HeaderValue::from_str(&format!(
+            "spent_usd={:.4}; limit_usd={:.2}; resets_at={}",
+            status.spent_usd, status.limit_usd, status.resets_at
+        ))
+        .ok()
+    });
+    let mut response = handler(api_key).await;
+    if let Some(warning) = warning {
+        response.headers_mut().insert(crate::budget::BUDGET_WARNING_HEADER, warning);
+    }
+    response
+}
+
 pub async fn handle_chat(
     State(ctx): State<SkinContext>,
+    headers: axum::http::HeaderMap,
+    api_key: Option<axum::Extension<crate::budget::ApiKeyName>>,
+    provider_key: Option<axum::Extension<crate::types::ProviderKey>>,
     crate::server::SkinAwareJson(req): crate::server::SkinAwareJson<OpenAIChatRequest>,
 ) -> axum::response::Response {
-    let model_ref = match ctx.resolve_model_ref(&req.model).await {
-        Some(model_ref) => model_ref,
-        None => {
-            return ctx.error_handler.handle_model_not_found(&req.model);
-        }
+    let provider_key = provider_key.map(|axum::Extension(key)| key);
+    within_budget(&ctx.clone(), api_key, |api_key| chat_completion(ctx, headers, api_key, provider_key, req)).await
+}
+
+async fn chat_completion(
+    ctx: SkinContext,
+    headers: axum::http::HeaderMap,
+    api_key: Option<String>,
+    provider_key: Option<crate::types::ProviderKey>,
+    req: OpenAIChatRequest,
+) -> axum::response::Response {
+    if let Err(error) = crate::skins::semantic::check_chat_request(&req) {
+        return ctx.error_handler.handle_invalid_field(&error);
+    }
+    let tenant = ctx.tenant_of(api_key.as_deref());
+    let model_ref = match resolve_requested_model(&ctx, &req.model, &headers, tenant.as_deref()).await {
+        Ok(model_ref) => model_ref,
+        Err(response) => return response,
     };
 
-    let model_alias = model_ref.alias.clone();
-    let ir = match openai_to_chat_request(req, model_ref) {
+    let mut ir = match openai_to_chat_request(req, model_ref) {
         Ok(ir) => ir,
         Err(e) => {
             return ctx.error_handler.handle_json_error(serde_json::Error::io(
@@ -579,7 +1019,31 @@ pub async fn handle_chat(
             ));
         }
     };
+    apply_tenant(&mut ir, tenant.as_deref());
+    if let Err(response) = ctx.apply_routing_policy(&mut ir).await {
+        return response;
+    }
+    let model_alias = ir.model.alias.clone();
+    if let Some(api_key) = &api_key {
+        ir.metadata.insert(crate::audit::API_KEY_NAME_METADATA.to_string(), api_key.clone());
+    }
+    if !ctx.extra_body_passthrough {
+        // The local sampling fields came in as unknown body fields too
+        ir.extra_body.clear();
+        ir.sampling.clear_local_fields();
+    }
 
+    ir.request_timeout = request_timeout(&headers).or(ir.request_timeout);
+    apply_no_store_header(&mut ir, &headers);
+    apply_idempotency_key(&mut ir, &headers);
+    if ctx.organization_headers {
+        apply_organization_headers(&mut ir, &headers);
+    }
+    if let Some(response) = apply_provider_key(&ctx, &mut ir, &headers, provider_key) {
+        return response;
+    }
+    let mut trace = start_trace(&ctx, &mut ir, &headers);
+    let privacy = ir.privacy;
     let request_id = ir.metadata.get("request_id").unwrap().clone();
 
     // Determine requested n from metadata
@@ -601,112 +1065,92 @@ pub async fn handle_chat(
             return (axum::http::StatusCode::BAD_REQUEST, axum::Json(error)).into_response();
         }
         let cancel = (*ctx.cancel_tokens).clone();
-        let stream = match ctx.router.route_chat(ir, cancel).await {
+        let stream = match ctx.route_chat(ir, cancel).await {
             Ok(stream) => stream,
-            Err(e) => {
-                return ctx.error_handler.handle_json_error(serde_json::Error::io(
-                    std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()),
-                ));
-            }
+            Err(e) => return with_trace_headers(route_error(&ctx, e), trace.as_ref()),
         };
+        let stream = observe_leading(stream, trace.as_mut()).await;
+        let stream = ctx.sse_keep_alive.with_heartbeats(stream);
 
-        let sse_stream = stream.map(move |ev| {
-            let chunk = match ev {
-                StreamEvent::TextDelta { content } => OpenAIStreamChunk {
-                    id: request_id.clone(),
-                    object: "response.chunk".to_string(),
-                    created: std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs(),
-                    model: model_alias.clone(),
-                    choices: vec![OpenAIStreamChoice {
-                        index: 0,
-                        delta: OpenAIDelta {
-                            role: None,
-                            content: Some(content),
-                            tool_calls: None,
-                        },
-                        finish_reason: None,
-                    }],
-                },
-                StreamEvent::Done => OpenAIStreamChunk {
-                    id: request_id.clone(),
-                    object: "response.chunk".to_string(),
-                    created: std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs(),
-                    model: model_alias.clone(),
-                    choices: vec![OpenAIStreamChoice {
-                        index: 0,
-                        delta: OpenAIDelta {
-                            role: None,
-                            content: None,
-                            tool_calls: None,
-                        },
-                        finish_reason: Some("stop".to_string()),
-                    }],
-                },
-                StreamEvent::Error { code, message } => {
-                    tracing::error!(%code, %message, "Stream error");
-                    return Err(axum::Error::new(std::io::Error::other(format!(
-                        "Stream error: {}",
-                        message
-                    ))));
-                }
-                _ => return Ok(axum::response::sse::Event::default().data("")),
-            };
+        // Resumable streams outlive the connection and number their chunks;
+        // no-store requests aren't buffered
+        if let Some(streams) = ctx.resumable_streams.as_ref().filter(|_| !privacy.is_no_store()) {
+            let mut encoder = crate::skins::chunks::ChunkEncoder::new(&request_id, &model_alias);
+            let chunks = stream.map(move |ev| encoder.encode(ev));
+            let response = axum::response::Sse::new(streams.start(request_id, api_key, chunks))
+                .keep_alive(ctx.sse_keep_alive.keep_alive())
+                .into_response();
+            return with_trace_headers(response, trace.as_ref());
+        }
 
-            Ok(axum::response::sse::Event::default().data(serde_json::to_string(&chunk).unwrap()))
-        });
+        let mut encoder = crate::skins::chunks::ChunkEncoder::new(&request_id, &model_alias);
+        let sse_stream = stream.map(move |ev| encoder.event(ev));
 
-        axum::response::Sse::new(sse_stream)
-            .keep_alive(axum::response::sse::KeepAlive::new())
-            .into_response()
+        let response = axum::response::Sse::new(sse_stream)
+            .keep_alive(ctx.sse_keep_alive.keep_alive())
+            .into_response();
+        with_trace_headers(response, trace.as_ref())
     } else {
-        // Helper to run one non-streamed completion and capture content + usage
+        // What one non-streamed completion produced
+        #[derive(Default)]
+        struct Completion {
+            content: String,
+            refusal: Option<String>,
+            annotations: Vec<serde_json::Value>,
+            usage: Option<(u32, u32)>,
+            /// Set for a reply cut off at its output limit
+            truncated: bool,
+            /// The provider's, if it sent them
+            system_fingerprint: Option<String>,
+            service_tier: Option<String>,
+        }
+
+        // Helper to run one non-streamed completion and capture its reply + usage
         async fn run_once(
             ctx: &SkinContext,
             ir: crate::ChatRequestIR,
-        ) -> Result<(String, Option<(u32, u32)>), axum::response::Response> {
+            mut trace: Option<&mut RouteTrace>,
+        ) -> Result<Completion, axum::response::Response> {
             let cancel = (*ctx.cancel_tokens).clone();
-            let mut stream = ctx.router.route_chat(ir, cancel).await.map_err(|e| {
-                ctx.error_handler
-                    .handle_json_error(serde_json::Error::io(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        e.to_string(),
-                    )))
-            })?;
-
-            let mut final_content = String::new();
-            let mut usage: Option<(u32, u32)> = None;
+            let mut stream = ctx.route_chat(ir, cancel).await.map_err(|e| route_error(ctx, e))?;
+
+            let mut completion = Completion::default();
             while let Some(ev) = stream.next().await {
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.observe(&ev);
+                }
                 match ev {
-                    StreamEvent::TextDelta { content } => final_content.push_str(&content),
-                    StreamEvent::Tokens { input, output } => usage = Some((input, output)),
-                    StreamEvent::FinalMessage { content, .. } => {
-                        final_content = content;
+                    StreamEvent::TextDelta { content } => completion.content.push_str(&content),
+                    StreamEvent::Refusal { text } => {
+                        completion.refusal.get_or_insert_with(String::new).push_str(&text);
+                    }
+                    StreamEvent::Annotation { annotation } => completion.annotations.push(annotation),
+                    StreamEvent::Tokens { input, output } => completion.usage = Some((input, output)),
+                    StreamEvent::OpenAIMetadata { system_fingerprint, service_tier, .. } => {
+                        completion.system_fingerprint = system_fingerprint;
+                        completion.service_tier = service_tier;
+                    }
+                    StreamEvent::FinalMessage { content, finish_reason, .. } => {
+                        completion.content = content;
+                        completion.truncated = finish_reason.as_deref() == Some("length");
                         break;
                     }
                     StreamEvent::Done => break,
                     StreamEvent::Error { code, message } => {
                         tracing::error!(%code, %message, "Non-stream error");
-                        return Err(ctx.error_handler.handle_json_error(serde_json::Error::io(
-                            std::io::Error::new(std::io::ErrorKind::InvalidData, message),
-                        )));
+                        return Err(stream_error(ctx, code, message));
                     }
                     _ => {}
                 }
             }
-            Ok((final_content, usage))
+            Ok(completion)
         }
 
         let mut choices: Vec<OpenAIChoice> = Vec::new();
         let mut agg_input = 0u32;
         let mut agg_output = 0u32;
-        let system_fingerprint = None;
-        let service_tier = None;
+        let mut system_fingerprint = None;
+        let mut service_tier = None;
         let prompt_tokens_details = None;
         let completion_tokens_details = None;
 
@@ -715,30 +1159,40 @@ pub async fn handle_chat(
             let mut ir_i = ir.clone();
             ir_i.metadata
                 .insert("request_id".to_string(), Uuid::new_v4().to_string());
-            match run_once(&ctx, ir_i).await {
-                Ok((content, usage)) => {
-                    if let Some((inp, out)) = usage {
+            match run_once(&ctx, ir_i, trace.as_mut()).await {
+                Ok(completion) => {
+                    if let Some((inp, out)) = completion.usage {
                         agg_input += inp;
                         agg_output += out;
                     }
+                    system_fingerprint = system_fingerprint.or(completion.system_fingerprint);
+                    service_tier = service_tier.or(completion.service_tier);
+                    // A refused request has its refusal instead of content,
+                    // and still finishes with `stop`
+                    let content = match completion.refusal {
+                        Some(_) => None,
+                        None => Some(completion.content),
+                    };
+                    let finish_reason = if completion.truncated { "length" } else { "stop" };
                     choices.push(OpenAIChoice {
                         index: i,
                         message: Some(OpenAIResponseMessage {
                             role: "assistant".to_string(),
-                            content: Some(content),
+                            content,
                             tool_calls: None,
-                            refusal: None,
-                            annotations: Vec::new(),
+                            refusal: completion.refusal,
+                            annotations: completion.annotations,
                         }),
                         delta: None,
-                        finish_reason: Some("stop".to_string()),
+                        finish_reason: Some(finish_reason.to_string()),
                         logprobs: None,
                     });
                 }
-                Err(resp) => return resp,
+                Err(resp) => return with_trace_headers(resp, trace.as_ref()),
             }
         }
 
+        let (system_fingerprint, service_tier) = ctx.system_fingerprint.resolve(system_fingerprint, service_tier);
         let response = OpenAIChatResponse {
             id: request_id,
             object: "chat.completion".to_string(),
@@ -769,11 +1223,137 @@ pub async fn handle_chat(
             } else {
                 None
             },
-            service_tier: service_tier.or(Some("default".to_string())),
-            system_fingerprint: system_fingerprint.or_else(|| Some(generate_system_fingerprint())),
+            service_tier,
+            system_fingerprint,
         };
 
-        axum::Json(response).into_response()
+        with_trace_headers(axum::Json(response).into_response(), trace.as_ref())
+    }
+}
+
+/// The SSE message for one event of a Chat Completions stream
+pub fn sse_chunk(event: StreamEvent, request_id: &str, model: &str) -> SseChunk {
+    let chunk = match event {
+        StreamEvent::TextDelta { content } => stream_chunk(request_id, model, Some(content), None),
+        StreamEvent::Refusal { text } => refusal_chunk(request_id, model, text),
+        StreamEvent::Annotation { annotation } => annotation_chunk(request_id, model, annotation),
+        StreamEvent::Done => stream_chunk(request_id, model, None, Some("stop".to_string())),
+        event => return contentless_chunk(event),
+    };
+    SseChunk::Data(serde_json::to_string(&chunk).unwrap())
+}
+
+/// The SSE message for an event other than a text delta or the end
+pub(crate) fn contentless_chunk(event: StreamEvent) -> SseChunk {
+    match event {
+        StreamEvent::Error { code, message } => {
+            tracing::error!(%code, %message, "Stream error");
+            SseChunk::Error(message)
+        }
+        // Progress updates carry no content; surface them as SSE comments
+        StreamEvent::Status { state, detail } => SseChunk::Comment(match detail {
+            Some(detail) => format!("{}: {}", state, detail),
+            None => state,
+        }),
+        // Tools the gateway runs itself get events of their own type, which
+        // clients reading only chunks skip
+        StreamEvent::ToolExecutionStart { .. } | StreamEvent::ToolExecutionEnd { .. } => {
+            let data = serde_json::to_value(&event).unwrap_or_default();
+            SseChunk::Named {
+                event: data["type"].as_str().unwrap_or_default().to_string(),
+                data: data.to_string(),
+            }
+        }
+        _ => SseChunk::Data(String::new()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ResumeQuery {
+    /// First chunk sequence number to replay
+    pub from: Option<u64>,
+}
+
+/// Reconnect to a resumable Chat Completions stream (see
+/// [`crate::skins::resumable`]): replay its chunks from `from`, or after the
+/// `Last-Event-ID` header, and continue live
+pub async fn handle_chat_stream_resume(
+    State(ctx): State<SkinContext>,
+    axum::extract::Path(request_id): axum::extract::Path<String>,
+    axum::extract::Query(query): axum::extract::Query<ResumeQuery>,
+    headers: axum::http::HeaderMap,
+    api_key: Option<axum::Extension<crate::budget::ApiKeyName>>,
+) -> axum::response::Response {
+    let Some(streams) = &ctx.resumable_streams else {
+        return ctx.error_handler.handle_not_found();
+    };
+    let from = query.from.unwrap_or_else(|| {
+        headers
+            .get("last-event-id")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map_or(0, |last| last + 1)
+    });
+    let owner = api_key.map(|axum::Extension(crate::budget::ApiKeyName(name))| name);
+    let error = |status: axum::http::StatusCode, code: &str, message: String| {
+        let error = serde_json::json!({
+            "error": {
+                "message": message,
+                "type": "invalid_request_error",
+                "code": code
+            }
+        });
+        (status, axum::Json(error)).into_response()
+    };
+    match streams.resume(&request_id, owner.as_deref(), from) {
+        Ok(events) => axum::response::Sse::new(events)
+            .keep_alive(ctx.sse_keep_alive.keep_alive())
+            .into_response(),
+        Err(ResumeError::NotFound) => error(
+            axum::http::StatusCode::NOT_FOUND,
+            "stream_not_found",
+            format!("No resumable stream with id '{}'", request_id),
+        ),
+        Err(ResumeError::Evicted { first_available }) => error(
+            axum::http::StatusCode::GONE,
+            "stream_chunks_evicted",
+            format!(
+                "Chunks before {} are no longer buffered for stream '{}'",
+                first_available, request_id
+            ),
+        ),
+    }
+}
+
+/// What the OpenAI skin reports as a response's `system_fingerprint` and
+/// `service_tier`. Clients compare fingerprints to notice backend changes, so
+/// only the provider's or one naming the gateway build mean anything.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SystemFingerprint {
+    /// The provider's fingerprint and service tier (see
+    /// [`StreamEvent::OpenAIMetadata`]), omitted when it sends none
+    #[default]
+    Passthrough,
+    /// This fingerprint on every response, e.g. naming the gateway build;
+    /// the service tier is the provider's
+    Static(String),
+    /// The provider's, or a random `fp_` fingerprint per response and
+    /// service tier `default`, as older versions always reported
+    Random,
+}
+
+impl SystemFingerprint {
+    /// The fingerprint and service tier of a response the provider sent
+    /// `fingerprint` and `service_tier` for
+    fn resolve(&self, fingerprint: Option<String>, service_tier: Option<String>) -> (Option<String>, Option<String>) {
+        match self {
+            SystemFingerprint::Passthrough => (fingerprint, service_tier),
+            SystemFingerprint::Static(fingerprint) => (Some(fingerprint.clone()), service_tier),
+            SystemFingerprint::Random => (
+                fingerprint.or_else(|| Some(generate_system_fingerprint())),
+                service_tier.or_else(|| Some("default".to_string())),
+            ),
+        }
     }
 }
 
@@ -789,20 +1369,38 @@ fn generate_system_fingerprint() -> String {
 
 pub async fn handle_responses(
     State(ctx): State<SkinContext>,
+    headers: axum::http::HeaderMap,
+    api_key: Option<axum::Extension<crate::budget::ApiKeyName>>,
+    provider_key: Option<axum::Extension<crate::types::ProviderKey>>,
     crate::server::SkinAwareJson(req): crate::server::SkinAwareJson<OpenAIResponsesRequestPayload>,
 ) -> axum::response::Response {
-    eprintln!("Handling responses request: {:?}", req);
+    let provider_key = provider_key.map(|axum::Extension(key)| key);
+    within_budget(&ctx.clone(), api_key, |api_key| create_response(ctx, headers, api_key, provider_key, req)).await
+}
+
+async fn create_response(
+    ctx: SkinContext,
+    headers: axum::http::HeaderMap,
+    api_key: Option<String>,
+    provider_key: Option<crate::types::ProviderKey>,
+    req: OpenAIResponsesRequestPayload,
+) -> axum::response::Response {
+    let no_store = no_store_requested(&headers) || req.store == Some(false);
+    if !no_store {
+        eprintln!("Handling responses request: {:?}", req);
+    }
     let max_output_tokens = req.max_output_tokens;
-    let model_id = req.model.as_deref().unwrap_or("gpt-4");
-    let model_ref = match ctx.resolve_model_ref(model_id).await {
-        Some(model_ref) => model_ref,
-        None => {
-            return ctx.error_handler.handle_model_not_found(model_id);
-        }
+    let model_id = req.model.as_deref().unwrap_or_default();
+    let tenant = ctx.tenant_of(api_key.as_deref());
+    let model_ref = match resolve_requested_model(&ctx, model_id, &headers, tenant.as_deref()).await {
+        Ok(model_ref) => model_ref,
+        Err(response) => return response,
     };
 
-    let model_alias = model_ref.alias.clone();
-    let ir = match responses_to_chat_request(req, model_ref) {
+    let previous_response_id = req.previous_response_id.clone();
+    let store = !no_store;
+    let user = req.user.clone();
+    let mut ir = match responses_to_chat_request(req, model_ref) {
         Ok(ir) => ir,
         Err(e) => {
             eprintln!("Error: {}", e);
@@ -812,19 +1410,79 @@ pub async fn handle_responses(
         }
     };
 
+    ir.request_timeout = request_timeout(&headers).or(ir.request_timeout);
+    apply_no_store_header(&mut ir, &headers);
+    apply_idempotency_key(&mut ir, &headers);
+    if ctx.organization_headers {
+        apply_organization_headers(&mut ir, &headers);
+    }
+    if let Some(response) = apply_provider_key(&ctx, &mut ir, &headers, provider_key) {
+        return response;
+    }
+    apply_tenant(&mut ir, tenant.as_deref());
+
+    // Continue the stored conversation the previous response ended
+    if let Some(previous) = &previous_response_id {
+        match ctx.conversations.get(previous).await {
+            Ok(Some(conversation)) => {
+                let input = std::mem::replace(&mut ir.messages, conversation.messages);
+                ir.messages.extend(input);
+            }
+            Ok(None) => return previous_response_not_found(previous),
+            Err(e) => return ctx.error_handler.handle_provider_error("store_error".to_string(), e.to_string()),
+        }
+    }
+    if let Err(response) = ctx.apply_routing_policy(&mut ir).await {
+        return response;
+    }
+    let model_alias = ir.model.alias.clone();
+    if let Some(api_key) = api_key {
+        ir.metadata.insert(crate::audit::API_KEY_NAME_METADATA.to_string(), api_key);
+    }
+    let mut trace = start_trace(&ctx, &mut ir, &headers);
+
     let request_id = ir.metadata.get("request_id").unwrap().clone();
+    let conversation = store.then(|| {
+        let mut conversation = crate::store::Conversation::new(request_id.clone()).with_messages(ir.messages.clone());
+        conversation.user = user.clone();
+        conversation.metadata.insert("model".to_string(), model_alias.clone());
+        conversation
+    });
+
+    if ir.background {
+        let unsupported = if ir.stream {
+            Some(("/stream", "background responses can't be streamed; poll GET /v1/responses/{id} instead"))
+        } else if !store {
+            Some(("/store", "background responses are kept until polled, so they need store: true"))
+        } else {
+            None
+        };
+        if let Some((pointer, message)) = unsupported {
+            let error = crate::skins::semantic::FieldError { pointer: pointer.to_string(), message: message.to_string() };
+            return ctx.error_handler.handle_invalid_field(&error);
+        }
+        let router = ctx.router.clone();
+        let conversations = ctx.conversations.clone();
+        let handle = ctx.background.submit(ir, &ctx.cancel_tokens, move |ir, cancel| async move {
+            let stream = router.route_chat(ir, cancel).await?;
+            Ok(record_response(stream, conversations, conversation))
+        });
+        let response = axum::Json(background_response_object(&handle, &ctx.system_fingerprint)).into_response();
+        return with_trace_headers(response, trace.as_ref());
+    }
 
     if ir.stream {
         let cancel = (*ctx.cancel_tokens).clone();
         let stream = match ctx.router.route_chat(ir, cancel).await {
             Ok(stream) => stream,
-            Err(e) => {
-                return ctx.error_handler.handle_json_error(serde_json::Error::io(
-                    std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()),
-                ));
-            }
+            Err(e) => return with_trace_headers(route_error(&ctx, e), trace.as_ref()),
         };
+        let stream = observe_leading(stream, trace.as_mut()).await;
+        let stream = record_response(stream, ctx.conversations.clone(), conversation);
+        let stream = ctx.sse_keep_alive.with_heartbeats(stream);
 
+        // A reply cut off at its output limit ends `incomplete`
+        let mut status = "completed";
         let sse_stream = stream.map(move |ev| {
             let chunk_data = match ev {
                 StreamEvent::TextDelta { content } => {
@@ -849,6 +1507,55 @@ pub async fn handle_responses(
                         }]
                     })
                 }
+                StreamEvent::Annotation { annotation } => {
+                    serde_json::json!({
+                        "id": request_id.clone(),
+                        "object": "response.chunk",
+                        "created_at": std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .unwrap()
+                            .as_secs(),
+                        "status": "in_progress",
+                        "output": [{
+                            "id": format!("msg_{}", Uuid::new_v4().to_string().replace("-", "")),
+                            "type": "message",
+                            "status": "in_progress",
+                            "content": [{
+                                "type": "output_text",
+                                "index": 0,
+                                "text": "",
+                                "annotations": [crate::types::providers::openai::responses_annotation(annotation)]
+                            }],
+                            "role": "assistant"
+                        }]
+                    })
+                }
+                StreamEvent::Refusal { text } => {
+                    serde_json::json!({
+                        "id": request_id.clone(),
+                        "object": "response.chunk",
+                        "created_at": std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .unwrap()
+                            .as_secs(),
+                        "status": "in_progress",
+                        "output": [{
+                            "id": format!("msg_{}", Uuid::new_v4().to_string().replace("-", "")),
+                            "type": "message",
+                            "status": "in_progress",
+                            "content": [{
+                                "type": "refusal",
+                                "index": 0,
+                                "refusal": text
+                            }],
+                            "role": "assistant"
+                        }]
+                    })
+                }
+                StreamEvent::FinalMessage { finish_reason: Some(reason), .. } if reason == "length" => {
+                    status = "incomplete";
+                    return Ok(axum::response::sse::Event::default().data(""));
+                }
                 StreamEvent::Done => {
                     serde_json::json!({
                         "id": request_id.clone(),
@@ -857,11 +1564,12 @@ pub async fn handle_responses(
                             .duration_since(std::time::UNIX_EPOCH)
                             .unwrap()
                             .as_secs(),
-                        "status": "completed",
+                        "status": status,
+                        "incomplete_details": (status == "incomplete").then(|| serde_json::json!({"reason": "max_output_tokens"})),
                         "output": [{
                             "id": format!("msg_{}", Uuid::new_v4().to_string().replace("-", "")),
                             "type": "message",
-                            "status": "completed",
+                            "status": status,
                             "content": [{
                                 "type": "output_text",
                                 "index": 0,
@@ -878,6 +1586,15 @@ pub async fn handle_responses(
                         message
                     ))));
                 }
+                // Progress updates carry no content; surface them as SSE comments
+                StreamEvent::Status { state, detail } => {
+                    let comment = match detail {
+                        Some(detail) => format!("{}: {}", state, detail),
+                        None => state,
+                    };
+                    return Ok(axum::response::sse::Event::default()
+                        .comment(comment.replace(['\r', '\n'], " ")));
+                }
                 _ => return Ok(axum::response::sse::Event::default().data("")),
             };
 
@@ -885,105 +1602,191 @@ pub async fn handle_responses(
                 .data(serde_json::to_string(&chunk_data).unwrap()))
         });
 
-        axum::response::Sse::new(sse_stream)
-            .keep_alive(axum::response::sse::KeepAlive::new())
-            .into_response()
+        let response = axum::response::Sse::new(sse_stream)
+            .keep_alive(ctx.sse_keep_alive.keep_alive())
+            .into_response();
+        with_trace_headers(response, trace.as_ref())
     } else {
         let cancel = (*ctx.cancel_tokens).clone();
-        let mut stream = match ctx.router.route_chat(ir, cancel).await {
+        let stream = match ctx.router.route_chat(ir, cancel).await {
             Ok(stream) => stream,
             Err(e) => {
                 eprintln!("Error: {}", e);
-                return ctx.error_handler.handle_json_error(serde_json::Error::io(
-                    std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()),
-                ));
+                return with_trace_headers(route_error(&ctx, e), trace.as_ref());
             }
         };
+        let mut stream = record_response(stream, ctx.conversations.clone(), conversation);
 
         let mut final_content = String::new();
+        let mut status = "completed";
+        let mut annotations = Vec::new();
+        let mut refusal: Option<String> = None;
         let mut input_tokens = 0;
         let mut output_tokens = 0;
-        let mut _system_fingerprint = None;
         let mut service_tier = None;
         let mut _prompt_tokens_details = None;
         let mut _completion_tokens_details = None;
 
         while let Some(ev) = stream.next().await {
+            if let Some(trace) = trace.as_mut() {
+                trace.observe(&ev);
+            }
             match ev {
                 StreamEvent::TextDelta { content } => {
                     final_content.push_str(&content);
                 }
+                StreamEvent::Annotation { annotation } => annotations.push(annotation),
+                StreamEvent::Refusal { text } => refusal.get_or_insert_with(String::new).push_str(&text),
                 StreamEvent::Tokens { input, output } => {
                     input_tokens = input;
                     output_tokens = output;
                 }
                 StreamEvent::OpenAIMetadata {
-                    system_fingerprint: fingerprint,
                     service_tier: tier,
                     prompt_tokens_details: prompt_details,
                     completion_tokens_details: completion_details,
+                    ..
                 } => {
-                    _system_fingerprint = fingerprint;
                     service_tier = tier;
                     _prompt_tokens_details = prompt_details;
                     _completion_tokens_details = completion_details;
                 }
-                StreamEvent::FinalMessage { content, .. } => {
+                StreamEvent::FinalMessage { content, finish_reason, .. } => {
                     final_content = content;
+                    // A reply cut off at its output limit is `incomplete`
+                    if finish_reason.as_deref() == Some("length") {
+                        status = "incomplete";
+                    }
                     break;
                 }
                 StreamEvent::Done => break,
                 StreamEvent::Error { code, message } => {
                     tracing::error!(%code, %message, "Non-stream error");
-                    return ctx.error_handler.handle_json_error(serde_json::Error::io(
-                        std::io::Error::new(std::io::ErrorKind::InvalidData, message),
-                    ));
+                    return with_trace_headers(stream_error(&ctx, code, message), trace.as_ref());
                 }
                 _ => {}
             }
         }
 
-        // Create a proper Responses API response format
-        let response = serde_json::json!({
-            "id": request_id,
-            "object": "response",
-            "created_at": std::time::SystemTime::now()
+        let response = ResponseObject {
+            id: &request_id,
+            created_at: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
-            "status": "completed",
-            "background": false,
+            status,
+            background: false,
+            model: &model_alias,
+            content: Some(&final_content),
+            annotations: &annotations,
+            refusal: refusal.as_deref(),
+            usage: Some((input_tokens, output_tokens)),
+            error: None,
+            max_output_tokens,
+            previous_response_id,
+            service_tier: ctx.system_fingerprint.resolve(None, service_tier).1,
+            store,
+            user,
+        }
+        .to_json();
+
+        with_trace_headers(axum::Json(response).into_response(), trace.as_ref())
+    }
+}
+
+/// A Responses API response object
+struct ResponseObject<'a> {
+    id: &'a str,
+    created_at: u64,
+    status: &'a str,
+    background: bool,
+    model: &'a str,
+    /// The reply; a response still running has no output
+    content: Option<&'a str>,
+    /// Annotations on the reply, in the Chat Completions shape of
+    /// [`StreamEvent::Annotation`]
+    annotations: &'a [serde_json::Value],
+    /// The model's refusal, sent as a `refusal` part instead of the text
+    refusal: Option<&'a str>,
+    /// Input and output tokens, once the response has completed
+    usage: Option<(u32, u32)>,
+    error: Option<serde_json::Value>,
+    max_output_tokens: Option<i64>,
+    previous_response_id: Option<String>,
+    service_tier: Option<String>,
+    store: bool,
+    user: Option<String>,
+}
+
+impl ResponseObject<'_> {
+    fn to_json(&self) -> serde_json::Value {
+        let output: Vec<serde_json::Value> = self
+            .content
+            .map(|content| {
+                let annotations: Vec<serde_json::Value> = self
+                    .annotations
+                    .iter()
+                    .cloned()
+                    .map(crate::types::providers::openai::responses_annotation)
+                    .collect();
+                let part = match self.refusal {
+                    Some(refusal) => serde_json::json!({"type": "refusal", "refusal": refusal}),
+                    None => serde_json::json!({
+                        "type": "output_text",
+                        "annotations": annotations,
+                        "logprobs": [],
+                        "text": content
+                    }),
+                };
+                serde_json::json!({
+                    "id": format!("msg_{}", Uuid::new_v4().to_string().replace("-", "")),
+                    "type": "message",
+                    "status": if self.status == "incomplete" { "incomplete" } else { "completed" },
+                    "content": [part],
+                    "role": "assistant"
+                })
+            })
+            .into_iter()
+            .collect();
+        let usage = self.usage.map(|(input_tokens, output_tokens)| {
+            serde_json::json!({
+                "input_tokens": input_tokens,
+                "input_tokens_details": {
+                    "cached_tokens": 0
+                },
+                "output_tokens": output_tokens,
+                "output_tokens_details": {
+                    "reasoning_tokens": 0
+                },
+                "total_tokens": input_tokens + output_tokens
+            })
+        });
+        serde_json::json!({
+            "id": self.id,
+            "object": "response",
+            "created_at": self.created_at,
+            "status": self.status,
+            "background": self.background,
             "billing": {
                 "payer": "openai"
             },
-            "error": null,
-            "incomplete_details": null,
+            "error": self.error,
+            "incomplete_details": (self.status == "incomplete").then(|| serde_json::json!({"reason": "max_output_tokens"})),
             "instructions": null,
-            "max_output_tokens": max_output_tokens,
+            "max_output_tokens": self.max_output_tokens,
             "max_tool_calls": null,
-            "model": model_alias.clone(),
-            "output": [{
-                "id": format!("msg_{}", Uuid::new_v4().to_string().replace("-", "")),
-                "type": "message",
-                "status": "completed",
-                "content": [{
-                    "type": "output_text",
-                    "annotations": [],
-                    "logprobs": [],
-                    "text": final_content
-                }],
-                "role": "assistant"
-            }],
+            "model": self.model,
+            "output": output,
             "parallel_tool_calls": true,
-            "previous_response_id": null,
+            "previous_response_id": self.previous_response_id,
             "prompt_cache_key": null,
             "reasoning": {
                 "effort": null,
                 "summary": null
             },
             "safety_identifier": null,
-            "service_tier": service_tier.unwrap_or_else(|| "default".to_string()),
-            "store": true,
+            "service_tier": self.service_tier,
+            "store": self.store,
             "temperature": 1.0,
             "text": {
                 "format": {
@@ -996,29 +1799,213 @@ pub async fn handle_responses(
             "top_logprobs": 0,
             "top_p": 1.0,
             "truncation": "disabled",
-            "usage": {
-                "input_tokens": input_tokens,
-                "input_tokens_details": {
-                    "cached_tokens": 0
-                },
-                "output_tokens": output_tokens,
-                "output_tokens_details": {
-                    "reasoning_tokens": 0
-                },
-                "total_tokens": input_tokens + output_tokens
-            },
-            "user": null,
+            "usage": usage,
+            "user": self.user,
             "metadata": {}
-        });
+        })
+    }
+}
 
-        axum::Json(response).into_response()
+/// The response object of a background request as it stands
+fn background_response_object(handle: &crate::background::ResponseHandle, fingerprint: &SystemFingerprint) -> serde_json::Value {
+    let state = handle.state();
+    let result = state.result.as_ref();
+    ResponseObject {
+        id: handle.id(),
+        created_at: handle.created_at(),
+        status: state.status.as_str(),
+        background: true,
+        model: handle.model(),
+        content: result.map(|result| result.reply.content.as_str()),
+        annotations: result.map_or(&[], |result| result.annotations.as_slice()),
+        refusal: result.and_then(|result| result.reply.refusal.as_deref()),
+        usage: result.map(|result| (result.input_tokens, result.output_tokens)),
+        error: state
+            .error
+            .as_ref()
+            .map(|(code, message)| serde_json::json!({"code": code, "message": message})),
+        max_output_tokens: None,
+        previous_response_id: None,
+        service_tier: fingerprint.resolve(None, None).1,
+        store: true,
+        user: None,
     }
+    .to_json()
+}
+
+/// The background request `id`, unless another API key submitted it
+fn background_request(
+    ctx: &SkinContext,
+    id: &str,
+    api_key: &Option<axum::Extension<crate::budget::ApiKeyName>>,
+) -> Option<crate::background::ResponseHandle> {
+    let handle = ctx.background.get(id)?;
+    let caller = api_key.as_ref().map(|axum::Extension(crate::budget::ApiKeyName(name))| name.as_str());
+    (handle.owner().is_none() || handle.owner() == caller).then_some(handle)
+}
+
+fn response_not_found(id: &str) -> axum::response::Response {
+    let error = serde_json::json!({
+        "error": {
+            "message": format!("Response with id '{}' not found.", id),
+            "type": "invalid_request_error",
+            "param": null,
+            "code": "response_not_found"
+        }
+    });
+    (axum::http::StatusCode::NOT_FOUND, axum::Json(error)).into_response()
 }
 
-pub async fn handle_models(State(ctx): State<SkinContext>) -> axum::response::Response {
+/// Serve `GET /responses/{id}`: a background response, completed or not
+pub async fn handle_get_response(
+    State(ctx): State<SkinContext>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    api_key: Option<axum::Extension<crate::budget::ApiKeyName>>,
+) -> axum::response::Response {
+    match background_request(&ctx, &id, &api_key) {
+        Some(handle) => axum::Json(background_response_object(&handle, &ctx.system_fingerprint)).into_response(),
+        None => response_not_found(&id),
+    }
+}
+
+/// Serve `POST /responses/{id}/cancel`: stop a background response; one
+/// that has already ended is returned as it is
+pub async fn handle_cancel_response(
+    State(ctx): State<SkinContext>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    api_key: Option<axum::Extension<crate::budget::ApiKeyName>>,
+) -> axum::response::Response {
+    let Some(handle) = background_request(&ctx, &id, &api_key) else {
+        return response_not_found(&id);
+    };
+    handle.cancel();
+    axum::Json(background_response_object(&handle, &ctx.system_fingerprint)).into_response()
+}
+
+/// Pass `events` through, saving `conversation` plus the assistant's reply
+/// once the reply is complete. Failed replies are not stored.
+fn record_response<S>(
+    mut events: S,
+    store: std::sync::Arc<dyn crate::store::ConversationStore>,
+    conversation: Option<crate::store::Conversation>,
+) -> impl futures_util::Stream<Item = StreamEvent> + Send + Unpin
+where
+    S: futures_util::Stream<Item = StreamEvent> + Send + Unpin + 'static,
+{
+    Box::pin(async_stream::stream! {
+        let mut conversation = conversation;
+        let mut content = String::new();
+        while let Some(event) = events.next().await {
+            match &event {
+                StreamEvent::TextDelta { content: delta } => content.push_str(delta),
+                StreamEvent::FinalMessage { content: final_content, .. } => content = final_content.clone(),
+                StreamEvent::Error { .. } => conversation = None,
+                _ => {}
+            }
+            if matches!(event, StreamEvent::FinalMessage { .. } | StreamEvent::Done) {
+                if let Some(mut conversation) = conversation.take() {
+                    conversation.messages.push(Message {
+                        role: Role::Assistant,
+                        parts: vec![ContentPart::Text(content.clone())],
+                        name: None,
+                    });
+                    let id = conversation.id.clone();
+                    if let Err(e) = store.put(conversation).await {
+                        tracing::warn!(response_id = %id, error = %e, "Failed to store response");
+                    }
+                }
+            }
+            yield event;
+        }
+    })
+}
+
+fn previous_response_not_found(id: &str) -> axum::response::Response {
+    let error = serde_json::json!({
+        "error": {
+            "message": format!("Previous response with id '{}' not found.", id),
+            "type": "invalid_request_error",
+            "param": "previous_response_id",
+            "code": "previous_response_not_found"
+        }
+    });
+    (axum::http::StatusCode::NOT_FOUND, axum::Json(error)).into_response()
+}
+
+/// Filters and pagination of `GET /models`; other parameters are ignored
+#[derive(Deserialize, Default)]
+pub struct ModelsQuery {
+    /// Only models of this provider
+    pub provider: Option<String>,
+    /// Only models with this capability: `tools`, `vision`, `json`, `audio` or `streaming`
+    pub capability: Option<String>,
+    /// Only models whose id contains this, ignoring case
+    pub search: Option<String>,
+    /// Page size; without it every model after the cursor is listed
+    pub limit: Option<usize>,
+    /// Cursor: list the models after the one with this id
+    pub after: Option<String>,
+}
+
+impl ModelsQuery {
+    fn matches(&self, model: &crate::types::DiscoveredModel) -> bool {
+        if self.provider.as_ref().is_some_and(|provider| *provider != model.provider_name) {
+            return false;
+        }
+        if let Some(capability) = &self.capability {
+            let capabilities = &model.capabilities;
+            let supported = match capability.as_str() {
+                "tools" => capabilities.supports_tools,
+                "vision" => capabilities.supports_vision,
+                "json" => capabilities.supports_json,
+                "audio" => capabilities.supports_audio,
+                "streaming" => capabilities.supports_streaming,
+                _ => false,
+            };
+            if !supported {
+                return false;
+            }
+        }
+        self.search
+            .as_ref()
+            .is_none_or(|search| model.id.to_lowercase().contains(&search.to_lowercase()))
+    }
+
+    /// Filter `models`, order them by provider then id and cut out the page,
+    /// returning it and whether more models follow it
+    fn page(&self, mut models: Vec<crate::types::DiscoveredModel>) -> (Vec<crate::types::DiscoveredModel>, bool) {
+        models.retain(|model| self.matches(model));
+        models.sort_by(|a, b| (&a.provider_name, &a.id).cmp(&(&b.provider_name, &b.id)));
+        if let Some(after) = &self.after {
+            let start = models.iter().position(|model| model.id == *after).map_or(models.len(), |i| i + 1);
+            models.drain(..start);
+        }
+        let limit = self.limit.map_or(models.len(), |limit| limit.max(1));
+        let has_more = models.len() > limit;
+        models.truncate(limit);
+        (models, has_more)
+    }
+
+    fn is_paginated(&self) -> bool {
+        self.limit.is_some() || self.after.is_some()
+    }
+}
+
+/// Serve `GET /models`: the discovered models the caller's tenant may use,
+/// filtered and paginated by [`ModelsQuery`]
+pub async fn handle_models(
+    State(ctx): State<SkinContext>,
+    axum::extract::Query(query): axum::extract::Query<ModelsQuery>,
+    api_key: Option<axum::Extension<crate::budget::ApiKeyName>>,
+) -> axum::response::Response {
+    let tenant = request_tenant(&ctx, &api_key);
     let res = {
         let mut manager = ctx.provider_manager.write().await;
-        manager.discover_models(&ctx.router).await
+        manager.discover_models(&ctx.router).await.map(|discovery| {
+            discovery.log_errors();
+            let scope = TenantScope::of(tenant.as_deref());
+            manager.list_models_in(scope).into_iter().cloned().collect::<Vec<_>>()
+        })
     };
     let models = match res {
         Ok(models) => models,
@@ -1032,23 +2019,234 @@ pub async fn handle_models(State(ctx): State<SkinContext>) -> axum::response::Re
         }
     };
 
+    let (models, has_more) = query.page(models);
     let openai_models: Vec<OpenAIModel> = models
         .into_iter()
         .map(|model| OpenAIModel {
             id: model.id,
             object: "model".to_string(),
-            created: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            created: model.created.unwrap_or_default(),
             owned_by: model.provider_name,
+            context_length: None,
+            architecture: Some(model_architecture(&model.modalities)),
+            top_provider: None,
+            supported_parameters: None,
         })
         .collect();
 
+    let paginated = query.is_paginated();
     let response = OpenAIModelsResponse {
         object: "list".to_string(),
+        first_id: openai_models.first().filter(|_| paginated).map(|model| model.id.clone()),
+        last_id: openai_models.last().filter(|_| paginated).map(|model| model.id.clone()),
+        has_more: paginated.then_some(has_more),
         data: openai_models,
     };
 
     axum::Json(response).into_response()
 }
+
+/// Proxy `POST /moderations` to the configured moderation provider
+pub async fn handle_moderations(
+    State(ctx): State<SkinContext>,
+    crate::server::SkinAwareJson(req): crate::server::SkinAwareJson<crate::moderation::ModerationRequest>,
+) -> axum::response::Response {
+    let Some(client) = ctx.moderation.clone() else {
+        return ctx.error_handler.handle_not_found();
+    };
+    match client.moderate(&req).await {
+        Ok(response) => axum::Json(response).into_response(),
+        Err(crate::adapter::AdapterError::Provider { code, message }) => {
+            ctx.error_handler.handle_provider_error(code, message)
+        }
+        Err(e) => ctx.error_handler.handle_provider_error("moderation_error".to_string(), e.to_string()),
+    }
+}
+
+/// Serve `POST /tokenize`: count the prompt tokens of a Chat Completions
+/// request body without sending it
+pub async fn handle_tokenize(
+    State(ctx): State<SkinContext>,
+    api_key: Option<axum::Extension<crate::budget::ApiKeyName>>,
+    crate::server::SkinAwareJson(req): crate::server::SkinAwareJson<OpenAIChatRequest>,
+) -> axum::response::Response {
+    let tenant = request_tenant(&ctx, &api_key);
+    let model_ref = match ctx.resolve_model(&req.model, tenant.as_deref()).await {
+        Ok(model_ref) => model_ref,
+        Err(response) => return response,
+    };
+
+    let mut ir = match openai_to_chat_request(req, model_ref) {
+        Ok(ir) => ir,
+        Err(e) => {
+            return ctx.error_handler.handle_json_error(serde_json::Error::io(
+                std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()),
+            ));
+        }
+    };
+    apply_tenant(&mut ir, tenant.as_deref());
+    if let Err(response) = ctx.apply_routing_policy(&mut ir).await {
+        return response;
+    }
+    let model_alias = ir.model.alias.clone();
+
+    match ctx.router.count_tokens(&ir.model, &ir.messages).await {
+        Ok(count) => axum::Json(serde_json::json!({
+            "object": "tokenize",
+            "model": model_alias,
+            "prompt_tokens": count.prompt_tokens,
+            "per_message": count.per_message,
+        }))
+        .into_response(),
+        Err(e) => route_error(&ctx, e),
+    }
+}
+
+/// Input/output modalities reported by `/models`
+fn model_architecture(modalities: &[Modality]) -> crate::types::providers::openai_compatible::OpenAIModelArchitecture {
+    let mut architecture = crate::types::providers::openai_compatible::OpenAIModelArchitecture::default();
+    for modality in modalities {
+        let (list, name) = match modality {
+            Modality::Text => {
+                architecture.input_modalities.push("text".to_string());
+                (&mut architecture.output_modalities, "text")
+            }
+            Modality::Vision => (&mut architecture.input_modalities, "image"),
+            Modality::AudioIn => (&mut architecture.input_modalities, "audio"),
+            Modality::AudioOut => (&mut architecture.output_modalities, "audio"),
+            Modality::Embeddings => (&mut architecture.output_modalities, "embeddings"),
+            Modality::ImageOut => (&mut architecture.output_modalities, "image"),
+        };
+        list.push(name.to_string());
+    }
+    architecture
+}
+
+/// Serve `POST /images/generations`. Without a `model`, the first discovered
+/// image-capable model is used.
+pub async fn handle_image_generations(
+    State(ctx): State<SkinContext>,
+    api_key: Option<axum::Extension<crate::budget::ApiKeyName>>,
+    crate::server::SkinAwareJson(req): crate::server::SkinAwareJson<OpenAIImageRequest>,
+) -> axum::response::Response {
+    let tenant = request_tenant(&ctx, &api_key);
+    let model_ref = match &req.model {
+        Some(model) => match ctx.resolve_model(model, tenant.as_deref()).await {
+            Ok(model_ref) => model_ref,
+            Err(response) => return response,
+        },
+        None => {
+            let manager = ctx.provider_manager.read().await;
+            let mut image_models: Vec<&DiscoveredModel> = manager
+                .list_models_in(TenantScope::of(tenant.as_deref()))
+                .into_iter()
+                .filter(|m| m.modalities.contains(&Modality::ImageOut))
+                .collect();
+            image_models.sort_by(|a, b| a.id.cmp(&b.id));
+            match image_models.first().and_then(|m| manager.resolve_model_ref(&m.id)) {
+                Some(model_ref) => model_ref,
+                None => return ctx.error_handler.handle_model_not_found("(default image model)"),
+            }
+        }
+    };
+
+    let response_format = match req.response_format.as_deref() {
+        None => None,
+        Some("url") => Some(ImageResponseFormat::Url),
+        Some("b64_json") => Some(ImageResponseFormat::B64Json),
+        Some(other) => {
+            return ctx.error_handler.handle_json_error(serde_json::Error::io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid value for 'response_format': '{}'. Expected 'url' or 'b64_json'.", other),
+            )));
+        }
+    };
+    let mut ir = ImageRequestIR::new(model_ref, req.prompt);
+    ir.n = req.n;
+    ir.size = req.size;
+    ir.quality = req.quality;
+    ir.response_format = response_format;
+    ir.metadata.insert("request_id".to_string(), Uuid::new_v4().to_string());
+
+    match ctx.router.route_image(ir).await {
+        Ok(response) => axum::Json(OpenAIImageResponse {
+            created: response.created,
+            data: response
+                .images
+                .into_iter()
+                .map(|image| OpenAIImageData {
+                    b64_json: image.b64_json,
+                    url: image.url,
+                    revised_prompt: image.revised_prompt,
+                })
+                .collect(),
+        })
+        .into_response(),
+        Err(e) => media_error(&ctx, e),
+    }
+}
+
+/// Serve `POST /audio/speech`, streaming the provider's audio body through
+/// as it arrives instead of buffering it.
+pub async fn handle_audio_speech(
+    State(ctx): State<SkinContext>,
+    api_key: Option<axum::Extension<crate::budget::ApiKeyName>>,
+    crate::server::SkinAwareJson(req): crate::server::SkinAwareJson<OpenAISpeechRequest>,
+) -> axum::response::Response {
+    let tenant = request_tenant(&ctx, &api_key);
+    let model_ref = match ctx.resolve_model(&req.model, tenant.as_deref()).await {
+        Ok(model_ref) => model_ref,
+        Err(response) => return response,
+    };
+
+    if let Some(speed) = req.speed {
+        if !(0.25..=4.0).contains(&speed) {
+            return ctx.error_handler.handle_json_error(serde_json::Error::io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid value for 'speed': {}. Expected a value between 0.25 and 4.0.", speed),
+            )));
+        }
+    }
+
+    let voice = match req.voice {
+        OpenAISpeechVoice::Named(voice) => match serde_json::to_value(voice) {
+            Ok(serde_json::Value::String(name)) => SpeechVoice::Preset(name),
+            _ => unreachable!("OpenAIVoice serializes as a string"),
+        },
+        OpenAISpeechVoice::Custom { id } => SpeechVoice::Custom(id),
+    };
+    let mut ir = SpeechRequestIR::new(model_ref, req.input, voice);
+    ir.response_format = req.response_format;
+    ir.speed = req.speed;
+    ir.metadata.insert("request_id".to_string(), Uuid::new_v4().to_string());
+
+    match ctx.router.route_speech(ir).await {
+        Ok(response) => (
+            [(axum::http::header::CONTENT_TYPE, response.content_type)],
+            axum::body::Body::from_stream(response.audio),
+        )
+            .into_response(),
+        Err(e) => media_error(&ctx, e),
+    }
+}
+
+/// Error mapping shared by the image and audio routes, which surface
+/// unsupported providers as a client error.
+fn media_error(ctx: &SkinContext, error: crate::error::EngineError) -> axum::response::Response {
+    match error {
+        crate::error::EngineError::Adapter(crate::adapter::AdapterError::Unsupported(message)) => {
+            let error = serde_json::json!({
+                "error": {
+                    "message": message,
+                    "type": "invalid_request_error",
+                    "code": "unsupported"
+                }
+            });
+            (axum::http::StatusCode::BAD_REQUEST, axum::Json(error)).into_response()
+        }
+        crate::error::EngineError::Adapter(crate::adapter::AdapterError::Provider { code, message }) => {
+            ctx.error_handler.handle_provider_error(code, message)
+        }
+        e => route_error(ctx, e),
+    }
+}
@@ -0,0 +1,324 @@
+//! Resumable Chat Completions streams
+//!
+//! With resumable streams enabled, a streamed chat completion keeps running
+//! when its client disconnects. Its chunks are numbered (the SSE `id` field)
+//! and buffered under the request id, which is also each chunk's `id`. A
+//! client reconnects with `GET .../chat/stream/{request_id}?from=<seq>` (or
+//! a `Last-Event-ID` header) to get the chunks it missed and then the rest of
+//! the stream live. Buffers hold at most a fixed number of chunks and are
+//! dropped a TTL after the generation completes.
+//!
+//! The generation runs at the provider's pace, and a connected client more
+//! than the buffer behind skips ahead. With a
+//! [`BackPressure`](crate::backpressure::BackPressure) that pauses, the
+//! generation instead waits while a connected client is the high-water mark
+//! behind; one that disconnects ends such a client's stream with a
+//! `client_too_slow` error. Without connected clients the generation runs on.
+
+use axum::response::sse::Event;
+use crate::backpressure::{BackPressure, SlowClientPolicy};
+use futures_util::{Stream, StreamExt};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// How long a completed stream can still be resumed
+pub const DEFAULT_RESUME_TTL: Duration = Duration::from_secs(300);
+
+/// Chunks kept per stream; older ones can no longer be replayed
+pub const DEFAULT_MAX_BUFFERED_CHUNKS: usize = 4096;
+
+/// One SSE message of a chat stream
+#[derive(Clone, Debug, PartialEq)]
+pub enum SseChunk {
+    /// A `data:` line holding a serialized chunk
+    Data(String),
+    /// An SSE comment, e.g. a progress update
+    Comment(String),
+    /// A `data:` line under its own `event:` type, e.g. engine-side tool progress
+    Named { event: String, data: String },
+    /// The stream failed; the connection is closed
+    Error(String),
+}
+
+impl SseChunk {
+    /// The SSE event for this chunk, numbered `seq` when set
+    pub fn into_event(self, seq: Option<u64>) -> Result<Event, axum::Error> {
+        let event = match self {
+            SseChunk::Data(data) => Event::default().data(data),
+            SseChunk::Comment(comment) => Event::default().comment(comment.replace(['\r', '\n'], " ")),
+            SseChunk::Named { event, data } => Event::default().event(event).data(data),
+            SseChunk::Error(message) => {
+                return Err(axum::Error::new(std::io::Error::other(format!("Stream error: {}", message))));
+            }
+        };
+        Ok(match seq {
+            Some(seq) => event.id(seq.to_string()),
+            None => event,
+        })
+    }
+}
+
+/// Why a stream can't be resumed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResumeError {
+    /// No stream with that id, it expired, or it belongs to another API key
+    NotFound,
+    /// The requested chunks were dropped from the buffer
+    Evicted { first_available: u64 },
+}
+
+/// Buffered chat streams by request id. Clones share their buffers.
+#[derive(Clone)]
+pub struct ResumableStreams {
+    ttl: Duration,
+    max_chunks: usize,
+    back_pressure: BackPressure,
+    streams: Arc<Mutex<HashMap<String, Arc<BufferedStream>>>>,
+}
+
+struct BufferedStream {
+    /// API key that started the stream; only it may resume
+    owner: Option<String>,
+    buffer: Mutex<Buffer>,
+    /// Bumped whenever a chunk is added or the stream finishes
+    updates: watch::Sender<u64>,
+    /// Next sequence number of each connected client, by follower id
+    followers: Mutex<HashMap<u64, u64>>,
+    next_follower: AtomicU64,
+    /// Bumped whenever a client moves on or leaves
+    progress: watch::Sender<u64>,
+}
+
+impl BufferedStream {
+    /// Sequence number of the next chunk
+    fn next_seq(&self) -> u64 {
+        let buffer = self.buffer.lock().unwrap_or_else(|e| e.into_inner());
+        buffer.first_seq + buffer.chunks.len() as u64
+    }
+
+    /// Wait until no connected client is `high_water_mark` chunks behind
+    async fn drained(&self, high_water_mark: usize) {
+        let mut progress = self.progress.subscribe();
+        loop {
+            progress.borrow_and_update();
+            let next = self.next_seq();
+            let slowest = self.followers.lock().unwrap_or_else(|e| e.into_inner()).values().min().copied();
+            if slowest.is_none_or(|slowest| next.saturating_sub(slowest) < high_water_mark as u64) {
+                return;
+            }
+            if progress.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Record that follower `id` reads from `next` on
+    fn advance(&self, id: u64, next: u64) {
+        self.followers.lock().unwrap_or_else(|e| e.into_inner()).insert(id, next);
+        self.progress.send_modify(|version| *version += 1);
+    }
+}
+
+/// Unregisters a follower when its stream is dropped
+struct Follower {
+    stream: Arc<BufferedStream>,
+    id: u64,
+}
+
+impl Drop for Follower {
+    fn drop(&mut self) {
+        self.stream.followers.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.id);
+        self.stream.progress.send_modify(|version| *version += 1);
+    }
+}
+
+#[derive(Default)]
+struct Buffer {
+    chunks: VecDeque<SseChunk>,
+    /// Sequence number of `chunks[0]`
+    first_seq: u64,
+    finished: bool,
+}
+
+impl Default for ResumableStreams {
+    fn default() -> Self {
+        Self::new(DEFAULT_RESUME_TTL)
+    }
+}
+
+impl ResumableStreams {
+    /// Keep completed streams resumable for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            max_chunks: DEFAULT_MAX_BUFFERED_CHUNKS,
+            back_pressure: BackPressure::skip(DEFAULT_MAX_BUFFERED_CHUNKS),
+            streams: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Buffer at most `max_chunks` chunks per stream
+    pub fn with_max_chunks(mut self, max_chunks: usize) -> Self {
+        self.max_chunks = max_chunks.max(1);
+        self
+    }
+
+    /// Treat connected clients that fall `back_pressure`'s high-water mark
+    /// behind by its policy; by default they skip ahead once their chunks
+    /// leave the buffer
+    pub fn with_back_pressure(mut self, back_pressure: BackPressure) -> Self {
+        self.back_pressure = back_pressure;
+        self
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Number of streams that can currently be resumed
+    pub fn len(&self) -> usize {
+        self.streams.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drive `chunks` to completion in the background, buffering them under
+    /// `request_id`, and return the live stream of numbered events
+    pub fn start<S>(
+        &self,
+        request_id: String,
+        owner: Option<String>,
+        chunks: S,
+    ) -> impl Stream<Item = Result<Event, axum::Error>> + Send + 'static
+    where
+        S: Stream<Item = SseChunk> + Send + 'static,
+    {
+        let stream = Arc::new(BufferedStream {
+            owner,
+            buffer: Mutex::new(Buffer::default()),
+            updates: watch::Sender::new(0),
+            followers: Mutex::new(HashMap::new()),
+            next_follower: AtomicU64::new(0),
+            progress: watch::Sender::new(0),
+        });
+        self.streams
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(request_id.clone(), stream.clone());
+
+        let streams = self.streams.clone();
+        let (ttl, max_chunks, back_pressure) = (self.ttl, self.max_chunks, self.back_pressure);
+        let buffered = stream.clone();
+        let live = follow(stream, 0, back_pressure);
+        tokio::spawn(async move {
+            let mut chunks = std::pin::pin!(chunks);
+            while let Some(chunk) = chunks.next().await {
+                if back_pressure.policy == SlowClientPolicy::Pause {
+                    buffered.drained(back_pressure.high_water_mark).await;
+                }
+                let mut buffer = buffered.buffer.lock().unwrap_or_else(|e| e.into_inner());
+                buffer.chunks.push_back(chunk);
+                if buffer.chunks.len() > max_chunks {
+                    buffer.chunks.pop_front();
+                    buffer.first_seq += 1;
+                }
+                drop(buffer);
+                buffered.updates.send_modify(|version| *version += 1);
+            }
+            buffered.buffer.lock().unwrap_or_else(|e| e.into_inner()).finished = true;
+            buffered.updates.send_modify(|version| *version += 1);
+
+            tokio::time::sleep(ttl).await;
+            let mut streams = streams.lock().unwrap_or_else(|e| e.into_inner());
+            if streams.get(&request_id).is_some_and(|current| Arc::ptr_eq(current, &buffered)) {
+                streams.remove(&request_id);
+            }
+        });
+        live
+    }
+
+    /// Replay the chunks of `request_id` from sequence number `from`, then
+    /// follow the stream live until it ends
+    pub fn resume(
+        &self,
+        request_id: &str,
+        owner: Option<&str>,
+        from: u64,
+    ) -> Result<impl Stream<Item = Result<Event, axum::Error>> + Send + 'static, ResumeError> {
+        let stream = self
+            .streams
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(request_id)
+            .filter(|stream| stream.owner.is_none() || stream.owner.as_deref() == owner)
+            .cloned()
+            .ok_or(ResumeError::NotFound)?;
+        let first_available = stream.buffer.lock().unwrap_or_else(|e| e.into_inner()).first_seq;
+        if from < first_available {
+            return Err(ResumeError::Evicted { first_available });
+        }
+        Ok(follow(stream, from, self.back_pressure))
+    }
+}
+
+/// Events of `stream` from sequence number `from` until it finishes. A
+/// follower that falls behind the buffer skips ahead with a comment; one
+/// the high-water mark behind is disconnected if `back_pressure` says so.
+/// The follower counts as connected from the start, so a paused generation
+/// waits for it before it is first polled.
+fn follow(
+    stream: Arc<BufferedStream>,
+    from: u64,
+    back_pressure: BackPressure,
+) -> impl Stream<Item = Result<Event, axum::Error>> + Send + 'static {
+    let follower = Follower {
+        id: stream.next_follower.fetch_add(1, Ordering::Relaxed),
+        stream,
+    };
+    follower.stream.advance(follower.id, from);
+    async_stream::stream! {
+        let stream = follower.stream.clone();
+        let mut updates = stream.updates.subscribe();
+        let mut next = from;
+        loop {
+            updates.borrow_and_update();
+            let (pending, skipped, finished) = {
+                let buffer = stream.buffer.lock().unwrap_or_else(|e| e.into_inner());
+                let skipped = buffer.first_seq.saturating_sub(next);
+                next = next.max(buffer.first_seq);
+                let start = (next - buffer.first_seq) as usize;
+                let pending: Vec<SseChunk> = buffer.chunks.iter().skip(start).cloned().collect();
+                (pending, skipped, buffer.finished)
+            };
+            if back_pressure.policy == SlowClientPolicy::Disconnect && pending.len() > back_pressure.high_water_mark {
+                let message = format!("{}: client fell {} chunks behind the stream", crate::backpressure::CLIENT_TOO_SLOW, pending.len());
+                yield SseChunk::Error(message).into_event(None);
+                return;
+            }
+            if skipped > 0 {
+                yield Ok(Event::default().comment(format!("lagged: {} chunks skipped", skipped)));
+            }
+            let idle = pending.is_empty();
+            for chunk in pending {
+                let failed = matches!(chunk, SseChunk::Error(_));
+                yield chunk.into_event(Some(next));
+                next += 1;
+                stream.advance(follower.id, next);
+                if failed {
+                    return;
+                }
+            }
+            if finished {
+                break;
+            }
+            if idle && updates.changed().await.is_err() {
+                break;
+            }
+        }
+    }
+}
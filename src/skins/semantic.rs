@@ -0,0 +1,149 @@
+//! Semantic checks of well-formed requests
+//!
+//! A body can deserialize and still make no sense: no messages, a message
+//! without content, a content part of an unknown kind, a tool whose
+//! parameters aren't an object schema, or a `tool_choice` naming a tool that
+//! isn't defined. The skins run these checks right after deserialization and
+//! answer 422 with the JSON pointer of the offending element in `param`,
+//! instead of letting the request fail somewhere in an adapter.
+
+use crate::types::providers::openai::{
+    OpenAIChatRequest, OpenAIContentPart, OpenAIFunctionDef, OpenAIMessageContent, OpenAIToolChoice,
+};
+
+/// Content part kinds of Chat Completions messages. Refusals are accepted
+/// in replayed assistant messages and dropped.
+const KNOWN_PART_KINDS: &[&str] = &["text", "image_url", "audio", "file", "refusal"];
+
+/// The first element of a request that fails a semantic check
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldError {
+    /// JSON pointer of the element, e.g. `/messages/0/content/1/type`
+    pub pointer: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(pointer: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            pointer: pointer.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.pointer)
+    }
+}
+
+/// Check a Chat Completions request
+pub fn check_chat_request(req: &OpenAIChatRequest) -> Result<(), FieldError> {
+    if req.messages.is_empty() {
+        return Err(FieldError::new("/messages", "'messages' must contain at least one message"));
+    }
+    for (index, message) in req.messages.iter().enumerate() {
+        let pointer = format!("/messages/{}", index);
+        let has_tool_calls = message.tool_calls.as_ref().is_some_and(|calls| !calls.is_empty());
+        match &message.content {
+            OpenAIMessageContent::Text(text) if text.is_empty() && !has_tool_calls => {
+                return Err(FieldError::new(format!("{}/content", pointer), "Message content must not be empty"));
+            }
+            OpenAIMessageContent::Text(_) => {}
+            OpenAIMessageContent::Parts(parts) => {
+                let mut has_content = false;
+                for (part_index, part) in parts.iter().enumerate() {
+                    has_content |= check_part(part, &format!("{}/content/{}", pointer, part_index))?;
+                }
+                if !has_content && !has_tool_calls {
+                    return Err(FieldError::new(
+                        format!("{}/content", pointer),
+                        "Message content must contain at least one non-empty part",
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut defined = Vec::new();
+    for (index, tool) in req.tools.iter().flatten().enumerate() {
+        // Other tool types are ignored when the request is converted
+        if tool.r#type == "function" {
+            check_function(&tool.function, &format!("/tools/{}/function", index))?;
+            defined.push(tool.function.name.as_str());
+        }
+    }
+    for (index, function) in req.functions.iter().flatten().enumerate() {
+        check_function(function, &format!("/functions/{}", index))?;
+        defined.push(function.name.as_str());
+    }
+
+    match &req.tool_choice {
+        Some(OpenAIToolChoice::String(choice)) if !["none", "auto", "required"].contains(&choice.as_str()) => {
+            return Err(FieldError::new(
+                "/tool_choice",
+                format!("Invalid tool_choice '{}'; expected 'none', 'auto', 'required' or a named function", choice),
+            ));
+        }
+        Some(OpenAIToolChoice::Named { function, .. }) if !defined.contains(&function.name.as_str()) => {
+            return Err(FieldError::new(
+                "/tool_choice/function/name",
+                format!("tool_choice names the function '{}', which is not among the request's tools", function.name),
+            ));
+        }
+        _ => {}
+    }
+    if let Some(name) = req.function_call.as_ref().and_then(|call| call.get("name")).and_then(|name| name.as_str()) {
+        if !defined.contains(&name) {
+            return Err(FieldError::new(
+                "/function_call/name",
+                format!("function_call names the function '{}', which is not among the request's functions", name),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check one content part, returning whether it has content
+fn check_part(part: &OpenAIContentPart, pointer: &str) -> Result<bool, FieldError> {
+    if !KNOWN_PART_KINDS.contains(&part.kind.as_str()) {
+        return Err(FieldError::new(
+            format!("{}/type", pointer),
+            format!("Unknown content part type '{}'; expected one of: {}", part.kind, KNOWN_PART_KINDS.join(", ")),
+        ));
+    }
+    let present = match part.kind.as_str() {
+        "text" => part.text.is_some(),
+        "image_url" => part.image_url.is_some(),
+        "audio" => part.audio.is_some(),
+        "file" => part.file.is_some(),
+        _ => return Ok(false),
+    };
+    if !present {
+        return Err(FieldError::new(
+            format!("{}/{}", pointer, part.kind),
+            format!("A '{}' content part needs a '{}' field", part.kind, part.kind),
+        ));
+    }
+    Ok(part.kind != "text" || part.text.as_deref().is_some_and(|text| !text.is_empty()))
+}
+
+fn check_function(function: &OpenAIFunctionDef, pointer: &str) -> Result<(), FieldError> {
+    if function.name.trim().is_empty() {
+        return Err(FieldError::new(format!("{}/name", pointer), "Function names must not be empty"));
+    }
+    // Omitted parameters mean the function takes none
+    match &function.parameters {
+        serde_json::Value::Null => Ok(()),
+        serde_json::Value::Object(schema) if schema.get("type").and_then(|t| t.as_str()) == Some("object") => Ok(()),
+        serde_json::Value::Object(_) => Err(FieldError::new(
+            format!("{}/parameters/type", pointer),
+            "Function parameters must be a JSON schema with \"type\": \"object\"",
+        )),
+        _ => Err(FieldError::new(
+            format!("{}/parameters", pointer),
+            "Function parameters must be a JSON schema object",
+        )),
+    }
+}
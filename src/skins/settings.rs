@@ -0,0 +1,166 @@
+//! Per-server and per-channel chat settings for the bot interfaces
+
+use crate::types::{ChatRequestIR, ContentPart, Message, Role};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::sync::RwLock;
+
+/// Where a set of settings applies
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SettingsScope {
+    /// Server-wide defaults (a Discord guild)
+    Guild(u64),
+    /// A single channel or chat; overrides the guild defaults
+    Channel(u64),
+}
+
+/// Chat settings; unset fields fall through to the next broader scope
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChatSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+}
+
+impl ChatSettings {
+    /// Layer `self` over `base`: fields set here win
+    pub fn merged_over(&self, base: &ChatSettings) -> ChatSettings {
+        ChatSettings {
+            model: self.model.clone().or_else(|| base.model.clone()),
+            temperature: self.temperature.or(base.temperature),
+            max_tokens: self.max_tokens.or(base.max_tokens),
+            system_prompt: self.system_prompt.clone().or_else(|| base.system_prompt.clone()),
+        }
+    }
+
+    /// Apply sampling settings and the system prompt to a request
+    pub fn apply(&self, request: &mut ChatRequestIR) {
+        if let Some(temperature) = self.temperature {
+            request.sampling.temperature = Some(temperature);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            request.sampling.max_tokens = Some(max_tokens);
+        }
+        if let Some(system) = &self.system_prompt {
+            request.messages.insert(
+                0,
+                Message {
+                    role: Role::System,
+                    parts: vec![ContentPart::Text(system.clone())],
+                    name: None,
+                },
+            );
+        }
+    }
+}
+
+/// Storage for [`ChatSettings`] keyed by scope
+#[async_trait]
+pub trait SettingsStore: Send + Sync {
+    async fn get(&self, scope: SettingsScope) -> Option<ChatSettings>;
+
+    async fn set(&self, scope: SettingsScope, settings: ChatSettings) -> std::io::Result<()>;
+
+    /// Effective settings for a channel: channel over guild over `defaults`
+    async fn resolve(&self, guild: Option<u64>, channel: u64, defaults: &ChatSettings) -> ChatSettings {
+        let mut settings = defaults.clone();
+        if let Some(guild) = guild {
+            if let Some(guild_settings) = self.get(SettingsScope::Guild(guild)).await {
+                settings = guild_settings.merged_over(&settings);
+            }
+        }
+        if let Some(channel_settings) = self.get(SettingsScope::Channel(channel)).await {
+            settings = channel_settings.merged_over(&settings);
+        }
+        settings
+    }
+}
+
+/// Settings kept in memory for the lifetime of the process
+#[derive(Default)]
+pub struct InMemorySettingsStore {
+    settings: RwLock<HashMap<SettingsScope, ChatSettings>>,
+}
+
+impl InMemorySettingsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SettingsStore for InMemorySettingsStore {
+    async fn get(&self, scope: SettingsScope) -> Option<ChatSettings> {
+        self.settings.read().await.get(&scope).cloned()
+    }
+
+    async fn set(&self, scope: SettingsScope, settings: ChatSettings) -> std::io::Result<()> {
+        self.settings.write().await.insert(scope, settings);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SettingsEntry {
+    scope: SettingsScope,
+    settings: ChatSettings,
+}
+
+/// Settings persisted to a JSON file, rewritten on every change
+pub struct FileSettingsStore {
+    path: PathBuf,
+    settings: RwLock<HashMap<SettingsScope, ChatSettings>>,
+}
+
+impl FileSettingsStore {
+    /// Load settings from `path`; a missing file starts empty
+    pub async fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        let settings = match tokio::fs::read(&path).await {
+            Ok(bytes) => {
+                let entries: Vec<SettingsEntry> = serde_json::from_slice(&bytes)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                entries.into_iter().map(|e| (e.scope, e.settings)).collect()
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path,
+            settings: RwLock::new(settings),
+        })
+    }
+}
+
+#[async_trait]
+impl SettingsStore for FileSettingsStore {
+    async fn get(&self, scope: SettingsScope) -> Option<ChatSettings> {
+        self.settings.read().await.get(&scope).cloned()
+    }
+
+    async fn set(&self, scope: SettingsScope, settings: ChatSettings) -> std::io::Result<()> {
+        let mut all = self.settings.write().await;
+        all.insert(scope, settings);
+
+        let entries: Vec<SettingsEntry> = all
+            .iter()
+            .map(|(scope, settings)| SettingsEntry {
+                scope: *scope,
+                settings: settings.clone(),
+            })
+            .collect();
+        let json = serde_json::to_vec_pretty(&entries)?;
+
+        // Write to a temporary file first so a crash never leaves a truncated file
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, json).await?;
+        tokio::fs::rename(&tmp, &self.path).await
+    }
+}
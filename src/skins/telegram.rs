@@ -0,0 +1,381 @@
+//! Telegram interface (enabled with the `telegram` feature)
+//!
+//! [`TelegramBot`] talks to the Telegram Bot API directly over reqwest using
+//! long polling. Text and photo messages are answered with a streamed reply
+//! that is edited in place; `/model` and `/reset` manage the chat's model and
+//! history. Conversation state is handled by the same [`ChatSession`] as the
+//! Discord interface.
+
+use crate::engine::OmniferenceEngine;
+use crate::skins::bot::{chunk_message, ChatSession, ReplyUpdate};
+use crate::skins::settings::{ChatSettings, SettingsScope, SettingsStore};
+use crate::store::ConversationStore;
+use crate::types::ContentPart;
+use base64::Engine as _;
+use futures_util::StreamExt;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::sync::Arc;
+
+/// Maximum length of a Telegram text message
+pub const TELEGRAM_MESSAGE_LIMIT: usize = 4096;
+
+/// Configuration for [`TelegramBot`]
+#[derive(Clone, Debug)]
+pub struct TelegramBotConfig {
+    pub token: String,
+    /// Bot API base URL (override for a local Bot API server)
+    pub api_base: String,
+    /// Model id or alias to use when the chat hasn't selected one;
+    /// defaults to the first discovered model
+    pub default_model: Option<String>,
+    pub system_prompt: Option<String>,
+    /// Messages kept per chat (user and assistant turns)
+    pub history_window: usize,
+    /// Prompt token budget for each turn; the oldest turns in the window are
+    /// left out to stay within it. `None` sends the whole window.
+    pub history_tokens: Option<usize>,
+    /// Number of text deltas between edits of the streaming reply.
+    /// Telegram rate-limits edits, so this is higher than for Discord.
+    pub edit_every: usize,
+    /// Long-polling timeout for `getUpdates`
+    pub poll_timeout_secs: u64,
+}
+
+impl TelegramBotConfig {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            api_base: "https://api.telegram.org".to_string(),
+            default_model: None,
+            system_prompt: None,
+            history_window: 20,
+            history_tokens: Some(4096),
+            edit_every: 40,
+            poll_timeout_secs: 30,
+        }
+    }
+}
+
+/// Telegram bot backed by an [`OmniferenceEngine`]
+pub struct TelegramBot {
+    config: TelegramBotConfig,
+    session: ChatSession,
+    client: reqwest::Client,
+}
+
+impl TelegramBot {
+    pub fn new(engine: impl Into<Arc<OmniferenceEngine>>, config: TelegramBotConfig) -> Self {
+        let defaults = ChatSettings {
+            model: config.default_model.clone(),
+            system_prompt: config.system_prompt.clone(),
+            ..Default::default()
+        };
+        let mut session = ChatSession::new(engine, config.history_window, defaults);
+        if let Some(tokens) = config.history_tokens {
+            session = session.with_history_token_budget(tokens);
+        }
+        Self {
+            config,
+            session,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Persist per-chat model settings somewhere other than memory
+    pub fn with_settings_store(mut self, settings: Arc<dyn SettingsStore>) -> Self {
+        self.session = self.session.with_settings_store(settings);
+        self
+    }
+
+    /// Persist conversation history in `store` (as `telegram:<chat id>`) instead of memory
+    pub fn with_conversation_store(mut self, store: Arc<dyn ConversationStore>) -> Self {
+        self.session = self.session.with_conversation_store(store, "telegram");
+        self
+    }
+
+    /// Poll for updates and answer messages until an API error occurs
+    pub async fn run(self) -> Result<(), reqwest::Error> {
+        let bot = Arc::new(self);
+        let mut offset: i64 = 0;
+        loop {
+            let updates: Vec<Update> = match bot
+                .call(
+                    "getUpdates",
+                    &serde_json::json!({
+                        "offset": offset,
+                        "timeout": bot.config.poll_timeout_secs,
+                        "allowed_updates": ["message"],
+                    }),
+                )
+                .await
+            {
+                Ok(updates) => updates,
+                Err(TelegramError::Http(e)) => return Err(e),
+                Err(TelegramError::Api(description)) => {
+                    tracing::warn!(error = %description, "Telegram getUpdates failed");
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+
+            for update in updates {
+                offset = offset.max(update.update_id + 1);
+                if let Some(message) = update.message {
+                    let bot = bot.clone();
+                    tokio::spawn(async move { bot.handle_message(message).await });
+                }
+            }
+        }
+    }
+
+    fn method_url(&self, method: &str) -> String {
+        format!("{}/bot{}/{}", self.config.api_base.trim_end_matches('/'), self.config.token, method)
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, body: &serde_json::Value) -> Result<T, TelegramError> {
+        let response: ApiResponse<T> = self
+            .client
+            .post(self.method_url(method))
+            .json(body)
+            .send()
+            .await?
+            .json()
+            .await?;
+        match response.result {
+            Some(result) if response.ok => Ok(result),
+            _ => Err(TelegramError::Api(
+                response.description.unwrap_or_else(|| format!("{} failed", method)),
+            )),
+        }
+    }
+
+    async fn send_text(&self, chat_id: i64, text: &str) -> Result<i64, TelegramError> {
+        let message: TgMessage = self
+            .call("sendMessage", &serde_json::json!({ "chat_id": chat_id, "text": text }))
+            .await?;
+        Ok(message.message_id)
+    }
+
+    async fn edit_text(&self, chat_id: i64, message_id: i64, text: &str) -> Result<(), TelegramError> {
+        let _: serde_json::Value = self
+            .call(
+                "editMessageText",
+                &serde_json::json!({ "chat_id": chat_id, "message_id": message_id, "text": text }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Download a photo via `getFile` and inline it as a data URL
+    async fn photo_part(&self, photo: &PhotoSize) -> Result<ContentPart, TelegramError> {
+        let file: TgFile = self
+            .call("getFile", &serde_json::json!({ "file_id": photo.file_id }))
+            .await?;
+        let path = file
+            .file_path
+            .ok_or_else(|| TelegramError::Api("file has no download path".to_string()))?;
+        let url = format!(
+            "{}/file/bot{}/{}",
+            self.config.api_base.trim_end_matches('/'),
+            self.config.token,
+            path
+        );
+        let bytes = self.client.get(url).send().await?.error_for_status()?.bytes().await?;
+        let mime = if path.ends_with(".png") { "image/png" } else { "image/jpeg" };
+        Ok(ContentPart::ImageUrl {
+            url: format!(
+                "data:{};base64,{}",
+                mime,
+                base64::engine::general_purpose::STANDARD.encode(&bytes)
+            ),
+            mime: Some(mime.to_string()),
+        })
+    }
+
+    async fn handle_message(&self, message: TgMessage) {
+        let chat_id = message.chat.id;
+        let text = message.text.as_deref().or(message.caption.as_deref()).unwrap_or("").trim();
+
+        if let Some((command, args)) = parse_command(text) {
+            let reply = match command {
+                "model" => self.model_command(chat_id, args).await,
+                "reset" => {
+                    self.session.reset(chat_id as u64).await;
+                    "🧹 Conversation history cleared.".to_string()
+                }
+                "start" | "help" => {
+                    "Send me a message or a photo. /model lists or sets the model, /reset clears the history.".to_string()
+                }
+                _ => return,
+            };
+            if let Err(e) = self.send_text(chat_id, &reply).await {
+                tracing::warn!(error = %e, "Failed to send Telegram message");
+            }
+            return;
+        }
+
+        let mut parts = Vec::new();
+        // Telegram sends several sizes of each photo; the last is the largest
+        if let Some(photo) = message.photo.as_ref().and_then(|sizes| sizes.last()) {
+            match self.photo_part(photo).await {
+                Ok(part) => parts.push(part),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to download Telegram photo");
+                    let _ = self.send_text(chat_id, "❌ Couldn't download that photo.").await;
+                    return;
+                }
+            }
+        }
+        if !text.is_empty() {
+            parts.push(ContentPart::Text(text.to_string()));
+        }
+        if parts.is_empty() {
+            return;
+        }
+
+        self.respond(chat_id, parts).await;
+    }
+
+    async fn respond(&self, chat_id: i64, parts: Vec<ContentPart>) {
+        let mut reply = StreamingReply::new(chat_id);
+        reply.render(self, "🤔 Thinking...").await;
+        let mut updates = match self.session.start_turn(None, chat_id as u64, parts, self.config.edit_every).await {
+            Ok(updates) => updates,
+            Err(e) => {
+                reply.render(self, &format!("❌ {}", e)).await;
+                return;
+            }
+        };
+
+        while let Some(update) = updates.next().await {
+            match update {
+                ReplyUpdate::Partial(text) => reply.render(self, &text).await,
+                ReplyUpdate::Final(text) if text.trim().is_empty() => {
+                    reply.render(self, "(empty response)").await
+                }
+                ReplyUpdate::Final(text) => reply.render(self, &text).await,
+                ReplyUpdate::Failed { partial, message } => {
+                    reply.render(self, &format!("{}\n\n❌ Error: {}", partial, message)).await
+                }
+            }
+        }
+    }
+
+    async fn model_command(&self, chat_id: i64, args: &str) -> String {
+        let conversation = chat_id as u64;
+        if args.is_empty() || args == "list" {
+            let settings = self.session.effective_settings(None, conversation).await;
+            let active = self.session.active_model(&settings).await.unwrap_or_else(|| "none".to_string());
+            let mut ids: Vec<String> = self.session.engine().list_models().await.into_iter().map(|m| m.id).collect();
+            ids.sort();
+            let mut text = format!("Active model: {}\nAvailable models:", active);
+            for id in ids {
+                text.push_str(&format!("\n- {}", id));
+            }
+            text.push_str("\n\nUse /model <name> to switch.");
+            return chunk_message(&text, TELEGRAM_MESSAGE_LIMIT).swap_remove(0);
+        }
+
+        let name = args.strip_prefix("set ").unwrap_or(args).trim();
+        let model_ref = match self.session.engine().resolve_model(name).await {
+            Ok(model_ref) => model_ref,
+            Err(_) => return format!("❌ Unknown model {}. Use /model to see available models.", name),
+        };
+        let scope = SettingsScope::Channel(conversation);
+        let store = self.session.settings_store();
+        let mut settings = store.get(scope).await.unwrap_or_default();
+        settings.model = Some(model_ref.alias.clone());
+        if let Err(e) = store.set(scope, settings).await {
+            return format!("❌ Failed to save settings: {}", e);
+        }
+        format!("✅ Model set to {}.", model_ref.alias)
+    }
+}
+
+/// Split `/command@botname args` into `("command", "args")`
+fn parse_command(text: &str) -> Option<(&str, &str)> {
+    let rest = text.strip_prefix('/')?;
+    let (command, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let command = command.split('@').next().unwrap_or(command);
+    Some((command, args.trim()))
+}
+
+/// A reply that grows as content streams in, spilling over into additional
+/// messages once it exceeds the Telegram length limit.
+struct StreamingReply {
+    chat_id: i64,
+    /// Messages sent so far with the content they currently show
+    sent: Vec<(i64, String)>,
+}
+
+impl StreamingReply {
+    fn new(chat_id: i64) -> Self {
+        Self {
+            chat_id,
+            sent: Vec::new(),
+        }
+    }
+
+    async fn render(&mut self, bot: &TelegramBot, content: &str) {
+        for (index, chunk) in chunk_message(content, TELEGRAM_MESSAGE_LIMIT).into_iter().enumerate() {
+            let result = match self.sent.get(index) {
+                Some((_, shown)) if *shown == chunk => continue,
+                Some((id, _)) => bot.edit_text(self.chat_id, *id, &chunk).await.map(|_| *id),
+                None => bot.send_text(self.chat_id, &chunk).await,
+            };
+            match result {
+                Ok(id) if index < self.sent.len() => self.sent[index] = (id, chunk),
+                Ok(id) => self.sent.push((id, chunk)),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to update Telegram reply");
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum TelegramError {
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("telegram api error: {0}")]
+    Api(String),
+}
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<TgMessage>,
+}
+
+#[derive(Deserialize)]
+struct TgMessage {
+    message_id: i64,
+    chat: Chat,
+    text: Option<String>,
+    caption: Option<String>,
+    photo: Option<Vec<PhotoSize>>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+#[derive(Deserialize)]
+struct PhotoSize {
+    file_id: String,
+}
+
+#[derive(Deserialize)]
+struct TgFile {
+    file_path: Option<String>,
+}
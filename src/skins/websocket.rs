@@ -0,0 +1,311 @@
+//! WebSocket transport for Chat Completions streaming
+//!
+//! An alternative to SSE for clients behind buffering proxies. A single socket
+//! carries any number of concurrent requests, each tagged with a client-chosen
+//! id:
+//!
+//! ```json
+//! {"type": "chat", "id": "1", "request": {"model": "llama3", "messages": [...]}}
+//! {"type": "cancel", "id": "1"}
+//! ```
+//!
+//! The server answers with `chunk` frames (the same schema as the SSE stream),
+//! followed by one of `done`, `cancelled` or `error` for each request.
+//!
+//! Frames queue for the socket up to the server's
+//! [`BackPressure`](crate::backpressure::BackPressure) high-water mark. A
+//! request whose chunks find the queue full waits for the client to read
+//! on, or, when the policy disconnects slow clients, ends with a
+//! `client_too_slow` error. Chunks are never skipped.
+
+use crate::backpressure::{SlowClientPolicy, CLIENT_TOO_SLOW};
+use crate::error::EngineError;
+use crate::skins::context::SkinContext;
+use crate::skins::openai::{annotation_chunk, openai_to_chat_request, refusal_chunk, stream_chunk};
+use crate::stream::StreamEvent;
+use crate::tenant::TenantScope;
+use crate::types::{OpenAIChatRequest, OpenAIStreamChunk};
+use axum::extract::ws::{Message as WsMessage, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// Frames sent by the client
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsClientFrame {
+    /// Start a streamed chat completion; `stream` is implied
+    Chat { id: String, request: Box<OpenAIChatRequest> },
+    /// Cancel the in-flight request with this id
+    Cancel { id: String },
+}
+
+/// Frames sent by the server
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsServerFrame {
+    Chunk { id: String, chunk: OpenAIStreamChunk },
+    Done { id: String },
+    Cancelled { id: String },
+    /// `id` is absent when the frame itself couldn't be parsed
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        code: String,
+        message: String,
+    },
+}
+
+impl WsServerFrame {
+    fn error(id: Option<String>, code: impl Into<String>, message: impl Into<String>) -> Self {
+        WsServerFrame::Error {
+            id,
+            code: code.into(),
+            message: message.into(),
+        }
+    }
+}
+
+type InFlight = Arc<Mutex<HashMap<String, CancellationToken>>>;
+
+/// Requests on the socket are attributed to the API key of the upgrade
+/// request (see [`crate::budget::ApiKeyName`])
+pub async fn handle_chat_ws(
+    State(ctx): State<SkinContext>,
+    api_key: Option<axum::Extension<crate::budget::ApiKeyName>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let api_key = api_key.map(|axum::Extension(crate::budget::ApiKeyName(name))| name);
+    ws.on_upgrade(move |socket| serve_socket(ctx, api_key, socket))
+}
+
+async fn serve_socket(ctx: SkinContext, api_key: Option<String>, socket: WebSocket) {
+    let (mut sink, mut source) = socket.split();
+    let (tx, mut rx) = mpsc::channel::<WsServerFrame>(ctx.back_pressure.high_water_mark);
+
+    tokio::spawn(async move {
+        while let Some(frame) = rx.recv().await {
+            let text = match serde_json::to_string(&frame) {
+                Ok(text) => text,
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to serialize WebSocket frame");
+                    continue;
+                }
+            };
+            if sink.send(WsMessage::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Closing the socket cancels everything still running on it
+    let connection = ctx.cancel_tokens.child_token();
+    let in_flight: InFlight = Arc::new(Mutex::new(HashMap::new()));
+
+    while let Some(Ok(message)) = source.next().await {
+        let text = match message {
+            WsMessage::Text(text) => text,
+            WsMessage::Close(_) => break,
+            _ => continue,
+        };
+
+        match serde_json::from_str::<WsClientFrame>(&text) {
+            Ok(WsClientFrame::Chat { id, request }) => {
+                let cancel = connection.child_token();
+                let duplicate = {
+                    let mut in_flight = in_flight.lock().unwrap();
+                    let duplicate = in_flight.contains_key(&id);
+                    if !duplicate {
+                        in_flight.insert(id.clone(), cancel.clone());
+                    }
+                    duplicate
+                };
+                if duplicate {
+                    let _ = tx
+                        .send(WsServerFrame::error(
+                            Some(id),
+                            "duplicate_request_id",
+                            "A request with this id is already in flight",
+                        ))
+                        .await;
+                    continue;
+                }
+                tokio::spawn(run_request(
+                    ctx.clone(),
+                    id,
+                    *request,
+                    api_key.clone(),
+                    cancel,
+                    tx.clone(),
+                    in_flight.clone(),
+                ));
+            }
+            Ok(WsClientFrame::Cancel { id }) => {
+                if let Some(cancel) = in_flight.lock().unwrap().get(&id) {
+                    cancel.cancel();
+                }
+            }
+            Err(e) => {
+                let _ = tx
+                    .send(WsServerFrame::error(
+                        None,
+                        "invalid_request_body",
+                        format!("Failed to parse frame: {}", e),
+                    ))
+                    .await;
+            }
+        }
+    }
+
+    connection.cancel();
+}
+
+async fn run_request(
+    ctx: SkinContext,
+    id: String,
+    request: OpenAIChatRequest,
+    api_key: Option<String>,
+    cancel: CancellationToken,
+    tx: mpsc::Sender<WsServerFrame>,
+    in_flight: InFlight,
+) {
+    let outcome = stream_request(&ctx, &id, request, api_key, &cancel, &tx).await;
+    in_flight.lock().unwrap().remove(&id);
+
+    let frame = match outcome {
+        Ok(()) if cancel.is_cancelled() => WsServerFrame::Cancelled { id },
+        Ok(()) => WsServerFrame::Done { id },
+        Err((code, message)) => WsServerFrame::error(Some(id), code, message),
+    };
+    let _ = tx.send(frame).await;
+}
+
+/// Error frame code and message for a model that could not be resolved
+fn resolution_error(error: EngineError) -> (String, String) {
+    match &error {
+        EngineError::ModelNotFound(model) => ("model_not_found".to_string(), format!("Model '{}' not found", model)),
+        EngineError::AmbiguousModel { model, candidates } => (
+            "model_ambiguous".to_string(),
+            format!("Model '{}' is ambiguous; specify one of: {}", model, candidates.join(", ")),
+        ),
+        _ => ("provider_error".to_string(), error.to_string()),
+    }
+}
+
+/// Stream one request's chunks to `tx`; same conversion as `handle_chat`
+async fn stream_request(
+    ctx: &SkinContext,
+    id: &str,
+    mut request: OpenAIChatRequest,
+    api_key: Option<String>,
+    cancel: &CancellationToken,
+    tx: &mpsc::Sender<WsServerFrame>,
+) -> Result<(), (String, String)> {
+    crate::skins::semantic::check_chat_request(&request).map_err(|e| ("invalid_value".to_string(), e.to_string()))?;
+    let tenant = ctx.tenant_of(api_key.as_deref());
+    let scope = TenantScope::of(tenant.as_deref());
+    let model_ref = match ctx.router.routing_policy().filter(|policy| policy.handles(&request.model)) {
+        Some(policy) => policy.placeholder_model(),
+        None => {
+            let resolved = ctx.provider_manager.read().await.try_resolve_model_ref_in(&request.model, scope);
+            resolved.map_err(|error| resolution_error(error.into_engine_error(&request.model)))?
+        }
+    };
+
+    if request.n.unwrap_or(1) > 1 {
+        return Err((
+            "unsupported_n_stream".to_string(),
+            "Streaming with n > 1 is not supported yet".to_string(),
+        ));
+    }
+    request.stream = Some(true);
+
+    let mut ir = openai_to_chat_request(request, model_ref)
+        .map_err(|e| ("invalid_request_body".to_string(), e.to_string()))?;
+    crate::service::apply_routing_policy(&ctx.router, &ctx.provider_manager, &mut ir, scope)
+        .await
+        .map_err(resolution_error)?;
+    let model_alias = ir.model.alias.clone();
+    let request_id = ir.metadata.get("request_id").cloned().unwrap_or_default();
+    if let Some(api_key) = api_key {
+        ir.metadata.insert(crate::audit::API_KEY_NAME_METADATA.to_string(), api_key);
+    }
+    if let Some(tenant) = tenant {
+        ir.metadata.insert(crate::tenant::TENANT_METADATA.to_string(), tenant);
+    }
+    if !ctx.extra_body_passthrough {
+        ir.extra_body.clear();
+        ir.sampling.clear_local_fields();
+    }
+
+    let mut stream = ctx
+        .router
+        .route_chat(ir, cancel.clone())
+        .await
+        .map_err(|e| match e {
+            EngineError::ContentPolicy { .. } => ("content_policy_violation".to_string(), e.to_string()),
+            EngineError::BudgetExceeded { .. } => ("budget_exceeded".to_string(), e.to_string()),
+            _ => ("provider_error".to_string(), e.to_string()),
+        })?;
+
+    let mut finish_reason = "stop".to_string();
+    loop {
+        let event = tokio::select! {
+            _ = cancel.cancelled() => return Ok(()),
+            event = stream.next() => event,
+        };
+        let chunk = match event {
+            Some(StreamEvent::TextDelta { content }) => stream_chunk(&request_id, &model_alias, Some(content), None),
+            Some(StreamEvent::Refusal { text }) => refusal_chunk(&request_id, &model_alias, text),
+            Some(StreamEvent::Annotation { annotation }) => annotation_chunk(&request_id, &model_alias, annotation),
+            // A reply cut off at its output limit finishes with `length`
+            Some(StreamEvent::FinalMessage { finish_reason: Some(reason), .. }) if reason == "length" => {
+                finish_reason = reason;
+                continue;
+            }
+            Some(StreamEvent::Done) | None => {
+                let chunk = stream_chunk(&request_id, &model_alias, None, Some(finish_reason));
+                send_chunk(ctx, tx, WsServerFrame::Chunk { id: id.to_string(), chunk }, cancel).await?;
+                return Ok(());
+            }
+            Some(StreamEvent::Error { code, message }) => {
+                tracing::error!(%code, %message, "Stream error");
+                return Err((code, message));
+            }
+            Some(_) => continue,
+        };
+        if !send_chunk(ctx, tx, WsServerFrame::Chunk { id: id.to_string(), chunk }, cancel).await? {
+            // The socket is gone or the request was cancelled
+            return Ok(());
+        }
+    }
+}
+
+/// Queue `frame` for the socket, waiting while the queue is full unless slow
+/// clients are disconnected. False once the socket is gone or `cancel` fires.
+async fn send_chunk(
+    ctx: &SkinContext,
+    tx: &mpsc::Sender<WsServerFrame>,
+    frame: WsServerFrame,
+    cancel: &CancellationToken,
+) -> Result<bool, (String, String)> {
+    if ctx.back_pressure.policy == SlowClientPolicy::Disconnect {
+        return match tx.try_send(frame) {
+            Ok(()) => Ok(true),
+            Err(mpsc::error::TrySendError::Closed(_)) => Ok(false),
+            Err(mpsc::error::TrySendError::Full(_)) => Err((
+                CLIENT_TOO_SLOW.to_string(),
+                format!("Client fell {} frames behind the stream", ctx.back_pressure.high_water_mark),
+            )),
+        };
+    }
+    tokio::select! {
+        _ = cancel.cancelled() => Ok(false),
+        sent = tx.send(frame) => Ok(sent.is_ok()),
+    }
+}
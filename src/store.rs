@@ -0,0 +1,377 @@
+//! Conversation persistence
+//!
+//! A [`ConversationStore`] keeps chat histories beyond the lifetime of a
+//! request: the Responses API's `previous_response_id` chains and the bot
+//! interfaces' per-channel history. Messages are stored as IR [`Message`]s,
+//! so a conversation can continue on any provider.
+//!
+//! [`InMemoryConversationStore`] is the default; with the `sqlite` feature,
+//! [`SqliteConversationStore`] persists conversations to a database file.
+
+use crate::types::Message;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::RwLock;
+
+/// A stored conversation
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: String,
+    /// Owner of the conversation, for [`ConversationStore::list_by_user`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Messages, oldest first
+    #[serde(default)]
+    pub messages: Vec<Message>,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// Unix timestamps in seconds
+    #[serde(default)]
+    pub created_at: u64,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+impl Conversation {
+    pub fn new(id: impl Into<String>) -> Self {
+        let now = unix_now();
+        Self {
+            id: id.into(),
+            created_at: now,
+            updated_at: now,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn with_messages(mut self, messages: Vec<Message>) -> Self {
+        self.messages = messages;
+        self
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Storage for [`Conversation`]s keyed by id
+#[async_trait]
+pub trait ConversationStore: Send + Sync {
+    async fn get(&self, id: &str) -> std::io::Result<Option<Conversation>>;
+
+    /// Create or replace a conversation
+    async fn put(&self, conversation: Conversation) -> std::io::Result<()>;
+
+    /// Append a message, creating the conversation if it doesn't exist.
+    /// Concurrent appends to one conversation are all kept.
+    async fn append_message(&self, id: &str, message: Message) -> std::io::Result<()>;
+
+    /// Returns whether the conversation existed
+    async fn delete(&self, id: &str) -> std::io::Result<bool>;
+
+    /// A user's conversations, most recently updated first
+    async fn list_by_user(&self, user: &str) -> std::io::Result<Vec<Conversation>>;
+}
+
+/// Conversations kept in memory for the lifetime of the process
+#[derive(Default)]
+pub struct InMemoryConversationStore {
+    conversations: RwLock<HashMap<String, Conversation>>,
+}
+
+impl InMemoryConversationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ConversationStore for InMemoryConversationStore {
+    async fn get(&self, id: &str) -> std::io::Result<Option<Conversation>> {
+        Ok(self.conversations.read().await.get(id).cloned())
+    }
+
+    async fn put(&self, mut conversation: Conversation) -> std::io::Result<()> {
+        let mut conversations = self.conversations.write().await;
+        conversation.updated_at = unix_now();
+        if let Some(existing) = conversations.get(&conversation.id) {
+            conversation.created_at = existing.created_at;
+        }
+        conversations.insert(conversation.id.clone(), conversation);
+        Ok(())
+    }
+
+    async fn append_message(&self, id: &str, message: Message) -> std::io::Result<()> {
+        let mut conversations = self.conversations.write().await;
+        let conversation = conversations
+            .entry(id.to_string())
+            .or_insert_with(|| Conversation::new(id));
+        conversation.messages.push(message);
+        conversation.updated_at = unix_now();
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> std::io::Result<bool> {
+        Ok(self.conversations.write().await.remove(id).is_some())
+    }
+
+    async fn list_by_user(&self, user: &str) -> std::io::Result<Vec<Conversation>> {
+        let mut conversations: Vec<Conversation> = self
+            .conversations
+            .read()
+            .await
+            .values()
+            .filter(|c| c.user.as_deref() == Some(user))
+            .cloned()
+            .collect();
+        conversations.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then_with(|| a.id.cmp(&b.id)));
+        Ok(conversations)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteConversationStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::{unix_now, Conversation, ConversationStore};
+    use crate::types::Message;
+    use async_trait::async_trait;
+    use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+    use std::io;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS conversations (
+            id TEXT PRIMARY KEY,
+            user TEXT,
+            metadata TEXT NOT NULL DEFAULT '{}',
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS conversations_by_user ON conversations (user, updated_at);
+        CREATE TABLE IF NOT EXISTS conversation_messages (
+            conversation_id TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            message TEXT NOT NULL,
+            PRIMARY KEY (conversation_id, seq)
+        );
+    ";
+
+    fn sql(e: rusqlite::Error) -> io::Error {
+        io::Error::other(e)
+    }
+
+    fn json(e: serde_json::Error) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+
+    /// Conversations persisted to a SQLite database (enabled with the `sqlite` feature).
+    ///
+    /// Tables are created on open if missing; no migrations are needed.
+    #[derive(Clone)]
+    pub struct SqliteConversationStore {
+        conn: Arc<Mutex<Connection>>,
+    }
+
+    impl SqliteConversationStore {
+        /// Open (or create) the database at `path`
+        pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+            let path = path.as_ref().to_path_buf();
+            let conn = tokio::task::spawn_blocking(move || {
+                let conn = Connection::open(path).map_err(sql)?;
+                conn.busy_timeout(std::time::Duration::from_secs(5)).map_err(sql)?;
+                conn.pragma_update(None, "journal_mode", "WAL").map_err(sql)?;
+                Ok::<_, io::Error>(conn)
+            })
+            .await
+            .map_err(io::Error::other)??;
+            Self::with_connection(conn)
+        }
+
+        /// A private database that lives as long as the store
+        pub fn in_memory() -> io::Result<Self> {
+            Self::with_connection(Connection::open_in_memory().map_err(sql)?)
+        }
+
+        fn with_connection(conn: Connection) -> io::Result<Self> {
+            conn.execute_batch(SCHEMA).map_err(sql)?;
+            Ok(Self {
+                conn: Arc::new(Mutex::new(conn)),
+            })
+        }
+
+        async fn run<T, F>(&self, f: F) -> io::Result<T>
+        where
+            T: Send + 'static,
+            F: FnOnce(&mut Connection) -> io::Result<T> + Send + 'static,
+        {
+            let conn = self.conn.clone();
+            tokio::task::spawn_blocking(move || {
+                let mut conn = conn.lock().unwrap_or_else(|e| e.into_inner());
+                f(&mut conn)
+            })
+            .await
+            .map_err(io::Error::other)?
+        }
+    }
+
+    fn load(conn: &Connection, id: &str) -> io::Result<Option<Conversation>> {
+        let row = conn
+            .query_row(
+                "SELECT user, metadata, created_at, updated_at FROM conversations WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, i64>(3)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(sql)?;
+        let Some((user, metadata, created_at, updated_at)) = row else {
+            return Ok(None);
+        };
+
+        let mut statement = conn
+            .prepare_cached("SELECT message FROM conversation_messages WHERE conversation_id = ?1 ORDER BY seq")
+            .map_err(sql)?;
+        let messages = statement
+            .query_map(params![id], |row| row.get::<_, String>(0))
+            .map_err(sql)?
+            .map(|message| serde_json::from_str::<Message>(&message.map_err(sql)?).map_err(json))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(Some(Conversation {
+            id: id.to_string(),
+            user,
+            messages,
+            metadata: serde_json::from_str(&metadata).map_err(json)?,
+            created_at: created_at as u64,
+            updated_at: updated_at as u64,
+        }))
+    }
+
+    #[async_trait]
+    impl ConversationStore for SqliteConversationStore {
+        async fn get(&self, id: &str) -> io::Result<Option<Conversation>> {
+            let id = id.to_string();
+            self.run(move |conn| load(conn, &id)).await
+        }
+
+        async fn put(&self, conversation: Conversation) -> io::Result<()> {
+            let metadata = serde_json::to_string(&conversation.metadata).map_err(json)?;
+            let messages = conversation
+                .messages
+                .iter()
+                .map(serde_json::to_string)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(json)?;
+            self.run(move |conn| {
+                let tx = conn
+                    .transaction_with_behavior(TransactionBehavior::Immediate)
+                    .map_err(sql)?;
+                let now = unix_now() as i64;
+                tx.execute(
+                    "INSERT INTO conversations (id, user, metadata, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT (id) DO UPDATE SET
+                        user = excluded.user, metadata = excluded.metadata, updated_at = excluded.updated_at",
+                    params![conversation.id, conversation.user, metadata, conversation.created_at as i64, now],
+                )
+                .map_err(sql)?;
+                tx.execute(
+                    "DELETE FROM conversation_messages WHERE conversation_id = ?1",
+                    params![conversation.id],
+                )
+                .map_err(sql)?;
+                for (seq, message) in messages.iter().enumerate() {
+                    tx.execute(
+                        "INSERT INTO conversation_messages (conversation_id, seq, message) VALUES (?1, ?2, ?3)",
+                        params![conversation.id, seq as i64, message],
+                    )
+                    .map_err(sql)?;
+                }
+                tx.commit().map_err(sql)
+            })
+            .await
+        }
+
+        async fn append_message(&self, id: &str, message: Message) -> io::Result<()> {
+            let id = id.to_string();
+            let message = serde_json::to_string(&message).map_err(json)?;
+            self.run(move |conn| {
+                // IMMEDIATE takes the write lock up front, so concurrent
+                // appends (also from other processes) can't pick the same seq
+                let tx = conn
+                    .transaction_with_behavior(TransactionBehavior::Immediate)
+                    .map_err(sql)?;
+                let now = unix_now() as i64;
+                tx.execute(
+                    "INSERT INTO conversations (id, metadata, created_at, updated_at) VALUES (?1, '{}', ?2, ?2)
+                     ON CONFLICT (id) DO UPDATE SET updated_at = excluded.updated_at",
+                    params![id, now],
+                )
+                .map_err(sql)?;
+                tx.execute(
+                    "INSERT INTO conversation_messages (conversation_id, seq, message)
+                     SELECT ?1, COALESCE(MAX(seq) + 1, 0), ?2 FROM conversation_messages WHERE conversation_id = ?1",
+                    params![id, message],
+                )
+                .map_err(sql)?;
+                tx.commit().map_err(sql)
+            })
+            .await
+        }
+
+        async fn delete(&self, id: &str) -> io::Result<bool> {
+            let id = id.to_string();
+            self.run(move |conn| {
+                let tx = conn.transaction().map_err(sql)?;
+                tx.execute("DELETE FROM conversation_messages WHERE conversation_id = ?1", params![id])
+                    .map_err(sql)?;
+                let deleted = tx
+                    .execute("DELETE FROM conversations WHERE id = ?1", params![id])
+                    .map_err(sql)?;
+                tx.commit().map_err(sql)?;
+                Ok(deleted > 0)
+            })
+            .await
+        }
+
+        async fn list_by_user(&self, user: &str) -> io::Result<Vec<Conversation>> {
+            let user = user.to_string();
+            self.run(move |conn| {
+                let ids = {
+                    let mut statement = conn
+                        .prepare_cached("SELECT id FROM conversations WHERE user = ?1 ORDER BY updated_at DESC, id")
+                        .map_err(sql)?;
+                    let ids = statement
+                        .query_map(params![user], |row| row.get::<_, String>(0))
+                        .map_err(sql)?
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(sql)?;
+                    ids
+                };
+                let mut conversations = Vec::with_capacity(ids.len());
+                for id in ids {
+                    conversations.extend(load(conn, &id)?);
+                }
+                Ok(conversations)
+            })
+            .await
+        }
+    }
+}
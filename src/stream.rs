@@ -1,17 +1,42 @@
+//! Events streamed back from chat requests
+//!
+//! [`StreamEvent`] is also a wire format: it serializes as a JSON object with
+//! a snake_case `type` tag next to the variant's fields, e.g.
+//!
+//! ```json
+//! {"type": "text_delta", "content": "Hel"}
+//! {"type": "tokens", "input": 12, "output": 3}
+//! {"type": "done"}
+//! ```
+//!
+//! Tag names and field shapes are stable: renaming or reshaping a variant is
+//! a breaking change. New variants may be added, so consumers should ignore
+//! types they don't know. [`to_ndjson`] turns an event stream into
+//! newline-delimited JSON for forwarding over other channels.
+
+use futures_util::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 
-use crate::types::{CompletionTokensDetails, PromptTokensDetails};
+use crate::types::{CompletionTokensDetails, ContentPart, PromptTokensDetails};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum StreamEvent {
+    /// More reply text
     TextDelta {
         content: String,
     },
+    /// A tool call begins. `args_json` is usually `{}` with the arguments
+    /// following in deltas; providers that send them whole put the parsed
+    /// arguments (or the raw argument string) here.
     ToolCallStart {
         id: String,
         name: String,
         args_json: serde_json::Value,
     },
+    /// More argument text for a tool call: a JSON string holding the next
+    /// fragment of the raw argument JSON
     ToolCallDelta {
         id: String,
         args_delta_json: serde_json::Value,
@@ -22,20 +47,63 @@ pub enum StreamEvent {
     SystemNote {
         content: String,
     },
+    /// A citation or other note on a span of the reply, in the shape of an
+    /// OpenAI annotation, e.g. `{"type": "url_citation", "url_citation": {...}}`
+    Annotation {
+        annotation: serde_json::Value,
+    },
+    /// What the model said instead of answering, when it refused the request.
+    /// Streamed refusals arrive in several events whose text is concatenated.
+    Refusal {
+        text: String,
+    },
+    /// Provider-native progress update emitted before (or between) content,
+    /// e.g. queue position, `response.in_progress`, or a model still loading.
+    /// Interfaces may ignore these; they carry no generated content.
+    Status {
+        state: String,
+        detail: Option<String>,
+    },
+    /// A tool call is being executed by the engine (e.g. an MCP tool)
+    ToolExecutionStart {
+        id: String,
+        name: String,
+    },
+    /// Engine-side tool execution finished; `output` was sent back to the model
+    ToolExecutionEnd {
+        id: String,
+        name: String,
+        output: String,
+        is_error: bool,
+    },
     Tokens {
         input: u32,
         output: u32,
     },
+    /// The complete reply, sent right before `Done`. Chat routing adds one
+    /// to every stream whose adapter doesn't send it (see [`with_final_message`]).
     FinalMessage {
         content: String,
         tool_calls: Vec<ToolCallSummary>,
+        /// `stop` or `tool_calls`, or the provider's own reason when the adapter reports it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        finish_reason: Option<String>,
     },
+    #[serde(rename = "openai_metadata")]
     OpenAIMetadata {
         system_fingerprint: Option<String>,
         service_tier: Option<String>,
         prompt_tokens_details: Option<PromptTokensDetails>,
         completion_tokens_details: Option<CompletionTokensDetails>,
     },
+    /// Price of the request's usage in USD, sent before the final message
+    /// when the router has a pricing table (see [`crate::pricing`]). Each
+    /// field is `null` when the model has no price.
+    Cost {
+        input_usd: Option<f64>,
+        output_usd: Option<f64>,
+        total_usd: Option<f64>,
+    },
     Error {
         code: String,
         message: String,
@@ -43,9 +111,272 @@ pub enum StreamEvent {
     Done,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ToolCallSummary {
     pub id: String,
     pub name: String,
+    /// Parsed arguments, or the raw argument string when it isn't valid JSON
     pub args_json: serde_json::Value,
 }
+
+/// Pass `events` through, sending a `FinalMessage` with the accumulated text
+/// and tool calls before `Done` unless the stream already has one. Failed
+/// streams (ending in `Error`) get none.
+pub fn with_final_message<S>(mut events: S) -> impl Stream<Item = StreamEvent> + Send
+where
+    S: Stream<Item = StreamEvent> + Send + Unpin,
+{
+    async_stream::stream! {
+        let mut reply = PartialReply::default();
+        let mut finished = false;
+        while let Some(event) = events.next().await {
+            reply.observe(&event);
+            match &event {
+                StreamEvent::FinalMessage { .. } | StreamEvent::Error { .. } => finished = true,
+                StreamEvent::Done if !finished => {
+                    finished = true;
+                    yield std::mem::take(&mut reply).final_message(None);
+                }
+                _ => {}
+            }
+            yield event;
+        }
+        // Streams that end without `Done` still get their final message
+        if !finished {
+            yield reply.final_message(None);
+        }
+    }
+}
+
+/// The text and tool calls of a reply so far, collected from its events
+#[derive(Debug, Default)]
+pub(crate) struct PartialReply {
+    content: String,
+    /// (id, name, raw arguments) in call order
+    calls: Vec<(String, String, String)>,
+}
+
+impl PartialReply {
+    pub(crate) fn observe(&mut self, event: &StreamEvent) {
+        match event {
+            StreamEvent::TextDelta { content } => self.content.push_str(content),
+            StreamEvent::ToolCallStart { id, name, args_json } => {
+                let arguments = match args_json {
+                    serde_json::Value::Object(map) if map.is_empty() => String::new(),
+                    other => ContentPart::arguments_string(other),
+                };
+                self.calls.push((id.clone(), name.clone(), arguments));
+            }
+            StreamEvent::ToolCallDelta { id, args_delta_json } => {
+                if let Some((_, _, arguments)) = self.calls.iter_mut().find(|(call, _, _)| call == id) {
+                    arguments.push_str(&ContentPart::arguments_string(args_delta_json));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The `FinalMessage` of the reply so far (see [`final_message`])
+    pub(crate) fn final_message(self, finish_reason: Option<String>) -> StreamEvent {
+        final_message(self.content, self.calls, finish_reason)
+    }
+}
+
+/// The `FinalMessage` for `content` and `calls` (id, name, raw arguments);
+/// without a `finish_reason` it is `tool_calls` or `stop`
+pub(crate) fn final_message(
+    content: String,
+    calls: Vec<(String, String, String)>,
+    finish_reason: Option<String>,
+) -> StreamEvent {
+    let finish_reason = finish_reason
+        .unwrap_or_else(|| if calls.is_empty() { "stop" } else { "tool_calls" }.to_string());
+    let tool_calls = calls
+        .into_iter()
+        .map(|(id, name, arguments)| ToolCallSummary {
+            id,
+            name,
+            args_json: if arguments.trim().is_empty() {
+                serde_json::json!({})
+            } else {
+                serde_json::from_str(&arguments).unwrap_or(serde_json::Value::String(arguments))
+            },
+        })
+        .collect();
+    StreamEvent::FinalMessage {
+        content,
+        tool_calls,
+        finish_reason: Some(finish_reason),
+    }
+}
+
+/// Corrects the order of an adapter's events. Chat routing passes every
+/// adapter stream through one (see [`normalize`]), so consumers can rely on:
+///
+/// - at most one `Done`, and nothing but `Tokens` arriving after it; those are
+///   moved before it, and `Done` is held back until the stream ends
+/// - `Error` ending the stream
+/// - `ToolCallDelta` and `ToolCallEnd` only for a call that is open, a call
+///   id started once, and calls still open at `Done` ended right before it
+///
+/// Events breaking these rules are dropped or moved, and recorded as
+/// violations.
+#[derive(Debug, Default)]
+pub struct EventOrder {
+    /// Calls started and not yet ended, in start order
+    open_calls: Vec<String>,
+    started_calls: std::collections::HashSet<String>,
+    /// `Done` arrived and is held back
+    done: bool,
+    /// `Error` arrived, or the held `Done` was sent
+    ended: bool,
+    violations: Vec<String>,
+}
+
+impl EventOrder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The events to send for `event`, in order
+    pub fn push(&mut self, event: StreamEvent) -> Vec<StreamEvent> {
+        if self.ended {
+            self.violation(format!("{} after the stream ended", event_name(&event)));
+            return Vec::new();
+        }
+        if self.done {
+            return match event {
+                StreamEvent::Tokens { .. } => {
+                    self.violation("Tokens after Done".to_string());
+                    vec![event]
+                }
+                other => {
+                    self.violation(format!("{} after Done", event_name(&other)));
+                    Vec::new()
+                }
+            };
+        }
+        match event {
+            StreamEvent::Done => {
+                self.done = true;
+                let unended = std::mem::take(&mut self.open_calls);
+                for id in &unended {
+                    self.violation(format!("tool call {} not ended before Done", id));
+                }
+                unended.into_iter().map(|id| StreamEvent::ToolCallEnd { id }).collect()
+            }
+            StreamEvent::Error { .. } => {
+                self.ended = true;
+                vec![event]
+            }
+            StreamEvent::ToolCallStart { ref id, .. } => {
+                if !self.started_calls.insert(id.clone()) {
+                    self.violation(format!("tool call {} started twice", id));
+                    return Vec::new();
+                }
+                self.open_calls.push(id.clone());
+                vec![event]
+            }
+            StreamEvent::ToolCallDelta { ref id, .. } if !self.open_calls.contains(id) => {
+                self.violation(format!("ToolCallDelta for tool call {}, which isn't open", id));
+                Vec::new()
+            }
+            StreamEvent::ToolCallEnd { ref id } => match self.open_calls.iter().position(|open| open == id) {
+                Some(index) => {
+                    self.open_calls.remove(index);
+                    vec![event]
+                }
+                None => {
+                    self.violation(format!("ToolCallEnd for tool call {}, which isn't open", id));
+                    Vec::new()
+                }
+            },
+            other => vec![other],
+        }
+    }
+
+    /// The events to send once the stream has ended: the held `Done`
+    pub fn finish(&mut self) -> Vec<StreamEvent> {
+        if !self.done || self.ended {
+            return Vec::new();
+        }
+        self.ended = true;
+        vec![StreamEvent::Done]
+    }
+
+    /// The violations recorded since the last call
+    pub fn take_violations(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.violations)
+    }
+
+    fn violation(&mut self, violation: String) {
+        self.violations.push(violation);
+    }
+}
+
+/// The variant name of `event`, e.g. `TextDelta`
+pub(crate) fn event_name(event: &StreamEvent) -> &'static str {
+    match event {
+        StreamEvent::TextDelta { .. } => "TextDelta",
+        StreamEvent::ToolCallStart { .. } => "ToolCallStart",
+        StreamEvent::ToolCallDelta { .. } => "ToolCallDelta",
+        StreamEvent::ToolCallEnd { .. } => "ToolCallEnd",
+        StreamEvent::SystemNote { .. } => "SystemNote",
+        StreamEvent::Annotation { .. } => "Annotation",
+        StreamEvent::Refusal { .. } => "Refusal",
+        StreamEvent::Status { .. } => "Status",
+        StreamEvent::ToolExecutionStart { .. } => "ToolExecutionStart",
+        StreamEvent::ToolExecutionEnd { .. } => "ToolExecutionEnd",
+        StreamEvent::Tokens { .. } => "Tokens",
+        StreamEvent::FinalMessage { .. } => "FinalMessage",
+        StreamEvent::OpenAIMetadata { .. } => "OpenAIMetadata",
+        StreamEvent::Cost { .. } => "Cost",
+        StreamEvent::Error { .. } => "Error",
+        StreamEvent::Done => "Done",
+    }
+}
+
+/// Pass `events` from an adapter of `kind` through an [`EventOrder`],
+/// logging each correction
+pub fn normalize<S>(kind: crate::types::ProviderKind, mut events: S) -> impl Stream<Item = StreamEvent> + Send
+where
+    S: Stream<Item = StreamEvent> + Send + Unpin,
+{
+    async_stream::stream! {
+        let mut order = EventOrder::new();
+        while let Some(event) = events.next().await {
+            let passed = order.push(event);
+            report_violations(&kind, &mut order);
+            for event in passed {
+                yield event;
+            }
+        }
+        for event in order.finish() {
+            yield event;
+        }
+    }
+}
+
+fn report_violations(kind: &crate::types::ProviderKind, order: &mut EventOrder) {
+    for violation in order.take_violations() {
+        tracing::warn!(provider_kind = %kind, violation = %violation, "Corrected the adapter's event order");
+    }
+}
+
+/// One JSON object per event, each line ending in `\n`
+pub fn to_ndjson<S>(events: S) -> impl Stream<Item = String> + Send
+where
+    S: Stream<Item = StreamEvent> + Send,
+{
+    events.map(|event| {
+        let mut line = serde_json::to_string(&event).unwrap_or_else(|e| {
+            let error = StreamEvent::Error {
+                code: "serialization_error".to_string(),
+                message: e.to_string(),
+            };
+            serde_json::to_string(&error).unwrap_or_default()
+        });
+        line.push('\n');
+        line
+    })
+}
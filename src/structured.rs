@@ -0,0 +1,109 @@
+//! Structured outputs: request JSON matching a Rust type and parse the reply
+//!
+//! Used by `OmniferenceEngine::chat_structured`; requires the `structured`
+//! feature.
+
+use crate::types::{ChatRequestIR, ContentPart, Message, ResponseFormat, Role};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+
+/// Schema name and JSON schema generated for `T`
+pub fn schema_for<T: JsonSchema>() -> (String, serde_json::Value) {
+    // Providers only accept `[a-zA-Z0-9_-]` in schema names
+    let name = T::schema_name()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    (name, schemars::schema_for!(T).to_value())
+}
+
+/// Rewrite a schema for strict mode, which requires every object to list all
+/// of its properties as required and forbid additional ones. Returns `None`
+/// when the schema has optional properties and can't be made strict.
+pub fn strict_schema(schema: &serde_json::Value) -> Option<serde_json::Value> {
+    let mut schema = schema.clone();
+    make_strict(&mut schema).then_some(schema)
+}
+
+fn make_strict(value: &mut serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Object(map) => {
+            if let Some(serde_json::Value::Object(properties)) = map.get("properties") {
+                let required: Vec<&str> = map
+                    .get("required")
+                    .and_then(|r| r.as_array())
+                    .map(|r| r.iter().filter_map(|v| v.as_str()).collect())
+                    .unwrap_or_default();
+                if !properties.keys().all(|key| required.contains(&key.as_str())) {
+                    return false;
+                }
+                map.insert("additionalProperties".to_string(), serde_json::Value::Bool(false));
+            }
+            map.values_mut().all(make_strict)
+        }
+        serde_json::Value::Array(items) => items.iter_mut().all(make_strict),
+        _ => true,
+    }
+}
+
+/// Constrain a request to the schema: via `response_format` when the model
+/// supports it (strict when the schema allows), otherwise by describing the
+/// schema in a system message.
+pub fn apply_schema(request: &mut ChatRequestIR, name: String, schema: serde_json::Value, supports_json: bool) {
+    if supports_json {
+        let strict = strict_schema(&schema);
+        request.response_format = Some(ResponseFormat::JsonSchema {
+            name,
+            description: None,
+            strict: Some(strict.is_some()),
+            schema: strict.unwrap_or(schema),
+        });
+        return;
+    }
+
+    let instructions = format!(
+        "Respond only with a JSON value matching this JSON schema, with no other text:\n{}",
+        schema
+    );
+    let position = request
+        .messages
+        .iter()
+        .take_while(|m| matches!(m.role, Role::System | Role::Developer))
+        .count();
+    request.messages.insert(
+        position,
+        Message {
+            role: Role::System,
+            parts: vec![ContentPart::Text(instructions)],
+            name: None,
+        },
+    );
+}
+
+/// Parse a reply, tolerating a surrounding Markdown code fence
+pub fn parse_reply<T: DeserializeOwned>(content: &str) -> Result<T, serde_json::Error> {
+    let trimmed = content.trim();
+    let unfenced = trimmed
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(|body| body.strip_prefix("json").unwrap_or(body))
+        .unwrap_or(trimmed);
+    serde_json::from_str(unfenced.trim())
+}
+
+/// Append the rejected reply and the parse error so the model can correct it
+pub fn append_correction(request: &mut ChatRequestIR, content: String, error: &serde_json::Error) {
+    request.messages.push(Message {
+        role: Role::Assistant,
+        parts: vec![ContentPart::Text(content)],
+        name: None,
+    });
+    request.messages.push(Message {
+        role: Role::User,
+        parts: vec![ContentPart::Text(format!(
+            "That reply did not match the schema: {}. Respond again with only the corrected JSON.",
+            error
+        ))],
+        name: None,
+    });
+}
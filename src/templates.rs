@@ -0,0 +1,893 @@
+//! Prompt templates for backends that take a single prompt
+//!
+//! Backends without a chat endpoint, such as TGI's `/generate`, need the
+//! conversation flattened into one string in the format the model was tuned
+//! on. A [`PromptTemplate`] is one of the common formats (`chatml`,
+//! `llama3`, `mistral`), a role-prefix [`ChatTemplate`], or a custom
+//! template string. [`render_prompt`] renders messages with it and
+//! [`stop_tokens`] gives the sequences that end the model's turn.
+//!
+//! Custom templates use the subset of Jinja that Hugging Face chat
+//! templates mostly keep to:
+//!
+//! ```text
+//! {% for message in messages %}<|{{ message.role }}|>
+//! {{ message.content | trim }}<|end|>
+//! {% endfor %}{% if add_generation_prompt %}<|assistant|>
+//! {% endif %}
+//! ```
+//!
+//! - `{{ expr }}`, `{% if %}`/`{% elif %}`/`{% else %}`/`{% endif %}`,
+//!   `{% for x in list %}`/`{% endfor %}` and `{# comments #}`
+//! - string and integer literals, `true`/`false`, `message.role`,
+//!   `message['role']`, `messages[0]`, `==`, `!=`, `and`, `or`, `not`, `+`
+//!   and the `trim` and `length` filters
+//! - `loop.index`, `loop.index0`, `loop.first`, `loop.last` and
+//!   `loop.length` inside loops
+//! - `{%-` and `-%}` strip the whitespace before and after a tag; as in
+//!   Hugging Face's environment, the newline after a block tag and the
+//!   indentation before one are dropped
+//!
+//! Templates see `messages` (each with a `role` and `content`, developer
+//! messages as `system`), `bos_token`, `eos_token` and
+//! `add_generation_prompt`, which is always true.
+
+use crate::types::{ContentPart, Message, Role};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How messages are flattened into a prompt
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum PromptTemplate {
+    /// One of the built-in formats, by name
+    Format(TemplateFormat),
+    /// A template string, or an object with one and its special tokens
+    Custom(CustomTemplate),
+    /// Role prefixes
+    Prefixes(ChatTemplate),
+}
+
+impl Default for PromptTemplate {
+    fn default() -> Self {
+        Self::Prefixes(ChatTemplate::default())
+    }
+}
+
+impl From<ChatTemplate> for PromptTemplate {
+    fn from(template: ChatTemplate) -> Self {
+        Self::Prefixes(template)
+    }
+}
+
+impl From<TemplateFormat> for PromptTemplate {
+    fn from(format: TemplateFormat) -> Self {
+        Self::Format(format)
+    }
+}
+
+impl PromptTemplate {
+    /// Check that a custom template parses
+    pub fn validate(&self) -> Result<(), TemplateError> {
+        match self {
+            Self::Custom(custom) => parse(&custom.template).map(|_| ()),
+            Self::Format(_) | Self::Prefixes(_) => Ok(()),
+        }
+    }
+}
+
+/// Built-in prompt formats
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateFormat {
+    /// `<|im_start|>role\n...<|im_end|>\n`, as used by Qwen and many
+    /// fine-tunes
+    ChatMl,
+    /// Llama 3's `<|start_header_id|>role<|end_header_id|>\n\n...<|eot_id|>`;
+    /// tool results use the `ipython` role
+    Llama3,
+    /// Mistral's `[INST] ... [/INST]`; system messages are folded into the
+    /// next user turn and tool results are sent as user turns
+    Mistral,
+}
+
+/// A custom template string with the special tokens it refers to
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(from = "CustomTemplateRepr")]
+pub struct CustomTemplate {
+    pub template: String,
+    /// Value of `bos_token`
+    pub bos_token: String,
+    /// Value of `eos_token`
+    pub eos_token: String,
+    /// Stop sequences; derived from the template when unset (see
+    /// [`stop_tokens`])
+    pub stop: Option<Vec<String>>,
+}
+
+impl CustomTemplate {
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            bos_token: String::new(),
+            eos_token: String::new(),
+            stop: None,
+        }
+    }
+}
+
+/// A bare string is the template alone
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CustomTemplateRepr {
+    Source(String),
+    Full {
+        template: String,
+        #[serde(default)]
+        bos_token: String,
+        #[serde(default)]
+        eos_token: String,
+        #[serde(default)]
+        stop: Option<Vec<String>>,
+    },
+}
+
+impl From<CustomTemplateRepr> for CustomTemplate {
+    fn from(repr: CustomTemplateRepr) -> Self {
+        match repr {
+            CustomTemplateRepr::Source(template) => Self::new(template),
+            CustomTemplateRepr::Full { template, bos_token, eos_token, stop } => Self {
+                template,
+                bos_token,
+                eos_token,
+                stop,
+            },
+        }
+    }
+}
+
+/// Role-prefix template flattening messages into a single prompt. Each
+/// message becomes its role's prefix, its text and `end_of_turn`; the
+/// prompt ends with the assistant prefix for the model to continue.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ChatTemplate {
+    /// Text the prompt starts with, e.g. a BOS token
+    pub prefix: String,
+    /// Prefix of system and developer messages
+    pub system: String,
+    /// Prefix of user messages and tool results
+    pub user: String,
+    pub assistant: String,
+    pub end_of_turn: String,
+    /// Stop sequences sent with every request, e.g. the next user prefix
+    pub stop: Vec<String>,
+}
+
+impl Default for ChatTemplate {
+    fn default() -> Self {
+        Self {
+            prefix: String::new(),
+            system: "System: ".to_string(),
+            user: "User: ".to_string(),
+            assistant: "Assistant: ".to_string(),
+            end_of_turn: "\n".to_string(),
+            stop: vec!["\nUser:".to_string()],
+        }
+    }
+}
+
+impl ChatTemplate {
+    /// The prompt for `messages`, ending with the assistant prefix. Only
+    /// text and tool results are rendered.
+    pub fn render(&self, messages: &[Message]) -> String {
+        let mut prompt = self.prefix.clone();
+        for message in messages {
+            prompt.push_str(match message.role {
+                Role::System | Role::Developer => &self.system,
+                Role::User | Role::Tool => &self.user,
+                Role::Assistant => &self.assistant,
+            });
+            prompt.push_str(&message_text(message));
+            prompt.push_str(&self.end_of_turn);
+        }
+        prompt.push_str(&self.assistant);
+        prompt
+    }
+}
+
+/// A custom template that doesn't parse
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid template: {0}")]
+pub struct TemplateError(pub String);
+
+/// End-of-turn markers looked for in custom templates without explicit
+/// stop sequences
+const KNOWN_END_TOKENS: &[&str] = &["<|im_end|>", "<|eot_id|>", "<|end|>", "<end_of_turn>", "<|endoftext|>", "</s>"];
+
+/// The prompt for `messages` in `template`, ending where the assistant's
+/// reply starts. Only text and tool results are rendered. A custom template
+/// that doesn't parse falls back to [`ChatTemplate::default`]; config files
+/// are checked for that on load.
+pub fn render_prompt(messages: &[Message], template: &PromptTemplate) -> String {
+    match template {
+        PromptTemplate::Format(TemplateFormat::ChatMl) => {
+            let mut prompt = String::new();
+            for message in messages {
+                let role = match message.role {
+                    Role::System | Role::Developer => "system",
+                    Role::User => "user",
+                    Role::Assistant => "assistant",
+                    Role::Tool => "tool",
+                };
+                prompt.push_str(&format!("<|im_start|>{}\n{}<|im_end|>\n", role, message_text(message)));
+            }
+            prompt.push_str("<|im_start|>assistant\n");
+            prompt
+        }
+        PromptTemplate::Format(TemplateFormat::Llama3) => {
+            let mut prompt = "<|begin_of_text|>".to_string();
+            for message in messages {
+                let role = match message.role {
+                    Role::System | Role::Developer => "system",
+                    Role::User => "user",
+                    Role::Assistant => "assistant",
+                    Role::Tool => "ipython",
+                };
+                prompt.push_str(&format!(
+                    "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
+                    role,
+                    message_text(message).trim()
+                ));
+            }
+            prompt.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+            prompt
+        }
+        PromptTemplate::Format(TemplateFormat::Mistral) => {
+            let mut prompt = "<s>".to_string();
+            let mut system = Vec::new();
+            for message in messages {
+                let text = message_text(message);
+                match message.role {
+                    Role::System | Role::Developer => system.push(text),
+                    Role::User | Role::Tool => {
+                        system.push(text);
+                        prompt.push_str(&format!("[INST] {} [/INST]", system.join("\n\n")));
+                        system.clear();
+                    }
+                    Role::Assistant => prompt.push_str(&format!("{}</s>", text)),
+                }
+            }
+            // System messages after the last user turn still reach the model
+            if !system.is_empty() {
+                prompt.push_str(&format!("[INST] {} [/INST]", system.join("\n\n")));
+            }
+            prompt
+        }
+        PromptTemplate::Prefixes(template) => template.render(messages),
+        PromptTemplate::Custom(custom) => match parse(&custom.template) {
+            Ok(nodes) => {
+                let mut scope = vec![
+                    ("messages".to_string(), Value::List(messages.iter().map(message_value).collect())),
+                    ("bos_token".to_string(), Value::Str(custom.bos_token.clone())),
+                    ("eos_token".to_string(), Value::Str(custom.eos_token.clone())),
+                    ("add_generation_prompt".to_string(), Value::Bool(true)),
+                ];
+                let mut prompt = String::new();
+                render_nodes(&nodes, &mut scope, &mut prompt);
+                prompt
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Falling back to the default chat template");
+                ChatTemplate::default().render(messages)
+            }
+        },
+    }
+}
+
+/// Stop sequences ending the model's turn in `template`. Custom templates
+/// without their own use the known end-of-turn markers they contain, and
+/// their `eos_token`.
+pub fn stop_tokens(template: &PromptTemplate) -> Vec<String> {
+    match template {
+        PromptTemplate::Format(TemplateFormat::ChatMl) => vec!["<|im_end|>".to_string()],
+        PromptTemplate::Format(TemplateFormat::Llama3) => vec!["<|eot_id|>".to_string()],
+        PromptTemplate::Format(TemplateFormat::Mistral) => vec!["</s>".to_string()],
+        PromptTemplate::Prefixes(template) => template.stop.clone(),
+        PromptTemplate::Custom(custom) => {
+            if let Some(stop) = &custom.stop {
+                return stop.clone();
+            }
+            let mut stop: Vec<String> = KNOWN_END_TOKENS
+                .iter()
+                .filter(|token| custom.template.contains(*token))
+                .map(|token| token.to_string())
+                .collect();
+            if !custom.eos_token.is_empty() && !stop.contains(&custom.eos_token) {
+                stop.push(custom.eos_token.clone());
+            }
+            stop
+        }
+    }
+}
+
+/// A message's text parts and tool results, concatenated
+fn message_text(message: &Message) -> String {
+    let mut text = String::new();
+    for part in &message.parts {
+        match part {
+            ContentPart::Text(part) => text.push_str(part),
+            ContentPart::ToolResult { content, .. } => text.push_str(content),
+            _ => {}
+        }
+    }
+    text
+}
+
+fn message_value(message: &Message) -> Value {
+    let role = match message.role {
+        Role::System | Role::Developer => "system",
+        Role::User => "user",
+        Role::Assistant => "assistant",
+        Role::Tool => "tool",
+    };
+    let mut fields = BTreeMap::from([
+        ("role".to_string(), Value::Str(role.to_string())),
+        ("content".to_string(), Value::Str(message_text(message))),
+    ]);
+    if let Some(name) = &message.name {
+        fields.insert("name".to_string(), Value::Str(name.clone()));
+    }
+    Value::Map(fields)
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    Undefined,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    fn truthy(&self) -> bool {
+        match self {
+            Value::Undefined => false,
+            Value::Bool(b) => *b,
+            Value::Int(i) => *i != 0,
+            Value::Str(s) => !s.is_empty(),
+            Value::List(items) => !items.is_empty(),
+            Value::Map(fields) => !fields.is_empty(),
+        }
+    }
+
+    fn render(&self, out: &mut String) {
+        match self {
+            Value::Undefined | Value::List(_) | Value::Map(_) => {}
+            Value::Bool(true) => out.push_str("True"),
+            Value::Bool(false) => out.push_str("False"),
+            Value::Int(i) => out.push_str(&i.to_string()),
+            Value::Str(s) => out.push_str(s),
+        }
+    }
+
+    fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Undefined, Value::Undefined) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Output(Expr),
+    If(Vec<(Expr, Vec<Node>)>, Vec<Node>),
+    For { var: String, iterable: Expr, body: Vec<Node> },
+}
+
+#[derive(Debug)]
+enum Expr {
+    Literal(Value),
+    Var(String),
+    Get(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare { left: Box<Expr>, right: Box<Expr>, equal: bool },
+    Concat(Box<Expr>, Box<Expr>),
+    Filter(Box<Expr>, Filter),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Filter {
+    Trim,
+    Length,
+}
+
+fn lookup(scope: &[(String, Value)], name: &str) -> Value {
+    scope
+        .iter()
+        .rev()
+        .find(|(bound, _)| bound == name)
+        .map(|(_, value)| value.clone())
+        .unwrap_or(Value::Undefined)
+}
+
+fn eval(expr: &Expr, scope: &[(String, Value)]) -> Value {
+    match expr {
+        Expr::Literal(value) => value.clone(),
+        Expr::Var(name) => lookup(scope, name),
+        Expr::Get(base, key) => match (eval(base, scope), eval(key, scope)) {
+            (Value::Map(mut fields), Value::Str(key)) => fields.remove(&key).unwrap_or(Value::Undefined),
+            (Value::List(items), Value::Int(index)) => {
+                let index = if index < 0 { items.len() as i64 + index } else { index };
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|index| items.get(index).cloned())
+                    .unwrap_or(Value::Undefined)
+            }
+            _ => Value::Undefined,
+        },
+        Expr::Not(inner) => Value::Bool(!eval(inner, scope).truthy()),
+        Expr::And(left, right) => Value::Bool(eval(left, scope).truthy() && eval(right, scope).truthy()),
+        Expr::Or(left, right) => Value::Bool(eval(left, scope).truthy() || eval(right, scope).truthy()),
+        Expr::Compare { left, right, equal } => Value::Bool(eval(left, scope).equals(&eval(right, scope)) == *equal),
+        Expr::Concat(left, right) => {
+            let mut out = String::new();
+            eval(left, scope).render(&mut out);
+            eval(right, scope).render(&mut out);
+            Value::Str(out)
+        }
+        Expr::Filter(inner, Filter::Trim) => {
+            let mut out = String::new();
+            eval(inner, scope).render(&mut out);
+            Value::Str(out.trim().to_string())
+        }
+        Expr::Filter(inner, Filter::Length) => Value::Int(match eval(inner, scope) {
+            Value::Str(s) => s.chars().count() as i64,
+            Value::List(items) => items.len() as i64,
+            Value::Map(fields) => fields.len() as i64,
+            _ => 0,
+        }),
+    }
+}
+
+fn render_nodes(nodes: &[Node], scope: &mut Vec<(String, Value)>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Output(expr) => eval(expr, scope).render(out),
+            Node::If(branches, otherwise) => {
+                let body = branches
+                    .iter()
+                    .find(|(condition, _)| eval(condition, scope).truthy())
+                    .map(|(_, body)| body)
+                    .unwrap_or(otherwise);
+                render_nodes(body, scope, out);
+            }
+            Node::For { var, iterable, body } => {
+                let Value::List(items) = eval(iterable, scope) else {
+                    continue;
+                };
+                let length = items.len();
+                for (index, item) in items.into_iter().enumerate() {
+                    let state = BTreeMap::from([
+                        ("index0".to_string(), Value::Int(index as i64)),
+                        ("index".to_string(), Value::Int(index as i64 + 1)),
+                        ("first".to_string(), Value::Bool(index == 0)),
+                        ("last".to_string(), Value::Bool(index + 1 == length)),
+                        ("length".to_string(), Value::Int(length as i64)),
+                    ]);
+                    scope.push(("loop".to_string(), Value::Map(state)));
+                    scope.push((var.clone(), item));
+                    render_nodes(body, scope, out);
+                    scope.truncate(scope.len() - 2);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Output(String),
+    Tag(String),
+}
+
+/// Split a template into text, `{{ }}` and `{% %}` tokens, applying
+/// whitespace control and dropping comments
+fn lex(source: &str) -> Result<Vec<Token>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    let mut at_start = true;
+    let mut trim_next = false;
+    let mut after_block = false;
+
+    loop {
+        let open = ["{{", "{%", "{#"]
+            .iter()
+            .filter_map(|marker| rest.find(marker))
+            .min();
+        let mut text = &rest[..open.unwrap_or(rest.len())];
+        if trim_next {
+            text = text.trim_start();
+        } else if after_block {
+            text = text.strip_prefix("\r\n").or_else(|| text.strip_prefix('\n')).unwrap_or(text);
+        }
+
+        let Some(open) = open else {
+            if !text.is_empty() {
+                tokens.push(Token::Text(text.to_string()));
+            }
+            return Ok(tokens);
+        };
+
+        let kind = &rest[open..open + 2];
+        let inner_start = open + 2;
+        let trim_before = rest[inner_start..].starts_with('-');
+        if trim_before {
+            text = text.trim_end();
+        } else if kind != "{{" {
+            // Indentation before a block tag, on a line of its own
+            let line_start = match text.rfind('\n') {
+                Some(newline) => Some(newline + 1),
+                None if at_start => Some(0),
+                None => None,
+            };
+            if let Some(line_start) = line_start.filter(|&i| text[i..].chars().all(|c| c == ' ' || c == '\t')) {
+                text = &text[..line_start];
+            }
+        }
+        if !text.is_empty() {
+            tokens.push(Token::Text(text.to_string()));
+        }
+
+        let close_marker = match kind {
+            "{{" => "}}",
+            "{%" => "%}",
+            _ => "#}",
+        };
+        let close = rest[inner_start..]
+            .find(close_marker)
+            .map(|i| i + inner_start)
+            .ok_or_else(|| TemplateError(format!("unclosed \"{}\"", kind)))?;
+        let mut inner = &rest[inner_start..close];
+        if trim_before {
+            inner = &inner[1..];
+        }
+        trim_next = inner.ends_with('-');
+        if trim_next {
+            inner = &inner[..inner.len() - 1];
+        }
+        match kind {
+            "{{" => tokens.push(Token::Output(inner.trim().to_string())),
+            "{%" => tokens.push(Token::Tag(inner.trim().to_string())),
+            _ => {}
+        }
+
+        after_block = kind != "{{";
+        at_start = false;
+        rest = &rest[close + 2..];
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<Node>, TemplateError> {
+    let mut parser = Parser { tokens: lex(source)?.into_iter() };
+    let (nodes, _) = parser.block(&[])?;
+    Ok(nodes)
+}
+
+struct Parser {
+    tokens: std::vec::IntoIter<Token>,
+}
+
+impl Parser {
+    /// Nodes up to one of the `ends` tags, which is returned; `None` at the
+    /// end of the template, which is only allowed without `ends`
+    fn block(&mut self, ends: &[&str]) -> Result<(Vec<Node>, Option<String>), TemplateError> {
+        let mut nodes = Vec::new();
+        while let Some(token) = self.tokens.next() {
+            match token {
+                Token::Text(text) => nodes.push(Node::Text(text)),
+                Token::Output(source) => nodes.push(Node::Output(parse_expr(&source)?)),
+                Token::Tag(tag) => {
+                    let (keyword, args) = tag.split_once(char::is_whitespace).unwrap_or((&tag, ""));
+                    if ends.contains(&keyword) {
+                        return Ok((nodes, Some(tag)));
+                    }
+                    match keyword {
+                        "if" => nodes.push(self.if_node(args)?),
+                        "for" => {
+                            let (var, iterable) = args
+                                .split_once(" in ")
+                                .ok_or_else(|| TemplateError(format!("expected \"for x in list\", got \"{}\"", tag)))?;
+                            let var = var.trim();
+                            if !is_identifier(var) {
+                                return Err(TemplateError(format!("invalid loop variable \"{}\"", var)));
+                            }
+                            let iterable = parse_expr(iterable)?;
+                            let (body, _) = self.expect_block(&["endfor"])?;
+                            nodes.push(Node::For { var: var.to_string(), iterable, body });
+                        }
+                        "elif" | "else" | "endif" | "endfor" => {
+                            return Err(TemplateError(format!("unexpected \"{{% {} %}}\"", tag)))
+                        }
+                        _ => return Err(TemplateError(format!("unsupported tag \"{{% {} %}}\"", tag))),
+                    }
+                }
+            }
+        }
+        if ends.is_empty() {
+            Ok((nodes, None))
+        } else {
+            Err(TemplateError(format!("missing \"{{% {} %}}\"", ends[ends.len() - 1])))
+        }
+    }
+
+    fn expect_block(&mut self, ends: &[&str]) -> Result<(Vec<Node>, String), TemplateError> {
+        let (nodes, end) = self.block(ends)?;
+        Ok((nodes, end.unwrap_or_default()))
+    }
+
+    fn if_node(&mut self, condition: &str) -> Result<Node, TemplateError> {
+        let mut branches = Vec::new();
+        let mut condition = parse_expr(condition)?;
+        loop {
+            let (body, end) = self.expect_block(&["elif", "else", "endif"])?;
+            branches.push((condition, body));
+            let (keyword, args) = end.split_once(char::is_whitespace).unwrap_or((&end, ""));
+            match keyword {
+                "elif" => condition = parse_expr(args)?,
+                "else" => {
+                    let (otherwise, _) = self.expect_block(&["endif"])?;
+                    return Ok(Node::If(branches, otherwise));
+                }
+                _ => return Ok(Node::If(branches, Vec::new())),
+            }
+        }
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ExprToken {
+    Str(String),
+    Int(i64),
+    Ident(String),
+    Op(&'static str),
+}
+
+fn lex_expr(source: &str) -> Result<Vec<ExprToken>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(TemplateError(format!("unterminated string in \"{}\"", source))),
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 't')) => value.push('\t'),
+                            Some((_, escaped)) => value.push(escaped),
+                            None => return Err(TemplateError(format!("unterminated string in \"{}\"", source))),
+                        },
+                        Some((_, quote)) if quote == c => break,
+                        Some((_, other)) => value.push(other),
+                    }
+                }
+                tokens.push(ExprToken::Str(value));
+            }
+            c if c.is_ascii_digit() => {
+                let mut end = start;
+                while let Some(&(i, d)) = chars.peek() {
+                    if !d.is_ascii_digit() {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                let value = source[start..end]
+                    .parse()
+                    .map_err(|_| TemplateError(format!("invalid number in \"{}\"", source)))?;
+                tokens.push(ExprToken::Int(value));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start;
+                while let Some(&(i, d)) = chars.peek() {
+                    if !(d.is_ascii_alphanumeric() || d == '_') {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                tokens.push(ExprToken::Ident(source[start..end].to_string()));
+            }
+            _ => {
+                let op = ["==", "!=", "+", "-", "|", ".", "[", "]", "(", ")"]
+                    .into_iter()
+                    .find(|op| source[start..].starts_with(op))
+                    .ok_or_else(|| TemplateError(format!("unexpected \"{}\" in \"{}\"", c, source)))?;
+                for _ in 0..op.len() {
+                    chars.next();
+                }
+                tokens.push(ExprToken::Op(op));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_expr(source: &str) -> Result<Expr, TemplateError> {
+    let tokens = lex_expr(source)?;
+    let mut parser = ExprParser { tokens: &tokens, pos: 0, source };
+    let expr = parser.or()?;
+    match parser.tokens.get(parser.pos) {
+        None => Ok(expr),
+        Some(_) => Err(parser.error()),
+    }
+}
+
+struct ExprParser<'a> {
+    tokens: &'a [ExprToken],
+    pos: usize,
+    source: &'a str,
+}
+
+impl ExprParser<'_> {
+    fn error(&self) -> TemplateError {
+        if self.pos < self.tokens.len() {
+            TemplateError(format!("unexpected {:?} in \"{}\"", self.tokens[self.pos], self.source))
+        } else {
+            TemplateError(format!("unexpected end of \"{}\"", self.source))
+        }
+    }
+
+    fn eat(&mut self, token: &ExprToken) -> bool {
+        if self.tokens.get(self.pos) == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        self.eat(&ExprToken::Ident(keyword.to_string()))
+    }
+
+    fn or(&mut self) -> Result<Expr, TemplateError> {
+        let mut left = self.and()?;
+        while self.eat_keyword("or") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, TemplateError> {
+        let mut left = self.not()?;
+        while self.eat_keyword("and") {
+            left = Expr::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, TemplateError> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.compare()
+    }
+
+    fn compare(&mut self) -> Result<Expr, TemplateError> {
+        let left = self.concat()?;
+        for (op, equal) in [("==", true), ("!=", false)] {
+            if self.eat(&ExprToken::Op(op)) {
+                let right = self.concat()?;
+                return Ok(Expr::Compare { left: Box::new(left), right: Box::new(right), equal });
+            }
+        }
+        Ok(left)
+    }
+
+    fn concat(&mut self) -> Result<Expr, TemplateError> {
+        let mut left = self.filtered()?;
+        while self.eat(&ExprToken::Op("+")) {
+            left = Expr::Concat(Box::new(left), Box::new(self.filtered()?));
+        }
+        Ok(left)
+    }
+
+    fn filtered(&mut self) -> Result<Expr, TemplateError> {
+        let mut expr = self.postfix()?;
+        while self.eat(&ExprToken::Op("|")) {
+            let filter = match self.tokens.get(self.pos) {
+                Some(ExprToken::Ident(name)) if name == "trim" => Filter::Trim,
+                Some(ExprToken::Ident(name)) if name == "length" => Filter::Length,
+                Some(ExprToken::Ident(name)) => {
+                    return Err(TemplateError(format!("unsupported filter \"{}\" in \"{}\"", name, self.source)))
+                }
+                _ => return Err(self.error()),
+            };
+            self.pos += 1;
+            expr = Expr::Filter(Box::new(expr), filter);
+        }
+        Ok(expr)
+    }
+
+    fn postfix(&mut self) -> Result<Expr, TemplateError> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat(&ExprToken::Op(".")) {
+                let Some(ExprToken::Ident(field)) = self.tokens.get(self.pos) else {
+                    return Err(self.error());
+                };
+                self.pos += 1;
+                expr = Expr::Get(Box::new(expr), Box::new(Expr::Literal(Value::Str(field.clone()))));
+            } else if self.eat(&ExprToken::Op("[")) {
+                let key = self.or()?;
+                if !self.eat(&ExprToken::Op("]")) {
+                    return Err(self.error());
+                }
+                expr = Expr::Get(Box::new(expr), Box::new(key));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, TemplateError> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return Err(self.error());
+        };
+        self.pos += 1;
+        match token {
+            ExprToken::Str(value) => Ok(Expr::Literal(Value::Str(value))),
+            ExprToken::Int(value) => Ok(Expr::Literal(Value::Int(value))),
+            ExprToken::Op("-") => match self.tokens.get(self.pos) {
+                Some(ExprToken::Int(value)) => {
+                    self.pos += 1;
+                    Ok(Expr::Literal(Value::Int(-value)))
+                }
+                _ => Err(self.error()),
+            },
+            ExprToken::Op("(") => {
+                let expr = self.or()?;
+                if !self.eat(&ExprToken::Op(")")) {
+                    return Err(self.error());
+                }
+                Ok(expr)
+            }
+            ExprToken::Ident(name) => Ok(match name.as_str() {
+                "true" | "True" => Expr::Literal(Value::Bool(true)),
+                "false" | "False" => Expr::Literal(Value::Bool(false)),
+                "none" | "None" => Expr::Literal(Value::Undefined),
+                _ if self.tokens.get(self.pos) == Some(&ExprToken::Op("(")) => {
+                    return Err(TemplateError(format!("function calls are not supported: \"{}\"", self.source)))
+                }
+                _ => Expr::Var(name),
+            }),
+            ExprToken::Op(_) => {
+                self.pos -= 1;
+                Err(self.error())
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Tenants: teams sharing one gateway without seeing each other's providers
+//!
+//! Providers registered with a [`tenant`](crate::types::ProviderConfig::tenant)
+//! belong to it: their models and pools are namespaced under the tenant
+//! (provider `openai` of tenant `team-a` serves `team-a/openai/gpt-4o`), and
+//! only requests of that tenant can list or use them. Model aliases can be
+//! registered per tenant too. Providers without a tenant are shared by all.
+//!
+//! Over HTTP, a request belongs to the tenant its API key
+//! ([`ApiKeyName`](crate::budget::ApiKeyName)) is assigned to in the server's
+//! [`TenantDirectory`]. Requests of other keys, and unauthenticated ones,
+//! only see shared providers. Tenant requests may leave out their tenant
+//! prefix: `openai/gpt-4o` resolves to `team-a/openai/gpt-4o` for `team-a`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, RwLock};
+
+/// Request metadata key naming the tenant the request belongs to
+pub const TENANT_METADATA: &str = "tenant";
+
+/// The providers and models a lookup may see
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TenantScope<'a> {
+    /// All of them, e.g. for library calls
+    All,
+    /// Shared ones only
+    Shared,
+    /// The tenant's own and the shared ones
+    Tenant(&'a str),
+}
+
+impl<'a> TenantScope<'a> {
+    /// The scope of a request of `tenant`, or of no tenant
+    pub fn of(tenant: Option<&'a str>) -> Self {
+        tenant.map_or(TenantScope::Shared, TenantScope::Tenant)
+    }
+
+    /// Whether something belonging to `owner` (`None` for shared) is visible
+    pub fn sees(&self, owner: Option<&str>) -> bool {
+        match (self, owner) {
+            (_, None) | (TenantScope::All, _) => true,
+            (TenantScope::Shared, Some(_)) => false,
+            (TenantScope::Tenant(tenant), Some(owner)) => *tenant == owner,
+        }
+    }
+
+    /// The tenant of a [`Tenant`](TenantScope::Tenant) scope
+    pub fn tenant(&self) -> Option<&'a str> {
+        match self {
+            TenantScope::Tenant(tenant) => Some(tenant),
+            _ => None,
+        }
+    }
+}
+
+/// The name of `tenant`'s provider, pool or model `name`: `{tenant}/{name}`,
+/// or `name` itself when shared
+pub fn qualified_name(tenant: Option<&str>, name: &str) -> String {
+    match tenant {
+        Some(tenant) => format!("{}/{}", tenant, name),
+        None => name.to_string(),
+    }
+}
+
+/// The tenant each API key belongs to. Clones share the assignments, so
+/// keys can be assigned while the server runs.
+#[derive(Clone, Debug, Default)]
+pub struct TenantDirectory {
+    keys: Arc<RwLock<BTreeMap<String, String>>>,
+}
+
+impl TenantDirectory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assign the API key `key` to `tenant`
+    pub fn with_key(self, key: impl Into<String>, tenant: impl Into<String>) -> Self {
+        self.assign(key, tenant);
+        self
+    }
+
+    /// Assign the API key `key` to `tenant`, returning its previous tenant
+    pub fn assign(&self, key: impl Into<String>, tenant: impl Into<String>) -> Option<String> {
+        self.keys
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.into(), tenant.into())
+    }
+
+    /// Take `key` out of its tenant, returning the tenant
+    pub fn unassign(&self, key: &str) -> Option<String> {
+        self.keys.write().unwrap_or_else(|e| e.into_inner()).remove(key)
+    }
+
+    /// The tenant of `key`, if it has one
+    pub fn tenant_of(&self, key: &str) -> Option<String> {
+        self.keys.read().unwrap_or_else(|e| e.into_inner()).get(key).cloned()
+    }
+
+    /// The keys assigned to `tenant`, sorted
+    pub fn keys_of(&self, tenant: &str) -> Vec<String> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        keys.iter().filter(|(_, owner)| *owner == tenant).map(|(key, _)| key.clone()).collect()
+    }
+
+    /// Every tenant with a key, sorted
+    pub fn tenants(&self) -> Vec<String> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        keys.values().cloned().collect::<BTreeSet<_>>().into_iter().collect()
+    }
+}
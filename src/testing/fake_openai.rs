@@ -0,0 +1,285 @@
+//! An in-process stand-in for an OpenAI-compatible server
+//!
+//! [`FakeOpenAI`] serves just enough of the API for tests that would
+//! otherwise need a live key: `GET /v1/models` and `POST /v1/chat/completions`,
+//! streamed and not. A [`FakeScenario`] per test scripts the reply and the
+//! trouble around it: rate limiting the first requests, stalling before the
+//! response, pausing between chunks and sending a malformed chunk.
+//!
+//! ```rust,no_run
+//! # async fn check() {
+//! use omniference::testing::{FakeOpenAI, FakeScenario};
+//!
+//! // The first chat request gets a 429, the next one "Hello!" in two chunks
+//! let fake = FakeOpenAI::start(FakeScenario::new().with_chunks(["Hel", "lo!"]).rate_limited(1)).await;
+//! let server = omniference::OmniferenceServerBuilder::new().with_provider(fake.provider("fake")).build();
+//! # }
+//! ```
+
+use crate::types::{ProviderConfig, ProviderEndpoint, ProviderKind};
+use axum::response::IntoResponse;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Model a [`FakeScenario`] lists unless told otherwise
+pub const FAKE_MODEL: &str = "fake-model";
+
+/// What a [`FakeOpenAI`] server answers
+#[derive(Clone, Debug)]
+pub struct FakeScenario {
+    /// Model ids listed by `/v1/models`
+    pub models: Vec<String>,
+    /// The reply's text, one streamed chunk per entry; non-streamed replies join them
+    pub chunks: Vec<String>,
+    /// Pause before each streamed chunk, by index; chunks past the end don't pause
+    pub chunk_delays: Vec<Duration>,
+    /// Pause before answering a chat request at all
+    pub stall: Option<Duration>,
+    /// Number of chat requests answered with a 429 before the reply
+    pub rate_limited: usize,
+    /// Send a chunk that isn't JSON before the chunk with this index
+    pub malformed_chunk: Option<usize>,
+    /// Prompt and completion tokens reported with the reply
+    pub usage: (u32, u32),
+}
+
+impl Default for FakeScenario {
+    fn default() -> Self {
+        Self {
+            models: vec![FAKE_MODEL.to_string()],
+            chunks: vec!["Hello".to_string(), "!".to_string()],
+            chunk_delays: Vec::new(),
+            stall: None,
+            rate_limited: 0,
+            malformed_chunk: None,
+            usage: (9, 2),
+        }
+    }
+}
+
+impl FakeScenario {
+    /// Reply "Hello!" in two chunks, listing [`FAKE_MODEL`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_models<S: Into<String>>(mut self, models: impl IntoIterator<Item = S>) -> Self {
+        self.models = models.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_chunks<S: Into<String>>(mut self, chunks: impl IntoIterator<Item = S>) -> Self {
+        self.chunks = chunks.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_chunk_delays(mut self, delays: Vec<Duration>) -> Self {
+        self.chunk_delays = delays;
+        self
+    }
+
+    pub fn with_stall(mut self, stall: Duration) -> Self {
+        self.stall = Some(stall);
+        self
+    }
+
+    /// Answer the first `requests` chat requests with a 429
+    pub fn rate_limited(mut self, requests: usize) -> Self {
+        self.rate_limited = requests;
+        self
+    }
+
+    pub fn with_malformed_chunk(mut self, before: usize) -> Self {
+        self.malformed_chunk = Some(before);
+        self
+    }
+
+    pub fn with_usage(mut self, prompt_tokens: u32, completion_tokens: u32) -> Self {
+        self.usage = (prompt_tokens, completion_tokens);
+        self
+    }
+
+    /// The reply's full text
+    pub fn reply(&self) -> String {
+        self.chunks.concat()
+    }
+
+    fn completion(&self, model: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": "chatcmpl-fake",
+            "object": "chat.completion",
+            "created": 1727000000,
+            "model": model,
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": self.reply()},
+                "finish_reason": "stop"
+            }],
+            "usage": self.usage_json()
+        })
+    }
+
+    fn stream_chunk(&self, model: &str, delta: serde_json::Value, finish_reason: Option<&str>) -> String {
+        let mut chunk = serde_json::json!({
+            "id": "chatcmpl-fake",
+            "object": "chat.completion.chunk",
+            "created": 1727000000,
+            "model": model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+        });
+        if finish_reason.is_some() {
+            chunk["usage"] = self.usage_json();
+        }
+        format!("data: {}\n\n", chunk)
+    }
+
+    fn usage_json(&self) -> serde_json::Value {
+        let (prompt_tokens, completion_tokens) = self.usage;
+        serde_json::json!({
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens
+        })
+    }
+}
+
+/// A running fake server; it stops when dropped
+pub struct FakeOpenAI {
+    base_url: String,
+    requests: Arc<Mutex<Vec<serde_json::Value>>>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+#[derive(Clone)]
+struct FakeState {
+    scenario: Arc<FakeScenario>,
+    requests: Arc<Mutex<Vec<serde_json::Value>>>,
+}
+
+impl FakeOpenAI {
+    /// Start a server playing `scenario` on a free local port
+    pub async fn start(scenario: FakeScenario) -> Self {
+        let requests: Arc<Mutex<Vec<serde_json::Value>>> = Arc::default();
+        let state = FakeState {
+            scenario: Arc::new(scenario),
+            requests: requests.clone(),
+        };
+        let app = axum::Router::new()
+            .route("/v1/models", axum::routing::get(models))
+            .route("/v1/chat/completions", axum::routing::post(chat))
+            .with_state(state);
+        let listener = match tokio::net::TcpListener::bind("127.0.0.1:0").await {
+            Ok(listener) => listener,
+            Err(e) => panic!("failed to start the fake OpenAI server: {}", e),
+        };
+        let base_url = format!("http://{}", listener.local_addr().expect("bound listener has an address"));
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        Self {
+            base_url,
+            requests,
+            server,
+        }
+    }
+
+    /// `http://127.0.0.1:<port>`, without the `/v1`
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// An OpenAI-compatible endpoint on the server
+    pub fn endpoint(&self) -> ProviderEndpoint {
+        ProviderEndpoint {
+            kind: ProviderKind::OpenAICompat,
+            base_url: self.base_url.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// An enabled provider named `name` on the server
+    pub fn provider(&self, name: impl Into<String>) -> ProviderConfig {
+        ProviderConfig {
+            name: name.into(),
+            endpoint: self.endpoint(),
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    /// The chat request bodies received so far, rate limited ones included
+    pub fn requests(&self) -> Vec<serde_json::Value> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Drop for FakeOpenAI {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+async fn models(axum::extract::State(state): axum::extract::State<FakeState>) -> axum::response::Response {
+    let data: Vec<serde_json::Value> = state
+        .scenario
+        .models
+        .iter()
+        .map(|id| serde_json::json!({"id": id, "object": "model", "created": 1727000000, "owned_by": "fake"}))
+        .collect();
+    axum::Json(serde_json::json!({"object": "list", "data": data})).into_response()
+}
+
+async fn chat(
+    axum::extract::State(state): axum::extract::State<FakeState>,
+    axum::Json(request): axum::Json<serde_json::Value>,
+) -> axum::response::Response {
+    let seen = {
+        let mut requests = state.requests.lock().unwrap_or_else(|e| e.into_inner());
+        requests.push(request.clone());
+        requests.len()
+    };
+    let scenario = state.scenario;
+    if let Some(stall) = scenario.stall {
+        tokio::time::sleep(stall).await;
+    }
+    if seen <= scenario.rate_limited {
+        let error = serde_json::json!({
+            "error": {
+                "message": "Rate limit reached for requests",
+                "type": "requests",
+                "param": null,
+                "code": "rate_limit_exceeded"
+            }
+        });
+        return (
+            axum::http::StatusCode::TOO_MANY_REQUESTS,
+            [(axum::http::header::RETRY_AFTER, "0")],
+            axum::Json(error),
+        )
+            .into_response();
+    }
+
+    let model = request["model"].as_str().unwrap_or(FAKE_MODEL).to_string();
+    if request["stream"].as_bool() != Some(true) {
+        return axum::Json(scenario.completion(&model)).into_response();
+    }
+    let chunks = async_stream::stream! {
+        yield Ok::<_, std::convert::Infallible>(scenario.stream_chunk(&model, serde_json::json!({"role": "assistant"}), None));
+        for (index, content) in scenario.chunks.iter().enumerate() {
+            if let Some(delay) = scenario.chunk_delays.get(index) {
+                tokio::time::sleep(*delay).await;
+            }
+            if scenario.malformed_chunk == Some(index) {
+                yield Ok("data: {\"id\":\"chatcmpl-fake\",\"choices\":[{\n\n".to_string());
+            }
+            yield Ok(scenario.stream_chunk(&model, serde_json::json!({"content": content}), None));
+        }
+        yield Ok(scenario.stream_chunk(&model, serde_json::json!({}), Some("stop")));
+        yield Ok("data: [DONE]\n\n".to_string());
+    };
+    (
+        [(axum::http::header::CONTENT_TYPE, "text/event-stream")],
+        axum::body::Body::from_stream(chunks),
+    )
+        .into_response()
+}
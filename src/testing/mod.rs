@@ -0,0 +1,545 @@
+//! Conformance checks for [`ChatAdapter`] implementations, and a fake
+//! OpenAI-compatible server for tests
+//!
+//! The router and the skins rely on a contract that adapters' types don't
+//! spell out. [`check_events`] checks an event sequence against it:
+//!
+//! - A stream ends with exactly one `Done` or one `Error`, never both, and
+//!   nothing follows it. `Tokens` and `ToolCallEnd` therefore come first.
+//! - `ToolCallDelta` and `ToolCallEnd` belong to a call opened by
+//!   `ToolCallStart`, and every call is ended once before `Done`.
+//!
+//! [`ConformanceSuite`] runs an adapter through a battery of [`Scenario`]s
+//! and checks each one's outcome as well: a plain reply, a streamed reply, a
+//! tool call, cancellation mid-stream (the stream must end promptly without
+//! `Done`), an upstream 500 (an `Err` from `execute_chat` or an `Error`
+//! event, without content) and malformed JSON (no panic, and the stream
+//! ends). Against a stub upstream, it serves each scenario the [`Fixture`]
+//! written in the adapter's wire format; against a live endpoint, only the
+//! scenarios that need no scripted upstream run.
+//!
+//! ```rust,no_run
+//! # async fn check(adapter: std::sync::Arc<dyn omniference::ChatAdapter>) {
+//! use omniference::testing::{ConformanceSuite, Fixture, Fixtures, Scenario};
+//!
+//! let fixtures = Fixtures::new()
+//!     .with(Scenario::PlainText, Fixture::json(r#"{"text": "Hi", "done": true}"#))
+//!     .with(Scenario::Streaming, Fixture::ndjson(&[r#"{"text": "Hi"}"#, r#"{"done": true}"#]));
+//! ConformanceSuite::stub(adapter, "my-model", fixtures).run().await.assert_conformant();
+//! # }
+//! ```
+//!
+//! [`FakeOpenAI`] stands in for a live OpenAI-compatible provider in tests
+//! that go through discovery, the router or the skins (see [`fake_openai`]).
+
+pub mod fake_openai;
+
+pub use fake_openai::{FakeOpenAI, FakeScenario, FAKE_MODEL};
+
+use crate::adapter::{AdapterError, ChatAdapter};
+use crate::stream::{event_name as name, StreamEvent};
+use crate::types::{ChatRequestIR, ContentPart, Message, ModelRef, ProviderEndpoint, Role, ToolSpec};
+use futures_util::StreamExt;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Longest a scenario may take before its stream counts as hung
+pub const SCENARIO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest a stream may keep going once its request is cancelled
+pub const CANCEL_DEADLINE: Duration = Duration::from_secs(2);
+
+/// Chunk delay of the cancellation scenario when it borrows the streaming fixture
+const CANCELLATION_CHUNK_DELAY: Duration = Duration::from_millis(200);
+
+/// A situation an adapter must handle
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Scenario {
+    /// A non-streamed reply
+    PlainText,
+    /// A streamed reply
+    Streaming,
+    /// A streamed reply calling the `get_weather` tool
+    ToolCall,
+    /// A slow stream whose request is cancelled after its first event
+    Cancellation,
+    /// The upstream answers 500
+    UpstreamError,
+    /// The upstream answers with JSON that doesn't parse
+    MalformedJson,
+}
+
+impl Scenario {
+    pub fn all() -> &'static [Scenario] {
+        &[
+            Scenario::PlainText,
+            Scenario::Streaming,
+            Scenario::ToolCall,
+            Scenario::Cancellation,
+            Scenario::UpstreamError,
+            Scenario::MalformedJson,
+        ]
+    }
+
+    /// Whether the scenario can run against a live endpoint
+    pub fn runs_live(&self) -> bool {
+        matches!(self, Scenario::PlainText | Scenario::Streaming | Scenario::Cancellation)
+    }
+
+    /// The request the scenario sends for `model`
+    pub fn request(&self, model: ModelRef) -> ChatRequestIR {
+        let mut request = ChatRequestIR {
+            model,
+            messages: vec![Message {
+                role: Role::User,
+                parts: vec![ContentPart::Text("Say hello.".to_string())],
+                name: None,
+            }],
+            stream: *self != Scenario::PlainText,
+            ..Default::default()
+        };
+        if *self == Scenario::ToolCall {
+            request.tools.push(ToolSpec::JsonSchema {
+                name: "get_weather".to_string(),
+                description: Some("Current weather in a city".to_string()),
+                schema: serde_json::json!({
+                    "type": "object",
+                    "properties": { "city": { "type": "string" } },
+                    "required": ["city"]
+                }),
+                strict: None,
+            });
+        }
+        request
+    }
+}
+
+/// A canned upstream response, sent in chunks
+#[derive(Clone, Debug)]
+pub struct Fixture {
+    pub status: u16,
+    pub content_type: String,
+    pub chunks: Vec<String>,
+    /// Pause before each chunk
+    pub chunk_delay: Duration,
+}
+
+impl Fixture {
+    /// A `200` JSON body
+    pub fn json(body: impl Into<String>) -> Self {
+        Self::status(200, body)
+    }
+
+    /// A `200` SSE stream, e.g. a recorded one; each event is one chunk
+    pub fn sse(recorded: &str) -> Self {
+        Self {
+            status: 200,
+            content_type: "text/event-stream".to_string(),
+            chunks: recorded.split_inclusive("\n\n").map(str::to_string).collect(),
+            chunk_delay: Duration::ZERO,
+        }
+    }
+
+    /// A `200` newline-delimited JSON stream; each line is one chunk
+    pub fn ndjson(lines: &[&str]) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/x-ndjson".to_string(),
+            chunks: lines.iter().map(|line| format!("{}\n", line)).collect(),
+            chunk_delay: Duration::ZERO,
+        }
+    }
+
+    /// A JSON body with `status`
+    pub fn status(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "application/json".to_string(),
+            chunks: vec![body.into()],
+            chunk_delay: Duration::ZERO,
+        }
+    }
+
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        self.chunk_delay = delay;
+        self
+    }
+}
+
+/// The fixture the stub upstream serves for each scenario, whatever the
+/// request's path. Scenarios without one are skipped, except that
+/// `UpstreamError` defaults to a `500` with an OpenAI-style error body and
+/// `Cancellation` to the `Streaming` fixture slowed down.
+#[derive(Clone, Debug, Default)]
+pub struct Fixtures {
+    responses: HashMap<Scenario, Fixture>,
+}
+
+impl Fixtures {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, scenario: Scenario, fixture: Fixture) -> Self {
+        self.responses.insert(scenario, fixture);
+        self
+    }
+
+    /// The fixture served for `scenario`, with the defaults applied
+    pub fn get(&self, scenario: Scenario) -> Option<Fixture> {
+        if let Some(fixture) = self.responses.get(&scenario) {
+            return Some(fixture.clone());
+        }
+        match scenario {
+            Scenario::UpstreamError => Some(Fixture::status(
+                500,
+                r#"{"error": {"message": "internal error", "type": "server_error", "code": "internal_error"}}"#,
+            )),
+            Scenario::Cancellation => self
+                .responses
+                .get(&Scenario::Streaming)
+                .map(|fixture| fixture.clone().with_chunk_delay(CANCELLATION_CHUNK_DELAY)),
+            _ => None,
+        }
+    }
+}
+
+/// Contract violations in an event sequence; empty when it conforms (see the
+/// [module docs](self))
+pub fn check_events(events: &[StreamEvent]) -> Vec<String> {
+    let mut violations = Vec::new();
+    let mut open_calls = HashSet::new();
+    let mut ended_calls = HashSet::new();
+    for (index, event) in events.iter().enumerate() {
+        let last = index + 1 == events.len();
+        match event {
+            StreamEvent::Done | StreamEvent::Error { .. } if !last => {
+                violations.push(format!("event {} ({}) is followed by {:?}", index, name(event), events[index + 1]));
+            }
+            StreamEvent::ToolCallStart { id, .. } if open_calls.contains(id) || ended_calls.contains(id) => {
+                violations.push(format!("event {}: tool call {} started twice", index, id));
+            }
+            StreamEvent::ToolCallStart { id, .. } => {
+                open_calls.insert(id.clone());
+            }
+            StreamEvent::ToolCallDelta { id, .. } if !open_calls.contains(id) => {
+                violations.push(format!("event {}: delta for tool call {}, which isn't open", index, id));
+            }
+            StreamEvent::ToolCallEnd { id } => {
+                if !open_calls.remove(id) {
+                    violations.push(format!("event {}: end of tool call {}, which isn't open", index, id));
+                }
+                ended_calls.insert(id.clone());
+            }
+            _ => {}
+        }
+    }
+    match events.last() {
+        Some(StreamEvent::Done) => {
+            let mut unended: Vec<&String> = open_calls.iter().collect();
+            unended.sort();
+            for id in unended {
+                violations.push(format!("tool call {} is never ended", id));
+            }
+        }
+        Some(StreamEvent::Error { .. }) => {}
+        Some(other) => violations.push(format!("the stream ends with {} instead of Done or Error", name(other))),
+        None => violations.push("the stream is empty; it must end with Done or Error".to_string()),
+    }
+    let done = events.iter().filter(|event| matches!(event, StreamEvent::Done)).count();
+    let errors = events.iter().filter(|event| matches!(event, StreamEvent::Error { .. })).count();
+    if done + errors > 1 {
+        violations.push(format!("{} Done and {} Error events; a stream ends with exactly one", done, errors));
+    }
+    violations
+}
+
+/// How one scenario went
+#[derive(Clone, Debug)]
+pub struct ScenarioReport {
+    pub scenario: Scenario,
+    /// No fixture for it, or it can't run live
+    pub skipped: bool,
+    /// The error `execute_chat` returned, if it did
+    pub error: Option<String>,
+    pub events: Vec<StreamEvent>,
+    pub violations: Vec<String>,
+}
+
+/// How an adapter did in every scenario
+#[derive(Clone, Debug)]
+pub struct ConformanceReport {
+    pub scenarios: Vec<ScenarioReport>,
+}
+
+impl ConformanceReport {
+    pub fn is_conformant(&self) -> bool {
+        self.scenarios.iter().all(|report| report.violations.is_empty())
+    }
+
+    /// Scenarios that ran
+    pub fn ran(&self) -> Vec<Scenario> {
+        self.scenarios.iter().filter(|report| !report.skipped).map(|report| report.scenario).collect()
+    }
+
+    /// Panic with every violation unless the adapter conforms
+    #[track_caller]
+    pub fn assert_conformant(&self) {
+        assert!(self.is_conformant(), "{}", self);
+    }
+}
+
+impl std::fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for report in &self.scenarios {
+            let status = match (report.skipped, report.violations.is_empty()) {
+                (true, _) => "skipped",
+                (false, true) => "ok",
+                (false, false) => "FAILED",
+            };
+            writeln!(f, "{:?}: {}", report.scenario, status)?;
+            for violation in &report.violations {
+                writeln!(f, "  - {}", violation)?;
+            }
+            if !report.violations.is_empty() {
+                writeln!(f, "  events: {:?}", report.events)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Target {
+    Stub(Fixtures),
+    Live,
+}
+
+/// Runs an adapter through every [`Scenario`] (see the [module docs](self))
+pub struct ConformanceSuite {
+    adapter: Arc<dyn ChatAdapter>,
+    model_id: String,
+    endpoint: ProviderEndpoint,
+    target: Target,
+}
+
+impl ConformanceSuite {
+    /// Run against a stub upstream serving `fixtures`
+    pub fn stub(adapter: Arc<dyn ChatAdapter>, model_id: impl Into<String>, fixtures: Fixtures) -> Self {
+        let endpoint = ProviderEndpoint {
+            kind: adapter.provider_kind(),
+            ..Default::default()
+        };
+        Self {
+            adapter,
+            model_id: model_id.into(),
+            endpoint,
+            target: Target::Stub(fixtures),
+        }
+    }
+
+    /// Run the scenarios that need no scripted upstream against `endpoint`
+    pub fn live(adapter: Arc<dyn ChatAdapter>, model_id: impl Into<String>, endpoint: ProviderEndpoint) -> Self {
+        Self {
+            adapter,
+            model_id: model_id.into(),
+            endpoint,
+            target: Target::Live,
+        }
+    }
+
+    /// Endpoint settings for the stub runs, e.g. `adapter_options`; the
+    /// base URL is replaced by the stub's
+    pub fn with_endpoint(mut self, endpoint: ProviderEndpoint) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    pub async fn run(&self) -> ConformanceReport {
+        let mut endpoint = self.endpoint.clone();
+        let current: Arc<Mutex<Option<Fixture>>> = Arc::default();
+        let server = match &self.target {
+            Target::Stub(_) => {
+                let (base_url, server) = match stub_upstream(current.clone()).await {
+                    Ok(stub) => stub,
+                    Err(e) => panic!("failed to start the stub upstream: {}", e),
+                };
+                endpoint.base_url = base_url;
+                Some(server)
+            }
+            Target::Live => None,
+        };
+
+        let mut scenarios = Vec::new();
+        for &scenario in Scenario::all() {
+            let fixture = match &self.target {
+                Target::Stub(fixtures) => fixtures.get(scenario),
+                Target::Live => None,
+            };
+            let runs = match &self.target {
+                Target::Stub(_) => fixture.is_some(),
+                Target::Live => scenario.runs_live(),
+            };
+            if !runs {
+                scenarios.push(ScenarioReport {
+                    scenario,
+                    skipped: true,
+                    error: None,
+                    events: Vec::new(),
+                    violations: Vec::new(),
+                });
+                continue;
+            }
+            *current.lock().unwrap_or_else(|e| e.into_inner()) = fixture;
+            let model = ModelRef {
+                alias: self.model_id.clone(),
+                provider: endpoint.clone(),
+                model_id: self.model_id.clone(),
+                modalities: Vec::new(),
+            };
+            scenarios.push(run_scenario(self.adapter.clone(), scenario, scenario.request(model)).await);
+        }
+        if let Some(server) = server {
+            server.abort();
+        }
+        ConformanceReport { scenarios }
+    }
+}
+
+/// Serve the current fixture on every path
+async fn stub_upstream(
+    current: Arc<Mutex<Option<Fixture>>>,
+) -> std::io::Result<(String, tokio::task::JoinHandle<()>)> {
+    let handler = move || {
+        let fixture = current.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        async move {
+            let Some(fixture) = fixture else {
+                return axum::http::StatusCode::NOT_FOUND.into_response();
+            };
+            let delay = fixture.chunk_delay;
+            let chunks = futures_util::stream::iter(fixture.chunks).then(move |chunk| async move {
+                tokio::time::sleep(delay).await;
+                Ok::<_, std::convert::Infallible>(chunk)
+            });
+            let status = axum::http::StatusCode::from_u16(fixture.status).unwrap_or(axum::http::StatusCode::OK);
+            (
+                status,
+                [(axum::http::header::CONTENT_TYPE, fixture.content_type)],
+                axum::body::Body::from_stream(chunks),
+            )
+                .into_response()
+        }
+    };
+    use axum::response::IntoResponse;
+    let app = axum::Router::new().fallback(handler);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base_url = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+    Ok((base_url, server))
+}
+
+async fn run_scenario(adapter: Arc<dyn ChatAdapter>, scenario: Scenario, request: ChatRequestIR) -> ScenarioReport {
+    let cancel = CancellationToken::new();
+    let run = tokio::spawn(collect(adapter, scenario, request, cancel.clone()));
+    let outcome = tokio::time::timeout(SCENARIO_TIMEOUT, run).await;
+    cancel.cancel();
+    let mut report = ScenarioReport {
+        scenario,
+        skipped: false,
+        error: None,
+        events: Vec::new(),
+        violations: Vec::new(),
+    };
+    let (result, cancelled_for) = match outcome {
+        Err(_) => {
+            report
+                .violations
+                .push(format!("the stream didn't end within {} s", SCENARIO_TIMEOUT.as_secs()));
+            return report;
+        }
+        Ok(Err(e)) => {
+            report.violations.push(format!("the adapter panicked: {}", e));
+            return report;
+        }
+        Ok(Ok(outcome)) => outcome,
+    };
+    match result {
+        Ok(events) => report.events = events,
+        Err(e) => report.error = Some(e.to_string()),
+    }
+
+    let events = &report.events;
+    let has = |check: fn(&StreamEvent) -> bool| events.iter().any(check);
+    if report.error.is_none() {
+        report.violations.extend(check_events(events));
+    }
+    let expect = |violations: &mut Vec<String>, ok: bool, message: &str| {
+        if !ok {
+            violations.push(message.to_string());
+        }
+    };
+    let done = matches!(events.last(), Some(StreamEvent::Done));
+    match scenario {
+        Scenario::PlainText | Scenario::Streaming => {
+            expect(&mut report.violations, report.error.is_none(), "execute_chat failed");
+            expect(&mut report.violations, has(|e| matches!(e, StreamEvent::TextDelta { .. })), "no text was streamed");
+            expect(&mut report.violations, done, "the stream doesn't complete with Done");
+        }
+        Scenario::ToolCall => {
+            expect(&mut report.violations, report.error.is_none(), "execute_chat failed");
+            expect(&mut report.violations, has(|e| matches!(e, StreamEvent::ToolCallStart { .. })), "no tool call was streamed");
+            expect(&mut report.violations, done, "the stream doesn't complete with Done");
+        }
+        Scenario::Cancellation => {
+            expect(&mut report.violations, report.error.is_none(), "execute_chat failed");
+            expect(&mut report.violations, !done, "the stream completed with Done after it was cancelled");
+            if let Some(elapsed) = cancelled_for {
+                expect(
+                    &mut report.violations,
+                    elapsed <= CANCEL_DEADLINE,
+                    &format!("the stream kept going for {} ms after it was cancelled", elapsed.as_millis()),
+                );
+            }
+        }
+        Scenario::UpstreamError => {
+            let failed = report.error.is_some() || matches!(events.last(), Some(StreamEvent::Error { .. }));
+            expect(&mut report.violations, failed, "an upstream 500 is neither an Err nor an Error event");
+            expect(
+                &mut report.violations,
+                !has(|e| matches!(e, StreamEvent::TextDelta { .. })),
+                "an upstream 500 produced text",
+            );
+        }
+        // Any clean outcome will do; panics and hangs are caught above
+        Scenario::MalformedJson => {}
+    }
+    report
+}
+
+/// Run the scenario's request, cancelling it after the first event for
+/// [`Scenario::Cancellation`]. Also returns how long a cancelled stream
+/// took to end.
+async fn collect(
+    adapter: Arc<dyn ChatAdapter>,
+    scenario: Scenario,
+    request: ChatRequestIR,
+    cancel: CancellationToken,
+) -> (Result<Vec<StreamEvent>, AdapterError>, Option<Duration>) {
+    let mut stream = match adapter.execute_chat(request, cancel.clone()).await {
+        Ok(stream) => stream,
+        Err(e) => return (Err(e), None),
+    };
+    let mut events = Vec::new();
+    let mut cancelled_at = None;
+    while let Some(event) = stream.next().await {
+        events.push(event);
+        if scenario == Scenario::Cancellation && cancelled_at.is_none() {
+            cancel.cancel();
+            cancelled_at = Some(tokio::time::Instant::now());
+        }
+    }
+    (Ok(events), cancelled_at.map(|at| at.elapsed()))
+}
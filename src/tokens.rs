@@ -0,0 +1,311 @@
+//! Token counting
+//!
+//! [`TokenCounter`]s estimate how many prompt tokens a conversation costs on a
+//! given model, so callers can check it against the context window before
+//! sending. [`DefaultTokenCounter`] picks the most accurate method available
+//! for the model's provider and falls back to a chars/4 heuristic.
+
+use crate::adapter::AdapterError;
+use crate::types::{ContentPart, Message, ModelRef, ProviderKind};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Tokens added per message for role and delimiters (OpenAI chat format)
+pub const MESSAGE_OVERHEAD: usize = 3;
+/// Tokens priming the assistant's reply
+pub const REPLY_PRIMING: usize = 3;
+/// Flat estimate for an image, audio clip, or file attachment
+pub const ATTACHMENT_TOKENS: usize = 85;
+
+/// Prompt size of a list of messages
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenCount {
+    /// Total, including per-message overhead and reply priming
+    pub prompt_tokens: usize,
+    /// Tokens per message, in order, including per-message overhead
+    pub per_message: Vec<usize>,
+}
+
+impl TokenCount {
+    /// Assemble a count from per-message content token counts
+    pub fn from_content_tokens(content_tokens: impl IntoIterator<Item = usize>) -> Self {
+        let per_message: Vec<usize> = content_tokens
+            .into_iter()
+            .map(|tokens| tokens + MESSAGE_OVERHEAD)
+            .collect();
+        Self {
+            prompt_tokens: per_message.iter().sum::<usize>() + REPLY_PRIMING,
+            per_message,
+        }
+    }
+}
+
+#[async_trait]
+pub trait TokenCounter: Send + Sync {
+    async fn count(&self, model: &ModelRef, messages: &[Message]) -> Result<TokenCount, AdapterError>;
+}
+
+/// Text of a message as the tokenizer sees it: text parts, tool call names
+/// and arguments, and tool results. Attachments are counted separately.
+pub fn message_text(message: &Message) -> String {
+    let mut text = String::new();
+    for part in &message.parts {
+        let piece = match part {
+            ContentPart::Text(t) => t.clone(),
+            ContentPart::ToolCall { name, arguments, .. } => {
+                format!("{} {}", name, ContentPart::arguments_string(arguments))
+            }
+            ContentPart::ToolResult { content, .. } => content.clone(),
+            _ => continue,
+        };
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&piece);
+    }
+    if let Some(name) = &message.name {
+        text.push_str(name);
+    }
+    text
+}
+
+fn attachment_tokens(message: &Message) -> usize {
+    message
+        .parts
+        .iter()
+        .filter(|part| {
+            matches!(
+                part,
+                ContentPart::ImageUrl { .. } | ContentPart::BlobRef { .. } | ContentPart::Audio { .. } | ContentPart::File { .. }
+            )
+        })
+        .count()
+        * ATTACHMENT_TOKENS
+}
+
+/// Estimates one token per four characters
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HeuristicCounter;
+
+impl HeuristicCounter {
+    pub fn count_text(text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+
+    pub fn count_messages(messages: &[Message]) -> TokenCount {
+        TokenCount::from_content_tokens(
+            messages
+                .iter()
+                .map(|m| Self::count_text(&message_text(m)) + attachment_tokens(m)),
+        )
+    }
+}
+
+#[async_trait]
+impl TokenCounter for HeuristicCounter {
+    async fn count(&self, _model: &ModelRef, messages: &[Message]) -> Result<TokenCount, AdapterError> {
+        Ok(Self::count_messages(messages))
+    }
+}
+
+/// Exact counts for OpenAI models using their tiktoken encodings
+#[cfg(feature = "tiktoken")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TiktokenCounter;
+
+#[cfg(feature = "tiktoken")]
+impl TiktokenCounter {
+    /// The encoding for a model, or `None` for models tiktoken doesn't know
+    pub fn encoding(model_id: &str) -> Option<&'static tiktoken_rs::CoreBPE> {
+        use tiktoken_rs::tokenizer::Tokenizer;
+
+        // Strip provider prefixes such as "openai/gpt-4o"
+        let model_id = model_id.rsplit('/').next().unwrap_or(model_id);
+        Some(match tiktoken_rs::tokenizer::get_tokenizer(model_id)? {
+            Tokenizer::O200kBase => tiktoken_rs::o200k_base_singleton(),
+            Tokenizer::Cl100kBase => tiktoken_rs::cl100k_base_singleton(),
+            Tokenizer::P50kBase => tiktoken_rs::p50k_base_singleton(),
+            Tokenizer::P50kEdit => tiktoken_rs::p50k_edit_singleton(),
+            Tokenizer::R50kBase | Tokenizer::Gpt2 => tiktoken_rs::r50k_base_singleton(),
+        })
+    }
+}
+
+#[cfg(feature = "tiktoken")]
+#[async_trait]
+impl TokenCounter for TiktokenCounter {
+    async fn count(&self, model: &ModelRef, messages: &[Message]) -> Result<TokenCount, AdapterError> {
+        let bpe = Self::encoding(&model.model_id)
+            .ok_or_else(|| AdapterError::unsupported(format!("no tiktoken encoding for '{}'", model.model_id)))?;
+        Ok(TokenCount::from_content_tokens(messages.iter().map(|m| {
+            bpe.encode_with_special_tokens(&message_text(m)).len() + attachment_tokens(m)
+        })))
+    }
+}
+
+#[derive(Serialize)]
+struct OllamaTokenizeRequest<'a> {
+    model: &'a str,
+    text: &'a str,
+}
+
+#[derive(Deserialize)]
+struct OllamaTokenizeResponse {
+    tokens: Vec<u32>,
+}
+
+/// Counts with the model's own tokenizer via Ollama's `/api/tokenize`
+#[derive(Clone, Debug, Default)]
+pub struct OllamaTokenizer {
+    client: reqwest::Client,
+}
+
+impl OllamaTokenizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn tokenize(&self, model: &ModelRef, text: &str) -> Result<usize, AdapterError> {
+        if text.is_empty() {
+            return Ok(0);
+        }
+        let url = crate::adapters::http::endpoint_url(&model.provider, "/api/tokenize");
+        let client = match crate::adapters::http::unix_socket(&model.provider.base_url) {
+            Some(_) => crate::adapters::http::client(&model.provider)?,
+            None => self.client.clone(),
+        };
+        let mut request = client.post(&url).json(&OllamaTokenizeRequest {
+            model: &model.model_id,
+            text,
+        });
+        if let Some(timeout) = model.provider.first_byte_timeout() {
+            request = request.timeout(timeout);
+        }
+        for (key, value) in model.provider.headers().await? {
+            request = request.header(key, value);
+        }
+
+        let resp = request
+            .send()
+            .await
+            .map_err(|e| AdapterError::Http(format!("Failed to send tokenize request: {}", e)))?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            return Err(AdapterError::Provider {
+                code: status.as_u16().to_string(),
+                message: text,
+            });
+        }
+        let body: OllamaTokenizeResponse = resp
+            .json()
+            .await
+            .map_err(|e| AdapterError::Internal(format!("Failed to parse tokenize response: {}", e)))?;
+        Ok(body.tokens.len())
+    }
+}
+
+#[async_trait]
+impl TokenCounter for OllamaTokenizer {
+    async fn count(&self, model: &ModelRef, messages: &[Message]) -> Result<TokenCount, AdapterError> {
+        let counts = futures_util::future::try_join_all(messages.iter().map(|m| async move {
+            Ok::<_, AdapterError>(self.tokenize(model, &message_text(m)).await? + attachment_tokens(m))
+        }))
+        .await?;
+        Ok(TokenCount::from_content_tokens(counts))
+    }
+}
+
+/// Uses tiktoken for OpenAI-family models (with the `tiktoken` feature) and
+/// Ollama's tokenizer for Ollama models, falling back to [`HeuristicCounter`]
+/// when neither applies or the exact method fails.
+#[derive(Clone, Debug, Default)]
+pub struct DefaultTokenCounter {
+    ollama: OllamaTokenizer,
+}
+
+impl DefaultTokenCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn exact(&self, model: &ModelRef, messages: &[Message]) -> Option<Result<TokenCount, AdapterError>> {
+        match model.provider.kind {
+            ProviderKind::Ollama => Some(self.ollama.count(model, messages).await),
+            #[cfg(feature = "tiktoken")]
+            ProviderKind::OpenAI | ProviderKind::OpenAICompat => {
+                TiktokenCounter::encoding(&model.model_id)?;
+                Some(TiktokenCounter.count(model, messages).await)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+impl TokenCounter for DefaultTokenCounter {
+    async fn count(&self, model: &ModelRef, messages: &[Message]) -> Result<TokenCount, AdapterError> {
+        match self.exact(model, messages).await {
+            Some(Ok(count)) => Ok(count),
+            Some(Err(e)) => {
+                tracing::debug!(
+                    model = %model.model_id,
+                    error = %e,
+                    "Exact token count unavailable; using heuristic"
+                );
+                Ok(HeuristicCounter::count_messages(messages))
+            }
+            None => Ok(HeuristicCounter::count_messages(messages)),
+        }
+    }
+}
+
+/// Drop the oldest messages until `count` fits within `budget` tokens.
+///
+/// System and developer messages are always kept, as is the final message,
+/// so the result may still exceed a budget too small for them. Tool results
+/// are dropped together with the assistant message that requested them.
+pub fn fit_to_budget(messages: Vec<Message>, count: &TokenCount, budget: usize) -> Vec<Message> {
+    use crate::types::Role;
+
+    split_to_budget(messages, count, budget, &[Role::System, Role::Developer]).0
+}
+
+/// Like [`fit_to_budget`] with configurable protected roles; returns the kept
+/// and the dropped messages, both in their original order.
+pub fn split_to_budget(
+    messages: Vec<Message>,
+    count: &TokenCount,
+    budget: usize,
+    protected: &[crate::types::Role],
+) -> (Vec<Message>, Vec<Message>) {
+    use crate::types::Role;
+
+    let mut total = count.prompt_tokens;
+    let last = messages.len().saturating_sub(1);
+    let mut keep = vec![true; messages.len()];
+    let mut dropped_previous = false;
+    for (i, message) in messages.iter().enumerate() {
+        if i == last {
+            break;
+        }
+        let orphaned = dropped_previous && message.role == Role::Tool;
+        if !orphaned {
+            if total <= budget {
+                break;
+            }
+            if protected.contains(&message.role) {
+                dropped_previous = false;
+                continue;
+            }
+        }
+        keep[i] = false;
+        dropped_previous = true;
+        total = total.saturating_sub(count.per_message.get(i).copied().unwrap_or(0));
+    }
+    let (kept, dropped): (Vec<_>, Vec<_>) = messages.into_iter().zip(keep).partition(|(_, keep)| *keep);
+    (
+        kept.into_iter().map(|(message, _)| message).collect(),
+        dropped.into_iter().map(|(message, _)| message).collect(),
+    )
+}
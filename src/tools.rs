@@ -0,0 +1,485 @@
+//! Engine-side tool execution
+//!
+//! Tools registered here (MCP server tools and local handlers) are advertised
+//! to the model and executed by the engine: when a response ends with calls to
+//! registered tools, their results are appended to the conversation and the
+//! request is re-issued until the model answers without calling tools.
+//!
+//! The HTTP skin runs the loop only for chat requests of aliases the server
+//! configures [`AutoToolExecution`] limits for, and for requests setting the
+//! [`AUTO_TOOLS_METADATA`] request metadata flag where the server lets
+//! clients opt in; the others return tool calls to the client.
+
+use crate::mcp::{McpClient, McpToolOutput};
+use crate::router::Router;
+use crate::stream::StreamEvent;
+use crate::types::{ChatRequestIR, ContentPart, Message, Role, ToolSpec};
+use async_trait::async_trait;
+use futures_util::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// Default cap on model round trips per chat request
+pub const DEFAULT_MAX_TOOL_ITERATIONS: usize = 8;
+
+/// Default cap on the time the HTTP skin's tool loop runs per request
+pub const DEFAULT_MAX_TOOL_LOOP_DURATION: Duration = Duration::from_secs(120);
+
+/// Request metadata turning the HTTP skin's tool loop off (`"false"`) for one
+/// chat request, or on (`"true"`) where [`AutoToolExecution`] lets clients
+/// opt in
+pub const AUTO_TOOLS_METADATA: &str = "auto_tools";
+
+/// Executes a tool call; `Err` is reported to the model as a failed call
+#[async_trait]
+pub trait ToolHandler: Send + Sync {
+    async fn call(&self, arguments: serde_json::Value) -> Result<String, String>;
+}
+
+/// Runs a tool on an MCP server
+pub struct McpToolHandler {
+    client: Arc<McpClient>,
+    name: String,
+}
+
+impl McpToolHandler {
+    pub fn new(client: Arc<McpClient>, name: impl Into<String>) -> Self {
+        Self {
+            client,
+            name: name.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for McpToolHandler {
+    async fn call(&self, arguments: serde_json::Value) -> Result<String, String> {
+        match self.client.call_tool(&self.name, arguments).await {
+            Ok(McpToolOutput { content, is_error: false }) => Ok(content),
+            Ok(McpToolOutput { content, is_error: true }) => Err(content),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// Runs a local async function; see `OmniferenceEngine::register_tool`
+pub struct FnToolHandler<F> {
+    handler: F,
+}
+
+impl<F> FnToolHandler<F> {
+    pub fn new(handler: F) -> Self {
+        Self { handler }
+    }
+}
+
+#[async_trait]
+impl<F, Fut, E> ToolHandler for FnToolHandler<F>
+where
+    F: Fn(serde_json::Value) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<serde_json::Value, E>> + Send,
+    E: std::fmt::Display,
+{
+    async fn call(&self, arguments: serde_json::Value) -> Result<String, String> {
+        match (self.handler)(arguments).await {
+            Ok(value) => Ok(ContentPart::arguments_string(&value)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RegisteredTool {
+    pub name: String,
+    pub description: Option<String>,
+    pub schema: serde_json::Value,
+    /// Where the tool comes from, e.g. the MCP server label
+    pub source: String,
+    pub handler: Arc<dyn ToolHandler>,
+}
+
+impl RegisteredTool {
+    pub fn spec(&self) -> ToolSpec {
+        ToolSpec::JsonSchema {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            schema: self.schema.clone(),
+            strict: None,
+        }
+    }
+}
+
+/// Tools the engine executes itself
+#[derive(Clone)]
+pub struct ToolRegistry {
+    tools: HashMap<String, RegisteredTool>,
+    max_iterations: usize,
+    max_duration: Option<Duration>,
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self {
+            tools: HashMap::new(),
+            max_iterations: DEFAULT_MAX_TOOL_ITERATIONS,
+            max_duration: None,
+        }
+    }
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a tool, replacing any tool with the same name
+    pub fn register(&mut self, tool: RegisteredTool) {
+        self.tools.insert(tool.name.clone(), tool);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RegisteredTool> {
+        self.tools.get(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.tools.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn max_iterations(&self) -> usize {
+        self.max_iterations
+    }
+
+    /// Cap the number of model round trips per chat request (at least 1)
+    pub fn set_max_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations.max(1);
+    }
+
+    pub fn max_duration(&self) -> Option<Duration> {
+        self.max_duration
+    }
+
+    /// Cap the time a chat request's tool loop runs, model replies and tool
+    /// calls included; `None` runs it until its iterations are used up
+    pub fn set_max_duration(&mut self, max_duration: Option<Duration>) {
+        self.max_duration = max_duration;
+    }
+
+    /// Run tool loops under `limits`
+    pub fn limit(&mut self, limits: ToolLoopLimits) {
+        self.set_max_iterations(limits.max_iterations);
+        self.set_max_duration(Some(limits.max_duration));
+    }
+
+    /// Add specs for registered tools the request doesn't already declare
+    fn advertise(&self, request: &mut ChatRequestIR) {
+        let declared: Vec<String> = request
+            .tools
+            .iter()
+            .map(|ToolSpec::JsonSchema { name, .. }| name.clone())
+            .collect();
+        let mut names = self.names();
+        names.retain(|name| !declared.contains(name));
+        request
+            .tools
+            .extend(names.iter().filter_map(|name| self.get(name)).map(RegisteredTool::spec));
+    }
+}
+
+/// Caps on one chat request's tool loop
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ToolLoopLimits {
+    /// Model round trips (at least 1)
+    pub max_iterations: usize,
+    /// Time for the whole loop, model replies and tool calls included
+    pub max_duration: Duration,
+}
+
+impl Default for ToolLoopLimits {
+    fn default() -> Self {
+        Self {
+            max_iterations: DEFAULT_MAX_TOOL_ITERATIONS,
+            max_duration: DEFAULT_MAX_TOOL_LOOP_DURATION,
+        }
+    }
+}
+
+impl ToolLoopLimits {
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = max_duration;
+        self
+    }
+}
+
+/// Which aliases' HTTP chat requests the gateway runs registered tools for,
+/// and under what limits. Without any, requests never run them, even when
+/// they set [`AUTO_TOOLS_METADATA`], unless clients may opt in.
+#[derive(Clone, Debug, Default)]
+pub struct AutoToolExecution {
+    default_limits: Option<ToolLoopLimits>,
+    limits: HashMap<String, ToolLoopLimits>,
+    client_opt_in: Option<ToolLoopLimits>,
+}
+
+impl AutoToolExecution {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run tools for aliases without limits of their own
+    pub fn with_default_limits(mut self, limits: ToolLoopLimits) -> Self {
+        self.default_limits = Some(limits);
+        self
+    }
+
+    /// Run tools for one model, keyed by discovered id (`provider/model`) or bare model name
+    pub fn with_limits(mut self, alias: impl Into<String>, limits: ToolLoopLimits) -> Self {
+        self.limits.insert(alias.into(), limits);
+        self
+    }
+
+    /// Run tools under `limits` for requests of other aliases that set
+    /// [`AUTO_TOOLS_METADATA`] to `"true"`
+    pub fn with_client_opt_in(mut self, limits: ToolLoopLimits) -> Self {
+        self.client_opt_in = Some(limits);
+        self
+    }
+
+    /// The limits `ir`'s tool loop runs under, or `None` when it returns tool
+    /// calls to the client. Takes [`AUTO_TOOLS_METADATA`] off the request so
+    /// it isn't sent upstream.
+    pub fn apply(&self, ir: &mut ChatRequestIR) -> Option<ToolLoopLimits> {
+        let configured = self
+            .limits
+            .get(&ir.model.alias)
+            .or_else(|| self.limits.get(&ir.model.model_id))
+            .copied()
+            .or(self.default_limits);
+        match ir.request_metadata.remove(AUTO_TOOLS_METADATA).as_deref() {
+            Some("true") => configured.or(self.client_opt_in),
+            Some("false") => None,
+            _ => configured,
+        }
+    }
+}
+
+/// What the tool loop does when the model calls a tool that isn't registered
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnknownToolPolicy {
+    /// End the stream normally so the caller can handle the call itself
+    ReturnToCaller,
+    /// End the stream with an `unknown_tool` error event
+    Error,
+}
+
+/// A tool call reassembled from stream events
+#[derive(Debug, Default)]
+struct PendingCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+impl PendingCall {
+    fn arguments_json(&self) -> serde_json::Value {
+        if self.arguments.trim().is_empty() {
+            return serde_json::json!({});
+        }
+        serde_json::from_str(&self.arguments).unwrap_or_else(|_| serde_json::Value::String(self.arguments.clone()))
+    }
+}
+
+/// Drive the request/tool-execution loop.
+///
+/// Events from each round trip are forwarded as they arrive, except `Done`
+/// and `FinalMessage`, which are only forwarded for the last one. The loop
+/// ends when the model stops calling tools, calls a tool the registry
+/// doesn't know (handled per `unknown_tools`), or the iteration or time cap
+/// is hit.
+///
+/// Calls from one response run concurrently unless the request sets
+/// `parallel_tool_calls: false`.
+pub fn run_tool_loop(
+    router: Arc<Router>,
+    tools: ToolRegistry,
+    mut request: ChatRequestIR,
+    cancel: CancellationToken,
+    unknown_tools: UnknownToolPolicy,
+) -> impl Stream<Item = StreamEvent> + Send {
+    tools.advertise(&mut request);
+    let deadline = tools.max_duration().map(|max_duration| Instant::now() + max_duration);
+
+    async_stream::stream! {
+        for _ in 0..tools.max_iterations() {
+            // Cancelled on its own when the loop runs out of time
+            let round = cancel.child_token();
+            let routed = tokio::select! {
+                _ = sleep_until(deadline) => {
+                    round.cancel();
+                    yield time_exceeded(&tools);
+                    return;
+                }
+                routed = router.route_chat(request.clone(), round.clone()) => routed,
+            };
+            let mut events = match routed {
+                Ok(events) => events,
+                Err(e) => {
+                    yield StreamEvent::Error { code: "routing_error".to_string(), message: e.to_string() };
+                    return;
+                }
+            };
+
+            let mut text = String::new();
+            let mut calls: Vec<PendingCall> = Vec::new();
+            let mut final_message = None;
+            loop {
+                let event = tokio::select! {
+                    _ = sleep_until(deadline) => {
+                        round.cancel();
+                        yield time_exceeded(&tools);
+                        return;
+                    }
+                    event = events.next() => event,
+                };
+                let Some(event) = event else { break };
+                match event {
+                    StreamEvent::TextDelta { ref content } => {
+                        text.push_str(content);
+                        yield event;
+                    }
+                    StreamEvent::ToolCallStart { ref id, ref name, ref args_json } => {
+                        let arguments = match args_json {
+                            serde_json::Value::Object(map) if map.is_empty() => String::new(),
+                            other => ContentPart::arguments_string(other),
+                        };
+                        calls.push(PendingCall { id: id.clone(), name: name.clone(), arguments });
+                        yield event;
+                    }
+                    StreamEvent::ToolCallDelta { ref id, ref args_delta_json } => {
+                        if let Some(call) = calls.iter_mut().find(|c| &c.id == id) {
+                            call.arguments.push_str(&ContentPart::arguments_string(args_delta_json));
+                        }
+                        yield event;
+                    }
+                    StreamEvent::FinalMessage { .. } => final_message = Some(event),
+                    StreamEvent::Done => break,
+                    StreamEvent::Error { .. } => {
+                        yield event;
+                        return;
+                    }
+                    other => yield other,
+                }
+            }
+
+            let unknown = calls.iter().find(|c| tools.get(&c.name).is_none());
+            if let (Some(call), UnknownToolPolicy::Error) = (unknown, unknown_tools) {
+                yield StreamEvent::Error {
+                    code: "unknown_tool".to_string(),
+                    message: format!("Model called unregistered tool '{}'", call.name),
+                };
+                return;
+            }
+            if calls.is_empty() || unknown.is_some() {
+                if let Some(final_message) = final_message {
+                    yield final_message;
+                }
+                yield StreamEvent::Done;
+                return;
+            }
+
+            let mut assistant_parts = Vec::new();
+            if !text.is_empty() {
+                assistant_parts.push(ContentPart::Text(text));
+            }
+            assistant_parts.extend(calls.iter().map(|call| ContentPart::ToolCall {
+                id: call.id.clone(),
+                name: call.name.clone(),
+                arguments: call.arguments_json(),
+            }));
+            request.messages.push(Message { role: Role::Assistant, parts: assistant_parts, name: None });
+
+            // Run the whole batch at once, or one call at a time
+            let batches: Vec<&[PendingCall]> = if request.sampling.parallel_tool_calls == Some(false) {
+                calls.chunks(1).collect()
+            } else {
+                vec![&calls[..]]
+            };
+            for batch in batches {
+                for call in batch {
+                    yield StreamEvent::ToolExecutionStart { id: call.id.clone(), name: call.name.clone() };
+                }
+                let executions = batch.iter().map(|call| {
+                    let handler = tools.get(&call.name).map(|t| t.handler.clone()).expect("checked above");
+                    let arguments = call.arguments_json();
+                    async move { handler.call(arguments).await }
+                });
+                let results = tokio::select! {
+                    _ = cancel.cancelled() => {
+                        yield StreamEvent::Error { code: "cancelled".to_string(), message: "Request was cancelled".to_string() };
+                        return;
+                    }
+                    _ = sleep_until(deadline) => {
+                        yield time_exceeded(&tools);
+                        return;
+                    }
+                    results = futures_util::future::join_all(executions) => results,
+                };
+
+                for (call, result) in batch.iter().zip(results) {
+                    let (output, is_error) = match result {
+                        Ok(output) => (output, false),
+                        Err(message) => (message, true),
+                    };
+                    yield StreamEvent::ToolExecutionEnd {
+                        id: call.id.clone(),
+                        name: call.name.clone(),
+                        output: output.clone(),
+                        is_error,
+                    };
+                    request.messages.push(Message {
+                        role: Role::Tool,
+                        parts: vec![ContentPart::ToolResult { call_id: call.id.clone(), content: output }],
+                        name: None,
+                    });
+                }
+            }
+        }
+
+        yield StreamEvent::Error {
+            code: "tool_iterations_exceeded".to_string(),
+            message: format!("Stopped after {} tool-calling round trips", tools.max_iterations()),
+        };
+    }
+}
+
+/// Wait for `deadline`, or forever without one
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+fn time_exceeded(tools: &ToolRegistry) -> StreamEvent {
+    StreamEvent::Error {
+        code: "tool_time_exceeded".to_string(),
+        message: format!(
+            "Stopped after {}s of tool calling",
+            tools.max_duration().unwrap_or_default().as_secs_f64()
+        ),
+    }
+}
@@ -0,0 +1,191 @@
+//! Routing traces of single requests
+//!
+//! When a backend misbehaves, it helps to know where a request went. Chat
+//! requests with [`TRACE_METADATA`] set report it in their event stream: for
+//! each call to a provider, the router sends a `Status` event with the state
+//! [`UPSTREAM_STATE`] once the provider's response headers arrive, e.g.
+//!
+//! ```text
+//! upstream: latency_ms=182 request_id=req_8f2c
+//! ```
+//!
+//! next to the `endpoint` status of pooled providers and the `json_repair`
+//! status of retried replies. A [`RouteTrace`] collects these events; the
+//! OpenAI skin sets the metadata for requests with the
+//! `x-omniference-trace: true` header and answers with the trace's
+//! [`headers`](RouteTrace::headers).
+
+use crate::stream::StreamEvent;
+use futures_util::{Stream, StreamExt};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Metadata key asking the router to trace a request
+pub const TRACE_METADATA: &str = "trace";
+
+/// `Status` state of a call to a provider
+pub const UPSTREAM_STATE: &str = "upstream";
+
+/// Response header naming the provider (or pool member) that served the request
+pub const RESOLVED_PROVIDER_HEADER: &str = "x-omniference-resolved-provider";
+
+/// Response header with the milliseconds until the provider's response headers arrived
+pub const UPSTREAM_LATENCY_HEADER: &str = "x-omniference-upstream-latency-ms";
+
+/// Response header counting the times the request was sent again
+pub const RETRIES_HEADER: &str = "x-omniference-retries";
+
+/// Response header with the provider's own id of the request
+pub const UPSTREAM_REQUEST_ID_HEADER: &str = "x-omniference-upstream-request-id";
+
+/// Headers providers put their request ids in
+pub const UPSTREAM_REQUEST_ID_HEADERS: &[&str] = &["x-request-id", "request-id", "x-amzn-requestid"];
+
+/// One call to a provider, as sent in an [`UPSTREAM_STATE`] event
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpstreamCall {
+    /// Until the response headers arrived
+    pub latency: Duration,
+    /// The provider's id of the request, when it sends one
+    pub request_id: Option<String>,
+}
+
+impl UpstreamCall {
+    pub fn to_event(&self) -> StreamEvent {
+        let mut detail = format!("latency_ms={}", self.latency.as_millis());
+        if let Some(id) = &self.request_id {
+            detail.push_str(&format!(" request_id={}", id));
+        }
+        StreamEvent::Status {
+            state: UPSTREAM_STATE.to_string(),
+            detail: Some(detail),
+        }
+    }
+
+    /// The call `event` reports, if it is an [`UPSTREAM_STATE`] event
+    pub fn from_event(event: &StreamEvent) -> Option<Self> {
+        let StreamEvent::Status { state, detail } = event else {
+            return None;
+        };
+        if state != UPSTREAM_STATE {
+            return None;
+        }
+        let mut call = UpstreamCall {
+            latency: Duration::ZERO,
+            request_id: None,
+        };
+        for field in detail.as_deref().unwrap_or_default().split_whitespace() {
+            match field.split_once('=') {
+                Some(("latency_ms", millis)) => call.latency = Duration::from_millis(millis.parse().ok()?),
+                Some(("request_id", id)) => call.request_id = Some(id.to_string()),
+                _ => {}
+            }
+        }
+        Some(call)
+    }
+}
+
+/// Where a request went, collected from its events
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RouteTrace {
+    /// The provider, pool member or pool serving the request
+    pub provider: Option<String>,
+    /// The latest call to a provider
+    pub upstream: Option<UpstreamCall>,
+    /// Times the request was sent again, e.g. to repair invalid JSON
+    pub retries: u32,
+}
+
+impl RouteTrace {
+    /// A trace of a request for `model`, served by the provider (or pool)
+    /// its ID starts with until an `endpoint` status says otherwise
+    pub fn for_model(model: &crate::types::ModelRef) -> Self {
+        // Tenant providers' names contain a slash themselves
+        let provider = model
+            .alias
+            .strip_suffix(model.model_id.as_str())
+            .and_then(|prefix| prefix.strip_suffix('/'))
+            .or_else(|| model.alias.split_once('/').map(|(provider, _)| provider));
+        Self {
+            provider: provider.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    /// Record what `event` says about the route
+    pub fn observe(&mut self, event: &StreamEvent) {
+        if let Some(call) = UpstreamCall::from_event(event) {
+            self.upstream = Some(call);
+            return;
+        }
+        match event {
+            StreamEvent::Status { state, detail: Some(provider) } if state == "endpoint" => {
+                self.provider = Some(provider.clone());
+            }
+            StreamEvent::Status { state, .. } if state == crate::validation::REPAIR_STATE => self.retries += 1,
+            _ => {}
+        }
+    }
+
+    /// The trace's response headers, leaving out what isn't known
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = Vec::new();
+        if let Some(provider) = &self.provider {
+            headers.push((RESOLVED_PROVIDER_HEADER, provider.clone()));
+        }
+        if let Some(call) = &self.upstream {
+            headers.push((UPSTREAM_LATENCY_HEADER, call.latency.as_millis().to_string()));
+            if let Some(id) = &call.request_id {
+                headers.push((UPSTREAM_REQUEST_ID_HEADER, id.clone()));
+            }
+        }
+        headers.push((RETRIES_HEADER, self.retries.to_string()));
+        headers
+    }
+}
+
+/// Record the events `events` starts with into `trace`, up to the first call
+/// to a provider or the first event that isn't a status, and pass them on
+/// followed by the rest. For setting the headers of streamed responses.
+pub async fn observe_leading<S>(mut events: S, trace: &mut RouteTrace) -> impl Stream<Item = StreamEvent> + Send + Unpin
+where
+    S: Stream<Item = StreamEvent> + Send + Unpin,
+{
+    let mut leading = Vec::new();
+    while let Some(event) = events.next().await {
+        trace.observe(&event);
+        let last = !matches!(event, StreamEvent::Status { .. }) || UpstreamCall::from_event(&event).is_some();
+        leading.push(event);
+        if last {
+            break;
+        }
+    }
+    futures_util::stream::iter(leading).chain(events)
+}
+
+tokio::task_local! {
+    static UPSTREAM_REQUEST_ID: Arc<Mutex<Option<String>>>;
+}
+
+/// Run `call`, returning its output and the request id of the last provider
+/// response it received (see [`record_response`])
+pub(crate) async fn capture_request_id<F: std::future::Future>(call: F) -> (F::Output, Option<String>) {
+    let slot = Arc::new(Mutex::new(None));
+    let output = UPSTREAM_REQUEST_ID.scope(slot.clone(), call).await;
+    let id = slot.lock().unwrap_or_else(|e| e.into_inner()).take();
+    (output, id)
+}
+
+/// Note the request id of a provider's `response` for [`capture_request_id`]
+pub(crate) fn record_response(response: &reqwest::Response) {
+    let Some(id) = UPSTREAM_REQUEST_ID_HEADERS
+        .iter()
+        .find_map(|name| response.headers().get(*name)?.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+    else {
+        return;
+    };
+    // Outside a capture there is nobody to tell
+    let _ = UPSTREAM_REQUEST_ID.try_with(|slot| *slot.lock().unwrap_or_else(|e| e.into_inner()) = Some(id.to_string()));
+}
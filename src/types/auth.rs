@@ -0,0 +1,202 @@
+//! Provider authentication beyond a static API key
+//!
+//! An endpoint with an [`AuthMethod`] sends a bearer token obtained from the
+//! configured flow instead of `api_key`. OAuth2 tokens are cached per
+//! `(token_url, client_id, scope)` and refreshed shortly before they expire;
+//! the refresh point is jittered so that replicas sharing a client don't all
+//! hit the token endpoint at once.
+//!
+//! An endpoint in [`AuthMode::Passthrough`] sends no credentials of its own:
+//! each request carries the caller's key (see [`ProviderKey`]), and requests
+//! without one are rejected.
+
+use super::{ProviderEndpoint, SecretString};
+use crate::adapter::AdapterError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Token requests attempted before giving up, including the first
+const TOKEN_ATTEMPTS: u32 = 3;
+/// Delay before the first retry; doubled for each further attempt
+const TOKEN_RETRY_BACKOFF: Duration = Duration::from_millis(250);
+/// Assumed lifetime of tokens issued without `expires_in`
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(300);
+
+/// Whose credentials an endpoint sends upstream
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    /// The endpoint's `api_key` or `auth`
+    #[default]
+    Configured,
+    /// The caller's own key, per request (bring your own key)
+    Passthrough,
+}
+
+impl AuthMode {
+    pub fn is_configured(&self) -> bool {
+        *self == AuthMode::Configured
+    }
+}
+
+/// The caller's own key for [`AuthMode::Passthrough`] providers, as a
+/// request extension. The server takes it from the `x-provider-api-key`
+/// header, or from `Authorization: Bearer` when no authentication layer
+/// claimed that header for the gateway by inserting an
+/// [`ApiKeyName`](crate::budget::ApiKeyName). Layers may insert it
+/// themselves.
+#[derive(Clone, Debug)]
+pub struct ProviderKey(pub SecretString);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// OAuth2 client-credentials grant (RFC 6749 §4.4)
+    #[serde(rename = "oauth2_client_credentials")]
+    OAuth2ClientCredentials {
+        token_url: String,
+        client_id: String,
+        client_secret: SecretString,
+        /// Space-separated scopes, sent as-is
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        scope: Option<String>,
+    },
+}
+
+impl AuthMethod {
+    pub fn oauth2_client_credentials(
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<SecretString>,
+        scope: Option<String>,
+    ) -> Self {
+        AuthMethod::OAuth2ClientCredentials {
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scope,
+        }
+    }
+
+    /// A bearer token for this method, fetched or taken from the cache
+    pub async fn token(&self) -> Result<String, AdapterError> {
+        match self {
+            AuthMethod::OAuth2ClientCredentials { token_url, client_id, scope, .. } => {
+                let key = (token_url.clone(), client_id.clone(), scope.clone().unwrap_or_default());
+                let slot = {
+                    let mut slots = token_slots().lock().unwrap_or_else(|e| e.into_inner());
+                    slots.entry(key).or_default().clone()
+                };
+                // Held across the fetch so concurrent requests share one refresh
+                let mut cached = slot.lock().await;
+                if let Some(token) = cached.as_ref() {
+                    if Instant::now() < token.refresh_at {
+                        return Ok(token.access_token.clone());
+                    }
+                }
+                let token = self.fetch_token().await?;
+                let access_token = token.access_token.clone();
+                *cached = Some(token);
+                Ok(access_token)
+            }
+        }
+    }
+
+    /// Drop the cached token so the next request fetches a new one
+    pub async fn invalidate(&self) {
+        let AuthMethod::OAuth2ClientCredentials { token_url, client_id, scope, .. } = self;
+        let key = (token_url.clone(), client_id.clone(), scope.clone().unwrap_or_default());
+        let slot = token_slots().lock().unwrap_or_else(|e| e.into_inner()).get(&key).cloned();
+        if let Some(slot) = slot {
+            *slot.lock().await = None;
+        }
+    }
+
+    async fn fetch_token(&self) -> Result<CachedToken, AdapterError> {
+        let AuthMethod::OAuth2ClientCredentials { token_url, client_id, client_secret, scope } = self;
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", client_id.as_str()),
+            ("client_secret", client_secret.expose_secret()),
+        ];
+        if let Some(scope) = scope {
+            form.push(("scope", scope.as_str()));
+        }
+
+        let client = reqwest::Client::new();
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let error = match client.post(token_url).form(&form).send().await {
+                Ok(resp) if resp.status().is_success() => {
+                    let fetched = Instant::now();
+                    let token: TokenResponse = resp.json().await.map_err(|e| {
+                        AdapterError::Auth(format!("invalid OAuth2 token response from {}: {}", token_url, e))
+                    })?;
+                    let lifetime = token.expires_in.map(Duration::from_secs).unwrap_or(DEFAULT_TOKEN_LIFETIME);
+                    return Ok(CachedToken {
+                        access_token: token.access_token,
+                        refresh_at: fetched + lifetime.saturating_sub(refresh_margin(lifetime)),
+                    });
+                }
+                Ok(resp) => {
+                    let status = resp.status();
+                    let text = resp.text().await.unwrap_or_default();
+                    let error = format!("OAuth2 token request to {} failed with {}: {}", token_url, status, text);
+                    // Rejected credentials won't succeed on retry
+                    if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                        return Err(AdapterError::Auth(error));
+                    }
+                    error
+                }
+                Err(e) => format!("OAuth2 token request to {} failed: {}", token_url, e),
+            };
+            if attempt >= TOKEN_ATTEMPTS {
+                return Err(AdapterError::Auth(error));
+            }
+            tracing::warn!(attempt, error = %error, "Retrying OAuth2 token request");
+            tokio::time::sleep(TOKEN_RETRY_BACKOFF * 2u32.pow(attempt - 1)).await;
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+struct CachedToken {
+    access_token: String,
+    refresh_at: Instant,
+}
+
+type TokenSlot = Arc<tokio::sync::Mutex<Option<CachedToken>>>;
+/// Cached tokens keyed by `(token_url, client_id, scope)`
+type TokenSlots = Mutex<HashMap<(String, String, String), TokenSlot>>;
+
+fn token_slots() -> &'static TokenSlots {
+    static SLOTS: OnceLock<TokenSlots> = OnceLock::new();
+    SLOTS.get_or_init(Default::default)
+}
+
+/// How long before expiry to refresh: a tenth of the lifetime (at most a
+/// minute) plus up to half as much again at random
+fn refresh_margin(lifetime: Duration) -> Duration {
+    let base = (lifetime / 10).min(Duration::from_secs(60));
+    let jitter = (uuid::Uuid::new_v4().as_u128() % 1000) as u32;
+    base + base / 2 * jitter / 1000
+}
+
+impl ProviderEndpoint {
+    /// The bearer token to send: from `auth` when set, else `api_key`
+    pub async fn bearer_token(&self) -> Result<Option<String>, AdapterError> {
+        match &self.auth {
+            Some(auth) => auth.token().await.map(Some),
+            None => Ok(self.api_key.as_ref().map(|key| key.expose_secret().to_string())),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Request headers resolved per request
+//!
+//! `ProviderEndpoint::extra_headers` holds fixed strings. A [`HeaderProvider`]
+//! produces header values when a request is sent instead: from the
+//! environment, from a file (e.g. a token rotated by a sidecar), or from
+//! library code through a [`HeaderCallback`] whose results are cached for a
+//! TTL. A provider that can't produce its header fails the request with
+//! [`AdapterError::Auth`] before the upstream is contacted.
+
+use super::{ProviderEndpoint, ProviderKind};
+use crate::adapter::AdapterError;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Header naming the OpenAI organization of a request (see
+/// [`ProviderEndpoint::organization`])
+pub const ORGANIZATION_HEADER: &str = "OpenAI-Organization";
+
+/// Header naming the OpenAI project of a request (see
+/// [`ProviderEndpoint::project`])
+pub const PROJECT_HEADER: &str = "OpenAI-Project";
+
+/// Produces headers for library-defined sources, e.g. a short-lived JWT
+#[async_trait]
+pub trait HeaderCallback: Send + Sync {
+    async fn headers(&self) -> Result<BTreeMap<String, String>, AdapterError>;
+}
+
+type HeaderCache = Arc<tokio::sync::Mutex<Option<(Instant, BTreeMap<String, String>)>>>;
+
+/// A [`HeaderCallback`] whose result is reused for `ttl`
+#[derive(Clone)]
+pub struct CachedHeaders {
+    callback: Arc<dyn HeaderCallback>,
+    ttl: Duration,
+    cache: HeaderCache,
+}
+
+impl CachedHeaders {
+    pub fn new(callback: Arc<dyn HeaderCallback>, ttl: Duration) -> Self {
+        Self {
+            callback,
+            ttl,
+            cache: Arc::new(tokio::sync::Mutex::new(None)),
+        }
+    }
+
+    /// Drop the cached headers so the next request calls the callback again
+    pub async fn invalidate(&self) {
+        *self.cache.lock().await = None;
+    }
+
+    async fn headers(&self) -> Result<BTreeMap<String, String>, AdapterError> {
+        // Held across the call so concurrent requests share one refresh
+        let mut cache = self.cache.lock().await;
+        if let Some((fetched, headers)) = cache.as_ref() {
+            if fetched.elapsed() < self.ttl {
+                return Ok(headers.clone());
+            }
+        }
+        let headers = self.callback.headers().await.map_err(|e| match e {
+            AdapterError::Auth(message) => AdapterError::Auth(message),
+            other => AdapterError::Auth(format!("header callback failed: {}", other)),
+        })?;
+        *cache = Some((Instant::now(), headers.clone()));
+        Ok(headers)
+    }
+}
+
+impl std::fmt::Debug for CachedHeaders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedHeaders").field("ttl", &self.ttl).finish_non_exhaustive()
+    }
+}
+
+/// A source of request headers, evaluated for every request
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum HeaderProvider {
+    /// Fixed values, like `extra_headers`
+    Static { headers: BTreeMap<String, String> },
+    /// `header` set from environment variable `var`
+    Env {
+        header: String,
+        var: String,
+        /// Prepended to the value, e.g. `"Bearer "`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prefix: Option<String>,
+    },
+    /// `header` set from the contents of `path`, trimmed
+    File {
+        header: String,
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prefix: Option<String>,
+    },
+    /// Headers from library code; only available programmatically, and
+    /// serialized as a placeholder
+    #[serde(serialize_with = "redacted_callback", skip_deserializing)]
+    Callback(CachedHeaders),
+}
+
+/// Endpoints holding a callback still serialize, e.g. into audit records
+fn redacted_callback<S: serde::Serializer>(_: &CachedHeaders, serializer: S) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeMap;
+
+    let mut map = serializer.serialize_map(Some(1))?;
+    map.serialize_entry("callback", "<redacted>")?;
+    map.end()
+}
+
+impl std::fmt::Debug for HeaderProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeaderProvider::Static { headers } => f
+                .debug_struct("Static")
+                .field("headers", &super::secret::RedactedHeaders(headers))
+                .finish(),
+            HeaderProvider::Env { header, var, prefix } => f
+                .debug_struct("Env")
+                .field("header", header)
+                .field("var", var)
+                .field("prefix", prefix)
+                .finish(),
+            HeaderProvider::File { header, path, prefix } => f
+                .debug_struct("File")
+                .field("header", header)
+                .field("path", path)
+                .field("prefix", prefix)
+                .finish(),
+            HeaderProvider::Callback(cached) => f.debug_tuple("Callback").field(cached).finish(),
+        }
+    }
+}
+
+impl HeaderProvider {
+    pub fn env(header: impl Into<String>, var: impl Into<String>) -> Self {
+        HeaderProvider::Env {
+            header: header.into(),
+            var: var.into(),
+            prefix: None,
+        }
+    }
+
+    pub fn file(header: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        HeaderProvider::File {
+            header: header.into(),
+            path: path.into(),
+            prefix: None,
+        }
+    }
+
+    /// Call `callback` at most once per `ttl`
+    pub fn callback(callback: Arc<dyn HeaderCallback>, ttl: Duration) -> Self {
+        HeaderProvider::Callback(CachedHeaders::new(callback, ttl))
+    }
+
+    /// Prepend `prefix` to env and file values (no effect on other sources)
+    pub fn with_prefix(mut self, value: impl Into<String>) -> Self {
+        if let HeaderProvider::Env { prefix, .. } | HeaderProvider::File { prefix, .. } = &mut self {
+            *prefix = Some(value.into());
+        }
+        self
+    }
+
+    async fn resolve(&self, headers: &mut BTreeMap<String, String>) -> Result<(), AdapterError> {
+        let (header, value, prefix) = match self {
+            HeaderProvider::Static { headers: values } => {
+                headers.extend(values.clone());
+                return Ok(());
+            }
+            HeaderProvider::Callback(cached) => {
+                headers.extend(cached.headers().await?);
+                return Ok(());
+            }
+            HeaderProvider::Env { header, var, prefix } => {
+                let value = std::env::var(var).map_err(|_| {
+                    AdapterError::Auth(format!("environment variable {} for header {} is not set", var, header))
+                })?;
+                (header, value, prefix)
+            }
+            HeaderProvider::File { header, path, prefix } => {
+                let value = tokio::fs::read_to_string(path).await.map_err(|e| {
+                    AdapterError::Auth(format!("failed to read header {} from {}: {}", header, path.display(), e))
+                })?;
+                (header, value, prefix)
+            }
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(AdapterError::Auth(format!("header {} resolved to an empty value", header)));
+        }
+        headers.insert(header.clone(), format!("{}{}", prefix.as_deref().unwrap_or(""), value));
+        Ok(())
+    }
+}
+
+impl ProviderEndpoint {
+    /// `extra_headers`, the OpenAI `organization` and `project` headers, and
+    /// the values of `header_providers`, later sources overriding earlier ones
+    pub async fn headers(&self) -> Result<BTreeMap<String, String>, AdapterError> {
+        let mut headers = self.extra_headers.clone();
+        if matches!(self.kind, ProviderKind::OpenAI | ProviderKind::OpenAICompat) {
+            for (name, value) in [(ORGANIZATION_HEADER, &self.organization), (PROJECT_HEADER, &self.project)] {
+                if let Some(value) = value {
+                    headers.retain(|existing, _| !existing.eq_ignore_ascii_case(name));
+                    headers.insert(name.to_string(), value.clone());
+                }
+            }
+        }
+        for provider in &self.header_providers {
+            provider.resolve(&mut headers).await?;
+        }
+        Ok(headers)
+    }
+}
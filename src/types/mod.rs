@@ -1,30 +1,394 @@
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
 
 // Provider-specific types are organized in the providers module
 pub mod providers;
+pub mod auth;
+pub mod headers;
+pub mod payload;
+pub mod secret;
 
 // Re-export provider types for convenience
 pub use providers::*;
+pub use auth::{AuthMethod, AuthMode, ProviderKey};
+pub use headers::{CachedHeaders, HeaderCallback, HeaderProvider, ORGANIZATION_HEADER, PROJECT_HEADER};
+pub use payload::{PayloadHook, PayloadTransformer};
+pub use secret::{expose_secrets, SecretString};
+pub use crate::templates::{ChatTemplate, PromptTemplate};
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[serde(from = "ProviderKindRepr")]
 pub enum ProviderKind {
     OpenAI,
     OpenAICompat,
     Anthropic,
+    Cohere,
+    Mistral,
     Google,
+    /// Hugging Face Text Generation Inference (`/generate`)
+    Tgi,
     Ollama,
     LMStudio,
     Custom(String),
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl ProviderKind {
+    /// Base URL used when the endpoint leaves `base_url` empty
+    pub fn default_base_url(&self) -> Option<&'static str> {
+        match self {
+            ProviderKind::OpenAI => Some("https://api.openai.com"),
+            ProviderKind::Anthropic => Some("https://api.anthropic.com"),
+            ProviderKind::Cohere => Some("https://api.cohere.com"),
+            ProviderKind::Mistral => Some("https://api.mistral.ai"),
+            ProviderKind::Google => Some("https://generativelanguage.googleapis.com"),
+            ProviderKind::Ollama => Some("http://localhost:11434"),
+            ProviderKind::LMStudio => Some("http://localhost:1234"),
+            ProviderKind::OpenAICompat | ProviderKind::Tgi | ProviderKind::Custom(_) => None,
+        }
+    }
+}
+
+/// Spellings accepted when deserializing a [`ProviderKind`]: the derived
+/// form (`"OpenAI"`, `{"Custom": "name"}`) or any name [`FromStr`](std::str::FromStr)
+/// understands, such as `"custom:myprovider"`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProviderKindRepr {
+    Name(String),
+    Custom {
+        #[serde(rename = "Custom")]
+        custom: String,
+    },
+}
+
+impl From<ProviderKindRepr> for ProviderKind {
+    fn from(repr: ProviderKindRepr) -> Self {
+        match repr {
+            ProviderKindRepr::Name(name) => match name.parse() {
+                Ok(kind) => kind,
+                Err(never) => match never {},
+            },
+            ProviderKindRepr::Custom { custom } => ProviderKind::Custom(custom),
+        }
+    }
+}
+
+/// Parses the names used for `provider_type` in configuration files. Known
+/// kinds match case-insensitively; `custom:<name>` and any other name become
+/// `Custom`.
+impl std::str::FromStr for ProviderKind {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((prefix, name)) = s.split_once(':') {
+            if prefix.eq_ignore_ascii_case("custom") {
+                return Ok(ProviderKind::Custom(name.to_string()));
+            }
+        }
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "openai" => ProviderKind::OpenAI,
+            "openaicompat" | "openai-compat" | "openai_compat" => ProviderKind::OpenAICompat,
+            "anthropic" => ProviderKind::Anthropic,
+            "cohere" => ProviderKind::Cohere,
+            "mistral" => ProviderKind::Mistral,
+            "tgi" | "text-generation-inference" => ProviderKind::Tgi,
+            "google" => ProviderKind::Google,
+            "ollama" => ProviderKind::Ollama,
+            "lmstudio" | "lm-studio" => ProviderKind::LMStudio,
+            _ => ProviderKind::Custom(s.to_string()),
+        })
+    }
+}
+
+impl std::fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProviderKind::Custom(name) => write!(f, "custom:{}", name),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// Known OpenAI-compatible vendors with their own defaults and parameter quirks.
+///
+/// Meant for `ProviderKind::OpenAICompat` endpoints, but the OpenAI chat
+/// adapter applies the profile's URL defaults and field changes to any
+/// endpoint that sets one. The default `Generic` changes nothing.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Default)]
+pub enum CompatProfile {
+    #[default]
+    Generic,
+    Groq,
+    Mistral,
+    XAI,
+    DeepSeek,
+    /// Self-hosted vLLM; enables its guided decoding and beam search fields
+    VLLM,
+}
+
+impl CompatProfile {
+    /// Base URL used when the endpoint leaves `base_url` empty
+    pub fn default_base_url(&self) -> Option<&'static str> {
+        match self {
+            CompatProfile::Generic => None,
+            CompatProfile::Groq => Some("https://api.groq.com/openai"),
+            CompatProfile::Mistral => Some("https://api.mistral.ai"),
+            CompatProfile::XAI => Some("https://api.x.ai"),
+            CompatProfile::DeepSeek => Some("https://api.deepseek.com"),
+            CompatProfile::VLLM => None,
+        }
+    }
+
+    /// Path prefix for API routes; DeepSeek serves them without `/v1`
+    pub fn api_prefix(&self) -> &'static str {
+        match self {
+            CompatProfile::DeepSeek => "",
+            _ => "/v1",
+        }
+    }
+
+    /// Body fields the vendor rejects and that must be stripped before sending
+    pub fn unsupported_fields(&self) -> &'static [&'static str] {
+        match self {
+            CompatProfile::Generic | CompatProfile::VLLM => &[],
+            CompatProfile::Groq => &[
+                "logit_bias",
+                "logprobs",
+                "top_logprobs",
+                "store",
+                "metadata",
+                "prediction",
+                "modalities",
+                "audio",
+                "web_search_options",
+            ],
+            CompatProfile::Mistral => &[
+                "logit_bias",
+                "logprobs",
+                "top_logprobs",
+                "user",
+                "store",
+                "metadata",
+                "service_tier",
+                "stream_options",
+                "reasoning_effort",
+                "prompt_cache_key",
+                "safety_identifier",
+            ],
+            CompatProfile::XAI => &["logit_bias", "store", "metadata", "web_search_options"],
+            CompatProfile::DeepSeek => &[
+                "logit_bias",
+                "store",
+                "metadata",
+                "service_tier",
+                "prediction",
+                "web_search_options",
+                "prompt_cache_key",
+                "safety_identifier",
+            ],
+        }
+    }
+
+    /// Body fields the vendor accepts under a different name, as `(ours, theirs)`
+    pub fn renamed_fields(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            CompatProfile::Mistral => &[
+                ("max_completion_tokens", "max_tokens"),
+                ("seed", "random_seed"),
+            ],
+            CompatProfile::DeepSeek => &[("max_completion_tokens", "max_tokens")],
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ProviderEndpoint {
     pub kind: ProviderKind,
+    #[serde(default)]
     pub base_url: String,
-    pub api_key: Option<String>,
+    pub api_key: Option<SecretString>,
+    #[serde(default, serialize_with = "secret::serialize_headers")]
     pub extra_headers: BTreeMap<String, String>,
+    /// Deprecated alias of `first_byte_timeout_ms`, used when that is unset
     pub timeout: Option<u64>,
+    /// Longest wait for a TCP/TLS connection, in milliseconds; failures have
+    /// the code `connect_timeout`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_ms: Option<u64>,
+    /// Longest wait for the response headers once the request is sent, in
+    /// milliseconds; failures have the code `first_byte_timeout`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_byte_timeout_ms: Option<u64>,
+    /// Longest gap between two chunks of a streamed response, in
+    /// milliseconds; the stream then ends with a `stream_stalled` error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_stream_timeout_ms: Option<u64>,
+    /// Proxy for this provider's requests, e.g. `http://proxy.corp:3128`, or
+    /// `"none"` to connect directly. Unset uses `HTTPS_PROXY` and friends.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<String>,
+    /// PEM file of CA certificates to trust besides the system roots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert_path: Option<String>,
+    /// Skip TLS certificate validation; only for testing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub danger_accept_invalid_certs: bool,
+    /// Speak HTTP/2 from the first byte, without TLS negotiation or an
+    /// upgrade; for gateways that serve cleartext HTTP/2 (h2c)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub http2_prior_knowledge: bool,
+    /// Idle connections kept open to the provider's host; unset keeps all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_max_idle_per_host: Option<usize>,
+    /// How long an idle pooled connection is kept, in milliseconds; unset
+    /// keeps it 90 seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_idle_timeout_ms: Option<u64>,
+    /// Interval of TCP keepalive probes on the provider's connections, in
+    /// milliseconds; unset sends none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive_ms: Option<u64>,
+    /// Gzip JSON request bodies of 1 KiB or more, with `Content-Encoding:
+    /// gzip`; only for providers whose server decodes compressed requests
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compress_requests: bool,
+    /// Static fields merged verbatim into every outbound request body sent to
+    /// this provider (e.g. OpenRouter `provider` preferences or `transforms`).
+    #[serde(default)]
+    pub extensions: serde_json::Map<String, serde_json::Value>,
+    /// Vendor preset for OpenAI-compatible endpoints
+    #[serde(default)]
+    pub compat_profile: CompatProfile,
+    /// Prompt templates by model id for adapters that send a single prompt
+    /// (TGI); `*` applies to models without their own, and
+    /// [`ChatTemplate::default`] to the rest (see [`crate::templates`])
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub chat_templates: BTreeMap<String, PromptTemplate>,
+    /// Headers evaluated per request, applied over `extra_headers`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub header_providers: Vec<HeaderProvider>,
+    /// Token-based authentication used instead of `api_key`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthMethod>,
+    /// Send the caller's own key instead of `api_key` or `auth`
+    #[serde(default, skip_serializing_if = "AuthMode::is_configured")]
+    pub auth_mode: AuthMode,
+    /// OpenAI organization billed for the requests, sent as
+    /// `OpenAI-Organization` to OpenAI and OpenAI-compatible providers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    /// OpenAI project billed for the requests, sent as `OpenAI-Project`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Settings for the adapter factory of a `Custom` kind (see
+    /// [`crate::AdapterRegistry::register_factory`]); also visible to the
+    /// adapter on every request
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub adapter_options: serde_json::Map<String, serde_json::Value>,
+    /// Headers added to requests in [`PrivacyMode::NoStore`], e.g. a proxy's
+    /// `X-Data-Retention: none`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty", serialize_with = "secret::serialize_headers")]
+    pub privacy_headers: BTreeMap<String, String>,
+    /// Hooks over the JSON bodies sent to and received from this provider;
+    /// only available programmatically
+    #[serde(skip)]
+    pub payload_transformer: Option<PayloadTransformer>,
+}
+
+impl std::fmt::Debug for ProviderEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProviderEndpoint")
+            .field("kind", &self.kind)
+            .field("base_url", &self.base_url)
+            .field("api_key", &self.api_key)
+            .field("extra_headers", &secret::RedactedHeaders(&self.extra_headers))
+            .field("timeout", &self.timeout)
+            .field("connect_timeout_ms", &self.connect_timeout_ms)
+            .field("first_byte_timeout_ms", &self.first_byte_timeout_ms)
+            .field("idle_stream_timeout_ms", &self.idle_stream_timeout_ms)
+            .field("proxy_url", &self.proxy_url.as_deref().map(secret::redact_url_password))
+            .field("ca_cert_path", &self.ca_cert_path)
+            .field("danger_accept_invalid_certs", &self.danger_accept_invalid_certs)
+            .field("http2_prior_knowledge", &self.http2_prior_knowledge)
+            .field("pool_max_idle_per_host", &self.pool_max_idle_per_host)
+            .field("pool_idle_timeout_ms", &self.pool_idle_timeout_ms)
+            .field("tcp_keepalive_ms", &self.tcp_keepalive_ms)
+            .field("compress_requests", &self.compress_requests)
+            .field("extensions", &self.extensions)
+            .field("compat_profile", &self.compat_profile)
+            .field("chat_templates", &self.chat_templates)
+            .field("header_providers", &self.header_providers)
+            .field("auth", &self.auth)
+            .field("auth_mode", &self.auth_mode)
+            .field("organization", &self.organization)
+            .field("project", &self.project)
+            .field("adapter_options", &self.adapter_options)
+            .field("privacy_headers", &secret::RedactedHeaders(&self.privacy_headers))
+            .field("payload_transformer", &self.payload_transformer)
+            .finish()
+    }
+}
+
+impl Default for ProviderEndpoint {
+    fn default() -> Self {
+        Self {
+            kind: ProviderKind::OpenAI,
+            base_url: String::new(),
+            api_key: None,
+            extra_headers: BTreeMap::new(),
+            timeout: None,
+            connect_timeout_ms: None,
+            first_byte_timeout_ms: None,
+            idle_stream_timeout_ms: None,
+            proxy_url: None,
+            ca_cert_path: None,
+            danger_accept_invalid_certs: false,
+            http2_prior_knowledge: false,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout_ms: None,
+            tcp_keepalive_ms: None,
+            compress_requests: false,
+            extensions: serde_json::Map::new(),
+            compat_profile: CompatProfile::Generic,
+            chat_templates: BTreeMap::new(),
+            header_providers: Vec::new(),
+            auth: None,
+            auth_mode: AuthMode::Configured,
+            organization: None,
+            project: None,
+            adapter_options: serde_json::Map::new(),
+            privacy_headers: BTreeMap::new(),
+            payload_transformer: None,
+        }
+    }
+}
+
+impl ProviderEndpoint {
+    /// The prompt template for `model` (see [`Self::chat_templates`])
+    pub fn chat_template(&self, model: &str) -> PromptTemplate {
+        self.chat_templates
+            .get(model)
+            .or_else(|| self.chat_templates.get("*"))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// `first_byte_timeout_ms`, or the deprecated `timeout`
+    pub fn first_byte_timeout(&self) -> Option<Duration> {
+        self.first_byte_timeout_ms.or(self.timeout).map(Duration::from_millis)
+    }
+
+    pub fn idle_stream_timeout(&self) -> Option<Duration> {
+        self.idle_stream_timeout_ms.map(Duration::from_millis)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -32,6 +396,152 @@ pub struct ProviderConfig {
     pub name: String,
     pub endpoint: ProviderEndpoint,
     pub enabled: bool,
+    /// Capability overrides keyed by model name as discovered from the provider
+    #[serde(default)]
+    pub model_overrides: BTreeMap<String, ModelCapabilityOverride>,
+    /// Providers sharing a pool serve the same models; requests for
+    /// `{pool}/{model}` are load-balanced across them (see [`crate::balancer`])
+    #[serde(default)]
+    pub pool: Option<String>,
+    /// Share of a weighted pool's traffic (default 1)
+    #[serde(default)]
+    pub weight: Option<u32>,
+    /// Requests sent to this provider at once; more wait in a queue (see [`crate::limiter`])
+    #[serde(default)]
+    pub max_concurrent_requests: Option<usize>,
+    /// Requests allowed to wait for a slot; more are rejected as overloaded
+    #[serde(default)]
+    pub max_queue_depth: Option<usize>,
+    /// Longest a queued request waits before it is rejected as overloaded
+    #[serde(default)]
+    pub queue_timeout_ms: Option<u64>,
+    /// Tenant owning the provider; shared by all tenants when unset (see [`crate::tenant`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Model asked for one token when the provider is verified (see [`crate::verify`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_model: Option<String>,
+    /// Resolve the provider's host and open a pooled connection when it is
+    /// registered (see [`crate::warmup`])
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub warm_up: bool,
+    /// Warm up the connection on registration and keep it from going idle
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub keep_warm: bool,
+}
+
+impl ProviderConfig {
+    /// The name the provider is registered under: `{tenant}/{name}` for a
+    /// tenant's provider, else its name
+    pub fn qualified_name(&self) -> String {
+        crate::tenant::qualified_name(self.tenant.as_deref(), &self.name)
+    }
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            endpoint: ProviderEndpoint::default(),
+            enabled: true,
+            model_overrides: BTreeMap::new(),
+            pool: None,
+            weight: None,
+            max_concurrent_requests: None,
+            max_queue_depth: None,
+            queue_timeout_ms: None,
+            tenant: None,
+            probe_model: None,
+            warm_up: false,
+            keep_warm: false,
+        }
+    }
+}
+
+/// Configured capability values for a single model.
+///
+/// These take precedence over anything the provider advertises and over the
+/// adapters' id-based heuristics; unset fields keep the discovered value.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct ModelCapabilityOverride {
+    pub context_length: Option<u32>,
+    pub max_tokens: Option<u32>,
+    pub supports_tools: Option<bool>,
+    pub supports_vision: Option<bool>,
+    pub supports_json: Option<bool>,
+    pub supports_audio: Option<bool>,
+    /// Provider-specific capability flags, set over the discovered ones
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extras: BTreeMap<String, serde_json::Value>,
+}
+
+impl ModelCapabilityOverride {
+    /// Apply the configured values to a discovered model, keeping its
+    /// modalities in sync with the vision/audio flags.
+    pub fn apply(&self, model: &mut DiscoveredModel) {
+        let caps = &mut model.capabilities;
+        if let Some(context_length) = self.context_length {
+            caps.context_length = Some(context_length);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            caps.max_tokens = Some(max_tokens);
+        }
+        if let Some(tools) = self.supports_tools {
+            caps.supports_tools = tools;
+        }
+        if let Some(json) = self.supports_json {
+            caps.supports_json = json;
+        }
+        if let Some(vision) = self.supports_vision {
+            caps.supports_vision = vision;
+            set_modality(&mut model.modalities, Modality::Vision, vision);
+        }
+        if let Some(audio) = self.supports_audio {
+            caps.supports_audio = audio;
+            set_modality(&mut model.modalities, Modality::AudioIn, audio);
+        }
+        caps.extras.extend(self.extras.clone());
+    }
+}
+
+/// Add or remove a modality, keeping the list free of duplicates
+pub(crate) fn set_modality(modalities: &mut Vec<Modality>, modality: Modality, enabled: bool) {
+    let present = modalities.contains(&modality);
+    if enabled && !present {
+        modalities.push(modality);
+    } else if !enabled && present {
+        modalities.retain(|m| *m != modality);
+    }
+}
+
+/// Unix timestamp in seconds of an RFC 3339 date-time such as
+/// `2024-05-07T11:41:34.455385849-07:00`
+pub(crate) fn parse_rfc3339(timestamp: &str) -> Option<u64> {
+    let (date, time) = timestamp.split_once(['T', 't', ' '])?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let (clock, offset) = match time.strip_suffix(['Z', 'z']) {
+        Some(clock) => (clock, 0),
+        None => {
+            let (clock, offset) = time.split_at(time.rfind(['+', '-'])?);
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let seconds = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+            (clock, if offset.starts_with('-') { -seconds } else { seconds })
+        }
+    };
+    let mut clock = clock.split('.').next()?.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (clock.next()??, clock.next()??, clock.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    // Days since 1970-01-01 in the proleptic Gregorian calendar
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    u64::try_from(days * 86_400 + hour * 3600 + minute * 60 + second - offset).ok()
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -42,9 +552,18 @@ pub struct DiscoveredModel {
     pub provider_kind: ProviderKind,
     pub modalities: Vec<Modality>,
     pub capabilities: ModelCapabilities,
+    /// Unix timestamp in seconds when the provider created or last changed
+    /// the model, if it says
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+/// What a model can do, as advertised by its provider, inferred from its id
+/// or configured. Build one from [`ModelCapabilities::text_only`] or
+/// `default()` and the `with_*` methods.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default)]
+#[non_exhaustive]
 pub struct ModelCapabilities {
     pub supports_streaming: bool,
     pub supports_tools: bool,
@@ -53,15 +572,81 @@ pub struct ModelCapabilities {
     pub supports_audio: bool,
     pub max_tokens: Option<u32>,
     pub context_length: Option<u32>,
+    /// Provider-specific capability flags, e.g. `"reasoning": true`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub extras: BTreeMap<String, serde_json::Value>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl ModelCapabilities {
+    /// A model that streams text and does nothing else
+    pub fn text_only() -> Self {
+        Self {
+            supports_streaming: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_streaming(mut self) -> Self {
+        self.supports_streaming = true;
+        self
+    }
+
+    pub fn with_tools(mut self) -> Self {
+        self.supports_tools = true;
+        self
+    }
+
+    pub fn with_vision(mut self) -> Self {
+        self.supports_vision = true;
+        self
+    }
+
+    pub fn with_json(mut self) -> Self {
+        self.supports_json = true;
+        self
+    }
+
+    pub fn with_audio(mut self) -> Self {
+        self.supports_audio = true;
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_context_length(mut self, context_length: u32) -> Self {
+        self.context_length = Some(context_length);
+        self
+    }
+
+    /// Set a provider-specific capability flag
+    pub fn with_extra(mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.extras.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn extra(&self, key: &str) -> Option<&serde_json::Value> {
+        self.extras.get(key)
+    }
+}
+
+/// Capabilities and modalities an adapter inferred for a model id
+pub(crate) struct InferredCapabilities {
+    pub capabilities: ModelCapabilities,
+    pub modalities: Vec<Modality>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum Modality {
     Text,
     Vision,
     AudioIn,
     AudioOut,
     Embeddings,
+    /// Generates images (`/images/generations`)
+    ImageOut,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -86,6 +671,127 @@ pub struct Sampling {
     pub logit_bias: Option<std::collections::HashMap<String, f32>>,
     pub logprobs: Option<bool>,
     pub top_logprobs: Option<u32>,
+    /// Drop tokens below this fraction of the top token's probability.
+    /// This and the fields below are only honoured by local backends
+    /// (Ollama, llama.cpp, vLLM) and ignored for hosted providers.
+    pub min_p: Option<f32>,
+    /// Locally typical sampling
+    pub typical_p: Option<f32>,
+    /// Penalty for repeating tokens from the last `repeat_last_n`
+    pub repeat_penalty: Option<f32>,
+    /// Tokens looked back on for `repeat_penalty`; 0 disables, -1 means the context size
+    pub repeat_last_n: Option<i32>,
+    /// Mirostat version: 0 off, 1 or 2
+    pub mirostat: Option<u8>,
+    /// Target entropy for Mirostat
+    pub mirostat_tau: Option<f32>,
+    /// Mirostat learning rate
+    pub mirostat_eta: Option<f32>,
+}
+
+impl Sampling {
+    /// Body field names and values of the local-backend fields that are set,
+    /// named as llama.cpp and Ollama take them
+    pub fn local_fields(&self) -> Vec<(&'static str, serde_json::Value)> {
+        // Through the shortest decimal form, so 0.05 isn't sent as 0.0500000007
+        let float = |value: Option<f32>| value.and_then(|v| v.to_string().parse::<f64>().ok()).map(serde_json::Value::from);
+        [
+            ("min_p", float(self.min_p)),
+            ("typical_p", float(self.typical_p)),
+            ("repeat_penalty", float(self.repeat_penalty)),
+            ("repeat_last_n", self.repeat_last_n.map(|v| serde_json::json!(v))),
+            ("mirostat", self.mirostat.map(|v| serde_json::json!(v))),
+            ("mirostat_tau", float(self.mirostat_tau)),
+            ("mirostat_eta", float(self.mirostat_eta)),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+        .collect()
+    }
+
+    /// Unset the local-backend fields
+    pub fn clear_local_fields(&mut self) {
+        self.min_p = None;
+        self.typical_p = None;
+        self.repeat_penalty = None;
+        self.repeat_last_n = None;
+        self.mirostat = None;
+        self.mirostat_tau = None;
+        self.mirostat_eta = None;
+    }
+}
+
+/// How much a reasoning model should think before answering
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReasoningEffort::Minimal => "minimal",
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
+    }
+
+    /// Parse the lowercase wire name, e.g. `"minimal"`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "minimal" => Some(ReasoningEffort::Minimal),
+            "low" => Some(ReasoningEffort::Low),
+            "medium" => Some(ReasoningEffort::Medium),
+            "high" => Some(ReasoningEffort::High),
+            _ => None,
+        }
+    }
+}
+
+/// Reasoning preferences, forwarded to providers that support them
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+pub struct ReasoningConfig {
+    pub effort: Option<ReasoningEffort>,
+    /// Reasoning summary detail (`auto`, `concise` or `detailed`); only the
+    /// Responses API uses it
+    pub summary: Option<String>,
+}
+
+/// Anthropic `thinking.budget_tokens` granted for each reasoning effort
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ThinkingBudgets {
+    pub minimal: u32,
+    pub low: u32,
+    pub medium: u32,
+    pub high: u32,
+}
+
+impl Default for ThinkingBudgets {
+    fn default() -> Self {
+        // Anthropic rejects budgets below 1024 tokens
+        Self {
+            minimal: 1024,
+            low: 4096,
+            medium: 10_000,
+            high: 32_000,
+        }
+    }
+}
+
+impl ThinkingBudgets {
+    pub fn budget_for(&self, effort: ReasoningEffort) -> u32 {
+        match effort {
+            ReasoningEffort::Minimal => self.minimal,
+            ReasoningEffort::Low => self.low,
+            ReasoningEffort::Medium => self.medium,
+            ReasoningEffort::High => self.high,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -127,6 +833,28 @@ pub enum ContentPart {
         filename: Option<String>,
         file_data: Option<String>,
     },
+    /// A tool invocation requested by the assistant (in `Role::Assistant` messages).
+    /// `arguments` holds the raw string when it isn't valid JSON.
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: serde_json::Value,
+    },
+    /// The output of a tool call (in `Role::Tool` messages)
+    ToolResult {
+        call_id: String,
+        content: String,
+    },
+}
+
+impl ContentPart {
+    /// Tool call arguments as the JSON string providers expect
+    pub fn arguments_string(arguments: &serde_json::Value) -> String {
+        match arguments {
+            serde_json::Value::String(raw) => raw.clone(),
+            other => other.to_string(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -204,6 +932,97 @@ pub struct ChatRequestIR {
     pub request_timeout: Option<Duration>,
     pub cache_key: Option<String>,
     pub safety_identifier: Option<String>,
+    /// Provider-specific body fields merged verbatim into the outbound request,
+    /// taking precedence over the endpoint's static `extensions`.
+    #[serde(default)]
+    pub provider_extensions: serde_json::Map<String, serde_json::Value>,
+    /// Body fields the client sent that the skin doesn't model (e.g. the
+    /// OpenAI SDK's `extra_body`), passed on to OpenAI-compatible providers.
+    /// Unlike `provider_extensions`, they never replace a field the typed
+    /// request already sets.
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra_body: serde_json::Map<String, serde_json::Value>,
+    /// Reasoning effort and summary for reasoning models
+    #[serde(default)]
+    pub reasoning: Option<ReasoningConfig>,
+    /// Ask the provider to keep the completion (e.g. for OpenAI's dashboard and evals)
+    #[serde(default)]
+    pub store: Option<bool>,
+    /// Run the request detached from its caller (see [`crate::background`]);
+    /// OpenAI Responses providers run it in their own background mode
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub background: bool,
+    /// Whether the engine may keep the request and its reply
+    #[serde(default)]
+    pub privacy: PrivacyMode,
+    /// Tags forwarded as the provider's `metadata` field, unlike `metadata`
+    /// which stays inside the engine. See [`validate_request_metadata`].
+    #[serde(default)]
+    pub request_metadata: HashMap<String, String>,
+    /// Endpoint fields replaced for this request only, e.g. a user's own API
+    /// key; merged into `model.provider` by the router once the endpoint is
+    /// chosen. Never serialized, so never audited or stored.
+    #[serde(skip)]
+    pub endpoint_overrides: Option<EndpointOverrides>,
+}
+
+/// Per-request replacements for [`ProviderEndpoint`] fields (see
+/// [`ChatRequestIR::endpoint_overrides`]). Formats and serializes with its
+/// values redacted.
+#[derive(Clone, Default, PartialEq, Serialize)]
+pub struct EndpointOverrides {
+    /// Sent instead of the endpoint's `api_key` or `auth` token
+    pub api_key: Option<SecretString>,
+    pub base_url: Option<String>,
+    /// Sent instead of the endpoint's `organization`
+    pub organization: Option<String>,
+    /// Sent instead of the endpoint's `project`
+    pub project: Option<String>,
+    /// Set over the endpoint's `extra_headers`; its `header_providers` still
+    /// apply over them
+    #[serde(serialize_with = "secret::serialize_headers")]
+    pub extra_headers: BTreeMap<String, String>,
+}
+
+impl std::fmt::Debug for EndpointOverrides {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EndpointOverrides")
+            .field("api_key", &self.api_key)
+            .field("base_url", &self.base_url.as_deref().map(secret::redact_url_password))
+            .field("organization", &self.organization)
+            .field("project", &self.project)
+            .field("extra_headers", &secret::RedactedHeaders(&self.extra_headers))
+            .finish()
+    }
+}
+
+impl EndpointOverrides {
+    /// Whether these swap the endpoint's key, URL or headers, rather than
+    /// only naming the organization and project billed
+    pub fn replaces_endpoint(&self) -> bool {
+        self.api_key.is_some() || self.base_url.is_some() || !self.extra_headers.is_empty()
+    }
+
+    /// Replace `endpoint`'s fields with the ones set here
+    pub fn apply(&self, endpoint: &mut ProviderEndpoint) {
+        if let Some(api_key) = &self.api_key {
+            endpoint.api_key = Some(api_key.clone());
+            endpoint.auth = None;
+        }
+        if let Some(base_url) = &self.base_url {
+            endpoint.base_url = base_url.clone();
+        }
+        if let Some(organization) = &self.organization {
+            endpoint.organization = Some(organization.clone());
+        }
+        if let Some(project) = &self.project {
+            endpoint.project = Some(project.clone());
+        }
+        for (name, value) in &self.extra_headers {
+            endpoint.extra_headers.retain(|existing, _| !existing.eq_ignore_ascii_case(name));
+            endpoint.extra_headers.insert(name.clone(), value.clone());
+        }
+    }
 }
 
 impl Default for ChatRequestIR {
@@ -211,13 +1030,7 @@ impl Default for ChatRequestIR {
         Self {
             model: ModelRef {
                 alias: String::new(),
-                provider: ProviderEndpoint {
-                    kind: ProviderKind::OpenAI,
-                    base_url: String::new(),
-                    api_key: None,
-                    extra_headers: BTreeMap::new(),
-                    timeout: None,
-                },
+                provider: ProviderEndpoint::default(),
                 model_id: String::new(),
                 modalities: vec![Modality::Text],
             },
@@ -234,6 +1047,214 @@ impl Default for ChatRequestIR {
             request_timeout: None,
             cache_key: None,
             safety_identifier: None,
+            provider_extensions: serde_json::Map::new(),
+            extra_body: serde_json::Map::new(),
+            reasoning: None,
+            store: None,
+            privacy: PrivacyMode::Default,
+            request_metadata: HashMap::new(),
+            background: false,
+            endpoint_overrides: None,
         }
     }
 }
+
+/// How much of a request the engine may keep
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivacyMode {
+    #[default]
+    Default,
+    /// Nothing is persisted: the conversation store and resumable stream
+    /// buffers skip the request, audit records keep only its metadata, and
+    /// the endpoint's `privacy_headers` are sent
+    NoStore,
+}
+
+impl PrivacyMode {
+    pub fn is_no_store(self) -> bool {
+        self == PrivacyMode::NoStore
+    }
+}
+
+/// Most key-value pairs OpenAI accepts in request `metadata`
+pub const MAX_REQUEST_METADATA_PAIRS: usize = 16;
+/// Longest `metadata` key OpenAI accepts, in characters
+pub const MAX_REQUEST_METADATA_KEY_CHARS: usize = 64;
+/// Longest `metadata` value OpenAI accepts, in characters
+pub const MAX_REQUEST_METADATA_VALUE_CHARS: usize = 512;
+
+/// Check request metadata against OpenAI's limits, so oversized tags fail
+/// here with a clear message rather than as an upstream 400
+pub fn validate_request_metadata(metadata: &HashMap<String, String>) -> Result<(), String> {
+    if metadata.len() > MAX_REQUEST_METADATA_PAIRS {
+        return Err(format!(
+            "metadata has {} keys; at most {} are allowed",
+            metadata.len(),
+            MAX_REQUEST_METADATA_PAIRS
+        ));
+    }
+    for (key, value) in metadata {
+        if key.chars().count() > MAX_REQUEST_METADATA_KEY_CHARS {
+            return Err(format!(
+                "metadata key '{}' is longer than {} characters",
+                key, MAX_REQUEST_METADATA_KEY_CHARS
+            ));
+        }
+        if value.chars().count() > MAX_REQUEST_METADATA_VALUE_CHARS {
+            return Err(format!(
+                "metadata value for '{}' is longer than {} characters",
+                key, MAX_REQUEST_METADATA_VALUE_CHARS
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Encoding of generated images
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageResponseFormat {
+    Url,
+    B64Json,
+}
+
+/// Provider-agnostic image generation request
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageRequestIR {
+    pub model: ModelRef,
+    pub prompt: String,
+    pub n: Option<u32>,
+    pub size: Option<String>,
+    pub quality: Option<String>,
+    /// `None` leaves the encoding to the provider
+    pub response_format: Option<ImageResponseFormat>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl ImageRequestIR {
+    pub fn new(model: ModelRef, prompt: impl Into<String>) -> Self {
+        Self {
+            model,
+            prompt: prompt.into(),
+            n: None,
+            size: None,
+            quality: None,
+            response_format: None,
+            metadata: BTreeMap::new(),
+        }
+    }
+}
+
+/// A generated image, either inline or hosted by the provider
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct GeneratedImage {
+    pub b64_json: Option<String>,
+    pub url: Option<String>,
+    pub revised_prompt: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageResponseIR {
+    /// Unix timestamp (seconds)
+    pub created: u64,
+    pub images: Vec<GeneratedImage>,
+}
+
+/// Voice for speech synthesis
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum SpeechVoice {
+    /// A built-in voice name, e.g. "alloy"
+    Preset(String),
+    /// A custom or provider-specific voice id
+    Custom(String),
+}
+
+/// Provider-agnostic text-to-speech request
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpeechRequestIR {
+    pub model: ModelRef,
+    pub input: String,
+    pub voice: SpeechVoice,
+    /// Audio encoding (mp3, opus, aac, flac, wav, pcm); `None` leaves it to the provider
+    pub response_format: Option<String>,
+    pub speed: Option<f32>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl SpeechRequestIR {
+    pub fn new(model: ModelRef, input: impl Into<String>, voice: SpeechVoice) -> Self {
+        Self {
+            model,
+            input: input.into(),
+            voice,
+            response_format: None,
+            speed: None,
+            metadata: BTreeMap::new(),
+        }
+    }
+}
+
+/// Audio bytes as they arrive from the provider
+pub type AudioStream = std::pin::Pin<
+    Box<dyn futures_util::Stream<Item = Result<bytes::Bytes, crate::adapter::AdapterError>> + Send>,
+>;
+
+/// Synthesized speech, streamed rather than buffered
+pub struct SpeechResponseIR {
+    pub content_type: String,
+    pub audio: AudioStream,
+}
+
+impl std::fmt::Debug for SpeechResponseIR {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpeechResponseIR")
+            .field("content_type", &self.content_type)
+            .finish_non_exhaustive()
+    }
+}
+
+/// MIME type for a speech `response_format` (mp3 when unspecified)
+pub fn speech_content_type(format: Option<&str>) -> &'static str {
+    match format.unwrap_or("mp3") {
+        "opus" => "audio/opus",
+        "aac" => "audio/aac",
+        "flac" => "audio/flac",
+        "wav" => "audio/wav",
+        "pcm" => "audio/pcm",
+        _ => "audio/mpeg",
+    }
+}
+
+impl ChatRequestIR {
+    /// Add provider-specific body fields (see [`ChatRequestIR::provider_extensions`]).
+    pub fn with_provider_extensions<I>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = (String, serde_json::Value)>,
+    {
+        self.provider_extensions.extend(extensions);
+        self
+    }
+
+    /// Constrain the output on vLLM endpoints, replacing any earlier guided
+    /// decoding (see [`providers::vllm`])
+    pub fn with_guided_decoding(mut self, guided: providers::vllm::GuidedDecoding) -> Self {
+        for field in providers::vllm::VLLM_GUIDED_FIELDS {
+            self.provider_extensions.remove(*field);
+        }
+        self.with_provider_extensions(providers::vllm::VllmExtensions::guided(guided).into_map())
+    }
+
+    /// Generate `best_of` candidates on vLLM endpoints, with beam search
+    /// instead of sampling when `beam_search` is set
+    pub fn with_best_of(self, best_of: u32, beam_search: bool) -> Self {
+        self.with_provider_extensions(
+            providers::vllm::VllmExtensions {
+                best_of: Some(best_of),
+                use_beam_search: beam_search.then_some(true),
+                ..Default::default()
+            }
+            .into_map(),
+        )
+    }
+}
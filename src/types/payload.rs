@@ -0,0 +1,80 @@
+//! Last-resort hooks over provider request and response bodies
+//!
+//! Adapters lag behind provider features. A [`PayloadTransformer`] on a
+//! provider's endpoint gets every JSON request body right before the adapter
+//! sends it, and every non-streamed JSON response body before the adapter
+//! parses it, together with the provider kind and the model id, and may
+//! change them in place. Hooks are synchronous closures. A hook that panics
+//! is logged and its changes are dropped; a hook that changes a body is
+//! logged at debug level.
+
+use super::ProviderKind;
+use serde_json::Value;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+/// A hook over one JSON body: `(provider kind, model id, body)`
+pub type PayloadHook = Arc<dyn Fn(&ProviderKind, &str, &mut Value) + Send + Sync>;
+
+/// Hooks over the JSON bodies exchanged with one provider
+#[derive(Clone, Default)]
+pub struct PayloadTransformer {
+    request: Option<PayloadHook>,
+    response: Option<PayloadHook>,
+}
+
+impl std::fmt::Debug for PayloadTransformer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PayloadTransformer")
+            .field("request", &self.request.is_some())
+            .field("response", &self.response.is_some())
+            .finish()
+    }
+}
+
+impl PayloadTransformer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Change request bodies with `hook` before they are sent
+    pub fn on_request(mut self, hook: impl Fn(&ProviderKind, &str, &mut Value) + Send + Sync + 'static) -> Self {
+        self.request = Some(Arc::new(hook));
+        self
+    }
+
+    /// Change non-streamed response bodies with `hook` before they are parsed
+    pub fn on_response(mut self, hook: impl Fn(&ProviderKind, &str, &mut Value) + Send + Sync + 'static) -> Self {
+        self.response = Some(Arc::new(hook));
+        self
+    }
+
+    /// Run the request hook over `body`
+    pub fn transform_request(&self, kind: &ProviderKind, model: &str, body: &mut Value) {
+        if let Some(hook) = &self.request {
+            apply(hook, "request", kind, model, body);
+        }
+    }
+
+    /// Run the response hook over `body`
+    pub fn transform_response(&self, kind: &ProviderKind, model: &str, body: &mut Value) {
+        if let Some(hook) = &self.response {
+            apply(hook, "response", kind, model, body);
+        }
+    }
+}
+
+/// Run `hook` over a copy of `body`, keeping the copy unless the hook panics
+fn apply(hook: &PayloadHook, direction: &str, kind: &ProviderKind, model: &str, body: &mut Value) {
+    let mut transformed = body.clone();
+    match catch_unwind(AssertUnwindSafe(|| hook(kind, model, &mut transformed))) {
+        Ok(()) if transformed != *body => {
+            tracing::debug!(provider_kind = %kind, model, direction, "Payload transformer modified the body");
+            *body = transformed;
+        }
+        Ok(()) => {}
+        Err(_) => {
+            tracing::error!(provider_kind = %kind, model, direction, "Payload transformer panicked; body left unchanged")
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Anthropic Messages API request and response types
+//!
+//! This module contains the data structures for Anthropic's `/v1/messages`
+//! endpoint, its streaming events and model discovery.
+
+use serde::{Deserialize, Serialize};
+
+/// Messages API request
+#[derive(Debug, Serialize)]
+pub struct AnthropicMessagesRequest {
+    pub model: String,
+    pub messages: Vec<AnthropicMessage>,
+    /// Anthropic requires an explicit output budget on every request
+    pub max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<AnthropicTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<AnthropicToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+    /// Extended thinking; reasoning effort is translated into its budget
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<AnthropicThinking>,
+    pub stream: bool,
+}
+
+/// Extended thinking configuration
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicThinking {
+    Enabled { budget_tokens: u32 },
+}
+
+/// A single turn; consecutive turns must alternate between user and assistant
+#[derive(Debug, Serialize)]
+pub struct AnthropicMessage {
+    pub role: String,
+    pub content: Vec<AnthropicContentBlock>,
+}
+
+/// Content block of a message, in requests and in complete responses
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicContentBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        source: AnthropicImageSource,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+    /// Block types this crate doesn't handle (e.g. `thinking`)
+    #[serde(other)]
+    Unknown,
+}
+
+/// Where an image block's data comes from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicImageSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+}
+
+/// Tool definition
+#[derive(Debug, Serialize)]
+pub struct AnthropicTool {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub input_schema: serde_json::Value,
+}
+
+/// How the model may use the tools it's given
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicToolChoice {
+    Auto,
+    Any,
+    None,
+    Tool { name: String },
+}
+
+/// Complete (non-streaming) Messages API response
+#[derive(Debug, Deserialize)]
+pub struct AnthropicMessagesResponse {
+    pub id: String,
+    pub model: String,
+    pub content: Vec<AnthropicContentBlock>,
+    pub stop_reason: Option<String>,
+    pub usage: AnthropicUsage,
+}
+
+/// Token usage; streaming events carry only the counts known so far
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AnthropicUsage {
+    #[serde(default)]
+    pub input_tokens: u32,
+    #[serde(default)]
+    pub output_tokens: u32,
+}
+
+/// One `data:` payload of a streaming response
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicStreamEvent {
+    MessageStart {
+        message: AnthropicStreamMessage,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: AnthropicContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: AnthropicBlockDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        delta: AnthropicMessageDelta,
+        usage: AnthropicUsage,
+    },
+    MessageStop,
+    Ping,
+    Error {
+        error: AnthropicError,
+    },
+}
+
+/// The message skeleton sent by `message_start`
+#[derive(Debug, Deserialize)]
+pub struct AnthropicStreamMessage {
+    pub id: String,
+    #[serde(default)]
+    pub usage: AnthropicUsage,
+}
+
+/// Incremental content of a block
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicBlockDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// Message-level changes sent near the end of a stream
+#[derive(Debug, Deserialize)]
+pub struct AnthropicMessageDelta {
+    pub stop_reason: Option<String>,
+}
+
+/// Error body, both for HTTP errors and for `error` stream events
+#[derive(Debug, Deserialize)]
+pub struct AnthropicError {
+    pub r#type: String,
+    pub message: String,
+}
+
+/// Top-level wrapper of HTTP error responses
+#[derive(Debug, Deserialize)]
+pub struct AnthropicErrorResponse {
+    pub error: AnthropicError,
+}
+
+/// Model listing from `/v1/models`
+#[derive(Debug, Deserialize)]
+pub struct AnthropicModelsResponse {
+    pub data: Vec<AnthropicModel>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnthropicModel {
+    pub id: String,
+    pub display_name: Option<String>,
+    /// RFC 3339 release date
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
@@ -0,0 +1,302 @@
+//! Cohere Chat API (v2) request and response types
+//!
+//! This module contains the data structures for Cohere's `/v2/chat`
+//! endpoint, its streaming events and the `/v1/models` listing.
+
+use serde::{Deserialize, Serialize};
+
+/// Chat API v2 request
+#[derive(Debug, Serialize)]
+pub struct CohereChatRequest {
+    pub model: String,
+    pub messages: Vec<CohereMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<CohereTool>,
+    /// `REQUIRED` or `NONE`; left out for the default, where the model decides
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<CohereResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Top-p
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p: Option<f32>,
+    /// Top-k
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    pub stream: bool,
+}
+
+/// One message of the conversation
+#[derive(Debug, Serialize)]
+#[serde(tag = "role", rename_all = "snake_case")]
+pub enum CohereMessage {
+    System {
+        content: String,
+    },
+    User {
+        content: Vec<CohereContent>,
+    },
+    Assistant {
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        content: Vec<CohereContent>,
+        /// The model's plan ahead of its tool calls
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tool_plan: Option<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        tool_calls: Vec<CohereToolCall>,
+    },
+    Tool {
+        tool_call_id: String,
+        content: String,
+    },
+}
+
+/// Content item of a message, in requests and in complete responses
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CohereContent {
+    Text {
+        text: String,
+    },
+    ImageUrl {
+        image_url: CohereImageUrl,
+    },
+    /// Content types this crate doesn't handle (e.g. `thinking`)
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CohereImageUrl {
+    pub url: String,
+}
+
+/// Tool definition
+#[derive(Debug, Serialize)]
+pub struct CohereTool {
+    pub r#type: String,
+    pub function: CohereFunction,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CohereFunction {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub parameters: serde_json::Value,
+}
+
+/// A tool call, in assistant messages and in responses
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CohereToolCall {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default = "function_type")]
+    pub r#type: String,
+    pub function: CohereFunctionCall,
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CohereFunctionCall {
+    #[serde(default)]
+    pub name: String,
+    /// The arguments as a JSON string
+    #[serde(default)]
+    pub arguments: String,
+}
+
+/// JSON output, optionally following a schema
+#[derive(Debug, Serialize)]
+pub struct CohereResponseFormat {
+    pub r#type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<serde_json::Value>,
+}
+
+/// Complete (non-streaming) Chat API response
+#[derive(Debug, Deserialize)]
+pub struct CohereChatResponse {
+    pub id: String,
+    pub finish_reason: Option<String>,
+    pub message: CohereResponseMessage,
+    #[serde(default)]
+    pub usage: Option<CohereUsage>,
+}
+
+/// The reply of a complete response
+#[derive(Debug, Default, Deserialize)]
+pub struct CohereResponseMessage {
+    #[serde(default)]
+    pub content: Vec<CohereContent>,
+    pub tool_plan: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<CohereToolCall>,
+    #[serde(default)]
+    pub citations: Vec<CohereCitation>,
+}
+
+/// A span of the reply and the documents or tool results backing it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CohereCitation {
+    pub start: Option<usize>,
+    pub end: Option<usize>,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub sources: Vec<serde_json::Value>,
+}
+
+/// Billed and processed token counts
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CohereUsage {
+    pub billed_units: Option<CohereTokens>,
+    pub tokens: Option<CohereTokens>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CohereTokens {
+    #[serde(default)]
+    pub input_tokens: f64,
+    #[serde(default)]
+    pub output_tokens: f64,
+}
+
+impl CohereUsage {
+    /// Input and output tokens, preferring the processed counts over the billed ones
+    pub fn counts(&self) -> (u32, u32) {
+        self.tokens
+            .as_ref()
+            .or(self.billed_units.as_ref())
+            .map(|tokens| (tokens.input_tokens as u32, tokens.output_tokens as u32))
+            .unwrap_or_default()
+    }
+}
+
+/// One `data:` payload of a streaming response
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum CohereStreamEvent {
+    MessageStart {
+        id: Option<String>,
+    },
+    ContentStart {
+        index: usize,
+        delta: CohereStreamDelta,
+    },
+    ContentDelta {
+        index: usize,
+        delta: CohereStreamDelta,
+    },
+    ContentEnd {
+        index: usize,
+    },
+    ToolPlanDelta {
+        delta: CohereStreamDelta,
+    },
+    ToolCallStart {
+        index: usize,
+        delta: CohereStreamDelta,
+    },
+    ToolCallDelta {
+        index: usize,
+        delta: CohereStreamDelta,
+    },
+    ToolCallEnd {
+        index: usize,
+    },
+    CitationStart {
+        index: usize,
+        delta: CohereStreamDelta,
+    },
+    CitationEnd {
+        index: usize,
+    },
+    MessageEnd {
+        delta: CohereMessageEndDelta,
+    },
+    /// `debug` and event types added later
+    #[serde(other)]
+    Unknown,
+}
+
+/// The `delta` of a streaming event; each event type fills one field of
+/// its `message`
+#[derive(Debug, Default, Deserialize)]
+pub struct CohereStreamDelta {
+    #[serde(default)]
+    pub message: CohereDeltaMessage,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CohereDeltaMessage {
+    pub content: Option<CohereDeltaContent>,
+    pub tool_plan: Option<String>,
+    /// A single call in `tool-call-start` and `tool-call-delta` events
+    pub tool_calls: Option<CohereDeltaToolCall>,
+    /// A single citation in `citation-start` events
+    pub citations: Option<CohereCitation>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CohereDeltaContent {
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CohereDeltaToolCall {
+    pub id: Option<String>,
+    #[serde(default)]
+    pub function: CohereFunctionCall,
+}
+
+/// Why the reply ended, and its usage
+#[derive(Debug, Default, Deserialize)]
+pub struct CohereMessageEndDelta {
+    pub finish_reason: Option<String>,
+    pub error: Option<String>,
+    pub usage: Option<CohereUsage>,
+}
+
+/// Error body of HTTP errors
+#[derive(Debug, Deserialize)]
+pub struct CohereErrorResponse {
+    pub message: String,
+}
+
+/// Model listing from `/v1/models`
+#[derive(Debug, Deserialize)]
+pub struct CohereModelsResponse {
+    #[serde(default)]
+    pub models: Vec<CohereModel>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CohereModel {
+    pub name: String,
+    /// APIs the model serves, e.g. `chat` or `embed`
+    #[serde(default)]
+    pub endpoints: Vec<String>,
+    pub context_length: Option<u32>,
+    /// e.g. `tools`, `json_mode`, `vision`
+    #[serde(default)]
+    pub features: Option<Vec<String>>,
+    #[serde(default)]
+    pub supports_vision: Option<bool>,
+}
@@ -0,0 +1,279 @@
+//! Mistral La Plateforme request and response types
+//!
+//! Mistral's chat completions API follows the OpenAI schema closely but has
+//! fields of its own (`safe_prompt`, `random_seed`), a `tool_choice` of `any`,
+//! tool call ids of nine alphanumeric characters, and rejects several OpenAI
+//! parameters outright. These types cover `/v1/chat/completions` and the
+//! `/v1/models` listing with its capability metadata.
+
+use serde::{Deserialize, Serialize};
+
+/// Chat completions request
+#[derive(Debug, Serialize)]
+pub struct MistralChatRequest {
+    pub model: String,
+    pub messages: Vec<MistralMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<MistralTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<MistralToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<MistralResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// Mistral's name for `seed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub random_seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    /// Prepend Mistral's safety system prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safe_prompt: Option<bool>,
+    pub stream: bool,
+}
+
+/// One message of the conversation
+#[derive(Debug, Serialize)]
+#[serde(tag = "role", rename_all = "snake_case")]
+pub enum MistralMessage {
+    System {
+        content: String,
+    },
+    User {
+        content: Vec<MistralContentChunk>,
+    },
+    Assistant {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        tool_calls: Vec<MistralToolCall>,
+    },
+    Tool {
+        tool_call_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        content: String,
+    },
+}
+
+/// Content chunk of a message
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MistralContentChunk {
+    Text {
+        text: String,
+    },
+    ImageUrl {
+        image_url: String,
+    },
+    /// Chunk types this crate doesn't handle (e.g. `thinking`, `reference`)
+    #[serde(other)]
+    Unknown,
+}
+
+/// Message content in responses: usually a string, or chunks from
+/// reasoning models
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum MistralContent {
+    Text(String),
+    Chunks(Vec<MistralContentChunk>),
+}
+
+impl MistralContent {
+    /// The text of the content, without reasoning or other chunks
+    pub fn text(&self) -> String {
+        match self {
+            MistralContent::Text(text) => text.clone(),
+            MistralContent::Chunks(chunks) => chunks
+                .iter()
+                .filter_map(|chunk| match chunk {
+                    MistralContentChunk::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Tool definition
+#[derive(Debug, Serialize)]
+pub struct MistralTool {
+    pub r#type: String,
+    pub function: MistralFunction,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MistralFunction {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub parameters: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// `auto`, `none`, `any` or `required`, or a named function
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum MistralToolChoice {
+    Mode(String),
+    Function {
+        r#type: String,
+        function: MistralNamedFunction,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct MistralNamedFunction {
+    pub name: String,
+}
+
+/// A tool call, in assistant messages, responses and stream deltas; streams
+/// send each call whole in one delta
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MistralToolCall {
+    /// Nine characters from `[a-zA-Z0-9]`
+    #[serde(default)]
+    pub id: String,
+    #[serde(default = "function_type")]
+    pub r#type: String,
+    pub function: MistralFunctionCall,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MistralFunctionCall {
+    #[serde(default)]
+    pub name: String,
+    /// A JSON string in requests; responses may carry the parsed object
+    #[serde(default)]
+    pub arguments: serde_json::Value,
+}
+
+/// JSON mode, or output following a schema
+#[derive(Debug, Serialize)]
+pub struct MistralResponseFormat {
+    pub r#type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<MistralJsonSchema>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MistralJsonSchema {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub schema: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// Complete response, or one chunk of a stream
+#[derive(Debug, Deserialize)]
+pub struct MistralChatResponse {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub choices: Vec<MistralChoice>,
+    pub usage: Option<MistralUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MistralChoice {
+    #[serde(default)]
+    pub index: u32,
+    /// Set in complete responses
+    pub message: Option<MistralResponseMessage>,
+    /// Set in stream chunks
+    pub delta: Option<MistralResponseMessage>,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct MistralResponseMessage {
+    pub content: Option<MistralContent>,
+    pub tool_calls: Option<Vec<MistralToolCall>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MistralUsage {
+    #[serde(default)]
+    pub prompt_tokens: u32,
+    #[serde(default)]
+    pub completion_tokens: u32,
+}
+
+/// Error body of HTTP errors; `message` is a string, or the validation
+/// errors of a 422 response
+#[derive(Debug, Deserialize)]
+pub struct MistralErrorResponse {
+    pub message: serde_json::Value,
+    pub r#type: Option<String>,
+    pub code: Option<serde_json::Value>,
+}
+
+/// Model listing from `/v1/models`
+#[derive(Debug, Deserialize)]
+pub struct MistralModelsResponse {
+    #[serde(default)]
+    pub data: Vec<MistralModel>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MistralModel {
+    pub id: String,
+    #[serde(default)]
+    pub created: Option<u64>,
+    #[serde(default)]
+    pub capabilities: MistralModelCapabilities,
+    pub max_context_length: Option<u32>,
+    /// Other ids serving the same model, e.g. `mistral-large-latest`
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// What a model supports, as Mistral reports it
+#[derive(Debug, Default, Deserialize)]
+pub struct MistralModelCapabilities {
+    #[serde(default)]
+    pub completion_chat: bool,
+    #[serde(default)]
+    pub function_calling: bool,
+    #[serde(default)]
+    pub vision: bool,
+}
+
+/// Mistral-specific body fields
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct MistralExtensions {
+    /// Prepend Mistral's safety system prompt to the conversation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safe_prompt: Option<bool>,
+}
+
+impl MistralExtensions {
+    /// Convert into the map form used by `ChatRequestIR::provider_extensions`
+    /// and `ProviderEndpoint::extensions`.
+    pub fn into_map(self) -> serde_json::Map<String, serde_json::Value> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        }
+    }
+}
@@ -4,33 +4,56 @@
 //! according to the AGENTS.md guidelines. Each provider has its own file
 //! containing request/response models and shared enums.
 
+pub mod anthropic;
+pub mod cohere;
+pub mod mistral;
 pub mod ollama;
 pub mod openai_compatible;
 pub mod openai;
-// Re-export OpenAI Compatible types (these are the primary OpenAI API types)
+pub mod openrouter;
+pub mod tgi;
+pub mod vllm;
+// Model listings, usage details and errors of OpenAI-compatible APIs
 pub use openai_compatible::{
-    OpenAIChatRequest as OpenAICompatChatRequest,
-    OpenAIChatResponse as OpenAICompatChatResponse,
-    OpenAIMessage as OpenAICompatMessage,
-    OpenAIChoice as OpenAICompatChoice,
-    OpenAIResponseMessage as OpenAICompatResponseMessage,
-    OpenAIToolCall as OpenAICompatToolCall,
-    OpenAIFunctionCall as OpenAICompatFunctionCall,
-    OpenAIFunctionCallDelta as OpenAICompatFunctionCallDelta,
-    OpenAIUsage as OpenAICompatUsage,
     PromptTokensDetails,
     CompletionTokensDetails,
-    OpenAIModel as OpenAICompatModel,
-    OpenAIModelsResponse as OpenAICompatModelsResponse,
     OpenAIErrorResponse,
-    OpenAITool as OpenAICompatTool,
-    OpenAIFunction as OpenAICompatFunction,
-    OpenAIResponseDelta as OpenAICompatResponseDelta,
-    OpenAIToolCallDelta as OpenAICompatToolCallDelta,
     OpenAIError
 };
 
-// Re-export OpenAI Chat Completions API types (from skins/openai.rs)
+// Deprecated names for the Chat Completions types of the former duplicate set
+#[deprecated(note = "use `OpenAIChatRequest`; this alias will be removed in the next release")]
+pub type OpenAICompatChatRequest = openai::OpenAIChatRequest;
+#[deprecated(note = "use `OpenAIChatResponse`; this alias will be removed in the next release")]
+pub type OpenAICompatChatResponse = openai::OpenAIChatResponse;
+#[deprecated(note = "use `OpenAIMessage`; this alias will be removed in the next release")]
+pub type OpenAICompatMessage = openai::OpenAIMessage;
+#[deprecated(note = "use `OpenAIChoice`; this alias will be removed in the next release")]
+pub type OpenAICompatChoice = openai::OpenAIChoice;
+#[deprecated(note = "use `OpenAIResponseMessage`; this alias will be removed in the next release")]
+pub type OpenAICompatResponseMessage = openai::OpenAIResponseMessage;
+#[deprecated(note = "use `OpenAIToolCall`; this alias will be removed in the next release")]
+pub type OpenAICompatToolCall = openai::OpenAIToolCall;
+#[deprecated(note = "use `OpenAIFunctionCall`; this alias will be removed in the next release")]
+pub type OpenAICompatFunctionCall = openai::OpenAIFunctionCall;
+#[deprecated(note = "use `OpenAIFunctionCallDelta`; this alias will be removed in the next release")]
+pub type OpenAICompatFunctionCallDelta = openai::OpenAIFunctionCallDelta;
+#[deprecated(note = "use `OpenAIUsage`; this alias will be removed in the next release")]
+pub type OpenAICompatUsage = openai::OpenAIUsage;
+#[deprecated(note = "use `OpenAIModel`; this alias will be removed in the next release")]
+pub type OpenAICompatModel = openai_compatible::OpenAIModel;
+#[deprecated(note = "use `OpenAIModelsResponse`; this alias will be removed in the next release")]
+pub type OpenAICompatModelsResponse = openai_compatible::OpenAIModelsResponse;
+#[deprecated(note = "use `OpenAIToolSpec`; this alias will be removed in the next release")]
+pub type OpenAICompatTool = openai::OpenAIToolSpec;
+#[deprecated(note = "use `OpenAIFunctionDef`; this alias will be removed in the next release")]
+pub type OpenAICompatFunction = openai::OpenAIFunctionDef;
+#[deprecated(note = "use `OpenAIDelta`; this alias will be removed in the next release")]
+pub type OpenAICompatResponseDelta = openai::OpenAIDelta;
+#[deprecated(note = "use `OpenAIToolCallDelta`; this alias will be removed in the next release")]
+pub type OpenAICompatToolCallDelta = openai::OpenAIToolCallDelta;
+
+// Re-export OpenAI Chat Completions API types, shared by the official and compatible APIs
 pub use openai::{
     OpenAIChatRequest, OpenAIChatResponse, OpenAIMessage, OpenAIChoice,
     OpenAIResponseMessage, OpenAIToolCall, OpenAIFunctionCall, OpenAIFunctionCallDelta,
@@ -44,7 +67,8 @@ pub use openai::{
     OpenAIImageUrl, OpenAIFileContent, OpenAIFunctionDef, OpenAINamedFunction,
     OpenAIJsonSchema, OpenAIVoice, OpenAIAudioFormat, OpenAIAudioContent,
     OpenAIApproximateLocation, OpenAIStreamChunk, OpenAIStreamChoice, OpenAIDelta,
-    OpenAIToolCallDelta
+    OpenAIToolCallDelta, OpenAIImageRequest, OpenAIImageResponse, OpenAIImageData,
+    OpenAISpeechRequest, OpenAISpeechVoice
 };
 
 // Re-export shared types from openai_compatible for openai module
@@ -61,3 +85,12 @@ pub use openai::{
     OpenAIStreamingContent, OpenAIOutputItem, OpenAIOutputContent, OpenAIToolCallPayload,
     OpenAIFunctionCallPayload, OpenAIInputMessageItem, OpenAIInputContentPart
 };
+
+// Re-export OpenRouter extension helpers
+pub use openrouter::{OpenRouterExtensions, OpenRouterProviderPreferences};
+
+// Re-export Mistral extension helpers
+pub use mistral::MistralExtensions;
+
+// Re-export vLLM extension helpers
+pub use vllm::{GuidedDecoding, VllmExtensions, VLLM_EXTENSION_FIELDS, VLLM_GUIDED_FIELDS};
@@ -12,6 +12,9 @@ pub struct OllamaChatRequest {
     pub messages: Vec<OllamaMessage>,
     pub stream: bool,
     pub options: Option<OllamaOptions>,
+    /// `"json"` or a JSON schema constraining the reply
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<serde_json::Value>,
 }
 
 /// A single message in an Ollama conversation
@@ -30,6 +33,20 @@ pub struct OllamaOptions {
     pub top_k: Option<u32>,
     pub num_predict: Option<u32>,
     pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typical_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_last_n: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirostat: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirostat_tau: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirostat_eta: Option<f32>,
 }
 
 /// Ollama chat completion response (streaming)
@@ -37,14 +54,27 @@ pub struct OllamaOptions {
 pub struct OllamaResponse {
     pub model: String,
     pub created_at: String,
+    #[serde(default)]
     pub response: String,
+    /// The generated text on `/api/chat`, which doesn't fill `response`
+    #[serde(default)]
+    pub message: Option<OllamaResponseMessage>,
     pub done: bool,
+    /// Why generation stopped; `"load"` marks a model-loading acknowledgement.
+    pub done_reason: Option<String>,
     pub total_duration: Option<u64>,
     pub load_duration: Option<u64>,
     pub prompt_eval_count: Option<u32>,
     pub eval_count: Option<u32>,
 }
 
+/// Assistant message fragment of an `/api/chat` response
+#[derive(Debug, Deserialize)]
+pub struct OllamaResponseMessage {
+    #[serde(default)]
+    pub content: String,
+}
+
 /// Information about a single Ollama model
 #[derive(Debug, Deserialize)]
 pub struct OllamaModel {
@@ -16,8 +16,10 @@ use super::openai_compatible::{PromptTokensDetails, CompletionTokensDetails};
 ///
 /// This structure mirrors the official OpenAI Chat Completions API specification
 /// and supports all current and legacy parameters for maximum compatibility.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct OpenAIChatRequest {
+    /// May be omitted when the server has a default model
+    #[serde(default)]
     pub model: String,
     pub messages: Vec<OpenAIMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -84,6 +86,10 @@ pub struct OpenAIChatRequest {
     pub prompt_cache_key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub safety_identifier: Option<String>,
+    /// Top-level fields this type doesn't model, such as vendor sampling knobs
+    /// sent through the OpenAI SDK's `extra_body`
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Options for streaming responses
@@ -110,7 +116,7 @@ pub enum OpenAIStop {
 }
 
 /// A single message in the conversation
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct OpenAIMessage {
     pub role: String,
     #[serde(default)]
@@ -232,6 +238,9 @@ pub enum OpenAIVoice {
     Onyx,
     Sage,
     Shimmer,
+    Verse,
+    Marin,
+    Cedar,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -301,13 +310,14 @@ pub struct OpenAINamedFunction {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum OpenAIResponseFormat {
-    Simple {
-        r#type: String, // "text" or "json_object"
-    },
+    // Listed first: untagged `Simple` would also accept a `json_schema` body
     JsonSchema {
         r#type: String, // "json_schema"
         json_schema: OpenAIJsonSchema,
     },
+    Simple {
+        r#type: String, // "text" or "json_object"
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -318,13 +328,13 @@ pub struct OpenAIJsonSchema {
     pub strict: Option<bool>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OpenAIInputMessageItem {
     pub role: Option<String>,
     pub content: Option<Vec<OpenAIInputContentPart>>, // for messages-style input
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OpenAIInputContentPart {
     #[serde(rename = "type")]
     pub kind: String,
@@ -335,7 +345,7 @@ pub struct OpenAIInputContentPart {
 // ---------------------
 // Response Types
 // ---------------------
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct OpenAIChatResponse {
     pub id: String,
     pub object: String,
@@ -347,7 +357,7 @@ pub struct OpenAIChatResponse {
     pub system_fingerprint: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct OpenAIChoice {
     pub index: u32,
     pub message: Option<OpenAIResponseMessage>,
@@ -357,7 +367,7 @@ pub struct OpenAIChoice {
     pub logprobs: Option<serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct OpenAIResponseMessage {
     pub role: String,
     pub content: Option<String>,
@@ -367,14 +377,18 @@ pub struct OpenAIResponseMessage {
     pub annotations: Vec<serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct OpenAIDelta {
     pub role: Option<String>,
     pub content: Option<String>,
     pub tool_calls: Option<Vec<OpenAIToolCallDelta>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct OpenAIUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
@@ -387,7 +401,7 @@ pub struct OpenAIUsage {
 
 
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct OpenAIStreamChunk {
     pub id: String,
     pub object: String,
@@ -396,7 +410,7 @@ pub struct OpenAIStreamChunk {
     pub choices: Vec<OpenAIStreamChoice>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct OpenAIStreamChoice {
     pub index: u32,
     pub delta: OpenAIDelta,
@@ -1023,7 +1037,10 @@ pub enum ResponseOutputContent {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ResponseOutputText {
     pub text: String,
-    pub annotations: Vec<Annotation>,
+    /// Kept as sent so citations pass through unchanged; [`Annotation`] has
+    /// the known shapes
+    #[serde(default)]
+    pub annotations: Vec<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logprobs: Option<Vec<Logprob>>,
 }
@@ -1116,6 +1133,8 @@ pub struct ResponseTextConfig {
 pub enum ResponseFormatTextConfig {
     #[serde(rename = "text")]
     Text,
+    #[serde(rename = "json_object")]
+    JsonObject,
     #[serde(rename = "json_schema")]
     JsonSchema {
         name: String,
@@ -1127,6 +1146,96 @@ pub enum ResponseFormatTextConfig {
     },
 }
 
+// ---------------------
+// OpenAI Images API
+// ---------------------
+
+/// `POST /v1/images/generations` request body
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenAIImageRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<String>,
+    /// "url" or "b64_json"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenAIImageResponse {
+    pub created: u64,
+    pub data: Vec<OpenAIImageData>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct OpenAIImageData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub b64_json: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revised_prompt: Option<String>,
+}
+
+// ---------------------
+// OpenAI Audio API
+// ---------------------
+
+/// `POST /v1/audio/speech` request body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAISpeechRequest {
+    pub model: String,
+    pub input: String,
+    pub voice: OpenAISpeechVoice,
+    /// mp3, opus, aac, flac, wav or pcm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+}
+
+/// A built-in voice, or `{"id": ...}` for voices the enum doesn't know
+/// (custom or provider-specific voices), which are passed through unchecked
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum OpenAISpeechVoice {
+    Named(OpenAIVoice),
+    Custom { id: String },
+}
+
+impl<'de> Deserialize<'de> for OpenAISpeechVoice {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Name(String),
+            Custom { id: String },
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Custom { id } => Ok(OpenAISpeechVoice::Custom { id }),
+            Raw::Name(name) => serde_json::from_value(serde_json::Value::String(name.clone()))
+                .map(OpenAISpeechVoice::Named)
+                .map_err(|_| {
+                    serde::de::Error::custom(format!(
+                        "unsupported voice '{}'; pass {{\"id\": \"{}\"}} to use a provider-specific voice",
+                        name, name
+                    ))
+                }),
+        }
+    }
+}
+
 // ---------------------
 // OpenAI Responses API Payload Types
 // ---------------------
@@ -1312,10 +1421,16 @@ pub struct StreamOptions {
     pub include_obfuscation: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct Reasoning {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub enabled: Option<bool>,
+    /// `minimal`, `low`, `medium` or `high`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effort: Option<String>,
+    /// Reasoning summary detail: `auto`, `concise` or `detailed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
 }
 
 /// A text input to the model.
@@ -1382,6 +1497,40 @@ pub enum Annotation {
     FilePath(FilePath),
 }
 
+/// `annotation` in the Chat Completions shape, where a URL citation's fields
+/// sit under a `url_citation` key rather than next to its type as in the
+/// Responses API. Other annotations are returned as they are.
+pub fn chat_annotation(annotation: serde_json::Value) -> serde_json::Value {
+    match annotation {
+        serde_json::Value::Object(mut fields)
+            if fields.get("type").and_then(|t| t.as_str()) == Some("url_citation")
+                && !fields.get("url_citation").is_some_and(serde_json::Value::is_object) =>
+        {
+            let kind = fields.remove("type").unwrap_or_default();
+            serde_json::json!({"type": kind, "url_citation": fields})
+        }
+        other => other,
+    }
+}
+
+/// `annotation` in the Responses API shape; the inverse of [`chat_annotation`]
+pub fn responses_annotation(annotation: serde_json::Value) -> serde_json::Value {
+    match annotation {
+        serde_json::Value::Object(mut fields)
+            if fields.get("type").and_then(|t| t.as_str()) == Some("url_citation")
+                && fields.get("url_citation").is_some_and(serde_json::Value::is_object) =>
+        {
+            let mut flat = match fields.remove("url_citation") {
+                Some(serde_json::Value::Object(citation)) => citation,
+                _ => serde_json::Map::new(),
+            };
+            flat.insert("type".to_string(), serde_json::Value::String("url_citation".to_string()));
+            serde_json::Value::Object(flat)
+        }
+        other => other,
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FileCitation {
     pub file_id: String,
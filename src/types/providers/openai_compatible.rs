@@ -1,147 +1,40 @@
-//! OpenAI-Compatible API request and response types
+//! OpenAI-Compatible API types beyond Chat Completions
 //!
-//! This module contains all data structures for interacting with OpenAI-compatible APIs,
-//! including chat completions, tool calling, and streaming responses.
-//! These types are designed to work with any OpenAI-compatible endpoint.
+//! Model listings, usage details and error bodies as returned by OpenAI and
+//! compatible endpoints. Chat Completions requests and responses are shared
+//! with the official API and live in [`super::openai`].
 
 use serde::{Deserialize, Serialize};
 
-/// OpenAI-compatible chat completion request
-#[derive(Debug, Serialize)]
-pub struct OpenAIChatRequest {
-    pub model: String,
-    pub messages: Vec<OpenAIMessage>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub temperature: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub top_p: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub max_completion_tokens: Option<u32>,
-    pub stream: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tools: Option<Vec<OpenAITool>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_choice: Option<serde_json::Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub stop: Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub presence_penalty: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub frequency_penalty: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub parallel_tool_calls: Option<bool>,
-}
-
-/// A single message in an OpenAI-compatible conversation
-#[derive(Debug, Serialize)]
-pub struct OpenAIMessage {
-    pub role: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub content: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_calls: Option<Vec<OpenAIToolCall>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_call_id: Option<String>,
-}
-
-/// Tool specification for OpenAI-compatible APIs
-#[derive(Debug, Serialize, Clone, Deserialize, PartialEq)]
-pub struct OpenAITool {
-    pub r#type: String,
-    pub function: OpenAIFunction,
-}
-
-/// Function definition within a tool
-#[derive(Debug, Serialize, Clone, Deserialize, PartialEq)]
-pub struct OpenAIFunction {
-    pub name: String,
-    pub description: Option<String>,
-    pub parameters: serde_json::Value,
-}
-
-/// A tool call made by the assistant
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct OpenAIToolCall {
-    pub id: String,
-    pub r#type: String,
-    pub function: OpenAIFunctionCall,
-}
-
-/// Function call details within a tool call
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct OpenAIFunctionCall {
-    pub name: String,
-    pub arguments: String,
-}
-
-/// OpenAI-compatible chat completion response
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct OpenAIChatResponse {
-    pub id: String,
-    pub object: String,
-    pub created: u64,
-    pub model: String,
-    pub choices: Vec<OpenAIChoice>,
-    pub usage: Option<OpenAIUsage>,
-    pub service_tier: Option<String>,
-    pub system_fingerprint: Option<String>,
-}
-
-/// A single choice in the response
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct OpenAIChoice {
-    pub index: u32,
-    pub message: Option<OpenAIResponseMessage>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub delta: Option<OpenAIResponseDelta>,
-    pub finish_reason: Option<String>,
-    pub logprobs: Option<serde_json::Value>,
-}
-
-/// Response message from the assistant
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct OpenAIResponseMessage {
-    pub role: String,
-    pub content: Option<String>,
-    pub tool_calls: Option<Vec<OpenAIToolCall>>,
-    pub refusal: Option<String>,
-    #[serde(default)]
-    pub annotations: Vec<serde_json::Value>,
-}
-
-/// Delta for streaming responses
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct OpenAIResponseDelta {
-    pub role: Option<String>,
-    pub content: Option<String>,
-    pub tool_calls: Option<Vec<OpenAIToolCallDelta>>,
-}
-
-/// Tool call delta for streaming
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct OpenAIToolCallDelta {
-    pub index: u32,
-    pub id: Option<String>,
-    pub r#type: Option<String>,
-    pub function: Option<OpenAIFunctionCallDelta>,
-}
-
-/// Function call delta for streaming
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct OpenAIFunctionCallDelta {
-    pub name: Option<String>,
-    pub arguments: Option<String>,
-}
-
-/// Token usage information
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct OpenAIUsage {
-    pub prompt_tokens: u32,
-    pub completion_tokens: u32,
-    pub total_tokens: u32,
-    pub prompt_tokens_details: Option<PromptTokensDetails>,
-    pub completion_tokens_details: Option<CompletionTokensDetails>,
-}
+// The Chat Completions types used to be defined here as well, with fewer
+// derives than their twins in `providers::openai`. These aliases keep old
+// paths compiling for one release.
+#[deprecated(note = "use `types::providers::openai::OpenAIChatRequest`; this alias will be removed in the next release")]
+pub type OpenAIChatRequest = super::openai::OpenAIChatRequest;
+#[deprecated(note = "use `types::providers::openai::OpenAIMessage`; this alias will be removed in the next release")]
+pub type OpenAIMessage = super::openai::OpenAIMessage;
+#[deprecated(note = "use `types::providers::openai::OpenAIToolSpec`; this alias will be removed in the next release")]
+pub type OpenAITool = super::openai::OpenAIToolSpec;
+#[deprecated(note = "use `types::providers::openai::OpenAIFunctionDef`; this alias will be removed in the next release")]
+pub type OpenAIFunction = super::openai::OpenAIFunctionDef;
+#[deprecated(note = "use `types::providers::openai::OpenAIToolCall`; this alias will be removed in the next release")]
+pub type OpenAIToolCall = super::openai::OpenAIToolCall;
+#[deprecated(note = "use `types::providers::openai::OpenAIFunctionCall`; this alias will be removed in the next release")]
+pub type OpenAIFunctionCall = super::openai::OpenAIFunctionCall;
+#[deprecated(note = "use `types::providers::openai::OpenAIChatResponse`; this alias will be removed in the next release")]
+pub type OpenAIChatResponse = super::openai::OpenAIChatResponse;
+#[deprecated(note = "use `types::providers::openai::OpenAIChoice`; this alias will be removed in the next release")]
+pub type OpenAIChoice = super::openai::OpenAIChoice;
+#[deprecated(note = "use `types::providers::openai::OpenAIResponseMessage`; this alias will be removed in the next release")]
+pub type OpenAIResponseMessage = super::openai::OpenAIResponseMessage;
+#[deprecated(note = "use `types::providers::openai::OpenAIDelta`; this alias will be removed in the next release")]
+pub type OpenAIResponseDelta = super::openai::OpenAIDelta;
+#[deprecated(note = "use `types::providers::openai::OpenAIToolCallDelta`; this alias will be removed in the next release")]
+pub type OpenAIToolCallDelta = super::openai::OpenAIToolCallDelta;
+#[deprecated(note = "use `types::providers::openai::OpenAIFunctionCallDelta`; this alias will be removed in the next release")]
+pub type OpenAIFunctionCallDelta = super::openai::OpenAIFunctionCallDelta;
+#[deprecated(note = "use `types::providers::openai::OpenAIUsage`; this alias will be removed in the next release")]
+pub type OpenAIUsage = super::openai::OpenAIUsage;
 
 /// Detailed prompt token usage
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -160,19 +53,96 @@ pub struct CompletionTokensDetails {
 }
 
 /// Model information from the models endpoint
+///
+/// Aggregators such as OpenRouter omit `object`/`owned_by` but advertise
+/// context length, modalities and supported parameters, which are kept here.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OpenAIModel {
     pub id: String,
+    #[serde(default)]
     pub object: String,
+    #[serde(default)]
     pub created: u64,
+    #[serde(default)]
     pub owned_by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub architecture: Option<OpenAIModelArchitecture>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_provider: Option<OpenAIModelTopProvider>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supported_parameters: Option<Vec<String>>,
+}
+
+/// Input/output modalities advertised for a model
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct OpenAIModelArchitecture {
+    #[serde(default)]
+    pub input_modalities: Vec<String>,
+    #[serde(default)]
+    pub output_modalities: Vec<String>,
+}
+
+/// Limits of the provider currently serving a model
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct OpenAIModelTopProvider {
+    pub context_length: Option<u32>,
+    pub max_completion_tokens: Option<u32>,
+}
+
+impl OpenAIModel {
+    /// Overlay whatever capability metadata the provider advertised onto
+    /// heuristically inferred values. Fields the provider doesn't report are
+    /// left untouched.
+    pub fn apply_advertised_capabilities(
+        &self,
+        capabilities: &mut crate::types::ModelCapabilities,
+        modalities: &mut Vec<crate::types::Modality>,
+    ) {
+        use crate::types::Modality;
+
+        let context_length = self
+            .context_length
+            .or_else(|| self.top_provider.as_ref().and_then(|p| p.context_length));
+        if let Some(context_length) = context_length {
+            capabilities.context_length = Some(context_length);
+        }
+        if let Some(max_tokens) = self.top_provider.as_ref().and_then(|p| p.max_completion_tokens) {
+            capabilities.max_tokens = Some(max_tokens);
+        }
+        if let Some(params) = &self.supported_parameters {
+            capabilities.supports_tools = params.iter().any(|p| p == "tools");
+            capabilities.supports_json = params
+                .iter()
+                .any(|p| p == "response_format" || p == "structured_outputs");
+        }
+        if let Some(arch) = &self.architecture {
+            let vision = arch.input_modalities.iter().any(|m| m == "image");
+            let audio = arch.input_modalities.iter().any(|m| m == "audio");
+            capabilities.supports_vision = vision;
+            capabilities.supports_audio = audio;
+            crate::types::set_modality(modalities, Modality::Vision, vision);
+            crate::types::set_modality(modalities, Modality::AudioIn, audio);
+            let images = arch.output_modalities.iter().any(|m| m == "image");
+            crate::types::set_modality(modalities, Modality::ImageOut, images);
+        }
+    }
 }
 
 /// Response from the models endpoint
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OpenAIModelsResponse {
+    #[serde(default)]
     pub object: String,
     pub data: Vec<OpenAIModel>,
+    /// Set on paginated lists: the page's first and last ids and whether more follow
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
 }
 
 /// Error response structure
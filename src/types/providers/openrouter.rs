@@ -0,0 +1,69 @@
+//! OpenRouter request extensions
+//!
+//! OpenRouter accepts the OpenAI Chat Completions schema plus a handful of
+//! routing extensions. These typed helpers serialize into the generic
+//! `provider_extensions` map that the OpenAI-compatible adapter merges into
+//! the outbound body.
+
+use serde::{Deserialize, Serialize};
+
+/// Model variant suffixes understood by OpenRouter (e.g. `openai/gpt-4o:online`).
+pub const OPENROUTER_MODEL_SUFFIXES: &[&str] = &[
+    "online", "nitro", "floor", "free", "extended", "thinking", "exacto",
+];
+
+/// Provider routing preferences (the `provider` request field)
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct OpenRouterProviderPreferences {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_fallbacks: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_parameters: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_collection: Option<String>, // "allow" | "deny"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantizations: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>, // "price" | "throughput" | "latency"
+}
+
+/// OpenRouter-specific body fields
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct OpenRouterExtensions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<OpenRouterProviderPreferences>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transforms: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route: Option<String>, // "fallback"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub models: Option<Vec<String>>,
+}
+
+impl OpenRouterExtensions {
+    /// Convert into the map form used by `ChatRequestIR::provider_extensions`
+    /// and `ProviderEndpoint::extensions`.
+    pub fn into_map(self) -> serde_json::Map<String, serde_json::Value> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        }
+    }
+}
+
+/// Split a known OpenRouter variant suffix off a model id.
+///
+/// `"gpt-4o:online"` becomes `("gpt-4o", Some("online"))`; unknown suffixes
+/// (such as Ollama tags) are left untouched.
+pub fn split_model_suffix(model: &str) -> (&str, Option<&str>) {
+    match model.rsplit_once(':') {
+        Some((base, suffix)) if OPENROUTER_MODEL_SUFFIXES.contains(&suffix) => (base, Some(suffix)),
+        _ => (model, None),
+    }
+}
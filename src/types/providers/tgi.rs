@@ -0,0 +1,122 @@
+//! Hugging Face Text Generation Inference request and response types
+//!
+//! TGI's native API takes a single prompt: `/generate` answers with the
+//! whole text, `/generate_stream` sends one SSE event per token, and `/info`
+//! describes the one model the server runs.
+
+use serde::{Deserialize, Serialize};
+
+/// Body of `/generate` and `/generate_stream`
+#[derive(Debug, Serialize)]
+pub struct TgiGenerateRequest {
+    pub inputs: String,
+    pub parameters: TgiParameters,
+}
+
+/// Generation parameters; TGI samples as soon as any sampling parameter is
+/// set, and decodes greedily otherwise
+#[derive(Debug, Default, Serialize)]
+pub struct TgiParameters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_new_tokens: Option<u32>,
+    /// Must be above 0
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Must be between 0 and 1, exclusive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typical_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repetition_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// Constrain the output, e.g. to a JSON schema
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grammar: Option<TgiGrammar>,
+    /// Return the finish reason and token counts
+    pub details: bool,
+    /// Return the prompt's tokens as `details.prefill`; not allowed when streaming
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub decoder_input_details: bool,
+    pub return_full_text: bool,
+}
+
+/// Output grammar: `json` with a JSON schema, or `regex` with a pattern
+#[derive(Debug, Serialize)]
+pub struct TgiGrammar {
+    pub r#type: String,
+    pub value: serde_json::Value,
+}
+
+/// Complete `/generate` response
+#[derive(Debug, Deserialize)]
+pub struct TgiGenerateResponse {
+    pub generated_text: String,
+    pub details: Option<TgiDetails>,
+}
+
+/// One event of `/generate_stream`: a token, with the details on the last
+/// one, or an error
+#[derive(Debug, Deserialize)]
+pub struct TgiStreamEvent {
+    pub token: Option<TgiToken>,
+    pub details: Option<TgiDetails>,
+    pub error: Option<String>,
+    pub error_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TgiToken {
+    #[serde(default)]
+    pub id: u32,
+    #[serde(default)]
+    pub text: String,
+    pub logprob: Option<f32>,
+    /// End-of-sequence and other special tokens, not part of the text
+    #[serde(default)]
+    pub special: bool,
+}
+
+/// How generation ended, and its token counts
+#[derive(Debug, Clone, Deserialize)]
+pub struct TgiDetails {
+    /// `length`, `eos_token` or `stop_sequence`
+    pub finish_reason: String,
+    #[serde(default)]
+    pub generated_tokens: u32,
+    pub seed: Option<u64>,
+    /// The prompt's tokens, with `decoder_input_details`
+    #[serde(default)]
+    pub prefill: Vec<TgiToken>,
+    /// Prompt length in tokens; sent by newer servers when streaming
+    pub input_length: Option<u32>,
+}
+
+impl TgiDetails {
+    /// Prompt tokens, from `input_length` or else the prefill
+    pub fn input_tokens(&self) -> u32 {
+        self.input_length.unwrap_or(self.prefill.len() as u32)
+    }
+}
+
+/// Error body of HTTP errors
+#[derive(Debug, Deserialize)]
+pub struct TgiErrorResponse {
+    pub error: String,
+    pub error_type: Option<String>,
+}
+
+/// The served model, from `/info`
+#[derive(Debug, Deserialize)]
+pub struct TgiInfo {
+    pub model_id: String,
+    /// Prompt and generated tokens together
+    pub max_total_tokens: Option<u32>,
+}
@@ -0,0 +1,66 @@
+//! vLLM request extensions
+//!
+//! vLLM's OpenAI-compatible server takes guided decoding and beam search
+//! parameters on top of the Chat Completions schema. These typed helpers
+//! serialize into `provider_extensions`; the OpenAI-compatible adapter only
+//! sends them to endpoints with the `VLLM` compat profile.
+
+use serde::{Deserialize, Serialize};
+
+/// Body fields that are only sent to vLLM endpoints
+pub const VLLM_EXTENSION_FIELDS: &[&str] = &[
+    "guided_json",
+    "guided_regex",
+    "guided_choice",
+    "best_of",
+    "use_beam_search",
+];
+
+/// Fields constraining the output to a grammar; vLLM accepts one at a time
+pub const VLLM_GUIDED_FIELDS: &[&str] = &["guided_json", "guided_regex", "guided_choice"];
+
+/// Constrain the output to a JSON schema, a regex or a fixed set of choices
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuidedDecoding {
+    Json(serde_json::Value),
+    Regex(String),
+    Choice(Vec<String>),
+}
+
+/// vLLM-specific body fields
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct VllmExtensions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guided_json: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guided_regex: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guided_choice: Option<Vec<String>>,
+    /// Candidates generated per completion before the best is returned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_of: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_beam_search: Option<bool>,
+}
+
+impl VllmExtensions {
+    /// Only the field of `guided` set
+    pub fn guided(guided: GuidedDecoding) -> Self {
+        let mut extensions = Self::default();
+        match guided {
+            GuidedDecoding::Json(schema) => extensions.guided_json = Some(schema),
+            GuidedDecoding::Regex(regex) => extensions.guided_regex = Some(regex),
+            GuidedDecoding::Choice(choices) => extensions.guided_choice = Some(choices),
+        }
+        extensions
+    }
+
+    /// Convert into the map form used by `ChatRequestIR::provider_extensions`
+    /// and `ProviderEndpoint::extensions`.
+    pub fn into_map(self) -> serde_json::Map<String, serde_json::Value> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        }
+    }
+}
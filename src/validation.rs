@@ -0,0 +1,195 @@
+//! Validation of JSON-mode replies
+//!
+//! Models asked for `json_object` or `json_schema` output sometimes wrap it
+//! in a Markdown fence or add prose around it. With validation enabled (per
+//! router, or per request through `json_validation` metadata) the text of
+//! such replies is held back until the stream ends, the JSON is extracted
+//! and sent as one `TextDelta` plus a `FinalMessage`. Replies without valid
+//! JSON end in an `invalid_json` error, or with [`JsonValidation::Repair`]
+//! are retried once with the parse error appended. Requests for other
+//! formats stream exactly as before.
+
+use crate::router::{Deadline, Router};
+use crate::stream::{StreamEvent, ToolCallSummary};
+use crate::types::{ChatRequestIR, ContentPart, Message, ResponseFormat, Role};
+use futures_util::{Stream, StreamExt};
+use tokio_util::sync::CancellationToken;
+
+/// Request metadata key overriding the router's [`JsonValidation`]
+pub const JSON_VALIDATION_METADATA: &str = "json_validation";
+
+/// `Status` state announcing that a reply is retried
+pub const REPAIR_STATE: &str = "json_repair";
+
+/// What to do with the text of JSON-mode replies
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JsonValidation {
+    /// Stream the reply unchanged
+    #[default]
+    Off,
+    /// Extract the JSON from the reply; fail with `invalid_json` if there is none
+    Extract,
+    /// Like `Extract`, but retry once with the parse error before failing
+    Repair,
+}
+
+impl JsonValidation {
+    /// Parse a `json_validation` metadata value: `off`, `extract` or `repair`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" | "false" | "none" => Some(JsonValidation::Off),
+            "extract" | "true" | "on" => Some(JsonValidation::Extract),
+            "repair" | "retry" => Some(JsonValidation::Repair),
+            _ => None,
+        }
+    }
+
+    /// The mode for `request`: its metadata override, else `self`. Always
+    /// `Off` for requests that don't ask for JSON.
+    pub fn for_request(self, request: &ChatRequestIR) -> Self {
+        if !matches!(
+            request.response_format,
+            Some(ResponseFormat::JsonObject | ResponseFormat::JsonSchema { .. })
+        ) {
+            return JsonValidation::Off;
+        }
+        request
+            .metadata
+            .get(JSON_VALIDATION_METADATA)
+            .and_then(|value| JsonValidation::parse(value))
+            .unwrap_or(self)
+    }
+}
+
+/// The JSON value in `content`: the whole text, the body of a Markdown code
+/// fence, or the first object or array with prose around it
+pub fn extract_json(content: &str) -> Result<String, serde_json::Error> {
+    let trimmed = content.trim();
+    let error = match serde_json::from_str::<serde_json::Value>(trimmed) {
+        Ok(_) => return Ok(trimmed.to_string()),
+        Err(e) => e,
+    };
+
+    if let Some((_, rest)) = trimmed.split_once("```") {
+        // Skip the info string (e.g. `json`) up to the end of the line
+        let body = rest.split_once('\n').map_or(rest, |(_, body)| body);
+        let body = body.split_once("```").map_or(body, |(body, _)| body).trim();
+        if serde_json::from_str::<serde_json::Value>(body).is_ok() {
+            return Ok(body.to_string());
+        }
+    }
+
+    if let Some(start) = trimmed.find(['{', '[']) {
+        let mut values = serde_json::Deserializer::from_str(&trimmed[start..]).into_iter::<serde_json::Value>();
+        if let Some(Ok(_)) = values.next() {
+            return Ok(trimmed[start..start + values.byte_offset()].to_string());
+        }
+    }
+    Err(error)
+}
+
+/// Append the rejected reply and the parse error so the model can correct it
+fn append_correction(request: &mut ChatRequestIR, content: String, error: &serde_json::Error) {
+    request.messages.push(Message {
+        role: Role::Assistant,
+        parts: vec![ContentPart::Text(content)],
+        name: None,
+    });
+    request.messages.push(Message {
+        role: Role::User,
+        parts: vec![ContentPart::Text(format!(
+            "That reply was not valid JSON: {}. Respond again with only the JSON, without code fences or other text.",
+            error
+        ))],
+        name: None,
+    });
+}
+
+/// Hold back the text of `events` and replace it with the extracted JSON,
+/// re-routing `request` once on failure when `mode` is `Repair` (within the
+/// original `deadline`)
+pub(crate) fn validate_json<S>(
+    router: Router,
+    mut request: ChatRequestIR,
+    cancel: CancellationToken,
+    deadline: Option<Deadline>,
+    mode: JsonValidation,
+    events: S,
+) -> impl Stream<Item = StreamEvent> + Send
+where
+    S: Stream<Item = StreamEvent> + Send + Unpin + 'static,
+{
+    async_stream::stream! {
+        let mut events: Box<dyn Stream<Item = StreamEvent> + Send + Unpin> = Box::new(events);
+        let mut retried = false;
+        loop {
+            let mut deltas = String::new();
+            let mut final_message: Option<(String, Vec<ToolCallSummary>, Option<String>)> = None;
+            let mut called_tools = false;
+            while let Some(event) = events.next().await {
+                match event {
+                    StreamEvent::TextDelta { content } => deltas.push_str(&content),
+                    StreamEvent::FinalMessage { content, tool_calls, finish_reason } => {
+                        final_message = Some((content, tool_calls, finish_reason))
+                    }
+                    StreamEvent::Done => break,
+                    StreamEvent::Error { .. } => {
+                        yield event;
+                        return;
+                    }
+                    other => {
+                        called_tools |= matches!(other, StreamEvent::ToolCallStart { .. });
+                        yield other;
+                    }
+                }
+            }
+
+            let (content, tool_calls, finish_reason) = match final_message {
+                Some((content, tool_calls, finish_reason)) if !content.is_empty() => (content, tool_calls, finish_reason),
+                Some((_, tool_calls, finish_reason)) => (deltas, tool_calls, finish_reason),
+                None => (deltas, Vec::new(), None),
+            };
+            // A turn that only calls tools has no JSON to check
+            if called_tools && content.trim().is_empty() {
+                if !tool_calls.is_empty() {
+                    yield StreamEvent::FinalMessage { content, tool_calls, finish_reason };
+                }
+                yield StreamEvent::Done;
+                return;
+            }
+
+            let error = match extract_json(&content) {
+                Ok(json) => {
+                    yield StreamEvent::TextDelta { content: json.clone() };
+                    yield StreamEvent::FinalMessage { content: json, tool_calls, finish_reason };
+                    yield StreamEvent::Done;
+                    return;
+                }
+                Err(e) => e,
+            };
+            if mode != JsonValidation::Repair || retried {
+                yield StreamEvent::Error {
+                    code: "invalid_json".to_string(),
+                    message: format!("Model reply is not valid JSON: {}", error),
+                };
+                return;
+            }
+
+            tracing::debug!(model_alias = %request.model.alias, error = %error, "Retrying reply that is not valid JSON");
+            yield StreamEvent::Status { state: REPAIR_STATE.to_string(), detail: Some(error.to_string()) };
+            retried = true;
+            append_correction(&mut request, content, &error);
+            events = match router.route_chat_unvalidated(request.clone(), cancel.clone(), deadline).await {
+                Ok(events) => events,
+                Err(e @ crate::error::EngineError::DeadlineExceeded(_)) => {
+                    yield StreamEvent::Error { code: "deadline_exceeded".to_string(), message: e.to_string() };
+                    return;
+                }
+                Err(e) => {
+                    yield StreamEvent::Error { code: "routing_error".to_string(), message: e.to_string() };
+                    return;
+                }
+            };
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Provider self-checks
+//!
+//! A wrong API key or base URL otherwise shows up with the first user request.
+//! [`OmniferenceEngine::verify_providers`](crate::OmniferenceEngine::verify_providers)
+//! checks every enabled provider end to end instead: it warms up the
+//! connection to the provider (see [`crate::warmup`]), discovers the
+//! provider's models and, for providers with a
+//! [`probe_model`](crate::types::ProviderConfig::probe_model), asks that model
+//! for a single token. Each provider gets a [`ProviderCheck`] with its latency
+//! and, on failure, the stage that failed and the provider's error.
+//!
+//! Servers run the check on start as their [`StartupCheck`] says, and
+//! `omniference --check` prints the report and exits.
+//!
+//! [`OmniferenceEngine::probe_determinism`](crate::OmniferenceEngine::probe_determinism)
+//! checks whether a model honours `seed`: it sends the same short prompt
+//! twice with [`DETERMINISM_SEED`] and temperature 0 and compares the
+//! replies. Its [`DeterminismReport`] tells a provider that refused the seed
+//! apart from one that took it and replied differently anyway;
+//! `omniference probe-seed` prints the reports.
+
+use crate::adapter::AdapterError;
+use crate::error::EngineError;
+use crate::router::Router;
+use crate::stream::StreamEvent;
+use crate::types::{ChatRequestIR, ContentPart, Message, ModelRef, Modality, ProviderConfig, Role};
+use crate::warmup::WarmUp;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// What a server does with the providers' check on start
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupCheck {
+    /// Don't check
+    #[default]
+    Off,
+    /// Log the failed providers
+    Warn,
+    /// Log and disable the failed providers
+    Disable,
+    /// Refuse to start when a provider fails
+    Refuse,
+}
+
+/// The step of a check that failed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStage {
+    Discovery,
+    Probe,
+}
+
+/// The result of checking one provider
+#[derive(Clone, Debug, Serialize)]
+pub struct ProviderCheck {
+    /// The provider's qualified name
+    pub provider: String,
+    pub ok: bool,
+    /// DNS and connection timings, for providers with a URL; a failed
+    /// warm-up doesn't fail the check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warm_up: Option<WarmUp>,
+    /// Models the provider listed
+    pub models: usize,
+    pub discovery_latency_ms: u64,
+    /// The probe model, when the provider has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probe_model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probe_latency_ms: Option<u64>,
+    /// Where and why the check failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_stage: Option<CheckStage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ProviderCheck {
+    fn fail(mut self, stage: CheckStage, error: impl std::fmt::Display) -> Self {
+        self.ok = false;
+        self.failed_stage = Some(stage);
+        self.error = Some(error.to_string());
+        self
+    }
+}
+
+/// The checks of all enabled providers, sorted by provider
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct VerificationReport {
+    pub providers: Vec<ProviderCheck>,
+}
+
+impl VerificationReport {
+    /// Whether every provider passed
+    pub fn is_ok(&self) -> bool {
+        self.providers.iter().all(|check| check.ok)
+    }
+
+    /// The providers that failed
+    pub fn failed(&self) -> impl Iterator<Item = &ProviderCheck> {
+        self.providers.iter().filter(|check| !check.ok)
+    }
+}
+
+/// Seed sent by [`OmniferenceEngine::probe_determinism`](crate::OmniferenceEngine::probe_determinism)
+pub const DETERMINISM_SEED: u64 = 1234;
+
+const DETERMINISM_PROMPT: &str = "Write one sentence about the sea.";
+
+/// How a model handled a seeded request sent twice
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedOutcome {
+    /// Both replies were the same
+    Deterministic,
+    /// The provider took the seed but replied differently, so it ignores it
+    Nondeterministic,
+    /// The provider refused the request over its `seed` parameter
+    SeedRejected,
+    /// The request failed for another reason
+    Failed,
+}
+
+/// The result of probing one model with [`DETERMINISM_SEED`]
+#[derive(Clone, Debug, Serialize)]
+pub struct DeterminismReport {
+    /// The model alias probed
+    pub model: String,
+    pub seed: u64,
+    pub outcome: SeedOutcome,
+    /// The replies received, in order
+    pub replies: Vec<String>,
+    /// The provider's error, when a request failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DeterminismReport {
+    pub(crate) fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            seed: DETERMINISM_SEED,
+            outcome: SeedOutcome::Deterministic,
+            replies: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Whether the model replied the same both times
+    pub fn is_deterministic(&self) -> bool {
+        self.outcome == SeedOutcome::Deterministic
+    }
+
+    /// Record a reply, comparing it with the first
+    pub(crate) fn reply(&mut self, content: String) {
+        if self.replies.first().is_some_and(|first| *first != content) {
+            self.outcome = SeedOutcome::Nondeterministic;
+        }
+        self.replies.push(content);
+    }
+
+    /// End the probe with `error`; a provider error about the seed means
+    /// the provider rejected it
+    pub(crate) fn fail(mut self, error: EngineError) -> Self {
+        let rejected = match &error {
+            EngineError::Adapter(AdapterError::Provider { code, message }) => {
+                code.contains("seed") || message.to_lowercase().contains("seed")
+            }
+            _ => false,
+        };
+        self.outcome = if rejected { SeedOutcome::SeedRejected } else { SeedOutcome::Failed };
+        self.error = Some(error.to_string());
+        self
+    }
+}
+
+/// The request [`OmniferenceEngine::probe_determinism`](crate::OmniferenceEngine::probe_determinism)
+/// sends `model`, twice
+pub(crate) fn determinism_request(model: ModelRef) -> ChatRequestIR {
+    ChatRequestIR {
+        model,
+        messages: vec![Message {
+            role: Role::User,
+            parts: vec![ContentPart::Text(DETERMINISM_PROMPT.to_string())],
+            name: None,
+        }],
+        metadata: [("request_id".to_string(), uuid::Uuid::new_v4().to_string())].into(),
+        sampling: crate::types::Sampling {
+            temperature: Some(0.0),
+            seed: Some(DETERMINISM_SEED),
+            max_tokens: Some(32),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Check `provider` (registered as `name`) with its adapter in `router`
+pub(crate) async fn check_provider(router: &Router, name: String, provider: &ProviderConfig) -> ProviderCheck {
+    let mut check = ProviderCheck {
+        provider: name,
+        ok: true,
+        warm_up: None,
+        models: 0,
+        discovery_latency_ms: 0,
+        probe_model: provider.probe_model.clone(),
+        probe_latency_ms: None,
+        failed_stage: None,
+        error: None,
+    };
+    let Some(adapter) = router.registry.get(&provider.endpoint.kind) else {
+        let error = format!("no adapter registered for provider kind {:?}", provider.endpoint.kind);
+        return check.fail(CheckStage::Discovery, error);
+    };
+
+    // Reported only; an unreachable provider fails discovery anyway
+    check.warm_up = crate::warmup::warm_up(&provider.endpoint).await;
+
+    let started = Instant::now();
+    let discovered = adapter.discover_models(&provider.endpoint).await;
+    check.discovery_latency_ms = started.elapsed().as_millis() as u64;
+    match discovered {
+        Ok(models) => check.models = models.len(),
+        Err(e) => return check.fail(CheckStage::Discovery, e),
+    }
+
+    let Some(model) = provider.probe_model.clone() else {
+        return check;
+    };
+    let request = ChatRequestIR {
+        model: ModelRef {
+            alias: format!("{}/{}", check.provider, model),
+            provider: provider.endpoint.clone(),
+            model_id: model,
+            modalities: vec![Modality::Text],
+        },
+        messages: vec![Message {
+            role: Role::User,
+            parts: vec![ContentPart::Text("ping".to_string())],
+            name: None,
+        }],
+        metadata: [("request_id".to_string(), uuid::Uuid::new_v4().to_string())].into(),
+        sampling: crate::types::Sampling {
+            max_tokens: Some(1),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let started = Instant::now();
+    let result = probe(adapter.as_ref(), request).await;
+    check.probe_latency_ms = Some(started.elapsed().as_millis() as u64);
+    match result {
+        Ok(()) => check,
+        Err(e) => check.fail(CheckStage::Probe, e),
+    }
+}
+
+/// Send `request`, failing on the first error event
+async fn probe(adapter: &dyn crate::adapter::ChatAdapter, request: ChatRequestIR) -> Result<(), AdapterError> {
+    let mut stream = adapter.execute_chat(request, Default::default()).await?;
+    while let Some(event) = stream.next().await {
+        match event {
+            StreamEvent::Error { code, message } => return Err(AdapterError::Provider { code, message }),
+            StreamEvent::Done => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
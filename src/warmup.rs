@@ -0,0 +1,127 @@
+//! Connection warm-up for hosted providers
+//!
+//! The first request to a provider otherwise pays for the DNS lookup, the
+//! TCP connection and the TLS handshake. Providers registered with
+//! [`warm_up`](crate::types::ProviderConfig::warm_up) resolve their host and
+//! open a connection in the shared client's pool (see
+//! [`crate::adapters::http`]) with a `HEAD` request to their base URL when
+//! they are registered. Providers with
+//! [`keep_warm`](crate::types::ProviderConfig::keep_warm) repeat the request
+//! every [`KEEP_WARM_INTERVAL`], before the pool drops the idle connection,
+//! for as long as they stay registered and enabled.
+//!
+//! The provider check (see [`crate::verify`]) reports a warm-up of every
+//! provider with a URL.
+
+use crate::service::ProviderManager;
+use crate::types::ProviderEndpoint;
+use serde::Serialize;
+use std::sync::Weak;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Time between the pings of providers kept warm; shorter than the 90
+/// seconds idle connections stay pooled
+pub const KEEP_WARM_INTERVAL: Duration = Duration::from_secs(45);
+
+/// The outcome of warming up a provider's connection
+#[derive(Clone, Debug, Serialize)]
+pub struct WarmUp {
+    pub ok: bool,
+    /// Addresses the host resolved to; absent for Unix sockets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub addresses: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns_latency_ms: Option<u64>,
+    /// Time until the `HEAD` request was answered, over a new connection
+    /// unless the pool had one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl WarmUp {
+    fn fail(mut self, error: impl std::fmt::Display) -> Self {
+        self.ok = false;
+        self.error = Some(error.to_string());
+        self
+    }
+}
+
+/// Resolve `endpoint`'s host and open a pooled connection to it. `None`
+/// when the endpoint has no URL to connect to, like most custom adapters.
+pub async fn warm_up(endpoint: &ProviderEndpoint) -> Option<WarmUp> {
+    let url = crate::adapters::http::endpoint_url(endpoint, "");
+    let parsed = reqwest::Url::parse(&url).ok().filter(|url| url.has_host())?;
+    let mut warm_up = WarmUp {
+        ok: true,
+        addresses: None,
+        dns_latency_ms: None,
+        connect_latency_ms: None,
+        error: None,
+    };
+
+    if crate::adapters::http::unix_socket(&endpoint.base_url).is_none() {
+        let host = parsed.host_str().unwrap_or_default().trim_matches(['[', ']']);
+        let port = parsed.port_or_known_default().unwrap_or(443);
+        let started = Instant::now();
+        let resolved = tokio::net::lookup_host((host, port)).await;
+        warm_up.dns_latency_ms = Some(started.elapsed().as_millis() as u64);
+        match resolved {
+            Ok(addresses) => warm_up.addresses = Some(addresses.count()),
+            Err(e) => return Some(warm_up.fail(format!("DNS lookup of {} failed: {}", host, e))),
+        }
+    }
+
+    let client = match crate::adapters::http::client(endpoint) {
+        Ok(client) => client,
+        Err(e) => return Some(warm_up.fail(e)),
+    };
+    let started = Instant::now();
+    // Any answer means the connection is open and pooled
+    let response = crate::adapters::http::send(client.head(&url), endpoint, "Warm-up request failed").await;
+    warm_up.connect_latency_ms = Some(started.elapsed().as_millis() as u64);
+    Some(match response {
+        Ok(_) => warm_up,
+        Err(e) => warm_up.fail(e),
+    })
+}
+
+/// Warm up provider `name` once if it asks for it, then ping it every
+/// [`KEEP_WARM_INTERVAL`] while it is registered, enabled and kept warm.
+/// Stops once `manager` is dropped.
+pub(crate) async fn keep_warm(name: String, manager: Weak<RwLock<ProviderManager>>) {
+    let mut ticks = tokio::time::interval(KEEP_WARM_INTERVAL);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut first = true;
+    loop {
+        ticks.tick().await;
+        let Some(manager) = manager.upgrade() else {
+            return;
+        };
+        let endpoint = {
+            let mut manager = manager.write().await;
+            let endpoint = manager
+                .get_provider(&name)
+                .filter(|provider| provider.enabled && (provider.keep_warm || (first && provider.warm_up)))
+                .map(|provider| provider.endpoint.clone());
+            let Some(endpoint) = endpoint else {
+                manager.stop_warming(&name);
+                return;
+            };
+            endpoint
+        };
+        drop(manager);
+        first = false;
+        match warm_up(&endpoint).await {
+            Some(WarmUp { ok: true, connect_latency_ms, .. }) => {
+                tracing::debug!(provider = %name, ?connect_latency_ms, "Warmed up provider connection")
+            }
+            Some(WarmUp { error, .. }) => {
+                tracing::warn!(provider = %name, error = error.as_deref().unwrap_or_default(), "Provider warm-up failed")
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Scripted adapter for exercising engine behavior without a live provider
+
+use async_trait::async_trait;
+use futures_util::Stream;
+use omniference::*;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub const MOCK_MODEL: &str = "mock-model";
+
+/// Replays one scripted event list per `execute_chat` call and records the
+/// requests it receives.
+pub struct MockAdapter {
+    script: Mutex<VecDeque<Vec<StreamEvent>>>,
+    requests: Mutex<Vec<ChatRequestIR>>,
+}
+
+impl MockAdapter {
+    pub fn new(script: Vec<Vec<StreamEvent>>) -> Arc<Self> {
+        Arc::new(Self {
+            script: Mutex::new(script.into()),
+            requests: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn requests(&self) -> Vec<ChatRequestIR> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// An engine routing to this adapter with `MOCK_MODEL` discovered
+    pub async fn engine(self: &Arc<Self>) -> OmniferenceEngine {
+        self.engine_with(|router| router).await
+    }
+
+    /// Like [`engine`](Self::engine), with the router customized first
+    pub async fn engine_with(self: &Arc<Self>, configure: impl FnOnce(Router) -> Router) -> OmniferenceEngine {
+        let registry = AdapterRegistry::default();
+        registry.register(self.clone());
+        let mut engine = OmniferenceEngine::with_router(configure(Router::new(registry)));
+        engine
+            .register_provider(ProviderConfig {
+                name: "mock".to_string(),
+                endpoint: ProviderEndpoint {
+                    kind: ProviderKind::Custom("mock".to_string()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        engine
+    }
+}
+
+#[async_trait]
+impl ChatAdapter for MockAdapter {
+    fn provider_kind(&self) -> ProviderKind {
+        ProviderKind::Custom("mock".to_string())
+    }
+
+    async fn execute_chat(
+        &self,
+        ir: ChatRequestIR,
+        _cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Box<dyn Stream<Item = StreamEvent> + Send + Unpin>, AdapterError> {
+        self.requests.lock().unwrap().push(ir);
+        let events = self
+            .script
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| AdapterError::internal("mock script exhausted"))?;
+        Ok(Box::new(futures_util::stream::iter(events)))
+    }
+
+    async fn discover_models(&self, _endpoint: &ProviderEndpoint) -> Result<Vec<DiscoveredModel>, AdapterError> {
+        Ok(vec![DiscoveredModel {
+            id: MOCK_MODEL.to_string(),
+            name: MOCK_MODEL.to_string(),
+            provider_name: "mock".to_string(),
+            provider_kind: self.provider_kind(),
+            modalities: vec![Modality::Text],
+            capabilities: ModelCapabilities::text_only().with_tools(),
+            created: None,
+        }])
+    }
+}
+
+/// Streams "Hel", then "lo" once its gate opens; counts requests
+pub struct GatedAdapter {
+    pub calls: AtomicUsize,
+    gate: tokio::sync::watch::Receiver<bool>,
+}
+
+impl GatedAdapter {
+    /// The adapter, and the sender opening its gate with `true`
+    pub fn new() -> (Arc<Self>, tokio::sync::watch::Sender<bool>) {
+        let (open, gate) = tokio::sync::watch::channel(false);
+        (Arc::new(Self { calls: AtomicUsize::new(0), gate }), open)
+    }
+
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl ChatAdapter for GatedAdapter {
+    fn provider_kind(&self) -> ProviderKind {
+        ProviderKind::Custom("gated".to_string())
+    }
+
+    async fn discover_models(&self, _endpoint: &ProviderEndpoint) -> Result<Vec<DiscoveredModel>, AdapterError> {
+        Ok(Vec::new())
+    }
+
+    async fn execute_chat(
+        &self,
+        _ir: ChatRequestIR,
+        _cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Box<dyn Stream<Item = StreamEvent> + Send + Unpin>, AdapterError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let mut gate = self.gate.clone();
+        Ok(Box::new(Box::pin(async_stream::stream! {
+            yield StreamEvent::TextDelta { content: "Hel".to_string() };
+            let _ = gate.wait_for(|open| *open).await;
+            yield StreamEvent::TextDelta { content: "lo".to_string() };
+            yield StreamEvent::Done;
+        })))
+    }
+}
@@ -7,6 +7,7 @@ mod openai_responses_tests {
     };
     use omniference::{
         server::OmniferenceServer,
+        testing::{FakeOpenAI, FakeScenario},
         types::{ProviderConfig, ProviderEndpoint, ProviderKind},
         types::providers::openai::*,
     };
@@ -27,23 +28,37 @@ mod openai_responses_tests {
         });
     }
 
-    /// Central setup function for tests requiring a live API connection.
-    /// It initializes the environment, checks for required keys, and returns
-    /// `Some(Router)` on success or `None` if the test should be skipped.
-    async fn setup_live_test_environment() -> Option<Router> {
+    /// Central setup function for tests that talk to a provider. It
+    /// initializes the environment and returns a router backed by the live
+    /// API when a key is set and live tests aren't skipped, or by a fake
+    /// OpenAI-compatible server otherwise. The fake server is returned too
+    /// and must outlive the router's requests.
+    async fn setup_live_test_environment() -> (Router, Option<FakeOpenAI>) {
         initialize_test_env();
 
         if std::env::var("SKIP_LIVE_TESTS").ok().as_deref() == Some("true") {
-            println!("⚠️  Skipping live test because SKIP_LIVE_TESTS is set.");
-            return None;
+            println!("⚠️  SKIP_LIVE_TESTS is set - using the fake OpenAI server.");
+            return setup_fake_server().await;
         }
 
         if std::env::var("OPENAI_API_KEY").is_err() {
-            println!("⚠️  Skipping live test: OPENAI_API_KEY not set in environment or .env file.");
-            return None;
+            println!("⚠️  OPENAI_API_KEY not set in environment or .env file - using the fake OpenAI server.");
+            return setup_fake_server().await;
         }
 
-        Some(setup_test_server().await)
+        (setup_test_server().await, None)
+    }
+
+    /// A server whose `openai` and `openai-compat` providers are both the
+    /// fake server, serving the models the live tests ask for
+    async fn setup_fake_server() -> (Router, Option<FakeOpenAI>) {
+        let fake = FakeOpenAI::start(FakeScenario::new().with_models(["gpt-5-nano"])).await;
+        let mut server = OmniferenceServer::new();
+        for name in ["openai", "openai-compat"] {
+            server.add_provider(fake.provider(name)).await.expect("Failed to add the fake provider");
+        }
+        server.service().discover_models().await.expect("Failed to discover the fake models");
+        (server.app(), Some(fake))
     }
 
     /// Sets up the test server. Can be called directly by tests that
@@ -63,11 +78,13 @@ mod openai_responses_tests {
                 endpoint: ProviderEndpoint {
                     kind: ProviderKind::OpenAI,
                     base_url: openai_base.clone(),
-                    api_key: Some(key.clone()),
+                    api_key: Some(key.clone().into()),
                     extra_headers: std::collections::BTreeMap::new(),
                     timeout: Some(30000),
+                    ..Default::default()
                 },
                 enabled: true,
+                ..Default::default()
             };
 
             let openai_compat_provider_config = ProviderConfig {
@@ -75,11 +92,13 @@ mod openai_responses_tests {
                 endpoint: ProviderEndpoint {
                     kind: ProviderKind::OpenAICompat,
                     base_url: openai_base,
-                    api_key: Some(key),
+                    api_key: Some(key.into()),
                     extra_headers: std::collections::BTreeMap::new(),
                     timeout: Some(30000),
+                    ..Default::default()
                 },
                 enabled: true,
+                ..Default::default()
             };
 
             server.add_provider(openai_provider_config).await.expect("Failed to add OpenAI provider");
@@ -147,6 +166,7 @@ mod openai_responses_tests {
             verbosity: None,
             prompt_cache_key: None,
             safety_identifier: None,
+            extra: serde_json::Map::new(),
         }
     }
 
@@ -203,6 +223,7 @@ mod openai_responses_tests {
             // Reasoning configuration
             reasoning: Some(Reasoning {
                 enabled: Some(true),
+                ..Default::default()
             }),
 
             // Stream options
@@ -243,7 +264,7 @@ mod openai_responses_tests {
 
     #[tokio::test]
     async fn test_minimal_openai_responses_request() {
-        let Some(app) = setup_live_test_environment().await else { return; };
+        let (app, _fake) = setup_live_test_environment().await;
 
         let request_payload = create_minimal_request();
         let request = Request::builder()
@@ -260,7 +281,7 @@ mod openai_responses_tests {
 
     #[tokio::test]
     async fn test_comprehensive_openai_responses_request() {
-        let Some(app) = setup_live_test_environment().await else { return; };
+        let (app, _fake) = setup_live_test_environment().await;
 
         let request_payload = create_comprehensive_request();
         println!("Testing comprehensive request with all parameters...");
@@ -281,7 +302,7 @@ mod openai_responses_tests {
 
     #[tokio::test]
     async fn test_streaming_openai_responses_request() {
-        let Some(app) = setup_live_test_environment().await else { return; };
+        let (app, _fake) = setup_live_test_environment().await;
 
         let mut request_payload = create_minimal_request();
         request_payload.stream = Some(true);
@@ -301,7 +322,7 @@ mod openai_responses_tests {
 
     #[tokio::test]
     async fn test_openai_compat_to_openai_responses() {
-        let Some(app) = setup_live_test_environment().await else { return; };
+        let (app, _fake) = setup_live_test_environment().await;
 
         // Request 1: OpenAI Compatible
         let chat_request = create_minimal_chat_request();
@@ -336,7 +357,7 @@ mod openai_responses_tests {
 
     #[tokio::test]
     async fn test_openai_responses_to_openai_compat() {
-        let Some(app) = setup_live_test_environment().await else { return; };
+        let (app, _fake) = setup_live_test_environment().await;
 
         // Request 1: OpenAI Responses
         let responses_request = create_minimal_request();
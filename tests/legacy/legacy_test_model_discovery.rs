@@ -20,15 +20,17 @@ pub async fn test_model_discovery() -> anyhow::Result<()> {
         endpoint: ProviderEndpoint {
             kind: ProviderKind::OpenAICompat,
             base_url: base,
-            api_key: key,
+            api_key: key.map(Into::into),
             extra_headers: std::collections::BTreeMap::new(),
             timeout: Some(30000),
+            ..Default::default()
         },
         enabled: true,
+        ..Default::default()
     };
 
     // Add provider to server
-    server.add_provider(provider).await.map_err(|e| anyhow::anyhow!(e))?;
+    server.add_provider(provider).await?;
     println!("✅ Test provider added");
 
     // Build the app to create SkinContext with shared ProviderManager
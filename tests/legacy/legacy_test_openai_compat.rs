@@ -9,9 +9,10 @@ pub async fn test_openai_compat() -> Result<(), Box<dyn std::error::Error>> {
     let endpoint = ProviderEndpoint {
         kind: ProviderKind::OpenAICompat,
         base_url: base,
-        api_key: key,
+        api_key: key.map(Into::into),
         extra_headers: BTreeMap::new(),
         timeout: Some(30000),
+        ..Default::default()
     };
     
     println!("Testing OpenAI compatible adapter...");
@@ -8,9 +8,10 @@ pub async fn test_openai_responses() -> Result<(), Box<dyn std::error::Error>> {
     let endpoint = ProviderEndpoint {
         kind: ProviderKind::OpenAI,
         base_url: base,
-        api_key: key,
+        api_key: key.map(Into::into),
         extra_headers: BTreeMap::new(),
         timeout: Some(30000),
+        ..Default::default()
     };
     
     println!("Testing OpenAI Responses API adapter...");
@@ -24,6 +24,34 @@ mod tests {
 
     #[tokio::test]
     async fn test_provider_registration() {
+        use axum::{body::Body, http::{Request, StatusCode}};
+        use tower::ServiceExt;
+
+        // Against the fake server: registered, discovered and served by the skins
+        let fake = testing::FakeOpenAI::start(testing::FakeScenario::new()).await;
+        let mut server = server::OmniferenceServer::new();
+        server.add_provider(fake.provider("fake")).await.unwrap();
+        let models = server.service().discover_models().await.unwrap();
+        let alias = format!("fake/{}", testing::FAKE_MODEL);
+        assert!(models.iter().any(|model| model.id == alias), "{:?}", models);
+        let request = serde_json::json!({"model": alias, "messages": [{"role": "user", "content": "Hi"}]});
+        let response = server
+            .app()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/openai-compatible/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(request.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let completion: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(completion["choices"][0]["message"]["content"], "Hello!");
+
         let mut server = server::OmniferenceServer::new();
         if std::env::var("SKIP_LIVE_TESTS").ok().as_deref() == Some("true") {
             eprintln!("Skipping provider registration live test");
@@ -38,11 +66,346 @@ mod tests {
                 api_key: None,
                 extra_headers: std::collections::BTreeMap::new(),
                 timeout: Some(30000),
+                ..Default::default()
             },
             enabled: true,
+            ..Default::default()
         };
         
         let result = server.add_provider(provider).await;
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_chunk_message() {
+        use skins::bot::chunk_message;
+
+        assert!(chunk_message("", 2000).is_empty());
+        assert_eq!(chunk_message("short reply", 2000), vec!["short reply"]);
+
+        // Splits on line boundaries
+        let text = format!("{}\n{}", "a".repeat(1500), "b".repeat(1500));
+        let chunks = chunk_message(&text, 2000);
+        assert_eq!(chunks, vec!["a".repeat(1500), "b".repeat(1500)]);
+
+        // Long lines break at whitespace, then hard-split
+        let chunks = chunk_message("aaaa bbbb cccc", 9);
+        assert_eq!(chunks, vec!["aaaa bbbb", "cccc"]);
+        let chunks = chunk_message(&"x".repeat(4500), 2000);
+        assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), vec![2000, 2000, 500]);
+
+        // Limits count characters and never split inside one
+        let chunks = chunk_message(&"é".repeat(2001), 2000);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].chars().count(), 2000);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 2000));
+    }
+
+    #[test]
+    fn test_conversation_history_window() {
+        let mut history = skins::bot::ConversationHistory::new(2);
+        history.push(1, types::Role::User, "one");
+        history.push(1, types::Role::Assistant, "two");
+        history.push(1, types::Role::User, "three");
+        history.push(2, types::Role::User, "other channel");
+
+        let messages = history.messages(1);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, types::Role::Assistant);
+        assert!(matches!(&messages[1].parts[0], types::ContentPart::Text(t) if t == "three"));
+
+        history.clear(1);
+        assert!(history.messages(1).is_empty());
+        assert_eq!(history.messages(2).len(), 1);
+    }
+
+    #[test]
+    fn test_history_fits_token_budget() {
+        use types::{ContentPart, Message, Role};
+
+        let message = |role: Role, part: ContentPart| Message { role, parts: vec![part], name: None };
+        let text = |role: Role, t: &str| message(role, ContentPart::Text(t.to_string()));
+        let messages = vec![
+            text(Role::System, "Be brief."),
+            text(Role::User, &"old question ".repeat(40)),
+            message(Role::Assistant, ContentPart::ToolCall {
+                id: "call_1".to_string(),
+                name: "lookup".to_string(),
+                arguments: serde_json::json!({"q": "x"}),
+            }),
+            message(Role::Tool, ContentPart::ToolResult { call_id: "call_1".to_string(), content: "result".to_string() }),
+            text(Role::User, "latest"),
+        ];
+        let count = HeuristicCounter::count_messages(&messages);
+        assert_eq!(count.per_message.len(), 5);
+        assert_eq!(
+            count.prompt_tokens,
+            count.per_message.iter().sum::<usize>() + tokens::REPLY_PRIMING
+        );
+
+        // Dropping the long user turn is enough, but the tool result must not
+        // outlive the call that requested it
+        let budget = count.prompt_tokens - count.per_message[1] - count.per_message[2];
+        let kept = tokens::fit_to_budget(messages.clone(), &count, budget);
+        let roles: Vec<Role> = kept.iter().map(|m| m.role.clone()).collect();
+        assert_eq!(roles, vec![Role::System, Role::User]);
+
+        // The system prompt and the latest message survive any budget
+        let kept = tokens::fit_to_budget(messages.clone(), &count, 0);
+        assert_eq!(kept.len(), 2);
+
+        let kept = tokens::fit_to_budget(messages, &count, count.prompt_tokens);
+        assert_eq!(kept.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_reply_updates() {
+        use futures_util::StreamExt;
+        use skins::bot::{reply_updates, ReplyUpdate};
+        use stream::StreamEvent;
+
+        let delta = |s: &str| StreamEvent::TextDelta { content: s.to_string() };
+        let events = futures_util::stream::iter(vec![delta("a"), delta("b"), delta("c"), StreamEvent::Done]);
+        let updates: Vec<_> = reply_updates(events, 2).collect().await;
+        assert_eq!(
+            updates,
+            vec![ReplyUpdate::Partial("ab".to_string()), ReplyUpdate::Final("abc".to_string())]
+        );
+
+        let events = futures_util::stream::iter(vec![
+            delta("partial"),
+            StreamEvent::Error { code: "upstream".to_string(), message: "boom".to_string() },
+            delta("ignored"),
+        ]);
+        let updates: Vec<_> = reply_updates(events, 10).collect().await;
+        assert_eq!(
+            updates,
+            vec![ReplyUpdate::Failed { partial: "partial".to_string(), message: "boom".to_string() }]
+        );
+    }
+
+    #[test]
+    fn test_websocket_frames() {
+        use skins::websocket::{WsClientFrame, WsServerFrame};
+
+        let frame: WsClientFrame = serde_json::from_value(serde_json::json!({
+            "type": "chat",
+            "id": "req-1",
+            "request": {"model": "llama3", "messages": [{"role": "user", "content": "hi"}]}
+        }))
+        .unwrap();
+        assert!(matches!(frame, WsClientFrame::Chat { ref id, ref request } if id == "req-1" && request.model == "llama3"));
+
+        let frame: WsClientFrame = serde_json::from_str(r#"{"type":"cancel","id":"req-1"}"#).unwrap();
+        assert!(matches!(frame, WsClientFrame::Cancel { ref id } if id == "req-1"));
+        assert!(serde_json::from_str::<WsClientFrame>(r#"{"type":"cancel"}"#).is_err());
+
+        let done = serde_json::to_value(WsServerFrame::Done { id: "req-1".to_string() }).unwrap();
+        assert_eq!(done, serde_json::json!({"type": "done", "id": "req-1"}));
+        let error = serde_json::to_value(WsServerFrame::Error {
+            id: None,
+            code: "invalid_request_body".to_string(),
+            message: "bad frame".to_string(),
+        })
+        .unwrap();
+        assert_eq!(error, serde_json::json!({"type": "error", "code": "invalid_request_body", "message": "bad frame"}));
+    }
+
+    #[cfg(feature = "grpc")]
+    #[tokio::test]
+    async fn test_grpc_service() {
+        use skins::grpc::{proto, proto::inference_server::Inference, to_chat_event, GrpcService};
+
+        let event = to_chat_event("req-1", StreamEvent::TextDelta { content: "hi".to_string() }).unwrap();
+        assert_eq!(event.request_id, "req-1");
+        assert_eq!(event.event, Some(proto::chat_event::Event::TextDelta("hi".to_string())));
+        let event = to_chat_event("req-1", StreamEvent::Tokens { input: 3, output: 5 }).unwrap();
+        assert_eq!(
+            event.event,
+            Some(proto::chat_event::Event::Usage(proto::Usage { input_tokens: 3, output_tokens: 5 }))
+        );
+        assert!(to_chat_event("req-1", StreamEvent::SystemNote { content: "note".to_string() }).is_none());
+
+        let grpc = GrpcService::new(OmniferenceService::new());
+        let models = grpc.list_models(tonic::Request::new(proto::ListModelsRequest {})).await.unwrap();
+        assert!(models.into_inner().models.is_empty());
+
+        let cancel = grpc
+            .cancel(tonic::Request::new(proto::CancelRequest { request_id: "unknown".to_string() }))
+            .await
+            .unwrap();
+        assert!(!cancel.into_inner().cancelled);
+
+        let status = grpc
+            .chat(tonic::Request::new(proto::ChatRequest { model: "missing".to_string(), ..Default::default() }))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_settings_resolution_and_apply() {
+        use skins::settings::{ChatSettings, InMemorySettingsStore, SettingsScope, SettingsStore};
+
+        let store = InMemorySettingsStore::new();
+        let defaults = ChatSettings {
+            model: Some("ollama/llama3.2".to_string()),
+            system_prompt: Some("Be brief.".to_string()),
+            ..Default::default()
+        };
+        store.set(SettingsScope::Guild(1), ChatSettings {
+            model: Some("ollama/qwen2.5".to_string()),
+            temperature: Some(0.2),
+            ..Default::default()
+        }).await.unwrap();
+        store.set(SettingsScope::Channel(10), ChatSettings {
+            model: Some("openai/gpt-4o".to_string()),
+            ..Default::default()
+        }).await.unwrap();
+
+        // Channel beats guild beats defaults, field by field
+        let settings = store.resolve(Some(1), 10, &defaults).await;
+        assert_eq!(settings.model.as_deref(), Some("openai/gpt-4o"));
+        assert_eq!(settings.temperature, Some(0.2));
+        assert_eq!(settings.system_prompt.as_deref(), Some("Be brief."));
+
+        let settings = store.resolve(Some(1), 11, &defaults).await;
+        assert_eq!(settings.model.as_deref(), Some("ollama/qwen2.5"));
+        assert_eq!(store.resolve(None, 11, &defaults).await, defaults);
+
+        let mut request = types::ChatRequestIR::default();
+        store.resolve(Some(1), 10, &defaults).await.apply(&mut request);
+        assert_eq!(request.sampling.temperature, Some(0.2));
+        assert_eq!(request.messages[0].role, types::Role::System);
+    }
+
+    #[tokio::test]
+    async fn test_file_settings_store_roundtrip() {
+        use skins::settings::{ChatSettings, FileSettingsStore, SettingsScope, SettingsStore};
+
+        let path = std::env::temp_dir().join(format!("omniference-settings-{}.json", uuid::Uuid::new_v4()));
+        let settings = ChatSettings {
+            temperature: Some(0.7),
+            max_tokens: Some(256),
+            ..Default::default()
+        };
+
+        let store = FileSettingsStore::open(&path).await.unwrap();
+        assert!(store.get(SettingsScope::Guild(42)).await.is_none());
+        store.set(SettingsScope::Guild(42), settings.clone()).await.unwrap();
+
+        let reopened = FileSettingsStore::open(&path).await.unwrap();
+        assert_eq!(reopened.get(SettingsScope::Guild(42)).await, Some(settings));
+        assert!(reopened.get(SettingsScope::Channel(42)).await.is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_semantic_request_checks() {
+        use axum::{body::Body, http::{Request, StatusCode}};
+        use skins::semantic::check_chat_request;
+        use tower::ServiceExt;
+
+        let user = serde_json::json!({"role": "user", "content": "hi"});
+        let weather = serde_json::json!({
+            "type": "function",
+            "function": {"name": "get_weather", "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}}
+        });
+        // (request fields next to `model`, pointer of the first invalid element)
+        let cases = [
+            (serde_json::json!({"messages": [user]}), None),
+            (serde_json::json!({"messages": []}), Some("/messages")),
+            (serde_json::json!({"messages": [user, {"role": "user", "content": ""}]}), Some("/messages/1/content")),
+            (serde_json::json!({"messages": [{"role": "user", "content": []}]}), Some("/messages/0/content")),
+            (
+                serde_json::json!({"messages": [{"role": "user", "content": [{"type": "text", "text": ""}]}]}),
+                Some("/messages/0/content"),
+            ),
+            (
+                serde_json::json!({"messages": [{"role": "user", "content": [{"type": "text", "text": "hi"}, {"type": "video", "url": "x"}]}]}),
+                Some("/messages/0/content/1/type"),
+            ),
+            (
+                serde_json::json!({"messages": [{"role": "user", "content": [{"type": "image_url"}]}]}),
+                Some("/messages/0/content/0/image_url"),
+            ),
+            // Assistant turns may carry only tool calls
+            (
+                serde_json::json!({"messages": [user, {"role": "assistant", "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{}"}}
+                ]}]}),
+                None,
+            ),
+            (serde_json::json!({"messages": [user], "tools": [weather], "tool_choice": "required"}), None),
+            (
+                serde_json::json!({"messages": [user], "tools": [weather], "tool_choice": {"type": "function", "function": {"name": "get_weather"}}}),
+                None,
+            ),
+            (
+                serde_json::json!({"messages": [user], "tools": [weather], "tool_choice": {"type": "function", "function": {"name": "get_time"}}}),
+                Some("/tool_choice/function/name"),
+            ),
+            (serde_json::json!({"messages": [user], "tool_choice": "sometimes"}), Some("/tool_choice")),
+            (
+                serde_json::json!({"messages": [user], "tools": [weather, {"type": "function", "function": {"name": "f", "parameters": {"type": "string"}}}]}),
+                Some("/tools/1/function/parameters/type"),
+            ),
+            (
+                serde_json::json!({"messages": [user], "tools": [{"type": "function", "function": {"name": "f", "parameters": []}}]}),
+                Some("/tools/0/function/parameters"),
+            ),
+            (
+                serde_json::json!({"messages": [user], "tools": [{"type": "function", "function": {"name": "", "parameters": null}}]}),
+                Some("/tools/0/function/name"),
+            ),
+            (
+                serde_json::json!({"messages": [user], "functions": [{"name": "f", "parameters": {}}]}),
+                Some("/functions/0/parameters/type"),
+            ),
+            (
+                serde_json::json!({"messages": [user], "functions": [{"name": "f", "parameters": {"type": "object"}}], "function_call": {"name": "g"}}),
+                Some("/function_call/name"),
+            ),
+        ];
+        for (fields, pointer) in &cases {
+            let mut body = fields.clone();
+            body["model"] = serde_json::json!("missing/model");
+            let request: types::providers::openai::OpenAIChatRequest = serde_json::from_value(body).unwrap();
+            let result = check_chat_request(&request);
+            assert_eq!(result.as_ref().err().map(|e| e.pointer.as_str()), *pointer, "{}: {:?}", fields, result);
+        }
+
+        // The server answers 422 with the pointer in `param`, before resolving the model
+        let mut server = server::OmniferenceServer::new();
+        let app = server.app();
+        for (fields, pointer) in &cases {
+            let mut body = fields.clone();
+            body["model"] = serde_json::json!("missing/model");
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/openai-compatible/v1/chat/completions")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let status = response.status();
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let error: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            match pointer {
+                Some(pointer) => {
+                    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", fields);
+                    assert_eq!(error["error"]["param"], *pointer);
+                    assert_eq!(error["error"]["code"], "invalid_value");
+                }
+                None => assert_eq!(status, StatusCode::NOT_FOUND, "{}: {}", fields, error),
+            }
+        }
+    }
 }